@@ -411,6 +411,7 @@ impl Client {
                 }
                 _ => Propagated::Noop,
             },
+            _ => Propagated::Noop,
         }
     }
 
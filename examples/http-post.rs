@@ -115,6 +115,7 @@ fn run(mut rtc: Rtc, socket: UdpSocket) -> Result<(), RtcError> {
                 }
                 continue;
             }
+            _ => continue,
         };
 
         let timeout = timeout - Instant::now();
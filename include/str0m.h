@@ -0,0 +1,117 @@
+/*
+ * C API for str0m. Build the library with:
+ *
+ *   cargo rustc --release --features ffi --crate-type staticlib
+ *
+ * See the `str0m::ffi` module documentation for how to drive the API.
+ */
+
+#ifndef STR0M_H
+#define STR0M_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes returned by all functions. */
+#define STR0M_OK 0
+#define STR0M_ERR_ARGUMENT -1
+#define STR0M_ERR_RTC -2
+#define STR0M_ERR_PANIC -3
+
+/* Opaque handle owning an Rtc. */
+typedef struct Str0mRtc Str0mRtc;
+
+/* A view of bytes owned by the handle, valid until the next call on the handle. */
+typedef struct {
+    const uint8_t *ptr;
+    size_t len;
+} Str0mSlice;
+
+/* An IP address and port. */
+typedef struct {
+    /* 4 for IPv4, 6 for IPv6. */
+    uint8_t family;
+    /* The address in network byte order. IPv4 uses the first 4 bytes. */
+    uint8_t ip[16];
+    /* Port in host byte order. */
+    uint16_t port;
+} Str0mAddr;
+
+typedef enum {
+    STR0M_PROTO_UDP = 0,
+    STR0M_PROTO_TCP = 1,
+    STR0M_PROTO_SSL_TCP = 2,
+    STR0M_PROTO_TLS = 3,
+} Str0mProtocol;
+
+typedef enum {
+    STR0M_OUTPUT_TIMEOUT = 0,
+    STR0M_OUTPUT_TRANSMIT = 1,
+    STR0M_OUTPUT_EVENT = 2,
+} Str0mOutputKind;
+
+typedef enum {
+    STR0M_EVENT_OTHER = 0,
+    STR0M_EVENT_CONNECTED = 1,
+    STR0M_EVENT_ICE_CONNECTION_STATE_CHANGE = 2,
+    STR0M_EVENT_MEDIA_ADDED = 3,
+    STR0M_EVENT_MEDIA_DATA = 4,
+    STR0M_EVENT_CHANNEL_OPEN = 5,
+    STR0M_EVENT_CHANNEL_DATA = 6,
+    STR0M_EVENT_CHANNEL_CLOSE = 7,
+} Str0mEventKind;
+
+typedef enum {
+    STR0M_ICE_NEW = 0,
+    STR0M_ICE_CHECKING = 1,
+    STR0M_ICE_CONNECTED = 2,
+    STR0M_ICE_COMPLETED = 3,
+    STR0M_ICE_DISCONNECTED = 4,
+} Str0mIceState;
+
+/* Output from str0m_rtc_poll_output(). Fields not used by the kind/event are zeroed. */
+typedef struct {
+    Str0mOutputKind kind;
+    /* STR0M_OUTPUT_TIMEOUT */
+    uint64_t timeout_us;
+    /* STR0M_OUTPUT_TRANSMIT */
+    Str0mProtocol proto;
+    Str0mAddr source;
+    Str0mAddr destination;
+    /* STR0M_OUTPUT_EVENT */
+    Str0mEventKind event;
+    Str0mIceState ice_state;
+    uint64_t channel_id;
+    bool binary;
+    Str0mSlice mid;
+    /* The datagram to send, or the data of the event. */
+    Str0mSlice data;
+} Str0mOutput;
+
+/* Times are microseconds on a monotonic clock of the caller's choice. */
+Str0mRtc *str0m_rtc_new(uint64_t now_us);
+void str0m_rtc_free(Str0mRtc *rtc);
+
+int str0m_rtc_add_local_candidate(Str0mRtc *rtc, const Str0mAddr *addr, uint32_t proto);
+int str0m_rtc_accept_offer(Str0mRtc *rtc, const uint8_t *offer, size_t len, Str0mSlice *answer);
+
+int str0m_rtc_handle_receive(Str0mRtc *rtc, uint64_t now_us, uint32_t proto,
+                             const Str0mAddr *source, const Str0mAddr *destination,
+                             const uint8_t *data, size_t len);
+int str0m_rtc_handle_timeout(Str0mRtc *rtc, uint64_t now_us);
+
+int str0m_rtc_poll_output(Str0mRtc *rtc, Str0mOutput *out);
+
+/* Describes the error of the last failed call. Empty if it succeeded. */
+Str0mSlice str0m_rtc_last_error(const Str0mRtc *rtc);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* STR0M_H */
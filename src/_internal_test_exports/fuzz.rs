@@ -6,9 +6,13 @@ use std::time::Instant;
 use crate::change::{SdpAnswer, SdpOffer};
 use crate::crypto::KeyingMaterial;
 use crate::crypto::SrtpProfile;
+#[cfg(feature = "sample-api")]
 use crate::format::Codec;
+#[cfg(feature = "sample-api")]
 use crate::packet::{DepacketizingBuffer, RtpMeta};
-use crate::rtp_::{Frequency, MediaTime, RtpHeader};
+use crate::rtp_::RtpHeader;
+#[cfg(feature = "sample-api")]
+use crate::rtp_::{Frequency, MediaTime};
 use crate::streams::register::ReceiverRegister;
 use crate::streams::rtx_cache_buf::EvictingBuffer;
 
@@ -84,6 +88,7 @@ pub fn sdp_answer(data: &[u8]) -> Option<()> {
     Some(())
 }
 
+#[cfg(feature = "sample-api")]
 pub fn depack(data: &[u8]) -> Option<()> {
     let mut rng = Rng::new(data);
 
@@ -96,7 +101,7 @@ pub fn depack(data: &[u8]) -> Option<()> {
         _ => unreachable!(),
     };
 
-    let mut depack = DepacketizingBuffer::new(codec.into(), rng.usize(300)?);
+    let mut depack = DepacketizingBuffer::new(codec.try_into().unwrap(), rng.usize(300)?);
 
     let exts = random_extmap(&mut rng, 10)?;
 
@@ -117,7 +122,7 @@ pub fn depack(data: &[u8]) -> Option<()> {
             };
             let len = rng.usize(1200)?;
             let data = rng.slice(len)?.to_vec();
-            depack.push(meta, data);
+            depack.push(meta, data.into());
         } else {
             depack.pop();
         }
@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use crate::rtp::{Extension, ExtensionMap};
+#[cfg(feature = "bwe")]
 use crate::Bitrate;
 use crate::RtcConfig;
 
@@ -11,7 +12,7 @@ pub fn random_extmap(rng: &mut Rng, to_set: usize) -> Option<ExtensionMap> {
     let mut e = ExtensionMap::empty();
     for _ in 0..to_set {
         let id = rng.u8(13)? + 1;
-        let ext = match rng.u8(12)? {
+        let ext = match rng.u8(13)? {
             0 => AbsoluteSendTime,
             1 => AudioLevel,
             2 => TransmissionTimeOffset,
@@ -25,6 +26,7 @@ pub fn random_extmap(rng: &mut Rng, to_set: usize) -> Option<ExtensionMap> {
             10 => RtpMid,
             11 => FrameMarking,
             12 => ColorSpace,
+            13 => AbsoluteCaptureTime,
             _ => unreachable!(),
         };
         e.set(id, ext);
@@ -49,6 +51,7 @@ pub fn random_config(rng: &mut Rng) -> Option<RtcConfig> {
         let t = Duration::from_millis(rng.u64(10_000)?);
         c = c.set_stats_interval(Some(t));
     }
+    #[cfg(feature = "bwe")]
     if rng.bool()? {
         rng.bool();
         c = c.enable_bwe(None);
@@ -57,6 +60,7 @@ pub fn random_config(rng: &mut Rng) -> Option<RtcConfig> {
     }
     c = c.set_reordering_size_audio(rng.usize(usize::MAX)?);
     c = c.set_reordering_size_video(rng.usize(usize::MAX)?);
+    c = c.set_emit_only_decodable(rng.bool()?);
     c = c.set_send_buffer_audio(rng.usize(usize::MAX)?.saturating_add(1)); // panics if set to 0
     c = c.set_send_buffer_video(rng.usize(usize::MAX)?);
     c = c.set_rtp_mode(rng.bool()?);
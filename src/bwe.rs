@@ -1,21 +1,28 @@
 //! Bandwidth estimation.
 
-use crate::{rtp_::Mid, Rtc};
+use crate::rtp_::{Mid, Rid};
+#[cfg(feature = "bwe")]
+use crate::Rtc;
 
 pub use crate::rtp_::Bitrate;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Bandwidth estimation kind.
 pub enum BweKind {
     /// Transport wide congestion control.
+    ///
+    /// Also the estimate from RFC 8888 congestion control feedback, when that is negotiated.
     Twcc(Bitrate),
     /// REMB (Receiver Estimated Maximum Bitrate)
     Remb(Mid, Bitrate),
 }
 
 /// Access to the Bandwidth Estimate subsystem.
+#[cfg(feature = "bwe")]
 pub struct Bwe<'a>(pub(crate) &'a mut Rtc);
 
+#[cfg(feature = "bwe")]
 impl<'a> Bwe<'a> {
     /// Configure the current bitrate.
     ///
@@ -83,6 +90,43 @@ impl<'a> Bwe<'a> {
         self.0.session.set_bwe_desired_bitrate(desired_bitrate);
     }
 
+    /// The latest target send bitrate from the estimator.
+    ///
+    /// This is the same value as emitted in [`Event::EgressBitrateEstimate`][crate::Event::EgressBitrateEstimate],
+    /// but without the tolerance that holds back events for small changes. The estimate is
+    /// produced by the delay-based (Google Congestion Control) estimator fed by TWCC feedback.
+    /// An incoming REMB from the remote caps the estimate, and is used as the estimate when
+    /// the remote doesn't provide TWCC feedback.
+    ///
+    /// Returns None if BWE is not enabled, or before the first TWCC feedback or REMB.
+    pub fn estimate(&self) -> Option<Bitrate> {
+        self.0.session.bwe_estimate()
+    }
+
+    /// Bound the estimate between a min and max bitrate.
+    ///
+    /// This takes effect immediately, for instance when a user caps the upload
+    /// bandwidth in some settings. The estimate is clamped to the new bounds.
+    /// Use the [`BitrateAllocator`] to apply per media priorities.
+    ///
+    /// If `min` is larger than `max`, the two are swapped.
+    pub fn set_bitrate_bounds(&mut self, min: Bitrate, max: Bitrate) {
+        self.0.session.set_bwe_bitrate_bounds(min, max);
+    }
+
+    /// Set an allocator to distribute each new estimate across the media.
+    ///
+    /// With an allocator, every [`Event::EgressBitrateEstimate`][crate::Event::EgressBitrateEstimate]
+    /// is followed by an [`Event::TargetBitrate`][crate::Event::TargetBitrate] for each media
+    /// whose allocation changed. The outgoing streams of simulcast layers are paused
+    /// and resumed to match, see [`StreamTx::set_paused()`][crate::rtp::StreamTx::set_paused].
+    ///
+    /// Call again when media or layers change. `None` removes the allocator, which leaves
+    /// the streams paused as they are.
+    pub fn set_bitrate_allocator(&mut self, allocator: Option<BitrateAllocator>) {
+        self.0.session.set_bitrate_allocator(allocator);
+    }
+
     /// Reset the BWE with a new init_bitrate
     ///
     /// # Example
@@ -95,3 +139,351 @@ impl<'a> Bwe<'a> {
         self.0.session.reset_bwe(init_bitrate);
     }
 }
+
+/// Distributes a target bitrate across media and simulcast/SVC layers.
+///
+/// Audio is always allocated first. Video layers are then activated lowest layer first,
+/// across all video media in priority order, as long as the target allows. Once a layer
+/// of a media doesn't fit, none of its higher layers are activated. Bitrate left over
+/// after that goes to the highest active layer of the highest priority video.
+///
+/// Simulcast layers are sent simultaneously, so each active layer costs its full bitrate.
+/// SVC layers are given as the bitrate each layer adds on top of the layers below.
+///
+/// Set on the [`Rtc`] using [`Bwe::set_bitrate_allocator()`] to have it applied to every
+/// new estimate, or use [`BitrateAllocator::allocate()`] directly.
+///
+/// ```
+/// # use str0m::bwe::{Bitrate, BitrateAllocator};
+/// let mut alloc = BitrateAllocator::new();
+///
+/// alloc.add_audio("a".into(), Bitrate::kbps(40));
+/// alloc.add_simulcast(
+///     "v".into(),
+///     1,
+///     &[
+///         ("l".into(), Bitrate::kbps(150)),
+///         ("m".into(), Bitrate::kbps(500)),
+///         ("h".into(), Bitrate::kbps(1500)),
+///     ],
+/// );
+///
+/// let targets = alloc.allocate(Bitrate::kbps(800));
+///
+/// // Audio + low + medium fits, high does not.
+/// let video = &targets[1];
+/// let active: Vec<_> = video.layers.iter().filter(|l| l.active).map(|l| l.rid).collect();
+/// assert_eq!(active, vec![Some("l".into()), Some("m".into())]);
+///
+/// // The 110kbps left over goes to the medium layer.
+/// assert_eq!(video.layers[1].bitrate, Bitrate::kbps(610));
+/// assert_eq!(video.bitrate, Bitrate::kbps(760));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BitrateAllocator {
+    tracks: Vec<AllocTrack>,
+}
+
+#[derive(Debug, Clone)]
+struct AllocTrack {
+    mid: Mid,
+    kind: TrackKind,
+    priority: u32,
+    layers: Vec<(Option<Rid>, Bitrate)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrackKind {
+    Audio,
+    Video,
+    Svc,
+}
+
+/// Target bitrate for one media, the result of a [`BitrateAllocator`].
+///
+/// Emitted as [`Event::TargetBitrate`][crate::Event::TargetBitrate] when the allocation
+/// for the media changes. The application configures its encoder(s) for the media with
+/// the bitrate of each active layer.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TargetBitrate {
+    /// The media this target is for.
+    pub mid: Mid,
+    /// Sum of the bitrate of all active layers.
+    pub bitrate: Bitrate,
+    /// One entry per layer, ordered lowest to highest.
+    pub layers: Vec<LayerAllocation>,
+}
+
+/// Allocation for one layer of a media.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LayerAllocation {
+    /// The simulcast layer, if any.
+    pub rid: Option<Rid>,
+    /// The index of the layer, counted from the lowest.
+    ///
+    /// For SVC this is the layer the encoder should produce up to.
+    pub index: usize,
+    /// The bitrate allocated to the layer. Zero if inactive.
+    pub bitrate: Bitrate,
+    /// Whether the layer should be sent.
+    pub active: bool,
+}
+
+impl TargetBitrate {
+    /// The highest active layer, if any.
+    pub fn highest_active(&self) -> Option<&LayerAllocation> {
+        self.layers.iter().rev().find(|l| l.active)
+    }
+}
+
+impl BitrateAllocator {
+    /// Creates an empty allocator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an audio media. Audio is allocated before any video, regardless of target.
+    pub fn add_audio(&mut self, mid: Mid, bitrate: Bitrate) {
+        self.tracks.push(AllocTrack {
+            mid,
+            kind: TrackKind::Audio,
+            priority: u32::MAX,
+            layers: vec![(None, bitrate)],
+        });
+    }
+
+    /// Add a video media without simulcast or SVC.
+    ///
+    /// Higher `priority` gets layers allocated before lower priority at the same layer level.
+    pub fn add_video(&mut self, mid: Mid, priority: u32, bitrate: Bitrate) {
+        self.tracks.push(AllocTrack {
+            mid,
+            kind: TrackKind::Video,
+            priority,
+            layers: vec![(None, bitrate)],
+        });
+    }
+
+    /// Add a simulcast video media with its layers ordered from lowest to highest.
+    ///
+    /// When used via [`Bwe::set_bitrate_allocator()`], the [`StreamTx`][crate::rtp::StreamTx]
+    /// of each layer is paused and resumed to match the allocation.
+    pub fn add_simulcast(&mut self, mid: Mid, priority: u32, layers: &[(Rid, Bitrate)]) {
+        self.tracks.push(AllocTrack {
+            mid,
+            kind: TrackKind::Video,
+            priority,
+            layers: layers.iter().map(|(rid, b)| (Some(*rid), *b)).collect(),
+        });
+    }
+
+    /// Add an SVC video media with its layers ordered from lowest to highest.
+    ///
+    /// Each bitrate is what the layer adds on top of the layers below it. All layers
+    /// are sent in the same stream, so the application is responsible for configuring
+    /// the encoder to produce up to the [`TargetBitrate::highest_active()`] layer.
+    pub fn add_svc(&mut self, mid: Mid, priority: u32, layers: &[Bitrate]) {
+        self.tracks.push(AllocTrack {
+            mid,
+            kind: TrackKind::Svc,
+            priority,
+            layers: layers.iter().map(|b| (None, *b)).collect(),
+        });
+    }
+
+    /// Allocate the `target` bitrate.
+    ///
+    /// The result contains one entry per added media, in the order they were added.
+    pub fn allocate(&self, target: Bitrate) -> Vec<TargetBitrate> {
+        let mut result: Vec<TargetBitrate> = self
+            .tracks
+            .iter()
+            .map(|t| TargetBitrate {
+                mid: t.mid,
+                bitrate: Bitrate::ZERO,
+                layers: t
+                    .layers
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (rid, _))| LayerAllocation {
+                        rid: *rid,
+                        index,
+                        bitrate: Bitrate::ZERO,
+                        active: false,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let mut order: Vec<usize> = (0..self.tracks.len()).collect();
+        // Stable sort keeps the order of addition for equal priority.
+        order.sort_by_key(|i| {
+            let t = &self.tracks[*i];
+            (t.kind != TrackKind::Audio, std::cmp::Reverse(t.priority))
+        });
+
+        let mut remaining = target;
+        // Tracks that failed to fit a layer don't get any higher layers.
+        let mut stopped = vec![false; self.tracks.len()];
+        let max_layers = self.tracks.iter().map(|t| t.layers.len()).max();
+
+        for level in 0..max_layers.unwrap_or(0) {
+            for &i in &order {
+                let track = &self.tracks[i];
+                let Some((_, bitrate)) = track.layers.get(level) else {
+                    continue;
+                };
+
+                let is_audio = track.kind == TrackKind::Audio;
+                if !is_audio && (stopped[i] || *bitrate > remaining) {
+                    stopped[i] = true;
+                    continue;
+                }
+
+                remaining = remaining.saturating_sub(*bitrate);
+
+                let a = &mut result[i].layers[level];
+                a.bitrate = *bitrate;
+                a.active = true;
+            }
+        }
+
+        // The left over goes to the highest active layer of the highest priority video.
+        let top = order.iter().find(|i| {
+            self.tracks[**i].kind != TrackKind::Audio && result[**i].highest_active().is_some()
+        });
+        if let Some(&i) = top {
+            if let Some(a) = result[i].layers.iter_mut().rev().find(|a| a.active) {
+                a.bitrate = a.bitrate + remaining;
+            }
+        }
+
+        for t in &mut result {
+            t.bitrate = t
+                .layers
+                .iter()
+                .fold(Bitrate::ZERO, |acc, l| acc + l.bitrate);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn active(t: &TargetBitrate) -> Vec<usize> {
+        t.layers
+            .iter()
+            .filter(|l| l.active)
+            .map(|l| l.index)
+            .collect()
+    }
+
+    #[test]
+    fn audio_first() {
+        let mut alloc = BitrateAllocator::new();
+        alloc.add_video("v".into(), 10, Bitrate::kbps(100));
+        alloc.add_audio("a".into(), Bitrate::kbps(40));
+
+        // Audio is allocated even if the target is too low.
+        let r = alloc.allocate(Bitrate::kbps(30));
+        assert_eq!(r[1].bitrate, Bitrate::kbps(40));
+        assert_eq!(active(&r[0]), Vec::<usize>::new());
+        assert_eq!(r[0].bitrate, Bitrate::ZERO);
+
+        // Audio is taken before the video, even though added after.
+        let r = alloc.allocate(Bitrate::kbps(120));
+        assert_eq!(r[1].bitrate, Bitrate::kbps(40));
+        assert_eq!(r[0].bitrate, Bitrate::ZERO);
+
+        let r = alloc.allocate(Bitrate::kbps(150));
+        assert_eq!(r[0].bitrate, Bitrate::kbps(110));
+    }
+
+    #[test]
+    fn priority_order() {
+        let mut alloc = BitrateAllocator::new();
+        alloc.add_simulcast(
+            "lo".into(),
+            1,
+            &[
+                ("l".into(), Bitrate::kbps(100)),
+                ("h".into(), Bitrate::kbps(500)),
+            ],
+        );
+        alloc.add_simulcast(
+            "hi".into(),
+            2,
+            &[
+                ("l".into(), Bitrate::kbps(100)),
+                ("h".into(), Bitrate::kbps(500)),
+            ],
+        );
+
+        // Only room for one low layer, the higher priority gets it.
+        let r = alloc.allocate(Bitrate::kbps(150));
+        assert_eq!(active(&r[0]), Vec::<usize>::new());
+        assert_eq!(active(&r[1]), vec![0]);
+        assert_eq!(r[1].bitrate, Bitrate::kbps(150));
+
+        // Low layers of all media come before any high layer.
+        let r = alloc.allocate(Bitrate::kbps(650));
+        assert_eq!(active(&r[0]), vec![0]);
+        assert_eq!(active(&r[1]), vec![0]);
+        // The left over goes to the higher priority.
+        assert_eq!(r[0].bitrate, Bitrate::kbps(100));
+        assert_eq!(r[1].bitrate, Bitrate::kbps(550));
+
+        let r = alloc.allocate(Bitrate::kbps(700));
+        assert_eq!(active(&r[0]), vec![0]);
+        assert_eq!(active(&r[1]), vec![0, 1]);
+        assert_eq!(r[1].layers[1].rid, Some("h".into()));
+    }
+
+    #[test]
+    fn stop_at_first_miss() {
+        let mut alloc = BitrateAllocator::new();
+        // Badly configured layers, where a higher layer is cheaper than a lower.
+        alloc.add_simulcast(
+            "v".into(),
+            2,
+            &[
+                ("l".into(), Bitrate::kbps(100)),
+                ("m".into(), Bitrate::kbps(500)),
+                ("h".into(), Bitrate::kbps(200)),
+            ],
+        );
+        alloc.add_video("w".into(), 1, Bitrate::kbps(300));
+
+        // m doesn't fit, so h is not considered even if it would fit.
+        let r = alloc.allocate(Bitrate::kbps(350));
+        assert_eq!(active(&r[0]), vec![0]);
+        // A miss for one media doesn't stop lower priority media.
+        assert_eq!(active(&r[1]), Vec::<usize>::new());
+
+        let r = alloc.allocate(Bitrate::kbps(400));
+        assert_eq!(active(&r[0]), vec![0]);
+        assert_eq!(active(&r[1]), vec![0]);
+        assert_eq!(r[0].bitrate, Bitrate::kbps(100));
+    }
+
+    #[test]
+    fn svc_layers() {
+        let mut alloc = BitrateAllocator::new();
+        alloc.add_svc(
+            "v".into(),
+            1,
+            &[Bitrate::kbps(100), Bitrate::kbps(200), Bitrate::kbps(400)],
+        );
+
+        let r = alloc.allocate(Bitrate::kbps(450));
+        assert_eq!(active(&r[0]), vec![0, 1]);
+        assert_eq!(r[0].highest_active().map(|l| l.index), Some(1));
+        assert_eq!(r[0].bitrate, Bitrate::kbps(450));
+        assert!(r[0].layers.iter().all(|l| l.rid.is_none()));
+    }
+}
@@ -1,9 +1,11 @@
+#[cfg(feature = "sctp")]
 use crate::channel::ChannelId;
 use crate::crypto::Fingerprint;
 use crate::media::{Media, MediaKind};
 use crate::rtp_::{Mid, Rid, Ssrc};
+#[cfg(feature = "sctp")]
 use crate::sctp::ChannelConfig;
-use crate::streams::{StreamRx, StreamTx, DEFAULT_RTX_CACHE_DURATION};
+use crate::streams::{StreamRx, StreamTx, SyncGroup, DEFAULT_RTX_CACHE_DURATION};
 use crate::IceCreds;
 use crate::Rtc;
 use crate::RtcError;
@@ -96,18 +98,33 @@ impl<'a> DirectApi<'a> {
     }
 
     /// Start the SCTP over DTLS.
+    #[cfg(feature = "sctp")]
     pub fn start_sctp(&mut self, client: bool) {
         self.rtc.init_sctp(client)
     }
 
+    /// Set the max message size the remote peer can receive on data channels.
+    ///
+    /// This corresponds to `a=max-message-size` in SDP. `None` means the default of 65536
+    /// bytes, and `Some(0)` means there is no limit. Larger writes fail with
+    /// [`SctpError::MessageTooLarge`][crate::error::SctpError::MessageTooLarge].
+    #[cfg(feature = "sctp")]
+    pub fn set_sctp_max_message_size(&mut self, max: Option<usize>) {
+        self.rtc.sctp.set_max_message_size(max);
+    }
+
     /// Create a new data channel.
-    pub fn create_data_channel(&mut self, config: ChannelConfig) -> ChannelId {
-        let id = self.rtc.chan.new_channel(&config);
+    ///
+    /// Fails if an out-of-band negotiated stream id is the reserved 65535.
+    #[cfg(feature = "sctp")]
+    pub fn create_data_channel(&mut self, config: ChannelConfig) -> Result<ChannelId, RtcError> {
+        let id = self.rtc.chan.new_channel(&config)?;
         self.rtc.chan.confirm(id, config);
-        id
+        Ok(id)
     }
 
     /// Close a data channel.
+    #[cfg(feature = "sctp")]
     pub fn close_data_channel(&mut self, channel_id: ChannelId) {
         self.rtc.chan.close_channel(channel_id, &mut self.rtc.sctp);
     }
@@ -122,6 +139,14 @@ impl<'a> DirectApi<'a> {
         self.rtc.session.enable_twcc_feedback()
     }
 
+    /// Enable receive side bandwidth estimation, sent as REMB feedback.
+    ///
+    /// This is for remotes that can't do TWCC. The estimate covers all incoming streams
+    /// and is sent about once a second.
+    pub fn enable_remb_feedback(&mut self) {
+        self.rtc.session.streams.enable_remb_feedback()
+    }
+
     /// Generate a ssrc that is not already used in session
     pub fn new_ssrc(&self) -> Ssrc {
         self.rtc.session.streams.new_ssrc()
@@ -131,6 +156,7 @@ impl<'a> DirectApi<'a> {
     ///
     /// This is useful when using out of band negotiated sctp stream id in
     /// [`Self::create_data_channel()`]
+    #[cfg(feature = "sctp")]
     pub fn channel_id_by_sctp_stream_id(&self, id: u16) -> Option<ChannelId> {
         self.rtc.chan.channel_id_by_stream_id(id)
     }
@@ -139,6 +165,7 @@ impl<'a> DirectApi<'a> {
     ///
     /// This is useful when using out of band negotiated sctp stream id in
     /// [`Self::create_data_channel()`]
+    #[cfg(feature = "sctp")]
     pub fn sctp_stream_id_by_channel_id(&self, id: ChannelId) -> Option<u16> {
         self.rtc.chan.stream_id_by_channel_id(id)
     }
@@ -214,10 +241,18 @@ impl<'a> DirectApi<'a> {
         self.rtc.session.streams.stream_rx_by_mid_rid(mid, rid)
     }
 
+    /// Incoming streams grouped by the CNAME sent by the remote.
+    ///
+    /// Streams without a CNAME yet are not part of any group.
+    pub fn sync_groups(&self) -> Vec<SyncGroup> {
+        self.rtc.session.streams.sync_groups()
+    }
+
     /// Declare the intention to send data using the given SSRC.
     ///
     /// * The resend RTX is optional but necessary to do resends. str0m does not do
-    ///   resends without RTX.
+    ///   resends without RTX, unless
+    ///   [`RtcConfig::set_resend_without_rtx()`][crate::RtcConfig::set_resend_without_rtx] is enabled.
     ///
     /// Can be called multiple times without changing any internal state. However
     /// the RTX value is only picked up the first ever time we see a new SSRC.
@@ -247,6 +282,8 @@ impl<'a> DirectApi<'a> {
         };
 
         stream.set_rtx_cache(size, DEFAULT_RTX_CACHE_DURATION);
+        stream.set_rtx_cache_max_bytes(self.rtc.session.rtx_cache_max_bytes);
+        stream.set_resend_without_rtx(self.rtc.session.resend_without_rtx);
 
         stream
     }
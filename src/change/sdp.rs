@@ -2,7 +2,9 @@
 
 use std::fmt;
 use std::ops::{Deref, DerefMut};
+use std::time::Duration;
 
+#[cfg(feature = "sctp")]
 use crate::channel::ChannelId;
 use crate::crypto::Fingerprint;
 use crate::format::CodecConfig;
@@ -12,6 +14,7 @@ use crate::media::Media;
 use crate::packet::MediaKind;
 use crate::rtp_::Rid;
 use crate::rtp_::{Direction, Extension, ExtensionMap, Mid, Pt, Ssrc};
+#[cfg(feature = "sctp")]
 use crate::sctp::ChannelConfig;
 use crate::sdp::SimulcastGroups;
 use crate::sdp::{self, MediaAttribute, MediaLine, MediaType, Msid, Sdp};
@@ -80,6 +83,7 @@ impl<'a> SdpApi<'a> {
         }
 
         add_ice_details(self.rtc, &offer, None)?;
+        maybe_restart_dtls(self.rtc, &offer)?;
 
         if self.rtc.remote_fingerprint.is_none() {
             if let Some(f) = offer.fingerprint() {
@@ -99,13 +103,18 @@ impl<'a> SdpApi<'a> {
         // Ensure setup=active/passive is corresponding remote and init dtls.
         init_dtls(self.rtc, &offer)?;
 
+        #[cfg(feature = "sctp")]
+        let max_message_size = remote_max_message_size(&offer);
+
         // Modify session with offer
         apply_offer(&mut self.rtc.session, offer)?;
 
         // Handle potentially new m=application line.
-        let client = self.rtc.dtls.is_active().expect("DTLS active to be set");
+        #[cfg(feature = "sctp")]
         if self.rtc.session.app().is_some() {
+            let client = self.rtc.dtls.is_active().expect("DTLS active to be set");
             self.rtc.init_sctp(client);
+            self.rtc.sctp.set_max_message_size(max_message_size);
         }
 
         let params = AsSdpParams::new(self.rtc, None);
@@ -138,7 +147,7 @@ impl<'a> SdpApi<'a> {
     /// ```
     pub fn accept_answer(
         self,
-        mut pending: SdpPendingOffer,
+        pending: SdpPendingOffer,
         answer: SdpAnswer,
     ) -> Result<(), RtcError> {
         debug!("Accept answer");
@@ -157,6 +166,7 @@ impl<'a> SdpApi<'a> {
         }
 
         add_ice_details(self.rtc, &answer, Some(&pending))?;
+        maybe_restart_dtls(self.rtc, &answer)?;
 
         // Ensure setup=active/passive is corresponding remote and init dtls.
         init_dtls(self.rtc, &answer)?;
@@ -171,19 +181,27 @@ impl<'a> SdpApi<'a> {
         }
 
         // Split out new channels, since that is not handled by the Session.
+        #[cfg(feature = "sctp")]
+        let mut pending = pending;
+        #[cfg(feature = "sctp")]
         let new_channels = pending.changes.take_new_channels();
 
+        #[cfg(feature = "sctp")]
+        let max_message_size = remote_max_message_size(&answer);
+
         // Modify session with answer
         apply_answer(&mut self.rtc.session, pending.changes, answer)?;
 
         // Handle potentially new m=application line.
-        let client = self.rtc.dtls.is_active().expect("DTLS to be inited");
+        #[cfg(feature = "sctp")]
         if self.rtc.session.app().is_some() {
+            let client = self.rtc.dtls.is_active().expect("DTLS to be inited");
             self.rtc.init_sctp(client);
-        }
+            self.rtc.sctp.set_max_message_size(max_message_size);
 
-        for (id, config) in new_channels {
-            self.rtc.chan.confirm(id, config);
+            for (id, config) in new_channels {
+                self.rtc.chan.confirm(id, config);
+            }
         }
 
         Ok(())
@@ -233,7 +251,63 @@ impl<'a> SdpApi<'a> {
         stream_id: Option<String>,
         track_id: Option<String>,
     ) -> Mid {
-        let mid = self.rtc.new_mid();
+        let config = MediaConfig {
+            stream_id,
+            track_id,
+            ..Default::default()
+        };
+
+        self.add_media_with_config(kind, dir, config)
+            .expect("MediaConfig without ids to be valid")
+    }
+
+    /// Add audio or video media with application chosen identifiers.
+    ///
+    /// Works like [`SdpApi::add_media()`], but the `mid`, the outgoing SSRCs and the
+    /// outgoing RIDs (simulcast) can be set instead of being randomly generated. This keeps
+    /// the values stable across reconnects and consistent with a signaling layer.
+    ///
+    /// Errors if the configuration is inconsistent, or any identifier is already in use.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, media::MediaKind, media::Direction};
+    /// # use str0m::change::MediaConfig;
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let mut changes = rtc.sdp_api();
+    ///
+    /// let mid = changes
+    ///     .add_media_with_config(
+    ///         MediaKind::Video,
+    ///         Direction::SendOnly,
+    ///         MediaConfig {
+    ///             mid: Some("v0".into()),
+    ///             rids: vec!["h".into(), "l".into()],
+    ///             ssrcs: vec![(1000.into(), Some(1001.into())), (2000.into(), Some(2001.into()))],
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .unwrap();
+    ///
+    /// assert_eq!(mid, "v0".into());
+    /// ```
+    pub fn add_media_with_config(
+        &mut self,
+        kind: MediaKind,
+        dir: Direction,
+        config: MediaConfig,
+    ) -> Result<Mid, RtcError> {
+        self.validate_media_config(&config)?;
+
+        let MediaConfig {
+            stream_id,
+            track_id,
+            mid,
+            rids,
+            ssrcs,
+        } = config;
+
+        let mid = mid.unwrap_or_else(|| self.rtc.new_mid());
 
         // https://www.rfc-editor.org/rfc/rfc8830
         // msid-id = 1*64token-char
@@ -262,10 +336,34 @@ impl<'a> SdpApi<'a> {
             Id::<20>::random().to_string()
         };
 
-        let rtx = kind.is_video().then(|| self.rtc.session.streams.new_ssrc());
-        let ssrcs = vec![(self.rtc.session.streams.new_ssrc(), rtx)];
+        let ssrcs = if ssrcs.is_empty() {
+            // One stream per RID, or a single stream without simulcast.
+            let count = rids.len().max(1);
+            let mut ssrcs: Vec<(Ssrc, Option<Ssrc>)> = Vec::with_capacity(count);
+
+            for _ in 0..count {
+                let new_ssrc = || loop {
+                    let ssrc = self.rtc.session.streams.new_ssrc();
+                    let taken = ssrcs.iter().any(|(s, r)| *s == ssrc || *r == Some(ssrc));
+                    if !taken && !self.changes.has_ssrc(ssrc) {
+                        break ssrc;
+                    }
+                };
+                let ssrc = new_ssrc();
+                let rtx = kind.is_video().then(|| loop {
+                    let rtx = new_ssrc();
+                    if rtx != ssrc {
+                        break rtx;
+                    }
+                });
+                ssrcs.push((ssrc, rtx));
+            }
+
+            ssrcs
+        } else {
+            ssrcs
+        };
 
-        // TODO: let user configure stream/track name.
         let msid = Msid {
             stream_id,
             track_id: track_id.clone(),
@@ -278,6 +376,7 @@ impl<'a> SdpApi<'a> {
             kind,
             dir,
             ssrcs,
+            rids,
 
             // Added later
             pts: vec![],
@@ -286,7 +385,52 @@ impl<'a> SdpApi<'a> {
         };
 
         self.changes.0.push(Change::AddMedia(add));
-        mid
+        Ok(mid)
+    }
+
+    fn validate_media_config(&self, config: &MediaConfig) -> Result<(), RtcError> {
+        let err = |s: String| Err(RtcError::InvalidMediaConfig(s));
+
+        if let Some(mid) = config.mid {
+            if self.rtc.session.has_mid(mid) || self.changes.has_mid(mid) {
+                return err(format!("mid already in use: {mid}"));
+            }
+        }
+
+        for (i, rid) in config.rids.iter().enumerate() {
+            if config.rids[..i].contains(rid) {
+                return err(format!("duplicate rid: {rid}"));
+            }
+        }
+
+        if config.ssrcs.is_empty() {
+            return Ok(());
+        }
+
+        let expected = config.rids.len().max(1);
+        if config.ssrcs.len() != expected {
+            return err(format!(
+                "expected {} ssrcs, one per rid, got {}",
+                expected,
+                config.ssrcs.len()
+            ));
+        }
+
+        let all = config
+            .ssrcs
+            .iter()
+            .flat_map(|(ssrc, rtx)| [Some(*ssrc), *rtx])
+            .flatten();
+
+        for (i, ssrc) in all.clone().enumerate() {
+            let streams = &self.rtc.session.streams;
+            let in_session = streams.has_stream_tx(ssrc) || streams.has_stream_rx(ssrc);
+            if in_session || self.changes.has_ssrc(ssrc) || all.clone().take(i).any(|s| s == ssrc) {
+                return err(format!("ssrc already in use: {ssrc}"));
+            }
+        }
+
+        Ok(())
     }
 
     /// Change the direction of an already existing media.
@@ -328,7 +472,46 @@ impl<'a> SdpApi<'a> {
     ///
     /// let cid = changes.add_channel("my special channel".to_string());
     /// ```
+    #[cfg(feature = "sctp")]
     pub fn add_channel(&mut self, label: String) -> ChannelId {
+        let config = ChannelConfig {
+            label,
+            ..Default::default()
+        };
+
+        self.add_channel_with_config(config)
+            .expect("in-band negotiated channel")
+    }
+
+    /// Add a new data channel with a custom configuration.
+    ///
+    /// Works like [`SdpApi::add_channel()`], but allows for unordered delivery and
+    /// partial reliability (RFC 3758), where messages are given up after a number
+    /// of retransmits or a max lifetime.
+    ///
+    /// Fails if an out-of-band negotiated stream id is the reserved 65535.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::channel::{ChannelConfig, Reliability};
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let mut changes = rtc.sdp_api();
+    ///
+    /// let cid = changes.add_channel_with_config(ChannelConfig {
+    ///     label: "game state".to_string(),
+    ///     ordered: false,
+    ///     reliability: Reliability::MaxRetransmits { retransmits: 0 },
+    ///     ..Default::default()
+    /// }).unwrap();
+    /// ```
+    #[cfg(feature = "sctp")]
+    pub fn add_channel_with_config(
+        &mut self,
+        config: ChannelConfig,
+    ) -> Result<ChannelId, RtcError> {
+        let id = self.rtc.chan.new_channel(&config)?;
+
         let has_media = self.rtc.session.app().is_some();
         let changes_contains_add_app = self.changes.contains_add_app();
 
@@ -337,16 +520,9 @@ impl<'a> SdpApi<'a> {
             self.changes.0.push(Change::AddApp(mid));
         }
 
-        let config = ChannelConfig {
-            label,
-            ..Default::default()
-        };
-
-        let id = self.rtc.chan.new_channel(&config);
-
         self.changes.0.push(Change::AddChannel((id, config)));
 
-        id
+        Ok(id)
     }
 
     /// Perform an ICE restart.
@@ -410,6 +586,7 @@ impl<'a> SdpApi<'a> {
             Some((offer, pending))
         } else {
             debug!("Apply direct changes");
+            #[cfg(feature = "sctp")]
             apply_direct_changes(self.rtc, self.changes);
             None
         }
@@ -445,6 +622,25 @@ impl<'a> SdpApi<'a> {
     }
 }
 
+/// Identifiers for media added with [`SdpApi::add_media_with_config()`].
+///
+/// Any field left as `None` or empty is generated, like in [`SdpApi::add_media()`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MediaConfig {
+    /// Stream id for the `a=msid` line. Random if not set.
+    pub stream_id: Option<String>,
+    /// Track id for the `a=msid` line. Random if not set.
+    pub track_id: Option<String>,
+    /// The mid of the new m-line. Must not already be in use.
+    pub mid: Option<Mid>,
+    /// RIDs to send simulcast with. Each RID gets its own outgoing stream.
+    pub rids: Vec<Rid>,
+    /// Outgoing SSRCs as (main, rtx) pairs.
+    ///
+    /// Either empty, to generate them, or one pair per RID (exactly one pair without RIDs).
+    pub ssrcs: Vec<(Ssrc, Option<Ssrc>)>,
+}
+
 /// Pending offer from a previous [`Rtc::sdp_api()`] call.
 ///
 /// This allows us to accept a remote answer. No changes have been made to the session
@@ -481,6 +677,7 @@ impl SdpPendingOffer {
             match c {
                 Change::AddMedia(v) => rtc.media(v.mid).is_none(),
                 Change::AddApp(_) => rtc.session.app().is_none(),
+                #[cfg(feature = "sctp")]
                 Change::AddChannel(v) => rtc.chan.stream_id_by_channel_id(v.0).is_none(),
                 Change::Direction(m, d) => {
                     // If mid is missing, this is not relevant.
@@ -516,7 +713,10 @@ impl Changes {
 #[allow(clippy::large_enum_variant)]
 pub(crate) enum Change {
     AddMedia(AddMedia),
+    // Only added together with a data channel.
+    #[cfg_attr(not(feature = "sctp"), allow(dead_code))]
     AddApp(Mid),
+    #[cfg(feature = "sctp")]
     AddChannel((ChannelId, ChannelConfig)),
     Direction(Mid, Direction),
     IceRestart(IceCreds, bool),
@@ -530,6 +730,8 @@ pub(crate) struct AddMedia {
     pub kind: MediaKind,
     pub dir: Direction,
     pub ssrcs: Vec<(Ssrc, Option<Ssrc>)>,
+    /// RIDs for sending simulcast, one per entry in `ssrcs`. Empty without simulcast.
+    pub rids: Vec<Rid>,
 
     // pts and index are filled in when creating the SDP OFFER.
     // The default PT order is set by the Session (BUNDLE).
@@ -558,11 +760,13 @@ fn requires_negotiation(c: &Change) -> bool {
         Change::IceRestart(_, _) => true,
         Change::AddMedia(_) => true,
         Change::AddApp(_) => true,
+        #[cfg(feature = "sctp")]
         Change::AddChannel(_) => false,
         Change::Direction(_, _) => true,
     }
 }
 
+#[cfg(feature = "sctp")]
 fn apply_direct_changes(rtc: &mut Rtc, mut changes: Changes) {
     // Split out new channels, since that is not handled by the Session.
     let new_channels = changes.take_new_channels();
@@ -632,6 +836,27 @@ fn add_ice_details(
     Ok(())
 }
 
+/// Renegotiate DTLS if the remote changed its fingerprint, or if we have a regenerated
+/// certificate to take into use. Either way, the new fingerprint is in this negotiation.
+fn maybe_restart_dtls(rtc: &mut Rtc, sdp: &Sdp) -> Result<(), RtcError> {
+    if !rtc.dtls.is_inited() {
+        return Ok(());
+    }
+
+    let remote = sdp.fingerprint();
+    let remote_changed = remote.is_some() && remote != rtc.remote_fingerprint;
+
+    if !remote_changed && !rtc.dtls.has_next_cert() {
+        return Ok(());
+    }
+
+    if remote_changed {
+        rtc.remote_fingerprint = remote;
+    }
+
+    rtc.restart_dtls()
+}
+
 fn init_dtls(rtc: &mut Rtc, remote_sdp: &Sdp) -> Result<(), RtcError> {
     let setup = match remote_sdp.setup() {
         Some(v) => match v {
@@ -740,6 +965,14 @@ fn as_sdp(session: &Session, params: AsSdpParams) -> Sdp {
     }
 }
 
+#[cfg(feature = "sctp")]
+fn remote_max_message_size(sdp: &Sdp) -> Option<usize> {
+    sdp.media_lines
+        .iter()
+        .find(|m| m.typ.is_channel())
+        .and_then(|m| m.max_message_size())
+}
+
 fn apply_offer(session: &mut Session, offer: SdpOffer) -> Result<(), RtcError> {
     offer.assert_consistency()?;
 
@@ -806,14 +1039,13 @@ fn ensure_stream_tx(session: &mut Session) {
             .filter(|p| media.remote_pts().contains(&p.pt))
             .any(|p| p.resend().is_some());
 
-        for rid in rids {
-            // If we already have the stream, we don't make any new one.
-            let has_stream = session
-                .streams
-                .stream_tx_by_mid_rid(media.mid(), rid)
-                .is_some();
+        let max_age = rtx_cache_max_age(&session.codec_config, media.kind());
 
-            if has_stream {
+        for rid in rids {
+            // If we already have the stream, we don't make any new one. The remote
+            // rtx-time might have changed though.
+            if let Some(stream) = session.streams.stream_tx_by_mid_rid(media.mid(), rid) {
+                stream.set_rtx_cache_max_age(max_age);
                 continue;
             }
 
@@ -836,11 +1068,24 @@ fn ensure_stream_tx(session: &mut Session) {
                 session.send_buffer_video
             };
 
-            stream.set_rtx_cache(size, DEFAULT_RTX_CACHE_DURATION);
+            stream.set_rtx_cache(size, max_age);
+            stream.set_rtx_cache_max_bytes(session.rtx_cache_max_bytes);
+            stream.set_resend_without_rtx(session.resend_without_rtx);
         }
     }
 }
 
+/// Max age of sent packets kept for resends, for all streams of a media kind. This is the
+/// largest effective `rtx-time` of the PTs with RTX.
+fn rtx_cache_max_age(codec_config: &CodecConfig, kind: MediaKind) -> Duration {
+    codec_config
+        .all_for_kind(kind)
+        .filter(|p| p.resend().is_some())
+        .filter_map(|p| p.effective_rtx_time())
+        .max()
+        .unwrap_or(DEFAULT_RTX_CACHE_DURATION)
+}
+
 fn add_pending_changes(session: &mut Session, pending: Changes) {
     // For pending AddMedia, we have outgoing SSRC communicated that needs to be added.
     for change in pending.0 {
@@ -860,11 +1105,18 @@ fn add_pending_changes(session: &mut Session, pending: Changes) {
         media.set_cname(add_media.cname);
         media.set_msid(add_media.msid);
 
-        for (ssrc, rtx) in add_media.ssrcs {
-            // TODO: When we allow sending RID, we need to add that here.
+        let rids: Vec<Option<Rid>> = if add_media.rids.is_empty() {
+            vec![None]
+        } else {
+            add_media.rids.into_iter().map(Some).collect()
+        };
+
+        for ((ssrc, rtx), rid) in add_media.ssrcs.into_iter().zip(rids) {
+            let max_age = rtx_cache_max_age(&session.codec_config, media.kind());
+
             let stream = session
                 .streams
-                .declare_stream_tx(ssrc, rtx, add_media.mid, None);
+                .declare_stream_tx(ssrc, rtx, add_media.mid, rid);
 
             let size = if media.kind().is_audio() {
                 session.send_buffer_audio
@@ -872,7 +1124,9 @@ fn add_pending_changes(session: &mut Session, pending: Changes) {
                 session.send_buffer_video
             };
 
-            stream.set_rtx_cache(size, DEFAULT_RTX_CACHE_DURATION);
+            stream.set_rtx_cache(size, max_age);
+            stream.set_rtx_cache_max_bytes(session.rtx_cache_max_bytes);
+            stream.set_resend_without_rtx(session.resend_without_rtx);
         }
     }
 }
@@ -992,6 +1246,30 @@ fn update_session(session: &mut Session, sdp: &Sdp) {
     if has_transport_cc && has_twcc_header {
         session.enable_twcc_feedback();
     }
+
+    // Does any m-line contain a a=rtcp-fb:xx ack ccfb, and do we want it too?
+    let has_ccfb = sdp
+        .media_lines
+        .iter()
+        .any(|m| m.rtp_params().iter().any(|p| p.fb_ccfb));
+    let want_ccfb = session.codec_config.iter().any(|p| p.fb_ccfb);
+
+    if has_ccfb && want_ccfb {
+        session.enable_ccfb_feedback();
+    }
+
+    // Without any transport wide feedback, the remote can't estimate the bandwidth
+    // on its side. Fall back on estimating it here, sent as REMB, if both sides do REMB.
+    let has_remb = sdp
+        .media_lines
+        .iter()
+        .any(|m| m.rtp_params().iter().any(|p| p.fb_remb));
+    let want_remb = session.codec_config.iter().any(|p| p.fb_remb);
+    let has_transport_feedback = (has_transport_cc && has_twcc_header) || (has_ccfb && want_ccfb);
+
+    if has_remb && want_remb && !has_transport_feedback {
+        session.streams.enable_remb_feedback();
+    }
 }
 
 /// Returns all media/channels as `AsMediaLine` trait.
@@ -1066,6 +1344,7 @@ fn update_media(
         remote_extmap.set(id, in_session.clone());
     }
     media.set_remote_extmap(remote_extmap);
+    media.set_remote_ptime(m.ptime());
 
     if new_dir.is_receiving() {
         // SSRC changes
@@ -1146,7 +1425,8 @@ impl AsSdpMediaLine for (Mid, usize) {
 
         MediaLine {
             typ: sdp::MediaType::Application,
-            disabled: false,
+            // Without SCTP support, the m-line from a remote offer is rejected.
+            disabled: !cfg!(feature = "sctp"),
             proto: Proto::Sctp,
             pts: vec![],
             bw: None,
@@ -1364,6 +1644,7 @@ impl fmt::Debug for SdpPendingOffer {
 }
 
 impl Changes {
+    #[cfg(feature = "sctp")]
     pub fn contains_add_app(&self) -> bool {
         for i in 0..self.0.len() {
             if matches!(&self.0[i], Change::AddApp(_)) {
@@ -1373,6 +1654,7 @@ impl Changes {
         false
     }
 
+    #[cfg(feature = "sctp")]
     pub fn take_new_channels(&mut self) -> Vec<(ChannelId, ChannelConfig)> {
         let mut v = vec![];
 
@@ -1435,6 +1717,21 @@ impl Changes {
         None
     }
 
+    fn has_mid(&self, mid: Mid) -> bool {
+        self.0.iter().any(|c| match c {
+            Change::AddMedia(v) => v.mid == mid,
+            Change::AddApp(v) => *v == mid,
+            _ => false,
+        })
+    }
+
+    fn has_ssrc(&self, ssrc: Ssrc) -> bool {
+        self.0.iter().any(|c| match c {
+            Change::AddMedia(v) => v.ssrcs.iter().any(|(s, r)| *s == ssrc || *r == Some(ssrc)),
+            _ => false,
+        })
+    }
+
     fn count_new_medias(&self) -> usize {
         self.0
             .iter()
@@ -1530,6 +1827,7 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "sctp")]
     fn test_out_of_order_error() {
         let mut rtc1 = Rtc::new();
         let mut rtc2 = Rtc::new();
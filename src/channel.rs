@@ -1,9 +1,9 @@
 //! Data channel related types.
 
-use std::{fmt, str, time::Instant};
+use std::{fmt, str};
 
-use crate::sctp::RtcSctp;
-use crate::util::already_happened;
+use crate::sctp::{RtcSctp, SctpError};
+use crate::util::{already_happened, Instant};
 use crate::{Rtc, RtcError};
 
 pub use crate::sctp::ChannelConfig;
@@ -14,8 +14,16 @@ pub use crate::sctp::Reliability;
 /// This is NOT the SCTP stream id.
 // Deliberately not Deref or From to avoid this Id being created outside of this module.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChannelId(usize);
 
+impl ChannelId {
+    #[cfg(feature = "ffi")]
+    pub(crate) fn index(&self) -> usize {
+        self.0
+    }
+}
+
 /// Data channel data from remote peer.
 ///
 /// This is obtained via [`Event::ChannelData`][crate::Event::ChannelData].
@@ -54,6 +62,32 @@ impl<'a> Channel<'a> {
     pub fn write(&mut self, binary: bool, buf: &[u8]) -> Result<usize, RtcError> {
         Ok(self.rtc.sctp.write(self.sctp_stream_id, binary, buf)?)
     }
+
+    /// Number of bytes written to this channel that are not yet sent to the remote peer.
+    ///
+    /// This corresponds to `bufferedAmount` in the browser API.
+    pub fn buffered_amount(&mut self) -> Result<usize, RtcError> {
+        Ok(self.rtc.sctp.buffered_amount(self.sctp_stream_id)?)
+    }
+
+    /// Set the threshold for [`Event::ChannelBufferedAmountLow`][crate::Event::ChannelBufferedAmountLow].
+    ///
+    /// The event is emitted when the buffered amount drains from above to at or
+    /// below the threshold. Defaults to 0.
+    pub fn set_buffered_amount_low_threshold(&mut self, threshold: usize) -> Result<(), RtcError> {
+        Ok(self
+            .rtc
+            .sctp
+            .set_buffered_amount_low_threshold(self.sctp_stream_id, threshold)?)
+    }
+
+    /// The configuration of this channel.
+    ///
+    /// For channels opened by the remote peer, this is the label, protocol and
+    /// reliability as received in the DCEP open message.
+    pub fn config(&self) -> Option<&ChannelConfig> {
+        self.rtc.sctp.config(self.sctp_stream_id)
+    }
 }
 
 impl fmt::Debug for ChannelData {
@@ -115,12 +149,14 @@ struct ChannelAllocation {
 }
 
 impl ChannelHandler {
-    pub fn new_channel(&mut self, config: &ChannelConfig) -> ChannelId {
-        let id = self.next_channel_id();
-
+    pub fn new_channel(&mut self, config: &ChannelConfig) -> Result<ChannelId, SctpError> {
         // For out-of-band negotiated, the id is already set.
         let sctp_stream_id = config.negotiated;
         if let Some(sctp_stream_id) = sctp_stream_id {
+            if sctp_stream_id == RESERVED_STREAM_ID {
+                return Err(SctpError::ReservedStreamId(sctp_stream_id));
+            }
+
             let exists = self
                 .allocations
                 .iter()
@@ -132,6 +168,8 @@ impl ChannelHandler {
             );
         }
 
+        let id = self.next_channel_id();
+
         let alloc = ChannelAllocation {
             id,
             sctp_stream_id,
@@ -142,7 +180,7 @@ impl ChannelHandler {
         debug!("Allocate channel id: {:?}", id);
         self.allocations.push(alloc);
 
-        id
+        Ok(id)
     }
 
     pub fn confirm(&mut self, id: ChannelId, config: ChannelConfig) {
@@ -162,6 +200,13 @@ impl ChannelHandler {
             .map(|a| a.id)
     }
 
+    /// All channels with an sctp stream id.
+    pub fn stream_ids(&self) -> impl Iterator<Item = (ChannelId, u16)> + '_ {
+        self.allocations
+            .iter()
+            .filter_map(|a| Some((a.id, a.sctp_stream_id?)))
+    }
+
     /// Look up sctp stream id for channel id.
     pub fn stream_id_by_channel_id(&self, id: ChannelId) -> Option<u16> {
         self.allocations
@@ -204,11 +249,7 @@ impl ChannelHandler {
             return;
         }
 
-        // RFC 8831
-        // Unless otherwise defined or negotiated, the
-        // streams are picked based on the DTLS role (the client picks even
-        // stream identifiers, and the server picks odd stream identifiers).
-        let base = if sctp.is_client() { 0 } else { 1 };
+        let base = local_parity(sctp.is_client());
 
         let mut taken: Vec<u16> = self
             .allocations
@@ -244,6 +285,15 @@ impl ChannelHandler {
                 continue;
             };
 
+            // Not an error, but the remote side might allocate the same id for
+            // an in-band negotiated channel.
+            if config.negotiated.is_some() && sctp_stream_id % 2 != local_parity(sctp.is_client()) {
+                warn!(
+                    "Negotiated stream id {} has the parity of the remote DTLS role",
+                    sctp_stream_id
+                );
+            }
+
             debug!("Open stream for: {:?}", a.id);
             sctp.open_stream(sctp_stream_id, config);
         }
@@ -290,6 +340,23 @@ impl ChannelHandler {
     }
 }
 
+/// RFC 8831
+/// The stream identifier 65535 is reserved due to SCTP INIT and INIT-ACK chunks
+/// only allowing a maximum of 65535 streams to be negotiated (0-65534).
+const RESERVED_STREAM_ID: u16 = 65535;
+
+/// RFC 8831
+/// Unless otherwise defined or negotiated, the
+/// streams are picked based on the DTLS role (the client picks even
+/// stream identifiers, and the server picks odd stream identifiers).
+fn local_parity(client: bool) -> u16 {
+    if client {
+        0
+    } else {
+        1
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,15 +366,68 @@ mod tests {
         let mut handler = ChannelHandler::default();
 
         // allocate first channel, get unique id
-        assert_eq!(handler.new_channel(&Default::default()), ChannelId(0));
+        assert_eq!(
+            handler.new_channel(&Default::default()).unwrap(),
+            ChannelId(0)
+        );
 
         // allocate second channel, get unique id
-        assert_eq!(handler.new_channel(&Default::default()), ChannelId(1));
+        assert_eq!(
+            handler.new_channel(&Default::default()).unwrap(),
+            ChannelId(1)
+        );
 
         // free channel 0, allocate two more channels and verify that the
         // new channels have unique IDs.
         handler.remove_channel(ChannelId(0));
-        assert_eq!(handler.new_channel(&Default::default()), ChannelId(2));
-        assert_eq!(handler.new_channel(&Default::default()), ChannelId(3));
+        assert_eq!(
+            handler.new_channel(&Default::default()).unwrap(),
+            ChannelId(2)
+        );
+        assert_eq!(
+            handler.new_channel(&Default::default()).unwrap(),
+            ChannelId(3)
+        );
+    }
+
+    #[test]
+    fn stream_id_allocation_skips_negotiated() {
+        let mut handler = ChannelHandler::default();
+        let mut sctp = RtcSctp::new(crate::io::DATAGRAM_MTU);
+        sctp.init(true, Instant::now());
+
+        let negotiated = ChannelConfig {
+            negotiated: Some(0),
+            ..Default::default()
+        };
+        let id0 = handler.new_channel(&negotiated).unwrap();
+        let id1 = handler.new_channel(&Default::default()).unwrap();
+
+        handler.do_allocations(&mut sctp);
+
+        assert_eq!(handler.stream_id_by_channel_id(id0), Some(0));
+        // Client picks even, and 0 is taken.
+        assert_eq!(handler.stream_id_by_channel_id(id1), Some(2));
+    }
+
+    #[test]
+    fn negotiated_reserved_stream_id() {
+        let mut handler = ChannelHandler::default();
+
+        let reserved = ChannelConfig {
+            negotiated: Some(65535),
+            ..Default::default()
+        };
+        assert_eq!(
+            handler.new_channel(&reserved),
+            Err(SctpError::ReservedStreamId(65535))
+        );
+
+        // Nothing was allocated.
+        assert!(!handler.need_allocation());
+        assert_eq!(
+            handler.new_channel(&Default::default()).unwrap(),
+            ChannelId(0)
+        );
     }
 }
@@ -2,10 +2,12 @@
 
 use std::collections::VecDeque;
 use std::fmt;
+use std::time::Duration;
 
 use crate::net::DatagramSend;
+use crate::util::SystemTime;
 
-use super::{CryptoError, Fingerprint, KeyingMaterial, SrtpProfile};
+use super::{CryptoError, Fingerprint, FingerprintHash, KeyingMaterial, SrtpProfile};
 
 // libWebRTC says "WebRTC" here when doing OpenSSL, for BoringSSL they seem
 // to generate a random 8 characters.
@@ -30,6 +32,9 @@ pub enum DtlsEvent {
 
     /// Decrypted data from incoming DTLS traffic.
     Data(Vec<u8>),
+
+    /// The remote peer sent a close_notify alert.
+    Closed,
 }
 
 /// Certificate used for DTLS.
@@ -50,22 +55,62 @@ impl DtlsCert {
         DtlsCert(DtlsCertInner::OpenSsl(cert))
     }
 
+    /// Create a new OpenSSL variant of the certificate valid for `lifetime`.
+    ///
+    /// [`DtlsCert::new_openssl()`] creates certificates valid for 7 days.
+    #[cfg(feature = "openssl")]
+    pub fn new_openssl_with_lifetime(lifetime: Duration) -> Self {
+        let cert = super::ossl::OsslDtlsCert::with_lifetime(lifetime);
+        DtlsCert(DtlsCertInner::OpenSsl(cert))
+    }
+
+    /// When this certificate was created.
+    pub fn created(&self) -> SystemTime {
+        match &self.0 {
+            #[cfg(feature = "openssl")]
+            DtlsCertInner::OpenSsl(v) => v.created(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// When this certificate expires.
+    ///
+    /// A DTLS handshake started after this time is expected to fail. Established
+    /// sessions are not affected, but the certificate must be regenerated before
+    /// renegotiating, see [`Rtc::regenerate_dtls_cert()`][crate::Rtc::regenerate_dtls_cert].
+    pub fn expires(&self) -> SystemTime {
+        match &self.0 {
+            #[cfg(feature = "openssl")]
+            DtlsCertInner::OpenSsl(v) => v.expires(),
+            _ => unreachable!(),
+        }
+    }
+
     /// Creates a fingerprint for this certificate.
     ///
     /// Fingerprints are used to verify a remote peer's certificate.
     pub fn fingerprint(&self) -> Fingerprint {
+        self.fingerprint_with(FingerprintHash::Sha256)
+    }
+
+    /// Creates a fingerprint for this certificate using the given hash function.
+    pub fn fingerprint_with(&self, hash: FingerprintHash) -> Fingerprint {
         match &self.0 {
             #[cfg(feature = "openssl")]
-            DtlsCertInner::OpenSsl(v) => v.fingerprint(),
+            DtlsCertInner::OpenSsl(v) => v.fingerprint(hash),
             _ => unreachable!(),
         }
     }
 
-    pub(crate) fn create_dtls_impl(&self) -> Result<DtlsImpl, CryptoError> {
+    pub(crate) fn create_dtls_impl(
+        &self,
+        srtp_profiles: &[SrtpProfile],
+    ) -> Result<DtlsImpl, CryptoError> {
         match &self.0 {
             #[cfg(feature = "openssl")]
             DtlsCertInner::OpenSsl(c) => Ok(DtlsImpl::OpenSsl(super::ossl::OsslDtlsImpl::new(
                 c.clone(),
+                srtp_profiles,
             )?)),
             _ => unreachable!(),
         }
@@ -92,6 +137,9 @@ pub trait DtlsInner: Sized {
     /// Handle the handshake. Once this succeeds, it becomes a no-op.
     fn handle_handshake(&mut self, o: &mut VecDeque<DtlsEvent>) -> Result<bool, CryptoError>;
 
+    /// Set the max size of outgoing datagrams. Ignored once the handshake has started.
+    fn set_mtu(&mut self, mtu: usize);
+
     /// If set_active, returns what was set.
     fn is_active(&self) -> Option<bool>;
 
@@ -106,6 +154,12 @@ pub trait DtlsInner: Sized {
 
     /// Whether the DTLS connection is established.
     fn is_connected(&self) -> bool;
+
+    /// Fingerprint of the remote certificate, once connected.
+    fn remote_fingerprint(&self, hash: FingerprintHash) -> Option<Fingerprint>;
+
+    /// Send a close_notify alert to the remote peer.
+    fn close(&mut self) -> Result<(), CryptoError>;
 }
 
 pub enum DtlsImpl {
@@ -122,6 +176,14 @@ impl DtlsImpl {
         }
     }
 
+    pub fn set_mtu(&mut self, mtu: usize) {
+        match self {
+            #[cfg(feature = "openssl")]
+            DtlsImpl::OpenSsl(i) => i.set_mtu(mtu),
+            _ => unreachable!(),
+        }
+    }
+
     pub fn handle_handshake(&mut self, o: &mut VecDeque<DtlsEvent>) -> Result<bool, CryptoError> {
         match self {
             #[cfg(feature = "openssl")]
@@ -173,4 +235,20 @@ impl DtlsImpl {
             _ => unreachable!(),
         }
     }
+
+    pub fn remote_fingerprint(&self, hash: FingerprintHash) -> Option<Fingerprint> {
+        match self {
+            #[cfg(feature = "openssl")]
+            DtlsImpl::OpenSsl(i) => i.remote_fingerprint(hash),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn close(&mut self) -> Result<(), CryptoError> {
+        match self {
+            #[cfg(feature = "openssl")]
+            DtlsImpl::OpenSsl(i) => i.close(),
+            _ => unreachable!(),
+        }
+    }
 }
@@ -15,6 +15,53 @@ pub struct Fingerprint {
     pub bytes: Vec<u8>,
 }
 
+impl Fingerprint {
+    /// The hash function, if it's one we can verify.
+    pub fn hash(&self) -> Option<FingerprintHash> {
+        FingerprintHash::from_name(&self.hash_func)
+    }
+}
+
+/// Hash functions supported for certificate fingerprints.
+///
+/// Some gateways signal `sha-384` or `sha-512` fingerprints. Whichever function
+/// the remote signals is used to verify its certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum FingerprintHash {
+    /// `sha-256`, used by all browsers.
+    #[default]
+    Sha256,
+    /// `sha-384`
+    Sha384,
+    /// `sha-512`
+    Sha512,
+}
+
+impl FingerprintHash {
+    /// The name of the hash function as used in the SDP `a=fingerprint` attribute.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FingerprintHash::Sha256 => "sha-256",
+            FingerprintHash::Sha384 => "sha-384",
+            FingerprintHash::Sha512 => "sha-512",
+        }
+    }
+
+    /// Parse the name of a hash function. Hash names are case insensitive (RFC 8122).
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Sha256, Self::Sha384, Self::Sha512]
+            .into_iter()
+            .find(|h| h.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+impl fmt::Display for FingerprintHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 // DO NOT CHANGE!
 // This format is exactly what's needed in n SDP.
 impl fmt::Display for Fingerprint {
@@ -68,4 +115,18 @@ mod test {
             "foo 00:01:02:03:04:05:06:07:08:09:0A:0B:0C:0D:0E:0F:10:11"
         );
     }
+
+    #[test]
+    fn fingerprint_hash_names() {
+        let f: Fingerprint = "SHA-384 00:01:02".parse().unwrap();
+        assert_eq!(f.hash(), Some(FingerprintHash::Sha384));
+
+        let f: Fingerprint = "sha-512 00:01:02".parse().unwrap();
+        assert_eq!(f.hash(), Some(FingerprintHash::Sha512));
+
+        let f: Fingerprint = "md5 00:01:02".parse().unwrap();
+        assert_eq!(f.hash(), None);
+
+        assert_eq!(FingerprintHash::default().to_string(), "sha-256");
+    }
 }
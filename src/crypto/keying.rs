@@ -4,6 +4,7 @@ use std::ops::Deref;
 pub struct KeyingMaterial(Vec<u8>);
 
 impl KeyingMaterial {
+    #[cfg_attr(not(feature = "openssl"), allow(dead_code))]
     pub fn new(m: Vec<u8>) -> Self {
         KeyingMaterial(m)
     }
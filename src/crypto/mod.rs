@@ -10,11 +10,14 @@ mod dtls;
 pub use dtls::{DtlsCert, DtlsEvent, DtlsImpl};
 
 mod finger;
-pub use finger::Fingerprint;
+pub use finger::{Fingerprint, FingerprintHash};
 
 mod keying;
 pub use keying::KeyingMaterial;
 
+mod md5;
+pub(crate) use md5::md5;
+
 mod srtp;
 pub use srtp::{aead_aes_128_gcm, aes_128_cm_sha1_80, new_aead_aes_128_gcm};
 pub use srtp::{new_aes_128_cm_sha1_80, srtp_aes_128_ecb_round, SrtpProfile};
@@ -1,4 +1,4 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use openssl::asn1::{Asn1Integer, Asn1Time, Asn1Type};
 use openssl::bn::BigNum;
@@ -9,28 +9,38 @@ use openssl::rsa::Rsa;
 use openssl::x509::{X509Name, X509};
 
 use crate::crypto::dtls::DTLS_CERT_IDENTITY;
-use crate::crypto::Fingerprint;
+use crate::crypto::{Fingerprint, FingerprintHash};
 
 use super::CryptoError;
 
 const RSA_F4: u32 = 0x10001;
 
+/// Validity of generated certificates unless otherwise specified.
+const DEFAULT_CERT_LIFETIME: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 /// Certificate used for DTLS.
 #[derive(Debug, Clone)]
 pub struct OsslDtlsCert {
     pub(crate) pkey: PKey<Private>,
     pub(crate) x509: X509,
+    created: SystemTime,
+    expires: SystemTime,
 }
 
 impl OsslDtlsCert {
     /// Creates a new (self signed) DTLS certificate.
     pub fn new() -> Self {
-        Self::self_signed().expect("create dtls cert")
+        Self::with_lifetime(DEFAULT_CERT_LIFETIME)
+    }
+
+    /// Creates a new (self signed) DTLS certificate valid for `lifetime` from now.
+    pub fn with_lifetime(lifetime: Duration) -> Self {
+        Self::self_signed(lifetime).expect("create dtls cert")
     }
 
     // The libWebRTC code we try to match is at:
     // https://webrtc.googlesource.com/src/+/1568f1b1330f94494197696fe235094e6293b258/rtc_base/openssl_certificate.cc#58
-    fn self_signed() -> Result<Self, CryptoError> {
+    fn self_signed(lifetime: Duration) -> Result<Self, CryptoError> {
         let f4 = BigNum::from_u32(RSA_F4).unwrap();
         let key = Rsa::generate_with_e(2048, &f4)?;
         let pkey = PKey::from_rsa(key)?;
@@ -47,9 +57,10 @@ impl OsslDtlsCert {
         let serial_bn = BigNum::from_slice(&serial_buf)?;
         let serial = Asn1Integer::from_bn(&serial_bn)?;
         x509b.set_serial_number(&serial)?;
-        let before = Asn1Time::from_unix(unix_time() - 3600)?;
+        let now = unix_time();
+        let before = Asn1Time::from_unix(now - 3600)?;
         x509b.set_not_before(&before)?;
-        let after = Asn1Time::days_from_now(7)?;
+        let after = Asn1Time::from_unix(now + lifetime.as_secs() as libc::time_t)?;
         x509b.set_not_after(&after)?;
         x509b.set_pubkey(&pkey)?;
 
@@ -75,23 +86,33 @@ impl OsslDtlsCert {
         x509b.sign(&pkey, MessageDigest::sha1())?;
         let x509 = x509b.build();
 
-        Ok(OsslDtlsCert { pkey, x509 })
+        let created = SystemTime::UNIX_EPOCH + Duration::from_secs(now as u64);
+        let expires = created + Duration::from_secs(lifetime.as_secs());
+
+        Ok(OsslDtlsCert {
+            pkey,
+            x509,
+            created,
+            expires,
+        })
+    }
+
+    /// When the certificate was created.
+    pub fn created(&self) -> SystemTime {
+        self.created
+    }
+
+    /// When the certificate stops being valid (the `notAfter` field).
+    pub fn expires(&self) -> SystemTime {
+        self.expires
     }
 
     /// Produce a (public) fingerprint of the cert.
     ///
     /// This is sent via SDP to the other peer to lock down the DTLS
     /// to this specific certificate.
-    pub fn fingerprint(&self) -> Fingerprint {
-        let digest: &[u8] = &self
-            .x509
-            .digest(MessageDigest::sha256())
-            .expect("digest to fingerprint");
-
-        Fingerprint {
-            hash_func: "sha-256".into(),
-            bytes: digest.to_vec(),
-        }
+    pub fn fingerprint(&self, hash: FingerprintHash) -> Fingerprint {
+        super::fingerprint(&self.x509, hash).expect("digest to fingerprint")
     }
 }
 
@@ -6,7 +6,7 @@ use openssl::nid::Nid;
 use openssl::ssl::{Ssl, SslContext, SslContextBuilder, SslMethod, SslOptions, SslVerifyMode};
 
 use crate::crypto::dtls::DtlsInner;
-use crate::crypto::{DtlsEvent, SrtpProfile};
+use crate::crypto::{DtlsEvent, Fingerprint, FingerprintHash, SrtpProfile};
 use crate::io::{DATAGRAM_MTU, DATAGRAM_MTU_WARN};
 
 use super::cert::OsslDtlsCert;
@@ -32,8 +32,11 @@ pub struct OsslDtlsImpl {
 }
 
 impl OsslDtlsImpl {
-    pub fn new(cert: OsslDtlsCert) -> Result<Self, super::CryptoError> {
-        let context = dtls_create_ctx(&cert)?;
+    pub fn new(
+        cert: OsslDtlsCert,
+        srtp_profiles: &[SrtpProfile],
+    ) -> Result<Self, super::CryptoError> {
+        let context = dtls_create_ctx(&cert, srtp_profiles)?;
         let ssl = dtls_ssl_create(&context)?;
         Ok(OsslDtlsImpl {
             _cert: cert,
@@ -48,6 +51,10 @@ impl DtlsInner for OsslDtlsImpl {
         self.tls.set_active(active);
     }
 
+    fn set_mtu(&mut self, mtu: usize) {
+        self.tls.set_mtu(mtu);
+    }
+
     fn is_active(&self) -> Option<bool> {
         self.tls.is_active()
     }
@@ -68,6 +75,13 @@ impl DtlsInner for OsslDtlsImpl {
             }
             Err(e) => return Err(e.into()),
         };
+
+        if n == 0 {
+            debug!("DTLS close_notify from remote");
+            o.push_back(DtlsEvent::Closed);
+            return Ok(());
+        }
+
         buf.truncate(n);
 
         o.push_back(DtlsEvent::Data(buf));
@@ -94,6 +108,14 @@ impl DtlsInner for OsslDtlsImpl {
         self.tls.is_connected()
     }
 
+    fn remote_fingerprint(&self, hash: FingerprintHash) -> Option<Fingerprint> {
+        self.tls.peer_fingerprint(hash)
+    }
+
+    fn close(&mut self) -> Result<(), CryptoError> {
+        Ok(self.tls.close()?)
+    }
+
     fn handle_handshake(&mut self, output: &mut VecDeque<DtlsEvent>) -> Result<bool, CryptoError> {
         if self.tls.is_handshaken() {
             // Nice. Nothing to do.
@@ -116,7 +138,10 @@ impl DtlsInner for OsslDtlsImpl {
     }
 }
 
-pub fn dtls_create_ctx(cert: &OsslDtlsCert) -> Result<SslContext, CryptoError> {
+pub fn dtls_create_ctx(
+    cert: &OsslDtlsCert,
+    srtp_profiles: &[SrtpProfile],
+) -> Result<SslContext, CryptoError> {
     // TODO: Technically we want to disallow DTLS < 1.2, but that requires
     // us to use this commented out unsafe. We depend on browsers disallowing
     // it instead.
@@ -127,7 +152,7 @@ pub fn dtls_create_ctx(cert: &OsslDtlsCert) -> Result<SslContext, CryptoError> {
     let srtp_profiles = {
         // Rust can't join directly to a string, need to allocate a vec first :(
         // This happens very rarely so the extra allocations don't matter
-        let all: Vec<_> = SrtpProfile::ALL
+        let all: Vec<_> = srtp_profiles
             .iter()
             .map(SrtpProfile::openssl_name)
             .collect();
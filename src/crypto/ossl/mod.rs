@@ -1,6 +1,9 @@
 //! OpenSSL implementation of cryptographic functions.
 
-use super::{CryptoError, SrtpProfile};
+use openssl::hash::MessageDigest;
+use openssl::x509::X509Ref;
+
+use super::{CryptoError, Fingerprint, FingerprintHash, SrtpProfile};
 
 mod cert;
 pub use cert::OsslDtlsCert;
@@ -14,6 +17,25 @@ pub use dtls::OsslDtlsImpl;
 mod srtp;
 pub use srtp::OsslSrtpCryptoImpl;
 
+/// Fingerprint of a certificate using the given hash function.
+pub(crate) fn fingerprint(
+    x509: &X509Ref,
+    hash: FingerprintHash,
+) -> Result<Fingerprint, CryptoError> {
+    let md = match hash {
+        FingerprintHash::Sha256 => MessageDigest::sha256(),
+        FingerprintHash::Sha384 => MessageDigest::sha384(),
+        FingerprintHash::Sha512 => MessageDigest::sha512(),
+    };
+
+    let digest: &[u8] = &x509.digest(md)?;
+
+    Ok(Fingerprint {
+        hash_func: hash.as_str().into(),
+        bytes: digest.to_vec(),
+    })
+}
+
 impl SrtpProfile {
     /// What this profile is called in OpenSSL parlance.
     pub(crate) fn openssl_name(&self) -> &'static str {
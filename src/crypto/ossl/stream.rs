@@ -1,12 +1,11 @@
 use std::panic::UnwindSafe;
 use std::{io, mem};
 
-use openssl::hash::MessageDigest;
 use openssl::srtp::SrtpProfileId;
 use openssl::ssl::{HandshakeError, MidHandshakeSslStream, Ssl, SslStream};
 
 use crate::change::Fingerprint;
-use crate::crypto::{KeyingMaterial, SrtpProfile};
+use crate::crypto::{FingerprintHash, KeyingMaterial, SrtpProfile};
 
 use super::CryptoError;
 
@@ -59,6 +58,17 @@ where
         self.active = Some(active);
     }
 
+    pub fn set_mtu(&mut self, mtu: usize) {
+        match &mut self.state {
+            State::Init(ssl, _) => {
+                if let Err(e) = ssl.set_mtu(mtu as u32) {
+                    warn!("Failed to set DTLS MTU {}: {:?}", mtu, e);
+                }
+            }
+            _ => debug!("Ignore DTLS MTU change after handshake start"),
+        }
+    }
+
     pub fn complete_handshake_until_block(&mut self) -> Result<bool, CryptoError> {
         if let Err(e) = self.handshaken() {
             if e.kind() == io::ErrorKind::WouldBlock {
@@ -89,6 +99,25 @@ where
         Ok(v)
     }
 
+    /// Fingerprint of the remote certificate, once handshaken.
+    pub fn peer_fingerprint(&self, hash: FingerprintHash) -> Option<Fingerprint> {
+        let State::Established(v) = &self.state else {
+            return None;
+        };
+        let x509 = v.ssl().peer_certificate()?;
+        super::fingerprint(&x509, hash).ok()
+    }
+
+    /// Send a close_notify alert, if the handshake is done.
+    pub fn close(&mut self) -> Result<(), io::Error> {
+        let State::Established(v) = &mut self.state else {
+            return Ok(());
+        };
+        v.shutdown()
+            .map(|_| ())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
     pub fn take_srtp_keying_material(
         &mut self,
     ) -> Option<(KeyingMaterial, SrtpProfile, Fingerprint)> {
@@ -167,12 +196,8 @@ fn export_srtp_keying_material<S>(
     let x509 = ssl
         .peer_certificate()
         .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "No remote X509 cert"))?;
-    let digest: &[u8] = &x509.digest(MessageDigest::sha256())?;
-
-    let fp = Fingerprint {
-        hash_func: "sha-256".into(),
-        bytes: digest.to_vec(),
-    };
+    let fp = super::fingerprint(&x509, FingerprintHash::Sha256)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
 
     let srtp_profile_id = ssl
         .selected_srtp_profile()
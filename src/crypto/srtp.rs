@@ -3,11 +3,17 @@ use std::fmt;
 use self::aead_aes_128_gcm::AeadKey;
 use self::aes_128_cm_sha1_80::AesKey;
 
+/// SRTP protection profiles negotiated in the DTLS handshake.
+///
+/// See [`RtcConfig::set_srtp_profiles()`][crate::RtcConfig::set_srtp_profiles].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SrtpProfile {
     #[cfg(feature = "_internal_test_exports")]
+    #[doc(hidden)]
     PassThrough,
+    /// SRTP_AES128_CM_HMAC_SHA1_80 (RFC 5764).
     Aes128CmSha1_80,
+    /// SRTP_AEAD_AES_128_GCM (RFC 7714).
     AeadAes128Gcm,
 }
 
@@ -88,6 +94,7 @@ pub fn srtp_aes_128_ecb_round(key: &[u8], input: &[u8], output: &mut [u8]) {
     }
 }
 
+#[cfg_attr(not(feature = "openssl"), allow(dead_code))]
 pub trait SrtpCryptoImpl {
     type Aes128CmSha1_80: aes_128_cm_sha1_80::CipherCtx;
     type AeadAes128Gcm: aead_aes_128_gcm::CipherCtx;
@@ -117,6 +124,7 @@ pub mod aes_128_cm_sha1_80 {
     pub type RtpIv = [u8; 16];
 
     pub trait CipherCtx: UnwindSafe + Send + Sync {
+        #[cfg_attr(not(feature = "openssl"), allow(dead_code))]
         fn new(key: AesKey, encrypt: bool) -> Self
         where
             Self: Sized;
@@ -190,6 +198,7 @@ pub mod aead_aes_128_gcm {
     pub type RtpIv = [u8; SALT_LEN];
 
     pub trait CipherCtx: UnwindSafe + Send + Sync {
+        #[cfg_attr(not(feature = "openssl"), allow(dead_code))]
         fn new(key: AeadKey, encrypt: bool) -> Self
         where
             Self: Sized;
@@ -1,11 +1,36 @@
 use std::collections::VecDeque;
+use std::time::Duration;
 use std::{fmt, io};
 use thiserror::Error;
 
-use crate::crypto::{CryptoError, DtlsImpl, Fingerprint};
+use crate::crypto::{CryptoError, DtlsImpl, Fingerprint, FingerprintHash, SrtpProfile};
 
 pub use crate::crypto::{DtlsCert, DtlsEvent};
 use crate::net::DatagramSend;
+use crate::util::Instant;
+
+/// Initial interval for retransmitting a handshake flight (RFC 6347 4.2.4.1).
+const HANDSHAKE_RTO_INITIAL: Duration = Duration::from_secs(1);
+
+/// Max interval between handshake retransmits, after backing off.
+const HANDSHAKE_RTO_MAX: Duration = Duration::from_secs(60);
+
+/// Record content type of a ChangeCipherSpec (RFC 6347 4.1).
+const CONTENT_TYPE_CHANGE_CIPHER_SPEC: u8 = 20;
+
+/// Record content type of a handshake message.
+const CONTENT_TYPE_HANDSHAKE: u8 = 22;
+
+/// Record content type of application data.
+const CONTENT_TYPE_APPLICATION_DATA: u8 = 23;
+
+/// Whether the datagram starts with a handshake or ChangeCipherSpec record.
+fn is_handshake(datagram: &[u8]) -> bool {
+    matches!(
+        datagram.first(),
+        Some(&CONTENT_TYPE_CHANGE_CIPHER_SPEC) | Some(&CONTENT_TYPE_HANDSHAKE)
+    )
+}
 
 /// Errors that can arise in DTLS.
 #[derive(Debug, Error)]
@@ -21,6 +46,7 @@ pub enum DtlsError {
 }
 
 impl DtlsError {
+    #[cfg(feature = "sctp")]
     pub(crate) fn is_would_block(&self) -> bool {
         #[allow(irrefutable_let_patterns)]
         let DtlsError::Io(e) = self
@@ -41,10 +67,56 @@ impl From<CryptoError> for DtlsError {
     }
 }
 
+/// State of the DTLS transport.
+///
+/// Mirrors the [`RTCDtlsTransportState`][1] of the browser API.
+///
+/// [1]: https://www.w3.org/TR/webrtc/#rtcdtlstransportstate-enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DtlsState {
+    /// The handshake has not started.
+    New,
+
+    /// The handshake is in progress.
+    Connecting,
+
+    /// The handshake completed and the remote fingerprint is verified.
+    Connected,
+
+    /// The handshake failed, or the remote fingerprint didn't verify.
+    Failed,
+
+    /// The transport was closed.
+    Closed,
+}
+
+impl DtlsState {
+    /// Tells if this state is the connected state.
+    pub fn is_connected(&self) -> bool {
+        *self == DtlsState::Connected
+    }
+}
+
 /// Encapsulation of DTLS.
 pub struct Dtls {
     dtls_impl: DtlsImpl,
 
+    /// The local certificate.
+    cert: DtlsCert,
+
+    /// Certificate replacing the local one from the next handshake.
+    next_cert: Option<DtlsCert>,
+
+    /// SRTP profiles to offer, kept for recreating the impl with a new certificate.
+    srtp_profiles: Vec<SrtpProfile>,
+
+    /// Max size of outgoing datagrams, kept for the same reason.
+    mtu: Option<usize>,
+
+    /// Hash function for the local fingerprint.
+    fingerprint_hash: FingerprintHash,
+
     /// The fingerprint of the certificate.
     fingerprint: Fingerprint,
 
@@ -53,6 +125,32 @@ pub struct Dtls {
 
     /// Events ready to be polled.
     events: VecDeque<DtlsEvent>,
+
+    /// Set when the handshake or verification failed.
+    failed: bool,
+
+    /// Set when the remote sent a close_notify.
+    remote_closed: bool,
+
+    /// When to retransmit the last flight.
+    ///
+    /// Unset until the next timeout after the handshake made progress.
+    retransmit_at: Option<Instant>,
+
+    /// Copies of the datagrams of the last handshake flight we sent.
+    flight: Vec<Vec<u8>>,
+
+    /// Set when the remote answered, which means the next datagram starts a new flight.
+    flight_ended: bool,
+
+    /// Set once the remote sent application data, and the last flight is not needed.
+    handshake_done: bool,
+
+    /// Retransmitted datagrams to send before anything new.
+    resend: VecDeque<DatagramSend>,
+
+    /// Current interval between handshake retransmits.
+    rto: Duration,
 }
 
 impl Dtls {
@@ -60,15 +158,28 @@ impl Dtls {
     ///
     /// `active` indicates whether this side should initiate the handshake or not.
     /// This in turn is governed by the `a=setup` SDP attribute.
-    pub fn new(cert: DtlsCert) -> Result<Self, DtlsError> {
-        let dtls_impl = cert.create_dtls_impl()?;
+    pub fn new(cert: DtlsCert, srtp_profiles: &[SrtpProfile]) -> Result<Self, DtlsError> {
+        let dtls_impl = cert.create_dtls_impl(srtp_profiles)?;
         let fingerprint = cert.fingerprint();
 
         Ok(Self {
             dtls_impl,
+            cert,
+            next_cert: None,
+            srtp_profiles: srtp_profiles.to_vec(),
+            mtu: None,
+            fingerprint_hash: FingerprintHash::Sha256,
             fingerprint,
             remote_fingerprint: None,
             events: VecDeque::new(),
+            failed: false,
+            remote_closed: false,
+            retransmit_at: None,
+            rto: HANDSHAKE_RTO_INITIAL,
+            flight: vec![],
+            flight_ended: false,
+            handshake_done: false,
+            resend: VecDeque::new(),
         })
     }
 
@@ -84,9 +195,93 @@ impl Dtls {
     /// i.e. initiating the client hello or not. This must be called
     /// exactly once before starting to handshake (I/O).
     pub fn set_active(&mut self, active: bool) {
+        debug!(active, "DTLS handshake start");
         self.dtls_impl.set_active(active)
     }
 
+    /// Set the max size of outgoing DTLS datagrams.
+    ///
+    /// Only has an effect before the handshake has started.
+    pub fn set_mtu(&mut self, mtu: usize) {
+        self.mtu = Some(mtu);
+        self.dtls_impl.set_mtu(mtu)
+    }
+
+    /// The local certificate in use.
+    pub fn cert(&self) -> &DtlsCert {
+        &self.cert
+    }
+
+    /// Replace the local certificate.
+    ///
+    /// Before the handshake has started, this takes effect straight away. After, the
+    /// certificate is used from the next handshake, see [`Dtls::restart()`].
+    ///
+    /// Either way, the local fingerprint is that of the new certificate. Returns true
+    /// if the certificate is in use already.
+    pub fn set_cert(&mut self, cert: DtlsCert) -> Result<bool, DtlsError> {
+        self.fingerprint = cert.fingerprint_with(self.fingerprint_hash);
+
+        if self.is_inited() {
+            self.next_cert = Some(cert);
+            return Ok(false);
+        }
+
+        self.dtls_impl = self.create_dtls_impl(&cert)?;
+        self.cert = cert;
+
+        Ok(true)
+    }
+
+    /// Tells if there is a certificate waiting for the next handshake.
+    pub fn has_next_cert(&self) -> bool {
+        self.next_cert.is_some()
+    }
+
+    /// Start over with a new handshake in the same role, with the next certificate if
+    /// there is one.
+    ///
+    /// This is for renegotiating DTLS, which happens when either side changes its
+    /// certificate. Does nothing if the handshake hasn't started.
+    pub fn restart(&mut self) -> Result<(), DtlsError> {
+        let Some(active) = self.is_active() else {
+            return Ok(());
+        };
+
+        if let Some(cert) = self.next_cert.take() {
+            self.cert = cert;
+        }
+
+        debug!(active, "DTLS restart handshake");
+
+        self.dtls_impl = self.create_dtls_impl(&self.cert)?;
+        self.remote_fingerprint = None;
+        self.events.clear();
+        self.failed = false;
+        self.remote_closed = false;
+        self.retransmit_at = None;
+        self.rto = HANDSHAKE_RTO_INITIAL;
+        self.flight.clear();
+        self.flight_ended = false;
+        self.handshake_done = false;
+        self.resend.clear();
+
+        self.set_active(active);
+        if active {
+            self.handle_handshake()?;
+        }
+
+        Ok(())
+    }
+
+    fn create_dtls_impl(&self, cert: &DtlsCert) -> Result<DtlsImpl, DtlsError> {
+        let mut dtls_impl = cert.create_dtls_impl(&self.srtp_profiles)?;
+        if let Some(mtu) = self.mtu {
+            dtls_impl.set_mtu(mtu);
+        }
+        Ok(dtls_impl)
+    }
+
     /// If set_active, returns what was set.
     pub fn is_active(&self) -> Option<bool> {
         self.dtls_impl.is_active()
@@ -94,19 +289,49 @@ impl Dtls {
 
     /// The local fingerprint.
     ///
-    /// To be communicated in SDP offers sent to the remote peer.
+    /// To be communicated in SDP offers sent to the remote peer. After replacing the
+    /// certificate, this is the fingerprint of the new one.
     pub fn local_fingerprint(&self) -> &Fingerprint {
         &self.fingerprint
     }
 
+    /// Set the hash function used for the local fingerprint.
+    pub fn set_fingerprint_hash(&mut self, hash: FingerprintHash) {
+        self.fingerprint_hash = hash;
+        let cert = self.next_cert.as_ref().unwrap_or(&self.cert);
+        self.fingerprint = cert.fingerprint_with(hash);
+    }
+
     /// Remote fingerprint.
     pub fn remote_fingerprint(&self) -> &Option<Fingerprint> {
         &self.remote_fingerprint
     }
 
+    /// Remote fingerprint using the given hash function.
+    ///
+    /// Only available once connected.
+    pub fn remote_fingerprint_with(&self, hash: FingerprintHash) -> Option<Fingerprint> {
+        self.dtls_impl.remote_fingerprint(hash)
+    }
+
     /// Poll for the next datagram to send.
     pub fn poll_datagram(&mut self) -> Option<DatagramSend> {
-        self.dtls_impl.poll_datagram()
+        if let Some(d) = self.resend.pop_front() {
+            return Some(d);
+        }
+
+        let datagram = self.dtls_impl.poll_datagram()?;
+
+        // Keep a copy of handshake datagrams to retransmit on our own timer.
+        if !self.handshake_done && is_handshake(&datagram) {
+            if self.flight_ended {
+                self.flight.clear();
+                self.flight_ended = false;
+            }
+            self.flight.push(datagram.to_vec());
+        }
+
+        Some(datagram)
     }
 
     /// Poll for an event.
@@ -119,6 +344,7 @@ impl Dtls {
     }
 
     /// Handling incoming data to be sent as DTLS datagrams.
+    #[cfg(feature = "sctp")]
     pub fn handle_input(&mut self, data: &[u8]) -> Result<(), DtlsError> {
         Ok(self.dtls_impl.handle_input(data)?)
     }
@@ -130,7 +356,31 @@ impl Dtls {
             return Ok(());
         }
 
-        Ok(self.dtls_impl.handle_receive(message, &mut self.events)?)
+        // Once connected, handshake datagrams from the remote means it didn't get our last
+        // flight. The TLS library drops them as replays when the remote retransmits the
+        // exact same datagrams, so we answer with our last flight here.
+        if self.dtls_impl.is_connected() && !self.handshake_done {
+            if is_handshake(message) {
+                debug!("DTLS resend last flight to remote still handshaking");
+                self.queue_flight();
+            } else if message.first() == Some(&CONTENT_TYPE_APPLICATION_DATA) {
+                // The remote is connected too.
+                self.handshake_done = true;
+                self.flight.clear();
+            }
+        }
+
+        let result = self.dtls_impl.handle_receive(message, &mut self.events);
+        if result.is_err() && !self.dtls_impl.is_connected() {
+            self.failed = true;
+        }
+
+        // The remote is responsive, start over with the retransmit interval.
+        self.retransmit_at = None;
+        self.rto = HANDSHAKE_RTO_INITIAL;
+        self.flight_ended = true;
+
+        Ok(result?)
     }
 
     /// Handle handshaking.
@@ -138,7 +388,13 @@ impl Dtls {
     /// Once handshaken, this becomes a noop.
     pub fn handle_handshake(&mut self) -> Result<bool, DtlsError> {
         let len_before = self.events.len();
-        let result = self.dtls_impl.handle_handshake(&mut self.events)?;
+        let result = match self.dtls_impl.handle_handshake(&mut self.events) {
+            Ok(v) => v,
+            Err(e) => {
+                self.failed = true;
+                return Err(e.into());
+            }
+        };
 
         if self.remote_fingerprint.is_none() && self.events.len() > len_before {
             for ev in &self.events {
@@ -154,6 +410,82 @@ impl Dtls {
     pub(crate) fn is_connected(&self) -> bool {
         self.dtls_impl.is_connected()
     }
+
+    /// When to retransmit the last handshake flight.
+    pub(crate) fn poll_timeout(&self) -> Option<Instant> {
+        self.retransmit_at
+    }
+
+    /// Retransmit the last handshake flight, if the remote hasn't answered in time.
+    ///
+    /// The TLS library keeps its own retransmit timer on the wallclock, which doesn't
+    /// follow the `now` we are driven with. Instead we resend our copy of the last flight,
+    /// with the back off of RFC 6347.
+    pub(crate) fn handle_timeout(&mut self, now: Instant) {
+        if !self.is_inited() || self.is_connected() || self.failed {
+            self.retransmit_at = None;
+            return;
+        }
+
+        let Some(retransmit_at) = self.retransmit_at else {
+            self.retransmit_at = Some(now + self.rto);
+            return;
+        };
+
+        if now < retransmit_at {
+            return;
+        }
+
+        debug!("DTLS handshake retransmit after {:?}", self.rto);
+        self.queue_flight();
+
+        self.rto = (self.rto * 2).min(HANDSHAKE_RTO_MAX);
+        self.retransmit_at = Some(now + self.rto);
+    }
+
+    fn queue_flight(&mut self) {
+        // Unless already queued.
+        if !self.resend.is_empty() {
+            return;
+        }
+        let copies = self.flight.iter().map(|d| DatagramSend::from(d.clone()));
+        self.resend.extend(copies);
+    }
+
+    /// Send a close_notify alert to the remote peer, if connected.
+    pub(crate) fn close(&mut self) -> Result<(), DtlsError> {
+        if !self.is_connected() {
+            return Ok(());
+        }
+        debug!("DTLS send close_notify");
+        Ok(self.dtls_impl.close()?)
+    }
+
+    /// Mark the transport as failed, i.e. when the remote fingerprint doesn't match.
+    pub(crate) fn set_failed(&mut self) {
+        self.failed = true;
+    }
+
+    /// Mark the transport as closed by the remote, after polling [`DtlsEvent::Closed`].
+    pub(crate) fn set_remote_closed(&mut self) {
+        self.remote_closed = true;
+    }
+
+    /// The current state. [`DtlsState::Closed`] is only for a close by the remote,
+    /// closing locally is up to the owner.
+    pub(crate) fn state(&self) -> DtlsState {
+        if self.failed {
+            DtlsState::Failed
+        } else if self.remote_closed {
+            DtlsState::Closed
+        } else if self.is_connected() {
+            DtlsState::Connected
+        } else if self.is_inited() {
+            DtlsState::Connecting
+        } else {
+            DtlsState::New
+        }
+    }
 }
 
 impl fmt::Debug for DtlsEvent {
@@ -169,6 +501,7 @@ impl fmt::Debug for DtlsEvent {
                 f.debug_tuple("RemoteFingerprint").field(arg0).finish()
             }
             Self::Data(arg0) => f.debug_tuple("Data").field(&arg0.len()).finish(),
+            Self::Closed => write!(f, "Closed"),
         }
     }
 }
@@ -0,0 +1,619 @@
+//! C ABI for embedding str0m in C/C++ applications.
+//!
+//! Enable with the `ffi` feature. The matching header is `include/str0m.h`. To get a library
+//! to link against, build with the crate type of your choice:
+//!
+//! ```text
+//! cargo rustc --release --features ffi --crate-type staticlib
+//! ```
+//!
+//! The API mirrors the sans-IO loop of [`Rtc`]. The [`Str0mRtc`] handle is opaque and owns the
+//! `Rtc`. Time is given as microseconds on a monotonic clock of the caller's choice, and
+//! addresses as [`Str0mAddr`].
+//!
+//! ```c
+//! Str0mRtc *rtc = str0m_rtc_new(now_us());
+//!
+//! Str0mAddr local = { .family = 4, .ip = {192, 168, 0, 2}, .port = 5000 };
+//! str0m_rtc_add_local_candidate(rtc, &local, STR0M_PROTO_UDP);
+//!
+//! Str0mSlice answer;
+//! str0m_rtc_accept_offer(rtc, offer, offer_len, &answer);
+//!
+//! for (;;) {
+//!     Str0mOutput out;
+//!     if (str0m_rtc_poll_output(rtc, &out) != STR0M_OK) {
+//!         break;
+//!     }
+//!     switch (out.kind) {
+//!     case STR0M_OUTPUT_TIMEOUT:
+//!         // wait for socket input or until out.timeout_us, then call
+//!         // str0m_rtc_handle_receive() or str0m_rtc_handle_timeout().
+//!         break;
+//!     case STR0M_OUTPUT_TRANSMIT:
+//!         // send out.data from out.source to out.destination.
+//!         break;
+//!     case STR0M_OUTPUT_EVENT:
+//!         break;
+//!     }
+//! }
+//!
+//! str0m_rtc_free(rtc);
+//! ```
+//!
+//! Byte slices handed out ([`Str0mSlice`]) point into the handle and are valid until the next
+//! call on the same handle. All functions return one of the `STR0M_*` status codes. On
+//! failure, [`str0m_rtc_last_error()`] describes what went wrong. A panic inside str0m is
+//! caught at the boundary and poisons the handle: all further calls return
+//! [`STR0M_ERR_PANIC`] and the handle can only be freed.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+use std::{ptr, slice};
+
+use crate::net::{Protocol, Receive};
+use crate::util::Instant;
+use crate::{Candidate, Event, IceConnectionState, Input, Output, Rtc};
+
+/// Success.
+pub const STR0M_OK: c_int = 0;
+/// A null pointer or otherwise invalid argument.
+pub const STR0M_ERR_ARGUMENT: c_int = -1;
+/// The [`Rtc`] returned an error.
+pub const STR0M_ERR_RTC: c_int = -2;
+/// str0m panicked, the handle is poisoned.
+pub const STR0M_ERR_PANIC: c_int = -3;
+
+/// Opaque handle owning an [`Rtc`].
+pub struct Str0mRtc {
+    rtc: Rtc,
+    clock: Clock,
+    /// The output the last [`str0m_rtc_poll_output()`] borrowed data from.
+    last_output: Option<Output>,
+    answer: String,
+    error: String,
+    poisoned: bool,
+}
+
+/// A view of bytes owned by the handle.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Str0mSlice {
+    /// Start of the data. Null when `len` is 0.
+    pub ptr: *const u8,
+    /// Number of bytes.
+    pub len: usize,
+}
+
+/// An IP address and port.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Str0mAddr {
+    /// 4 for IPv4, 6 for IPv6.
+    pub family: u8,
+    /// The address in network byte order. IPv4 uses the first 4 bytes.
+    pub ip: [u8; 16],
+    /// Port in host byte order.
+    pub port: u16,
+}
+
+/// Transport protocol, mirrors [`Protocol`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Str0mProtocol {
+    Udp = 0,
+    Tcp = 1,
+    SslTcp = 2,
+    Tls = 3,
+}
+
+/// The kind of [`Str0mOutput`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Str0mOutputKind {
+    /// Call again with input at `timeout_us` at the latest.
+    Timeout = 0,
+    /// Send `data` from `source` to `destination` using `proto`.
+    Transmit = 1,
+    /// An event, see `event`.
+    Event = 2,
+}
+
+/// The events that are mapped over the C ABI.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Str0mEventKind {
+    /// Not an event, or an event that isn't mapped.
+    Other = 0,
+    /// [`Event::Connected`].
+    Connected = 1,
+    /// [`Event::IceConnectionStateChange`], the new state is in `ice_state`.
+    IceConnectionStateChange = 2,
+    /// [`Event::MediaAdded`], the mid is in `mid`.
+    MediaAdded = 3,
+    /// [`Event::MediaData`], the mid is in `mid` and the sample in `data`.
+    MediaData = 4,
+    /// [`Event::ChannelOpen`], the channel is `channel_id` and the label in `data`.
+    ChannelOpen = 5,
+    /// [`Event::ChannelData`], the channel is `channel_id` and the data in `data`.
+    ChannelData = 6,
+    /// [`Event::ChannelClose`], the channel is `channel_id`.
+    ChannelClose = 7,
+}
+
+/// ICE connection state, mirrors [`IceConnectionState`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Str0mIceState {
+    New = 0,
+    Checking = 1,
+    Connected = 2,
+    Completed = 3,
+    Disconnected = 4,
+}
+
+/// Output from [`str0m_rtc_poll_output()`].
+///
+/// Which fields are set depends on `kind` and `event`, the rest are zeroed.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Str0mOutput {
+    /// The kind of output.
+    pub kind: Str0mOutputKind,
+    /// For [`Str0mOutputKind::Timeout`].
+    pub timeout_us: u64,
+    /// For [`Str0mOutputKind::Transmit`].
+    pub proto: Str0mProtocol,
+    /// For [`Str0mOutputKind::Transmit`].
+    pub source: Str0mAddr,
+    /// For [`Str0mOutputKind::Transmit`].
+    pub destination: Str0mAddr,
+    /// For [`Str0mOutputKind::Event`].
+    pub event: Str0mEventKind,
+    /// For [`Str0mEventKind::IceConnectionStateChange`].
+    pub ice_state: Str0mIceState,
+    /// For the channel events.
+    pub channel_id: u64,
+    /// Whether channel data is binary.
+    pub binary: bool,
+    /// The mid for media events.
+    pub mid: Str0mSlice,
+    /// The datagram to send, or the data of the event.
+    pub data: Str0mSlice,
+}
+
+/// Maps the caller's clock to `Instant`. The caller's `now_us` at creation is the
+/// `Instant` at creation.
+#[derive(Clone, Copy)]
+struct Clock {
+    base: Instant,
+    base_us: u64,
+}
+
+enum FfiError {
+    Argument(&'static str),
+    Rtc(String),
+}
+
+impl Clock {
+    fn instant(&self, now_us: u64) -> Instant {
+        if now_us >= self.base_us {
+            self.base + Duration::from_micros(now_us - self.base_us)
+        } else {
+            let before = Duration::from_micros(self.base_us - now_us);
+            self.base.checked_sub(before).unwrap_or(self.base)
+        }
+    }
+
+    fn micros(&self, t: Instant) -> u64 {
+        match t.checked_duration_since(self.base) {
+            Some(d) => self.base_us.saturating_add(d.as_micros() as u64),
+            None => self
+                .base_us
+                .saturating_sub((self.base - t).as_micros() as u64),
+        }
+    }
+}
+
+impl Str0mSlice {
+    const EMPTY: Str0mSlice = Str0mSlice {
+        ptr: ptr::null(),
+        len: 0,
+    };
+
+    fn new(b: &[u8]) -> Self {
+        if b.is_empty() {
+            return Self::EMPTY;
+        }
+        Str0mSlice {
+            ptr: b.as_ptr(),
+            len: b.len(),
+        }
+    }
+}
+
+impl From<SocketAddr> for Str0mAddr {
+    fn from(v: SocketAddr) -> Self {
+        let mut ip = [0; 16];
+        let family = match v.ip() {
+            IpAddr::V4(v4) => {
+                ip[..4].copy_from_slice(&v4.octets());
+                4
+            }
+            IpAddr::V6(v6) => {
+                ip.copy_from_slice(&v6.octets());
+                6
+            }
+        };
+        Str0mAddr {
+            family,
+            ip,
+            port: v.port(),
+        }
+    }
+}
+
+fn socket_addr(v: &Str0mAddr) -> Result<SocketAddr, FfiError> {
+    let ip: IpAddr = match v.family {
+        4 => Ipv4Addr::new(v.ip[0], v.ip[1], v.ip[2], v.ip[3]).into(),
+        6 => Ipv6Addr::from(v.ip).into(),
+        _ => return Err(FfiError::Argument("address family must be 4 or 6")),
+    };
+    Ok(SocketAddr::new(ip, v.port))
+}
+
+impl From<Protocol> for Str0mProtocol {
+    fn from(v: Protocol) -> Self {
+        match v {
+            Protocol::Udp => Str0mProtocol::Udp,
+            Protocol::Tcp => Str0mProtocol::Tcp,
+            Protocol::SslTcp => Str0mProtocol::SslTcp,
+            Protocol::Tls => Str0mProtocol::Tls,
+        }
+    }
+}
+
+fn protocol(v: u32) -> Result<Protocol, FfiError> {
+    Ok(match v {
+        0 => Protocol::Udp,
+        1 => Protocol::Tcp,
+        2 => Protocol::SslTcp,
+        3 => Protocol::Tls,
+        _ => return Err(FfiError::Argument("unknown protocol")),
+    })
+}
+
+impl From<IceConnectionState> for Str0mIceState {
+    fn from(v: IceConnectionState) -> Self {
+        match v {
+            IceConnectionState::New => Str0mIceState::New,
+            IceConnectionState::Checking => Str0mIceState::Checking,
+            IceConnectionState::Connected => Str0mIceState::Connected,
+            IceConnectionState::Completed => Str0mIceState::Completed,
+            IceConnectionState::Disconnected => Str0mIceState::Disconnected,
+        }
+    }
+}
+
+impl Str0mOutput {
+    fn new(kind: Str0mOutputKind) -> Self {
+        Str0mOutput {
+            kind,
+            timeout_us: 0,
+            proto: Str0mProtocol::Udp,
+            source: Str0mAddr::default(),
+            destination: Str0mAddr::default(),
+            event: Str0mEventKind::Other,
+            ice_state: Str0mIceState::New,
+            channel_id: 0,
+            binary: false,
+            mid: Str0mSlice::EMPTY,
+            data: Str0mSlice::EMPTY,
+        }
+    }
+
+    fn from_output(clock: Clock, output: &Output) -> Self {
+        match output {
+            Output::Timeout(t) => Str0mOutput {
+                timeout_us: clock.micros(*t),
+                ..Str0mOutput::new(Str0mOutputKind::Timeout)
+            },
+            Output::Transmit(t) => Str0mOutput {
+                proto: t.proto.into(),
+                source: t.source.into(),
+                destination: t.destination.into(),
+                data: Str0mSlice::new(&t.contents),
+                ..Str0mOutput::new(Str0mOutputKind::Transmit)
+            },
+            Output::Event(e) => Str0mOutput::from_event(e),
+        }
+    }
+
+    fn from_event(e: &Event) -> Self {
+        let out = Str0mOutput::new(Str0mOutputKind::Event);
+        match e {
+            Event::Connected => Str0mOutput {
+                event: Str0mEventKind::Connected,
+                ..out
+            },
+            Event::IceConnectionStateChange(s) => Str0mOutput {
+                event: Str0mEventKind::IceConnectionStateChange,
+                ice_state: (*s).into(),
+                ..out
+            },
+            Event::MediaAdded(m) => Str0mOutput {
+                event: Str0mEventKind::MediaAdded,
+                mid: Str0mSlice::new(m.mid.as_bytes()),
+                ..out
+            },
+            #[cfg(feature = "sample-api")]
+            Event::MediaData(m) => Str0mOutput {
+                event: Str0mEventKind::MediaData,
+                mid: Str0mSlice::new(m.mid.as_bytes()),
+                data: Str0mSlice::new(&m.data),
+                ..out
+            },
+            #[cfg(feature = "sctp")]
+            Event::ChannelOpen(id, label) => Str0mOutput {
+                event: Str0mEventKind::ChannelOpen,
+                channel_id: id.index() as u64,
+                data: Str0mSlice::new(label.as_bytes()),
+                ..out
+            },
+            #[cfg(feature = "sctp")]
+            Event::ChannelData(d) => Str0mOutput {
+                event: Str0mEventKind::ChannelData,
+                channel_id: d.id.index() as u64,
+                binary: d.binary,
+                data: Str0mSlice::new(&d.data),
+                ..out
+            },
+            #[cfg(feature = "sctp")]
+            Event::ChannelClose(id) => Str0mOutput {
+                event: Str0mEventKind::ChannelClose,
+                channel_id: id.index() as u64,
+                ..out
+            },
+            _ => out,
+        }
+    }
+}
+
+/// Runs `f` on the handle, translating errors and panics to status codes.
+fn with_rtc(rtc: *mut Str0mRtc, f: impl FnOnce(&mut Str0mRtc) -> Result<(), FfiError>) -> c_int {
+    // SAFETY: The caller guarantees `rtc` is null or a live handle from `str0m_rtc_new()`
+    // that isn't used concurrently.
+    let Some(rtc) = (unsafe { rtc.as_mut() }) else {
+        return STR0M_ERR_ARGUMENT;
+    };
+
+    if rtc.poisoned {
+        return STR0M_ERR_PANIC;
+    }
+
+    // Borrowed data from the previous call is no longer valid.
+    rtc.last_output = None;
+    rtc.error.clear();
+
+    match panic::catch_unwind(AssertUnwindSafe(|| f(rtc))) {
+        Ok(Ok(())) => STR0M_OK,
+        Ok(Err(FfiError::Argument(e))) => {
+            rtc.error = e.to_string();
+            STR0M_ERR_ARGUMENT
+        }
+        Ok(Err(FfiError::Rtc(e))) => {
+            rtc.error = e;
+            STR0M_ERR_RTC
+        }
+        Err(_) => {
+            rtc.poisoned = true;
+            rtc.error = "str0m panicked".to_string();
+            STR0M_ERR_PANIC
+        }
+    }
+}
+
+fn rtc_err(e: impl std::fmt::Display) -> FfiError {
+    FfiError::Rtc(e.to_string())
+}
+
+/// Creates a new [`Rtc`] with the default configuration.
+///
+/// `now_us` is the current time of the caller's monotonic clock in microseconds. All
+/// times passed in and out of the handle are on that clock.
+///
+/// Returns null if creation panicked.
+#[no_mangle]
+pub extern "C" fn str0m_rtc_new(now_us: u64) -> *mut Str0mRtc {
+    let Ok(rtc) = panic::catch_unwind(Rtc::new) else {
+        return ptr::null_mut();
+    };
+
+    let handle = Str0mRtc {
+        rtc,
+        clock: Clock {
+            base: Instant::now(),
+            base_us: now_us,
+        },
+        last_output: None,
+        answer: String::new(),
+        error: String::new(),
+        poisoned: false,
+    };
+
+    Box::into_raw(Box::new(handle))
+}
+
+/// Frees a handle created by [`str0m_rtc_new()`].
+///
+/// A panic while dropping the handle is caught. What remains of the handle is then leaked.
+///
+/// # Safety
+///
+/// `rtc` must be null or a handle from [`str0m_rtc_new()`] that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn str0m_rtc_free(rtc: *mut Str0mRtc) {
+    if rtc.is_null() {
+        return;
+    }
+    // SAFETY: Guaranteed by the caller.
+    let handle = unsafe { Box::from_raw(rtc) };
+
+    // A handle poisoned by an earlier panic might panic again in drop.
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(handle)));
+}
+
+/// Adds a local host candidate, see [`Candidate::host()`].
+///
+/// `proto` is one of the `STR0M_PROTO_*` values.
+///
+/// # Safety
+///
+/// `rtc` must be a live handle and `addr` must point to a `Str0mAddr`.
+#[no_mangle]
+pub unsafe extern "C" fn str0m_rtc_add_local_candidate(
+    rtc: *mut Str0mRtc,
+    addr: *const Str0mAddr,
+    proto: u32,
+) -> c_int {
+    with_rtc(rtc, |h| {
+        // SAFETY: Guaranteed by the caller.
+        let addr = unsafe { addr.as_ref() }.ok_or(FfiError::Argument("addr is null"))?;
+        let addr = socket_addr(addr)?;
+        let candidate = Candidate::host(addr, protocol(proto)?).map_err(rtc_err)?;
+        h.rtc.add_local_candidate(candidate);
+        Ok(())
+    })
+}
+
+/// Accepts an SDP offer, see [`SdpApi::accept_offer()`][crate::change::SdpApi::accept_offer].
+///
+/// On success `answer` is set to the SDP answer.
+///
+/// # Safety
+///
+/// `rtc` must be a live handle, `offer` must point to `len` bytes of UTF-8 and
+/// `answer` must point to a `Str0mSlice`.
+#[no_mangle]
+pub unsafe extern "C" fn str0m_rtc_accept_offer(
+    rtc: *mut Str0mRtc,
+    offer: *const u8,
+    len: usize,
+    answer: *mut Str0mSlice,
+) -> c_int {
+    with_rtc(rtc, |h| {
+        if offer.is_null() || answer.is_null() {
+            return Err(FfiError::Argument("offer or answer is null"));
+        }
+        // SAFETY: Guaranteed by the caller.
+        let offer = unsafe { slice::from_raw_parts(offer, len) };
+        let offer =
+            std::str::from_utf8(offer).map_err(|_| FfiError::Argument("offer is not UTF-8"))?;
+        let offer = crate::change::SdpOffer::from_sdp_string(offer).map_err(rtc_err)?;
+
+        let sdp_answer = h.rtc.sdp_api().accept_offer(offer).map_err(rtc_err)?;
+        h.answer = sdp_answer.to_sdp_string();
+
+        // SAFETY: Checked for null above, guaranteed valid by the caller.
+        unsafe { *answer = Str0mSlice::new(h.answer.as_bytes()) };
+        Ok(())
+    })
+}
+
+/// Handles a received datagram, see [`Input::Receive`].
+///
+/// # Safety
+///
+/// `rtc` must be a live handle, `source` and `destination` must point to a `Str0mAddr`
+/// and `data` must point to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn str0m_rtc_handle_receive(
+    rtc: *mut Str0mRtc,
+    now_us: u64,
+    proto: u32,
+    source: *const Str0mAddr,
+    destination: *const Str0mAddr,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    with_rtc(rtc, |h| {
+        // SAFETY: Guaranteed by the caller.
+        let (source, destination) = unsafe { (source.as_ref(), destination.as_ref()) };
+        let (Some(source), Some(destination)) = (source, destination) else {
+            return Err(FfiError::Argument("source or destination is null"));
+        };
+        if data.is_null() {
+            return Err(FfiError::Argument("data is null"));
+        }
+        // SAFETY: Guaranteed by the caller.
+        let data = unsafe { slice::from_raw_parts(data, len) };
+
+        let receive = Receive::new(
+            protocol(proto)?,
+            socket_addr(source)?,
+            socket_addr(destination)?,
+            data,
+        )
+        .map_err(rtc_err)?;
+
+        let now = h.clock.instant(now_us);
+        h.rtc
+            .handle_input(Input::Receive(now, receive))
+            .map_err(rtc_err)
+    })
+}
+
+/// Advances time, see [`Input::Timeout`].
+///
+/// # Safety
+///
+/// `rtc` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn str0m_rtc_handle_timeout(rtc: *mut Str0mRtc, now_us: u64) -> c_int {
+    with_rtc(rtc, |h| {
+        let now = h.clock.instant(now_us);
+        h.rtc.handle_input(Input::Timeout(now)).map_err(rtc_err)
+    })
+}
+
+/// Polls the next output, see [`Rtc::poll_output()`].
+///
+/// Call repeatedly until the output is a [`Str0mOutputKind::Timeout`].
+///
+/// # Safety
+///
+/// `rtc` must be a live handle and `out` must point to a `Str0mOutput`.
+#[no_mangle]
+pub unsafe extern "C" fn str0m_rtc_poll_output(rtc: *mut Str0mRtc, out: *mut Str0mOutput) -> c_int {
+    with_rtc(rtc, |h| {
+        if out.is_null() {
+            return Err(FfiError::Argument("out is null"));
+        }
+
+        let output = h.rtc.poll_output().map_err(rtc_err)?;
+        let output = h.last_output.insert(output);
+        let mapped = Str0mOutput::from_output(h.clock, output);
+
+        // SAFETY: Checked for null above, guaranteed valid by the caller.
+        unsafe { *out = mapped };
+        Ok(())
+    })
+}
+
+/// Describes the error of the last failed call. Empty if the last call succeeded.
+///
+/// # Safety
+///
+/// `rtc` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn str0m_rtc_last_error(rtc: *const Str0mRtc) -> Str0mSlice {
+    // SAFETY: Guaranteed by the caller.
+    match unsafe { rtc.as_ref() } {
+        Some(h) => Str0mSlice::new(h.error.as_bytes()),
+        None => Str0mSlice::EMPTY,
+    }
+}
@@ -3,6 +3,7 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::RangeInclusive;
+use std::time::Duration;
 
 use crate::packet::{H264ProfileLevel, MediaKind};
 use crate::rtp_::Pt;
@@ -11,7 +12,7 @@ use crate::sdp::FormatParam;
 
 // These really don't belong anywhere, but I guess they're kind of related
 // to codecs etc.
-pub use crate::packet::{CodecExtra, H264CodecExtra, Vp8CodecExtra, Vp9CodecExtra};
+pub use crate::packet::{CodecExtra, H264CodecExtra, H265CodecExtra, Vp8CodecExtra, Vp9CodecExtra};
 
 /// Session config for all codecs.
 #[derive(Debug, Clone, Default)]
@@ -41,12 +42,24 @@ pub struct PayloadParams {
     /// This is used to, via PT, separate RTX resend streams from the main stream.
     pub(crate) resend: Option<Pt>,
 
+    /// How long sent packets are kept for resends, in milliseconds. Signalled as `rtx-time`
+    /// on the RTX PT. Kept as millis to not grow `MediaData`.
+    #[serde(default)]
+    pub(crate) rtx_time: Option<u32>,
+
+    /// The `rtx-time` signalled by the remote, in milliseconds.
+    #[serde(default)]
+    pub(crate) remote_rtx_time: Option<u32>,
+
     /// The codec with settings for this group of parameters.
     pub(crate) spec: CodecSpec,
 
     /// Whether the payload use the TWCC feedback mechanic.
     pub(crate) fb_transport_cc: bool,
 
+    /// Whether the payload uses the RFC 8888 congestion control feedback mechanic.
+    pub(crate) fb_ccfb: bool,
+
     /// Whether the payload uses NACK to request resends.
     pub(crate) fb_nack: bool,
 
@@ -72,8 +85,10 @@ impl PartialEq for PayloadParams {
     fn eq(&self, other: &Self) -> bool {
         self.pt == other.pt
             && self.resend == other.resend
+            && self.rtx_time == other.rtx_time
             && self.spec == other.spec
             && self.fb_transport_cc == other.fb_transport_cc
+            && self.fb_ccfb == other.fb_ccfb
             && self.fb_nack == other.fb_nack
             && self.fb_pli == other.fb_pli
             && self.fb_fir == other.fb_fir
@@ -105,9 +120,15 @@ pub struct CodecSpec {
 #[allow(missing_docs)]
 pub enum Codec {
     Opus,
+    /// G.711 µ-law. Static payload type 0.
+    Pcmu,
+    /// G.711 A-law. Static payload type 8.
+    Pcma,
+    /// G.722. Static payload type 9.
+    ///
+    /// The RTP clock rate is 8kHz, even though the sampling rate is 16kHz (RFC 3551 §4.5.2).
+    G722,
     H264,
-    // TODO show this when we support h265.
-    #[doc(hidden)]
     H265,
     Vp8,
     Vp9,
@@ -162,8 +183,19 @@ pub struct FormatParams {
     /// * 64 00 1f - 6400=high (H)                  1f=level 3.1
     pub profile_level_id: Option<u32>,
 
-    /// VP9 profile id.
+    /// VP9 or H265 profile id.
+    ///
+    /// For H265 1 is Main and 2 is Main 10.
     pub profile_id: Option<u32>,
+
+    /// H265 tier flag.
+    ///
+    /// * 0 - Main tier.
+    /// * 1 - High tier.
+    pub tier_flag: Option<u8>,
+
+    /// H265 level id. This is 30 times the level number, i.e. 93 is level 3.1.
+    pub level_id: Option<u8>,
 }
 
 impl PayloadParams {
@@ -178,12 +210,17 @@ impl PayloadParams {
         PayloadParams {
             pt,
             resend,
+            rtx_time: None,
+            remote_rtx_time: None,
 
             spec,
 
             // Both audio and video use TWCC
             fb_transport_cc: true,
 
+            // RFC 8888 feedback is opt-in.
+            fb_ccfb: false,
+
             // Only true for video.
             fb_fir: is_video,
             fb_nack: is_video,
@@ -205,6 +242,37 @@ impl PayloadParams {
         self.resend
     }
 
+    /// Sets how long sent packets are kept to answer NACKs with resends.
+    ///
+    /// This is signalled as `rtx-time` (in milliseconds) on the RTX PT in the SDP, and
+    /// bounds the age of the send streams' RTX cache. Only used when [`PayloadParams::resend()`]
+    /// is set. Defaults to `None`, which means 3 seconds and no `rtx-time` in the SDP.
+    pub fn set_rtx_time(&mut self, rtx_time: Option<Duration>) {
+        self.rtx_time = rtx_time.map(|v| v.as_millis().min(u32::MAX as u128) as u32);
+    }
+
+    /// How long sent packets are kept to answer NACKs with resends.
+    pub fn rtx_time(&self) -> Option<Duration> {
+        self.rtx_time.map(|v| Duration::from_millis(v as u64))
+    }
+
+    /// The `rtx-time` signalled by the remote peer, if any.
+    ///
+    /// When set, this also bounds how long sent packets are kept for resends, since
+    /// the remote doesn't expect resends older than this.
+    pub fn remote_rtx_time(&self) -> Option<Duration> {
+        self.remote_rtx_time
+            .map(|v| Duration::from_millis(v as u64))
+    }
+
+    /// The `rtx-time` in effect for this PT, the smaller of our own and the remote's.
+    pub(crate) fn effective_rtx_time(&self) -> Option<Duration> {
+        match (self.rtx_time(), self.remote_rtx_time()) {
+            (Some(local), Some(remote)) => Some(local.min(remote)),
+            (local, remote) => local.or(remote),
+        }
+    }
+
     /// The codec with settings for this group of parameters.
     pub fn spec(&self) -> CodecSpec {
         self.spec
@@ -220,6 +288,20 @@ impl PayloadParams {
         self.fb_transport_cc
     }
 
+    /// Sets whether the payload uses the RFC 8888 congestion control feedback mechanic.
+    ///
+    /// This is signalled as `a=rtcp-fb:<pt> ack ccfb` in the SDP. When negotiated, str0m sends
+    /// [`Ccfb`][crate::rtp::rtcp::Ccfb] reports for received RTP, which is the standardized
+    /// alternative to TWCC used by non-libWebRTC stacks.
+    pub fn set_fb_ccfb(&mut self, fb_ccfb: bool) {
+        self.fb_ccfb = fb_ccfb
+    }
+
+    /// Whether the payload uses the RFC 8888 congestion control feedback mechanic.
+    pub fn fb_ccfb(&self) -> bool {
+        self.fb_ccfb
+    }
+
     /// Sets whether the payload uses NACK to request resends.
     pub fn set_fb_nack(&mut self, fb_nack: bool) {
         self.fb_nack = fb_nack
@@ -290,10 +372,22 @@ impl PayloadParams {
             return Some(Self::match_opus_score(c0, c1));
         }
 
+        if c0.codec.is_g7xx() {
+            // "PCMU/8000" and "PCMU/8000/1" are the same.
+            if c0.channels.unwrap_or(1) != c1.channels.unwrap_or(1) {
+                return None;
+            }
+            return Some(100);
+        }
+
         if c0.codec == Codec::H264 {
             return Self::match_h264_score(c0, c1);
         }
 
+        if c0.codec == Codec::H265 {
+            return Self::match_h265_score(c0, c1);
+        }
+
         if c0.codec == Codec::Vp9 {
             return Self::match_vp9_score(c0, c1);
         }
@@ -345,6 +439,35 @@ impl PayloadParams {
         Some(100)
     }
 
+    fn match_h265_score(c0: CodecSpec, c1: CodecSpec) -> Option<usize> {
+        // Default profile is 1 (Main), tier 0 (Main) and level 93 (3.1).
+        // https://www.rfc-editor.org/rfc/rfc7798#section-7.1
+        let c0_profile_id = c0.format.profile_id.unwrap_or(1);
+        let c1_profile_id = c1.format.profile_id.unwrap_or(1);
+
+        if c0_profile_id != c1_profile_id {
+            return None;
+        }
+
+        let c0_tier_flag = c0.format.tier_flag.unwrap_or(0);
+        let c1_tier_flag = c1.format.tier_flag.unwrap_or(0);
+
+        if c0_tier_flag != c1_tier_flag {
+            return None;
+        }
+
+        // The level is the highest level the receiver can decode, a different
+        // level is compatible, but an exact match is preferred.
+        let c0_level_id = c0.format.level_id.unwrap_or(93);
+        let c1_level_id = c1.format.level_id.unwrap_or(93);
+
+        if c0_level_id != c1_level_id {
+            return Some(99);
+        }
+
+        Some(100)
+    }
+
     fn match_h264_score(c0: CodecSpec, c1: CodecSpec) -> Option<usize> {
         // Default packetization mode is 0. https://www.rfc-editor.org/rfc/rfc6184#section-6.2
         let c0_packetization_mode = c0.format.packetization_mode.unwrap_or(0);
@@ -389,6 +512,9 @@ impl PayloadParams {
         let remote_pt = first.pt;
         let remote_rtx = first.resend;
 
+        // The remote rtx-time is not part of the negotiated PT and can change.
+        self.remote_rtx_time = first.rtx_time;
+
         if self.locked {
             // This can happen if the incoming PTs are suggestions (send-direction) rather than demanded
             // (receive-direction). We only want to warn if we get receive direction changes.
@@ -482,7 +608,10 @@ impl CodecConfig {
                 format,
             },
             resend,
+            rtx_time: None,
+            remote_rtx_time: None,
             fb_transport_cc,
+            fb_ccfb: false,
             fb_fir,
             fb_nack,
             fb_pli,
@@ -490,7 +619,35 @@ impl CodecConfig {
             locked: false,
         };
 
-        self.params.push(p);
+        self.add_payload_params(p);
+    }
+
+    /// Add a fully specified set of payload parameters.
+    ///
+    /// Any previously configured parameters that use the same PT, either as main
+    /// or resend (RTX) PT, are replaced. A PT can only map to one codec.
+    pub fn add_payload_params(&mut self, params: PayloadParams) {
+        let claims = |p: &PayloadParams, pt: Pt| p.pt == pt || p.resend == Some(pt);
+
+        self.params.retain(|p| {
+            let collides =
+                claims(p, params.pt) || params.resend.map(|r| claims(p, r)).unwrap_or(false);
+            if collides {
+                debug!("Replace payload params for PT {}: {:?}", p.pt, p.spec.codec);
+            }
+            !collides
+        });
+
+        self.params.push(params);
+    }
+
+    /// Remove the payload parameters for the given PT.
+    ///
+    /// Returns true if there was a configuration to remove.
+    pub fn remove_pt(&mut self, pt: Pt) -> bool {
+        let before = self.params.len();
+        self.params.retain(|p| p.pt != pt);
+        self.params.len() != before
     }
 
     /// Convenience for adding a h264 payload type.
@@ -536,6 +693,41 @@ impl CodecConfig {
         )
     }
 
+    /// Add the G.711 payload types, PCMU (0) and PCMA (8).
+    pub fn enable_g711(&mut self, enabled: bool) {
+        self.params
+            .retain(|c| c.spec.codec != Codec::Pcmu && c.spec.codec != Codec::Pcma);
+        if !enabled {
+            return;
+        }
+        for codec in [Codec::Pcmu, Codec::Pcma] {
+            self.add_static(codec);
+        }
+    }
+
+    /// Add the G.722 payload type (9).
+    pub fn enable_g722(&mut self, enabled: bool) {
+        self.params.retain(|c| c.spec.codec != Codec::G722);
+        if !enabled {
+            return;
+        }
+        self.add_static(Codec::G722);
+    }
+
+    fn add_static(&mut self, codec: Codec) {
+        // unwrap is OK, only called for codecs with a static payload type.
+        let pt = codec.static_pt().unwrap();
+        let spec = CodecSpec::from_static_pt(pt).unwrap();
+        self.add_config(
+            pt,
+            None,
+            spec.codec,
+            spec.clock_rate,
+            spec.channels,
+            spec.format,
+        )
+    }
+
     /// Add a default VP8 payload type.
     pub fn enable_vp8(&mut self, enabled: bool) {
         self.params.retain(|c| c.spec.codec != Codec::Vp8);
@@ -573,6 +765,29 @@ impl CodecConfig {
         }
     }
 
+    /// Add a default H265 payload type.
+    ///
+    /// Main profile, Main tier, level 3.1.
+    pub fn enable_h265(&mut self, enabled: bool) {
+        self.params.retain(|c| c.spec.codec != Codec::H265);
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            49.into(),
+            Some(50.into()),
+            Codec::H265,
+            Frequency::NINETY_KHZ,
+            None,
+            FormatParams {
+                profile_id: Some(1),
+                tier_flag: Some(0),
+                level_id: Some(93),
+                ..Default::default()
+            },
+        )
+    }
+
     // TODO: AV1 depacketizer/packetizer.
     //
     // /// Add a default AV1 payload type.
@@ -743,6 +958,7 @@ impl CodecConfig {
         }
     }
 
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
     pub(crate) fn has_pt(&self, pt: Pt) -> bool {
         self.params.iter().any(|p| p.pt() == pt)
     }
@@ -812,7 +1028,10 @@ impl FormatParams {
             PacketizationMode(v) => self.packetization_mode = Some(*v),
             ProfileLevelId(v) => self.profile_level_id = Some(*v),
             ProfileId(v) => self.profile_id = Some(*v),
+            TierFlag(v) => self.tier_flag = Some(*v),
+            LevelId(v) => self.level_id = Some(*v),
             Apt(_) => {}
+            RtxTime(_) => {}
             Unknown => {}
         }
     }
@@ -842,6 +1061,12 @@ impl FormatParams {
         if let Some(v) = self.profile_id {
             r.push(ProfileId(v));
         }
+        if let Some(v) = self.tier_flag {
+            r.push(TierFlag(v));
+        }
+        if let Some(v) = self.level_id {
+            r.push(LevelId(v));
+        }
 
         r
     }
@@ -863,7 +1088,7 @@ impl Codec {
     /// Tells if codec is audio.
     pub fn is_audio(&self) -> bool {
         use Codec::*;
-        matches!(self, Opus)
+        matches!(self, Opus | Pcmu | Pcma | G722)
     }
 
     /// Tells if codec is video.
@@ -880,6 +1105,78 @@ impl Codec {
             MediaKind::Video
         }
     }
+
+    /// The static payload type of the codec (RFC 3551), if it has one.
+    pub fn static_pt(&self) -> Option<Pt> {
+        match self {
+            Codec::Pcmu => Some(0.into()),
+            Codec::Pcma => Some(8.into()),
+            Codec::G722 => Some(9.into()),
+            _ => None,
+        }
+    }
+
+    /// Whether an RTP payload of this codec is the first packet of a keyframe.
+    ///
+    /// This only inspects the payload descriptor and the first bytes of the codec data,
+    /// which makes it cheap enough for a forwarder to check every packet in RTP mode, for
+    /// instance to find a safe point to switch simulcast layer. Supported for VP8, VP9,
+    /// H264, H265 and AV1, always `false` for other codecs.
+    ///
+    /// For H264 and H265 a packet starting with the parameter sets (SPS or VPS) counts as
+    /// the start of a keyframe.
+    pub fn is_keyframe_start(&self, payload: &[u8]) -> bool {
+        crate::packet::is_keyframe_start(*self, payload)
+    }
+
+    /// Whether the codec has a constant 8 bits per sample and channel at an 8kHz RTP clock.
+    ///
+    /// For these codecs (G.711 and G.722) the RTP time advances one tick per byte and channel.
+    pub(crate) fn is_g7xx(&self) -> bool {
+        matches!(self, Codec::Pcmu | Codec::Pcma | Codec::G722)
+    }
+}
+
+impl CodecSpec {
+    /// The spec of a static payload type (RFC 3551) that may be used without `a=rtpmap`.
+    pub(crate) fn from_static_pt(pt: Pt) -> Option<CodecSpec> {
+        let codec = [Codec::Pcmu, Codec::Pcma, Codec::G722]
+            .into_iter()
+            .find(|c| c.static_pt() == Some(pt))?;
+
+        Some(CodecSpec {
+            codec,
+            clock_rate: Frequency::EIGHT_KHZ,
+            channels: None,
+            format: FormatParams::default(),
+        })
+    }
+
+    /// Number of bytes of media for a packet time (ptime).
+    ///
+    /// Only for the constant bitrate codecs G.711 (PCMU/PCMA) and G.722, which use
+    /// 8 bytes per millisecond and channel. `None` for other codecs.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::format::{Codec, CodecSpec, FormatParams};
+    /// # use str0m::media::Frequency;
+    /// let spec = CodecSpec {
+    ///     codec: Codec::Pcmu,
+    ///     clock_rate: Frequency::EIGHT_KHZ,
+    ///     channels: None,
+    ///     format: FormatParams::default(),
+    /// };
+    ///
+    /// assert_eq!(spec.ptime_size(Duration::from_millis(20)), Some(160));
+    /// ```
+    pub fn ptime_size(&self, ptime: Duration) -> Option<usize> {
+        if !self.codec.is_g7xx() {
+            return None;
+        }
+        let channels = self.channels.unwrap_or(1) as usize;
+        Some(ptime.as_millis() as usize * 8 * channels)
+    }
 }
 
 impl<'a> From<&'a str> for Codec {
@@ -887,6 +1184,9 @@ impl<'a> From<&'a str> for Codec {
         let lc = v.to_ascii_lowercase();
         match &lc[..] {
             "opus" => Codec::Opus,
+            "pcmu" => Codec::Pcmu,
+            "pcma" => Codec::Pcma,
+            "g722" => Codec::G722,
             "h264" => Codec::H264,
             "h265" => Codec::H265,
             "vp8" => Codec::Vp8,
@@ -902,6 +1202,9 @@ impl fmt::Display for Codec {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Codec::Opus => write!(f, "opus"),
+            Codec::Pcmu => write!(f, "PCMU"),
+            Codec::Pcma => write!(f, "PCMA"),
+            Codec::G722 => write!(f, "G722"),
             Codec::H264 => write!(f, "H264"),
             Codec::H265 => write!(f, "H265"),
             Codec::Vp8 => write!(f, "VP8"),
@@ -951,10 +1254,49 @@ mod test {
                 packetization_mode,
                 profile_level_id,
                 profile_id: None, // VP8
+                tier_flag: None,
+                level_id: None,
             },
         }
     }
 
+    #[test]
+    fn test_h265_profile_matching() {
+        fn spec(profile_id: Option<u32>, tier_flag: Option<u8>, level_id: Option<u8>) -> CodecSpec {
+            CodecSpec {
+                codec: Codec::H265,
+                clock_rate: Frequency::NINETY_KHZ,
+                channels: None,
+                format: FormatParams {
+                    profile_id,
+                    tier_flag,
+                    level_id,
+                    ..Default::default()
+                },
+            }
+        }
+
+        let main = spec(Some(1), Some(0), Some(93));
+
+        // Defaults are Main profile, Main tier, level 3.1.
+        assert_eq!(
+            PayloadParams::match_h265_score(main, spec(None, None, None)),
+            Some(100)
+        );
+        assert_eq!(
+            PayloadParams::match_h265_score(main, spec(Some(1), Some(0), Some(120))),
+            Some(99)
+        );
+        assert_eq!(
+            PayloadParams::match_h265_score(main, spec(Some(2), Some(0), Some(93))),
+            None
+        );
+        assert_eq!(
+            PayloadParams::match_h265_score(main, spec(Some(1), Some(1), Some(93))),
+            None
+        );
+    }
+
     #[test]
     fn test_h264_profile_matching() {
         struct Case {
@@ -995,4 +1337,42 @@ mod test {
             assert_eq!(matched, must_match, "{msg}\nc0: {c0:#?}\nc1: {c1:#?}");
         }
     }
+
+    #[test]
+    fn add_config_replaces_same_pt() {
+        let mut c = CodecConfig::new_with_defaults();
+        let before = c.params().len();
+
+        // 96 is used by VP8 in the defaults (and 97 by its RTX).
+        c.add_h264(96.into(), Some(97.into()), true, 0x42e01f);
+
+        assert_eq!(c.params().len(), before);
+        let p = c.find(|p| p.pt() == 96.into()).unwrap();
+        assert_eq!(p.spec().codec, Codec::H264);
+        assert!(c.find(|p| p.spec().codec == Codec::Vp8).is_none());
+
+        assert!(c.remove_pt(96.into()));
+        assert!(!c.remove_pt(96.into()));
+    }
+
+    #[test]
+    fn remote_rtx_time_bounds_cache_duration() {
+        let mut c = CodecConfig::new_with_defaults();
+
+        let mut remote = *c.find(|p| p.spec().codec == Codec::Vp8).unwrap();
+        remote.set_rtx_time(Some(Duration::from_millis(500)));
+
+        c.update_params(&[remote], Direction::SendRecv);
+
+        let p = c.find(|p| p.spec().codec == Codec::Vp8).unwrap();
+        assert_eq!(p.rtx_time(), None);
+        assert_eq!(p.remote_rtx_time(), Some(Duration::from_millis(500)));
+        assert_eq!(p.effective_rtx_time(), Some(Duration::from_millis(500)));
+
+        let mut p = *p;
+        p.set_rtx_time(Some(Duration::from_secs(1)));
+        assert_eq!(p.effective_rtx_time(), Some(Duration::from_millis(500)));
+        p.set_rtx_time(Some(Duration::from_millis(200)));
+        assert_eq!(p.effective_rtx_time(), Some(Duration::from_millis(200)));
+    }
 }
@@ -1,6 +1,6 @@
 use std::collections::{HashSet, VecDeque};
 use std::net::SocketAddr;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
@@ -8,6 +8,7 @@ use crate::io::{Id, StunClass, StunMethod, DATAGRAM_MTU_WARN};
 use crate::io::{Protocol, StunPacket};
 use crate::io::{StunMessage, TransId, STUN_TIMEOUT};
 use crate::io::{Transmit, DATAGRAM_MTU};
+use crate::util::Instant;
 use crate::util::NonCryptographicRng;
 
 use super::candidate::{Candidate, CandidateKind};
@@ -112,6 +113,7 @@ const REMOTE_PEER_REFLEXIVE_TEMP_FOUNDATION: &str = "tmp_prflx";
 ///
 /// [1]: https://www.rfc-editor.org/rfc/rfc8445
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum IceConnectionState {
     /// The ICE agent is gathering addresses.
     New,
@@ -330,10 +332,16 @@ impl IceAgent {
 
     /// Determines whether any remote candidates match the specified address and
     /// have been verified with a STUN request/response.
+    ///
+    /// With ice-lite, we never send requests, and an authenticated request from the
+    /// remote peer verifies the candidate.
     pub fn has_viable_remote_candidate(&self, addr: SocketAddr) -> bool {
         self.candidate_pairs
             .iter()
-            .filter(|cand| cand.state() == CheckState::Succeeded)
+            .filter(|pair| {
+                pair.state() == CheckState::Succeeded
+                    || self.ice_lite && pair.remote_binding_requests > 0
+            })
             .any(|pair| self.remote_candidates[pair.remote_idx()].addr() == addr)
     }
 
@@ -404,7 +412,6 @@ impl IceAgent {
     /// Stats for the agent.
     ///
     /// Resets on ICE restart.
-    #[allow(unused)]
     pub fn stats(&self) -> IceAgentStats {
         self.stats
     }
@@ -857,33 +864,10 @@ impl IceAgent {
         let class = message.class();
         match (method, class) {
             (StunMethod::Binding, StunClass::Request | StunClass::Indication) => {
-                // The username for the credential is formed by concatenating the
-                // username fragment provided by the peer with the username fragment of
-                // the ICE agent sending the request, separated by a colon (":").
-                // The existence of this username is checked in the STUN parser.
-                let (local, remote) = message.split_username().unwrap();
-
-                let local_creds = self.local_credentials();
-                if local != local_creds.ufrag {
-                    trace!(
-                        "Message rejected, local user mismatch: {} != {}",
-                        local,
-                        local_creds.ufrag
-                    );
+                if !self.is_addressed_to_us(message) {
                     return false;
                 }
 
-                if let Some(remote_creds) = &self.remote_credentials {
-                    if remote != remote_creds.ufrag {
-                        trace!(
-                            "Message rejected, remote user mismatch: {} != {}",
-                            remote,
-                            remote_creds.ufrag
-                        );
-                        return false;
-                    }
-                }
-
                 do_integrity_check(true)
             }
             (StunMethod::Binding, StunClass::Success | StunClass::Failure) => {
@@ -912,12 +896,85 @@ impl IceAgent {
         }
     }
 
+    /// Tells whether the message is a binding request for this agent that fails
+    /// authentication.
+    ///
+    /// Such requests are answered with an error response by [`IceAgent::handle_packet`],
+    /// instead of being accepted.
+    pub fn rejects_message(&self, message: &StunMessage<'_>) -> bool {
+        self.auth_error(message).is_some()
+    }
+
+    /// The error to answer a binding request for this agent that fails authentication.
+    ///
+    /// RFC 5389 §10.1.2: a request without MESSAGE-INTEGRITY is answered with 400 (Bad Request)
+    /// and a request failing the integrity check with 401 (Unauthorized).
+    fn auth_error(&self, message: &StunMessage<'_>) -> Option<(u16, &'static str)> {
+        if !message.is_binding_request() || !self.is_addressed_to_us(message) {
+            return None;
+        }
+
+        if !message.has_integrity() {
+            return Some((400, "Bad Request"));
+        }
+
+        let (_, password) = self.stun_credentials(true);
+        if !message.check_integrity(&password) {
+            return Some((401, "Unauthorized"));
+        }
+
+        None
+    }
+
+    /// Whether the USERNAME of a binding request matches our local ufrag and, if
+    /// set, the remote ufrag.
+    fn is_addressed_to_us(&self, message: &StunMessage<'_>) -> bool {
+        // The username for the credential is formed by concatenating the
+        // username fragment provided by the peer with the username fragment of
+        // the ICE agent sending the request, separated by a colon (":").
+        // The existence of this username is checked in the STUN parser.
+        let Some((local, remote)) = message.split_username() else {
+            return false;
+        };
+
+        let local_creds = self.local_credentials();
+        if local != local_creds.ufrag {
+            trace!(
+                "Message rejected, local user mismatch: {} != {}",
+                local,
+                local_creds.ufrag
+            );
+            return false;
+        }
+
+        if let Some(remote_creds) = &self.remote_credentials {
+            if remote != remote_creds.ufrag {
+                trace!(
+                    "Message rejected, remote user mismatch: {} != {}",
+                    remote,
+                    remote_creds.ufrag
+                );
+                return false;
+            }
+        }
+
+        true
+    }
+
     /// Handles an incoming STUN message.
     ///
-    /// Will not be used if [`IceAgent::accepts_message`] returns false.
+    /// Binding requests for this agent failing authentication are answered with an
+    /// error response. Other messages are not used if [`IceAgent::accepts_message`]
+    /// returns false.
     pub fn handle_packet(&mut self, now: Instant, packet: StunPacket) -> bool {
         trace!("Handle receive: {:?}", &packet.message);
 
+        if let Some((code, reason)) = self.auth_error(&packet.message) {
+            debug!("STUN request rejected, {} {}", code, reason);
+            self.stun_server_send_error(&packet, code, reason);
+            return false;
+        }
+
         // Regardless of whether we have remote_creds at this point, we can
         // at least check the message integrity.
         if !self.accepts_message(&packet.message) {
@@ -929,6 +986,8 @@ impl IceAgent {
             self.stun_server_handle_message(now, &packet);
         } else if packet.message.is_successful_binding_response() {
             self.stun_client_handle_response(now, packet.message);
+        } else if let Some((code, reason)) = packet.message.error_code() {
+            debug!("STUN error response: {} {}", code, reason);
         }
 
         self.emit_event(IceAgentEvent::DiscoveredRecv {
@@ -1179,6 +1238,35 @@ impl IceAgent {
         }
     }
 
+    fn stun_server_send_error(&mut self, packet: &StunPacket, code: u16, reason: &str) {
+        let reply = StunMessage::error_reply(packet.message.trans_id(), code, reason);
+
+        trace!(
+            "Send STUN error reply: {} -> {} {:?}",
+            packet.destination,
+            packet.source,
+            reply
+        );
+
+        let mut buf = vec![0_u8; DATAGRAM_MTU];
+
+        // The request could not be authenticated, so neither can the response.
+        let n = reply
+            .to_bytes_key(None, &mut buf)
+            .expect("IO error writing STUN reply");
+        buf.truncate(n);
+
+        let trans = Transmit {
+            proto: packet.proto,
+            source: packet.destination,
+            destination: packet.source,
+            contents: buf.into(),
+            segment_size: None,
+        };
+
+        self.transmit.push_back(trans);
+    }
+
     fn stun_server_handle_request(&mut self, req: StunRequest) {
         let remote_creds = self.remote_credentials.as_ref().expect("Remote ICE creds");
         if req.remote_ufrag != remote_creds.ufrag {
@@ -1369,6 +1457,7 @@ impl IceAgent {
             source: local_addr,
             destination: remote_addr,
             contents: buf.into(),
+            segment_size: None,
         };
 
         self.transmit.push_back(trans);
@@ -1416,6 +1505,7 @@ impl IceAgent {
             source: local.base(),
             destination: remote.addr(),
             contents: buf.into(),
+            segment_size: None,
         };
 
         self.transmit.push_back(trans);
@@ -1429,8 +1519,6 @@ impl IceAgent {
             .iter_mut()
             .find(|p| p.has_binding_attempt(trans_id));
 
-        self.stats.bind_success_recv += 1;
-
         let pair = match maybe_pair {
             Some(v) => v,
             // Not finding the candidate pair is fine. That might mean the
@@ -1442,6 +1530,8 @@ impl IceAgent {
             }
         };
 
+        self.stats.bind_success_recv += 1;
+
         // The ICE agent MUST check the mapped address from the STUN response.
         // If the transport address does not match any of the local candidates
         // that the agent knows about, the mapped address represents a new
@@ -1575,6 +1665,13 @@ impl IceAgent {
         }
     }
 
+    /// The candidate pair nominated for sending, if any.
+    pub(crate) fn nominated_send_pair(&self) -> Option<&CandidatePair> {
+        let id = self.nominated_send?;
+
+        self.candidate_pairs.iter().find(|p| p.id() == id)
+    }
+
     fn nominated_pair_priority(&self) -> Option<u64> {
         let id = self.nominated_send?;
 
@@ -1585,7 +1682,7 @@ impl IceAgent {
 
     fn set_connection_state(&mut self, state: IceConnectionState, reason: &'static str) {
         if self.state != state {
-            info!("State change ({}): {:?} -> {:?}", reason, self.state, state);
+            info!(from = ?self.state, to = ?state, reason, "ICE state change");
             self.state = state;
             self.emit_event(IceAgentEvent::IceConnectionStateChange(state));
         }
@@ -1898,6 +1995,26 @@ mod test {
         assert_eq!(pair.remote_binding_request_time, Some(now));
     }
 
+    #[test]
+    fn viable_remote_candidate() {
+        let mut agent = IceAgent::new();
+
+        agent.add_remote_candidate(Candidate::host(ipv4_3(), "udp").unwrap());
+        agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap());
+
+        // An unchecked pair is not verified.
+        assert!(!agent.has_viable_remote_candidate(ipv4_3()));
+
+        // A request from the remote peer verifies it with ice-lite only, since
+        // otherwise we do the checks ourselves.
+        agent.candidate_pairs[0].increase_remote_binding_requests(Instant::now());
+        assert!(!agent.has_viable_remote_candidate(ipv4_3()));
+
+        agent.set_ice_lite(true);
+        assert!(agent.has_viable_remote_candidate(ipv4_3()));
+        assert!(!agent.has_viable_remote_candidate(ipv4_2()));
+    }
+
     #[test]
     fn form_pairs_skip_invalidated_local() {
         let mut agent = IceAgent::new();
@@ -2052,6 +2169,93 @@ mod test {
         assert!(agent.poll_transmit().is_none());
     }
 
+    #[test]
+    fn rejects_binding_request_with_wrong_password() {
+        let mut agent = IceAgent::new();
+        let remote_creds = IceCreds::new();
+        agent.set_remote_credentials(remote_creds.clone());
+
+        let username = format!("{}:{}", agent.local_credentials.ufrag, remote_creds.ufrag);
+        let req = StunMessage::binding_request(&username, TransId::new(), false, 0, 1, false);
+        let request = serialize_stun_msg(req, "wrong password");
+        let message = StunMessage::parse(&request).unwrap();
+
+        assert!(!agent.accepts_message(&message));
+        assert!(agent.rejects_message(&message));
+
+        let accepted = agent.handle_packet(
+            Instant::now(),
+            StunPacket {
+                proto: Protocol::Udp,
+                source: ipv4_3(),
+                destination: ipv4_1(),
+                message,
+            },
+        );
+        assert!(!accepted);
+
+        let transmit = agent.poll_transmit().unwrap();
+        assert_eq!(transmit.source, ipv4_1());
+        assert_eq!(transmit.destination, ipv4_3());
+
+        let reply = StunMessage::parse(&transmit.contents).unwrap();
+        assert_eq!(reply.class(), StunClass::Failure);
+        assert_eq!(reply.trans_id(), message.trans_id());
+        assert_eq!(reply.error_code(), Some((401, "Unauthorized")));
+        assert_eq!(agent.stats().bind_request_recv, 0);
+    }
+
+    #[test]
+    fn rejects_binding_request_without_integrity() {
+        let mut agent = IceAgent::new();
+        let remote_creds = IceCreds::new();
+
+        let username = format!("{}:{}", agent.local_credentials.ufrag, remote_creds.ufrag);
+        let req = StunMessage::binding_request(&username, TransId::new(), false, 0, 1, false);
+        let mut request = vec![0_u8; DATAGRAM_MTU];
+        let n = req.to_bytes_key(None, &mut request).unwrap();
+        let message = StunMessage::parse(&request[..n]).unwrap();
+
+        agent.handle_packet(
+            Instant::now(),
+            StunPacket {
+                proto: Protocol::Udp,
+                source: ipv4_3(),
+                destination: ipv4_1(),
+                message,
+            },
+        );
+
+        let transmit = agent.poll_transmit().unwrap();
+        let reply = StunMessage::parse(&transmit.contents).unwrap();
+        assert_eq!(reply.error_code(), Some((400, "Bad Request")));
+    }
+
+    #[test]
+    fn ignores_unauthenticated_request_for_other_agent() {
+        let mut agent = IceAgent::new();
+        let other = IceCreds::new();
+
+        let username = format!("{}:{}", other.ufrag, other.ufrag);
+        let req = StunMessage::binding_request(&username, TransId::new(), false, 0, 1, false);
+        let request = serialize_stun_msg(req, "wrong password");
+        let message = StunMessage::parse(&request).unwrap();
+
+        assert!(!agent.rejects_message(&message));
+
+        agent.handle_packet(
+            Instant::now(),
+            StunPacket {
+                proto: Protocol::Udp,
+                source: ipv4_3(),
+                destination: ipv4_1(),
+                message,
+            },
+        );
+
+        assert!(agent.poll_transmit().is_none());
+    }
+
     fn make_serialized_binding_request(
         local_creds: &IceCreds,
         remote_creds: &IceCreds,
@@ -1,9 +1,10 @@
 use std::collections::VecDeque;
 use std::fmt;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::io::{stun_resend_delay, STUN_MAX_RETRANS};
 use crate::io::{Id, TransId, STUN_MAX_RTO_MILLIS};
+use crate::util::Instant;
 use crate::Candidate;
 
 const MIN_TIMEOUT: Duration = Duration::from_millis(STUN_MAX_RTO_MILLIS);
@@ -283,6 +284,23 @@ impl CandidatePair {
         trace!("Recorded binding response: {:?}", self);
     }
 
+    /// Round trip time of the last answered binding request.
+    pub fn rtt(&self) -> Option<Duration> {
+        self.binding_attempts
+            .iter()
+            .rev()
+            .find_map(|b| Some(b.respone_recv? - b.request_sent))
+    }
+
+    /// The time of the last binding response, which is the last consent from
+    /// the remote peer to receive data on this pair (RFC 7675).
+    pub fn last_response_time(&self) -> Option<Instant> {
+        self.binding_attempts
+            .iter()
+            .rev()
+            .find_map(|b| b.respone_recv)
+    }
+
     /// The time of the last binding request attempt.
     ///
     /// `None` means there has been no attempts.
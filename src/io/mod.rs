@@ -24,9 +24,18 @@ pub(crate) use id::Id;
 /// Targeted MTU
 pub(crate) const DATAGRAM_MTU: usize = 1150;
 
+/// Smallest configurable MTU.
+pub(crate) const MIN_MTU: usize = 576;
+
+/// Largest configurable MTU.
+pub(crate) const MAX_MTU: usize = 1500;
+
 /// Warn if any packet we are about to send is above this size.
 pub(crate) const DATAGRAM_MTU_WARN: usize = 1280;
 
+/// Largest total size of a GSO send, which is the largest UDP payload over IPv4.
+pub(crate) const GSO_MAX_BYTES: usize = 65_507;
+
 /// Max UDP packet size
 pub(crate) const DATAGRAM_MAX_PACKET_SIZE: usize = 2000;
 
@@ -62,6 +71,7 @@ pub enum Protocol {
 
 /// An instruction to send an outgoing packet.
 #[derive(Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Transmit {
     /// Protocol the transmission should use.
     ///
@@ -113,6 +123,21 @@ pub struct Transmit {
 
     /// Contents of the datagram.
     pub contents: DatagramSend,
+
+    /// Size of each datagram when `contents` holds several back to back, for UDP GSO.
+    ///
+    /// Only set by [`Rtc::poll_output_gso()`][crate::Rtc::poll_output_gso]. All segments
+    /// are this size, except the last which might be shorter. Without GSO support, send each
+    /// of [`Transmit::segments()`] as a separate datagram.
+    pub segment_size: Option<usize>,
+}
+
+impl Transmit {
+    /// The datagrams in this transmit. Only more than one if [`Transmit::segment_size`] is set.
+    pub fn segments(&self) -> impl Iterator<Item = &[u8]> {
+        let size = self.segment_size.unwrap_or(self.contents.len()).max(1);
+        self.contents.chunks(size)
+    }
 }
 
 /// A wrapper for some payload that is to be sent.
@@ -193,6 +218,8 @@ pub(crate) enum DatagramRecvInner<'a> {
     Dtls(&'a [u8]),
     Rtp(&'a [u8]),
     Rtcp(&'a [u8]),
+    Zrtp(&'a [u8]),
+    TurnChannel(&'a [u8]),
 }
 
 impl<'a> TryFrom<&'a [u8]> for DatagramRecv<'a> {
@@ -208,18 +235,29 @@ impl<'a> TryFrom<&'a [u8]> for DatagramRecv<'a> {
             MultiplexKind::Dtls => Dtls(value),
             MultiplexKind::Rtp => Rtp(value),
             MultiplexKind::Rtcp => Rtcp(value),
+            MultiplexKind::Zrtp => Zrtp(value),
+            MultiplexKind::TurnChannel => TurnChannel(value),
         };
 
         Ok(DatagramRecv { inner })
     }
 }
 
+/// Class of datagram on the shared ICE transport, by the first byte as of RFC 7983.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) enum MultiplexKind {
+    /// 0-3
     Stun,
+    /// 20-63
     Dtls,
+    /// 128-191
     Rtp,
+    /// 128-191, with the second byte as an RTCP packet type.
     Rtcp,
+    /// 16-19. We don't do ZRTP, these are dropped.
+    Zrtp,
+    /// 64-79. TURN channel data is unwrapped before us, these are dropped.
+    TurnChannel,
 }
 
 impl<'a> TryFrom<&'a [u8]> for MultiplexKind {
@@ -233,10 +271,14 @@ impl<'a> TryFrom<&'a [u8]> for MultiplexKind {
         let byte0 = value[0];
         let len = value.len();
 
-        if byte0 < 2 && len >= 20 {
+        if byte0 < 4 && len >= 20 {
             Ok(MultiplexKind::Stun)
+        } else if (16..20).contains(&byte0) {
+            Ok(MultiplexKind::Zrtp)
         } else if byte0 >= 20 && byte0 < 64 {
             Ok(MultiplexKind::Dtls)
+        } else if (64..80).contains(&byte0) {
+            Ok(MultiplexKind::TurnChannel)
         } else if byte0 >= 128 && byte0 < 192 && len > 2 {
             let byte1 = value[1];
             let payload_type = byte1 & 0x7f;
@@ -281,6 +323,7 @@ impl fmt::Debug for Transmit {
             .field("source", &self.source)
             .field("destination", &self.destination)
             .field("len", &self.contents.len())
+            .field("segment_size", &self.segment_size)
             .finish()
     }
 }
@@ -306,6 +349,8 @@ impl fmt::Debug for DatagramRecvInner<'_> {
             Self::Dtls(v) => write!(f, "Dtls(len: {})", v.len()),
             Self::Rtp(v) => write!(f, "Rtp(len: {})", v.len()),
             Self::Rtcp(v) => write!(f, "Rtcp(len: {})", v.len()),
+            Self::Zrtp(v) => write!(f, "Zrtp(len: {})", v.len()),
+            Self::TurnChannel(v) => write!(f, "TurnChannel(len: {})", v.len()),
         }
     }
     //
@@ -343,3 +388,36 @@ impl fmt::Display for Protocol {
         write!(f, "{}", x)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kind(byte0: u8, byte1: u8) -> Option<MultiplexKind> {
+        let mut buf = [0; 20];
+        buf[0] = byte0;
+        buf[1] = byte1;
+        MultiplexKind::try_from(&buf[..]).ok()
+    }
+
+    #[test]
+    fn multiplex_rfc7983_ranges() {
+        use MultiplexKind::*;
+
+        for b in 0..=255 {
+            let expected = match b {
+                0..=3 => Some(Stun),
+                16..=19 => Some(Zrtp),
+                20..=63 => Some(Dtls),
+                64..=79 => Some(TurnChannel),
+                128..=191 => Some(Rtp),
+                _ => None,
+            };
+            assert_eq!(kind(b, 111), expected, "byte0: {b}");
+        }
+
+        // RTCP is told apart from RTP on the packet type.
+        assert_eq!(kind(128, 200), Some(Rtcp));
+        assert_eq!(kind(128, 111), Some(Rtp));
+    }
+}
@@ -108,18 +108,18 @@ impl<'a> StunMessage<'a> {
 
         let attrs = Attributes::parse(&buf[20..], trans_id, &mut message_integrity_offset)?;
 
-        // message-integrity only includes the length up until and including
-        // the message-integrity attribute.
-        if message_integrity_offset == 0 {
-            return Err(StunError::Parse("No message integrity in incoming".into()));
-        }
-
-        // length including message integrity attribute
-        let integrity_len = (message_integrity_offset + 4 + 20) as u16;
-
-        // password as key is called "short-term credentials"
-        // buffer from beginning including header (+20) to where message-integrity starts.
-        let integrity = &buf[0..(message_integrity_offset + 20)];
+        // A message without message-integrity is parsed, but never passes the
+        // integrity check. This lets the ICE agent answer it with an error.
+        let (integrity, integrity_len) = if attrs.message_integrity.is_some() {
+            // message-integrity only includes the length up until and including
+            // the message-integrity attribute.
+            let integrity_len = (message_integrity_offset + 4 + 20) as u16;
+
+            // buffer from beginning including header (+20) to where message-integrity starts.
+            (&buf[0..(message_integrity_offset + 20)], integrity_len)
+        } else {
+            (&buf[0..0], 0)
+        };
 
         if method == Method::Binding && class == Class::Success {
             if attrs.xor_mapped_address.is_none() {
@@ -211,6 +211,36 @@ impl<'a> StunMessage<'a> {
         }
     }
 
+    /// Constructs a new STUN BINDING error response.
+    ///
+    /// `code` is one of the STUN error codes (300-699), such as 400 (Bad Request) or
+    /// 401 (Unauthorized), and `reason` a short human readable reason phrase.
+    pub(crate) fn error_reply(trans_id: TransId, code: u16, reason: &'a str) -> StunMessage<'a> {
+        StunMessage {
+            class: Class::Failure,
+            method: Method::Binding,
+            trans_id,
+            attrs: Attributes {
+                error_code: Some((code, reason)),
+                ..Default::default()
+            },
+            integrity: &[],
+            integrity_len: 0,
+        }
+    }
+
+    /// Set the REALM and NONCE attributes used with long-term credentials.
+    ///
+    /// For requests, the USERNAME is the long-term username and the message must be
+    /// serialized with the key from [`StunMessage::long_term_key`]. For 401 error responses,
+    /// these are the challenge for the client.
+    #[cfg(test)]
+    pub(crate) fn with_realm_nonce(mut self, realm: &'a str, nonce: &'a str) -> Self {
+        self.attrs.realm = Some(realm);
+        self.attrs.nonce = Some(nonce);
+        self
+    }
+
     /// If present, splits the value of the USERNAME attribute into local and remote (separated by `:`).
     pub fn split_username(&self) -> Option<(&str, &str)> {
         self.attrs.split_username()
@@ -231,12 +261,77 @@ impl<'a> StunMessage<'a> {
         self.attrs.use_candidate
     }
 
+    /// If present, returns the value of the ERROR-CODE attribute as code and reason phrase.
+    pub(crate) fn error_code(&self) -> Option<(u16, &str)> {
+        self.attrs.error_code
+    }
+
+    /// If present, returns the value of the USERNAME attribute.
+    pub fn username(&self) -> Option<&str> {
+        self.attrs.username
+    }
+
+    /// If present, returns the value of the REALM attribute.
+    pub fn realm(&self) -> Option<&str> {
+        self.attrs.realm
+    }
+
+    /// If present, returns the value of the NONCE attribute.
+    pub fn nonce(&self) -> Option<&str> {
+        self.attrs.nonce
+    }
+
+    /// Whether this message has the MESSAGE-INTEGRITY attribute.
+    pub(crate) fn has_integrity(&self) -> bool {
+        self.attrs.message_integrity.is_some()
+    }
+
+    /// The key of long-term credentials (RFC 5389 §15.4), as used by TURN.
+    ///
+    /// The key is `MD5(username ":" realm ":" password)`. The username and password
+    /// are expected to already be processed with SASLprep.
+    pub fn long_term_key(username: &str, realm: &str, password: &str) -> [u8; 16] {
+        crate::crypto::md5(&[
+            username.as_bytes(),
+            b":",
+            realm.as_bytes(),
+            b":",
+            password.as_bytes(),
+        ])
+    }
+
     /// Verify the integrity of this message against the provided password.
+    ///
+    /// The password as key is called "short-term credentials", which is what ICE uses.
     #[must_use]
     pub(crate) fn check_integrity(&self, password: &str) -> bool {
+        self.check_integrity_key(password.as_bytes())
+    }
+
+    /// Verify the integrity of this message with long-term credentials (RFC 5389 §10.2).
+    ///
+    /// This is for TURN, where the key is derived from the USERNAME and REALM attributes
+    /// of the message together with the `password`. Checking that the NONCE is one
+    /// handed out, and not stale, is up to the caller. False if the message lacks
+    /// USERNAME, REALM or NONCE.
+    #[must_use]
+    pub fn check_long_term_integrity(&self, password: &str) -> bool {
+        let (Some(username), Some(realm), Some(_)) = (self.username(), self.realm(), self.nonce())
+        else {
+            return false;
+        };
+        let key = Self::long_term_key(username, realm, password);
+        self.check_integrity_key(&key)
+    }
+
+    /// Verify the integrity of this message against the provided key.
+    ///
+    /// For long-term credentials, the key is derived using [`StunMessage::long_term_key`].
+    #[must_use]
+    pub fn check_integrity_key(&self, key: &[u8]) -> bool {
         if let Some(integ) = self.attrs.message_integrity {
             let comp = crate::crypto::sha1_hmac(
-                password.as_bytes(),
+                key,
                 &[
                     &self.integrity[..2],
                     &[(self.integrity_len >> 8) as u8, self.integrity_len as u8],
@@ -254,16 +349,31 @@ impl<'a> StunMessage<'a> {
     ///
     /// The provided password is used to authenticate the message.
     pub(crate) fn to_bytes(self, password: &str, buf: &mut [u8]) -> Result<usize, StunError> {
+        self.to_bytes_key(Some(password.as_bytes()), buf)
+    }
+
+    /// Serialize this message into the provided buffer, returning the final length of the message.
+    ///
+    /// The message is authenticated with the provided key. Without a key, the message has no
+    /// MESSAGE-INTEGRITY, which is the case for 400 and 401 error responses to requests
+    /// that could not be authenticated.
+    pub(crate) fn to_bytes_key(
+        self,
+        key: Option<&[u8]>,
+        buf: &mut [u8],
+    ) -> Result<usize, StunError> {
         const MSG_HEADER_LEN: usize = 20;
         const MSG_INTEGRITY_LEN: usize = 20;
         const FPRINT_LEN: usize = 4;
         const ATTR_TLV_LENGTH: usize = 4;
 
-        let attr_len = self.attrs.padded_len()
-            + MSG_INTEGRITY_LEN
-            + ATTR_TLV_LENGTH
-            + FPRINT_LEN
-            + ATTR_TLV_LENGTH;
+        let integrity_len = if key.is_some() {
+            MSG_INTEGRITY_LEN + ATTR_TLV_LENGTH
+        } else {
+            0
+        };
+
+        let attr_len = self.attrs.padded_len() + integrity_len + FPRINT_LEN + ATTR_TLV_LENGTH;
 
         let mut buf = io::Cursor::new(buf);
 
@@ -282,26 +392,31 @@ impl<'a> StunMessage<'a> {
         self.attrs.to_bytes(&mut buf, &self.trans_id.0)?;
 
         // Message integrity
-        buf.write_all(&Attributes::MESSAGE_INTEGRITY.to_be_bytes())?;
-        buf.write_all(&(MSG_INTEGRITY_LEN as u16).to_be_bytes())?;
-        buf.write_all(&[0; MSG_INTEGRITY_LEN])?; // placeholder
         let integrity_value_offset = MSG_HEADER_LEN + self.attrs.padded_len() + ATTR_TLV_LENGTH;
+        if key.is_some() {
+            buf.write_all(&Attributes::MESSAGE_INTEGRITY.to_be_bytes())?;
+            buf.write_all(&(MSG_INTEGRITY_LEN as u16).to_be_bytes())?;
+            buf.write_all(&[0; MSG_INTEGRITY_LEN])?; // placeholder
+        }
 
         // Fingerprint
         buf.write_all(&Attributes::FINGERPRINT.to_be_bytes())?;
         buf.write_all(&(FPRINT_LEN as u16).to_be_bytes())?;
         buf.write_all(&[0; FPRINT_LEN])?; // placeholder
-        let fingerprint_value_offest = integrity_value_offset + MSG_INTEGRITY_LEN + ATTR_TLV_LENGTH;
+        let fingerprint_value_offest =
+            MSG_HEADER_LEN + self.attrs.padded_len() + integrity_len + ATTR_TLV_LENGTH;
 
         let buf = buf.into_inner();
 
         // Compute and fill in message integrity
-        let hmac = crate::crypto::sha1_hmac(
-            password.as_bytes(),
-            &[&buf[0..(integrity_value_offset - ATTR_TLV_LENGTH)]],
-        );
-        buf[integrity_value_offset..(integrity_value_offset + MSG_INTEGRITY_LEN)]
-            .copy_from_slice(&hmac);
+        if let Some(key) = key {
+            let hmac = crate::crypto::sha1_hmac(
+                key,
+                &[&buf[0..(integrity_value_offset - ATTR_TLV_LENGTH)]],
+            );
+            buf[integrity_value_offset..(integrity_value_offset + MSG_INTEGRITY_LEN)]
+                .copy_from_slice(&hmac);
+        }
 
         // Fill in total message length
         buf[2..4].copy_from_slice(&(attr_len as u16).to_be_bytes());
@@ -515,8 +630,26 @@ impl<'a> Attributes<'a> {
         } else {
             0
         };
-
-        username + ice_controlled + ice_controlling + priority + address + use_candidate
+        let str_len = |v: Option<&str>| {
+            v.map(|v| ATTR_TLV_LENGTH + v.len() + str_pad(v))
+                .unwrap_or_default()
+        };
+        let error_code = self
+            .error_code
+            .map(|(_, reason)| ATTR_TLV_LENGTH + 4 + reason.len() + str_pad(reason))
+            .unwrap_or_default();
+        let realm = str_len(self.realm);
+        let nonce = str_len(self.nonce);
+
+        username
+            + ice_controlled
+            + ice_controlling
+            + priority
+            + address
+            + use_candidate
+            + error_code
+            + realm
+            + nonce
     }
 
     fn to_bytes(self, vec: &mut dyn Write, trans_id: &[u8]) -> io::Result<()> {
@@ -555,6 +688,21 @@ impl<'a> Attributes<'a> {
             vec.write_all(&Self::USE_CANDIDATE.to_be_bytes())?;
             vec.write_all(&0_u16.to_be_bytes())?;
         }
+        if let Some((code, reason)) = self.error_code {
+            vec.write_all(&Self::ERROR_CODE.to_be_bytes())?;
+            vec.write_all(&((4 + reason.len()) as u16).to_be_bytes())?;
+            vec.write_all(&[0, 0, (code / 100) as u8, (code % 100) as u8])?;
+            vec.write_all(reason.as_bytes())?;
+            vec.write_all(&[0; 3][..str_pad(reason)])?;
+        }
+        for (typ, v) in [(Self::REALM, self.realm), (Self::NONCE, self.nonce)] {
+            if let Some(v) = v {
+                vec.write_all(&typ.to_be_bytes())?;
+                vec.write_all(&(v.len() as u16).to_be_bytes())?;
+                vec.write_all(v.as_bytes())?;
+                vec.write_all(&[0; 3][..str_pad(v)])?;
+            }
+        }
 
         Ok(())
     }
@@ -702,6 +850,11 @@ impl<'a> Attributes<'a> {
     }
 }
 
+/// Padding to the 32 bit boundary after a string attribute value.
+fn str_pad(v: &str) -> usize {
+    (4 - v.len() % 4) % 4
+}
+
 fn decode_str(typ: u16, buf: &[u8], len: usize) -> Result<&str, StunError> {
     if len > 128 {
         return Err(StunError::Parse(format!(
@@ -853,6 +1006,75 @@ mod test {
         );
     }
 
+    #[test]
+    fn error_reply_without_integrity() {
+        let trans_id = TransId::new();
+        let reply = StunMessage::error_reply(trans_id, 401, "Unauthorized")
+            .with_realm_nonce("example.org", "f//499k954d6OL34oL9FSTvy64sA");
+
+        let mut buf = vec![0; 1500];
+        let n = reply.to_bytes_key(None, &mut buf).unwrap();
+
+        let message = StunMessage::parse(&buf[..n]).unwrap();
+        assert_eq!(message.class(), Class::Failure);
+        assert_eq!(message.trans_id(), trans_id);
+        assert_eq!(message.error_code(), Some((401, "Unauthorized")));
+        assert_eq!(message.realm(), Some("example.org"));
+        assert_eq!(message.nonce(), Some("f//499k954d6OL34oL9FSTvy64sA"));
+        assert!(!message.has_integrity());
+        assert!(!message.check_integrity(""));
+    }
+
+    #[test]
+    fn long_term_integrity() {
+        let key = StunMessage::long_term_key("user", "example.org", "secret");
+        // MD5("user:example.org:secret")
+        assert_eq!(
+            key,
+            [
+                0xa9, 0x83, 0x2f, 0xed, 0x4a, 0x75, 0x67, 0xb4, 0x0e, 0x43, 0x44, 0x3f, 0x9f, 0x30,
+                0xc2, 0x72
+            ]
+        );
+
+        let req = StunMessage::binding_request("user:peer", TransId::new(), true, 0, 1, false)
+            .with_realm_nonce("example.org", "nonce");
+
+        let mut buf = vec![0; 1500];
+        let n = req.to_bytes_key(Some(&key), &mut buf).unwrap();
+
+        let message = StunMessage::parse(&buf[..n]).unwrap();
+        assert!(message.has_integrity());
+        assert!(message.check_integrity_key(&key));
+        assert!(!message.check_integrity("secret"));
+        assert_eq!(message.realm(), Some("example.org"));
+        assert_eq!(message.nonce(), Some("nonce"));
+    }
+
+    #[test]
+    fn check_long_term_integrity() {
+        let key = StunMessage::long_term_key("user:peer", "example.org", "secret");
+
+        let req = StunMessage::binding_request("user:peer", TransId::new(), true, 0, 1, false)
+            .with_realm_nonce("example.org", "nonce");
+
+        let mut buf = vec![0; 1500];
+        let n = req.to_bytes_key(Some(&key), &mut buf).unwrap();
+
+        let message = StunMessage::parse(&buf[..n]).unwrap();
+        assert_eq!(message.username(), Some("user:peer"));
+        assert!(message.check_long_term_integrity("secret"));
+        assert!(!message.check_long_term_integrity("wrong"));
+
+        // Without REALM and NONCE, there are no long-term credentials.
+        let req = StunMessage::binding_request("user:peer", TransId::new(), true, 0, 1, false);
+        let n = req.to_bytes_key(Some(&key), &mut buf).unwrap();
+
+        let message = StunMessage::parse(&buf[..n]).unwrap();
+        assert!(message.check_integrity_key(&key));
+        assert!(!message.check_long_term_integrity("secret"));
+    }
+
     #[test]
     fn parse_zero_length_buffer() {
         let result = StunMessage::parse(&[]);
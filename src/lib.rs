@@ -152,6 +152,9 @@
 //!
 //!             continue;
 //!         }
+//!
+//!         // Output is non_exhaustive, future versions may add more kinds.
+//!         _ => continue,
 //!     };
 //!
 //!     // Duration until timeout.
@@ -377,6 +380,10 @@
 //! 2. [`StreamTx::write_rtp`][wrtrtp] to write outgoing RTP packets.
 //! 3. [`StreamRx::request_keyframe`][reqkey2] to request keyframes from remote.
 //!
+//! When forwarding, [`RewriteContext`][rewrite] maps packets from one or more incoming streams
+//! (such as simulcast layers) onto an outgoing stream with contiguous sequence numbers and
+//! RTP time.
+//!
 //! ## NIC enumeration and TURN (and STUN)
 //!
 //! The [ICE RFC][ice] talks about "gathering ice candidates". This means
@@ -498,6 +505,31 @@
 //! | Turn                     | :x:                | :white_check_mark: |
 //! | Network interface enum   | :x:                | :white_check_mark: |
 //!
+//! ### Cargo features
+//!
+//! Some subsystems are behind cargo features, all on by default. Constrained deployments,
+//! such as an audio-only gateway or a data-only relay, can turn them off with
+//! `default-features = false` to reduce binary size and attack surface.
+//!
+//! | Feature      | Subsystem                                                       |
+//! | ------------ | --------------------------------------------------------------- |
+//! | `sctp`       | SCTP and data channels.                                         |
+//! | `bwe`        | Send side bandwidth estimation and the leaky bucket pacer.      |
+//! | `sample-api` | Packetizing of samples, `Writer::write` and `Event::MediaData`. |
+//!
+//! Without `sample-api` str0m always runs in RTP mode. FEC is not a separate subsystem in
+//! str0m (Opus in-band FEC is negotiated via the format parameters), so there is no feature
+//! for it. Remember to keep `openssl` (or bring another crypto provider) when disabling
+//! default features.
+//!
+//! The `serde` feature, off by default, derives `Serialize` for the types in [`stats`], which
+//! are the payloads of [`Event::PeerStats`], [`Event::MediaIngressStats`] and
+//! [`Event::MediaEgressStats`], and the result of [`Rtc::stats()`]. It also covers the other
+//! event payloads that report state, such as [`Event::EgressBitrateEstimate`],
+//! [`Event::TargetBitrate`], [`Event::StreamPaused`], [`Event::StreamWritable`] and
+//! [`Event::DominantSpeakerChanged`]. [`Event`] itself is not serializable, since many of its
+//! variants carry media data or non-serializable types such as `Instant`.
+//!
 //! ### Platform Support
 //!
 //! Platforms str0m is compiled and tested on:
@@ -508,6 +540,14 @@
 //! | `x86_64-unknown-linux-gnu` | :white_check_mark:| :white_check_mark:|
 //! | `x86_64-apple-darwin`      | :white_check_mark:| :white_check_mark:|
 //!
+//! str0m has no IO of its own and can also be built for `wasm32` targets (browser and WASI)
+//! with `default-features = false`, since openssl doesn't build for wasm. Add back the subsystems
+//! you need, e.g. `features = ["bwe", "sample-api"]`. Without a crypto provider str0m can't do
+//! DTLS, but the RTP, SDP and ICE parts are usable. On `wasm32-unknown-unknown`, where std has no
+//! clock, the `Instant` in the API is [`web_time::Instant`](https://docs.rs/web-time). The `sctp`
+//! feature (data channels) is not available there, since the SCTP implementation uses std's
+//! `Instant`. It does build for WASI.
+//!
 //! If your platform isn't listed but is supported by Rust, we'd love for you to give str0m a try and
 //! share your experience. We greatly appreciate your feedback!
 //!
@@ -520,6 +560,12 @@
 //! Absolutely! str0m is fully sync, ensuring that it integrates seamlessly with any Rust async
 //! runtime you opt for.
 //!
+//! ### Can I use str0m from C or C++?
+//!
+//! Yes, the `ffi` feature exposes a C ABI in `str0m::ffi` with a matching header in
+//! `include/str0m.h`. It covers the sans-IO loop (candidates, SDP offer/answer, input and output)
+//! so existing C/C++ media servers can embed str0m incrementally.
+//!
 //! ### Can I create a client with str0m?
 //!
 //! Of course! You have the freedom to create a client with str0m. However, please note that some
@@ -567,8 +613,11 @@
 //! [rtppak]:     https://docs.rs/str0m/*/str0m/enum.Event.html#variant.RtpPacket
 //! [wrtrtp]:     https://docs.rs/str0m/*/str0m/rtp/struct.StreamTx.html#method.write_rtp
 //! [reqkey2]:    https://docs.rs/str0m/*/str0m/rtp/struct.StreamRx.html#method.request_keyframe
+//! [rewrite]:    https://docs.rs/str0m/*/str0m/rtp/struct.RewriteContext.html
 
-#![forbid(unsafe_code)]
+// The C ABI is the only place that needs unsafe.
+#![cfg_attr(not(feature = "ffi"), forbid(unsafe_code))]
+#![cfg_attr(feature = "ffi", deny(unsafe_code))]
 #![allow(clippy::new_without_default)]
 #![allow(clippy::bool_to_int_with_if)]
 #![allow(clippy::assertions_on_constants)]
@@ -579,22 +628,37 @@
 #[macro_use]
 extern crate tracing;
 
-use bwe::{Bwe, BweKind};
+// sctp-proto is driven by std's Instant, which has no clock on wasm32-unknown-unknown.
+#[cfg(all(feature = "sctp", target_family = "wasm", target_os = "unknown"))]
+compile_error!("The sctp feature is not supported on wasm32-unknown-unknown");
+
+#[cfg(feature = "bwe")]
+use bwe::Bwe;
+use bwe::BweKind;
+use bwe::TargetBitrate;
 use change::{DirectApi, SdpApi};
-use rtp::RawPacket;
+use rtp::{Observer, RawPacket, TappedPacket};
 use std::fmt;
 use std::net::SocketAddr;
-use std::time::{Duration, Instant};
+use std::ops::RangeInclusive;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::Duration;
 use streams::RtpPacket;
-use streams::StreamPaused;
+use streams::{RtcpBandwidth, RtcpIntervals, UnknownSsrcPolicy};
+use streams::{SsrcCollision, StreamDiscontinuity, StreamEnded, StreamPaused};
+use streams::{StreamWritable, UnknownSsrc, DEFAULT_RTX_CACHE_MAX_BYTES};
 use thiserror::Error;
-use util::InstantExt;
+use tracing::Span;
+use util::{TimeAnchor, TimeAnchorGuard};
 
 mod crypto;
-use crypto::Fingerprint;
+pub use crypto::SrtpProfile;
+use crypto::{Fingerprint, FingerprintHash};
 
 mod dtls;
 use dtls::DtlsCert;
+pub use dtls::DtlsState;
 use dtls::{Dtls, DtlsEvent};
 
 #[path = "ice/mod.rs"]
@@ -616,7 +680,8 @@ pub mod ice {
 }
 
 mod io;
-use io::DatagramRecvInner;
+use io::{DatagramRecvInner, MultiplexKind};
+use io::{DATAGRAM_MTU, GSO_MAX_BYTES, MAX_MTU, MIN_MTU};
 
 mod packet;
 
@@ -629,20 +694,38 @@ use rtp_::{Extension, ExtensionMap};
 pub mod rtp {
     /// Feedback for RTP.
     pub mod rtcp {
+        pub use crate::rtp_::{Ccfb, CcfbMetric, CcfbReport, Ecn, EcnCounts};
         pub use crate::rtp_::{Descriptions, ExtendedReport, Fir, Goodbye, Nack, Pli};
         pub use crate::rtp_::{Dlrr, NackEntry, ReceptionReport, ReportBlock};
         pub use crate::rtp_::{FirEntry, ReceiverReport, SenderInfo, SenderReport, Twcc};
         pub use crate::rtp_::{ReportList, Rrtr, Rtcp, Sdes, SdesType};
     }
     use self::rtcp::Rtcp;
+    use crate::util::Instant;
 
     /// Video Layers Allocation RTP Header Extension
     pub mod vla;
+
+    mod observer;
+    mod rewrite;
     pub use crate::rtp_::{Extension, ExtensionMap, ExtensionSerializer};
     pub use crate::rtp_::{ExtensionValues, UserExtensionValues};
+    pub(crate) use observer::Observer;
+    pub use observer::{ObservedPacket, PacketObserver, PacketStage};
+    pub use rewrite::{RewriteContext, Rewritten};
 
-    pub use crate::rtp_::{RtpHeader, SeqNo, Ssrc, VideoOrientation};
-    pub use crate::streams::{RtpPacket, StreamPaused, StreamRx, StreamTx};
+    pub use crate::rtp_::{AbsCaptureTime, RtpHeader, SeqNo, Ssrc, VideoOrientation};
+    pub use crate::streams::{DiscontinuityReason, StreamDiscontinuity, StreamEnded};
+    pub use crate::streams::{RtcpBandwidth, SsrcCollision, SyncGroup};
+    pub use crate::streams::{RtpPacket, StreamPaused, StreamRx, StreamRxStats};
+    pub use crate::streams::{StreamTx, StreamTxStats, StreamWritable};
+    pub use crate::streams::{UnknownSsrc, UnknownSsrcPolicy};
+
+    /// Cheaply cloneable buffer used for RTP payloads.
+    ///
+    /// Cloning shares the underlying data, which means a payload can be forwarded to
+    /// several [`StreamTx`] without copying it.
+    pub use bytes::Bytes;
 
     /// Debug output of the unencrypted RTP and RTCP packets.
     ///
@@ -660,11 +743,42 @@ pub mod rtp {
         /// Incoming RTP.
         RtpRx(RtpHeader, Vec<u8>),
     }
+
+    /// Copy of an unencrypted RTP or RTCP packet sent to the packet tap.
+    ///
+    /// Enable using [`RtcConfig::enable_packet_tap()`][crate::RtcConfig::enable_packet_tap]
+    /// and poll using [`Rtc::poll_tapped_packet()`][crate::Rtc::poll_tapped_packet].
+    /// The data is the entire packet as it appears on the wire, but without SRTP/SRTCP
+    /// encryption, which makes it suitable for writing pcap or rtpdump files.
+    #[derive(Debug, Clone)]
+    pub struct TappedPacket {
+        /// When the packet was received or sent.
+        pub timestamp: Instant,
+        /// The kind of packet.
+        pub kind: TappedKind,
+        /// The unencrypted packet, RTP header included.
+        pub data: Vec<u8>,
+    }
+
+    /// Kind of a [`TappedPacket`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TappedKind {
+        /// Sent RTCP (compound packet).
+        RtcpTx,
+        /// Incoming RTCP (compound packet).
+        RtcpRx,
+        /// Sent RTP.
+        RtpTx,
+        /// Incoming RTP.
+        RtpRx,
+    }
 }
 
 pub mod bwe;
 
+#[cfg(feature = "sctp")]
 mod sctp;
+#[cfg(feature = "sctp")]
 use sctp::{RtcSctp, SctpEvent};
 
 mod sdp;
@@ -672,13 +786,19 @@ mod sdp;
 pub mod format;
 use format::CodecConfig;
 
+#[cfg(feature = "sctp")]
 pub mod channel;
+#[cfg(feature = "sctp")]
 use channel::{Channel, ChannelData, ChannelHandler, ChannelId};
 
 pub mod media;
-use media::{Direction, Media, Mid, Pt, Rid, Writer};
+use media::{Direction, Media, Mid, Pt, Rid};
+
+use media::DominantSpeakerChanged;
 use media::{KeyframeRequest, KeyframeRequestKind};
-use media::{MediaAdded, MediaChanged, MediaData};
+use media::{MediaAdded, MediaChanged};
+#[cfg(feature = "sample-api")]
+use media::{MediaData, Writer};
 
 pub mod change;
 
@@ -689,8 +809,11 @@ mod session;
 use session::Session;
 
 pub mod stats;
+use stats::{BufferPoolStats, CandidatePairStats, DatagramCounts, RtcStats, RttStats};
 use stats::{MediaEgressStats, MediaIngressStats, PeerStats, Stats, StatsEvent, StatsSnapshot};
 
+use crate::util::{Instant, SystemTime};
+
 mod streams;
 
 /// Network related types to get socket data in/out of [`Rtc`].
@@ -705,6 +828,7 @@ pub mod error {
     pub use crate::io::NetError;
     pub use crate::packet::PacketError;
     pub use crate::rtp_::RtpError;
+    #[cfg(feature = "sctp")]
     pub use crate::sctp::{ProtoError, SctpError};
     pub use crate::sdp::SdpError;
 }
@@ -782,6 +906,7 @@ pub enum RtcError {
     Ice(#[from] error::IceError),
 
     /// SCTP (data channel engine) errors.
+    #[cfg(feature = "sctp")]
     #[error("{0}")]
     Sctp(#[from] error::SctpError),
 
@@ -800,10 +925,33 @@ pub enum RtcError {
     /// is an incorrect usage pattern of the str0m API.
     #[error("Consecutive calls to write() without poll_output() in between")]
     WriteWithoutPoll,
+
+    /// The send queue delay is over the budget and the stream does not accept writes.
+    ///
+    /// See [`StreamTx::set_queue_delay_budget()`][crate::rtp::StreamTx::set_queue_delay_budget].
+    #[error("Send queue delay is over budget")]
+    SendQueueFull,
+
+    /// The [`RtcConfig`] is not valid. See [`RtcConfig::try_build()`].
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+
+    /// The [`MediaConfig`][crate::change::MediaConfig] is not valid, or uses identifiers
+    /// that are already taken. See [`SdpApi::add_media_with_config()`][crate::change::SdpApi::add_media_with_config].
+    #[error("Invalid media config: {0}")]
+    InvalidMediaConfig(String),
 }
 
 /// Instance that does WebRTC. Main struct of the entire library.
 ///
+/// All logging done in [`Rtc::handle_input()`] and [`Rtc::poll_output()`] happens in
+/// a `tracing` span named `rtc` with a `session` field, which can be used to filter
+/// the logs of one session when running many in the same process.
+///
+/// `Rtc` is `Send + Sync + 'static`. It holds no thread-local state, which means a session
+/// can be moved between the worker threads of a thread-per-core server or a work-stealing
+/// runtime. This is enforced at compile time.
+///
 /// ## Usage
 ///
 /// ```no_run
@@ -821,6 +969,7 @@ pub enum RtcError {
 ///             // TODO: Handle event.
 ///             continue; // poll again
 ///         }
+///         _ => continue,
 ///     };
 ///
 ///     // TODO: Wait for one of two events, reaching `timeout`
@@ -833,27 +982,188 @@ pub enum RtcError {
 /// ```
 pub struct Rtc {
     alive: bool,
+    close_state: CloseState,
+    remote_close_pending: bool,
     ice: IceAgent,
     dtls: Dtls,
+    #[cfg(feature = "sctp")]
     sctp: RtcSctp,
+    #[cfg(feature = "sctp")]
     chan: ChannelHandler,
     stats: Option<Stats>,
     session: Session,
     remote_fingerprint: Option<Fingerprint>,
+    dtls_cert_expiry_warning: Option<Duration>,
+    dtls_cert_created_at: Option<Instant>,
+    dtls_cert_warned: bool,
+    dtls_cert_regenerated: bool,
+    dtls_cert_expiring: bool,
+    last_dtls_state: DtlsState,
+    last_connection_state: ConnectionState,
     remote_addrs: Vec<SocketAddr>,
     send_addr: Option<SendAddr>,
-    need_init_time: bool,
+    /// Maps `Instant` to NTP/unix time. Anchored on the first input.
+    time_anchor: Option<TimeAnchor>,
     last_now: Instant,
     peer_bytes_rx: u64,
     peer_bytes_tx: u64,
+    datagrams_rx: DatagramCounts,
     change_counter: usize,
     last_timeout_reason: Reason,
+    /// Output or error polled, but not returned, by [`Rtc::poll_output_gso()`].
+    held_output: Option<HeldOutput>,
+    /// The per-session tracing span, entered for all work in and out of the `Rtc`.
+    ///
+    /// `Span` holds the subscriber as a trait object, which isn't `UnwindSafe`. The span
+    /// is only ever entered, so a panic can't leave it in a broken state.
+    span: AssertUnwindSafe<Span>,
+}
+
+/// Output polled ahead by [`Rtc::poll_output_gso()`].
+struct HeldOutput(Result<Output, RtcError>);
+
+/// This is okay because the held output is only ever handed back as is, there is no state
+/// in it that a panic could leave broken. It's here since `RtcError` can wrap `io::Error`.
+impl std::panic::UnwindSafe for HeldOutput {}
+
+/// Merge the transmits polled after `first` into one for UDP GSO.
+///
+/// Returns the output along with anything polled that couldn't be merged, which is to be
+/// returned by the next poll. An error is held like that too, since the datagrams polled
+/// before it must still be sent.
+fn coalesce_gso(
+    first: net::Transmit,
+    max_segments: usize,
+    mut poll: impl FnMut() -> Result<Output, RtcError>,
+) -> (Output, Option<Result<Output, RtcError>>) {
+    if first.proto != net::Protocol::Udp || first.contents.is_empty() {
+        return (Output::Transmit(first), None);
+    }
+
+    let size = first.contents.len();
+    let mut segments = 1;
+    let mut merged: Option<Vec<u8>> = None;
+    let mut held = None;
+
+    while segments < max_segments && (segments + 1) * size <= GSO_MAX_BYTES {
+        let t = match poll() {
+            Ok(Output::Transmit(t))
+                if t.proto == first.proto
+                    && t.source == first.source
+                    && t.destination == first.destination
+                    && !t.contents.is_empty()
+                    && t.contents.len() <= size =>
+            {
+                t
+            }
+            // Timeouts are recalculated on the next poll, and don't need holding.
+            Ok(Output::Timeout(_)) => break,
+            other => {
+                held = Some(other);
+                break;
+            }
+        };
+
+        let buf = merged.get_or_insert_with(|| {
+            let mut buf = Vec::with_capacity(size * max_segments.min(GSO_MAX_BYTES / size));
+            buf.extend_from_slice(&first.contents);
+            buf
+        });
+        buf.extend_from_slice(&t.contents);
+        segments += 1;
+
+        // A shorter datagram must be the last segment.
+        if t.contents.len() < size {
+            break;
+        }
+    }
+
+    let Some(buf) = merged else {
+        return (Output::Transmit(first), held);
+    };
+
+    trace!(segments, size, "OUT GSO");
+
+    let o = Output::Transmit(net::Transmit {
+        contents: buf.into(),
+        segment_size: Some(size),
+        ..first
+    });
+
+    (o, held)
+}
+
+// Sessions must be able to move between threads. Fail the build, not just a test,
+// if anything in Rtc or the types going in and out of it stops being Send.
+const _: () = {
+    const fn assert_send<T: Send + 'static>() {}
+    assert_send::<Rtc>();
+    assert_send::<RtcConfig>();
+    assert_send::<Event>();
+    assert_send::<Output>();
+    assert_send::<RtcError>();
+};
+
+/// How long to wait for the remote to complete the SCTP shutdown in [`Rtc::disconnect()`].
+#[cfg(feature = "sctp")]
+const SCTP_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Progress of an orderly close after [`Rtc::disconnect()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CloseState {
+    /// Not closing.
+    Open,
+    /// RTCP BYE is queued. Remaining RTP and RTCP is being polled.
+    Goodbye,
+    /// SCTP SHUTDOWN is sent. Input is still handled to complete the shutdown with
+    /// the remote, until the association is closed or the deadline passed.
+    #[cfg(feature = "sctp")]
+    SctpShutdown(Instant),
+    /// DTLS close_notify is queued. Remaining datagrams are being polled.
+    Draining,
+    /// All output is polled, [`Event::Closed`] is not emitted yet.
+    Drained,
+    /// [`Event::Closed`] is emitted.
+    Closed,
 }
 
 struct SendAddr {
     proto: net::Protocol,
     source: SocketAddr,
     destination: SocketAddr,
+    /// Counters for the stats of this pair. STUN is not counted, neither
+    /// sent nor received, since it's in the ICE binding stats.
+    packets_tx: u64,
+    bytes_tx: u64,
+    packets_rx: u64,
+    bytes_rx: u64,
+}
+
+impl SendAddr {
+    fn count_tx(&mut self, t: &net::Transmit) {
+        if t.source != self.source || t.destination != self.destination {
+            return;
+        }
+        if matches!(
+            MultiplexKind::try_from(&*t.contents),
+            Ok(MultiplexKind::Stun)
+        ) {
+            return;
+        }
+        self.packets_tx += 1;
+        self.bytes_tx += t.contents.len() as u64;
+    }
+
+    fn count_rx(&mut self, r: &net::Receive, bytes: usize) {
+        if r.source != self.destination || r.destination != self.source {
+            return;
+        }
+        if matches!(r.contents.inner, DatagramRecvInner::Stun(_)) {
+            return;
+        }
+        self.packets_rx += 1;
+        self.bytes_rx += bytes as u64;
+    }
 }
 
 /// Events produced by [`Rtc::poll_output()`].
@@ -866,10 +1176,39 @@ pub enum Event {
     /// Emitted when we got ICE connection and established DTLS.
     Connected,
 
+    /// The last event after [`Rtc::disconnect()`].
+    ///
+    /// Emitted once all remaining output, such as RTCP BYE and DTLS close_notify,
+    /// has been polled. After this, the instance produces no more output.
+    Closed,
+
     /// ICE connection state changes tells us whether the [`Rtc`] instance is
     /// connected to the peer or not.
     IceConnectionStateChange(IceConnectionState),
 
+    /// The overall connection state changed.
+    ///
+    /// This is the combined state of ICE and DTLS, like `connectionState` of
+    /// the browser `RTCPeerConnection`.
+    ConnectionStateChange(ConnectionState),
+
+    // =================== DTLS related events ===================
+
+    /// The DTLS transport state changed.
+    DtlsStateChange(DtlsState),
+
+    /// The local DTLS certificate was replaced using [`Rtc::regenerate_dtls_cert()`].
+    ///
+    /// The new fingerprint must be signaled to the remote peer, i.e. in the next
+    /// SDP offer/answer.
+    DtlsCertRegenerated(Fingerprint),
+
+    /// The local DTLS certificate expires soon.
+    ///
+    /// The value is the time of expiry. Emitted once per certificate, ahead of time as
+    /// configured by [`RtcConfig::set_dtls_cert_expiry_warning()`].
+    DtlsCertExpiring(SystemTime),
+
     // =================== Media related events ==================
 
     /// Upon adding new media to the session. The lines are emitted.
@@ -878,6 +1217,7 @@ pub enum Event {
     MediaAdded(MediaAdded),
 
     /// Incoming media data sent by the remote peer.
+    #[cfg(feature = "sample-api")]
     MediaData(MediaData),
 
     /// Changes to the media may be emitted.
@@ -899,14 +1239,23 @@ pub enum Event {
     ///
     /// For [`SdpApi`]: The first ever data channel results in an SDP
     /// negotiation, and this events comes at the end of that.
+    #[cfg(feature = "sctp")]
     ChannelOpen(ChannelId, String),
 
     /// Incoming data channel data from the remote peer.
+    #[cfg(feature = "sctp")]
     ChannelData(ChannelData),
 
     /// A data channel has been closed.
+    #[cfg(feature = "sctp")]
     ChannelClose(ChannelId),
 
+    /// The buffered amount of a data channel has drained below the threshold.
+    ///
+    /// See [`Channel::set_buffered_amount_low_threshold()`].
+    #[cfg(feature = "sctp")]
+    ChannelBufferedAmountLow(ChannelId),
+
     // =================== Statistics and BWE related events ===================
 
     /// Statistics event for the Rtc instance
@@ -923,6 +1272,12 @@ pub enum Event {
     /// A new estimate from the bandwidth estimation subsystem.
     EgressBitrateEstimate(BweKind),
 
+    /// New target bitrate for a media, allocated from the estimate.
+    ///
+    /// Only emitted with an allocator set using
+    /// [`Bwe::set_bitrate_allocator()`][crate::bwe::Bwe::set_bitrate_allocator].
+    TargetBitrate(TargetBitrate),
+
     // =================== RTP related events ===================
 
     /// Incoming keyframe request for media that we are sending to the remote peer.
@@ -935,6 +1290,40 @@ pub enum Event {
     /// This means the stream has not received any data for some time (default 1.5 seconds).
     StreamPaused(StreamPaused),
 
+    /// An incoming encoded stream had a discontinuity.
+    ///
+    /// This happens when the remote changes SSRC mid-session or the sequence numbers
+    /// jump massively (e.g. after an SFU switches source). Receive state is reset and
+    /// decoders should be flushed.
+    StreamDiscontinuity(StreamDiscontinuity),
+
+    /// An incoming encoded stream was ended by the remote with an RTCP BYE.
+    ///
+    /// Receive state for the stream is torn down. If the remote reuses the SSRC,
+    /// the stream starts over as new.
+    StreamEnded(StreamEnded),
+
+    /// Incoming RTP/RTCP from an SSRC not bound to any media.
+    ///
+    /// Only emitted with [`UnknownSsrcPolicy::Event`][crate::rtp::UnknownSsrcPolicy::Event].
+    UnknownSsrc(UnknownSsrc),
+
+    /// The remote used one of our SSRCs, and the outgoing stream moved to a new one.
+    ///
+    /// The application might need to resignal the SSRC, e.g. when using the Direct API.
+    SsrcCollision(SsrcCollision),
+
+    /// Whether an outgoing encoded stream accepts writes.
+    ///
+    /// Emitted when the send queue delay crosses the budget set by
+    /// [`StreamTx::set_queue_delay_budget()`][crate::rtp::StreamTx::set_queue_delay_budget].
+    StreamWritable(StreamWritable),
+
+    /// The dominant speaker among the incoming audio streams changed.
+    ///
+    /// Enable using [`RtcConfig::enable_dominant_speaker()`].
+    DominantSpeakerChanged(DominantSpeakerChanged),
+
     /// Incoming RTP data.
     RtpPacket(RtpPacket),
 
@@ -955,10 +1344,36 @@ impl Event {
             None
         }
     }
+
+    /// The [`Mid`] of the media this event relates to, if any.
+    ///
+    /// Lets code route events to the right media without matching on each variant,
+    /// including variants added in later versions.
+    pub fn mid(&self) -> Option<Mid> {
+        match self {
+            Event::MediaAdded(v) => Some(v.mid),
+            #[cfg(feature = "sample-api")]
+            Event::MediaData(v) => Some(v.mid),
+            Event::MediaChanged(v) => Some(v.mid),
+            Event::MediaIngressStats(v) => Some(v.mid),
+            Event::MediaEgressStats(v) => Some(v.mid),
+            Event::KeyframeRequest(v) => Some(v.mid),
+            Event::StreamPaused(v) => Some(v.mid),
+            Event::StreamDiscontinuity(v) => Some(v.mid),
+            Event::StreamEnded(v) => Some(v.mid),
+            Event::UnknownSsrc(v) => v.mid,
+            Event::SsrcCollision(v) => Some(v.mid),
+            Event::StreamWritable(v) => Some(v.mid),
+            Event::DominantSpeakerChanged(v) => Some(v.mid),
+            Event::TargetBitrate(v) => Some(v.mid),
+            _ => None,
+        }
+    }
 }
 
 /// Input as expected by [`Rtc::handle_input()`]. Either network data or a timeout.
 #[derive(Debug)]
+#[non_exhaustive]
 #[allow(clippy::large_enum_variant)] // We purposely don't want to allocate.
 pub enum Input<'a> {
     /// A timeout without any network input.
@@ -967,10 +1382,31 @@ pub enum Input<'a> {
     Receive(Instant, net::Receive<'a>),
 }
 
+impl Input<'_> {
+    /// The time of this input.
+    pub fn timestamp(&self) -> Instant {
+        match self {
+            Input::Timeout(now) => *now,
+            Input::Receive(now, _) => *now,
+        }
+    }
+}
+
 /// Output produced by [`Rtc::poll_output()`]
+///
+/// The output is independent of how the network IO is done. The same loop can be driven
+/// by blocking sockets, an event loop such as mio, or an async runtime such as tokio,
+/// as long as the [`Output::Timeout`] is honored and network data is fed back via
+/// [`Rtc::handle_input()`].
+#[derive(Debug)]
+#[non_exhaustive]
 #[allow(clippy::large_enum_variant)]
 pub enum Output {
     /// When the [`Rtc`] instance expects an [`Input::Timeout`].
+    ///
+    /// This is the soonest timer of all subsystems (ICE, DTLS, RTCP, NACK, pacing, stats
+    /// etc.), which means one timer is all the event loop needs. The subsystem it's
+    /// for is in [`Rtc::last_timeout_reason()`].
     Timeout(Instant),
 
     /// Network data that is to be sent.
@@ -980,8 +1416,56 @@ pub enum Output {
     Event(Event),
 }
 
+impl Output {
+    /// The time of the timeout if this is an [`Output::Timeout`].
+    pub fn as_timeout(&self) -> Option<Instant> {
+        if let Self::Timeout(v) = self {
+            Some(*v)
+        } else {
+            None
+        }
+    }
+
+    /// Reference to the [`net::Transmit`] if this is an [`Output::Transmit`].
+    pub fn as_transmit(&self) -> Option<&net::Transmit> {
+        if let Self::Transmit(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    /// The [`net::Transmit`] if this is an [`Output::Transmit`].
+    pub fn into_transmit(self) -> Option<net::Transmit> {
+        if let Self::Transmit(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    /// Reference to the [`Event`] if this is an [`Output::Event`].
+    pub fn as_event(&self) -> Option<&Event> {
+        if let Self::Event(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    /// The [`Event`] if this is an [`Output::Event`].
+    pub fn into_event(self) -> Option<Event> {
+        if let Self::Event(v) = self {
+            Some(v)
+        } else {
+            None
+        }
+    }
+}
+
 /// The reason for the next [`Output::Timeout`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Reason {
     /// No timeout scheduled.
     ///
@@ -1003,6 +1487,16 @@ pub enum Reason {
     /// Scheduled when we need to open allocations using SCTP.
     Channel,
 
+    /// The DTLS subsystem.
+    ///
+    /// Retransmits of the handshake while it's in progress.
+    Dtls,
+
+    /// DTLS certificate expiry (if warning is enabled).
+    ///
+    /// Scheduled ahead of the local certificate expiring.
+    DtlsCert,
+
     /// Stats gathering (if enabled).
     ///
     /// Periodic gathering of statistics.
@@ -1024,6 +1518,12 @@ pub enum Reason {
     /// sides support it.
     Twcc,
 
+    /// Reporting of RFC 8888 congestion control feedback (if enabled).
+    ///
+    /// All incoming RTP packets are reported per SSRC. Enabled via SDP if both sides
+    /// support it.
+    Ccfb,
+
     /// RTP streams not receiving data goes into a paused state.
     ///
     /// Whenever an RTP receive stream receives data, a new timeout is scheduled.
@@ -1039,6 +1539,12 @@ pub enum Reason {
     /// Written media data needs packetizing. This is not used in RTP mode.
     Packetize,
 
+    /// Release of a frame held back in the jitter buffer (if adaptive).
+    ///
+    /// With [`RtcConfig::set_jitter_buffer_delay()`], a frame missing packets is released
+    /// once it has waited the target delay, also when no more packets arrive.
+    JitterBuffer,
+
     /// Paced sending of RTP packets (if BWE is enabled).
     ///
     /// The pacer ensures bigger RTP chunks, like keyframes, are not sent as a burst,
@@ -1057,6 +1563,45 @@ impl Default for Reason {
     }
 }
 
+/// The overall state of the connection, combined from ICE and DTLS.
+///
+/// Mirrors the [`RTCPeerConnectionState`][1] of the browser API. See
+/// [`Rtc::connection_state()`] and [`Event::ConnectionStateChange`].
+///
+/// [1]: https://www.w3.org/TR/webrtc/#rtcpeerconnectionstate-enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ConnectionState {
+    /// Neither ICE nor DTLS has started.
+    New,
+
+    /// ICE or DTLS is establishing a connection.
+    Connecting,
+
+    /// Both ICE and DTLS are connected.
+    Connected,
+
+    /// ICE lost connectivity. This may resolve itself.
+    Disconnected,
+
+    /// DTLS failed, either the handshake or the fingerprint verification.
+    ///
+    /// This is a final state.
+    Failed,
+
+    /// The instance was disconnected using [`Rtc::disconnect()`].
+    ///
+    /// This is a final state.
+    Closed,
+}
+
+impl ConnectionState {
+    /// Tells if this state is the connected state.
+    pub fn is_connected(&self) -> bool {
+        *self == ConnectionState::Connected
+    }
+}
+
 impl Rtc {
     /// Creates a new instance with default settings.
     ///
@@ -1087,11 +1632,15 @@ impl Rtc {
     pub(crate) fn new_from_config(config: RtcConfig) -> Self {
         let session = Session::new(&config);
 
+        // The session id never changes, so the span is created once.
+        let span = info_span!("rtc", session = %session.id());
+
         let local_creds = config.local_ice_credentials.unwrap_or_else(IceCreds::new);
         let mut ice = IceAgent::with_local_credentials(local_creds);
         if config.ice_lite {
             ice.set_ice_lite(config.ice_lite);
         }
+        ice.set_timing_advance(config.ice_timing_advance);
 
         let dtls_cert = if let Some(c) = config.dtls_cert {
             c
@@ -1108,24 +1657,48 @@ impl Rtc {
 
         Rtc {
             alive: true,
+            close_state: CloseState::Open,
+            remote_close_pending: false,
             ice,
-            dtls: Dtls::new(dtls_cert).expect("DTLS to init without problem"),
+            dtls: {
+                let mut dtls = Dtls::new(dtls_cert, &config.srtp_profiles)
+                    .expect("DTLS to init without problem");
+                dtls.set_mtu(config.mtu);
+                dtls.set_fingerprint_hash(config.fingerprint_hash);
+                dtls
+            },
             session,
-            sctp: RtcSctp::new(),
+            #[cfg(feature = "sctp")]
+            sctp: RtcSctp::new(config.mtu),
+            #[cfg(feature = "sctp")]
             chan: ChannelHandler::default(),
             stats: config.stats_interval.map(Stats::new),
             remote_fingerprint: None,
+            dtls_cert_expiry_warning: config.dtls_cert_expiry_warning,
+            dtls_cert_created_at: None,
+            dtls_cert_warned: false,
+            dtls_cert_regenerated: false,
+            dtls_cert_expiring: false,
+            last_dtls_state: DtlsState::New,
+            last_connection_state: ConnectionState::New,
             remote_addrs: vec![],
             send_addr: None,
-            need_init_time: true,
+            time_anchor: None,
             last_now: already_happened(),
             peer_bytes_rx: 0,
             peer_bytes_tx: 0,
+            datagrams_rx: DatagramCounts::default(),
             change_counter: 0,
             last_timeout_reason: Reason::NotHappening,
+            held_output: None,
+            span: AssertUnwindSafe(span),
         }
     }
 
+    fn span(&self) -> Span {
+        self.span.0.clone()
+    }
+
     /// Tests if this instance is still working.
     ///
     /// Certain events will straight away disconnect the `Rtc` instance, such as
@@ -1147,23 +1720,175 @@ impl Rtc {
         self.alive
     }
 
-    /// Force disconnects the instance making [`Rtc::is_alive()`] return `false`.
+    /// Disconnects the instance making [`Rtc::is_alive()`] return `false`.
     ///
-    /// This makes [`Rtc::poll_output`] and [`Rtc::handle_input`] go inert and not
-    /// produce anymore network output or events.
+    /// This is an orderly shutdown. First an RTCP BYE is sent for all outgoing SSRCs,
+    /// then the SCTP association is shut down, and last DTLS sends a close_notify.
+    /// [`Rtc::poll_output`] produces the remaining output followed by a final
+    /// [`Event::Closed`]. After that, no more network output or events are produced.
+    ///
+    /// [`Rtc::handle_input`] keeps handling DTLS datagrams and timeouts until the remote
+    /// completed the SCTP shutdown, or up to 3 seconds. Other input is ignored.
     ///
     /// ```
-    /// # use str0m::Rtc;
+    /// # use str0m::{Rtc, Event, Output};
     /// let mut rtc = Rtc::new();
     ///
     /// rtc.disconnect();
     /// assert!(!rtc.is_alive());
+    ///
+    /// loop {
+    ///     match rtc.poll_output().unwrap() {
+    ///         Output::Event(Event::Closed) => break,
+    ///         _ => {} // send remaining transmits.
+    ///     }
+    /// }
     /// ```
     pub fn disconnect(&mut self) {
-        if self.alive {
-            info!("Set alive=false");
-            self.alive = false;
+        if !self.alive {
+            return;
+        }
+
+        info!("Set alive=false");
+        self.alive = false;
+
+        // No goodbyes to a peer that failed verification.
+        if self.dtls.state() == DtlsState::Failed {
+            self.close_state = CloseState::Drained;
+            return;
+        }
+
+        self.session.goodbye_all();
+
+        self.close_state = CloseState::Goodbye;
+    }
+
+    /// Remaining output after [`Rtc::disconnect()`]. `None` once drained, or while
+    /// waiting for the remote to complete the SCTP shutdown.
+    ///
+    /// The order is RTCP BYE, then the SCTP shutdown, and DTLS close_notify last, since
+    /// SCTP is carried over DTLS.
+    fn poll_closing(&mut self) -> Option<net::Transmit> {
+        loop {
+            let datagram = match self.close_state {
+                CloseState::Goodbye => {
+                    let x = self.session.poll_datagram(self.last_now);
+                    if x.is_none() {
+                        self.start_sctp_shutdown();
+                        continue;
+                    }
+                    x
+                }
+                #[cfg(feature = "sctp")]
+                CloseState::SctpShutdown(deadline) => {
+                    self.poll_sctp_shutdown();
+                    let x = self.dtls.poll_datagram();
+                    let done =
+                        !self.sctp.is_established() || self.dtls.state() == DtlsState::Closed;
+                    if x.is_none() && (done || self.last_now >= deadline) {
+                        if !done {
+                            debug!("SCTP shutdown not completed in time");
+                        }
+                        self.close_dtls();
+                        continue;
+                    }
+                    x
+                }
+                CloseState::Draining => {
+                    let x = self.dtls.poll_datagram();
+                    if x.is_none() {
+                        self.close_state = CloseState::Drained;
+                    }
+                    x
+                }
+                _ => None,
+            };
+
+            let contents = datagram?;
+
+            // Without a nominated pair, there's nowhere to send the remaining output.
+            let Some(send) = &self.send_addr else {
+                continue;
+            };
+
+            return Some(net::Transmit {
+                proto: send.proto,
+                source: send.source,
+                destination: send.destination,
+                contents,
+                segment_size: None,
+            });
+        }
+    }
+
+    fn start_sctp_shutdown(&mut self) {
+        #[cfg(feature = "sctp")]
+        if self.sctp.is_established() {
+            self.sctp.shutdown();
+            let deadline = self.last_now + SCTP_SHUTDOWN_TIMEOUT;
+            self.close_state = CloseState::SctpShutdown(deadline);
+            return;
+        }
+
+        self.close_dtls();
+    }
+
+    /// Move SCTP packets both ways between DTLS and the association being shut down.
+    #[cfg(feature = "sctp")]
+    fn poll_sctp_shutdown(&mut self) {
+        while let Some(e) = self.dtls.poll_event() {
+            match e {
+                DtlsEvent::Data(v) => self.sctp.handle_input(self.last_now, &v),
+                DtlsEvent::Closed => self.dtls.set_remote_closed(),
+                _ => {}
+            }
+        }
+
+        while let Some(e) = self.sctp.poll() {
+            let SctpEvent::Transmit { packets } = e else {
+                continue;
+            };
+            for p in packets {
+                if let Err(e) = self.dtls.handle_input(&p) {
+                    debug!("Drop SCTP on close: {:?}", e);
+                }
+            }
+        }
+    }
+
+    fn close_dtls(&mut self) {
+        if let Err(e) = self.dtls.close() {
+            debug!("DTLS close failed: {:?}", e);
+        }
+        self.close_state = CloseState::Draining;
+    }
+
+    /// Whether input is still handled after [`Rtc::disconnect()`].
+    fn accepts_input(&self) -> bool {
+        #[cfg(feature = "sctp")]
+        if matches!(self.close_state, CloseState::SctpShutdown(_)) {
+            return true;
+        }
+        self.alive
+    }
+
+    /// Input while closing, which only drives the SCTP shutdown.
+    #[cfg(feature = "sctp")]
+    fn handle_closing_input(&mut self, input: Input) -> Result<(), RtcError> {
+        let now = input.timestamp();
+        self.last_now = now;
+
+        if let Input::Receive(_, r) = input {
+            if let DatagramRecvInner::Dtls(v) = r.contents.inner {
+                if self.is_verified_remote_addr(r.source) {
+                    self.dtls.handle_receive(v)?;
+                }
+            }
         }
+
+        self.sctp.handle_timeout(now);
+
+        Ok(())
     }
 
     /// Add a local ICE candidate. Local candidates are socket addresses the `Rtc` instance
@@ -1222,42 +1947,217 @@ impl Rtc {
         self.ice.state().is_connected() && self.dtls.is_connected()
     }
 
-    /// Make changes to the Rtc session via SDP.
-    ///
-    /// ```no_run
-    /// # use str0m::Rtc;
-    /// # use str0m::media::{MediaKind, Direction};
-    /// # use str0m::change::SdpAnswer;
-    /// let mut rtc = Rtc::new();
-    ///
-    /// let mut changes = rtc.sdp_api();
-    /// let mid_audio = changes.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
-    /// let mid_video = changes.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    /// The state of the DTLS transport.
     ///
-    /// let (offer, pending) = changes.apply().unwrap();
-    /// let json = serde_json::to_vec(&offer).unwrap();
+    /// Changes are also emitted as [`Event::DtlsStateChange`].
     ///
-    /// // Send json OFFER to remote peer. Receive an answer back.
-    /// let answer: SdpAnswer = todo!();
+    /// ```
+    /// # use str0m::{Rtc, DtlsState};
+    /// let mut rtc = Rtc::new();
+    /// assert_eq!(rtc.dtls_state(), DtlsState::New);
     ///
-    /// rtc.sdp_api().accept_answer(pending, answer).unwrap();
+    /// rtc.disconnect();
+    /// assert_eq!(rtc.dtls_state(), DtlsState::Closed);
     /// ```
-    pub fn sdp_api(&mut self) -> SdpApi {
-        SdpApi::new(self)
+    pub fn dtls_state(&self) -> DtlsState {
+        let state = self.dtls.state();
+        if !self.alive && state != DtlsState::Failed {
+            DtlsState::Closed
+        } else {
+            state
+        }
     }
 
-    /// Makes direct changes to the Rtc session.
+    /// The overall connection state, combined from ICE and DTLS.
     ///
-    /// This is a low level API. For "normal" use via SDP, see [`Rtc::sdp_api()`].
-    pub fn direct_api(&mut self) -> DirectApi {
-        DirectApi::new(self)
-    }
-
-    /// Send outgoing media data (samples) or request keyframes.
+    /// Changes are also emitted as [`Event::ConnectionStateChange`].
     ///
-    /// Returns `None` if the direction isn't sending (`sendrecv` or `sendonly`).
+    /// ```
+    /// # use str0m::{Rtc, ConnectionState};
+    /// let mut rtc = Rtc::new();
+    /// assert_eq!(rtc.connection_state(), ConnectionState::New);
     ///
-    /// ```no_run
+    /// rtc.disconnect();
+    /// assert_eq!(rtc.connection_state(), ConnectionState::Closed);
+    /// ```
+    pub fn connection_state(&self) -> ConnectionState {
+        let ice = self.ice.state();
+        let dtls = self.dtls_state();
+
+        if dtls == DtlsState::Failed {
+            ConnectionState::Failed
+        } else if !self.alive || dtls == DtlsState::Closed {
+            ConnectionState::Closed
+        } else if ice.is_disconnected() {
+            ConnectionState::Disconnected
+        } else if ice.is_connected() && dtls.is_connected() {
+            ConnectionState::Connected
+        } else if ice == IceConnectionState::New && dtls == DtlsState::New {
+            ConnectionState::New
+        } else {
+            ConnectionState::Connecting
+        }
+    }
+
+    /// Event for a changed DTLS or connection state since the last one emitted.
+    fn poll_state_change(&mut self) -> Option<Event> {
+        let dtls = self.dtls_state();
+        if dtls != self.last_dtls_state {
+            self.last_dtls_state = dtls;
+            return Some(Event::DtlsStateChange(dtls));
+        }
+
+        let connection = self.connection_state();
+        if connection != self.last_connection_state {
+            self.last_connection_state = connection;
+            return Some(Event::ConnectionStateChange(connection));
+        }
+
+        None
+    }
+
+    /// The local DTLS certificate in use.
+    ///
+    /// Use [`DtlsCert::expires()`] to find out how long the certificate is valid.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let rtc = Rtc::new();
+    ///
+    /// let cert = rtc.dtls_cert();
+    /// assert!(cert.created() < cert.expires());
+    /// ```
+    pub fn dtls_cert(&self) -> &DtlsCert {
+        self.dtls.cert()
+    }
+
+    /// Replace the local DTLS certificate with a newly generated one.
+    ///
+    /// Before the DTLS handshake has started, i.e. before the first SDP negotiation or
+    /// [`DirectApi::start_dtls()`], the new certificate is used straight away. After,
+    /// it takes effect with the next SDP negotiation, which signals the new fingerprint
+    /// and renegotiates DTLS. This is typically done along with an ICE restart,
+    /// see [`SdpApi::ice_restart()`].
+    ///
+    /// Emits [`Event::DtlsCertRegenerated`] with the new fingerprint.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let before = rtc.direct_api().local_dtls_fingerprint();
+    /// let after = rtc.regenerate_dtls_cert().unwrap();
+    ///
+    /// assert_ne!(before, after);
+    /// ```
+    #[cfg(feature = "openssl")]
+    pub fn regenerate_dtls_cert(&mut self) -> Result<Fingerprint, RtcError> {
+        let old = self.dtls.cert();
+        let lifetime = old
+            .expires()
+            .duration_since(old.created())
+            .unwrap_or(Duration::ZERO);
+
+        let cert = DtlsCert::new_openssl_with_lifetime(lifetime);
+        if self.dtls.set_cert(cert)? {
+            self.dtls_cert_changed();
+        }
+
+        self.dtls_cert_regenerated = true;
+
+        Ok(self.dtls.local_fingerprint().clone())
+    }
+
+    /// Renegotiate DTLS, taking a regenerated certificate into use.
+    pub(crate) fn restart_dtls(&mut self) -> Result<(), RtcError> {
+        let new_cert = self.dtls.has_next_cert();
+
+        self.dtls.restart()?;
+
+        if new_cert {
+            self.dtls_cert_changed();
+        }
+
+        Ok(())
+    }
+
+    fn dtls_cert_changed(&mut self) {
+        self.dtls_cert_created_at = None;
+        self.dtls_cert_warned = false;
+        self.dtls_cert_expiring = false;
+    }
+
+    /// The time to warn about the local DTLS certificate expiring.
+    fn dtls_cert_warn_at(&self) -> Option<Instant> {
+        if self.dtls_cert_warned {
+            return None;
+        }
+        let warning = self.dtls_cert_expiry_warning?;
+        let created_at = self.dtls_cert_created_at?;
+
+        let cert = self.dtls.cert();
+        let lifetime = cert.expires().duration_since(cert.created()).ok()?;
+
+        Some(created_at + lifetime.saturating_sub(warning))
+    }
+
+    /// Map the creation time of the certificate in use to an Instant.
+    fn init_dtls_cert_created_at(&mut self, now: Instant) {
+        if self.dtls_cert_created_at.is_some() {
+            return;
+        }
+
+        // The anchor is set by the first handle_input/poll_output.
+        let Some(anchor) = self.time_anchor else {
+            return;
+        };
+
+        // Certificates are typically created just before use, but one passed in
+        // via RtcConfig might be older.
+        let age = anchor
+            .system_time(now)
+            .duration_since(self.dtls.cert().created())
+            .unwrap_or_default();
+
+        self.dtls_cert_created_at = Some(now.checked_sub(age).unwrap_or(now));
+    }
+
+    /// Make changes to the Rtc session via SDP.
+    ///
+    /// ```no_run
+    /// # use str0m::Rtc;
+    /// # use str0m::media::{MediaKind, Direction};
+    /// # use str0m::change::SdpAnswer;
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let mut changes = rtc.sdp_api();
+    /// let mid_audio = changes.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+    /// let mid_video = changes.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    ///
+    /// let (offer, pending) = changes.apply().unwrap();
+    /// let json = serde_json::to_vec(&offer).unwrap();
+    ///
+    /// // Send json OFFER to remote peer. Receive an answer back.
+    /// let answer: SdpAnswer = todo!();
+    ///
+    /// rtc.sdp_api().accept_answer(pending, answer).unwrap();
+    /// ```
+    pub fn sdp_api(&mut self) -> SdpApi {
+        SdpApi::new(self)
+    }
+
+    /// Makes direct changes to the Rtc session.
+    ///
+    /// This is a low level API. For "normal" use via SDP, see [`Rtc::sdp_api()`].
+    pub fn direct_api(&mut self) -> DirectApi {
+        DirectApi::new(self)
+    }
+
+    /// Send outgoing media data (samples) or request keyframes.
+    ///
+    /// Returns `None` if the direction isn't sending (`sendrecv` or `sendonly`).
+    ///
+    /// ```no_run
     /// # use str0m::Rtc;
     /// # use str0m::media::{MediaData, Mid};
     /// # use str0m::format::PayloadParams;
@@ -1280,6 +2180,7 @@ impl Rtc {
     ///
     /// This is a sample level API: For RTP level see [`DirectApi::stream_tx()`] and [`DirectApi::stream_rx()`].
     ///
+    #[cfg(feature = "sample-api")]
     pub fn writer(&mut self, mid: Mid) -> Option<Writer> {
         if self.session.rtp_mode {
             panic!("In rtp_mode use direct_api().stream_tx().write_rtp()");
@@ -1297,6 +2198,16 @@ impl Rtc {
         self.session.media_by_mid(mid)
     }
 
+    /// All currently configured media, in the order of the m-lines.
+    ///
+    /// A session can have any number of media of the same [`MediaKind`][media::MediaKind],
+    /// for instance camera and screenshare video, each identified by its own [`Mid`].
+    ///
+    /// Read only access. Changes are made via [`Rtc::sdp_api()`] or [`Rtc::direct_api()`].
+    pub fn medias(&self) -> impl Iterator<Item = &Media> {
+        self.session.medias().iter()
+    }
+
     fn init_dtls(&mut self, active: bool) -> Result<(), RtcError> {
         if self.dtls.is_inited() {
             return Ok(());
@@ -1312,6 +2223,7 @@ impl Rtc {
         Ok(())
     }
 
+    #[cfg(feature = "sctp")]
     fn init_sctp(&mut self, client: bool) {
         // If we got an m=application line, ensure we have negotiated the
         // SCTP association with the other side.
@@ -1348,17 +2260,28 @@ impl Rtc {
     ///
     /// See [`Rtc`] instance documentation for how this is expected to be used in a loop.
     pub fn poll_output(&mut self) -> Result<Output, RtcError> {
+        if let Some(HeldOutput(o)) = self.held_output.take() {
+            return o;
+        }
+
+        let _guard = self.span().entered();
+        let _time = self.time_anchor.map(TimeAnchor::enter);
         let o = self.do_poll_output()?;
 
         match &o {
             Output::Event(e) => match e {
-                Event::ChannelData(_) | Event::MediaData(_) | Event::RtpPacket(_) => {
-                    trace!("{:?}", e)
-                }
+                #[cfg(feature = "sctp")]
+                Event::ChannelData(_) => trace!("{:?}", e),
+                #[cfg(feature = "sample-api")]
+                Event::MediaData(_) => trace!("{:?}", e),
+                Event::RtpPacket(_) => trace!("{:?}", e),
                 _ => debug!("{:?}", e),
             },
             Output::Transmit(t) => {
                 self.peer_bytes_tx += t.contents.len() as u64;
+                if let Some(a) = &mut self.send_addr {
+                    a.count_tx(t);
+                }
                 trace!("OUT {:?}", t)
             }
             Output::Timeout(_t) => {}
@@ -1367,8 +2290,127 @@ impl Rtc {
         Ok(o)
     }
 
+    /// Poll the `Rtc` instance for a batch of transmits to the same destination.
+    ///
+    /// This is an alternative to [`Rtc::poll_output()`] for servers that send with
+    /// `sendmmsg` or UDP GSO, where several datagrams to the same socket can be sent
+    /// in one syscall.
+    ///
+    /// [`Output::Transmit`] are appended to `batch` as long as they share protocol, source
+    /// and destination with the first transmit in the batch. The function returns:
+    ///
+    /// * `Ok(None)` when `batch` holds `max` transmits. Send the batch and poll again.
+    /// * `Ok(Some(output))` for any other output. This is either an event, a timeout, or
+    ///   a transmit to another destination, which should be handled _after_ sending the
+    ///   current batch.
+    ///
+    /// ```no_run
+    /// # use str0m::{Rtc, Output};
+    /// let mut rtc = Rtc::new();
+    /// let mut batch = Vec::new();
+    ///
+    /// loop {
+    ///     let output = rtc.poll_output_batch(&mut batch, 16).unwrap();
+    ///
+    ///     // sendmmsg() all datagrams in the batch.
+    ///     batch.clear();
+    ///
+    ///     match output {
+    ///         None => continue,
+    ///         Some(Output::Timeout(_)) => break,
+    ///         Some(o) => {} // Deal with transmit or event.
+    ///     }
+    /// }
+    /// ```
+    pub fn poll_output_batch(
+        &mut self,
+        batch: &mut Vec<net::Transmit>,
+        max: usize,
+    ) -> Result<Option<Output>, RtcError> {
+        while batch.len() < max.max(1) {
+            let o = self.poll_output()?;
+
+            let Output::Transmit(t) = o else {
+                return Ok(Some(o));
+            };
+
+            let same = batch.first().map(|f| {
+                f.proto == t.proto && f.source == t.source && f.destination == t.destination
+            });
+
+            if same == Some(false) {
+                return Ok(Some(Output::Transmit(t)));
+            }
+
+            batch.push(t);
+        }
+
+        Ok(None)
+    }
+
+    /// Poll the `Rtc` instance for output, coalescing a burst of transmits for UDP GSO.
+    ///
+    /// This works like [`Rtc::poll_output()`], but consecutive UDP transmits to the same
+    /// destination, such as packets released together by the pacer, are merged into one
+    /// [`Transmit`][net::Transmit] with [`segment_size`][net::Transmit::segment_size] set.
+    /// The contents are then the datagrams back to back, all of the same size except the
+    /// last which may be shorter. This matches what UDP GSO (`UDP_SEGMENT`) expects.
+    ///
+    /// A transmit that can't be merged is returned on its own, without `segment_size`.
+    /// At most `max_segments` datagrams are merged (Linux allows 64), and never more
+    /// than fit in a single UDP send. An error hit while merging is returned by the
+    /// next poll, after the datagrams polled before it.
+    ///
+    /// ```no_run
+    /// # use str0m::{Rtc, Output};
+    /// let mut rtc = Rtc::new();
+    ///
+    /// loop {
+    ///     match rtc.poll_output_gso(64).unwrap() {
+    ///         Output::Transmit(t) => {
+    ///             if let Some(size) = t.segment_size {
+    ///                 // sendmsg() with UDP_SEGMENT set to size.
+    ///             } else {
+    ///                 // send() a single datagram.
+    ///             }
+    ///         }
+    ///         Output::Timeout(_) => break,
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn poll_output_gso(&mut self, max_segments: usize) -> Result<Output, RtcError> {
+        let o = self.poll_output()?;
+
+        let Output::Transmit(first) = o else {
+            return Ok(o);
+        };
+
+        let (o, held) = coalesce_gso(first, max_segments, || self.poll_output());
+        self.held_output = held.map(HeldOutput);
+
+        Ok(o)
+    }
+
     fn do_poll_output(&mut self) -> Result<Output, RtcError> {
         if !self.alive {
+            if let Some(t) = self.poll_closing() {
+                return Ok(Output::Transmit(t));
+            }
+            // The final state changes are still emitted.
+            if let Some(ev) = self.poll_state_change() {
+                return Ok(Output::Event(ev));
+            }
+            if self.close_state == CloseState::Drained {
+                self.close_state = CloseState::Closed;
+                return Ok(Output::Event(Event::Closed));
+            }
+            #[cfg(feature = "sctp")]
+            if let CloseState::SctpShutdown(deadline) = self.close_state {
+                let next = self.sctp.poll_timeout().unwrap_or(deadline).min(deadline);
+                self.last_timeout_reason = Reason::Sctp;
+                return Ok(Output::Timeout(next));
+            }
             self.last_timeout_reason = Reason::NotHappening;
             return Ok(Output::Timeout(not_happening()));
         }
@@ -1397,10 +2439,22 @@ impl Rtc {
                         "ICE nominated send from: {:?} to: {:?} with protocol {:?}",
                         source, destination, proto,
                     );
+                    if let Some(prev) = self.send_addr.as_ref() {
+                        if prev.destination != destination {
+                            info!(
+                                "Remote address changed from {} to {}",
+                                prev.destination, destination
+                            );
+                        }
+                    }
                     self.send_addr = Some(SendAddr {
                         proto,
                         source,
                         destination,
+                        packets_tx: 0,
+                        bytes_tx: 0,
+                        packets_rx: 0,
+                        bytes_rx: 0,
                     });
                 }
             }
@@ -1411,31 +2465,51 @@ impl Rtc {
         while let Some(e) = self.dtls.poll_event() {
             match e {
                 DtlsEvent::Connected => {
-                    debug!("DTLS connected");
+                    debug!(active = self.dtls.is_active(), "DTLS connected");
                     dtls_connected = true;
                 }
                 DtlsEvent::SrtpKeyingMaterial(mat, srtp_profile) => {
-                    info!(
-                        "DTLS set SRTP keying material and profile: {}",
-                        srtp_profile
-                    );
+                    info!(%srtp_profile, "DTLS set SRTP keying material and profile");
                     let active = self.dtls.is_active().expect("DTLS must be inited by now");
                     self.session.set_keying_material(mat, srtp_profile, active);
                 }
                 DtlsEvent::RemoteFingerprint(v1) => {
-                    debug!("DTLS verify remote fingerprint");
-                    if let Some(v2) = &self.remote_fingerprint {
-                        if v1 != *v2 {
+                    debug!(fingerprint = %v1, "DTLS verify remote fingerprint");
+                    if let Some(v2) = self.remote_fingerprint.clone() {
+                        // Verify using whichever hash function the remote signaled.
+                        let Some(hash) = v2.hash() else {
+                            self.dtls.set_failed();
+                            self.disconnect();
+                            return Err(RtcError::RemoteSdp(format!(
+                                "unsupported fingerprint hash: {}",
+                                v2.hash_func
+                            )));
+                        };
+                        let v1 = if v1.hash() == Some(hash) {
+                            Some(v1)
+                        } else {
+                            self.dtls.remote_fingerprint_with(hash)
+                        };
+                        if v1.map(|v| v.bytes) != Some(v2.bytes) {
+                            self.dtls.set_failed();
                             self.disconnect();
                             return Err(RtcError::RemoteSdp("remote fingerprint no match".into()));
                         }
                     } else {
+                        self.dtls.set_failed();
                         self.disconnect();
                         return Err(RtcError::RemoteSdp("no a=fingerprint before dtls".into()));
                     }
                 }
                 DtlsEvent::Data(v) => {
+                    #[cfg(feature = "sctp")]
                     self.sctp.handle_input(self.last_now, &v);
+                    #[cfg(not(feature = "sctp"))]
+                    trace!("Drop DTLS data without SCTP: {}", v.len());
+                }
+                DtlsEvent::Closed => {
+                    // Applied once the SCTP data that came before it is polled.
+                    self.remote_close_pending = true;
                 }
             }
         }
@@ -1444,6 +2518,23 @@ impl Rtc {
             return Ok(Output::Event(Event::Connected));
         }
 
+        if let Some(ev) = self.poll_state_change() {
+            return Ok(Output::Event(ev));
+        }
+
+        if self.dtls_cert_regenerated {
+            self.dtls_cert_regenerated = false;
+            let fingerprint = self.dtls.local_fingerprint().clone();
+            return Ok(Output::Event(Event::DtlsCertRegenerated(fingerprint)));
+        }
+
+        if self.dtls_cert_expiring {
+            self.dtls_cert_expiring = false;
+            let expires = self.dtls.cert().expires();
+            return Ok(Output::Event(Event::DtlsCertExpiring(expires)));
+        }
+
+        #[cfg(feature = "sctp")]
         while let Some(e) = self.sctp.poll() {
             match e {
                 SctpEvent::Transmit { mut packets } => {
@@ -1478,6 +2569,12 @@ impl Rtc {
                     self.chan.remove_channel(id);
                     return Ok(Output::Event(Event::ChannelClose(id)));
                 }
+                SctpEvent::BufferedAmountLow { id } => {
+                    let Some(id) = self.chan.channel_id_by_stream_id(id) else {
+                        continue;
+                    };
+                    return Ok(Output::Event(Event::ChannelBufferedAmountLow(id)));
+                }
                 SctpEvent::Data { id, binary, data } => {
                     let Some(id) = self.chan.channel_id_by_stream_id(id) else {
                         warn!("Drop ChannelData event for id: {:?}", id);
@@ -1489,6 +2586,14 @@ impl Rtc {
             }
         }
 
+        if self.remote_close_pending {
+            self.remote_close_pending = false;
+            self.dtls.set_remote_closed();
+            if let Some(ev) = self.poll_state_change() {
+                return Ok(Output::Event(ev));
+            }
+        }
+
         if let Some(ev) = self.session.poll_event() {
             return Ok(Output::Event(ev));
         }
@@ -1522,6 +2627,7 @@ impl Rtc {
                     source: send.source,
                     destination: send.destination,
                     contents,
+                    segment_size: None,
                 };
                 return Ok(Output::Transmit(t));
             }
@@ -1531,11 +2637,16 @@ impl Rtc {
 
         let time_and_reason = (None, Reason::NotHappening)
             .soonest((self.ice.poll_timeout(), Reason::Ice))
+            .soonest((self.dtls.poll_timeout(), Reason::Dtls))
+            .soonest((self.dtls_cert_warn_at(), Reason::DtlsCert))
             .soonest(self.session.poll_timeout())
-            .soonest((self.sctp.poll_timeout(), Reason::Sctp))
-            .soonest((self.chan.poll_timeout(&self.sctp), Reason::Channel))
             .soonest((stats.and_then(|s| s.poll_timeout()), Reason::Stats));
 
+        #[cfg(feature = "sctp")]
+        let time_and_reason = time_and_reason
+            .soonest((self.sctp.poll_timeout(), Reason::Sctp))
+            .soonest((self.chan.poll_timeout(&self.sctp), Reason::Channel));
+
         // trace!("poll_output timeout reason: {}", time_and_reason.1);
 
         let time = time_and_reason.0.unwrap_or_else(not_happening);
@@ -1620,9 +2731,10 @@ impl Rtc {
         }
 
         // STUN can use the ufrag/password to identify that a message belongs
-        // to this Rtc instance.
+        // to this Rtc instance. Binding requests with our ufrag that fail
+        // authentication are also ours, since we answer them with an error.
         if let DatagramRecvInner::Stun(v) = &r.contents.inner {
-            return self.ice.accepts_message(v);
+            return self.ice.accepts_message(v) || self.ice.rejects_message(v);
         }
 
         // Slow path: Occasionally, traffic comes in on a socket address corresponding
@@ -1635,6 +2747,11 @@ impl Rtc {
         false
     }
 
+    fn is_verified_remote_addr(&self, addr: SocketAddr) -> bool {
+        let is_send_addr = self.send_addr.as_ref().map(|a| a.destination) == Some(addr);
+        is_send_addr || self.ice.has_viable_remote_candidate(addr)
+    }
+
     /// Provide input to this `Rtc` instance. Input is either a [`Input::Timeout`] for some
     /// time that was previously obtained from [`Rtc::poll_output()`], or [`Input::Receive`]
     /// for network data.
@@ -1644,6 +2761,11 @@ impl Rtc {
     /// of receiving the network data as precise as possible. This time is used to calculate
     /// things like jitter and bandwidth.
     ///
+    /// str0m never reads the clock itself. The only exception is the system wallclock, which is
+    /// read once per `Rtc`, together with the first `Instant` provided, to map `Instant` to NTP
+    /// time for sender reports. Given the same input, the output is the same, which means tests
+    /// and simulations can run faster than real time.
+    ///
     /// It's always okay to call [`Rtc::handle_input()`] with a timeout, also before the
     /// time obtained via [`Rtc::poll_output()`].
     ///
@@ -1660,10 +2782,17 @@ impl Rtc {
     /// }
     /// ```
     pub fn handle_input(&mut self, input: Input) -> Result<(), RtcError> {
-        if !self.alive {
+        if !self.accepts_input() {
             return Ok(());
         }
 
+        let _guard = self.span().entered();
+
+        #[cfg(feature = "sctp")]
+        if !self.alive {
+            return self.handle_closing_input(input);
+        }
+
         match input {
             Input::Timeout(now) => self.do_handle_timeout(now)?,
             Input::Receive(now, r) => {
@@ -1674,26 +2803,81 @@ impl Rtc {
         Ok(())
     }
 
-    fn init_time(&mut self, now: Instant) {
-        // The operation is somewhat expensive, hence we only do it once.
-        if !self.need_init_time {
-            return;
+    /// Provide several inputs in one call.
+    ///
+    /// This is for servers that receive with `recvmmsg` or UDP GRO. The result is the same
+    /// as calling [`Rtc::handle_input()`] for each input, but the timeout handling is only
+    /// done once, for the latest time in the inputs.
+    ///
+    /// ```no_run
+    /// # use str0m::{Rtc, Input};
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let inputs: Vec<Input> = todo!(); // recvmmsg() several datagrams.
+    /// rtc.handle_inputs(inputs).unwrap();
+    /// ```
+    pub fn handle_inputs<'a>(
+        &mut self,
+        inputs: impl IntoIterator<Item = Input<'a>>,
+    ) -> Result<(), RtcError> {
+        if !self.accepts_input() {
+            return Ok(());
         }
 
-        // We assume this first "now" is a time 0 start point for calculating ntp/unix time offsets.
-        // This initializes the conversion of Instant -> NTP/Unix time.
-        let _ = now.to_unix_duration();
+        let _guard = self.span().entered();
+
+        #[cfg(feature = "sctp")]
+        if !self.alive {
+            for input in inputs {
+                self.handle_closing_input(input)?;
+            }
+            return Ok(());
+        }
+
+        let mut latest: Option<Instant> = None;
+
+        for input in inputs {
+            let now = input.timestamp();
+            if let Input::Receive(_, r) = input {
+                self.do_handle_receive(now, r)?;
+            }
+            latest = Some(latest.map(|l| l.max(now)).unwrap_or(now));
+        }
 
-        self.need_init_time = false;
+        if let Some(now) = latest {
+            self.do_handle_timeout(now)?;
+        }
+
+        Ok(())
+    }
+
+    fn enter_time(&mut self, now: Instant) -> TimeAnchorGuard {
+        // We assume this first "now" is a time 0 start point for calculating ntp/unix time offsets.
+        // The operation is somewhat expensive, hence we only do it once.
+        self.time_anchor
+            .get_or_insert_with(|| TimeAnchor::new(now))
+            .enter()
     }
 
     fn do_handle_timeout(&mut self, now: Instant) -> Result<(), RtcError> {
-        self.init_time(now);
+        let _time = self.enter_time(now);
 
         self.last_now = now;
         self.ice.handle_timeout(now);
-        self.sctp.handle_timeout(now);
-        self.chan.handle_timeout(now, &mut self.sctp);
+        self.dtls.handle_timeout(now);
+
+        self.init_dtls_cert_created_at(now);
+        if self.dtls_cert_warn_at().map(|t| now >= t).unwrap_or(false) {
+            warn!("DTLS certificate expires soon");
+            self.dtls_cert_warned = true;
+            self.dtls_cert_expiring = true;
+        }
+
+        #[cfg(feature = "sctp")]
+        {
+            self.sctp.handle_timeout(now);
+            self.chan.handle_timeout(now, &mut self.sctp);
+        }
         self.session.handle_timeout(now)?;
 
         if let Some(stats) = &mut self.stats {
@@ -1701,7 +2885,8 @@ impl Rtc {
                 let mut snapshot = StatsSnapshot::new(now);
                 snapshot.peer_rx = self.peer_bytes_rx;
                 snapshot.peer_tx = self.peer_bytes_tx;
-                self.session.visit_stats(now, &mut snapshot);
+                snapshot.datagrams_rx = self.datagrams_rx.clone();
+                self.session.visit_stats(now, &mut snapshot, true);
                 stats.do_handle_timeout(&mut snapshot);
             }
         }
@@ -1710,20 +2895,46 @@ impl Rtc {
     }
 
     fn do_handle_receive(&mut self, now: Instant, r: net::Receive) -> Result<(), RtcError> {
-        self.init_time(now);
+        let _time = self.enter_time(now);
 
         trace!("IN {:?}", r);
         self.last_now = now;
         use DatagramRecvInner::*;
 
+        let counts = &mut self.datagrams_rx;
+        match r.contents.inner {
+            Stun(_) => counts.stun += 1,
+            Dtls(_) => counts.dtls += 1,
+            Rtp(_) => counts.rtp += 1,
+            Rtcp(_) => counts.rtcp += 1,
+            Zrtp(_) => counts.zrtp += 1,
+            TurnChannel(_) => counts.turn_channel += 1,
+        }
+
         let bytes_rx = match r.contents.inner {
             // TODO: stun is already parsed (depacketized) here
             Stun(_) => 0,
             Dtls(v) | Rtp(v) | Rtcp(v) => v.len(),
+            Zrtp(_) | TurnChannel(_) => {
+                trace!("Drop datagram not for us: {:?}", r.contents);
+                return Ok(());
+            }
         };
 
+        // STUN is how addresses are verified. Everything else must come from a verified
+        // candidate pair, which also covers switching to a new pair (mobility).
+        if !matches!(r.contents.inner, Stun(_)) && !self.is_verified_remote_addr(r.source) {
+            debug!("Drop datagram from unverified address: {}", r.source);
+            self.datagrams_rx.unverified += 1;
+            return Ok(());
+        }
+
         self.peer_bytes_rx += bytes_rx as u64;
 
+        if let Some(a) = &mut self.send_addr {
+            a.count_rx(&r, bytes_rx);
+        }
+
         match r.contents.inner {
             Stun(stun) => {
                 let packet = io::StunPacket {
@@ -1733,10 +2944,21 @@ impl Rtc {
                     message: stun,
                 };
                 self.ice.handle_packet(now, packet);
+
+                // A binding response on the nominated pair is an RTT sample.
+                if let Some(pair) = self.ice.nominated_send_pair() {
+                    if pair.last_response_time() == Some(now) {
+                        if let Some(rtt) = pair.rtt() {
+                            self.session.add_rtt_sample(now, rtt);
+                        }
+                    }
+                }
             }
             Dtls(dtls) => self.dtls.handle_receive(dtls)?,
             Rtp(rtp) => self.session.handle_rtp_receive(now, rtp),
             Rtcp(rtcp) => self.session.handle_rtcp_receive(now, rtcp),
+            // Dropped above.
+            Zrtp(_) | TurnChannel(_) => {}
         }
 
         Ok(())
@@ -1757,6 +2979,7 @@ impl Rtc {
     /// let channel = rtc.channel(cid).unwrap();
     /// // TODO write data channel data.
     /// ```
+    #[cfg(feature = "sctp")]
     pub fn channel(&mut self, id: ChannelId) -> Option<Channel<'_>> {
         if !self.alive {
             return None;
@@ -1771,29 +2994,235 @@ impl Rtc {
         Some(Channel::new(sctp_stream_id, self))
     }
 
-    /// Configure the Bandwidth Estimate (BWE) subsystem.
+    /// Hand back the data of a [`ChannelData`] to be reused for incoming data.
     ///
-    /// Only relevant if BWE was enabled in the [`RtcConfig::enable_bwe()`]
-    pub fn bwe(&mut self) -> Bwe {
-        Bwe(self)
-    }
-
-    fn is_correct_change_id(&self, change_id: usize) -> bool {
-        self.change_counter == change_id + 1
+    /// This is an optimization for applications receiving many data channel messages.
+    /// Recycled buffers are used for subsequent [`Event::ChannelData`], which avoids
+    /// allocating a new buffer per message.
+    ///
+    /// ```no_run
+    /// # use str0m::{Rtc, Event};
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let event: Event = todo!(); // obtained via poll_output()
+    /// if let Event::ChannelData(data) = event {
+    ///     // use the data...
+    ///     rtc.recycle_channel_data(data);
+    /// }
+    /// ```
+    #[cfg(feature = "sctp")]
+    pub fn recycle_channel_data(&mut self, data: ChannelData) {
+        self.sctp.recycle(data.data);
     }
 
-    fn next_change_id(&mut self) -> usize {
-        let n = self.change_counter;
-        self.change_counter += 1;
-        n
+    /// Hand back the contents of a [`net::Transmit`] to be reused for outgoing data.
+    ///
+    /// This is an optimization for servers sending a lot of media. Recycled buffers are
+    /// used for subsequent outgoing RTP and RTCP, which avoids allocating a new buffer per
+    /// packet. See [`Rtc::buffer_pool_stats()`].
+    /// It's also possible to provide buffers allocated elsewhere via
+    /// `DatagramSend::from(Vec<u8>)`. Buffers with a capacity smaller than the MTU
+    /// are ignored.
+    ///
+    /// ```no_run
+    /// # use str0m::{Rtc, Output};
+    /// let mut rtc = Rtc::new();
+    ///
+    /// if let Output::Transmit(t) = rtc.poll_output().unwrap() {
+    ///     // send the data...
+    ///     rtc.recycle_transmit(t.contents);
+    /// }
+    /// ```
+    pub fn recycle_transmit(&mut self, contents: net::DatagramSend) {
+        self.session.recycle_send_buffer(contents.into());
     }
 
-    /// The codec configs for sending/receiving data.
+    /// Poll for the next copy of an unencrypted RTP or RTCP packet.
     ///
-    /// The configurations can be set with [`RtcConfig`] before setting up the session, and they
-    /// might be further updated by SDP negotiation.
-    pub fn codec_config(&self) -> &CodecConfig {
-        &self.session.codec_config
+    /// Only produces packets when enabled via [`RtcConfig::enable_packet_tap()`].
+    /// The packets are in the order they were processed.
+    ///
+    /// ```no_run
+    /// # use str0m::Rtc;
+    /// let mut rtc = Rtc::builder().enable_packet_tap(true).build();
+    ///
+    /// // ... drive the instance with handle_input() and poll_output().
+    ///
+    /// while let Some(packet) = rtc.poll_tapped_packet() {
+    ///     // write packet.data to a pcap file...
+    /// }
+    /// ```
+    pub fn poll_tapped_packet(&mut self) -> Option<TappedPacket> {
+        self.session.poll_tapped_packet()
+    }
+
+    /// Take a snapshot of the current statistics.
+    ///
+    /// This is an alternative to the periodic stats events enabled via
+    /// [`RtcConfig::set_stats_interval()`]. The loss fractions are calculated
+    /// since the previous stats event. Taking a snapshot doesn't start a new
+    /// interval, so it doesn't affect the loss in the stats events.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let stats = rtc.stats();
+    /// assert!(stats.ingress.is_empty());
+    /// assert!(stats.candidate_pair.is_none());
+    /// ```
+    pub fn stats(&mut self) -> RtcStats {
+        let now = self.last_now;
+
+        let mut snapshot = StatsSnapshot::new(now);
+        snapshot.peer_rx = self.peer_bytes_rx;
+        snapshot.peer_tx = self.peer_bytes_tx;
+        snapshot.datagrams_rx = self.datagrams_rx.clone();
+        self.session.visit_stats(now, &mut snapshot, false);
+
+        let pair = self.ice.nominated_send_pair();
+        let ice_stats = self.ice.stats();
+        let candidate_pair = self.send_addr.as_ref().map(|a| CandidatePairStats {
+            proto: a.proto,
+            local: a.source,
+            remote: a.destination,
+            state: self.ice.state(),
+            packets_sent: a.packets_tx,
+            bytes_sent: a.bytes_tx,
+            packets_received: a.packets_rx,
+            bytes_received: a.bytes_rx,
+            rtt: pair.and_then(|p| p.rtt()),
+            last_consent: pair.and_then(|p| p.last_response_time()),
+            binding_requests_sent: ice_stats.bind_request_sent,
+            binding_responses_received: ice_stats.bind_success_recv,
+            binding_requests_received: ice_stats.bind_request_recv,
+        });
+
+        #[cfg(feature = "bwe")]
+        let bwe = self.session.bwe_stats(now);
+        #[cfg(not(feature = "bwe"))]
+        let bwe = None;
+        let rtt = self.session.rtt_stats();
+
+        let stats = RtcStats::new(snapshot, candidate_pair, bwe, rtt);
+
+        #[cfg(feature = "sctp")]
+        let stats = RtcStats {
+            channels: self
+                .chan
+                .stream_ids()
+                .filter_map(|(id, sctp_stream_id)| self.sctp.channel_stats(sctp_stream_id, id))
+                .collect(),
+            ..stats
+        };
+
+        stats
+    }
+
+    /// Round trip time of the transport.
+    ///
+    /// The RTT is measured using STUN on the nominated candidate pair and RTCP
+    /// sender/receiver reports. `None` until there is a first measurement.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let rtc = Rtc::new();
+    ///
+    /// assert!(rtc.rtt().is_none());
+    /// ```
+    pub fn rtt(&self) -> Option<RttStats> {
+        self.session.rtt_stats()
+    }
+
+    /// Usage of the internal buffer pool for outgoing SRTP and SRTCP packets.
+    ///
+    /// The pool is refilled by [`Rtc::recycle_transmit()`]. A low hit rate means
+    /// outgoing packets allocate new buffers.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let rtc = Rtc::new();
+    ///
+    /// assert_eq!(rtc.buffer_pool_stats().hit_rate(), 0.0);
+    /// ```
+    pub fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.session.buffer_pool_stats()
+    }
+
+    /// Change the max size of outgoing UDP datagrams for this session.
+    ///
+    /// Use this when the path MTU changes, for instance after the selected ICE candidate pair
+    /// moves to a different network. The value is clamped to `576..=1500`.
+    ///
+    /// RTP packetization, padding and RTCP use the new size straight away. DTLS only picks it up
+    /// before the handshake has started, and SCTP only before the association is set up.
+    ///
+    /// See [`RtcConfig::set_mtu()`] to configure it up front.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let mut rtc = Rtc::new();
+    ///
+    /// rtc.set_mtu(1400);
+    /// assert_eq!(rtc.mtu(), 1400);
+    /// ```
+    pub fn set_mtu(&mut self, mtu: usize) {
+        let mtu = mtu.clamp(MIN_MTU, MAX_MTU);
+        self.session.set_mtu(mtu);
+        self.dtls.set_mtu(mtu);
+        #[cfg(feature = "sctp")]
+        self.sctp.set_mtu(mtu);
+    }
+
+    /// The current max size of outgoing UDP datagrams.
+    pub fn mtu(&self) -> usize {
+        self.session.mtu()
+    }
+
+    /// Change the interval between statistics events.
+    ///
+    /// This overrides [`RtcConfig::set_stats_interval()`]. `None` turns off the stats
+    /// events, and `Some` turns them on, the first events are emitted one interval later.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let mut rtc = Rtc::new();
+    ///
+    /// rtc.set_stats_interval(Some(Duration::from_secs(1)));
+    /// ```
+    pub fn set_stats_interval(&mut self, interval: Option<Duration>) {
+        match (interval, &mut self.stats) {
+            (None, _) => self.stats = None,
+            (Some(interval), Some(stats)) => stats.set_interval(interval),
+            (Some(interval), None) => self.stats = Some(Stats::new(interval)),
+        }
+    }
+
+    /// Configure the Bandwidth Estimate (BWE) subsystem.
+    ///
+    /// Only relevant if BWE was enabled in the [`RtcConfig::enable_bwe()`]
+    #[cfg(feature = "bwe")]
+    pub fn bwe(&mut self) -> Bwe {
+        Bwe(self)
+    }
+
+    fn is_correct_change_id(&self, change_id: usize) -> bool {
+        self.change_counter == change_id + 1
+    }
+
+    fn next_change_id(&mut self) -> usize {
+        let n = self.change_counter;
+        self.change_counter += 1;
+        n
+    }
+
+    /// The codec configs for sending/receiving data.
+    ///
+    /// The configurations can be set with [`RtcConfig`] before setting up the session, and they
+    /// might be further updated by SDP negotiation.
+    pub fn codec_config(&self) -> &CodecConfig {
+        &self.session.codec_config
     }
 }
 
@@ -1812,19 +3241,41 @@ impl Rtc {
 pub struct RtcConfig {
     local_ice_credentials: Option<IceCreds>,
     dtls_cert: Option<DtlsCert>,
+    dtls_cert_expiry_warning: Option<Duration>,
+    fingerprint_hash: FingerprintHash,
     fingerprint_verification: bool,
     ice_lite: bool,
     codec_config: CodecConfig,
     exts: ExtensionMap,
     stats_interval: Option<Duration>,
     /// Whether to use Bandwidth Estimation to discover the egress bandwidth.
+    #[cfg(feature = "bwe")]
     bwe_initial_bitrate: Option<Bitrate>,
+    #[cfg(feature = "bwe")]
+    bwe_bitrate_bounds: (Bitrate, Bitrate),
+    #[cfg(feature = "bwe")]
+    bwe_estimate_tolerance: f64,
     reordering_size_audio: usize,
     reordering_size_video: usize,
+    jitter_buffer_delay: Option<(Duration, Duration)>,
+    emit_only_decodable: bool,
     send_buffer_audio: usize,
     send_buffer_video: usize,
+    rtx_cache_max_bytes: usize,
+    resend_without_rtx: bool,
+    mtu: usize,
+    srtp_profiles: Vec<SrtpProfile>,
+    rtcp_interval_audio: Duration,
+    rtcp_interval_video: Duration,
+    rtcp_bandwidth: Option<RtcpBandwidth>,
+    keyframe_request_interval: Duration,
+    unknown_ssrc_policy: UnknownSsrcPolicy,
+    ice_timing_advance: Duration,
     rtp_mode: bool,
     enable_raw_packets: bool,
+    enable_dominant_speaker: bool,
+    enable_packet_tap: bool,
+    packet_observer: Option<Observer>,
 }
 
 impl RtcConfig {
@@ -1883,6 +3334,29 @@ impl RtcConfig {
         self
     }
 
+    /// How long before the local DTLS certificate expires to emit [`Event::DtlsCertExpiring`].
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to one day.
+    /// assert_eq!(config.dtls_cert_expiry_warning(), Some(Duration::from_secs(86_400)));
+    /// ```
+    pub fn dtls_cert_expiry_warning(&self) -> Option<Duration> {
+        self.dtls_cert_expiry_warning
+    }
+
+    /// Set how long before the local DTLS certificate expires to warn about it.
+    ///
+    /// Long lived sessions can use the [`Event::DtlsCertExpiring`] to regenerate the
+    /// certificate and set up the session again. `None` disables the warning.
+    pub fn set_dtls_cert_expiry_warning(mut self, warning: Option<Duration>) -> Self {
+        self.dtls_cert_expiry_warning = warning;
+        self
+    }
+
     /// Toggle ice lite. Ice lite is a mode for WebRTC servers with public IP address.
     /// An [`Rtc`] instance in ice lite mode will not make STUN binding requests, but only
     /// answer to requests from the remote peer.
@@ -1895,6 +3369,29 @@ impl RtcConfig {
         self
     }
 
+    /// Hash function used for the local DTLS fingerprint.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::change::FingerprintHash;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to sha-256.
+    /// assert_eq!(config.fingerprint_hash(), FingerprintHash::Sha256);
+    /// ```
+    pub fn fingerprint_hash(&self) -> FingerprintHash {
+        self.fingerprint_hash
+    }
+
+    /// Set the hash function used for the local DTLS fingerprint (`a=fingerprint`).
+    ///
+    /// The remote certificate is always verified with the hash function the remote
+    /// signaled, regardless of this setting.
+    pub fn set_fingerprint_hash(mut self, hash: FingerprintHash) -> Self {
+        self.fingerprint_hash = hash;
+        self
+    }
+
     /// Get fingerprint verification mode.
     ///
     /// ```
@@ -1958,6 +3455,22 @@ impl RtcConfig {
         self
     }
 
+    /// Enable G.711 audio codecs, PCMU and PCMA.
+    ///
+    /// Disabled by default.
+    pub fn enable_g711(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_g711(enabled);
+        self
+    }
+
+    /// Enable G.722 audio codec.
+    ///
+    /// Disabled by default.
+    pub fn enable_g722(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_g722(enabled);
+        self
+    }
+
     /// Enable VP8 video codec.
     ///
     /// Enabled by default.
@@ -1974,6 +3487,14 @@ impl RtcConfig {
         self
     }
 
+    /// Enable H265 video codec.
+    ///
+    /// Disabled by default.
+    pub fn enable_h265(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_h265(enabled);
+        self
+    }
+
     // TODO: AV1 depacketizer/packetizer.
     //
     // /// Enable AV1 video codec.
@@ -2039,7 +3560,8 @@ impl RtcConfig {
     ///
     /// None turns off the stats events.
     ///
-    /// This includes [`MediaEgressStats`], [`MediaIngressStats`], [`MediaEgressStats`]
+    /// This includes [`PeerStats`], [`MediaIngressStats`] and [`MediaEgressStats`].
+    /// The interval can also be changed later via [`Rtc::set_stats_interval()`].
     pub fn set_stats_interval(mut self, interval: Option<Duration>) -> Self {
         self.stats_interval = interval;
         self
@@ -2066,6 +3588,7 @@ impl RtcConfig {
     /// None disables the BWE. This is an estimation of the send bandwidth, not receive.
     ///
     /// This includes setting the initial estimate to start with.
+    #[cfg(feature = "bwe")]
     pub fn enable_bwe(mut self, initial_estimate: Option<Bitrate>) -> Self {
         self.bwe_initial_bitrate = initial_estimate;
 
@@ -2081,10 +3604,66 @@ impl RtcConfig {
     /// // Defaults to None - BWE off.
     /// assert_eq!(config.bwe_initial_bitrate(), None);
     /// ```
+    #[cfg(feature = "bwe")]
     pub fn bwe_initial_bitrate(&self) -> Option<Bitrate> {
         self.bwe_initial_bitrate
     }
 
+    /// Sets the minimum and maximum bitrate the BWE is allowed to estimate.
+    ///
+    /// The bounds can be changed at runtime using [`Bwe::set_bitrate_bounds()`][crate::bwe::Bwe::set_bitrate_bounds].
+    ///
+    /// Defaults to 40kbit/s and 10Gbit/s.
+    #[cfg(feature = "bwe")]
+    pub fn set_bwe_bitrate_bounds(mut self, min: Bitrate, max: Bitrate) -> Self {
+        self.bwe_bitrate_bounds = (min, max);
+
+        self
+    }
+
+    /// The minimum and maximum BWE bitrate as set by [`Self::set_bwe_bitrate_bounds()`].
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::bwe::Bitrate;
+    /// let config = Rtc::builder();
+    ///
+    /// assert_eq!(config.bwe_bitrate_bounds(), (Bitrate::kbps(40), Bitrate::gbps(10)));
+    /// ```
+    #[cfg(feature = "bwe")]
+    pub fn bwe_bitrate_bounds(&self) -> (Bitrate, Bitrate) {
+        self.bwe_bitrate_bounds
+    }
+
+    /// Sets the relative change of the estimate needed to emit a new
+    /// [`Event::EgressBitrateEstimate`].
+    ///
+    /// The BWE updates the estimate on every TWCC feedback. To avoid reconfiguring
+    /// encoders for tiny changes, an event is only emitted when the estimate moves
+    /// outside `last_emitted * (1 ± tolerance)`. The value is clamped to 0.0..=1.0.
+    ///
+    /// Defaults to 0.05 (5%).
+    #[cfg(feature = "bwe")]
+    pub fn set_bwe_estimate_tolerance(mut self, tolerance: f64) -> Self {
+        self.bwe_estimate_tolerance = tolerance.clamp(0.0, 1.0);
+
+        self
+    }
+
+    /// The tolerance set by [`Self::set_bwe_estimate_tolerance()`].
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 5%.
+    /// assert_eq!(config.bwe_estimate_tolerance(), 0.05);
+    /// ```
+    #[cfg(feature = "bwe")]
+    pub fn bwe_estimate_tolerance(&self) -> f64 {
+        self.bwe_estimate_tolerance
+    }
+
     /// Sets the number of packets held back for reordering audio packets.
     ///
     /// Str0m tries to deliver the samples in order. This number determines how many
@@ -2151,6 +3730,79 @@ impl RtcConfig {
         self.reordering_size_video
     }
 
+    /// Make the time frames with missing packets are held back adapt to the network.
+    ///
+    /// Without this, a frame with missing packets is held back until the reordering size
+    /// ([`RtcConfig::set_reordering_size_audio()`] and [`RtcConfig::set_reordering_size_video()`])
+    /// is reached. With this, the jitter buffer also gives up waiting after a target delay
+    /// that follows the observed jitter, and how late reordered and retransmitted packets
+    /// arrive, kept within the `delay` bounds. The reordering size is still the upper limit.
+    ///
+    /// The current target is in [`JitterBufferStats::target_delay`][crate::media::JitterBufferStats].
+    ///
+    /// Defaults to `None`.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder()
+    ///     .set_jitter_buffer_delay(Some(Duration::from_millis(20)..=Duration::from_millis(500)));
+    ///
+    /// assert_eq!(
+    ///     config.jitter_buffer_delay(),
+    ///     Some(Duration::from_millis(20)..=Duration::from_millis(500))
+    /// );
+    /// ```
+    pub fn set_jitter_buffer_delay(mut self, delay: Option<RangeInclusive<Duration>>) -> Self {
+        self.jitter_buffer_delay = delay.map(|d| (*d.start(), *d.end()));
+        self
+    }
+
+    /// The bounds of the adaptive jitter buffer delay, if enabled.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to None.
+    /// assert_eq!(config.jitter_buffer_delay(), None);
+    /// ```
+    pub fn jitter_buffer_delay(&self) -> Option<RangeInclusive<Duration>> {
+        self.jitter_buffer_delay.map(|(min, max)| min..=max)
+    }
+
+    /// Only emit video samples that are decodable.
+    ///
+    /// By default str0m emits every assembled frame, and flags gaps using
+    /// [`MediaData::contiguous`][crate::media::MediaData::contiguous]. With this
+    /// enabled, frames following an unrecovered loss are dropped until the next
+    /// keyframe, so every emitted frame can be decoded.
+    ///
+    /// This applies to H264, H265, VP8 and VP9. Remember to request a keyframe when
+    /// media stops flowing as a result.
+    ///
+    /// Defaults to false.
+    ///
+    /// This setting is ignored in [RTP mode][`RtcConfig::set_rtp_mode()`].
+    pub fn set_emit_only_decodable(mut self, enabled: bool) -> Self {
+        self.emit_only_decodable = enabled;
+
+        self
+    }
+
+    /// Returns whether only decodable video samples are emitted.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to false.
+    /// assert_eq!(config.emit_only_decodable(), false);
+    /// ```
+    pub fn emit_only_decodable(&self) -> bool {
+        self.emit_only_decodable
+    }
+
     /// Sets the buffer size for outgoing audio packets.
     ///
     /// This must be larger than 0. The value configures an internal ring buffer used as a temporary
@@ -2212,6 +3864,248 @@ impl RtcConfig {
         self.send_buffer_video
     }
 
+    /// Sets the max bytes held for resends, per outgoing stream.
+    ///
+    /// The resend cache is bounded by the packet count of the send buffer
+    /// ([`RtcConfig::set_send_buffer_audio()`] and [`RtcConfig::set_send_buffer_video()`]),
+    /// by the `rtx-time` (see [`PayloadParams::set_rtx_time()`][crate::format::PayloadParams::set_rtx_time]),
+    /// and by this sum of payload bytes. The oldest packets are evicted first.
+    pub fn set_rtx_cache_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.rtx_cache_max_bytes = max_bytes;
+        self
+    }
+
+    /// Returns the setting for max bytes held for resends.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 2MB.
+    /// assert_eq!(config.rtx_cache_max_bytes(), 2 * 1024 * 1024);
+    /// ```
+    pub fn rtx_cache_max_bytes(&self) -> usize {
+        self.rtx_cache_max_bytes
+    }
+
+    /// Answer NACKs by resending on the main SSRC when RTX is not negotiated.
+    ///
+    /// Normally str0m only does resends using RTX. Some endpoints don't negotiate RTX, and
+    /// with this enabled, NACKs for such streams are answered by sending the original packet
+    /// again, unmodified, from the send buffer. Only the transport-wide sequence number is
+    /// reassigned.
+    ///
+    /// See [`StreamTx::set_resend_without_rtx()`][crate::rtp::StreamTx::set_resend_without_rtx]
+    /// to change this for a single stream.
+    pub fn set_resend_without_rtx(mut self, enabled: bool) -> Self {
+        self.resend_without_rtx = enabled;
+        self
+    }
+
+    /// Returns the setting for resends without RTX.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to false.
+    /// assert_eq!(config.resend_without_rtx(), false);
+    /// ```
+    pub fn resend_without_rtx(&self) -> bool {
+        self.resend_without_rtx
+    }
+
+    /// Sets the max size of outgoing UDP datagrams.
+    ///
+    /// The size is the UDP payload, i.e. excluding IP and UDP headers. It's used for RTP
+    /// packetization, padding, RTCP, DTLS fragmentation and SCTP. The value is clamped to
+    /// `576..=1500`.
+    ///
+    /// The default is conservative to work over tunnels and VPNs. If the path MTU is known
+    /// to be larger, fewer packets are sent for the same media. To change it for an ongoing
+    /// session, see [`Rtc::set_mtu()`].
+    pub fn set_mtu(mut self, mtu: usize) -> Self {
+        self.mtu = mtu.clamp(MIN_MTU, MAX_MTU);
+        self
+    }
+
+    /// Sets the SRTP profiles offered in the DTLS handshake, in order of preference.
+    ///
+    /// Profiles not in the list are never negotiated. Must not be empty.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, SrtpProfile};
+    /// let rtc = Rtc::builder()
+    ///     .set_srtp_profiles(&[SrtpProfile::Aes128CmSha1_80])
+    ///     .build();
+    /// ```
+    pub fn set_srtp_profiles(mut self, profiles: &[SrtpProfile]) -> Self {
+        self.srtp_profiles = profiles.to_vec();
+        self
+    }
+
+    /// The SRTP profiles offered in the DTLS handshake.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, SrtpProfile};
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to AEAD AES-128 GCM, then AES-128 CM SHA1-80.
+    /// assert_eq!(
+    ///     config.srtp_profiles(),
+    ///     &[SrtpProfile::AeadAes128Gcm, SrtpProfile::Aes128CmSha1_80]
+    /// );
+    /// ```
+    pub fn srtp_profiles(&self) -> &[SrtpProfile] {
+        &self.srtp_profiles
+    }
+
+    /// Sets the interval between RTCP sender/receiver reports for audio and video streams.
+    ///
+    /// RFC 8829 suggests 4 seconds, but libWebRTC expects reports for video every second.
+    /// Must not be zero.
+    pub fn set_rtcp_report_interval(mut self, audio: Duration, video: Duration) -> Self {
+        self.rtcp_interval_audio = audio;
+        self.rtcp_interval_video = video;
+        self
+    }
+
+    /// The interval between RTCP sender/receiver reports as `(audio, video)`.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 5 seconds for audio and 1 second for video.
+    /// assert_eq!(
+    ///     config.rtcp_report_interval(),
+    ///     (Duration::from_secs(5), Duration::from_secs(1))
+    /// );
+    /// ```
+    pub fn rtcp_report_interval(&self) -> (Duration, Duration) {
+        (self.rtcp_interval_audio, self.rtcp_interval_video)
+    }
+
+    /// Derives the RTCP report intervals from the media bitrate instead.
+    ///
+    /// Replaces the fixed [`RtcConfig::set_rtcp_report_interval()`] with intervals that
+    /// keep RTCP to a fraction of each stream's bitrate (RFC 3550 6.2). Useful for
+    /// sessions with very low or very high media bitrates. `None` (the default) uses
+    /// the fixed intervals.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::rtp::RtcpBandwidth;
+    /// let mut bw = RtcpBandwidth::default();
+    /// bw.fraction = 0.02;
+    ///
+    /// let config = Rtc::builder().set_rtcp_bandwidth(Some(bw));
+    /// ```
+    pub fn set_rtcp_bandwidth(mut self, bandwidth: Option<RtcpBandwidth>) -> Self {
+        self.rtcp_bandwidth = bandwidth;
+        self
+    }
+
+    /// The RTCP bandwidth used to derive report intervals, if set.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to None, i.e. fixed report intervals.
+    /// assert_eq!(config.rtcp_bandwidth(), None);
+    /// ```
+    pub fn rtcp_bandwidth(&self) -> Option<RtcpBandwidth> {
+        self.rtcp_bandwidth
+    }
+
+    /// Sets the minimum time between keyframe requests (PLI/FIR) per stream.
+    ///
+    /// Applies in both directions. Outgoing requests via
+    /// [`StreamRx::request_keyframe()`][crate::rtp::StreamRx::request_keyframe] are held back
+    /// until the interval has passed since the last one was sent, and any requests made in
+    /// between are sent as one. Incoming PLI/FIR arriving within the interval of the last
+    /// [`Event::KeyframeRequest`] for the same stream are dropped. They are still counted in
+    /// the stats. Zero turns this off.
+    pub fn set_keyframe_request_interval(mut self, interval: Duration) -> Self {
+        self.keyframe_request_interval = interval;
+        self
+    }
+
+    /// The minimum time between keyframe requests (PLI/FIR) per stream.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 300 milliseconds.
+    /// assert_eq!(config.keyframe_request_interval(), Duration::from_millis(300));
+    /// ```
+    pub fn keyframe_request_interval(&self) -> Duration {
+        self.keyframe_request_interval
+    }
+
+    /// Sets what to do with incoming RTP/RTCP from SSRCs not bound to any media.
+    ///
+    /// Packets are dropped, optionally with an [`Event::UnknownSsrc`], or held briefly
+    /// waiting for the SSRC to be bound. See [`UnknownSsrcPolicy`][crate::rtp::UnknownSsrcPolicy].
+    pub fn set_unknown_ssrc_policy(mut self, policy: UnknownSsrcPolicy) -> Self {
+        self.unknown_ssrc_policy = policy;
+        self
+    }
+
+    /// What to do with incoming RTP/RTCP from SSRCs not bound to any media.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::rtp::UnknownSsrcPolicy;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to dropping the packets.
+    /// assert_eq!(config.unknown_ssrc_policy(), UnknownSsrcPolicy::Drop);
+    /// ```
+    pub fn unknown_ssrc_policy(&self) -> UnknownSsrcPolicy {
+        self.unknown_ssrc_policy
+    }
+
+    /// Sets the ICE timing advance (Ta), the minimum time between ICE connectivity checks.
+    ///
+    /// A lower value connects faster when there are many candidate pairs, at the cost of
+    /// more bursty STUN traffic. Must not be zero.
+    pub fn set_ice_timing_advance(mut self, duration: Duration) -> Self {
+        self.ice_timing_advance = duration;
+        self
+    }
+
+    /// The ICE timing advance (Ta).
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 50ms.
+    /// assert_eq!(config.ice_timing_advance(), Duration::from_millis(50));
+    /// ```
+    pub fn ice_timing_advance(&self) -> Duration {
+        self.ice_timing_advance
+    }
+
+    /// Returns the max size of outgoing UDP datagrams.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 1150.
+    /// assert_eq!(config.mtu(), 1150);
+    /// ```
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
     /// Make the entire Rtc be in RTP mode.
     ///
     /// This means all media, read from [`RtpPacket`] and written to
@@ -2219,6 +4113,8 @@ impl RtcConfig {
     /// It bypasses all internal packetization/depacketization inside str0m.
     ///
     /// WARNING: This is a low level API and is not str0m's primary use case.
+    ///
+    /// Without the `sample-api` cargo feature, RTP mode is always on.
     pub fn set_rtp_mode(mut self, enabled: bool) -> Self {
         self.rtp_mode = enabled;
 
@@ -2235,7 +4131,7 @@ impl RtcConfig {
     /// assert_eq!(config.rtp_mode(), false);
     /// ```
     pub fn rtp_mode(&self) -> bool {
-        self.rtp_mode
+        self.rtp_mode || !cfg!(feature = "sample-api")
     }
 
     /// Enable the [`Event::RawPacket`] event.
@@ -2247,10 +4143,159 @@ impl RtcConfig {
         self
     }
 
+    /// Enable the [`Event::DominantSpeakerChanged`] event.
+    ///
+    /// Detects the dominant speaker from the audio level header extension (RFC 6464) of the
+    /// incoming audio streams. The levels are smoothed, and another stream must be clearly
+    /// louder for half a second to take over, to not switch on short noises. When everybody
+    /// is silent, the last dominant speaker stays.
+    ///
+    /// Requires the remote to send [`Extension::AudioLevel`][crate::rtp::Extension::AudioLevel].
+    /// Defaults to disabled.
+    pub fn enable_dominant_speaker(mut self, enabled: bool) -> Self {
+        self.enable_dominant_speaker = enabled;
+        self
+    }
+
+    /// Keep copies of all unencrypted RTP and RTCP, to be polled with
+    /// [`Rtc::poll_tapped_packet()`].
+    ///
+    /// Incoming packets are tapped after SRTP decryption, outgoing packets before
+    /// SRTP encryption. Each packet is timestamped with the `Instant` of the
+    /// [`Rtc::handle_input()`] or [`Rtc::poll_output()`] call that processed it.
+    /// If the packets are not polled, the oldest are dropped after 1000 packets.
+    ///
+    /// Like [`RtcConfig::enable_raw_packets()`], this clones data and should only be
+    /// used for troubleshooting.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new().enable_packet_tap(true);
+    ///
+    /// assert!(config.packet_tap());
+    /// ```
+    pub fn enable_packet_tap(mut self, enabled: bool) -> Self {
+        self.enable_packet_tap = enabled;
+        self
+    }
+
+    /// Whether the packet tap is enabled.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new();
+    ///
+    /// // Defaults to false.
+    /// assert!(!config.packet_tap());
+    /// ```
+    pub fn packet_tap(&self) -> bool {
+        self.enable_packet_tap
+    }
+
+    /// Set a [`PacketObserver`][rtp::PacketObserver] called for each packet at the stages
+    /// of the pipeline, see [`PacketStage`][rtp::PacketStage].
+    ///
+    /// Unlike the packet tap, nothing is copied. The same observer can be shared by many
+    /// [`Rtc`] instances.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use str0m::RtcConfig;
+    /// # use str0m::rtp::{ObservedPacket, PacketObserver};
+    /// #[derive(Debug)]
+    /// struct Noop;
+    ///
+    /// impl PacketObserver for Noop {
+    ///     fn on_packet(&self, _packet: &ObservedPacket<'_>) {}
+    /// }
+    ///
+    /// let config = RtcConfig::new().set_packet_observer(Some(Arc::new(Noop)));
+    ///
+    /// assert!(config.packet_observer().is_some());
+    /// ```
+    pub fn set_packet_observer(mut self, observer: Option<Arc<dyn rtp::PacketObserver>>) -> Self {
+        self.packet_observer = observer.map(Observer);
+        self
+    }
+
+    /// The packet observer, if set.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new();
+    ///
+    /// // Defaults to None.
+    /// assert!(config.packet_observer().is_none());
+    /// ```
+    pub fn packet_observer(&self) -> Option<&Arc<dyn rtp::PacketObserver>> {
+        self.packet_observer.as_ref().map(|o| &o.0)
+    }
+
     /// Create a [`Rtc`] from the configuration.
+    ///
+    /// The configuration is not checked. Use [`RtcConfig::try_build()`] to get an error
+    /// for invalid settings.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// // Not a valid config, but build() doesn't fail.
+    /// let rtc = Rtc::builder()
+    ///     .set_srtp_profiles(&[])
+    ///     .build();
+    /// ```
     pub fn build(self) -> Rtc {
+        if let Err(e) = self.validate() {
+            warn!("{}, building anyway", e);
+        }
         Rtc::new_from_config(self)
     }
+
+    /// Create a [`Rtc`] from the configuration, checking that it's valid first.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, RtcError};
+    /// let result = Rtc::builder()
+    ///     .set_srtp_profiles(&[])
+    ///     .try_build();
+    ///
+    /// assert!(matches!(result, Err(RtcError::InvalidConfig(_))));
+    /// ```
+    pub fn try_build(self) -> Result<Rtc, RtcError> {
+        self.validate()?;
+        Ok(Rtc::new_from_config(self))
+    }
+
+    fn validate(&self) -> Result<(), RtcError> {
+        let invalid = |s: &str| Err(RtcError::InvalidConfig(s.to_string()));
+
+        #[cfg(feature = "bwe")]
+        if self.bwe_bitrate_bounds.0 > self.bwe_bitrate_bounds.1 {
+            return invalid("bwe bitrate bounds min is larger than max");
+        }
+
+        if self.srtp_profiles.is_empty() {
+            return invalid("no SRTP profiles");
+        }
+
+        if self.rtcp_interval_audio.is_zero() || self.rtcp_interval_video.is_zero() {
+            return invalid("RTCP report interval is zero");
+        }
+
+        if let Some(bw) = &self.rtcp_bandwidth {
+            if bw.fraction <= 0.0 || !(0.0..=1.0).contains(&bw.sender_share) {
+                return invalid("RTCP bandwidth fraction or sender share out of range");
+            }
+            if bw.min_interval.is_zero() || bw.min_interval > bw.max_interval {
+                return invalid("RTCP bandwidth min interval is zero or larger than max");
+            }
+        }
+
+        if self.ice_timing_advance.is_zero() {
+            return invalid("ICE timing advance is zero");
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for RtcConfig {
@@ -2258,18 +4303,40 @@ impl Default for RtcConfig {
         Self {
             local_ice_credentials: None,
             dtls_cert: None,
+            dtls_cert_expiry_warning: Some(Duration::from_secs(24 * 60 * 60)),
+            fingerprint_hash: FingerprintHash::Sha256,
             fingerprint_verification: true,
             ice_lite: false,
             codec_config: CodecConfig::new_with_defaults(),
             exts: ExtensionMap::standard(),
             stats_interval: None,
+            #[cfg(feature = "bwe")]
             bwe_initial_bitrate: None,
+            #[cfg(feature = "bwe")]
+            bwe_bitrate_bounds: (Bitrate::kbps(40), Bitrate::gbps(10)),
+            #[cfg(feature = "bwe")]
+            bwe_estimate_tolerance: 0.05,
             reordering_size_audio: 15,
             reordering_size_video: 30,
+            jitter_buffer_delay: None,
+            emit_only_decodable: false,
             send_buffer_audio: 50,
             send_buffer_video: 1000,
+            rtx_cache_max_bytes: DEFAULT_RTX_CACHE_MAX_BYTES,
+            resend_without_rtx: false,
+            mtu: DATAGRAM_MTU,
+            srtp_profiles: SrtpProfile::ALL.to_vec(),
+            rtcp_interval_audio: RtcpIntervals::DEFAULT_AUDIO,
+            rtcp_interval_video: RtcpIntervals::DEFAULT_VIDEO,
+            rtcp_bandwidth: None,
+            unknown_ssrc_policy: UnknownSsrcPolicy::Drop,
+            keyframe_request_interval: RtcpIntervals::DEFAULT_KEYFRAME_REQUEST,
+            ice_timing_advance: Duration::from_millis(50),
             rtp_mode: false,
             enable_raw_packets: false,
+            enable_dominant_speaker: false,
+            enable_packet_tap: false,
+            packet_observer: None,
         }
     }
 }
@@ -2277,12 +4344,19 @@ impl Default for RtcConfig {
 impl PartialEq for Event {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
+            (Self::Connected, Self::Connected) => true,
             (Self::IceConnectionStateChange(l0), Self::IceConnectionStateChange(r0)) => l0 == r0,
             (Self::MediaAdded(m0), Self::MediaAdded(m1)) => m0 == m1,
+            #[cfg(feature = "sample-api")]
             (Self::MediaData(m1), Self::MediaData(m2)) => m1 == m2,
+            #[cfg(feature = "sctp")]
             (Self::ChannelOpen(l0, l1), Self::ChannelOpen(r0, r1)) => l0 == r0 && l1 == r1,
+            #[cfg(feature = "sctp")]
             (Self::ChannelData(l0), Self::ChannelData(r0)) => l0 == r0,
+            #[cfg(feature = "sctp")]
             (Self::ChannelClose(l0), Self::ChannelClose(r0)) => l0 == r0,
+            #[cfg(feature = "sctp")]
+            (Self::ChannelBufferedAmountLow(l0), Self::ChannelBufferedAmountLow(r0)) => l0 == r0,
             _ => false,
         }
     }
@@ -2311,6 +4385,7 @@ impl fmt::Debug for Rtc {
 /// These logs can be easily grepped for, parsed and graphed, or otherwise analyzed.
 ///
 /// This macro turns into a NO-OP if the `_internal_dont_use_log_stats` feature is not enabled
+#[allow(unused_macros)]
 macro_rules! log_stat {
     ($name:expr, $($arg:expr),+) => {
         #[cfg(feature = "_internal_dont_use_log_stats")]
@@ -2331,6 +4406,7 @@ macro_rules! log_stat {
         }
     };
 }
+#[allow(unused_imports)]
 pub(crate) use log_stat;
 
 #[cfg(test)]
@@ -2341,7 +4417,7 @@ mod test {
 
     #[test]
     fn rtc_is_send() {
-        fn is_send<T: Send>(_t: T) {}
+        fn is_send<T: Send + 'static>(_t: T) {}
         fn is_sync<T: Sync>(_t: T) {}
         is_send(Rtc::new());
         is_sync(Rtc::new());
@@ -2358,8 +4434,91 @@ mod test {
         let n = std::mem::size_of::<Event>();
         assert!(n < 450);
     }
+
+    fn gso_transmit(len: usize) -> net::Transmit {
+        net::Transmit {
+            proto: net::Protocol::Udp,
+            source: (std::net::Ipv4Addr::new(1, 1, 1, 1), 1000).into(),
+            destination: (std::net::Ipv4Addr::new(2, 2, 2, 2), 2000).into(),
+            contents: vec![0; len].into(),
+            segment_size: None,
+        }
+    }
+
+    #[test]
+    fn coalesce_gso_holds_error() {
+        // Popped from the back.
+        let mut polled = vec![
+            Err(RtcError::NoSenderSource),
+            Ok(Output::Transmit(gso_transmit(100))),
+            Ok(Output::Transmit(gso_transmit(100))),
+        ];
+
+        let (o, held) = coalesce_gso(gso_transmit(100), 8, || polled.pop().unwrap());
+
+        let Output::Transmit(t) = o else {
+            panic!("Expected a transmit");
+        };
+        assert_eq!(t.segment_size, Some(100));
+        assert_eq!(t.segments().count(), 3);
+
+        assert!(matches!(held, Some(Err(RtcError::NoSenderSource))));
+        assert!(polled.is_empty());
+    }
+
+    #[test]
+    fn coalesce_gso_holds_other_destination() {
+        let mut other = gso_transmit(100);
+        other.destination = (std::net::Ipv4Addr::new(3, 3, 3, 3), 3000).into();
+        let mut polled = vec![Ok(Output::Transmit(other))];
+
+        let (o, held) = coalesce_gso(gso_transmit(100), 8, || polled.pop().unwrap());
+
+        let Output::Transmit(t) = o else {
+            panic!("Expected a transmit");
+        };
+        assert_eq!(t.segment_size, None);
+
+        let Some(Ok(Output::Transmit(t))) = held else {
+            panic!("Expected a held transmit");
+        };
+        assert_eq!(t.destination.port(), 3000);
+    }
+
+    #[test]
+    fn output_accessors() {
+        let now = Instant::now();
+
+        let o = Output::Timeout(now);
+        assert_eq!(o.as_timeout(), Some(now));
+        assert!(o.as_event().is_none());
+        assert!(o.into_transmit().is_none());
+
+        let o = Output::Event(Event::Connected);
+        assert_eq!(o.as_timeout(), None);
+        assert_eq!(o.as_event(), Some(&Event::Connected));
+        assert_eq!(o.into_event().and_then(|e| e.mid()), None);
+
+        let mid = Mid::from("a");
+        let e = Event::KeyframeRequest(KeyframeRequest {
+            mid,
+            rid: None,
+            kind: KeyframeRequestKind::Pli,
+        });
+        assert_eq!(e.mid(), Some(mid));
+    }
 }
 
+#[cfg(feature = "loopback")]
+pub mod loopback;
+
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+#[cfg(feature = "ffi")]
+#[allow(unsafe_code)]
+pub mod ffi;
+
 #[cfg(feature = "_internal_test_exports")]
 #[allow(missing_docs)]
 pub mod _internal_test_exports;
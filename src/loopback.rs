@@ -0,0 +1,276 @@
+//! In-memory loopback of two [`Rtc`] instances for end-to-end tests.
+//!
+//! Enable with the `loopback` feature.
+//!
+//! The [`Loopback`] wires two `Rtc` back-to-back. Instead of using a socket, the datagrams
+//! are passed directly between the instances with simulated loss, latency and jitter. Time is
+//! driven by a clock owned by the loopback, which means the tests run as fast as the CPU
+//! allows, and given the same seed, are deterministic.
+//!
+//! ```no_run
+//! # use std::time::{Duration, Instant};
+//! # use str0m::Rtc;
+//! # use str0m::media::{Direction, MediaKind};
+//! use str0m::loopback::{LinkConditions, Loopback};
+//!
+//! let mut lo = Loopback::new(Rtc::new(), Rtc::new(), Instant::now());
+//!
+//! lo.set_conditions(LinkConditions {
+//!     loss: 0.02,
+//!     latency: Duration::from_millis(50),
+//!     jitter: Duration::from_millis(10),
+//! });
+//!
+//! let mid = lo.negotiate(|api| api.add_media(MediaKind::Audio, Direction::SendOnly, None, None));
+//!
+//! assert!(lo.run_until_connected(Duration::from_secs(5)).unwrap());
+//!
+//! // Write media via lo.l.rtc and advance time.
+//! let until = lo.now() + Duration::from_secs(1);
+//! lo.run_until(until).unwrap();
+//! ```
+
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use crate::change::SdpApi;
+use crate::net::{Protocol, Receive};
+use crate::{Candidate, Event, Input, Output, Rtc, RtcError};
+
+/// Simulated network conditions for one direction of the loopback.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinkConditions {
+    /// Probability in `0.0..=1.0` that a datagram is lost.
+    pub loss: f32,
+    /// Fixed delay added to every datagram.
+    pub latency: Duration,
+    /// Random extra delay in `0..=jitter` added to every datagram.
+    ///
+    /// Jitter can reorder datagrams.
+    pub jitter: Duration,
+}
+
+/// One side of the [`Loopback`].
+pub struct Peer {
+    /// The instance under test.
+    pub rtc: Rtc,
+    /// All events polled from the instance, with the time they were polled.
+    pub events: Vec<(Instant, Event)>,
+    addr: SocketAddr,
+    timeout: Instant,
+    conditions: LinkConditions,
+}
+
+struct InFlight {
+    at: Instant,
+    to_l: bool,
+    proto: Protocol,
+    source: SocketAddr,
+    destination: SocketAddr,
+    data: Vec<u8>,
+}
+
+/// Two [`Rtc`] instances connected back-to-back in memory.
+pub struct Loopback {
+    /// The left peer. This is the offerer in [`Loopback::negotiate()`].
+    pub l: Peer,
+    /// The right peer.
+    pub r: Peer,
+    now: Instant,
+    rng: fastrand::Rng,
+    in_flight: VecDeque<InFlight>,
+}
+
+impl Loopback {
+    /// Creates a loopback for the two instances with a clock starting at `start`.
+    ///
+    /// Each instance gets a host candidate. Conditions default to no loss, latency or jitter.
+    pub fn new(l: Rtc, r: Rtc, start: Instant) -> Self {
+        let mut lo = Loopback {
+            l: Peer::new(l, (Ipv4Addr::new(1, 1, 1, 1), 1000).into(), start),
+            r: Peer::new(r, (Ipv4Addr::new(2, 2, 2, 2), 2000).into(), start),
+            now: start,
+            rng: fastrand::Rng::with_seed(0),
+            in_flight: VecDeque::new(),
+        };
+
+        for p in [&mut lo.l, &mut lo.r] {
+            let c = Candidate::host(p.addr, "udp").expect("host candidate");
+            p.rtc.add_local_candidate(c);
+        }
+
+        lo
+    }
+
+    /// Set the seed for the random loss and jitter. Defaults to 0.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+
+    /// Set the same network conditions in both directions.
+    pub fn set_conditions(&mut self, conditions: LinkConditions) {
+        self.l.conditions = conditions;
+        self.r.conditions = conditions;
+    }
+
+    /// Set the network conditions for datagrams sent from `l` to `r` and `r` to `l`.
+    pub fn set_conditions_per_direction(&mut self, l_to_r: LinkConditions, r_to_l: LinkConditions) {
+        self.l.conditions = l_to_r;
+        self.r.conditions = r_to_l;
+    }
+
+    /// The current time of the loopback clock.
+    pub fn now(&self) -> Instant {
+        self.now
+    }
+
+    /// Perform an SDP offer/answer where `l` is the offerer.
+    ///
+    /// The closure makes the changes to the offer.
+    pub fn negotiate<F, R>(&mut self, do_change: F) -> R
+    where
+        F: FnOnce(&mut SdpApi) -> R,
+    {
+        let mut change = self.l.rtc.sdp_api();
+        let result = do_change(&mut change);
+        let (offer, pending) = change.apply().expect("change to result in an offer");
+
+        let answer = self
+            .r
+            .rtc
+            .sdp_api()
+            .accept_offer(offer)
+            .expect("offer to be accepted");
+
+        self.l
+            .rtc
+            .sdp_api()
+            .accept_answer(pending, answer)
+            .expect("answer to be accepted");
+
+        result
+    }
+
+    /// Advance the clock to the next timeout or datagram delivery, but at most 10ms.
+    pub fn progress(&mut self) -> Result<(), RtcError> {
+        // Deliver everything due.
+        while self.in_flight.front().map(|f| f.at <= self.now) == Some(true) {
+            let f = self.in_flight.pop_front().expect("front");
+            let peer = if f.to_l { &mut self.l } else { &mut self.r };
+            let receive = Receive {
+                proto: f.proto,
+                source: f.source,
+                destination: f.destination,
+                contents: (&*f.data).try_into()?,
+            };
+            peer.rtc.handle_input(Input::Receive(self.now, receive))?;
+            self.poll_peer(f.to_l)?;
+        }
+
+        self.poll_peer(true)?;
+        self.poll_peer(false)?;
+
+        let next_delivery = self.in_flight.front().map(|f| f.at);
+        let next = next_delivery
+            .into_iter()
+            .chain([self.l.timeout, self.r.timeout])
+            .min()
+            .expect("at least two timeouts");
+
+        // Always move time forward, but in small enough steps to allow writing media
+        // in between.
+        self.now = next.clamp(
+            self.now + Duration::from_millis(1),
+            self.now + Duration::from_millis(10),
+        );
+
+        Ok(())
+    }
+
+    /// Progress until the clock reaches `until`.
+    pub fn run_until(&mut self, until: Instant) -> Result<(), RtcError> {
+        while self.now < until {
+            self.progress()?;
+        }
+        Ok(())
+    }
+
+    /// Progress until both instances are connected, or at most `max`.
+    ///
+    /// Returns whether the instances are connected.
+    pub fn run_until_connected(&mut self, max: Duration) -> Result<bool, RtcError> {
+        let until = self.now + max;
+        loop {
+            if self.l.rtc.is_connected() && self.r.rtc.is_connected() {
+                return Ok(true);
+            }
+            if self.now >= until {
+                return Ok(false);
+            }
+            self.progress()?;
+        }
+    }
+
+    fn poll_peer(&mut self, is_l: bool) -> Result<(), RtcError> {
+        let now = self.now;
+        let peer = if is_l { &mut self.l } else { &mut self.r };
+
+        peer.rtc.handle_input(Input::Timeout(now))?;
+
+        loop {
+            match peer.rtc.poll_output()? {
+                Output::Timeout(t) => {
+                    peer.timeout = t;
+                    break;
+                }
+                Output::Transmit(t) => {
+                    let c = peer.conditions;
+
+                    if self.rng.f32() < c.loss {
+                        continue;
+                    }
+
+                    let jitter = c.jitter.mul_f64(self.rng.f64());
+                    let at = now + c.latency + jitter;
+
+                    // Keep the queue ordered by delivery time, FIFO for the same time.
+                    let index = self.in_flight.partition_point(|f| f.at <= at);
+                    self.in_flight.insert(
+                        index,
+                        InFlight {
+                            at,
+                            to_l: !is_l,
+                            proto: t.proto,
+                            source: t.source,
+                            destination: t.destination,
+                            data: t.contents.to_vec(),
+                        },
+                    );
+                }
+                Output::Event(e) => {
+                    peer.events.push((now, e));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Peer {
+    fn new(rtc: Rtc, addr: SocketAddr, start: Instant) -> Self {
+        Peer {
+            rtc,
+            events: vec![],
+            addr,
+            timeout: start,
+            conditions: LinkConditions::default(),
+        }
+    }
+
+    /// The address of the host candidate of this peer.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
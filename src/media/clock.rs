@@ -0,0 +1,136 @@
+use crate::rtp_::{Frequency, MediaTime};
+use crate::util::{Instant, NonCryptographicRng};
+
+/// Media clock of an outgoing stream.
+///
+/// Derives the RTP time from the wallclock for callers that don't keep their own
+/// timestamp counters. The media time keeps advancing with the wallclock, also
+/// across pauses in writing, as RFC 3550 expects of RTP timestamps. Like RFC 3550 also
+/// says, the media time starts from a random value.
+#[derive(Debug)]
+pub(crate) struct MediaClock {
+    /// Random media time of the first write.
+    base: u32,
+
+    /// Wallclock and media time the clock is counted from.
+    ///
+    /// Re-anchored when the clock rate changes, to avoid accumulating rounding errors.
+    anchor: Option<(Instant, MediaTime)>,
+
+    /// The last media time handed out.
+    last: Option<MediaTime>,
+}
+
+impl Default for MediaClock {
+    fn default() -> Self {
+        MediaClock {
+            base: NonCryptographicRng::u32(),
+            anchor: None,
+            last: None,
+        }
+    }
+}
+
+impl MediaClock {
+    /// Media time corresponding to `wallclock` in the given clock rate.
+    ///
+    /// The returned values never go backwards, even if the wallclock does.
+    pub fn media_time(&mut self, wallclock: Instant, frequency: Frequency) -> MediaTime {
+        let (anchor_wall, anchor_time) = match self.anchor {
+            Some((wall, time)) if time.frequency() == frequency => (wall, time),
+            _ => {
+                // First write, or a change of clock rate. Continue from the last
+                // media time to not make a jump in the RTP time.
+                let time = self
+                    .last
+                    .map(|t| t.rebase(frequency))
+                    .unwrap_or(MediaTime::new(self.base as u64, frequency));
+                self.anchor = Some((wallclock, time));
+                (wallclock, time)
+            }
+        };
+
+        let elapsed = wallclock.saturating_duration_since(anchor_wall);
+        let time = anchor_time + MediaTime::from(elapsed).rebase(frequency);
+
+        let time = match self.last {
+            Some(last) if last.rebase(frequency) > time => last.rebase(frequency),
+            _ => time,
+        };
+
+        self.last = Some(time);
+
+        time
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn media_time_random_base() {
+        let now = Instant::now();
+        let f = Frequency::FORTY_EIGHT_KHZ;
+
+        let mut clock = MediaClock::default();
+        clock.base = 1234;
+        assert_eq!(clock.media_time(now, f), MediaTime::new(1234, f));
+
+        // Two clocks starting at the same base is a 1 in 2^32 chance.
+        let t1 = MediaClock::default().media_time(now, f);
+        let t2 = MediaClock::default().media_time(now, f);
+        assert_ne!(t1, t2);
+        assert!(t1.numer() <= u32::MAX as u64);
+    }
+
+    #[test]
+    fn media_time_follows_wallclock() {
+        let mut clock = MediaClock::default();
+        let now = Instant::now();
+        let f = Frequency::FORTY_EIGHT_KHZ;
+
+        let t0 = clock.media_time(now, f).numer();
+
+        let t = clock.media_time(now + Duration::from_millis(20), f);
+        assert_eq!(t.numer() - t0, 960);
+
+        // A pause keeps the clock running.
+        let t = clock.media_time(now + Duration::from_secs(2), f);
+        assert_eq!(t.numer() - t0, 96_000);
+    }
+
+    #[test]
+    fn media_time_never_goes_backwards() {
+        let mut clock = MediaClock::default();
+        clock.base = 0;
+        let now = Instant::now();
+        let f = Frequency::NINETY_KHZ;
+
+        clock.media_time(now, f);
+        clock.media_time(now + Duration::from_millis(100), f);
+        let t = clock.media_time(now + Duration::from_millis(50), f);
+        assert_eq!(t, MediaTime::new(9000, f));
+    }
+
+    #[test]
+    fn media_time_clock_rate_change() {
+        let mut clock = MediaClock::default();
+        clock.base = 0;
+        let now = Instant::now();
+        let f48 = Frequency::FORTY_EIGHT_KHZ;
+        let f8 = Frequency::new(8000).unwrap();
+
+        clock.media_time(now, f48);
+        clock.media_time(now + Duration::from_millis(100), f48);
+
+        // Continues from the last time in the new rate.
+        let t = clock.media_time(now + Duration::from_millis(100), f8);
+        assert_eq!(t, MediaTime::new(800, f8));
+
+        let t = clock.media_time(now + Duration::from_millis(120), f8);
+        assert_eq!(t, MediaTime::new(960, f8));
+    }
+}
@@ -1,10 +1,10 @@
 use std::fmt;
 use std::ops::RangeInclusive;
-use std::time::Instant;
 
 use crate::packet::MediaKind;
 use crate::rtp_::{Direction, ExtensionValues, MediaTime, Mid, Pt, Rid, SenderInfo, SeqNo};
 use crate::sdp::Simulcast as SdpSimulcast;
+use crate::util::Instant;
 
 use super::PayloadParams;
 use crate::format::CodecExtra;
@@ -112,6 +112,14 @@ pub struct MediaData {
     /// For audio this flag most likely doesn't matter.
     pub contiguous: bool,
 
+    /// Whether the packet just before this data was lost, and the data carries in-band FEC
+    /// that can recover it.
+    ///
+    /// Only set for Opus when `useinbandfec=1` is negotiated. The decoder should first
+    /// decode this data with FEC enabled (`decode_fec` in libopus) to get the lost frame,
+    /// then decode it again as normal.
+    pub decode_fec: bool,
+
     /// The actual packet data a.k.a Sample.
     ///
     /// Bigger samples don't fit in one UDP packet, thus WebRTC RTP is chopping up codec
@@ -150,6 +158,20 @@ pub struct KeyframeRequest {
     pub kind: KeyframeRequestKind,
 }
 
+/// Target layer for an incoming SVC (scalable video coding) stream.
+///
+/// Set via [`StreamRx::set_svc_target()`][crate::rtp::StreamRx::set_svc_target]. Currently
+/// only applies to VP9, where frames of higher temporal layers are dropped and the data of
+/// higher spatial layers is cut off before emitting [`MediaData`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SvcTarget {
+    /// The highest spatial layer to keep (0-indexed).
+    pub spatial: u8,
+
+    /// The highest temporal layer to keep (0-indexed).
+    pub temporal: u8,
+}
+
 /// Type of keyframe request.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyframeRequestKind {
@@ -162,6 +184,28 @@ pub enum KeyframeRequestKind {
     Fir,
 }
 
+impl MediaData {
+    /// Narrow the data to the given SVC target.
+    ///
+    /// Returns `false` if the entire data is above the target and should be dropped.
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
+    pub(crate) fn select_svc_target(&mut self, target: SvcTarget) -> bool {
+        let CodecExtra::Vp9(extra) = &mut self.codec_extra else {
+            return true;
+        };
+
+        extra.select_layers(&mut self.data, target.spatial, target.temporal)
+    }
+
+    /// Whether this data is a keyframe.
+    ///
+    /// This is derived from [`MediaData::codec_extra`] and is only ever `true` for
+    /// video codecs where str0m inspects the payload (VP8, VP9, H264 and H265).
+    pub fn is_keyframe(&self) -> bool {
+        self.codec_extra.is_keyframe()
+    }
+}
+
 impl fmt::Debug for MediaData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("MediaData")
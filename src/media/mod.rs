@@ -1,32 +1,64 @@
 //! Media (audio/video) related content.
 
+#[cfg(feature = "sample-api")]
 use std::collections::{HashMap, VecDeque};
-use std::time::Instant;
+use std::time::Duration;
 
 use crate::change::AddMedia;
+#[cfg(feature = "sample-api")]
+use crate::format::Codec;
 use crate::format::CodecConfig;
-use crate::io::{Id, DATAGRAM_MTU};
-use crate::packet::{DepacketizingBuffer, Payloader, RtpMeta};
+use crate::io::Id;
+#[cfg(feature = "sample-api")]
+use crate::packet::{CodecDepacketizer, DepacketizingBuffer, PacketError, Payloader, RtpMeta};
 use crate::rtp_::ExtensionMap;
+#[cfg(feature = "sample-api")]
+use crate::rtp_::Ssrc;
+#[cfg(feature = "sample-api")]
 use crate::rtp_::SRTP_BLOCK_SIZE;
+#[cfg(feature = "sample-api")]
 use crate::rtp_::SRTP_OVERHEAD;
+use crate::util::Instant;
+#[cfg(feature = "sample-api")]
 use crate::RtcError;
 
 use crate::format::PayloadParams;
 use crate::sdp::Simulcast as SdpSimulcast;
 use crate::sdp::{MediaLine, Msid};
+use crate::sdp::{RestrictionId, SimulcastGroups};
+#[cfg(feature = "sample-api")]
 use crate::streams::{RtpPacket, Streams};
+#[cfg(feature = "sample-api")]
 use crate::util::already_happened;
 
 mod event;
 pub use event::*;
 
+#[cfg(feature = "sample-api")]
+mod clock;
+#[cfg(feature = "sample-api")]
+use clock::MediaClock;
+
+mod speaker;
+pub(crate) use speaker::DominantSpeaker;
+pub use speaker::DominantSpeakerChanged;
+
+#[cfg(feature = "sample-api")]
 mod writer;
+#[cfg(feature = "sample-api")]
 pub use writer::Writer;
 
+#[cfg(feature = "sample-api")]
+pub use crate::packet::JitterBufferStats;
 pub use crate::packet::MediaKind;
+pub use crate::packet::Vp9Forwarding;
 pub use crate::rtp_::{Direction, ExtensionValues, Frequency, MediaTime, Mid, Pt, Rid};
 
+/// Room left for the RTP header with extensions, the RTX original sequence number and
+/// SRTP padding when packetizing. Without it, datagrams end up above the MTU.
+#[cfg(feature = "sample-api")]
+const RTP_HEADER_ALLOWANCE: usize = 64;
+
 #[derive(Debug)]
 /// Information about some configured media.
 pub struct Media {
@@ -97,18 +129,34 @@ pub struct Media {
     /// SDP property.
     simulcast: Option<SdpSimulcast>,
 
+    /// Packet time the remote wants to receive, `a=ptime`.
+    ///
+    /// SDP property.
+    remote_ptime: Option<Duration>,
+
     // ========================================= Payloaders, etc =========================================
     //
     /// Buffers of incoming RTP packets. These do reordering/jitter buffer and also
     /// depayload from RTP to samples.
+    #[cfg(feature = "sample-api")]
     depayloaders: HashMap<(Pt, Option<Rid>), DepacketizingBuffer>,
 
+    /// SVC targets of the incoming streams, as configured on the corresponding StreamRx.
+    #[cfg(feature = "sample-api")]
+    svc_targets: HashMap<Option<Rid>, SvcTarget>,
+
     /// Payloaders for outoing RTP packets.
+    #[cfg(feature = "sample-api")]
     payloaders: HashMap<(Pt, Option<Rid>), Payloader>,
 
     /// Samples to payload. Should typically only be 0 or 1.
+    #[cfg(feature = "sample-api")]
     to_payload: VecDeque<ToPayload>,
 
+    /// Media clocks of the outgoing streams, for writes without an RTP time.
+    #[cfg(feature = "sample-api")]
+    clocks: HashMap<Option<Rid>, MediaClock>,
+
     pub(crate) need_open_event: bool,
     pub(crate) need_changed_event: bool,
 
@@ -144,6 +192,7 @@ impl Rids {
     }
 }
 
+#[cfg(feature = "sample-api")]
 #[derive(Debug)]
 pub(crate) struct ToPayload {
     pub pt: Pt,
@@ -152,6 +201,7 @@ pub(crate) struct ToPayload {
     pub rtp_time: MediaTime,
     pub data: Vec<u8>,
     pub ext_vals: ExtensionValues,
+    pub csrc: Vec<Ssrc>,
 }
 
 impl Media {
@@ -236,17 +286,30 @@ impl Media {
         self.simulcast.as_ref()
     }
 
+    /// Statistics of the jitter buffers for incoming media.
+    ///
+    /// There is one jitter buffer per payload type and rid received.
+    #[cfg(feature = "sample-api")]
+    pub fn jitter_buffer_stats(
+        &self,
+    ) -> impl Iterator<Item = (Pt, Option<Rid>, JitterBufferStats)> + '_ {
+        self.depayloaders
+            .iter()
+            .map(|((pt, rid), buf)| (*pt, *rid, buf.stats()))
+    }
+
+    #[cfg(feature = "sample-api")]
     pub(crate) fn poll_sample(
         &mut self,
         params: &[PayloadParams],
     ) -> Result<Option<MediaData>, RtcError> {
         for ((pt, rid), buf) in &mut self.depayloaders {
-            if let Some(r) = buf.pop() {
+            while let Some(r) = buf.pop() {
                 let dep = r.map_err(|e| RtcError::Packet(self.mid, *pt, e))?;
                 let Some(codec) = params.iter().find(|c| c.pt() == *pt) else {
                     return Ok(None);
                 };
-                return Ok(Some(MediaData {
+                let mut data = MediaData {
                     mid: self.mid,
                     pt: *pt,
                     rid: *rid,
@@ -255,16 +318,28 @@ impl Media {
                     network_time: dep.first_network_time(),
                     seq_range: dep.seq_range(),
                     contiguous: dep.contiguous,
+                    decode_fec: dep.decode_fec,
                     ext_vals: dep.ext_vals().clone(),
                     codec_extra: dep.codec_extra,
                     last_sender_info: dep.first_sender_info(),
                     data: dep.data,
-                }));
+                };
+
+                if let Some(target) = self.svc_targets.get(rid) {
+                    if !data.select_svc_target(*target) {
+                        trace!("Drop MediaData above SVC target: {:?}", target);
+                        continue;
+                    }
+                }
+
+                return Ok(Some(data));
             }
         }
         Ok(None)
     }
 
+    #[cfg(feature = "sample-api")]
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn depayload(
         &mut self,
         rid: Option<Rid>,
@@ -272,11 +347,20 @@ impl Media {
         reordering_size_audio: usize,
         reordering_size_video: usize,
         params: &[PayloadParams],
+        svc_target: Option<SvcTarget>,
+        only_decodable: bool,
+        adaptive_delay: Option<(Duration, Duration)>,
     ) {
         if !self.dir.is_receiving() {
             return;
         }
 
+        if let Some(target) = svc_target {
+            self.svc_targets.insert(rid, target);
+        } else if !self.svc_targets.is_empty() {
+            self.svc_targets.remove(&rid);
+        }
+
         let pt = packet.header.payload_type;
 
         let key = (pt, rid);
@@ -297,7 +381,23 @@ impl Media {
                 reordering_size_video
             };
 
-            let buffer = DepacketizingBuffer::new(codec.into(), hold_back);
+            let depack = match CodecDepacketizer::try_from(codec) {
+                Ok(v) => v,
+                Err(e) => {
+                    debug!("Drop packet for pt {}: {}", pt, e);
+                    return;
+                }
+            };
+
+            let mut buffer = DepacketizingBuffer::new(depack, hold_back);
+            buffer.set_only_decodable(only_decodable);
+            buffer.set_is_audio(codec.is_audio());
+            buffer.set_inband_fec(
+                codec == Codec::Opus && params.spec.format.use_inband_fec == Some(true),
+            );
+            if let Some((min, max)) = adaptive_delay {
+                buffer.set_adaptive_delay(min, max);
+            }
 
             self.depayloaders.insert((pt, rid), buffer);
         }
@@ -329,24 +429,43 @@ impl Media {
         self.dir = new_dir;
     }
 
+    /// Packet time the remote wants to receive, if signaled with `a=ptime` in SDP.
+    ///
+    /// Written G.711 and G.722 audio is split in packets of this time, or 20ms if not set.
+    pub fn remote_ptime(&self) -> Option<Duration> {
+        self.remote_ptime
+    }
+
+    pub(crate) fn set_remote_ptime(&mut self, ptime: Option<Duration>) {
+        self.remote_ptime = ptime;
+    }
+
     pub(crate) fn set_simulcast(&mut self, s: SdpSimulcast) {
         info!("Set simulcast: {:?}", s);
         self.simulcast = Some(s);
     }
 
+    #[cfg(feature = "sample-api")]
     fn payloader_for(
         &mut self,
         pt: Pt,
         rid: Option<Rid>,
         params: &[PayloadParams],
-    ) -> &mut Payloader {
-        self.payloaders.entry((pt, rid)).or_insert_with(|| {
+    ) -> Result<&mut Payloader, PacketError> {
+        let key = (pt, rid);
+
+        if !self.payloaders.contains_key(&key) {
             // Unwrap is OK, the pt should be checked already when calling this function.
             let params = params.iter().find(|p| p.pt == pt).unwrap();
-            Payloader::new(params.spec)
-        })
+            let payloader = Payloader::new(params.spec, self.remote_ptime)?;
+            self.payloaders.insert(key, payloader);
+        }
+
+        // The entry will be there by now.
+        Ok(self.payloaders.get_mut(&key).unwrap())
     }
 
+    #[cfg(feature = "sample-api")]
     fn set_to_payload(&mut self, to_payload: ToPayload) -> Result<(), RtcError> {
         if self.to_payload.len() > 100 {
             return Err(RtcError::WriteWithoutPoll);
@@ -357,19 +476,49 @@ impl Media {
         Ok(())
     }
 
+    #[cfg(feature = "sample-api")]
+    fn media_time(
+        &mut self,
+        rid: Option<Rid>,
+        wallclock: Instant,
+        frequency: Frequency,
+    ) -> MediaTime {
+        let clock = self.clocks.entry(rid).or_default();
+        clock.media_time(wallclock, frequency)
+    }
+
     pub(crate) fn poll_timeout(&self) -> Option<Instant> {
+        #[cfg(feature = "sample-api")]
         if !self.to_payload.is_empty() {
-            Some(already_happened())
-        } else {
-            None
+            return Some(already_happened());
+        }
+
+        None
+    }
+
+    /// When a frame held back in a jitter buffer is to be released.
+    #[cfg(feature = "sample-api")]
+    pub(crate) fn depayload_timeout(&self) -> Option<Instant> {
+        self.depayloaders
+            .values()
+            .filter_map(|b| b.poll_timeout())
+            .min()
+    }
+
+    #[cfg(feature = "sample-api")]
+    pub(crate) fn handle_depayload_timeout(&mut self, now: Instant) {
+        for buffer in self.depayloaders.values_mut() {
+            buffer.handle_timeout(now);
         }
     }
 
+    #[cfg(feature = "sample-api")]
     pub(crate) fn do_payload(
         &mut self,
         now: Instant,
         streams: &mut Streams,
         params: &[PayloadParams],
+        mtu: usize,
     ) -> Result<(), RtcError> {
         let Some(to_payload) = self.to_payload.pop_front() else {
             return Ok(());
@@ -387,15 +536,18 @@ impl Media {
 
         let pt = *pt;
 
-        let payloader = self.payloader_for(pt, *rid, params);
+        let mid = self.mid;
+        let payloader = self
+            .payloader_for(pt, *rid, params)
+            .map_err(|e| RtcError::Packet(mid, pt, e))?;
 
-        const RTP_SIZE: usize = DATAGRAM_MTU - SRTP_OVERHEAD;
+        let rtp_size = mtu - SRTP_OVERHEAD - RTP_HEADER_ALLOWANCE - to_payload.csrc.len() * 4;
         // align to SRTP block size to minimize padding needs
-        const MTU: usize = RTP_SIZE - RTP_SIZE % SRTP_BLOCK_SIZE;
+        let rtp_size = rtp_size - rtp_size % SRTP_BLOCK_SIZE;
 
         payloader
-            .push_sample(now, to_payload, MTU, is_audio, stream)
-            .map_err(|e| RtcError::Packet(self.mid, pt, e))?;
+            .push_sample(now, to_payload, rtp_size, is_audio, stream)
+            .map_err(|e| RtcError::Packet(mid, pt, e))?;
 
         Ok(())
     }
@@ -449,10 +601,16 @@ impl Media {
             .find_map(|p| p.resend().map(|_| p.pt))
     }
 
+    #[cfg(feature = "sample-api")]
     pub(crate) fn reset_depayloader(&mut self, payload_type: Pt, rid: Option<Rid>) {
         // Simply remove the depayloader, it will be re-created on the next RTP packet.
         self.depayloaders.remove(&(payload_type, rid));
     }
+
+    #[cfg(feature = "sample-api")]
+    pub(crate) fn reset_depayloaders(&mut self, rid: Option<Rid>) {
+        self.depayloaders.retain(|(_, r), _| *r != rid);
+    }
 }
 
 impl Default for Media {
@@ -472,10 +630,18 @@ impl Default for Media {
             remote_created: false,
             dir: Direction::SendRecv,
             simulcast: None,
+            remote_ptime: None,
             rids_rx: Rids::Any,
+            #[cfg(feature = "sample-api")]
+            svc_targets: HashMap::new(),
+            #[cfg(feature = "sample-api")]
             payloaders: HashMap::new(),
+            #[cfg(feature = "sample-api")]
             depayloaders: HashMap::new(),
+            #[cfg(feature = "sample-api")]
             to_payload: VecDeque::default(),
+            #[cfg(feature = "sample-api")]
+            clocks: HashMap::new(),
             need_open_event: true,
             need_changed_event: false,
         }
@@ -507,6 +673,18 @@ impl Media {
     // from_add_media is only used when creating temporary Media to be
     // included in the SDP. We don't want to make an _actual_ changes with this.
     pub(crate) fn from_add_media(a: AddMedia) -> Self {
+        // Outgoing RIDs are offered as send simulcast.
+        let simulcast = (!a.rids.is_empty()).then(|| SdpSimulcast {
+            send: SimulcastGroups(
+                a.rids
+                    .iter()
+                    .map(|rid| RestrictionId::new_active(rid.to_string()))
+                    .collect(),
+            ),
+            recv: SimulcastGroups(vec![]),
+            is_munged: false,
+        });
+
         Media {
             mid: a.mid,
             index: a.index,
@@ -517,6 +695,7 @@ impl Media {
             remote_pts: a.pts,
             remote_exts: a.exts,
             remote_created: false,
+            simulcast,
             ..Default::default()
         }
     }
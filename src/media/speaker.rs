@@ -0,0 +1,235 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use crate::rtp_::{Mid, Ssrc};
+use crate::util::Instant;
+
+/// Time constant of the exponential smoothing of audio levels.
+const SMOOTHING: Duration = Duration::from_millis(300);
+
+/// How long another stream must be loudest before it becomes the dominant speaker.
+const HANGOVER: Duration = Duration::from_millis(500);
+
+/// Streams without audio level for this long are not considered speaking.
+const STALE: Duration = Duration::from_secs(1);
+
+/// Smoothed level (in dB above -127 dBov) considered speech, i.e. -50 dBov.
+const SPEECH_THRESHOLD: f32 = 77.0;
+
+/// How many dB louder than the dominant speaker another stream must be to take over.
+const MARGIN: f32 = 3.0;
+
+/// The dominant (active) speaker changed.
+///
+/// Enable using [`RtcConfig::enable_dominant_speaker()`][crate::RtcConfig::enable_dominant_speaker].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DominantSpeakerChanged {
+    /// The mid of the audio media of the new dominant speaker.
+    pub mid: Mid,
+
+    /// The main SSRC of the incoming audio stream of the new dominant speaker.
+    pub ssrc: Ssrc,
+}
+
+/// Detects the dominant speaker from the audio level header extension (RFC 6464)
+/// of incoming audio streams.
+#[derive(Debug, Default)]
+pub(crate) struct DominantSpeaker {
+    speakers: HashMap<Ssrc, Speaker>,
+    dominant: Option<Ssrc>,
+    /// A stream louder than the dominant and since when.
+    candidate: Option<(Ssrc, Instant)>,
+    events: VecDeque<DominantSpeakerChanged>,
+}
+
+#[derive(Debug)]
+struct Speaker {
+    mid: Mid,
+    level: f32,
+    last: Instant,
+}
+
+impl DominantSpeaker {
+    /// Handle the audio level of an incoming packet of the audio stream `ssrc`.
+    ///
+    /// `audio_level` is in -dBov, 0 being the loudest and -127 silence.
+    pub fn handle_audio_level(
+        &mut self,
+        now: Instant,
+        mid: Mid,
+        ssrc: Ssrc,
+        audio_level: i8,
+        voice_activity: Option<bool>,
+    ) {
+        let level = if voice_activity == Some(false) {
+            0.0
+        } else {
+            (127 + audio_level.clamp(-127, 0) as i32) as f32
+        };
+
+        let speaker = self.speakers.entry(ssrc).or_insert(Speaker {
+            mid,
+            level,
+            last: now,
+        });
+
+        let elapsed = now.saturating_duration_since(speaker.last);
+        let alpha = 1.0 - (-elapsed.as_secs_f32() / SMOOTHING.as_secs_f32()).exp();
+        speaker.level += (level - speaker.level) * alpha;
+        speaker.last = now;
+        speaker.mid = mid;
+
+        self.update(now);
+    }
+
+    fn update(&mut self, now: Instant) {
+        let is_speaking = |s: &Speaker| {
+            now.saturating_duration_since(s.last) < STALE && s.level >= SPEECH_THRESHOLD
+        };
+
+        let Some((loudest, level)) = self
+            .speakers
+            .iter()
+            .filter(|(_, s)| is_speaking(s))
+            .max_by(|(_, a), (_, b)| a.level.total_cmp(&b.level))
+            .map(|(ssrc, s)| (*ssrc, s.level))
+        else {
+            // Nobody speaks, the last dominant speaker stays.
+            self.candidate = None;
+            return;
+        };
+
+        if self.dominant == Some(loudest) {
+            self.candidate = None;
+            return;
+        }
+
+        let dominant_level = self
+            .dominant
+            .and_then(|d| self.speakers.get(&d))
+            .filter(|s| is_speaking(s))
+            .map(|s| s.level);
+
+        if let Some(d) = dominant_level {
+            if level < d + MARGIN {
+                self.candidate = None;
+                return;
+            }
+        }
+
+        let since = match self.candidate {
+            Some((ssrc, since)) if ssrc == loudest => since,
+            _ => {
+                self.candidate = Some((loudest, now));
+                now
+            }
+        };
+
+        // The very first speaker doesn't have to wait.
+        if self.dominant.is_some() && now.saturating_duration_since(since) < HANGOVER {
+            return;
+        }
+
+        self.dominant = Some(loudest);
+        self.candidate = None;
+
+        let mid = self.speakers[&loudest].mid;
+        debug!("Dominant speaker {} {}", mid, loudest);
+        self.events
+            .push_back(DominantSpeakerChanged { mid, ssrc: loudest });
+    }
+
+    /// Forget an incoming stream, for instance when it ended.
+    pub fn remove(&mut self, ssrc: Ssrc) {
+        self.speakers.remove(&ssrc);
+        if self.candidate.map(|(s, _)| s) == Some(ssrc) {
+            self.candidate = None;
+        }
+    }
+
+    pub fn poll_event(&mut self) -> Option<DominantSpeakerChanged> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn feed(
+        ds: &mut DominantSpeaker,
+        start: Instant,
+        from_ms: u64,
+        to_ms: u64,
+        levels: &[(u32, i8)],
+    ) -> Vec<u32> {
+        let mid = Mid::from("a");
+        let mut changes = vec![];
+
+        for ms in (from_ms..to_ms).step_by(20) {
+            let now = start + Duration::from_millis(ms);
+            for (ssrc, level) in levels {
+                ds.handle_audio_level(now, mid, (*ssrc).into(), *level, None);
+            }
+            while let Some(e) = ds.poll_event() {
+                changes.push(*e.ssrc);
+            }
+        }
+
+        changes
+    }
+
+    #[test]
+    fn first_speaker_is_dominant() {
+        let mut ds = DominantSpeaker::default();
+        let start = Instant::now();
+
+        assert_eq!(feed(&mut ds, start, 0, 200, &[(1, -127), (2, -127)]), []);
+        assert_eq!(feed(&mut ds, start, 200, 1000, &[(1, -20), (2, -127)]), [1]);
+    }
+
+    #[test]
+    fn hangover_ignores_short_bursts() {
+        let mut ds = DominantSpeaker::default();
+        let start = Instant::now();
+
+        assert_eq!(feed(&mut ds, start, 0, 1000, &[(1, -30), (2, -127)]), [1]);
+
+        // A short loud burst on 2 doesn't take over.
+        assert_eq!(feed(&mut ds, start, 1000, 1800, &[(1, -30), (2, -10)]), []);
+        assert_eq!(feed(&mut ds, start, 1800, 2600, &[(1, -30), (2, -127)]), []);
+
+        // Speaking for long does.
+        assert_eq!(
+            feed(&mut ds, start, 2600, 4000, &[(1, -127), (2, -20)]),
+            [2]
+        );
+    }
+
+    #[test]
+    fn silence_keeps_last_speaker() {
+        let mut ds = DominantSpeaker::default();
+        let start = Instant::now();
+
+        assert_eq!(feed(&mut ds, start, 0, 1000, &[(1, -127), (2, -20)]), [2]);
+        assert_eq!(
+            feed(&mut ds, start, 1000, 5000, &[(1, -127), (2, -127)]),
+            []
+        );
+    }
+
+    #[test]
+    fn voice_activity_false_is_silent() {
+        let mut ds = DominantSpeaker::default();
+        let start = Instant::now();
+        let mid = Mid::from("a");
+
+        for ms in (0..1000).step_by(20) {
+            let now = start + Duration::from_millis(ms);
+            ds.handle_audio_level(now, mid, 1.into(), -10, Some(false));
+        }
+
+        assert!(ds.poll_event().is_none());
+    }
+}
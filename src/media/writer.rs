@@ -1,8 +1,7 @@
-use std::time::Instant;
-
 use crate::format::PayloadParams;
-use crate::rtp_::VideoOrientation;
+use crate::rtp_::{RtpError, Ssrc, VideoOrientation};
 use crate::session::Session;
+use crate::util::Instant;
 use crate::RtcError;
 
 use super::{ExtensionValues, KeyframeRequestKind, Media, MediaTime, Mid, Pt, Rid, ToPayload};
@@ -18,6 +17,7 @@ pub struct Writer<'a> {
     mid: Mid,
     rid: Option<Rid>,
     ext_vals: ExtensionValues,
+    csrc: Vec<Ssrc>,
 }
 
 impl<'a> Writer<'a> {
@@ -30,6 +30,7 @@ impl<'a> Writer<'a> {
             mid,
             rid: None,
             ext_vals: ExtensionValues::default(),
+            csrc: vec![],
         }
     }
 
@@ -84,6 +85,15 @@ impl<'a> Writer<'a> {
         self
     }
 
+    /// Set the contributing sources (CSRC) of the media.
+    ///
+    /// A mixer combining the media of several sources lists the SSRC of the sources that
+    /// contributed (RFC 3550 §7.1). At most 15.
+    pub fn csrc(mut self, csrc: Vec<Ssrc>) -> Self {
+        self.csrc = csrc;
+        self
+    }
+
     /// Set a user extension value.
     pub fn user_extension_value<T: Send + Sync + 'static>(mut self, val: T) -> Self {
         self.ext_vals.user_values.set(val);
@@ -104,6 +114,16 @@ impl<'a> Writer<'a> {
     ///
     /// If you write media before `IceConnectionState` is `Connected` it will be dropped.
     ///
+    /// Fails with [`RtcError::NotSendingDirection`] if the current direction of the media
+    /// is `recvonly` or `inactive`, for instance after the remote peer paused the media via
+    /// a renegotiation.
+    ///
+    /// Fails with [`RtcError::SendQueueFull`] if the stream is not writable, see
+    /// [`StreamTx::set_queue_delay_budget()`][crate::rtp::StreamTx::set_queue_delay_budget].
+    ///
+    /// Fails with [`RtpError::TooManyCsrc`][crate::error::RtpError::TooManyCsrc] if more than
+    /// 15 contributing sources are set, see [`Writer::csrc()`].
+    ///
     /// Panics if [`RtcConfig::set_rtp_mode()`][crate::RtcConfig::set_rtp_mode] is `true`.
     pub fn write(
         self,
@@ -115,16 +135,42 @@ impl<'a> Writer<'a> {
         // This (indirect) unwrap is OK due to the invariant of self.mid being resolvable
         let media = media_by_mid_mut(&mut self.session.medias, self.mid);
 
+        if !media.direction().is_sending() {
+            return Err(RtcError::NotSendingDirection(media.direction()));
+        }
+
         if !self.session.codec_config.has_pt(pt) {
             return Err(RtcError::UnknownPt(pt));
         }
 
+        if self.csrc.len() > 15 {
+            return Err(RtpError::TooManyCsrc(self.csrc.len()).into());
+        }
+
         if let Some(rid) = self.rid {
-            if !media.rids_rx().expects(rid) && media.rids_rx().is_specific() {
+            // Each simulcast layer we send is a separate StreamTx.
+            let has_stream = self
+                .session
+                .streams
+                .stream_tx_by_mid_rid(self.mid, Some(rid))
+                .is_some();
+
+            if !has_stream {
                 return Err(RtcError::UnknownRid(rid));
             }
         }
 
+        let is_writable = self
+            .session
+            .streams
+            .stream_tx_by_mid_rid(self.mid, self.rid)
+            .map(|s| s.is_writable())
+            .unwrap_or(true);
+
+        if !is_writable {
+            return Err(RtcError::SendQueueFull);
+        }
+
         let data: Vec<u8> = data.into();
 
         trace!(
@@ -143,6 +189,7 @@ impl<'a> Writer<'a> {
             rtp_time,
             data,
             ext_vals: self.ext_vals,
+            csrc: self.csrc,
         };
 
         media.set_to_payload(to_payload)?;
@@ -150,6 +197,37 @@ impl<'a> Writer<'a> {
         Ok(())
     }
 
+    /// Write media, deriving the RTP time from the wallclock.
+    ///
+    /// This is for applications that don't keep their own RTP time for the media. Each
+    /// outgoing stream (mid and rid) has a media clock that starts from a random RTP time
+    /// and follows the `wallclock` in the clock rate of the `pt`. The clock keeps running across pauses in writing,
+    /// and continues from the last time if the clock rate changes with a new `pt`.
+    ///
+    /// Otherwise the same as [`Writer::write()`].
+    pub fn write_wallclock(
+        self,
+        pt: Pt,
+        wallclock: Instant,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<(), RtcError> {
+        let Some(params) = self
+            .session
+            .codec_config
+            .params()
+            .iter()
+            .find(|p| p.pt == pt)
+        else {
+            return Err(RtcError::UnknownPt(pt));
+        };
+        let frequency = params.spec().clock_rate;
+
+        let media = media_by_mid_mut(&mut self.session.medias, self.mid);
+        let rtp_time = media.media_time(self.rid, wallclock, frequency);
+
+        self.write(pt, wallclock, rtp_time, data)
+    }
+
     /// Test if the kind of keyframe request is possible.
     ///
     /// Sending a keyframe request requires the mechanic to be negotiated as a feedback mechanic
@@ -168,7 +246,9 @@ impl<'a> Writer<'a> {
     /// Request a keyframe from a remote peer sending media data.
     ///
     /// For SDP: This can fail if the kind of request (PLI or FIR), as specified by the
-    /// [`KeyframeRequestKind`], is not negotiated in the SDP answer/offer for this m-line.
+    /// [`KeyframeRequestKind`], is not negotiated in the SDP answer/offer for this m-line
+    /// ([`RtcError::FeedbackNotEnabled`]), or if the media is not receiving
+    /// ([`RtcError::NotReceivingDirection`]).
     ///
     /// To ensure the call will not fail, use [`Writer::is_request_keyframe_possible()`] to
     /// check whether the feedback mechanism is enabled.
@@ -192,10 +272,17 @@ impl<'a> Writer<'a> {
         rid: Option<Rid>,
         kind: KeyframeRequestKind,
     ) -> Result<(), RtcError> {
-        if !self.is_request_keyframe_possible(kind) {
+        // This unwrap is OK due to the invariant of self.mid being resolvable
+        let media = self.session.media_by_mid(self.mid).unwrap();
+
+        if !media.direction().is_receiving() {
             return Err(RtcError::NotReceivingDirection);
         }
 
+        if !self.is_request_keyframe_possible(kind) {
+            return Err(RtcError::FeedbackNotEnabled(kind));
+        }
+
         let stream = self
             .session
             .streams
@@ -2,15 +2,24 @@ use core::panic;
 use std::collections::VecDeque;
 use std::fmt;
 use std::ops::{Range, RangeInclusive};
-use std::time::Instant;
+use std::time::Duration;
+
+use bytes::Bytes;
 
 use crate::rtp_::{ExtensionValues, MediaTime, RtpHeader, SenderInfo, SeqNo};
+use crate::util::Instant;
 
 use super::contiguity::{self, Contiguity};
 use super::contiguity_vp8::Vp8Contiguity;
 use super::contiguity_vp9::Vp9Contiguity;
 use super::{CodecDepacketizer, CodecExtra, Depacketizer, PacketError, Vp8CodecExtra};
 
+/// How many times the interarrival jitter to wait for missing packets.
+const JITTER_FACTOR: f64 = 3.0;
+
+/// Decay of the observed reordering delay for every packet arriving in order.
+const REORDER_DECAY: f64 = 0.998;
+
 #[derive(Clone, PartialEq, Eq)]
 /// Holds metadata incoming RTP data.
 pub struct RtpMeta {
@@ -32,6 +41,7 @@ pub struct RtpMeta {
 pub struct Depacketized {
     pub time: MediaTime,
     pub contiguous: bool,
+    pub decode_fec: bool,
     pub meta: Vec<RtpMeta>,
     pub data: Vec<u8>,
     pub codec_extra: CodecExtra,
@@ -67,10 +77,67 @@ impl Depacketized {
     }
 }
 
+/// Statistics of the jitter buffer of one incoming media stream.
+///
+/// The jitter buffer reorders packets and assembles them into frames. It holds back
+/// up to `target_frames` frames while waiting for missing packets (retransmissions)
+/// before giving up and emitting what it has. With an adaptive delay, it also gives up
+/// once a frame has waited `target_delay`.
+///
+/// Obtained via [`Media::jitter_buffer_stats()`][crate::media::Media::jitter_buffer_stats].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct JitterBufferStats {
+    /// Time between receiving the first packet of the last emitted frame and emitting it.
+    ///
+    /// This is the value for the last frame only, not an average.
+    pub current_delay: Option<Duration>,
+    /// Number of frames held back waiting for missing packets.
+    ///
+    /// Configured using [`RtcConfig::set_reordering_size_audio()`][crate::RtcConfig::set_reordering_size_audio]
+    /// and [`RtcConfig::set_reordering_size_video()`][crate::RtcConfig::set_reordering_size_video].
+    pub target_frames: usize,
+    /// Number of packets currently held in the buffer.
+    pub packets_held: usize,
+    /// Packets that arrived after a later packet was emitted, and were dropped.
+    pub packets_late: u64,
+    /// Packets that were never received before the buffer moved past them.
+    pub packets_lost: u64,
+    /// Packets that were dropped because they were already in the buffer.
+    pub packets_duplicate: u64,
+    /// Number of frames emitted.
+    pub frames_emitted: u64,
+    /// Time between receiving the first and last packet of the last emitted frame.
+    pub frame_assembly_latency: Option<Duration>,
+    /// Audio only. Number of times packets were missing when emitting a frame, meaning
+    /// the decoder has to conceal the gap.
+    ///
+    /// Gaps in the RTP timestamp without missing sequence numbers are discontinuous
+    /// transmission (DTX) and not counted.
+    pub concealment_events: u64,
+    /// Audio only. Estimated number of samples the decoder has to conceal.
+    pub concealed_samples: u64,
+    /// Audio only. Estimated duration the decoder has to conceal.
+    pub concealed_duration: Duration,
+    /// Audio with in-band FEC only. Number of lost packets that can be recovered from
+    /// the FEC data of the packet following them.
+    ///
+    /// These are not part of [`JitterBufferStats::concealed_samples`].
+    pub fec_recoverable_losses: u64,
+    /// Audio with in-band FEC only. Estimated number of samples the decoder can recover
+    /// from FEC data.
+    pub fec_recovered_samples: u64,
+    /// How long a frame with missing packets is currently held back, when the delay is adaptive.
+    ///
+    /// Configured using [`RtcConfig::set_jitter_buffer_delay()`][crate::RtcConfig::set_jitter_buffer_delay].
+    pub target_delay: Option<Duration>,
+}
+
 #[derive(Debug)]
 struct Entry {
     meta: RtpMeta,
-    data: Vec<u8>,
+    data: Bytes,
     head: bool,
     tail: bool,
 }
@@ -85,6 +152,33 @@ pub struct DepacketizingBuffer {
     max_time: Option<MediaTime>,
     depack_cache: Option<(Range<usize>, Depacketized)>,
     contiguity: Contiguity,
+    /// Drop frames following a discontinuity until the next keyframe.
+    only_decodable: bool,
+    /// Whether we are currently dropping frames waiting for a keyframe.
+    need_keyframe: bool,
+    /// Last sequence number drained from the queue, for loss counting.
+    last_drained: Option<SeqNo>,
+    /// Whether to estimate audio concealment.
+    is_audio: bool,
+    /// Last sequence number and time of the last emitted frame, for concealment.
+    last_frame: Option<(SeqNo, MediaTime)>,
+    /// Smallest timestamp difference between two contiguous frames.
+    frame_duration: Option<u64>,
+    /// Whether the audio carries in-band FEC for the previous frame.
+    inband_fec: bool,
+    /// Bounds of the adaptive hold time.
+    adaptive_delay: Option<(Duration, Duration)>,
+    /// Interarrival jitter in seconds (RFC 3550 §6.4.1).
+    jitter: f64,
+    /// Sequence number, arrival and media time of the highest packet pushed.
+    highest: Option<(SeqNo, Instant, MediaTime)>,
+    /// Peak delay of reordered (or retransmitted) packets, decaying over time.
+    reorder_delay: Duration,
+    /// Latest time seen, from pushed packets or timeouts.
+    now: Option<Instant>,
+    /// Arrival of the first packet of the frame held back waiting for missing packets.
+    held_since: Option<Instant>,
+    stats: JitterBufferStats,
 }
 
 impl DepacketizingBuffer {
@@ -92,7 +186,9 @@ impl DepacketizingBuffer {
         let contiguity = match depack {
             CodecDepacketizer::Vp8(_) => Contiguity::Vp8(Vp8Contiguity::new()),
             CodecDepacketizer::Vp9(_) => Contiguity::Vp9(Vp9Contiguity::new()),
-            CodecDepacketizer::H264(_)
+            CodecDepacketizer::G711(_)
+            | CodecDepacketizer::G722(_)
+            | CodecDepacketizer::H264(_)
             | CodecDepacketizer::H265(_)
             | CodecDepacketizer::Boxed(_)
             | CodecDepacketizer::Opus(_)
@@ -108,10 +204,81 @@ impl DepacketizingBuffer {
             max_time: None,
             depack_cache: None,
             contiguity,
+            only_decodable: false,
+            need_keyframe: true,
+            last_drained: None,
+            is_audio: false,
+            last_frame: None,
+            frame_duration: None,
+            inband_fec: false,
+            adaptive_delay: None,
+            jitter: 0.0,
+            highest: None,
+            reorder_delay: Duration::ZERO,
+            now: None,
+            held_since: None,
+            stats: JitterBufferStats {
+                target_frames: hold_back,
+                ..Default::default()
+            },
         }
     }
 
-    pub fn push(&mut self, meta: RtpMeta, data: Vec<u8>) {
+    /// Only emit frames that are decodable, i.e. drop frames after a discontinuity
+    /// until the next keyframe.
+    ///
+    /// This only has an effect for video codecs where we can detect keyframes.
+    pub fn set_only_decodable(&mut self, enabled: bool) {
+        // We can only gate on codecs where the depacketizer detects keyframes.
+        let detects_keyframes = matches!(
+            self.depack,
+            CodecDepacketizer::H264(_)
+                | CodecDepacketizer::H265(_)
+                | CodecDepacketizer::Vp8(_)
+                | CodecDepacketizer::Vp9(_)
+        );
+        self.only_decodable = enabled && detects_keyframes;
+    }
+
+    /// Estimate concealment of audio, see [`JitterBufferStats::concealed_samples`].
+    pub fn set_is_audio(&mut self, is_audio: bool) {
+        self.is_audio = is_audio;
+    }
+
+    /// Mark frames following a loss for the decoder to recover the lost frame from FEC data.
+    ///
+    /// Only has an effect for audio, where the codec carries in-band FEC (Opus `useinbandfec`).
+    pub fn set_inband_fec(&mut self, inband_fec: bool) {
+        self.inband_fec = inband_fec;
+    }
+
+    /// Hold back frames with missing packets an adaptive time between `min` and `max`.
+    ///
+    /// The time follows the observed jitter and how late reordered or retransmitted
+    /// packets arrive. The hold back in frames still applies as an upper limit.
+    pub fn set_adaptive_delay(&mut self, min: Duration, max: Duration) {
+        self.adaptive_delay = Some((min, max.max(min)));
+    }
+
+    /// The current hold time for frames with missing packets, if adaptive.
+    pub fn target_delay(&self) -> Option<Duration> {
+        let (min, max) = self.adaptive_delay?;
+        let jitter = Duration::from_secs_f64(self.jitter * JITTER_FACTOR);
+        Some(jitter.max(self.reorder_delay).clamp(min, max))
+    }
+
+    /// When a frame held back for missing packets has waited the adaptive target delay.
+    ///
+    /// Without more packets arriving, this is what releases the frame.
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        Some(self.held_since? + self.target_delay()?)
+    }
+
+    pub fn handle_timeout(&mut self, now: Instant) {
+        self.now = Some(self.now.map_or(now, |t| t.max(now)));
+    }
+
+    pub fn push(&mut self, meta: RtpMeta, data: Bytes) {
         // We're not emitting samples in the wrong order. If we receive
         // packets that are before the last emitted, we drop.
         //
@@ -120,10 +287,17 @@ impl DepacketizingBuffer {
         if let Some((last, _)) = self.last_emitted {
             if meta.seq_no <= last && self.hold_back > 0 {
                 trace!("Drop before emitted: {} <= {}", meta.seq_no, last);
+                self.stats.packets_late += 1;
                 return;
             }
         }
 
+        self.handle_timeout(meta.received);
+
+        if self.adaptive_delay.is_some() {
+            self.observe_arrival(&meta);
+        }
+
         // Record that latest seen max time (used for extending time to u64).
         self.max_time = Some(if let Some(m) = self.max_time {
             m.max(meta.time)
@@ -138,6 +312,7 @@ impl DepacketizingBuffer {
             Ok(_) => {
                 // exact same seq_no found. ignore
                 trace!("Drop exactly same packet: {}", meta.seq_no);
+                self.stats.packets_duplicate += 1;
             }
             Err(i) => {
                 let head = self.depack.is_partition_head(&data);
@@ -156,6 +331,9 @@ impl DepacketizingBuffer {
     }
 
     pub fn pop(&mut self) -> Option<Result<Depacketized, PacketError>> {
+        // Set again below if the first frame is still held back.
+        self.held_since = None;
+
         self.update_segments();
 
         // println!(
@@ -177,6 +355,7 @@ impl DepacketizingBuffer {
             Err(e) => {
                 // this segment cannot be decoded correctly
                 // remove from the queue and return the error
+                self.count_lost(stop, seq);
                 self.last_emitted = Some((seq, CodecExtra::None));
                 self.queue.drain(0..=stop);
                 return Some(Err(e));
@@ -189,9 +368,18 @@ impl DepacketizingBuffer {
 
         let more_than_hold_back = self.segments.len() >= self.hold_back;
         let contiguous_seq = self.is_following_last(start);
-        let wait_for_contiguity = !contiguous_seq && !more_than_hold_back;
+        let first_received = self
+            .queue
+            .range(start..=stop)
+            .map(|e| e.meta.received)
+            .min()
+            .expect("entries for segment");
+        let waited_target_delay = self.has_waited_target_delay(first_received);
+        let wait_for_contiguity = !contiguous_seq && !more_than_hold_back && !waited_target_delay;
 
         if wait_for_contiguity {
+            self.held_since = Some(first_received);
+
             // if we are not sending, cache the depacked
             self.depack_cache = Some((start..stop, dep));
             return None;
@@ -207,6 +395,8 @@ impl DepacketizingBuffer {
             .meta
             .seq_no;
 
+        self.count_lost(stop, last);
+
         // We're not going to emit samples in the incorrect order, there's no point in keeping
         // stuff before the emitted range.
         self.queue.drain(0..=stop);
@@ -215,11 +405,145 @@ impl DepacketizingBuffer {
             return None;
         }
 
+        if self.only_decodable {
+            if !dep.contiguous {
+                self.need_keyframe = true;
+            }
+
+            if self.need_keyframe {
+                if !dep.codec_extra.is_keyframe() {
+                    trace!(
+                        "Drop undecodable frame before keyframe: {:?}",
+                        dep.seq_range()
+                    );
+                    self.last_emitted = Some((last, dep.codec_extra));
+                    // The next frame might be the keyframe.
+                    return self.pop();
+                }
+                self.need_keyframe = false;
+            }
+        }
+
         self.last_emitted = Some((last, dep.codec_extra));
 
+        let first = dep.first_network_time();
+        let latest = dep.meta.iter().map(|m| m.received).max().unwrap_or(first);
+        self.stats.frames_emitted += 1;
+        self.stats.frame_assembly_latency = Some(latest - first);
+        // Frames are emitted at the latest time we were driven to, either by a
+        // pushed packet or a timeout.
+        self.stats.current_delay = self.now.map(|t| t.saturating_duration_since(first));
+
+        if self.is_audio {
+            self.update_concealment(&mut dep);
+        }
+
         Some(Ok(dep))
     }
 
+    fn observe_arrival(&mut self, meta: &RtpMeta) {
+        let Some((seq, received, time)) = self.highest else {
+            self.highest = Some((meta.seq_no, meta.received, meta.time));
+            return;
+        };
+
+        if meta.seq_no <= seq {
+            // Reordered or retransmitted, arriving this long after a later packet.
+            let delay = meta.received.saturating_duration_since(received);
+            self.reorder_delay = self.reorder_delay.max(delay);
+            return;
+        }
+
+        let arrival = meta
+            .received
+            .saturating_duration_since(received)
+            .as_secs_f64();
+        let d = arrival - (meta.time.as_seconds() - time.as_seconds());
+        self.jitter += (d.abs() - self.jitter) / 16.0;
+        self.reorder_delay = self.reorder_delay.mul_f64(REORDER_DECAY);
+
+        self.highest = Some((meta.seq_no, meta.received, meta.time));
+    }
+
+    /// Whether a frame with its first packet arriving at `first` was held back the
+    /// adaptive target delay.
+    fn has_waited_target_delay(&self, first: Instant) -> bool {
+        let (Some(target), Some(now)) = (self.target_delay(), self.now) else {
+            return false;
+        };
+
+        now.saturating_duration_since(first) >= target
+    }
+
+    /// Count the packets missing between the last drained and `last`, given the
+    /// queue entries up to and including index `stop` are about to be drained.
+    fn count_lost(&mut self, stop: usize, last: SeqNo) {
+        if let Some(prev) = self.last_drained {
+            let span = (*last).saturating_sub(*prev);
+            let present = stop as u64 + 1;
+            self.stats.packets_lost += span.saturating_sub(present);
+        }
+
+        self.last_drained = Some(last);
+    }
+
+    fn update_concealment(&mut self, dep: &mut Depacketized) {
+        let first_seq = *dep.seq_range().start();
+        let last_seq = *dep.seq_range().end();
+
+        let Some((prev_seq, prev_time)) = self.last_frame.replace((last_seq, dep.time)) else {
+            return;
+        };
+
+        let Some(delta) = dep.time.checked_sub(prev_time).map(|t| t.numer()) else {
+            return;
+        };
+
+        if delta == 0 {
+            return;
+        }
+
+        if prev_seq.is_next(first_seq) {
+            // Contiguous frames, either regular or DTX. The shortest is the frame duration.
+            self.frame_duration = Some(self.frame_duration.map_or(delta, |d| d.min(delta)));
+            return;
+        }
+
+        // Packets are missing. The previous frame covers the start of the gap, and the decoder
+        // has to conceal the rest.
+        let missing = (*first_seq).saturating_sub(*prev_seq).saturating_sub(1);
+        let frame_duration = self.frame_duration.unwrap_or(delta / (missing + 1));
+        let mut concealed = delta.saturating_sub(frame_duration);
+
+        if self.inband_fec {
+            // The FEC data in this frame is a lower quality copy of the frame just before it,
+            // which means the last lost packet can be recovered.
+            dep.decode_fec = true;
+            let recovered = concealed.min(frame_duration);
+            concealed -= recovered;
+
+            self.stats.fec_recoverable_losses += 1;
+            self.stats.fec_recovered_samples += recovered;
+        }
+
+        if concealed == 0 {
+            return;
+        }
+
+        self.stats.concealment_events += 1;
+        self.stats.concealed_samples += concealed;
+        self.stats.concealed_duration +=
+            Duration::from_secs_f64(concealed as f64 / dep.time.denom() as f64);
+    }
+
+    pub fn stats(&self) -> JitterBufferStats {
+        JitterBufferStats {
+            packets_held: self.queue.len(),
+            target_delay: self.target_delay(),
+            ..self.stats.clone()
+        }
+    }
+
     fn depacketize(
         &mut self,
         start: usize,
@@ -248,6 +572,7 @@ impl DepacketizingBuffer {
         Ok(Depacketized {
             time,
             contiguous: true, // the caller taking ownership will modify this accordingly
+            decode_fec: false,
             meta,
             data,
             codec_extra,
@@ -475,6 +800,214 @@ mod test {
         ])
     }
 
+    #[test]
+    fn jitter_buffer_stats() {
+        let start = Instant::now();
+        let depack = CodecDepacketizer::Boxed(Box::new(TestDepack));
+        let mut buf = DepacketizingBuffer::new(depack, 3);
+
+        let push = |buf: &mut DepacketizingBuffer, seq: u64, time: u64, ms: u64| {
+            let meta = RtpMeta {
+                received: start + Duration::from_millis(ms),
+                seq_no: seq.into(),
+                time: MediaTime::from_90khz(time),
+                last_sender_info: None,
+                header: RtpHeader {
+                    sequence_number: seq as u16,
+                    timestamp: time as u32,
+                    ..Default::default()
+                },
+            };
+            buf.push(meta, vec![1, 9].into());
+            while let Some(r) = buf.pop() {
+                r.unwrap();
+            }
+        };
+
+        push(&mut buf, 1, 1, 0);
+        push(&mut buf, 3, 3, 20);
+        push(&mut buf, 3, 3, 21);
+        push(&mut buf, 4, 4, 40);
+
+        let stats = buf.stats();
+        assert_eq!(stats.target_frames, 3);
+        assert_eq!(stats.packets_held, 2);
+        assert_eq!(stats.packets_duplicate, 1);
+        assert_eq!(stats.frames_emitted, 1);
+
+        // Gives up waiting for 2.
+        push(&mut buf, 5, 5, 60);
+        push(&mut buf, 2, 2, 70);
+
+        let stats = buf.stats();
+        assert_eq!(stats.packets_held, 0);
+        assert_eq!(stats.packets_lost, 1);
+        assert_eq!(stats.packets_late, 1);
+        assert_eq!(stats.frames_emitted, 4);
+        assert_eq!(stats.frame_assembly_latency, Some(Duration::ZERO));
+        assert_eq!(stats.current_delay, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn audio_concealment() {
+        let depack = CodecDepacketizer::Boxed(Box::new(TestDepack));
+        let mut buf = DepacketizingBuffer::new(depack, 0);
+        buf.set_is_audio(true);
+
+        for (seq, time) in [(1, 0), (2, 900), (3, 1800), (5, 3600), (6, 9000)] {
+            let meta = RtpMeta {
+                received: Instant::now(),
+                seq_no: (seq as u64).into(),
+                time: MediaTime::from_90khz(time),
+                last_sender_info: None,
+                header: RtpHeader {
+                    sequence_number: seq,
+                    timestamp: time as u32,
+                    ..Default::default()
+                },
+            };
+            buf.push(meta, vec![1, 9].into());
+            while let Some(r) = buf.pop() {
+                r.unwrap();
+            }
+        }
+
+        let stats = buf.stats();
+        // Only the missing 4 is concealed, the jump to 6 is DTX.
+        assert_eq!(stats.concealment_events, 1);
+        assert_eq!(stats.concealed_samples, 900);
+        assert_eq!(stats.concealed_duration.as_millis(), 10);
+    }
+
+    fn push_at(buf: &mut DepacketizingBuffer, start: Instant, seq: u16, ms: u64) {
+        let meta = RtpMeta {
+            received: start + Duration::from_millis(ms),
+            seq_no: (seq as u64).into(),
+            time: MediaTime::from_90khz(seq as u64 * 1800),
+            last_sender_info: None,
+            header: RtpHeader {
+                sequence_number: seq,
+                timestamp: seq as u32 * 1800,
+                ..Default::default()
+            },
+        };
+        buf.push(meta, vec![1, 9].into());
+    }
+
+    fn pop_all(buf: &mut DepacketizingBuffer) -> Vec<u64> {
+        let mut popped = vec![];
+        while let Some(r) = buf.pop() {
+            popped.push(**r.unwrap().seq_range().start());
+        }
+        popped
+    }
+
+    #[test]
+    fn adaptive_delay() {
+        let depack = CodecDepacketizer::Boxed(Box::new(TestDepack));
+        let mut buf = DepacketizingBuffer::new(depack, 10);
+        buf.set_adaptive_delay(Duration::from_millis(20), Duration::from_millis(200));
+
+        let start = Instant::now();
+
+        // No jitter, the target is the minimum.
+        push_at(&mut buf, start, 1, 0);
+        assert_eq!(pop_all(&mut buf), [1]);
+        push_at(&mut buf, start, 2, 20);
+        assert_eq!(pop_all(&mut buf), [2]);
+        assert_eq!(buf.target_delay(), Some(Duration::from_millis(20)));
+
+        // 3 is missing. 4 is held until it waited the target delay.
+        push_at(&mut buf, start, 4, 60);
+        assert_eq!(pop_all(&mut buf), []);
+        push_at(&mut buf, start, 5, 80);
+        assert_eq!(pop_all(&mut buf), [4, 5]);
+
+        // A reordered packet arriving 50ms late raises the target.
+        push_at(&mut buf, start, 7, 120);
+        push_at(&mut buf, start, 6, 170);
+        assert_eq!(pop_all(&mut buf), [6, 7]);
+        assert_eq!(buf.target_delay(), Some(Duration::from_millis(50)));
+        assert_eq!(buf.stats().target_delay, Some(Duration::from_millis(50)));
+
+        push_at(&mut buf, start, 9, 200);
+        assert_eq!(pop_all(&mut buf), []);
+        push_at(&mut buf, start, 10, 220);
+        assert_eq!(pop_all(&mut buf), []);
+        push_at(&mut buf, start, 11, 250);
+        assert_eq!(pop_all(&mut buf), [9, 10, 11]);
+    }
+
+    #[test]
+    fn adaptive_delay_timeout() {
+        let depack = CodecDepacketizer::Boxed(Box::new(TestDepack));
+        let mut buf = DepacketizingBuffer::new(depack, 10);
+        buf.set_adaptive_delay(Duration::from_millis(20), Duration::from_millis(200));
+
+        let start = Instant::now();
+
+        push_at(&mut buf, start, 1, 0);
+        push_at(&mut buf, start, 2, 20);
+        assert_eq!(pop_all(&mut buf), [1, 2]);
+        assert_eq!(buf.poll_timeout(), None);
+
+        // 3 is missing, and nothing more arrives. The timeout releases 4.
+        push_at(&mut buf, start, 4, 60);
+        assert_eq!(pop_all(&mut buf), []);
+
+        let at = start + Duration::from_millis(80);
+        assert_eq!(buf.poll_timeout(), Some(at));
+
+        buf.handle_timeout(at - Duration::from_millis(1));
+        assert_eq!(pop_all(&mut buf), []);
+
+        buf.handle_timeout(at);
+        assert_eq!(pop_all(&mut buf), [4]);
+        assert_eq!(buf.poll_timeout(), None);
+
+        // 4 was received at 60 and emitted on the timeout at 80.
+        let stats = buf.stats();
+        assert_eq!(stats.current_delay, Some(Duration::from_millis(20)));
+        assert_eq!(stats.frame_assembly_latency, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn audio_inband_fec() {
+        let depack = CodecDepacketizer::Boxed(Box::new(TestDepack));
+        let mut buf = DepacketizingBuffer::new(depack, 0);
+        buf.set_is_audio(true);
+        buf.set_inband_fec(true);
+
+        let mut decode_fec = vec![];
+
+        for (seq, time) in [(1, 0), (2, 900), (3, 1800), (5, 3600), (6, 4500), (9, 7200)] {
+            let meta = RtpMeta {
+                received: Instant::now(),
+                seq_no: (seq as u64).into(),
+                time: MediaTime::from_90khz(time),
+                last_sender_info: None,
+                header: RtpHeader {
+                    sequence_number: seq,
+                    timestamp: time as u32,
+                    ..Default::default()
+                },
+            };
+            buf.push(meta, vec![1, 9].into());
+            while let Some(r) = buf.pop() {
+                decode_fec.push(r.unwrap().decode_fec);
+            }
+        }
+
+        assert_eq!(decode_fec, [false, false, false, true, false, true]);
+
+        let stats = buf.stats();
+        // 4 and 8 are recovered by FEC, only 7 needs concealment.
+        assert_eq!(stats.fec_recoverable_losses, 2);
+        assert_eq!(stats.fec_recovered_samples, 1800);
+        assert_eq!(stats.concealment_events, 1);
+        assert_eq!(stats.concealed_samples, 900);
+    }
+
     fn test(
         v: &[(
             u64,   // seq
@@ -533,7 +1066,7 @@ mod test {
                 },
             };
 
-            buf.push(meta, data.to_vec());
+            buf.push(meta, data.to_vec().into());
 
             let mut depacks = vec![];
             while let Some(res) = buf.pop() {
@@ -599,7 +1132,7 @@ mod test {
     #[test]
     fn rtp_out_of_order() {
         let construct_input =
-            |(time, seq, marker, cc, data): (u32, u16, bool, u16, Vec<u8>)| -> (RtpMeta, Vec<u8>) {
+            |(time, seq, marker, cc, data): (u32, u16, bool, u16, Vec<u8>)| -> (RtpMeta, Bytes) {
                 (
                     RtpMeta {
                         received: Instant::now(),
@@ -614,6 +1147,7 @@ mod test {
                             sequence_number: seq,
                             timestamp: time,
                             ssrc: Ssrc::from(2930203832),
+                            csrc: vec![],
                             ext_vals: ExtensionValues {
                                 transport_cc: Some(cc),
                                 ..Default::default()
@@ -622,7 +1156,7 @@ mod test {
                         },
                         last_sender_info: None,
                     },
-                    data,
+                    data.into(),
                 )
             };
 
@@ -1,6 +1,7 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::rtp_::DataSize;
+use crate::util::Instant;
 use crate::Bitrate;
 
 // Ported from libWebRTC's src/modules/congestion_controller/goog_cc/bitrate_estimator.cc at
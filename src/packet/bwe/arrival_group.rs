@@ -1,7 +1,8 @@
 use std::mem;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::rtp_::SeqNo;
+use crate::util::Instant;
 
 use super::AckedPacket;
 
@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use crate::rtp_::Bitrate;
+use crate::util::Instant;
+
+/// Loss fraction below which we allow the estimate to increase.
+const LOW_LOSS_THRESHOLD: f32 = 0.02;
+/// Loss fraction above which we decrease the estimate.
+const HIGH_LOSS_THRESHOLD: f32 = 0.1;
+/// Number of packets needed before we calculate a loss fraction.
+const MIN_PACKETS_FOR_LOSS: u64 = 20;
+/// Minimum time between two decreases, on top of the RTT.
+const DECREASE_INTERVAL: Duration = Duration::from_millis(300);
+/// Time between two increases.
+const INCREASE_INTERVAL: Duration = Duration::from_millis(1000);
+/// The coefficient used for multiplicative rate increase.
+const INCREASE_COEF: f64 = 1.08;
+/// Additive part of the increase, making sure we increase at low bitrates too.
+const INCREASE_ADD: Bitrate = Bitrate::kbps(1);
+
+/// Loss based bandwidth controller.
+///
+/// This is the "classic" loss based controller from libWebRTC's `SendSideBandwidthEstimation`:
+///
+/// * Less than 2% loss: increase by 8% once per second.
+/// * More than 10% loss: decrease by half the loss fraction, at most once per 300ms + RTT.
+/// * In between: hold.
+///
+/// The loss based estimate is capped by the delay based estimate, which means it can only
+/// pull the combined estimate down.
+pub(super) struct LossController {
+    estimate: Bitrate,
+    min_bitrate: Bitrate,
+
+    /// Lost packets since we last calculated a loss fraction.
+    lost: u64,
+    /// Total packets since we last calculated a loss fraction.
+    total: u64,
+
+    /// The last calculated loss fraction.
+    last_loss: Option<f32>,
+
+    last_decrease: Option<Instant>,
+    last_increase: Option<Instant>,
+}
+
+impl LossController {
+    pub(super) fn new(start_bitrate: Bitrate, min_bitrate: Bitrate) -> Self {
+        Self {
+            estimate: start_bitrate,
+            min_bitrate,
+            lost: 0,
+            total: 0,
+            last_loss: None,
+            last_decrease: None,
+            last_increase: None,
+        }
+    }
+
+    pub(super) fn set_min_bitrate(&mut self, min_bitrate: Bitrate) {
+        self.min_bitrate = min_bitrate;
+        self.estimate = self.estimate.max(min_bitrate);
+    }
+
+    /// Record the outcome of packets from a TWCC report.
+    pub(super) fn record(&mut self, lost: u64, total: u64) {
+        self.lost += lost;
+        self.total += total;
+
+        if self.total >= MIN_PACKETS_FOR_LOSS {
+            self.last_loss = Some(self.lost as f32 / self.total as f32);
+            self.lost = 0;
+            self.total = 0;
+        }
+    }
+
+    /// Update the loss based estimate given the current delay based estimate.
+    pub(super) fn update(&mut self, delay_estimate: Bitrate, rtt: Option<Duration>, now: Instant) {
+        // Never run away from the delay based estimate.
+        self.estimate = self.estimate.min(delay_estimate);
+
+        let Some(loss) = self.last_loss else {
+            return;
+        };
+
+        if loss < LOW_LOSS_THRESHOLD {
+            let due = self
+                .last_increase
+                .map(|t| now.duration_since(t) >= INCREASE_INTERVAL)
+                .unwrap_or(true);
+
+            if due {
+                self.estimate = self.estimate * INCREASE_COEF + INCREASE_ADD;
+                self.last_increase = Some(now);
+            }
+        } else if loss > HIGH_LOSS_THRESHOLD {
+            let interval = DECREASE_INTERVAL + rtt.unwrap_or_default();
+            let due = self
+                .last_decrease
+                .map(|t| now.duration_since(t) >= interval)
+                .unwrap_or(true);
+
+            if due {
+                self.estimate = self.estimate * (1.0 - 0.5 * loss as f64);
+                self.last_decrease = Some(now);
+                // Use a new loss fraction for the next decrease.
+                self.last_loss = None;
+            }
+        }
+
+        self.estimate = self.estimate.max(self.min_bitrate);
+    }
+
+    pub(super) fn estimate(&self) -> Bitrate {
+        self.estimate
+    }
+
+    pub(super) fn last_loss(&self) -> Option<f32> {
+        self.last_loss
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn decrease_on_high_loss() {
+        let now = Instant::now();
+        let mut c = LossController::new(Bitrate::kbps(1000), Bitrate::kbps(40));
+
+        c.record(10, 50);
+        c.update(Bitrate::kbps(1000), None, now);
+        assert_eq!(c.estimate().as_u64(), 900_000);
+
+        // Too soon for another decrease.
+        c.record(10, 50);
+        c.update(Bitrate::kbps(1000), None, now + Duration::from_millis(100));
+        assert_eq!(c.estimate().as_u64(), 900_000);
+    }
+
+    #[test]
+    fn hold_on_moderate_loss() {
+        let now = Instant::now();
+        let mut c = LossController::new(Bitrate::kbps(1000), Bitrate::kbps(40));
+
+        c.record(5, 100);
+        c.update(Bitrate::kbps(2000), None, now);
+        assert_eq!(c.estimate(), Bitrate::kbps(1000));
+    }
+
+    #[test]
+    fn increase_capped_by_delay_estimate() {
+        let now = Instant::now();
+        let mut c = LossController::new(Bitrate::kbps(500), Bitrate::kbps(40));
+
+        c.record(0, 100);
+        c.update(Bitrate::kbps(2000), None, now);
+        assert_eq!(c.estimate().as_u64(), 541_000);
+
+        c.update(Bitrate::kbps(300), None, now + Duration::from_secs(1));
+        assert!(c.estimate() <= Bitrate::kbps(400));
+    }
+}
@@ -40,22 +40,8 @@ macro_rules! log_rate_control_applied_change {
     }
 }
 
-macro_rules! log_pacer_media_debt {
-    ($($arg:expr),+) => {
-        crate::log_stat!("PACER_DEBT", $($arg),+, "media");
-    }
-}
-
-macro_rules! log_pacer_padding_debt {
-    ($($arg:expr),+) => {
-        crate::log_stat!("PACER_DEBT", $($arg),+, "padding");
-    }
-}
-
 pub(crate) use log_bitrate_estimate;
 pub(crate) use log_delay_variation;
-pub(crate) use log_pacer_media_debt;
-pub(crate) use log_pacer_padding_debt;
 pub(crate) use log_rate_control_applied_change;
 pub(crate) use log_rate_control_observed_bitrate;
 pub(crate) use log_rate_control_state;
@@ -3,23 +3,31 @@
 //!
 //! Much of this code has been ported from the libWebRTC implementations. The complete system has
 //! not been ported, only a smaller part that corresponds roughly to the IETF draft is implemented.
+//! The delay based estimate is combined with a loss based estimate, and the lower of the two is
+//! used as the target bitrate.
 
 mod acked_bitrate_estimator;
 mod arrival_group;
+mod loss_controller;
 pub(crate) mod macros;
+mod probe_control;
 mod rate_control;
 mod trendline_estimator;
 
 use std::cmp::Ordering;
 use std::collections::VecDeque;
 use std::fmt;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::rtp_::{Bitrate, DataSize, SeqNo, TwccSendRecord};
+use crate::stats::BweStats;
 use crate::util::already_happened;
+use crate::util::Instant;
 
 use acked_bitrate_estimator::AckedBitrateEstimator;
 use arrival_group::{ArrivalGroupAccumulator, InterGroupDelayDelta};
+use loss_controller::LossController;
+use probe_control::ProbeControl;
 use rate_control::RateControl;
 use trendline_estimator::TrendlineEstimator;
 
@@ -38,6 +46,8 @@ pub struct SendSideBandwithEstimator {
     arrival_group_accumulator: ArrivalGroupAccumulator,
     trendline_estimator: TrendlineEstimator,
     rate_control: RateControl,
+    loss_controller: LossController,
+    probe_control: ProbeControl,
     acked_bitrate_estimator: AckedBitrateEstimator,
     /// Last estimate produced, unlike [`next_estimate`] this will always have a value after the
     /// first estimate.
@@ -46,6 +56,8 @@ pub struct SendSideBandwithEstimator {
     max_rtt_history: VecDeque<Duration>,
     /// Calculated mean of max_rtt_history.
     mean_max_rtt: Option<Duration>,
+    /// RTT of the transport from STUN and RTCP, used until TWCC gives us one.
+    transport_rtt: Option<Duration>,
 
     /// The next time we should poll.
     next_timeout: Instant,
@@ -63,9 +75,12 @@ impl SendSideBandwithEstimator {
                 BITRATE_WINDOW,
             ),
             rate_control: RateControl::new(initial_bitrate, Bitrate::kbps(40), Bitrate::gbps(10)),
+            loss_controller: LossController::new(initial_bitrate, Bitrate::kbps(40)),
+            probe_control: ProbeControl::new(),
             last_estimate: None,
             max_rtt_history: VecDeque::default(),
             mean_max_rtt: None,
+            transport_rtt: None,
             next_timeout: already_happened(),
             last_twcc_report: already_happened(),
         }
@@ -80,16 +95,23 @@ impl SendSideBandwithEstimator {
         let mut acked: Vec<AckedPacket> = Vec::new();
 
         let mut max_rtt = None;
+        let mut lost = 0;
+        let mut total = 0;
         for record in records {
+            total += 1;
             let Ok(acked_packet) = record.try_into() else {
+                // Records in a TWCC report without receive time are lost.
+                lost += 1;
                 continue;
             };
             acked.push(acked_packet);
             max_rtt = max_rtt.max(record.rtt());
         }
         acked.sort_by(AckedPacket::order_by_receive_time);
+        self.loss_controller.record(lost, total);
 
         for acked_packet in acked {
+            self.probe_control.on_acked(&acked_packet);
             self.acked_bitrate_estimator
                 .update(acked_packet.remote_recv_time, acked_packet.size);
 
@@ -114,7 +136,7 @@ impl SendSideBandwithEstimator {
         self.update_estimate(
             new_hypothesis,
             self.acked_bitrate_estimator.current_estimate(),
-            self.mean_max_rtt,
+            self.rtt(),
             now,
         );
         self.last_twcc_report = now;
@@ -125,14 +147,15 @@ impl SendSideBandwithEstimator {
     }
 
     pub(crate) fn handle_timeout(&mut self, now: Instant) {
+        if let Some(achieved) = self.probe_control.poll_result(now) {
+            self.rate_control.apply_probe_result(achieved);
+        }
+
         if !self.trendline_hypothesis_valid(now) {
             // We haven't received a TWCC report in a while. The trendline hypothesis can
             // no longer be considered valid. We need another TWCC report before we can update
             // estimates.
-            let next_timeout_in = self
-                .mean_max_rtt
-                .unwrap_or(MAX_TWCC_GAP)
-                .min(UPDATE_INTERVAL);
+            let next_timeout_in = self.rtt().unwrap_or(MAX_TWCC_GAP).min(UPDATE_INTERVAL);
 
             // Set this even if we didn't update, otherwise we get stuck in a poll -> handle loop
             // that starves the run loop.
@@ -143,16 +166,65 @@ impl SendSideBandwithEstimator {
         self.update_estimate(
             self.trendline_estimator.hypothesis(),
             self.acked_bitrate_estimator.current_estimate(),
-            self.mean_max_rtt,
+            self.rtt(),
             now,
         );
     }
 
+    /// Set the RTT of the transport, which is used when TWCC hasn't given us an RTT.
+    pub(crate) fn set_transport_rtt(&mut self, rtt: Option<Duration>) {
+        self.transport_rtt = rtt;
+    }
+
+    fn rtt(&self) -> Option<Duration> {
+        self.mean_max_rtt.or(self.transport_rtt)
+    }
+
+    /// Bound the estimate between a min and max bitrate.
+    pub(crate) fn set_bitrate_bounds(&mut self, min: Bitrate, max: Bitrate) {
+        self.rate_control.set_bounds(min, max);
+        self.loss_controller.set_min_bitrate(min);
+        self.last_estimate = self.last_estimate.map(|e| e.clamp(min, max));
+    }
+
+    /// The bitrate to pad up to if we are currently probing.
+    ///
+    /// Starts a new probe cluster if one is due and we are below the desired bitrate.
+    pub(crate) fn probe_target(&mut self, desired: Bitrate, now: Instant) -> Option<Bitrate> {
+        let estimate = self.last_estimate?;
+        self.probe_control.probe_target(estimate, desired, now)
+    }
+
     /// Get the latest estimate.
     pub(crate) fn last_estimate(&self) -> Option<Bitrate> {
         self.last_estimate
     }
 
+    /// Internal state for debugging.
+    ///
+    /// The estimate, REMB cap and pacer queue delay are owned by the session.
+    pub(crate) fn stats(
+        &self,
+        estimate: Option<Bitrate>,
+        remb_cap: Option<Bitrate>,
+        pacer_queue_delay: Duration,
+    ) -> BweStats {
+        BweStats {
+            estimate,
+            delay_estimate: self.rate_control.estimated_bitrate(),
+            loss_estimate: self.loss_controller.estimate(),
+            loss_fraction: self.loss_controller.last_loss(),
+            acked_bitrate: self.acked_bitrate_estimator.current_estimate(),
+            trendline_slope: self.trendline_estimator.trend(),
+            trendline_threshold: self.trendline_estimator.threshold(),
+            overusing: self.trendline_estimator.hypothesis() == BandwithUsage::Overuse,
+            rtt: self.mean_max_rtt,
+            last_probe: self.probe_control.last_result(),
+            remb_cap,
+            pacer_queue_delay,
+        }
+    }
+
     fn add_max_rtt(&mut self, max_rtt: Duration) {
         while self.max_rtt_history.len() > MAX_RTT_HISTORY_WINDOW {
             self.max_rtt_history.pop_front();
@@ -177,9 +249,14 @@ impl SendSideBandwithEstimator {
         if let Some(observed_bitrate) = observed_bitrate {
             self.rate_control
                 .update(hypothesis.into(), observed_bitrate, mean_max_rtt, now);
-            let estimated_rate = self.rate_control.estimated_bitrate();
+            let delay_rate = self.rate_control.estimated_bitrate();
+
+            self.loss_controller.update(delay_rate, mean_max_rtt, now);
+            let estimated_rate = delay_rate.min(self.loss_controller.estimate());
 
             crate::packet::bwe::macros::log_bitrate_estimate!(estimated_rate.as_f64());
+            self.probe_control
+                .on_estimate(self.last_estimate, estimated_rate, now);
             self.last_estimate = Some(estimated_rate);
         }
 
@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use crate::rtp_::{Bitrate, DataSize};
+use crate::util::already_happened;
+use crate::util::Instant;
+
+use super::AckedPacket;
+
+/// For how long we send at the probe rate.
+const PROBE_DURATION: Duration = Duration::from_millis(150);
+/// How long to wait for TWCC feedback of a cluster after it ended.
+const PROBE_FEEDBACK_WAIT: Duration = Duration::from_millis(1000);
+/// Multiplier of the current estimate to probe at.
+const PROBE_FACTOR: f64 = 2.0;
+/// Time between probes while we are below the desired bitrate.
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+/// Time to wait before probing after a large drop in the estimate.
+const PROBE_AFTER_DROP_DELAY: Duration = Duration::from_secs(1);
+/// An estimate dropping below this ratio of the previous counts as a large drop.
+const LARGE_DROP_RATIO: f64 = 0.66;
+/// Minimum number of acked packets in a cluster for a valid result.
+const MIN_CLUSTER_PACKETS: usize = 5;
+
+/// Schedules probe clusters and measures the throughput they achieved.
+///
+/// A probe cluster is a short period where the pacer pads up to a rate above the
+/// current estimate. The TWCC feedback for the packets sent in that period tells us
+/// the rate the link delivered. A successful probe lets the estimate ramp up faster
+/// than the regular multiplicative increase.
+pub(super) struct ProbeControl {
+    cluster: Option<ProbeCluster>,
+    next_probe_at: Instant,
+    /// Target and achieved bitrate of the last finished cluster with a valid result.
+    last_result: Option<(Bitrate, Bitrate)>,
+}
+
+struct ProbeCluster {
+    target: Bitrate,
+    start: Instant,
+    end: Instant,
+    acked: DataSize,
+    acked_count: usize,
+    first_recv: Option<Instant>,
+    last_recv: Option<Instant>,
+}
+
+impl ProbeControl {
+    pub(super) fn new() -> Self {
+        Self {
+            cluster: None,
+            // Probe at startup.
+            next_probe_at: already_happened(),
+            last_result: None,
+        }
+    }
+
+    /// The rate to pad up to if we are currently probing. Starts a cluster if one is due.
+    pub(super) fn probe_target(
+        &mut self,
+        estimate: Bitrate,
+        desired: Bitrate,
+        now: Instant,
+    ) -> Option<Bitrate> {
+        if self.cluster.is_none() && now >= self.next_probe_at && estimate < desired {
+            let target = (estimate * PROBE_FACTOR).min(desired);
+            debug!("Start BWE probe cluster at {}", target);
+
+            self.cluster = Some(ProbeCluster {
+                target,
+                start: now,
+                end: now + PROBE_DURATION,
+                acked: DataSize::ZERO,
+                acked_count: 0,
+                first_recv: None,
+                last_recv: None,
+            });
+        }
+
+        let cluster = self.cluster.as_ref()?;
+        (now < cluster.end).then_some(cluster.target)
+    }
+
+    /// Account for a packet acked in a TWCC report.
+    pub(super) fn on_acked(&mut self, packet: &AckedPacket) {
+        let Some(cluster) = &mut self.cluster else {
+            return;
+        };
+
+        if packet.local_send_time < cluster.start || packet.local_send_time >= cluster.end {
+            return;
+        }
+
+        cluster.acked = cluster.acked + packet.size;
+        cluster.acked_count += 1;
+        cluster.first_recv = Some(
+            cluster
+                .first_recv
+                .map_or(packet.remote_recv_time, |t| t.min(packet.remote_recv_time)),
+        );
+        cluster.last_recv = Some(
+            cluster
+                .last_recv
+                .map_or(packet.remote_recv_time, |t| t.max(packet.remote_recv_time)),
+        );
+    }
+
+    /// The delivered bitrate of a finished cluster, if any.
+    pub(super) fn poll_result(&mut self, now: Instant) -> Option<Bitrate> {
+        let cluster = self.cluster.as_ref()?;
+
+        if now < cluster.end + PROBE_FEEDBACK_WAIT {
+            return None;
+        }
+
+        let cluster = self.cluster.take()?;
+        self.next_probe_at = now + PROBE_INTERVAL;
+
+        if cluster.acked_count < MIN_CLUSTER_PACKETS {
+            return None;
+        }
+
+        let duration = cluster.last_recv? - cluster.first_recv?;
+        if duration.is_zero() {
+            return None;
+        }
+
+        let achieved = cluster.acked / duration;
+        self.last_result = Some((cluster.target, achieved));
+        debug!(
+            "BWE probe cluster target {} achieved {}",
+            cluster.target, achieved
+        );
+
+        Some(achieved)
+    }
+
+    /// Target and achieved bitrate of the last successful probe cluster.
+    pub(super) fn last_result(&self) -> Option<(Bitrate, Bitrate)> {
+        self.last_result
+    }
+
+    /// Inform about a new estimate to detect large drops.
+    pub(super) fn on_estimate(
+        &mut self,
+        previous: Option<Bitrate>,
+        estimate: Bitrate,
+        now: Instant,
+    ) {
+        let Some(previous) = previous else {
+            return;
+        };
+
+        if estimate < previous * LARGE_DROP_RATIO {
+            self.next_probe_at = self.next_probe_at.min(now + PROBE_AFTER_DROP_DELAY);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::rtp_::SeqNo;
+
+    fn acked(seq: u64, sent: Instant, recv: Instant) -> AckedPacket {
+        AckedPacket {
+            seq_no: SeqNo::from(seq),
+            size: DataSize::bytes(1000),
+            local_send_time: sent,
+            remote_recv_time: recv,
+        }
+    }
+
+    #[test]
+    fn probe_below_desired() {
+        let now = Instant::now();
+        let mut p = ProbeControl::new();
+
+        // Already at the desired bitrate, no probing.
+        assert_eq!(
+            p.probe_target(Bitrate::kbps(500), Bitrate::kbps(500), now),
+            None
+        );
+
+        let target = p.probe_target(Bitrate::kbps(500), Bitrate::mbps(2), now);
+        assert_eq!(target, Some(Bitrate::mbps(1)));
+
+        // Cluster ends after the probe duration.
+        let after = now + PROBE_DURATION;
+        assert_eq!(
+            p.probe_target(Bitrate::kbps(500), Bitrate::mbps(2), after),
+            None
+        );
+    }
+
+    #[test]
+    fn probe_result() {
+        let now = Instant::now();
+        let mut p = ProbeControl::new();
+        p.probe_target(Bitrate::kbps(500), Bitrate::mbps(2), now);
+
+        // 10 packets of 1000 bytes received over 36ms.
+        for i in 0..10 {
+            let sent = now + Duration::from_millis(i * 4);
+            let recv = now + Duration::from_millis(50 + i * 4);
+            p.on_acked(&acked(i, sent, recv));
+        }
+
+        assert_eq!(p.poll_result(now + Duration::from_millis(200)), None);
+
+        let achieved = p.poll_result(now + PROBE_DURATION + PROBE_FEEDBACK_WAIT);
+        assert_eq!(achieved.map(|b| b.as_u64()), Some(2_222_223));
+        assert_eq!(
+            p.last_result().map(|(t, a)| (t.as_u64(), a.as_u64())),
+            Some((1_000_000, 2_222_223))
+        );
+    }
+}
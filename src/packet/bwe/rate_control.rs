@@ -1,7 +1,8 @@
 use std::fmt;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::rtp_::Bitrate;
+use crate::util::Instant;
 
 use super::BandwithUsage;
 
@@ -97,6 +98,21 @@ impl RateControl {
         self.estimated_bitrate
     }
 
+    /// Change the bounds of the estimate.
+    pub(super) fn set_bounds(&mut self, min_bitrate: Bitrate, max_bitrate: Bitrate) {
+        self.min_bitrate = min_bitrate;
+        self.max_bitrate = max_bitrate;
+        self.estimated_bitrate = self.estimated_bitrate.clamp(min_bitrate, max_bitrate);
+    }
+
+    /// Raise the estimate to a bitrate proven by a probe cluster.
+    pub(super) fn apply_probe_result(&mut self, bitrate: Bitrate) {
+        let bitrate = bitrate.clamp(self.min_bitrate, self.max_bitrate);
+        if bitrate > self.estimated_bitrate {
+            self.estimated_bitrate = bitrate;
+        }
+    }
+
     fn increase(&mut self, observed_bitrate: Bitrate, now: Instant) {
         let last_estimate_update = *self.last_estimate_update.get_or_insert(now);
 
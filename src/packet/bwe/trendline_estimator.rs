@@ -1,9 +1,11 @@
 use std::collections::VecDeque;
 use std::ops::RangeInclusive;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use super::{BandwithUsage, InterGroupDelayDelta};
 
+use crate::util::Instant;
+
 const SMOOTHING_COEF: f64 = 0.9;
 const OVER_USE_THRESHOLD_DEFAULT_MS: f64 = 12.5;
 const OVER_USE_TIME_THRESHOLD: Duration = Duration::from_millis(10);
@@ -97,6 +99,16 @@ impl TrendlineEstimator {
         }
     }
 
+    /// The slope of the last trendline fit.
+    pub(super) fn trend(&self) -> f64 {
+        self.previous_trend
+    }
+
+    /// The current adaptive threshold the modified trend is compared to.
+    pub(super) fn threshold(&self) -> f64 {
+        self.delay_threshold
+    }
+
     pub(super) fn hypothesis(&self) -> BandwithUsage {
         self.hypothesis
     }
@@ -1,4 +1,9 @@
-use super::{MediaKind, PacketError, Packetizer};
+use std::time::Duration;
+
+use super::{CodecExtra, Depacketizer, MediaKind, PacketError, Packetizer};
+
+/// Default packet time, RFC 3551 §4.5.
+pub const DEFAULT_PTIME: Duration = Duration::from_millis(20);
 
 /// Packetizes G711 RTP packets.
 pub type G711Packetizer = G7xxPacketizer;
@@ -6,8 +11,22 @@ pub type G711Packetizer = G7xxPacketizer;
 pub type G722Packetizer = G7xxPacketizer;
 
 /// Generic packetizer for G711 and G722 packets.
+///
+/// Both codecs are 8 bits per sample at an 8kHz RTP clock, one millisecond is 8 bytes.
 #[derive(Default, Debug, Copy, Clone)]
-pub struct G7xxPacketizer;
+pub struct G7xxPacketizer {
+    /// Max bytes per packet, from the packet time.
+    ptime_size: Option<usize>,
+}
+
+impl G7xxPacketizer {
+    /// Packetizer splitting the data in packets of at most `ptime` (mono).
+    pub fn with_ptime(ptime: Duration) -> Self {
+        G7xxPacketizer {
+            ptime_size: Some(ptime.as_millis() as usize * 8),
+        }
+    }
+}
 
 impl Packetizer for G7xxPacketizer {
     /// Payload fragments an G7xx packet across one or more byte arrays
@@ -16,6 +35,11 @@ impl Packetizer for G7xxPacketizer {
             return Ok(vec![]);
         }
 
+        let mtu = match self.ptime_size {
+            Some(v) if v > 0 => mtu.min(v),
+            _ => mtu,
+        };
+
         let mut payload_data_remaining = payload.len();
         let mut payload_data_index = 0;
         let mut payloads = Vec::with_capacity(payload_data_remaining / mtu);
@@ -37,6 +61,38 @@ impl Packetizer for G7xxPacketizer {
     }
 }
 
+/// Depacketizes G711 RTP packets.
+pub type G711Depacketizer = G7xxDepacketizer;
+/// Depacketizes G722 RTP packets.
+pub type G722Depacketizer = G7xxDepacketizer;
+
+/// Generic depacketizer for G711 and G722 packets.
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
+pub struct G7xxDepacketizer;
+
+impl Depacketizer for G7xxDepacketizer {
+    fn depacketize(
+        &mut self,
+        packet: &[u8],
+        out: &mut Vec<u8>,
+        _: &mut CodecExtra,
+    ) -> Result<(), PacketError> {
+        if !packet.is_empty() {
+            out.extend_from_slice(packet);
+        }
+
+        Ok(())
+    }
+
+    fn is_partition_head(&self, _payload: &[u8]) -> bool {
+        true
+    }
+
+    fn is_partition_tail(&self, _marker: bool, _payload: &[u8]) -> bool {
+        true
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -89,4 +145,22 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_g7xx_ptime() -> Result<(), PacketError> {
+        let mut pck = G711Packetizer::with_ptime(DEFAULT_PTIME);
+
+        // 50ms of audio is two full packets and one half.
+        let samples = vec![0xd5; 400];
+        let payloads = pck.packetize(1200, &samples)?;
+
+        let lens: Vec<_> = payloads.iter().map(|p| p.len()).collect();
+        assert_eq!(lens, vec![160, 160, 80]);
+
+        // The MTU still applies.
+        let payloads = pck.packetize(100, &samples)?;
+        assert!(payloads.iter().all(|p| p.len() <= 100));
+
+        Ok(())
+    }
 }
@@ -43,7 +43,7 @@ pub const OUTPUT_STAP_AHEADER: u8 = 0x78;
 pub static ANNEXB_NALUSTART_CODE: &[u8] = &[0x00, 0x00, 0x00, 0x01];
 
 impl H264Packetizer {
-    fn next_ind(nalu: &[u8], start: usize) -> (isize, isize) {
+    pub(crate) fn next_ind(nalu: &[u8], start: usize) -> (isize, isize) {
         let mut zero_count = 0;
 
         for (i, &b) in nalu[start..].iter().enumerate() {
@@ -1,7 +1,19 @@
 #![allow(clippy::all)]
 #![allow(unused)]
 
-use super::{CodecExtra, Depacketizer, PacketError};
+use super::h264::{H264Packetizer, ANNEXB_NALUSTART_CODE};
+use super::{CodecExtra, Depacketizer, PacketError, Packetizer};
+
+/// H265 information describing the depacketized / packetized data
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct H265CodecExtra {
+    /// Flag which indicates that within [`MediaData`], there is an individual frame
+    /// containing complete and independent visual information (an IRAP picture).
+    /// This frame serves as a reference point for other frames in the video sequence.
+    ///
+    /// [`MediaData`]: crate::media::MediaData
+    pub is_keyframe: bool,
+}
 
 ///
 /// Network Abstraction Unit Header implementation
@@ -9,12 +21,192 @@ use super::{CodecExtra, Depacketizer, PacketError};
 
 const H265NALU_HEADER_SIZE: usize = 2;
 /// https://datatracker.ietf.org/doc/html/rfc7798#section-4.4.2
-const H265NALU_AGGREGATION_PACKET_TYPE: u8 = 48;
+pub(crate) const H265NALU_AGGREGATION_PACKET_TYPE: u8 = 48;
 /// https://datatracker.ietf.org/doc/html/rfc7798#section-4.4.3
-const H265NALU_FRAGMENTATION_UNIT_TYPE: u8 = 49;
+pub(crate) const H265NALU_FRAGMENTATION_UNIT_TYPE: u8 = 49;
 /// https://datatracker.ietf.org/doc/html/rfc7798#section-4.4.4
 const H265NALU_PACI_PACKET_TYPE: u8 = 50;
 
+/// NAL unit types from ITU-T H.265 Table 7-1.
+const H265NALU_BLA_W_LP_TYPE: u8 = 16;
+const H265NALU_CRA_TYPE: u8 = 21;
+pub(crate) const H265NALU_VPS_TYPE: u8 = 32;
+pub(crate) const H265NALU_SPS_TYPE: u8 = 33;
+const H265NALU_PPS_TYPE: u8 = 34;
+const H265NALU_AUD_TYPE: u8 = 35;
+const H265NALU_FD_TYPE: u8 = 38;
+
+/// Whether the NAL unit type is an intra random access point (IRAP), i.e. a keyframe.
+pub(crate) fn is_irap(nalu_type: u8) -> bool {
+    (H265NALU_BLA_W_LP_TYPE..=H265NALU_CRA_TYPE).contains(&nalu_type)
+}
+
+/// Packetizes H265 RTP packets.
+///
+/// The input is an Annex B byte stream. Parameter sets (VPS, SPS, PPS) are held back
+/// and sent as an Aggregation Packet ahead of the next NAL unit. NAL units larger
+/// than the MTU are split in Fragmentation Units. DONL is never used.
+#[derive(Default, Debug, Clone)]
+pub struct H265Packetizer {
+    vps_nalu: Option<Vec<u8>>,
+    sps_nalu: Option<Vec<u8>>,
+    pps_nalu: Option<Vec<u8>>,
+}
+
+impl H265Packetizer {
+    fn emit(&mut self, nalu: &[u8], mtu: usize, payloads: &mut Vec<Vec<u8>>) {
+        if nalu.len() <= H265NALU_HEADER_SIZE {
+            return;
+        }
+
+        let header = H265NALUHeader::new(nalu[0], nalu[1]);
+
+        match header.nalu_type() {
+            H265NALU_AUD_TYPE | H265NALU_FD_TYPE => return,
+            H265NALU_VPS_TYPE => {
+                self.vps_nalu = Some(nalu.to_vec());
+                return;
+            }
+            H265NALU_SPS_TYPE => {
+                self.sps_nalu = Some(nalu.to_vec());
+                return;
+            }
+            H265NALU_PPS_TYPE => {
+                self.pps_nalu = Some(nalu.to_vec());
+                return;
+            }
+            _ => {}
+        }
+
+        self.emit_parameter_sets(mtu, payloads);
+
+        // Single NAL unit packet
+        if nalu.len() <= mtu {
+            payloads.push(nalu.to_vec());
+            return;
+        }
+
+        // Fragmentation units
+        const TOTAL_HEADER_SIZE: usize = H265NALU_HEADER_SIZE + H265FRAGMENTATION_UNIT_HEADER_SIZE;
+        if mtu <= TOTAL_HEADER_SIZE {
+            return;
+        }
+        let max_fragment_size = mtu - TOTAL_HEADER_SIZE;
+
+        // The PayloadHdr keeps F, LayerId and TID of the fragmented NAL unit, but
+        // with Type 49. The type of the NAL unit goes in the FU header.
+        let payload_header = [
+            (nalu[0] & 0b1000_0001) | (H265NALU_FRAGMENTATION_UNIT_TYPE << 1),
+            nalu[1],
+        ];
+
+        // The NAL unit header is not part of the FU payload.
+        let data = &nalu[H265NALU_HEADER_SIZE..];
+        let count = (data.len() + max_fragment_size - 1) / max_fragment_size;
+
+        for (i, fragment) in data.chunks(max_fragment_size).enumerate() {
+            let mut out = Vec::with_capacity(TOTAL_HEADER_SIZE + fragment.len());
+            out.extend_from_slice(&payload_header);
+
+            // +---------------+
+            // |0|1|2|3|4|5|6|7|
+            // +-+-+-+-+-+-+-+-+
+            // |S|E|  FuType   |
+            // +---------------+
+            let mut fu_header = header.nalu_type();
+            if i == 0 {
+                fu_header |= 1 << 7;
+            } else if i == count - 1 {
+                fu_header |= 1 << 6;
+            }
+            out.push(fu_header);
+
+            out.extend_from_slice(fragment);
+            payloads.push(out);
+        }
+    }
+
+    /// Sends held back parameter sets, as an Aggregation Packet if there are several.
+    fn emit_parameter_sets(&mut self, mtu: usize, payloads: &mut Vec<Vec<u8>>) {
+        let units: Vec<Vec<u8>> = [
+            self.vps_nalu.take(),
+            self.sps_nalu.take(),
+            self.pps_nalu.take(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if units.len() == 1 {
+            if units[0].len() <= mtu {
+                payloads.push(units[0].clone());
+            }
+            return;
+        }
+
+        if units.is_empty() {
+            return;
+        }
+
+        // The PayloadHdr of an AP uses the lowest LayerId and TID of the aggregated units.
+        let headers = units.iter().map(|u| H265NALUHeader::new(u[0], u[1]));
+        let layer_id = headers.clone().map(|h| h.layer_id()).min().unwrap_or(0) as u16;
+        let tid = headers.map(|h| h.tid()).min().unwrap_or(0) as u16;
+        let payload_header =
+            ((H265NALU_AGGREGATION_PACKET_TYPE as u16) << 9) | (layer_id << 3) | tid;
+
+        let mut ap = payload_header.to_be_bytes().to_vec();
+        for unit in &units {
+            ap.extend_from_slice(&(unit.len() as u16).to_be_bytes());
+            ap.extend_from_slice(unit);
+        }
+
+        if ap.len() <= mtu {
+            payloads.push(ap);
+        }
+    }
+}
+
+impl Packetizer for H265Packetizer {
+    /// Payload fragments a H265 Annex B byte stream across one or more byte arrays
+    fn packetize(&mut self, mtu: usize, payload: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
+        if payload.is_empty() || mtu == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut payloads = vec![];
+
+        let (mut next_ind_start, mut next_ind_len) = H264Packetizer::next_ind(payload, 0);
+        if next_ind_start == -1 {
+            self.emit(payload, mtu, &mut payloads);
+        } else {
+            while next_ind_start != -1 {
+                let prev_start = (next_ind_start + next_ind_len) as usize;
+                let (next_ind_start2, next_ind_len2) =
+                    H264Packetizer::next_ind(payload, prev_start);
+                next_ind_start = next_ind_start2;
+                next_ind_len = next_ind_len2;
+                if next_ind_start != -1 {
+                    self.emit(
+                        &payload[prev_start..next_ind_start as usize],
+                        mtu,
+                        &mut payloads,
+                    );
+                } else {
+                    // Emit until end of stream, no end indicator found
+                    self.emit(&payload[prev_start..], mtu, &mut payloads);
+                }
+            }
+        }
+
+        Ok(payloads)
+    }
+
+    fn is_marker(&mut self, _data: &[u8], _previous: Option<&[u8]>, last: bool) -> bool {
+        last
+    }
+}
+
 /// H265NALUHeader is a H265 NAL Unit Header
 /// https://datatracker.ietf.org/doc/html/rfc7798#section-1.1.4
 /// +---------------+---------------+
@@ -711,10 +903,13 @@ impl Default for H265Payload {
 }
 
 /// Depacketizes H265 RTP packets.
+///
+/// The output is an Annex B byte stream.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct H265Depacketizer {
     payload: H265Payload,
     might_need_donl: bool,
+    fu_buffer: Option<Vec<u8>>,
 }
 
 impl H265Depacketizer {
@@ -733,6 +928,23 @@ impl H265Depacketizer {
     pub fn payload(&self) -> &H265Payload {
         &self.payload
     }
+
+    fn output_nalu(out: &mut Vec<u8>, extra: &mut CodecExtra, nalu: &[u8]) {
+        if nalu.len() < H265NALU_HEADER_SIZE {
+            return;
+        }
+
+        let t = H265NALUHeader::new(nalu[0], nalu[1]).nalu_type();
+        let is_keyframe = if let CodecExtra::H265(e) = extra {
+            is_irap(t) | e.is_keyframe
+        } else {
+            is_irap(t)
+        };
+        *extra = CodecExtra::H265(H265CodecExtra { is_keyframe });
+
+        out.extend_from_slice(ANNEXB_NALUSTART_CODE);
+        out.extend_from_slice(nalu);
+    }
 }
 
 impl Depacketizer for H265Depacketizer {
@@ -741,7 +953,7 @@ impl Depacketizer for H265Depacketizer {
         &mut self,
         packet: &[u8],
         out: &mut Vec<u8>,
-        _: &mut CodecExtra,
+        extra: &mut CodecExtra,
     ) -> Result<(), PacketError> {
         if packet.len() <= H265NALU_HEADER_SIZE {
             return Err(PacketError::ErrShortPacket);
@@ -756,6 +968,9 @@ impl Depacketizer for H265Depacketizer {
             let mut decoded = H265PACIPacket::default();
             decoded.depacketize(packet)?;
 
+            // PACI is not used in WebRTC, the payload is not passed on.
+            trace!("Ignore H265 PACI packet");
+
             self.payload = H265Payload::H265PACIPacket(decoded);
         } else if header.is_fragmentation_unit() {
             let mut decoded = H265FragmentationUnitPacket::default();
@@ -763,6 +978,26 @@ impl Depacketizer for H265Depacketizer {
 
             decoded.depacketize(packet)?;
 
+            let fu_header = decoded.fu_header();
+
+            if fu_header.s() {
+                // Reconstruct the NAL unit header from the PayloadHdr and the FU type.
+                let nalu_header =
+                    (decoded.payload_header().0 & 0x81ff) | ((fu_header.fu_type() as u16) << 9);
+                self.fu_buffer = Some(nalu_header.to_be_bytes().to_vec());
+            }
+
+            // Without the start fragment, the rest of the NAL unit is discarded.
+            if let Some(fu_buffer) = &mut self.fu_buffer {
+                fu_buffer.extend_from_slice(&decoded.payload);
+            }
+
+            if fu_header.e() {
+                if let Some(fu_buffer) = self.fu_buffer.take() {
+                    Self::output_nalu(out, extra, &fu_buffer);
+                }
+            }
+
             self.payload = H265Payload::H265FragmentationUnitPacket(decoded);
         } else if header.is_aggregation_packet() {
             let mut decoded = H265AggregationPacket::default();
@@ -770,6 +1005,13 @@ impl Depacketizer for H265Depacketizer {
 
             decoded.depacketize(packet)?;
 
+            if let Some(first) = &decoded.first_unit {
+                Self::output_nalu(out, extra, &first.nal_unit);
+            }
+            for unit in &decoded.other_units {
+                Self::output_nalu(out, extra, &unit.nal_unit);
+            }
+
             self.payload = H265Payload::H265AggregationPacket(decoded);
         } else {
             let mut decoded = H265SingleNALUnitPacket::default();
@@ -777,18 +1019,36 @@ impl Depacketizer for H265Depacketizer {
 
             decoded.depacketize(packet)?;
 
+            if decoded.donl.is_some() {
+                // Leave out the DONL.
+                let mut nalu = decoded.payload_header.0.to_be_bytes().to_vec();
+                nalu.extend_from_slice(&decoded.payload);
+                Self::output_nalu(out, extra, &nalu);
+            } else {
+                Self::output_nalu(out, extra, packet);
+            }
+
             self.payload = H265Payload::H265SingleNALUnitPacket(decoded);
         }
 
-        out.extend_from_slice(packet);
-
         Ok(())
     }
 
     /// is_partition_head checks if this is the head of a packetized nalu stream.
-    fn is_partition_head(&self, _payload: &[u8]) -> bool {
-        //TODO:
-        true
+    fn is_partition_head(&self, packet: &[u8]) -> bool {
+        if packet.len() < H265NALU_HEADER_SIZE {
+            return false;
+        }
+
+        let header = H265NALUHeader::new(packet[0], packet[1]);
+        if header.is_fragmentation_unit() {
+            packet
+                .get(H265NALU_HEADER_SIZE)
+                .map(|b| H265FragmentationUnitHeader(*b).s())
+                .unwrap_or(false)
+        } else {
+            true
+        }
     }
 
     fn is_partition_tail(&self, marker: bool, _payload: &[u8]) -> bool {
@@ -1697,4 +1957,86 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_h265_packetize_roundtrip() -> Result<()> {
+        let vps = [0x40, 0x01, 0x0c, 0x01, 0xff];
+        let sps = [0x42, 0x01, 0x01, 0x01, 0x60];
+        let pps = [0x44, 0x01, 0xc0, 0xf2];
+        let mut idr = vec![0x26, 0x01];
+        idr.extend((0..3000).map(|i| (i % 251) as u8 + 2));
+
+        let mut stream = vec![];
+        for nalu in [&vps[..], &sps[..], &pps[..], &idr[..]] {
+            stream.extend_from_slice(ANNEXB_NALUSTART_CODE);
+            stream.extend_from_slice(nalu);
+        }
+
+        let mut pck = H265Packetizer::default();
+        let payloads = pck.packetize(1200, &stream)?;
+
+        // AP with the parameter sets, followed by the IDR in 3 FUs.
+        assert_eq!(payloads.len(), 4);
+        assert!(payloads.iter().all(|p| p.len() <= 1200));
+        assert_eq!(
+            H265NALUHeader::new(payloads[0][0], payloads[0][1]).nalu_type(),
+            H265NALU_AGGREGATION_PACKET_TYPE
+        );
+        for p in &payloads[1..] {
+            let header = H265NALUHeader::new(p[0], p[1]);
+            assert!(header.is_fragmentation_unit());
+            assert_eq!(header.tid(), 1);
+            assert_eq!(H265FragmentationUnitHeader(p[2]).fu_type(), 19);
+        }
+
+        let mut depack = H265Depacketizer::default();
+        let heads: Vec<_> = payloads
+            .iter()
+            .map(|p| depack.is_partition_head(p))
+            .collect();
+        assert_eq!(heads, vec![true, true, false, false]);
+
+        let mut out = vec![];
+        let mut extra = CodecExtra::None;
+        for p in &payloads {
+            depack.depacketize(p, &mut out, &mut extra)?;
+        }
+
+        assert_eq!(out, stream);
+        assert!(extra.is_keyframe());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_h265_packetize_single_nalu() -> Result<()> {
+        let stream = [0x00, 0x00, 0x01, 0x02, 0x01, 0xaa, 0xbb];
+
+        let mut pck = H265Packetizer::default();
+        let payloads = pck.packetize(1200, &stream)?;
+        assert_eq!(payloads, vec![vec![0x02, 0x01, 0xaa, 0xbb]]);
+
+        let mut depack = H265Depacketizer::default();
+        let mut out = vec![];
+        let mut extra = CodecExtra::None;
+        depack.depacketize(&payloads[0], &mut out, &mut extra)?;
+
+        assert_eq!(out, [0x00, 0x00, 0x00, 0x01, 0x02, 0x01, 0xaa, 0xbb]);
+        assert!(!extra.is_keyframe());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_h265_depacketize_fu_without_start() -> Result<()> {
+        let mut depack = H265Depacketizer::default();
+        let mut out = vec![];
+        let mut extra = CodecExtra::None;
+
+        // End fragment of a NAL unit whose start was lost.
+        depack.depacketize(&[0x62, 0x01, 0x53, 0xaa, 0xbb], &mut out, &mut extra)?;
+        assert!(out.is_empty());
+
+        Ok(())
+    }
 }
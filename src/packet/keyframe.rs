@@ -0,0 +1,270 @@
+//! Keyframe detection on single RTP payloads.
+//!
+//! These only look at the payload descriptor and the first bytes of the codec data,
+//! without depacketizing, to be cheap enough to run on every forwarded packet.
+
+use crate::format::Codec;
+
+use super::h264::{FUA_NALU_TYPE, FU_START_BITMASK, NALU_TYPE_BITMASK};
+use super::h264::{IDR_NALU_TYPE, SPS_NALU_TYPE, STAPA_NALU_TYPE};
+use super::h265::{is_irap, H265NALU_AGGREGATION_PACKET_TYPE};
+use super::h265::{H265NALU_FRAGMENTATION_UNIT_TYPE, H265NALU_SPS_TYPE, H265NALU_VPS_TYPE};
+
+/// Whether the RTP payload is the first packet of a keyframe.
+pub(crate) fn is_keyframe_start(codec: Codec, payload: &[u8]) -> bool {
+    match codec {
+        Codec::Vp8 => vp8(payload),
+        Codec::Vp9 => vp9(payload),
+        Codec::H264 => h264(payload),
+        Codec::H265 => h265(payload),
+        Codec::Av1 => av1(payload),
+        _ => false,
+    }
+}
+
+/// RFC 7741 §4.2 and §9.1.
+fn vp8(payload: &[u8]) -> bool {
+    let Some(&b0) = payload.first() else {
+        return false;
+    };
+
+    // S bit set and partition index 0.
+    if b0 & 0x17 != 0x10 {
+        return false;
+    }
+
+    let mut i = 1;
+
+    if b0 & 0x80 != 0 {
+        let Some(&x) = payload.get(i) else {
+            return false;
+        };
+        i += 1;
+
+        // I
+        if x & 0x80 != 0 {
+            let Some(&pid) = payload.get(i) else {
+                return false;
+            };
+            // M bit means 15 bit picture id.
+            i += if pid & 0x80 != 0 { 2 } else { 1 };
+        }
+
+        // L
+        if x & 0x40 != 0 {
+            i += 1;
+        }
+
+        // T or K
+        if x & 0x30 != 0 {
+            i += 1;
+        }
+    }
+
+    // The P bit of the VP8 payload header is 0 for keyframes.
+    payload.get(i).map(|b| b & 0x01 == 0).unwrap_or(false)
+}
+
+/// draft-ietf-payload-vp9 §4.2.
+fn vp9(payload: &[u8]) -> bool {
+    let Some(&b0) = payload.first() else {
+        return false;
+    };
+
+    let i = b0 & 0x80 != 0;
+    let p = b0 & 0x40 != 0;
+    let l = b0 & 0x20 != 0;
+    let b = b0 & 0x08 != 0;
+
+    // Start of a frame not predicted from an earlier frame.
+    if !b || p {
+        return false;
+    }
+
+    if !l {
+        return true;
+    }
+
+    let mut idx = 1;
+
+    if i {
+        let Some(&pid) = payload.get(idx) else {
+            return false;
+        };
+        idx += if pid & 0x80 != 0 { 2 } else { 1 };
+    }
+
+    // Only the lowest spatial layer starts the keyframe.
+    payload
+        .get(idx)
+        .map(|layer| (layer >> 1) & 0x07 == 0)
+        .unwrap_or(false)
+}
+
+/// RFC 6184 §5.
+///
+/// A keyframe either starts with the SPS, or with the IDR if the SPS is not repeated.
+fn h264(payload: &[u8]) -> bool {
+    let Some(&b0) = payload.first() else {
+        return false;
+    };
+
+    let is_start = |t: u8| t == IDR_NALU_TYPE || t == SPS_NALU_TYPE;
+
+    match b0 & NALU_TYPE_BITMASK {
+        STAPA_NALU_TYPE => {
+            let mut i = 1;
+            while i + 2 < payload.len() {
+                let size = u16::from_be_bytes([payload[i], payload[i + 1]]) as usize;
+                if is_start(payload[i + 2] & NALU_TYPE_BITMASK) {
+                    return true;
+                }
+                i += 2 + size;
+            }
+            false
+        }
+        FUA_NALU_TYPE => payload
+            .get(1)
+            .map(|h| h & FU_START_BITMASK != 0 && is_start(h & NALU_TYPE_BITMASK))
+            .unwrap_or(false),
+        t => is_start(t),
+    }
+}
+
+/// RFC 7798 §4.4.
+///
+/// A keyframe starts with the parameter sets, or with the IRAP picture if they are not repeated.
+fn h265(payload: &[u8]) -> bool {
+    if payload.len() < 2 {
+        return false;
+    }
+
+    let is_start = |t: u8| is_irap(t) || t == H265NALU_VPS_TYPE || t == H265NALU_SPS_TYPE;
+
+    match (payload[0] >> 1) & 0x3f {
+        H265NALU_AGGREGATION_PACKET_TYPE => {
+            // Assumes no DONL, since sprop-max-don-diff is not negotiated.
+            let mut i = 2;
+            while i + 2 < payload.len() {
+                let size = u16::from_be_bytes([payload[i], payload[i + 1]]) as usize;
+                if is_start((payload[i + 2] >> 1) & 0x3f) {
+                    return true;
+                }
+                i += 2 + size;
+            }
+            false
+        }
+        H265NALU_FRAGMENTATION_UNIT_TYPE => payload
+            .get(2)
+            .map(|h| h & 0x80 != 0 && is_start(h & 0x3f))
+            .unwrap_or(false),
+        t => is_start(t),
+    }
+}
+
+/// AV1 RTP specification §4.4.
+///
+/// The N bit of the aggregation header is set on the first packet of a coded video sequence,
+/// which starts with a keyframe.
+fn av1(payload: &[u8]) -> bool {
+    // Z (continuation of an OBU) unset and N set.
+    payload.first().map(|b| b & 0x88 == 0x08).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn vp8_keyframe_start() {
+        // S, PID 0, no extension. P bit 0.
+        assert!(is_keyframe_start(Codec::Vp8, &[0x10, 0x50, 0x01]));
+        // P bit 1, delta frame.
+        assert!(!is_keyframe_start(Codec::Vp8, &[0x10, 0x51, 0x01]));
+        // Not the start of the partition.
+        assert!(!is_keyframe_start(Codec::Vp8, &[0x00, 0x50, 0x01]));
+        // X, I with 15 bit picture id, L, T.
+        assert!(is_keyframe_start(
+            Codec::Vp8,
+            &[0x90, 0xe0, 0x81, 0x23, 0x05, 0x40, 0x50]
+        ));
+        assert!(!is_keyframe_start(
+            Codec::Vp8,
+            &[0x90, 0xe0, 0x81, 0x23, 0x05, 0x40, 0x51]
+        ));
+        // Truncated.
+        assert!(!is_keyframe_start(Codec::Vp8, &[0x90, 0xe0, 0x81]));
+    }
+
+    #[test]
+    fn vp9_keyframe_start() {
+        // B, not P, no layer indices.
+        assert!(is_keyframe_start(Codec::Vp9, &[0x08, 0x00]));
+        // P
+        assert!(!is_keyframe_start(Codec::Vp9, &[0x48, 0x00]));
+        // Not B
+        assert!(!is_keyframe_start(Codec::Vp9, &[0x00, 0x00]));
+        // I with 15 bit picture id, L with spatial layer 0.
+        assert!(is_keyframe_start(
+            Codec::Vp9,
+            &[0xa8, 0x81, 0x23, 0x00, 0x00]
+        ));
+        // Spatial layer 1.
+        assert!(!is_keyframe_start(
+            Codec::Vp9,
+            &[0xa8, 0x81, 0x23, 0x02, 0x00]
+        ));
+    }
+
+    #[test]
+    fn h264_keyframe_start() {
+        // SPS
+        assert!(is_keyframe_start(Codec::H264, &[0x67, 0x42]));
+        // IDR
+        assert!(is_keyframe_start(Codec::H264, &[0x65, 0x88]));
+        // Non-IDR slice
+        assert!(!is_keyframe_start(Codec::H264, &[0x41, 0x9a]));
+        // STAP-A with SPS and PPS.
+        assert!(is_keyframe_start(
+            Codec::H264,
+            &[0x78, 0x00, 0x02, 0x67, 0x42, 0x00, 0x02, 0x68, 0xce]
+        ));
+        // FU-A start of IDR.
+        assert!(is_keyframe_start(Codec::H264, &[0x7c, 0x85, 0x88]));
+        // FU-A middle of IDR.
+        assert!(!is_keyframe_start(Codec::H264, &[0x7c, 0x05, 0x88]));
+    }
+
+    #[test]
+    fn h265_keyframe_start() {
+        // VPS
+        assert!(is_keyframe_start(Codec::H265, &[0x40, 0x01, 0x0c]));
+        // IDR_W_RADL
+        assert!(is_keyframe_start(Codec::H265, &[0x26, 0x01, 0xaf]));
+        // TRAIL_R
+        assert!(!is_keyframe_start(Codec::H265, &[0x02, 0x01, 0xd0]));
+        // AP with VPS and SPS.
+        assert!(is_keyframe_start(
+            Codec::H265,
+            &[0x60, 0x01, 0x00, 0x02, 0x40, 0x01, 0x00, 0x02, 0x42, 0x01]
+        ));
+        // FU start of IDR_W_RADL.
+        assert!(is_keyframe_start(Codec::H265, &[0x62, 0x01, 0x93, 0xaf]));
+        // FU end of IDR_W_RADL.
+        assert!(!is_keyframe_start(Codec::H265, &[0x62, 0x01, 0x53, 0xaf]));
+    }
+
+    #[test]
+    fn av1_keyframe_start() {
+        assert!(is_keyframe_start(Codec::Av1, &[0x18]));
+        assert!(!is_keyframe_start(Codec::Av1, &[0x10]));
+        // Z, continuation of an OBU.
+        assert!(!is_keyframe_start(Codec::Av1, &[0x88]));
+    }
+
+    #[test]
+    fn audio_is_not_keyframe() {
+        assert!(!is_keyframe_start(Codec::Opus, &[0x10, 0x50]));
+        assert!(!is_keyframe_start(Codec::Vp8, &[]));
+    }
+}
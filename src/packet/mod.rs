@@ -3,13 +3,14 @@
 
 use std::fmt;
 use std::panic::UnwindSafe;
+use std::time::Duration;
 use thiserror::Error;
 
 use crate::format::Codec;
 use crate::sdp::MediaType;
 
 mod g7xx;
-use g7xx::{G711Packetizer, G722Packetizer};
+use g7xx::{G711Depacketizer, G711Packetizer, G722Depacketizer, G722Packetizer, DEFAULT_PTIME};
 
 mod h264;
 pub use h264::H264CodecExtra;
@@ -19,7 +20,11 @@ mod h264_profile;
 pub(crate) use h264_profile::H264ProfileLevel;
 
 mod h265;
-use h265::H265Depacketizer;
+pub use h265::H265CodecExtra;
+use h265::{H265Depacketizer, H265Packetizer};
+
+mod keyframe;
+pub(crate) use keyframe::is_keyframe_start;
 
 mod opus;
 use opus::{OpusDepacketizer, OpusPacketizer};
@@ -32,19 +37,33 @@ mod vp9;
 pub use vp9::Vp9CodecExtra;
 use vp9::{Vp9Depacketizer, Vp9Packetizer};
 
+mod vp9_forwarding;
+pub use vp9_forwarding::Vp9Forwarding;
+
 mod null;
 use null::{NullDepacketizer, NullPacketizer};
 
+#[cfg(feature = "sample-api")]
 mod buffer_rx;
+#[cfg(feature = "sample-api")]
+pub use buffer_rx::JitterBufferStats;
+#[cfg(feature = "sample-api")]
 pub(crate) use buffer_rx::{Depacketized, DepacketizingBuffer, RtpMeta};
+#[cfg(feature = "sample-api")]
 mod contiguity;
+#[cfg(feature = "sample-api")]
 mod contiguity_vp8;
+#[cfg(feature = "sample-api")]
 mod contiguity_vp9;
 
+#[cfg(feature = "sample-api")]
 mod payload;
+#[cfg(feature = "sample-api")]
 pub(crate) use payload::Payloader;
 
+#[cfg(feature = "bwe")]
 mod bwe;
+#[cfg(feature = "bwe")]
 pub(crate) use bwe::SendSideBandwithEstimator;
 
 mod pacer;
@@ -94,6 +113,24 @@ pub enum CodecExtra {
     Vp9(Vp9CodecExtra),
     /// Codec extra parameters for H264.
     H264(H264CodecExtra),
+    /// Codec extra parameters for H265.
+    H265(H265CodecExtra),
+}
+
+impl CodecExtra {
+    /// Whether the data is a keyframe.
+    ///
+    /// Always `false` for [`CodecExtra::None`], i.e. for codecs where str0m doesn't
+    /// inspect the payload, such as audio codecs.
+    pub fn is_keyframe(&self) -> bool {
+        match self {
+            CodecExtra::None => false,
+            CodecExtra::Vp8(e) => e.is_keyframe,
+            CodecExtra::Vp9(e) => e.is_keyframe,
+            CodecExtra::H264(e) => e.is_keyframe,
+            CodecExtra::H265(e) => e.is_keyframe,
+        }
+    }
 }
 
 /// Depacketizes an RTP payload.
@@ -139,6 +176,8 @@ pub enum PacketError {
     NaluTypeIsNotHandled(u8),
     #[error("VP9 corrupted packet")]
     ErrVP9CorruptedPacket,
+    #[error("No packetizer or depacketizer for codec: {0}")]
+    UnsupportedCodec(Codec),
 }
 
 /// Helper to replace Bytes. Provides get_u8 and get_u16 over some buffer of bytes.
@@ -187,7 +226,7 @@ pub(crate) enum CodecPacketizer {
     G711(G711Packetizer),
     G722(G722Packetizer),
     H264(H264Packetizer),
-    // H265() TODO
+    H265(H265Packetizer),
     Opus(OpusPacketizer),
     Vp8(Vp8Packetizer),
     Vp9(Vp9Packetizer),
@@ -197,6 +236,8 @@ pub(crate) enum CodecPacketizer {
 
 #[derive(Debug)]
 pub(crate) enum CodecDepacketizer {
+    G711(G711Depacketizer),
+    G722(G722Depacketizer),
     H264(H264Depacketizer),
     H265(H265Depacketizer),
     Opus(OpusDepacketizer),
@@ -206,37 +247,54 @@ pub(crate) enum CodecDepacketizer {
     Boxed(Box<dyn Depacketizer + Send + Sync + UnwindSafe>),
 }
 
-impl From<Codec> for CodecPacketizer {
-    fn from(c: Codec) -> Self {
-        match c {
+impl CodecPacketizer {
+    /// Use another packet time than the default, for the codecs that have one.
+    pub(crate) fn with_ptime(self, ptime: Duration) -> Self {
+        match self {
+            CodecPacketizer::G711(_) => CodecPacketizer::G711(G711Packetizer::with_ptime(ptime)),
+            CodecPacketizer::G722(_) => CodecPacketizer::G722(G722Packetizer::with_ptime(ptime)),
+            v => v,
+        }
+    }
+}
+
+impl TryFrom<Codec> for CodecPacketizer {
+    type Error = PacketError;
+
+    fn try_from(c: Codec) -> Result<Self, Self::Error> {
+        Ok(match c {
             Codec::Opus => CodecPacketizer::Opus(OpusPacketizer),
+            Codec::Pcmu | Codec::Pcma => {
+                CodecPacketizer::G711(G711Packetizer::with_ptime(DEFAULT_PTIME))
+            }
+            Codec::G722 => CodecPacketizer::G722(G722Packetizer::with_ptime(DEFAULT_PTIME)),
             Codec::H264 => CodecPacketizer::H264(H264Packetizer::default()),
-            Codec::H265 => unimplemented!("Missing packetizer for H265"),
+            Codec::H265 => CodecPacketizer::H265(H265Packetizer::default()),
             Codec::Vp8 => CodecPacketizer::Vp8(Vp8Packetizer::default()),
             Codec::Vp9 => CodecPacketizer::Vp9(Vp9Packetizer::default()),
-            Codec::Av1 => unimplemented!("Missing packetizer for AV1"),
             Codec::Null => CodecPacketizer::Null(NullPacketizer),
-            Codec::Rtx => panic!("Cant instantiate packetizer for RTX codec"),
-            Codec::Unknown => panic!("Cant instantiate packetizer for unknown codec"),
-            _ => panic!("Cant instantiate packetizer for unhandled codec"),
-        }
+            // AV1 is missing a packetizer, and RTX/unknown can't have one.
+            _ => return Err(PacketError::UnsupportedCodec(c)),
+        })
     }
 }
 
-impl From<Codec> for CodecDepacketizer {
-    fn from(c: Codec) -> Self {
-        match c {
+impl TryFrom<Codec> for CodecDepacketizer {
+    type Error = PacketError;
+
+    fn try_from(c: Codec) -> Result<Self, Self::Error> {
+        Ok(match c {
             Codec::Opus => CodecDepacketizer::Opus(OpusDepacketizer),
+            Codec::Pcmu | Codec::Pcma => CodecDepacketizer::G711(G711Depacketizer::default()),
+            Codec::G722 => CodecDepacketizer::G722(G722Depacketizer::default()),
             Codec::H264 => CodecDepacketizer::H264(H264Depacketizer::default()),
             Codec::H265 => CodecDepacketizer::H265(H265Depacketizer::default()),
             Codec::Vp8 => CodecDepacketizer::Vp8(Vp8Depacketizer::default()),
             Codec::Vp9 => CodecDepacketizer::Vp9(Vp9Depacketizer::default()),
-            Codec::Av1 => unimplemented!("Missing depacketizer for AV1"),
             Codec::Null => CodecDepacketizer::Null(NullDepacketizer),
-            Codec::Rtx => panic!("Cant instantiate depacketizer for RTX codec"),
-            Codec::Unknown => panic!("Cant instantiate depacketizer for unknown codec"),
-            _ => panic!("Cant instantiate packetizer for unhandled codec"),
-        }
+            // AV1 is missing a depacketizer, and RTX/unknown can't have one.
+            _ => return Err(PacketError::UnsupportedCodec(c)),
+        })
     }
 }
 
@@ -247,6 +305,7 @@ impl Packetizer for CodecPacketizer {
             G711(v) => v.packetize(mtu, b),
             G722(v) => v.packetize(mtu, b),
             H264(v) => v.packetize(mtu, b),
+            H265(v) => v.packetize(mtu, b),
             Opus(v) => v.packetize(mtu, b),
             Vp8(v) => v.packetize(mtu, b),
             Vp9(v) => v.packetize(mtu, b),
@@ -261,6 +320,7 @@ impl Packetizer for CodecPacketizer {
             CodecPacketizer::G722(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Opus(v) => v.is_marker(data, previous, last),
             CodecPacketizer::H264(v) => v.is_marker(data, previous, last),
+            CodecPacketizer::H265(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Vp8(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Vp9(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Null(v) => v.is_marker(data, previous, last),
@@ -278,6 +338,8 @@ impl Depacketizer for CodecDepacketizer {
     ) -> Result<(), PacketError> {
         use CodecDepacketizer::*;
         match self {
+            G711(v) => v.depacketize(packet, out, extra),
+            G722(v) => v.depacketize(packet, out, extra),
             H264(v) => v.depacketize(packet, out, extra),
             H265(v) => v.depacketize(packet, out, extra),
             Opus(v) => v.depacketize(packet, out, extra),
@@ -291,6 +353,8 @@ impl Depacketizer for CodecDepacketizer {
     fn is_partition_head(&self, packet: &[u8]) -> bool {
         use CodecDepacketizer::*;
         match self {
+            G711(v) => v.is_partition_head(packet),
+            G722(v) => v.is_partition_head(packet),
             H264(v) => v.is_partition_head(packet),
             H265(v) => v.is_partition_head(packet),
             Opus(v) => v.is_partition_head(packet),
@@ -304,6 +368,8 @@ impl Depacketizer for CodecDepacketizer {
     fn is_partition_tail(&self, marker: bool, packet: &[u8]) -> bool {
         use CodecDepacketizer::*;
         match self {
+            G711(v) => v.is_partition_tail(marker, packet),
+            G722(v) => v.is_partition_tail(marker, packet),
             H264(v) => v.is_partition_tail(marker, packet),
             H265(v) => v.is_partition_tail(marker, packet),
             Opus(v) => v.is_partition_tail(marker, packet),
@@ -333,3 +399,22 @@ impl fmt::Display for MediaKind {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unsupported_codec_is_error() {
+        for codec in [Codec::Av1, Codec::Rtx, Codec::Unknown] {
+            let err = CodecPacketizer::try_from(codec).unwrap_err();
+            assert_eq!(err, PacketError::UnsupportedCodec(codec));
+
+            let err = CodecDepacketizer::try_from(codec).unwrap_err();
+            assert_eq!(err, PacketError::UnsupportedCodec(codec));
+        }
+
+        assert!(CodecPacketizer::try_from(Codec::Opus).is_ok());
+        assert!(CodecDepacketizer::try_from(Codec::Vp8).is_ok());
+    }
+}
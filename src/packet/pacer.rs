@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 use std::fmt;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::rtp_::MAX_BLANK_PADDING_PAYLOAD_SIZE;
 use crate::rtp_::{Bitrate, DataSize, Mid};
 use crate::util::already_happened;
 use crate::util::not_happening;
+use crate::util::Instant;
 use crate::util::Soonest;
 
 use super::MediaKind;
@@ -16,6 +17,18 @@ const MAX_PADDING_PACKET_SIZE: DataSize = DataSize::bytes(MAX_BLANK_PADDING_PAYL
 const PADDING_BURST_INTERVAL: Duration = Duration::from_millis(5);
 const PACING: Duration = Duration::from_millis(40);
 
+macro_rules! log_pacer_media_debt {
+    ($($arg:expr),+) => {
+        crate::log_stat!("PACER_DEBT", $($arg),+, "media");
+    }
+}
+
+macro_rules! log_pacer_padding_debt {
+    ($($arg:expr),+) => {
+        crate::log_stat!("PACER_DEBT", $($arg),+, "padding");
+    }
+}
+
 pub enum PacerImpl {
     Null(NullPacer),
     LeakyBucket(LeakyBucketPacer),
@@ -365,7 +378,7 @@ impl Pacer for LeakyBucketPacer {
         self.media_debt = self
             .media_debt
             .min(self.adjusted_bitrate * MAX_DEBT_IN_TIME);
-        crate::packet::bwe::macros::log_pacer_media_debt!(self.media_debt.as_bytes_usize());
+        log_pacer_media_debt!(self.media_debt.as_bytes_usize());
         self.add_padding_debt(packet_size);
     }
 }
@@ -409,8 +422,8 @@ impl LeakyBucketPacer {
         self.padding_debt = self
             .padding_debt
             .saturating_sub(self.padding_bitrate * elapsed);
-        crate::packet::bwe::macros::log_pacer_media_debt!(self.media_debt.as_bytes_usize());
-        crate::packet::bwe::macros::log_pacer_padding_debt!(self.padding_debt.as_bytes_usize());
+        log_pacer_media_debt!(self.media_debt.as_bytes_usize());
+        log_pacer_padding_debt!(self.padding_debt.as_bytes_usize());
     }
 
     fn next_poll(&self, now: Instant) -> Option<(Instant, Option<&QueueState>)> {
@@ -534,7 +547,7 @@ impl LeakyBucketPacer {
         self.padding_debt = self
             .padding_debt
             .min(self.padding_bitrate * MAX_DEBT_IN_TIME);
-        crate::packet::bwe::macros::log_pacer_padding_debt!(self.padding_debt.as_bytes_usize());
+        log_pacer_padding_debt!(self.padding_debt.as_bytes_usize());
     }
 
     /// Optimistically attempt to create a padding request.
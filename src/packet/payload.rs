@@ -2,12 +2,13 @@ use std::collections::HashMap;
 use std::collections::{BTreeMap, VecDeque};
 
 use std::fmt;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::format::CodecSpec;
 use crate::media::ToPayload;
 use crate::rtp_::{ExtensionValues, Frequency, MediaTime, Rid, RtpHeader, SeqNo, Ssrc};
 use crate::streams::StreamTx;
+use crate::util::Instant;
 
 use super::{CodecPacketizer, PacketError, Packetizer, QueueSnapshot};
 use super::{MediaKind, QueuePriority};
@@ -16,14 +17,27 @@ use super::{MediaKind, QueuePriority};
 pub struct Payloader {
     pack: CodecPacketizer,
     clock_rate: Frequency,
+    /// For G.711/G.722, the number of channels. Each packet advances the RTP time
+    /// by the number of samples in the previous packets.
+    g7xx_channels: Option<u32>,
 }
 
 impl Payloader {
-    pub(crate) fn new(spec: CodecSpec) -> Self {
-        Payloader {
-            pack: spec.codec.into(),
-            clock_rate: spec.clock_rate,
+    /// The `ptime` is the packet time the remote wants, which G.711 and G.722 follow.
+    pub(crate) fn new(spec: CodecSpec, ptime: Option<Duration>) -> Result<Self, PacketError> {
+        let mut pack: CodecPacketizer = spec.codec.try_into()?;
+        if let Some(ptime) = ptime {
+            pack = pack.with_ptime(ptime);
         }
+
+        Ok(Payloader {
+            pack,
+            clock_rate: spec.clock_rate,
+            g7xx_channels: spec
+                .codec
+                .is_g7xx()
+                .then(|| spec.channels.unwrap_or(1).max(1) as u32),
+        })
     }
 
     pub(crate) fn push_sample(
@@ -41,8 +55,13 @@ impl Payloader {
             rtp_time,
             data,
             ext_vals,
+            csrc,
         } = to_payload;
 
+        stream
+            .set_csrc(&csrc)
+            .expect("CSRC count checked in Writer::write");
+
         let chunks = self.pack.packetize(mtu, &data)?;
         let len = chunks.len();
 
@@ -57,6 +76,11 @@ impl Payloader {
             let previous_data = stream.last_packet();
             let marker = self.pack.is_marker(data.as_slice(), previous_data, last);
 
+            let rtp_offset = match self.g7xx_channels {
+                Some(channels) => data_len as u32 / channels,
+                None => 0,
+            };
+
             data_len += data.len();
 
             let seq_no = stream.next_seq_no();
@@ -67,7 +91,7 @@ impl Payloader {
             stream.write_rtp(
                 pt,
                 seq_no,
-                rtp_time.rebase(self.clock_rate).numer() as u32,
+                (rtp_time.rebase(self.clock_rate).numer() as u32).wrapping_add(rtp_offset),
                 wallclock,
                 marker,
                 ext_vals.clone(),
@@ -7,7 +7,7 @@ use std::sync::Arc;
 
 /// Flexible mode 15 bit picture ID
 const VP9HEADER_SIZE: usize = 3;
-const MAX_SPATIAL_LAYERS: usize = 3;
+pub(crate) const MAX_SPATIAL_LAYERS: usize = 3;
 const MAX_VP9REF_PICS: usize = 3;
 
 /// Vp9 information describing the depacketized/packetized data.
@@ -43,6 +43,9 @@ pub struct Vp9CodecExtra {
     ///
     /// [`MediaData::data`]: crate::media::MediaData::data
     ///
+    /// The same narrowing can be done by str0m using
+    /// [`StreamRx::set_svc_target()`][crate::rtp::StreamRx::set_svc_target].
+    ///
     /// ## Example
     ///
     /// Say you have a VP9 track working in "L3T3" scalability mode and you
@@ -95,6 +98,20 @@ pub struct Vp9CodecExtra {
     /// Picture ID.
     pub pid: u16,
 
+    /// Whether the stream is in flexible mode, where each frame lists its references.
+    ///
+    /// See [`Vp9Forwarding`][crate::media::Vp9Forwarding].
+    pub flexible: bool,
+
+    /// Reference indices (P_DIFF) for each spatial layer in flexible mode.
+    ///
+    /// The referenced picture id is `pid - pdiff`. Unused entries are 0, all 0 for a layer
+    /// that is not predicted from earlier pictures.
+    pub layers_pdiff: [[u8; MAX_VP9REF_PICS]; MAX_SPATIAL_LAYERS],
+
+    /// Whether each spatial layer depends on the lower spatial layer of the same picture.
+    pub layers_inter_dependent: [bool; MAX_SPATIAL_LAYERS],
+
     /// Flag which indicates that within [`MediaData`], there is an individual frame
     /// containing complete and independent visual information. This frame serves
     /// as a reference point for other frames in the video sequence.
@@ -103,6 +120,33 @@ pub struct Vp9CodecExtra {
     pub is_keyframe: bool,
 }
 
+impl Vp9CodecExtra {
+    /// Narrow `data` to the spatial and temporal layer.
+    ///
+    /// Returns `false` if the data is above the temporal layer and should be dropped.
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
+    pub(crate) fn select_layers(&mut self, data: &mut Vec<u8>, spatial: u8, temporal: u8) -> bool {
+        if let Some(tid) = self.tid {
+            if tid > temporal {
+                return false;
+            }
+        }
+
+        let spatial = spatial as usize;
+
+        if let Some(Some(end)) = self.layers_scheme.get(spatial).copied() {
+            data.truncate(end);
+
+            // Keep layers_scheme in sync with the data.
+            for l in self.layers_scheme.iter_mut().skip(spatial + 1) {
+                *l = None;
+            }
+        }
+
+        true
+    }
+}
+
 /// Packetizes VP9 RTP packets.
 #[derive(Default, Clone)]
 pub struct Vp9Packetizer {
@@ -319,6 +363,7 @@ impl Depacketizer for Vp9Depacketizer {
             payload_index = self.parse_layer_info(&mut reader, payload_index)?;
         }
 
+        self.pdiff.clear();
         if self.f && self.p {
             payload_index = self.parse_ref_indices(&mut reader, payload_index)?;
         }
@@ -381,6 +426,19 @@ impl Vp9Depacketizer {
         }
 
         vp9_extra.pid = self.picture_id;
+
+        vp9_extra.flexible = self.f;
+        if self.f {
+            let sid = if self.l { self.sid as usize } else { 0 };
+
+            let mut pdiff = [0; MAX_VP9REF_PICS];
+            for (p, v) in pdiff.iter_mut().zip(&self.pdiff) {
+                *p = *v;
+            }
+            vp9_extra.layers_pdiff[sid] = pdiff;
+            vp9_extra.layers_inter_dependent[sid] = self.l && self.d;
+        }
+
         vp9_extra
             .layers_widths
             .copy_from_slice(&self.width[..MAX_SPATIAL_LAYERS]);
@@ -513,7 +571,7 @@ impl Vp9Depacketizer {
             payload_index += 1;
 
             self.pdiff.push(b >> 1);
-            if self.pdiff.len() >= MAX_VP9REF_PICS {
+            if self.pdiff.len() > MAX_VP9REF_PICS {
                 return Err(PacketError::ErrTooManyPDiff);
             }
         }
@@ -617,6 +675,47 @@ impl Vp9Depacketizer {
 mod test {
     use super::*;
 
+    #[test]
+    fn select_layers() {
+        let extra = Vp9CodecExtra {
+            layers_scheme: [Some(2), Some(5), Some(9)],
+            tid: Some(1),
+            ..Default::default()
+        };
+        let data: Vec<u8> = (0..9).collect();
+
+        // Everything up to the target is kept as is.
+        let mut e = extra;
+        let mut d = data.clone();
+        assert!(e.select_layers(&mut d, 2, 1));
+        assert_eq!(d, data);
+        assert_eq!(e, extra);
+
+        // Higher spatial layers are cut off.
+        let mut e = extra;
+        let mut d = data.clone();
+        assert!(e.select_layers(&mut d, 1, 2));
+        assert_eq!(d, [0, 1, 2, 3, 4]);
+        assert_eq!(e.layers_scheme, [Some(2), Some(5), None]);
+
+        let mut e = extra;
+        let mut d = data.clone();
+        assert!(e.select_layers(&mut d, 0, 1));
+        assert_eq!(d, [0, 1]);
+        assert_eq!(e.layers_scheme, [Some(2), None, None]);
+
+        // Higher temporal layers are dropped entirely.
+        let mut e = extra;
+        let mut d = data.clone();
+        assert!(!e.select_layers(&mut d, 2, 0));
+
+        // Without layer information, the data is kept.
+        let mut e = Vp9CodecExtra::default();
+        let mut d = data.clone();
+        assert!(e.select_layers(&mut d, 0, 0));
+        assert_eq!(d, data);
+    }
+
     #[test]
     fn test_vp9_packet_unmarshal() -> Result<(), PacketError> {
         let tests: Vec<(&str, &[u8], Vp9Depacketizer, &[u8], Option<PacketError>)> = vec![
@@ -986,4 +1085,30 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_vp9_flexible_references() -> Result<(), PacketError> {
+        let mut vp9 = Vp9Depacketizer::default();
+
+        // Flexible mode, picture id 5, T1 S1 with inter-layer dependency, P_DIFF 1.
+        let packet = [0xFC, 0x05, 0x23, 0x02, 0xAA];
+
+        // The reference indices don't pile up over packets.
+        for _ in 0..5 {
+            let mut out = Vec::new();
+            let mut extra = CodecExtra::None;
+            vp9.depacketize(&packet, &mut out, &mut extra)?;
+
+            let CodecExtra::Vp9(extra) = extra else {
+                panic!("Expected VP9 extra");
+            };
+            assert!(extra.flexible);
+            assert_eq!(extra.pid, 5);
+            assert_eq!(extra.layers_pdiff[0], [0; MAX_VP9REF_PICS]);
+            assert_eq!(extra.layers_pdiff[1], [1, 0, 0]);
+            assert_eq!(extra.layers_inter_dependent, [false, true, false]);
+        }
+
+        Ok(())
+    }
 }
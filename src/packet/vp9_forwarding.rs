@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+
+use crate::media::SvcTarget;
+
+use super::vp9::MAX_SPATIAL_LAYERS;
+use super::Vp9CodecExtra;
+
+/// How many forwarded picture ids to remember per spatial layer. P_DIFF is 7 bits.
+const HISTORY: usize = 128;
+
+/// Picture ids are assumed to be 15 bit (the M bit set), which is what browsers use.
+const PID_MASK: u16 = 0x7fff;
+
+/// Decides which VP9 frames an SFU can forward when it drops some of them.
+///
+/// In flexible mode every frame lists the earlier pictures it references (P_DIFF) for each
+/// spatial layer. This keeps track of the picture ids forwarded per spatial layer, and
+/// only lets a layer through if all its references were forwarded, i.e. if the receiver
+/// can decode it.
+///
+/// In non-flexible mode the references are not known, and every layer up to the
+/// target is considered forwardable.
+///
+/// ```no_run
+/// # use str0m::format::CodecExtra;
+/// # use str0m::media::{MediaData, SvcTarget, Vp9Forwarding};
+/// let mut forwarding = Vp9Forwarding::new();
+/// let target = SvcTarget { spatial: 1, temporal: 2 };
+///
+/// let mut media_data: MediaData = todo!();
+///
+/// if let CodecExtra::Vp9(extra) = media_data.codec_extra {
+///     match forwarding.forwardable(&extra, target) {
+///         Some(sid) => {
+///             // Forward the data up to and including spatial layer `sid`.
+///             if let Some(end) = extra.layers_scheme[sid as usize] {
+///                 media_data.data.truncate(end);
+///             }
+///         }
+///         None => {
+///             // Drop the frame.
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Vp9Forwarding {
+    forwarded: [VecDeque<u16>; MAX_SPATIAL_LAYERS],
+}
+
+impl Vp9Forwarding {
+    /// Creates a new instance with no frames forwarded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The highest spatial layer of a frame that can be forwarded for the `target`.
+    ///
+    /// Returns `None` if the frame should be dropped, because it is above the temporal
+    /// target, or because the lowest spatial layer can't be decoded. The frame is recorded
+    /// as forwarded up to the returned layer, which means the caller must forward it
+    /// accordingly.
+    pub fn forwardable(&mut self, extra: &Vp9CodecExtra, target: SvcTarget) -> Option<u8> {
+        if let Some(tid) = extra.tid {
+            if tid > target.temporal {
+                return None;
+            }
+        }
+
+        if extra.is_keyframe {
+            // Nothing before a keyframe is referenced.
+            for f in &mut self.forwarded {
+                f.clear();
+            }
+        }
+
+        // Without layer indices, there is only spatial layer 0.
+        let present = extra
+            .layers_scheme
+            .iter()
+            .rposition(|l| l.is_some())
+            .unwrap_or(0);
+        let top = present.min(target.spatial as usize);
+
+        let pid = extra.pid & PID_MASK;
+        let mut forwardable = None;
+
+        for sid in 0..=top {
+            if sid > 0 && extra.layers_scheme[sid].is_none() {
+                break;
+            }
+
+            // Layers are checked bottom up. An upper layer that depends on the lower
+            // layer of the same picture is only reached if the lower is decodable.
+            if extra.flexible && !self.has_references(extra, sid, pid) {
+                break;
+            }
+
+            forwardable = Some(sid as u8);
+        }
+
+        if let Some(top) = forwardable {
+            for f in &mut self.forwarded[..=top as usize] {
+                if f.len() == HISTORY {
+                    f.pop_front();
+                }
+                f.push_back(pid);
+            }
+        }
+
+        forwardable
+    }
+
+    fn has_references(&self, extra: &Vp9CodecExtra, sid: usize, pid: u16) -> bool {
+        extra.layers_pdiff[sid]
+            .iter()
+            .filter(|pdiff| **pdiff > 0)
+            .all(|pdiff| {
+                let reference = pid.wrapping_sub(*pdiff as u16) & PID_MASK;
+                self.forwarded[sid].contains(&reference)
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame(pid: u16, tid: u8, pdiff: &[&[u8]]) -> Vp9CodecExtra {
+        let mut extra = Vp9CodecExtra {
+            pid,
+            tid: Some(tid),
+            flexible: true,
+            is_keyframe: pid == 0,
+            ..Default::default()
+        };
+
+        for (sid, refs) in pdiff.iter().enumerate() {
+            extra.layers_scheme[sid] = Some((sid + 1) * 100);
+            extra.layers_inter_dependent[sid] = sid > 0;
+            for (i, r) in refs.iter().enumerate() {
+                extra.layers_pdiff[sid][i] = *r;
+            }
+        }
+
+        extra
+    }
+
+    const ALL: SvcTarget = SvcTarget {
+        spatial: 2,
+        temporal: 2,
+    };
+
+    #[test]
+    fn l1t3_drop_referenced_frame() {
+        let mut f = Vp9Forwarding::new();
+
+        // T0 0, T2 1 (refs 0), T1 2 (refs 0), T2 3 (refs 2), T0 4 (refs 0)
+        assert_eq!(f.forwardable(&frame(0, 0, &[&[]]), ALL), Some(0));
+        assert_eq!(f.forwardable(&frame(1, 2, &[&[1]]), ALL), Some(0));
+
+        // Frame 2 is lost or dropped by the SFU.
+        assert_eq!(f.forwardable(&frame(3, 2, &[&[1]]), ALL), None);
+
+        // The T0 frame only references frame 0.
+        assert_eq!(f.forwardable(&frame(4, 0, &[&[4]]), ALL), Some(0));
+        assert_eq!(f.forwardable(&frame(5, 2, &[&[1]]), ALL), Some(0));
+    }
+
+    #[test]
+    fn temporal_target_drops_dependants() {
+        let mut f = Vp9Forwarding::new();
+        let target = SvcTarget {
+            spatial: 0,
+            temporal: 1,
+        };
+
+        assert_eq!(f.forwardable(&frame(0, 0, &[&[]]), target), Some(0));
+        assert_eq!(f.forwardable(&frame(1, 2, &[&[1]]), target), None);
+        assert_eq!(f.forwardable(&frame(2, 1, &[&[2]]), target), Some(0));
+
+        // Switching up to T2 is possible with a frame that references T1 and below.
+        assert_eq!(f.forwardable(&frame(3, 2, &[&[1]]), ALL), Some(0));
+    }
+
+    #[test]
+    fn l2_upper_layer_missing_reference() {
+        let mut f = Vp9Forwarding::new();
+
+        assert_eq!(f.forwardable(&frame(0, 0, &[&[], &[]]), ALL), Some(1));
+
+        // Forwarded at spatial layer 0 only.
+        let low = SvcTarget {
+            spatial: 0,
+            temporal: 2,
+        };
+        assert_eq!(f.forwardable(&frame(1, 0, &[&[1], &[1]]), low), Some(0));
+
+        // Layer 1 references picture 1 on layer 1, which was not forwarded.
+        assert_eq!(f.forwardable(&frame(2, 0, &[&[1], &[1]]), ALL), Some(0));
+
+        // A layer 1 only predicted from layer 0 of the same picture is a switch point.
+        assert_eq!(f.forwardable(&frame(3, 0, &[&[1], &[]]), ALL), Some(1));
+        assert_eq!(f.forwardable(&frame(4, 0, &[&[1], &[1]]), ALL), Some(1));
+    }
+
+    #[test]
+    fn non_flexible_is_forwarded() {
+        let mut f = Vp9Forwarding::new();
+
+        let mut extra = frame(7, 1, &[&[3], &[3]]);
+        extra.flexible = false;
+
+        assert_eq!(f.forwardable(&extra, ALL), Some(1));
+    }
+}
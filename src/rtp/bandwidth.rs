@@ -8,6 +8,7 @@ use std::time::Duration;
 /// Internally the value is tracked as a floating point number for accuracy in the presence of
 /// repeated calculations that can yield decimal values.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Bitrate(f64);
 
 impl Bitrate {
@@ -8,10 +8,12 @@ use std::hash::Hasher;
 use std::panic::UnwindSafe;
 use std::str::from_utf8;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::util::already_happened;
+use crate::util::beginning_instant;
 use crate::util::epoch_to_beginning;
+use crate::util::Instant;
 use crate::util::InstantExt;
 
 use crate::rtp_::Frequency;
@@ -59,6 +61,12 @@ pub enum Extension {
     FrameMarking,
     /// <http://www.webrtc.org/experiments/rtp-hdrext/color-space>
     ColorSpace,
+    /// <http://www.webrtc.org/experiments/rtp-hdrext/abs-capture-time>
+    ///
+    /// NTP timestamp of when the first frame in the packet was captured, and optionally the
+    /// estimated offset between the capture system clock and the sender clock. Lets a
+    /// receiver several hops down the line measure end-to-end latency.
+    AbsoluteCaptureTime,
 
     /// Not recognized URI, but it could still be user parseable.
     #[doc(hidden)]
@@ -221,6 +229,10 @@ const EXT_URI: &[(Extension, &str)] = &[
         Extension::ColorSpace,
         "http://www.webrtc.org/experiments/rtp-hdrext/color-space",
     ),
+    (
+        Extension::AbsoluteCaptureTime,
+        "http://www.webrtc.org/experiments/rtp-hdrext/abs-capture-time",
+    ),
 ];
 
 impl Extension {
@@ -292,6 +304,7 @@ impl Extension {
                 | TransportSequenceNumber
                 | TransmissionTimeOffset
                 | PlayoutDelay
+                | AbsoluteCaptureTime
         )
     }
 
@@ -316,6 +329,7 @@ impl Extension {
                 | VideoTiming
                 | FrameMarking
                 | ColorSpace
+                | AbsoluteCaptureTime
         )
     }
 }
@@ -541,14 +555,21 @@ impl ExtensionMap {
                 match form {
                     ExtensionsForm::OneByte => {
                         if let Some(n) = v.ext.write_to(&mut b[1..], ev) {
-                            assert!(n <= 16);
-                            assert!(n > 0);
+                            if n > 16 {
+                                // A user serializer must ask for the two byte form.
+                                warn!("Skip extension {:?}, {} bytes in one byte form", v.ext, n);
+                                continue;
+                            }
                             b[0] = (idx as u8 + 1) << 4 | (n as u8 - 1);
                             b = &mut b[1 + n..];
                         }
                     }
                     ExtensionsForm::TwoByte => {
                         if let Some(n) = v.ext.write_to(&mut b[2..], ev) {
+                            if n > 255 {
+                                warn!("Skip extension {:?}, {} bytes in two byte form", v.ext, n);
+                                continue;
+                            }
                             b[0] = (idx + 1) as u8;
                             b[1] = n as u8;
                             b = &mut b[2 + n..];
@@ -708,6 +729,16 @@ impl Extension {
                 // TODO HDR color space
                 None
             }
+            AbsoluteCaptureTime => {
+                let v = ev.abs_capture_time.as_deref()?;
+                buf[..8].copy_from_slice(&v.capture_time.to_be_bytes());
+                if let Some(offset) = v.clock_offset {
+                    buf[8..16].copy_from_slice(&offset.to_be_bytes());
+                    Some(16)
+                } else {
+                    Some(8)
+                }
+            }
             UnknownUri(_, serializer) => {
                 let n = serializer.write_to(buf, ev);
 
@@ -827,6 +858,22 @@ impl Extension {
             ColorSpace => {
                 // TODO HDR color space
             }
+            // 8 or 16
+            AbsoluteCaptureTime => {
+                if buf.len() < 8 {
+                    return None;
+                }
+                let capture_time = u64::from_be_bytes(buf[..8].try_into().unwrap());
+                let clock_offset = if buf.len() >= 16 {
+                    Some(i64::from_be_bytes(buf[8..16].try_into().unwrap()))
+                } else {
+                    None
+                };
+                ev.abs_capture_time = Some(Box::new(AbsCaptureTime {
+                    capture_time,
+                    clock_offset,
+                }));
+            }
             UnknownUri(_, serializer) => {
                 let success = serializer.parse_value(buf, ev);
                 if !success {
@@ -853,6 +900,11 @@ pub struct ExtensionValues {
     /// Tell a receiver what rotation a video need to replay correctly.
     pub video_orientation: Option<VideoOrientation>,
 
+    /// When the media in the packet was captured, in the capture system's NTP clock.
+    ///
+    /// Boxed since it's rarely set, and would otherwise make every [`ExtensionValues`] bigger.
+    pub abs_capture_time: Option<Box<AbsCaptureTime>>,
+
     // The values below are considered internal until we have a reason to expose them.
     // Generally we want to avoid expose experimental features unless there are strong
     // reasons to do so.
@@ -901,7 +953,7 @@ impl ExtensionValues {
 
         let since_beginning = closest_64.saturating_sub(epoch_to_beginning());
 
-        let mut offset = already_happened() + since_beginning;
+        let mut offset = beginning_instant() + since_beginning;
 
         if offset + relative_64_secs > now {
             offset -= Duration::from_secs(64);
@@ -1078,6 +1130,9 @@ impl fmt::Debug for ExtensionValues {
         if let Some(t) = &self.frame_mark {
             write!(f, " frame_mark: {t}")?;
         }
+        if let Some(t) = &self.abs_capture_time {
+            write!(f, " abs_capture_time: {t:?}")?;
+        }
 
         write!(f, " }}")?;
         Ok(())
@@ -1115,6 +1170,7 @@ impl fmt::Display for Extension {
                 RtpMid => "mid",
                 FrameMarking => "frame-marking07",
                 ColorSpace => "color-space",
+                AbsoluteCaptureTime => "abs-capture-time",
                 UnknownUri(uri, _) => uri,
             }
         )
@@ -1138,6 +1194,45 @@ impl fmt::Debug for ExtensionMap {
     }
 }
 
+/// Value of the [`Extension::AbsoluteCaptureTime`] header extension.
+///
+/// <https://webrtc.googlesource.com/src/+/refs/heads/main/docs/native-code/rtp-hdrext/abs-capture-time>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbsCaptureTime {
+    /// NTP timestamp (64 bit, Q32.32) of when the first frame in the packet was captured,
+    /// as measured by the clock of the capture system.
+    pub capture_time: u64,
+
+    /// Estimated offset (signed Q32.32) between the capture system clock and the clock of
+    /// the sender of this packet. Adding it to `capture_time` gives the capture time in the
+    /// sender clock.
+    ///
+    /// Senders that are the capture system themselves leave this out, and hops that forward
+    /// the media are expected to update it with their own estimate.
+    pub clock_offset: Option<i64>,
+}
+
+impl AbsCaptureTime {
+    /// Create a value from a capture time in the local clock.
+    pub fn new(capture_time: Instant) -> Self {
+        AbsCaptureTime {
+            capture_time: capture_time.as_ntp_64(),
+            clock_offset: None,
+        }
+    }
+
+    /// The capture time in the local clock.
+    ///
+    /// This applies the clock offset, which is only correct if the offset has been
+    /// estimated against the local clock.
+    pub fn capture_instant(&self) -> Instant {
+        // Two's complement, adding the offset as u64 is the same as adding it signed.
+        let offset = self.clock_offset.unwrap_or(0) as u64;
+        let ntp = self.capture_time.wrapping_add(offset);
+        Instant::from_ntp_64(ntp)
+    }
+}
+
 /// How the video is rotated.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VideoOrientation {
@@ -1178,6 +1273,7 @@ impl PartialEq for Extension {
             (Extension::RtpMid, Extension::RtpMid) => true,
             (Extension::FrameMarking, Extension::FrameMarking) => true,
             (Extension::ColorSpace, Extension::ColorSpace) => true,
+            (Extension::AbsoluteCaptureTime, Extension::AbsoluteCaptureTime) => true,
             (Extension::UnknownUri(uri1, _), Extension::UnknownUri(uri2, _)) => uri1 == uri2,
             _ => false,
         }
@@ -1190,6 +1286,55 @@ impl Eq for Extension {}
 mod test {
     use super::*;
 
+    /// A user extension that writes a fixed number of bytes.
+    #[derive(Debug)]
+    struct Fixed(usize);
+
+    impl ExtensionSerializer for Fixed {
+        fn write_to(&self, buf: &mut [u8], _ev: &ExtensionValues) -> usize {
+            buf[..self.0].fill(0xaa);
+            self.0
+        }
+        fn parse_value(&self, _buf: &[u8], _ev: &mut ExtensionValues) -> bool {
+            false
+        }
+        fn is_video(&self) -> bool {
+            true
+        }
+        fn is_audio(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn skip_too_large_one_byte_form() {
+        let now = Instant::now() + Duration::from_secs(1000);
+
+        let mut exts = ExtensionMap::empty();
+        exts.set(
+            1,
+            Extension::with_serializer("http://example.com/big", Fixed(20)),
+        );
+        exts.set(2, Extension::AbsoluteSendTime);
+        let ev = ExtensionValues {
+            abs_send_time: Some(now),
+            ..Default::default()
+        };
+
+        assert_eq!(ExtensionsForm::OneByte, exts.form(&ev));
+
+        // The big extension doesn't fit in the one byte form, and is skipped
+        // instead of panicking. The others are still written.
+        let mut buf = vec![0_u8; 64];
+        let n = exts.write_to(&mut buf[..], &ev, ExtensionsForm::OneByte);
+        assert_eq!(n, 4);
+        assert_eq!(buf[0], 2 << 4 | 2);
+
+        let mut ev2 = ExtensionValues::default();
+        exts.parse(&buf[..n], ExtensionsForm::OneByte, &mut ev2);
+        assert!(ev2.abs_send_time.is_some());
+    }
+
     #[test]
     fn abs_send_time() {
         let now = Instant::now() + Duration::from_secs(1000);
@@ -1245,6 +1390,30 @@ mod test {
         assert!(abs < Duration::from_millis(1));
     }
 
+    #[test]
+    fn abs_capture_time() {
+        let mut exts = ExtensionMap::empty();
+        exts.set(3, Extension::AbsoluteCaptureTime);
+
+        for clock_offset in [None, Some(-(1 << 32))] {
+            let ev = ExtensionValues {
+                abs_capture_time: Some(Box::new(AbsCaptureTime {
+                    capture_time: 0xe8f0_1234_8000_0000,
+                    clock_offset,
+                })),
+                ..Default::default()
+            };
+
+            let mut buf = vec![0_u8; 20];
+            exts.write_to(&mut buf[..], &ev, ExtensionsForm::OneByte);
+
+            let mut ev2 = ExtensionValues::default();
+            exts.parse(&buf, ExtensionsForm::OneByte, &mut ev2);
+
+            assert_eq!(ev.abs_capture_time, ev2.abs_capture_time);
+        }
+    }
+
     #[test]
     fn playout_delay() {
         let mut exts = ExtensionMap::empty();
@@ -3,6 +3,9 @@
 use super::ext::{ExtensionMap, ExtensionValues, ExtensionsForm};
 use super::{Pt, SeqNo, Ssrc, MAX_BLANK_PADDING_PAYLOAD_SIZE};
 
+/// Max number of contributing sources in an RTP header.
+const MAX_CSRC: usize = 15;
+
 /// Parsed header from an RTP packet.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RtpHeader {
@@ -12,7 +15,6 @@ pub struct RtpHeader {
     pub has_padding: bool,
     /// RTP packet has "RTP header extensions".
     pub has_extension: bool,
-    // pub csrc_count: usize, // "contributing source" (other ssrc)
     /// A marker indicates the end of a series of packets belonging together such
     /// as for a single video frame.
     pub marker: bool,
@@ -25,7 +27,9 @@ pub struct RtpHeader {
     pub timestamp: u32,
     /// Sender source identifier.
     pub ssrc: Ssrc,
-    // pub csrc: [u32; 15],
+    /// Contributing sources. Set by mixers to the sources that contributed to the
+    /// payload of the packet. At most 15.
+    pub csrc: Vec<Ssrc>,
     /// The extension values parsed using the mapping via SDP.
     pub ext_vals: ExtensionValues,
     /// Length of header.
@@ -34,9 +38,11 @@ pub struct RtpHeader {
 
 impl RtpHeader {
     pub(crate) fn write_to(&self, buf: &mut [u8], exts: &ExtensionMap) -> usize {
+        assert!(self.csrc.len() <= MAX_CSRC, "At most 15 CSRC");
         buf[0] = 0b10_0_0_0000
             | if self.has_padding { 1 << 5 } else { 0 }
-            | if self.has_extension { 1 << 4 } else { 0 };
+            | if self.has_extension { 1 << 4 } else { 0 }
+            | self.csrc.len() as u8;
 
         assert!(*self.payload_type <= 127);
         buf[1] = *self.payload_type & 0b0111_1111 | if self.marker { 1 << 7 } else { 0 };
@@ -45,6 +51,14 @@ impl RtpHeader {
         buf[4..8].copy_from_slice(&self.timestamp.to_be_bytes());
         buf[8..12].copy_from_slice(&self.ssrc.to_be_bytes());
 
+        for (i, csrc) in self.csrc.iter().enumerate() {
+            let from = 12 + i * 4;
+            buf[from..from + 4].copy_from_slice(&csrc.to_be_bytes());
+        }
+
+        let csrc_len = self.csrc.len() * 4;
+        let buf = &mut buf[csrc_len..];
+
         let exts_form = exts.form(&self.ext_vals);
         buf[12..14].copy_from_slice(&exts_form.serialize());
 
@@ -62,7 +76,7 @@ impl RtpHeader {
         let bede_len = (ext_len / 4) as u16;
         buf[14..16].copy_from_slice(&bede_len.to_be_bytes());
 
-        16 + ext_len
+        16 + csrc_len + ext_len
     }
 
     fn do_pad(buf: &mut [u8], from: usize, pad: usize) {
@@ -161,11 +175,10 @@ impl RtpHeader {
             return None;
         }
 
-        let mut csrc = [0_u32; 15];
-        for i in 0..csrc_count {
-            let n = u32::from_be_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
-            csrc[i] = n;
-        }
+        let csrc = buf[..csrc_len]
+            .chunks_exact(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]).into())
+            .collect();
 
         let buf: &[u8] = &buf[csrc_len..];
 
@@ -208,13 +221,12 @@ impl RtpHeader {
             version,
             has_padding,
             has_extension,
-            // csrc_count,
             marker,
             payload_type,
             sequence_number,
             timestamp,
             ssrc: ssrc.into(),
-            // csrc,
+            csrc,
             ext_vals: ext,
             header_len,
         };
@@ -320,6 +332,7 @@ impl Default for RtpHeader {
             sequence_number: 0,
             timestamp: 0,
             ssrc: 0.into(),
+            csrc: vec![],
             ext_vals: ExtensionValues::default(),
             header_len: 16,
         }
@@ -483,6 +496,39 @@ mod test {
         assert_eq!(&buf3, p3);
     }
 
+    #[test]
+    fn test_csrc_roundtrip() {
+        let mut exts = ExtensionMap::empty();
+        exts.set(3, Extension::AudioLevel);
+
+        let header = RtpHeader {
+            payload_type: 33.into(),
+            sequence_number: 47_000,
+            timestamp: 10_000,
+            ssrc: 44.into(),
+            csrc: vec![1.into(), 2.into(), 0xffff_ffff.into()],
+            ext_vals: ExtensionValues {
+                audio_level: Some(-42),
+                voice_activity: Some(false),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut buf = vec![0; DATAGRAM_MAX_PACKET_SIZE];
+        let n = header.write_to(&mut buf[..], &exts);
+        buf.truncate(n);
+
+        assert_eq!(n, 32);
+        assert_eq!(buf[0] & 0b0000_1111, 3);
+        assert_eq!(&buf[12..24], &[0, 0, 0, 1, 0, 0, 0, 2, 255, 255, 255, 255]);
+
+        let parsed = RtpHeader::parse(&buf, &exts).unwrap();
+        assert_eq!(parsed.csrc, header.csrc);
+        assert_eq!(parsed.ext_vals.audio_level, Some(-42));
+        assert_eq!(parsed.header_len, 32);
+    }
+
     #[test]
     fn test_write_rtp_headers_two_byte_form() {
         fn mk_header(seq: u16, ts: u32, level: i8, marker: bool, exts: &ExtensionMap) -> Vec<u8> {
@@ -564,6 +610,7 @@ mod test {
                 sequence_number: 47000,
                 timestamp: 10000,
                 ssrc: 777459193.into(),
+                csrc: vec![],
                 ext_vals: ExtensionValues {
                     mid: Some("xYj".into()),
                     abs_send_time: Some(abs1),
@@ -590,6 +637,7 @@ mod test {
                 sequence_number: 47001,
                 timestamp: 12000,
                 ssrc: 777459193.into(),
+                csrc: vec![],
                 ext_vals: ExtensionValues {
                     mid: Some("xYj".into()),
                     abs_send_time: Some(abs2),
@@ -616,6 +664,7 @@ mod test {
                 sequence_number: 47002,
                 timestamp: 14000,
                 ssrc: 777459193.into(),
+                csrc: vec![],
                 ext_vals: ExtensionValues {
                     mid: Some("xYj".into()),
                     abs_send_time: Some(abs3),
@@ -664,6 +713,7 @@ mod test {
                 sequence_number: 47000,
                 timestamp: 10000,
                 ssrc: 777459193.into(),
+                csrc: vec![],
                 ext_vals: ExtensionValues {
                     mid: Some("xYj".into()),
                     abs_send_time: Some(abs1),
@@ -690,6 +740,7 @@ mod test {
                 sequence_number: 47001,
                 timestamp: 12000,
                 ssrc: 777459193.into(),
+                csrc: vec![],
                 ext_vals: ExtensionValues {
                     mid: Some("xYj".into()),
                     abs_send_time: Some(abs2),
@@ -716,6 +767,7 @@ mod test {
                 sequence_number: 47002,
                 timestamp: 14000,
                 ssrc: 777459193.into(),
+                csrc: vec![],
                 ext_vals: ExtensionValues {
                     mid: Some("xYj".into()),
                     abs_send_time: Some(abs3),
@@ -114,7 +114,7 @@ macro_rules! num_id {
 /// encoded streams. For example a simulcast of 3 layers would have
 /// 3 incoming StreamRx, but since they belong to the same media,
 /// the have the same `Mid`.
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Mid([u8; 16]);
 str_id!(Mid, "Mid", 16, 3);
 
@@ -125,7 +125,7 @@ str_id!(Mid, "Mid", 16, 3);
 ///
 /// In SDP this is an optional value that will be seen in [`MediaData`][crate::media::MediaData]
 /// if the remote peer is configured for simulcast.
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Rid([u8; 8]);
 str_id!(Rid, "Rid", 8, 3);
 
@@ -6,8 +6,8 @@ mod id;
 pub use id::{Mid, Pt, Rid, SeqNo, SessionId, Ssrc};
 
 mod ext;
+pub use ext::{AbsCaptureTime, UserExtensionValues, VideoOrientation};
 pub use ext::{Extension, ExtensionMap, ExtensionSerializer, ExtensionValues};
-pub use ext::{UserExtensionValues, VideoOrientation};
 
 mod dir;
 pub use dir::Direction;
@@ -22,7 +22,9 @@ pub(crate) use header::{extend_u15, extend_u16, extend_u32, extend_u7, extend_u8
 
 mod srtp;
 pub(crate) use srtp::SrtpContext;
-pub(crate) use srtp::{SRTCP_OVERHEAD, SRTP_BLOCK_SIZE, SRTP_OVERHEAD};
+#[cfg(feature = "sample-api")]
+pub(crate) use srtp::SRTP_OVERHEAD;
+pub(crate) use srtp::{SRTCP_OVERHEAD, SRTP_BLOCK_SIZE};
 
 mod rtcp;
 pub use rtcp::*;
@@ -50,6 +52,14 @@ pub enum RtpError {
     /// Failed to parse RTP header.
     #[error("Failed to parse RTP header")]
     ParseHeader,
+
+    /// The payload type doesn't fit the 7 bits of the RTP header.
+    #[error("Payload type out of range: {0}")]
+    PayloadTypeOutOfRange(Pt),
+
+    /// More contributing sources than fit the 4 bits of the RTP header.
+    #[error("Too many CSRC: {0} > 15")]
+    TooManyCsrc(usize),
 }
 
 impl From<CryptoError> for RtpError {
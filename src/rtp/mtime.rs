@@ -4,10 +4,11 @@ use std::num::NonZeroU32;
 use std::ops::{Add, AddAssign};
 use std::str::FromStr;
 use std::time::Duration;
-use std::time::Instant;
 
 use serde::{Deserialize, Serialize};
 
+use crate::util::Instant;
+
 /// Media timeline frequency as represented by a non-zero unsigned integer.
 ///
 /// The frequency can be found in the negotiated payload parameters for a
@@ -48,6 +49,9 @@ impl Frequency {
     /// Cycles in a second of a 48 kHz signal.
     pub const FORTY_EIGHT_KHZ: Frequency = Self::make(48_000);
 
+    /// Cycles in a second of an 8 kHz signal.
+    pub const EIGHT_KHZ: Frequency = Self::make(8_000);
+
     /// Milliseconds in a second.
     pub const MILLIS: Frequency = Self::make(1_000);
 
@@ -263,6 +267,75 @@ impl MediaTime {
         }
     }
 
+    /// Convert this offset time to a different denominator (frequency)
+    /// only if it can be done without losing precision.
+    ///
+    /// ```
+    /// # use str0m::media::{Frequency, MediaTime};
+    /// let t = MediaTime::from_millis(20);
+    /// assert_eq!(t.try_rebase(Frequency::FORTY_EIGHT_KHZ), Some(MediaTime::new(960, Frequency::FORTY_EIGHT_KHZ)));
+    /// assert_eq!(MediaTime::from_90khz(1).try_rebase(Frequency::MILLIS), None);
+    /// ```
+    pub const fn try_rebase(self, denom: Frequency) -> Option<MediaTime> {
+        let scaled = self.0 as u128 * denom.get() as u128;
+        let old = self.1.get() as u128;
+        if scaled % old != 0 || scaled / old > u64::MAX as u128 {
+            return None;
+        }
+        Some(MediaTime::new((scaled / old) as u64, denom))
+    }
+
+    /// Checked addition. Returns `None` on overflow.
+    ///
+    /// The result has the higher frequency of the two operands.
+    pub fn checked_add(self, rhs: MediaTime) -> Option<MediaTime> {
+        let (t0, t1) = MediaTime::same_base(self, rhs);
+        t0.0.checked_add(t1.0).map(|v| MediaTime::new(v, t0.1))
+    }
+
+    /// Checked subtraction. Returns `None` if `rhs` is greater than `self`.
+    ///
+    /// The result has the higher frequency of the two operands.
+    pub fn checked_sub(self, rhs: MediaTime) -> Option<MediaTime> {
+        let (t0, t1) = MediaTime::same_base(self, rhs);
+        t0.0.checked_sub(t1.0).map(|v| MediaTime::new(v, t0.1))
+    }
+
+    /// Saturating addition. Clamps at the max numerator.
+    pub fn saturating_add(self, rhs: MediaTime) -> MediaTime {
+        let (t0, t1) = MediaTime::same_base(self, rhs);
+        MediaTime::new(t0.0.saturating_add(t1.0), t0.1)
+    }
+
+    /// Saturating subtraction. Clamps at zero.
+    pub fn saturating_sub(self, rhs: MediaTime) -> MediaTime {
+        let (t0, t1) = MediaTime::same_base(self, rhs);
+        MediaTime::new(t0.0.saturating_sub(t1.0), t0.1)
+    }
+
+    /// The numerator truncated to the 32 bits used in RTP headers.
+    #[inline(always)]
+    pub const fn as_rtp_u32(&self) -> u32 {
+        self.0 as u32
+    }
+
+    /// Signed difference `self - other` in the frequency of `self`, treating
+    /// both numerators as wrapping 32 bit RTP timestamps.
+    ///
+    /// Useful to compare RTP times across a 32 bit roll-over.
+    ///
+    /// ```
+    /// # use str0m::media::MediaTime;
+    /// let a = MediaTime::from_90khz(10);
+    /// let b = MediaTime::from_90khz(u32::MAX as u64 - 9);
+    /// assert_eq!(a.wrapping_rtp_diff(b), 20);
+    /// assert_eq!(b.wrapping_rtp_diff(a), -20);
+    /// ```
+    pub const fn wrapping_rtp_diff(&self, other: MediaTime) -> i64 {
+        let other = other.rebase(self.1);
+        self.as_rtp_u32().wrapping_sub(other.as_rtp_u32()) as i32 as i64
+    }
+
     #[inline(always)]
     fn same_base(t0: MediaTime, t1: MediaTime) -> (MediaTime, MediaTime) {
         let max = Frequency(t0.1 .0.max(t1.1 .0));
@@ -355,4 +428,30 @@ mod test {
 
         println!("{}", (10.0234_f64).fract());
     }
+
+    #[test]
+    fn ts_try_rebase() {
+        let t = MediaTime::from_90khz(1800);
+        assert_eq!(
+            t.try_rebase(Frequency::FORTY_EIGHT_KHZ).unwrap().numer(),
+            960
+        );
+        assert_eq!(t.try_rebase(Frequency::MILLIS).unwrap().numer(), 20);
+        assert!(MediaTime::from_90khz(1)
+            .try_rebase(Frequency::FORTY_EIGHT_KHZ)
+            .is_none());
+    }
+
+    #[test]
+    fn ts_checked_arith() {
+        let a = MediaTime::from_millis(10);
+        let b = MediaTime::from_90khz(90);
+        assert_eq!(a.checked_sub(b), Some(MediaTime::from_millis(9)));
+        assert_eq!(b.checked_sub(a), None);
+        assert_eq!(b.saturating_sub(a), MediaTime::ZERO);
+        assert_eq!(
+            MediaTime::from_secs(u64::MAX).checked_add(MediaTime::from_secs(1)),
+            None
+        );
+    }
 }
@@ -0,0 +1,143 @@
+use std::fmt::Debug;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::Arc;
+
+use crate::rtp_::{Mid, RtpHeader, SeqNo};
+use crate::util::Instant;
+
+/// Stage in the packet pipeline where a [`PacketObserver`] is called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PacketStage {
+    /// Incoming SRTP or SRTCP, before decryption.
+    Received,
+    /// Incoming RTP or RTCP, after decryption.
+    Decrypted,
+    /// Incoming RTP mapped to its [`StreamRx`][crate::rtp::StreamRx]. RTX is unwrapped here.
+    Demuxed,
+    /// Outgoing RTP entered the send queue of a [`StreamTx`][crate::rtp::StreamTx].
+    Queued,
+    /// Outgoing RTP or RTCP, after encryption.
+    Sent,
+}
+
+/// A packet at some [`PacketStage`], given to a [`PacketObserver`].
+///
+/// Only borrows data str0m already has at that stage, nothing is copied or allocated.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ObservedPacket<'a> {
+    /// The pipeline stage.
+    pub stage: PacketStage,
+    /// The `Instant` of the [`Rtc::handle_input()`][crate::Rtc::handle_input] or
+    /// [`Rtc::poll_output()`][crate::Rtc::poll_output] call processing the packet.
+    pub timestamp: Instant,
+    /// Whether this is RTCP (a compound packet). Otherwise RTP.
+    pub is_rtcp: bool,
+    /// The RTP header. `None` for RTCP.
+    pub header: Option<&'a RtpHeader>,
+    /// The mid, when known. That is from [`PacketStage::Demuxed`] and for outgoing RTP.
+    pub mid: Option<Mid>,
+    /// Extended sequence number, when known. Not for [`PacketStage::Received`] or RTCP.
+    pub seq_no: Option<SeqNo>,
+    /// Packet data.
+    ///
+    /// For [`PacketStage::Received`] and [`PacketStage::Sent`] this is the entire encrypted
+    /// packet. For the other stages it is the RTP payload, or the entire RTCP packet.
+    pub data: &'a [u8],
+}
+
+/// Observes packets at key stages of the pipeline.
+///
+/// Set with [`RtcConfig::set_packet_observer()`][crate::RtcConfig::set_packet_observer]. The
+/// observer is called synchronously from within [`Rtc::handle_input()`][crate::Rtc::handle_input]
+/// and [`Rtc::poll_output()`][crate::Rtc::poll_output] and should be cheap, such as
+/// incrementing counters or pushing to a channel. Without an observer, the overhead is one
+/// branch per stage.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use std::sync::atomic::{AtomicU64, Ordering};
+/// # use str0m::RtcConfig;
+/// use str0m::rtp::{ObservedPacket, PacketObserver, PacketStage};
+///
+/// #[derive(Debug, Default)]
+/// struct Counter(AtomicU64);
+///
+/// impl PacketObserver for Counter {
+///     fn on_packet(&self, packet: &ObservedPacket<'_>) {
+///         if packet.stage == PacketStage::Sent {
+///             self.0.fetch_add(packet.data.len() as u64, Ordering::Relaxed);
+///         }
+///     }
+/// }
+///
+/// let counter = Arc::new(Counter::default());
+/// let observer: Arc<dyn PacketObserver> = counter.clone();
+///
+/// let rtc = RtcConfig::new()
+///     .set_packet_observer(Some(observer))
+///     .build();
+/// ```
+pub trait PacketObserver: Debug + Send + Sync + 'static {
+    /// Called for each packet passing a stage.
+    fn on_packet(&self, packet: &ObservedPacket<'_>);
+}
+
+/// Shared handle to a [`PacketObserver`].
+///
+/// The observer only gets to look at packets, and a panic in it can't leave str0m
+/// state inconsistent, which is why this is considered unwind safe.
+#[derive(Debug, Clone)]
+pub(crate) struct Observer(pub Arc<dyn PacketObserver>);
+
+impl UnwindSafe for Observer {}
+impl RefUnwindSafe for Observer {}
+
+impl Observer {
+    #[inline(always)]
+    pub fn observe(&self, packet: &ObservedPacket<'_>) {
+        self.0.on_packet(packet);
+    }
+}
+
+impl<'a> ObservedPacket<'a> {
+    pub(crate) fn rtp(
+        stage: PacketStage,
+        timestamp: Instant,
+        header: &'a RtpHeader,
+        data: &'a [u8],
+    ) -> Self {
+        ObservedPacket {
+            stage,
+            timestamp,
+            is_rtcp: false,
+            header: Some(header),
+            mid: None,
+            seq_no: None,
+            data,
+        }
+    }
+
+    pub(crate) fn rtcp(stage: PacketStage, timestamp: Instant, data: &'a [u8]) -> Self {
+        ObservedPacket {
+            stage,
+            timestamp,
+            is_rtcp: true,
+            header: None,
+            mid: None,
+            seq_no: None,
+            data,
+        }
+    }
+
+    pub(crate) fn with_mid(mut self, mid: Mid) -> Self {
+        self.mid = Some(mid);
+        self
+    }
+
+    pub(crate) fn with_seq_no(mut self, seq_no: SeqNo) -> Self {
+        self.seq_no = Some(seq_no);
+        self
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::VecDeque;
+
+use crate::format::Codec;
+use crate::rtp_::{SeqNo, Ssrc};
+use crate::streams::RtpPacket;
+use crate::util::Instant;
+
+/// How many input sequence numbers back we remember what was forwarded.
+///
+/// Older packets are dropped, since we can't tell whether they were already forwarded.
+const WINDOW: u64 = 1024;
+
+/// Rewrites incoming RTP packets onto one outgoing SSRC for forwarding in an SFU.
+///
+/// An SFU forwarding a simulcast or otherwise switching video source, needs to present the
+/// receiving peer with a single continuous stream: sequence numbers without jumps, and RTP
+/// timestamps that keep advancing at the clock rate. This context maps the sequence number and
+/// RTP time of each forwarded [`RtpPacket`] onto the outgoing stream.
+///
+/// * **Layer switches**. A packet with an SSRC different to the current source switches the
+///   context to that source. The first packet after the switch continues directly after the last
+///   forwarded sequence number, and the RTP time is advanced by the wallclock time elapsed since
+///   the last forwarded packet. Packets from the new source older than the switch are dropped.
+///   The caller decides when to switch, which typically is on a keyframe.
+/// * **Switch at keyframe**. Instead of deciding per packet, [`RewriteContext::switch_at_keyframe()`]
+///   marks a pending switch, and [`RewriteContext::forward()`] keeps forwarding the current source
+///   until the first packet of a keyframe arrives on the new one.
+/// * **Dropped packets**. Packets the SFU chooses not to forward (such as a higher temporal layer)
+///   must be passed to [`RewriteContext::skip()`] to keep the outgoing sequence numbers contiguous.
+/// * **RTX**. Incoming RTX packets are already unwrapped by str0m to the original sequence
+///   number of the main stream, and the outgoing [`StreamTx`][crate::rtp::StreamTx] wraps
+///   resends in RTX. A repaired packet maps to the same outgoing sequence number as the lost
+///   original would have, and packets that were already forwarded are not forwarded again.
+/// * **Marker bits** are kept as is. Since a switch can't complete a frame already partially
+///   forwarded, [`RewriteContext::is_frame_complete()`] tells whether a switch would cut a frame.
+///
+/// ```no_run
+/// # use str0m::rtp::{RewriteContext, RtpPacket, StreamTx};
+/// # use str0m::media::Pt;
+/// # fn forward(ctx: &mut RewriteContext, packet: RtpPacket, tx: &mut StreamTx, pt: Pt) {
+/// let Some(rewritten) = ctx.rewrite(&packet) else {
+///     return;
+/// };
+///
+/// tx.write_rtp(
+///     pt,
+///     rewritten.seq_no,
+///     rewritten.time,
+///     packet.timestamp,
+///     rewritten.marker,
+///     packet.header.ext_vals,
+///     true,
+///     packet.payload,
+/// )
+/// .unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RewriteContext {
+    ssrc: Ssrc,
+    source: Option<Source>,
+    last: Option<Last>,
+    pending: Option<Ssrc>,
+}
+
+#[derive(Debug)]
+struct Source {
+    ssrc: Ssrc,
+    /// The first input sequence number after the switch. Older packets are dropped.
+    first_seq: u64,
+    /// Highest input sequence number seen, forwarded or skipped.
+    max_seq: u64,
+    /// Added (wrapping) to the input sequence number.
+    seq_offset: u64,
+    /// Added (wrapping) to the input RTP time.
+    time_offset: u64,
+    /// Skipped input sequence numbers in ascending order, within the window.
+    skipped: VecDeque<u64>,
+    /// Input sequence numbers forwarded or skipped, indexed by `seq % WINDOW`.
+    seen: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Last {
+    seq_no: u64,
+    time: u64,
+    timestamp: Instant,
+    marker: bool,
+}
+
+/// Outgoing values for a packet rewritten by [`RewriteContext::rewrite()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rewritten {
+    /// Sequence number to send the packet with.
+    pub seq_no: SeqNo,
+    /// RTP time to send the packet with.
+    pub time: u32,
+    /// Whether to mark the packet.
+    pub marker: bool,
+}
+
+impl RewriteContext {
+    /// Create a new context writing to the outgoing `ssrc`.
+    ///
+    /// The first forwarded source passes its sequence numbers and RTP times through unchanged.
+    pub fn new(ssrc: Ssrc) -> Self {
+        RewriteContext {
+            ssrc,
+            source: None,
+            last: None,
+            pending: None,
+        }
+    }
+
+    /// The outgoing SSRC.
+    pub fn ssrc(&self) -> Ssrc {
+        self.ssrc
+    }
+
+    /// The SSRC of the incoming stream currently forwarded.
+    pub fn source(&self) -> Option<Ssrc> {
+        self.source.as_ref().map(|s| s.ssrc)
+    }
+
+    /// The SSRC of the incoming stream to switch to at its next keyframe.
+    ///
+    /// See [`RewriteContext::switch_at_keyframe()`].
+    pub fn pending_switch(&self) -> Option<Ssrc> {
+        self.pending
+    }
+
+    /// Switch to the incoming stream `ssrc` at the next keyframe it sends.
+    ///
+    /// Until then [`RewriteContext::forward()`] continues forwarding the current source. The
+    /// caller would typically request a keyframe on the new source when doing this. Switching
+    /// to the current source cancels a pending switch.
+    pub fn switch_at_keyframe(&mut self, ssrc: Ssrc) {
+        if self.source() == Some(ssrc) {
+            self.pending = None;
+        } else {
+            self.pending = Some(ssrc);
+        }
+    }
+
+    /// Rewrite a packet from any of the incoming streams of a layer switch.
+    ///
+    /// Forwards packets of the current source, until a pending switch, set with
+    /// [`RewriteContext::switch_at_keyframe()`], finds the start of a keyframe on the new
+    /// source (see [`Codec::is_keyframe_start()`]). From that packet on, the new source is
+    /// forwarded and the old is dropped. Packets of other streams are dropped.
+    ///
+    /// The very first packet forwarded with no pending switch makes its stream the source.
+    pub fn forward(&mut self, packet: &RtpPacket, codec: Codec) -> Option<Rewritten> {
+        let ssrc = packet.header.ssrc;
+
+        if self.pending == Some(ssrc) {
+            if !codec.is_keyframe_start(&packet.payload) {
+                return None;
+            }
+            self.pending = None;
+            return self.rewrite(packet);
+        }
+
+        let is_source = match self.source() {
+            Some(s) => s == ssrc,
+            None => self.pending.is_none(),
+        };
+
+        if !is_source {
+            return None;
+        }
+
+        self.rewrite(packet)
+    }
+
+    /// Whether the last forwarded packet ended a frame (had the marker bit set).
+    ///
+    /// Switching source while this is false leaves the receiver with an incomplete frame.
+    pub fn is_frame_complete(&self) -> bool {
+        self.last.map(|l| l.marker).unwrap_or(true)
+    }
+
+    /// Rewrite an incoming packet for forwarding.
+    ///
+    /// Returns `None` if the packet should not be forwarded. That is when it's older than
+    /// the last switch, too old to tell, or already forwarded.
+    pub fn rewrite(&mut self, packet: &RtpPacket) -> Option<Rewritten> {
+        let ssrc = packet.header.ssrc;
+        let seq = *packet.seq_no;
+        let time = packet.time.numer();
+
+        if self.source() != Some(ssrc) {
+            self.switch(packet);
+        }
+
+        let source = self.source.as_mut().expect("source after switch");
+
+        if !source.accept(seq) {
+            return None;
+        }
+
+        let seq_no = source.out_seq(seq);
+        let time = time.wrapping_add(source.time_offset);
+
+        let is_newest = self.last.map(|l| seq_no > l.seq_no).unwrap_or(true);
+        if is_newest {
+            self.last = Some(Last {
+                seq_no,
+                time,
+                timestamp: packet.timestamp,
+                marker: packet.header.marker,
+            });
+        }
+
+        Some(Rewritten {
+            seq_no: seq_no.into(),
+            time: time as u32,
+            marker: packet.header.marker,
+        })
+    }
+
+    /// Tell the context about a packet from the current source that is not forwarded.
+    ///
+    /// This closes the gap in outgoing sequence numbers the packet would otherwise leave.
+    /// Packets from other sources are ignored. A skipped packet arriving out of order, after
+    /// a newer packet was forwarded, can't be closed and leaves a gap.
+    pub fn skip(&mut self, packet: &RtpPacket) {
+        let Some(source) = &mut self.source else {
+            return;
+        };
+        if source.ssrc != packet.header.ssrc {
+            return;
+        }
+
+        let seq = *packet.seq_no;
+
+        if !source.accept(seq) {
+            return;
+        }
+
+        // Only packets after everything seen so far can be removed from the sequence.
+        // The max_seq was updated by accept().
+        if seq == source.max_seq && source.skipped.back().map(|s| *s < seq).unwrap_or(true) {
+            source.skipped.push_back(seq);
+        }
+    }
+
+    fn switch(&mut self, packet: &RtpPacket) {
+        let seq = *packet.seq_no;
+        let time = packet.time.numer();
+
+        let (next_seq, next_time) = match self.last {
+            Some(last) => {
+                let elapsed = packet.timestamp.saturating_duration_since(last.timestamp);
+                let ticks = elapsed.as_micros() as u64 * packet.time.denom() as u64 / 1_000_000;
+                // The new frame must never share RTP time with the previous.
+                (last.seq_no + 1, last.time.wrapping_add(ticks.max(1)))
+            }
+            // The first source passes through.
+            None => (seq, time),
+        };
+
+        debug!(
+            "Rewrite {} switch source {:?} -> {}",
+            self.ssrc,
+            self.source(),
+            packet.header.ssrc
+        );
+
+        self.source = Some(Source {
+            ssrc: packet.header.ssrc,
+            first_seq: seq,
+            max_seq: seq,
+            seq_offset: next_seq.wrapping_sub(seq),
+            time_offset: next_time.wrapping_sub(time),
+            skipped: VecDeque::new(),
+            seen: vec![u64::MAX; WINDOW as usize],
+        });
+    }
+}
+
+impl Source {
+    /// Check whether the input sequence number is new, and if so mark it as seen.
+    fn accept(&mut self, seq: u64) -> bool {
+        if seq < self.first_seq || seq + WINDOW <= self.max_seq {
+            return false;
+        }
+
+        let slot = &mut self.seen[(seq % WINDOW) as usize];
+        if *slot == seq {
+            return false;
+        }
+        *slot = seq;
+
+        if seq > self.max_seq {
+            self.max_seq = seq;
+
+            // Skipped packets falling out of the window are folded into the offset.
+            while let Some(s) = self.skipped.front() {
+                if *s + WINDOW > self.max_seq {
+                    break;
+                }
+                self.skipped.pop_front();
+                self.seq_offset = self.seq_offset.wrapping_sub(1);
+            }
+        }
+
+        true
+    }
+
+    fn out_seq(&self, seq: u64) -> u64 {
+        let skipped = self.skipped.iter().take_while(|s| **s < seq).count() as u64;
+        seq.wrapping_add(self.seq_offset).wrapping_sub(skipped)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::rtp_::{MediaTime, RtpHeader};
+
+    static START: once_cell::sync::OnceCell<Instant> = once_cell::sync::OnceCell::new();
+
+    fn packet(ssrc: u32, seq: u64, time: u64, ms: u64, marker: bool) -> RtpPacket {
+        let start = *START.get_or_init(Instant::now);
+        RtpPacket {
+            seq_no: seq.into(),
+            time: MediaTime::from_90khz(time),
+            header: RtpHeader {
+                ssrc: ssrc.into(),
+                marker,
+                sequence_number: seq as u16,
+                timestamp: time as u32,
+                ..Default::default()
+            },
+            payload: vec![].into(),
+            timestamp: start + Duration::from_millis(ms),
+            last_sender_info: None,
+            nackable: true,
+        }
+    }
+
+    fn with_payload(mut packet: RtpPacket, payload: &[u8]) -> RtpPacket {
+        packet.payload = payload.to_vec().into();
+        packet
+    }
+
+    fn seq_time(r: Option<Rewritten>) -> (u64, u32) {
+        let r = r.unwrap();
+        (*r.seq_no, r.time)
+    }
+
+    #[test]
+    fn first_source_passes_through() {
+        let mut ctx = RewriteContext::new(1.into());
+
+        assert_eq!(
+            seq_time(ctx.rewrite(&packet(10, 100, 9000, 0, true))),
+            (100, 9000)
+        );
+        assert_eq!(
+            seq_time(ctx.rewrite(&packet(10, 101, 12000, 33, true))),
+            (101, 12000)
+        );
+        assert_eq!(ctx.source(), Some(10.into()));
+    }
+
+    #[test]
+    fn switch_continues_sequence() {
+        let mut ctx = RewriteContext::new(1.into());
+
+        ctx.rewrite(&packet(10, 100, 9000, 0, true));
+        ctx.rewrite(&packet(10, 101, 12000, 33, true));
+
+        // Switch 10ms later to an unrelated stream.
+        let r = ctx.rewrite(&packet(20, 5000, 777, 43, false));
+        assert_eq!(seq_time(r), (102, 12000 + 900));
+        assert_eq!(ctx.source(), Some(20.into()));
+        assert!(!ctx.is_frame_complete());
+
+        assert_eq!(
+            seq_time(ctx.rewrite(&packet(20, 5001, 777, 43, true))),
+            (103, 12900)
+        );
+        assert!(ctx.is_frame_complete());
+
+        // Older than the switch.
+        assert!(ctx.rewrite(&packet(20, 4999, 700, 44, true)).is_none());
+    }
+
+    #[test]
+    fn switch_advances_time() {
+        let mut ctx = RewriteContext::new(1.into());
+
+        ctx.rewrite(&packet(10, 100, 9000, 0, true));
+        let r = ctx.rewrite(&packet(20, 1, 0, 0, true));
+
+        assert_eq!(seq_time(r), (101, 9001));
+    }
+
+    #[test]
+    fn skip_closes_gap() {
+        let mut ctx = RewriteContext::new(1.into());
+
+        ctx.rewrite(&packet(10, 100, 9000, 0, true));
+        ctx.skip(&packet(10, 101, 12000, 33, true));
+        ctx.skip(&packet(10, 102, 15000, 66, true));
+
+        let r = ctx.rewrite(&packet(10, 103, 18000, 99, true));
+        assert_eq!(seq_time(r), (101, 18000));
+
+        // Other sources are ignored.
+        ctx.skip(&packet(20, 104, 18000, 99, true));
+        let r = ctx.rewrite(&packet(10, 104, 21000, 132, true));
+        assert_eq!(seq_time(r), (102, 21000));
+    }
+
+    #[test]
+    fn reordered_and_repaired() {
+        let mut ctx = RewriteContext::new(1.into());
+
+        ctx.rewrite(&packet(10, 100, 9000, 0, true));
+        assert_eq!(
+            seq_time(ctx.rewrite(&packet(10, 102, 9000, 2, true))),
+            (102, 9000)
+        );
+
+        // Lost packet repaired via RTX maps to its original place.
+        assert_eq!(
+            seq_time(ctx.rewrite(&packet(10, 101, 9000, 50, false))),
+            (101, 9000)
+        );
+
+        // Already forwarded.
+        assert!(ctx.rewrite(&packet(10, 101, 9000, 60, false)).is_none());
+
+        // Too old.
+        ctx.rewrite(&packet(10, 103 + WINDOW, 9000, 70, true));
+        assert!(ctx.rewrite(&packet(10, 103, 9000, 80, true)).is_none());
+    }
+
+    #[test]
+    fn skipped_fall_out_of_window() {
+        let mut ctx = RewriteContext::new(1.into());
+
+        ctx.rewrite(&packet(10, 100, 9000, 0, true));
+        ctx.skip(&packet(10, 101, 9000, 0, true));
+
+        let r = ctx.rewrite(&packet(10, 101 + WINDOW * 2, 9000, 0, true));
+        assert_eq!(*r.unwrap().seq_no, 100 + WINDOW * 2);
+        assert!(ctx.source.as_ref().unwrap().skipped.is_empty());
+    }
+
+    #[test]
+    fn switch_at_keyframe() {
+        const KEY: &[u8] = &[0x10, 0x50, 0x01];
+        const DELTA: &[u8] = &[0x10, 0x51, 0x01];
+
+        let mut ctx = RewriteContext::new(1.into());
+        let fwd = |ctx: &mut RewriteContext, ssrc, seq, time, ms, payload| {
+            let p = with_payload(packet(ssrc, seq, time, ms, true), payload);
+            ctx.forward(&p, Codec::Vp8).map(|r| (*r.seq_no, r.time))
+        };
+
+        assert_eq!(fwd(&mut ctx, 10, 100, 9000, 0, KEY), Some((100, 9000)));
+        // Other layers are dropped.
+        assert_eq!(fwd(&mut ctx, 20, 500, 100, 0, KEY), None);
+
+        ctx.switch_at_keyframe(20.into());
+        assert_eq!(ctx.pending_switch(), Some(20.into()));
+
+        // Keep forwarding the current layer until the new one has a keyframe.
+        assert_eq!(fwd(&mut ctx, 20, 501, 3100, 33, DELTA), None);
+        assert_eq!(fwd(&mut ctx, 10, 101, 12000, 33, DELTA), Some((101, 12000)));
+
+        // Keyframe on the new layer, 10ms after the last forwarded.
+        assert_eq!(fwd(&mut ctx, 20, 502, 6100, 43, KEY), Some((102, 12900)));
+        assert_eq!(ctx.source(), Some(20.into()));
+        assert_eq!(ctx.pending_switch(), None);
+
+        // The old layer is dropped, the new continues.
+        assert_eq!(fwd(&mut ctx, 10, 102, 15000, 66, DELTA), None);
+        assert_eq!(fwd(&mut ctx, 20, 503, 9100, 76, DELTA), Some((103, 15900)));
+    }
+
+    #[test]
+    fn switch_at_keyframe_to_current_cancels() {
+        let mut ctx = RewriteContext::new(1.into());
+
+        ctx.rewrite(&packet(10, 100, 9000, 0, true));
+        ctx.switch_at_keyframe(20.into());
+        ctx.switch_at_keyframe(10.into());
+
+        assert_eq!(ctx.pending_switch(), None);
+    }
+}
@@ -5,6 +5,15 @@ use super::{FeedbackMessageType, ReportList, RtcpHeader, RtcpPacket, RtcpType, S
 pub struct Goodbye {
     /// The SSRC that are no longer in use.
     pub reports: ReportList<Ssrc>,
+    /// Optional reason for leaving.
+    pub reason: Option<String>,
+}
+
+impl Goodbye {
+    /// Length of reason text, capped to what fits the 8 bit length field.
+    fn reason_len(&self) -> usize {
+        self.reason.as_ref().map(|r| r.len().min(255)).unwrap_or(0)
+    }
 }
 
 impl RtcpPacket for Goodbye {
@@ -18,7 +27,14 @@ impl RtcpPacket for Goodbye {
 
     fn length_words(&self) -> usize {
         // each ssrc is one word
-        1 + self.reports.len()
+        let ssrcs = 1 + self.reports.len();
+
+        if self.reason.is_none() {
+            return ssrcs;
+        }
+
+        // length byte + text, padded to a word boundary
+        ssrcs + (1 + self.reason_len() + 3) / 4
     }
 
     fn write_to(&self, buf: &mut [u8]) -> usize {
@@ -28,6 +44,16 @@ impl RtcpPacket for Goodbye {
             buf[i * 4..(i + 1) * 4].copy_from_slice(&s.to_be_bytes());
         }
 
+        if let Some(reason) = &self.reason {
+            let len = self.reason_len();
+            let buf = &mut buf[self.reports.len() * 4..(self.length_words() - 1) * 4];
+            buf[0] = len as u8;
+            buf[1..1 + len].copy_from_slice(&reason.as_bytes()[..len]);
+            for b in &mut buf[1 + len..] {
+                *b = 0;
+            }
+        }
+
         self.length_words() * 4
     }
 }
@@ -55,8 +81,51 @@ impl<'a> TryFrom<(usize, &'a [u8])> for Goodbye {
             buf = &buf[4..];
         }
 
-        // TODO parse the optional reason string here.
+        // Skip SSRCs we don't keep.
+        let buf = &buf[(count - max) * 4..];
+
+        // The reason is optional, and its length byte is followed by the text.
+        let len = buf.first().copied().unwrap_or(0) as usize;
+        let reason = if len > 0 {
+            if buf.len() < 1 + len {
+                return Err("Less than reason length bytes for Goodbye");
+            }
+            Some(String::from_utf8_lossy(&buf[1..1 + len]).into_owned())
+        } else {
+            None
+        };
+
+        Ok(Goodbye { reports, reason })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn roundtrip(reason: Option<&str>) {
+        let mut g1 = Goodbye {
+            reports: ReportList::new(),
+            reason: reason.map(|r| r.to_string()),
+        };
+        g1.reports.push(1.into());
+        g1.reports.push(2.into());
+
+        let mut buf = vec![0; 50];
+        let n = g1.write_to(&mut buf);
+        buf.truncate(n);
+
+        assert_eq!(n % 4, 0);
+
+        let g2: Goodbye = (2, &buf[4..]).try_into().unwrap();
+
+        assert_eq!(g1, g2);
+    }
 
-        Ok(Goodbye { reports })
+    #[test]
+    fn goodbye_serialize_deserialize() {
+        roundtrip(None);
+        roundtrip(Some("bye"));
+        roundtrip(Some("see you"));
     }
 }
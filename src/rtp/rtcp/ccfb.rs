@@ -0,0 +1,486 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::util::Instant;
+use crate::util::InstantExt;
+
+use super::{FeedbackMessageType, RtcpHeader, RtcpPacket};
+use super::{RtcpType, SeqNo, Ssrc, TransportType};
+
+/// Max number of packets a single report block can cover.
+///
+/// <https://www.rfc-editor.org/rfc/rfc8888#section-3.1>
+const MAX_REPORTS_PER_BLOCK: u64 = 16384;
+
+/// Arrival time offset value meaning "at or beyond the representable range".
+const ATO_OVERRANGE: u16 = 0x1ffe;
+
+/// RTP Control Protocol (RTCP) Feedback for Congestion Control.
+///
+/// The standardized alternative to [`Twcc`][super::Twcc], defined in
+/// <https://www.rfc-editor.org/rfc/rfc8888>. Unlike TWCC, the feedback is per
+/// SSRC and RTP sequence number and carries the ECN marking of each packet.
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P| FMT=11  |   PT = 205    |          length               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 SSRC of RTCP packet sender                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                   SSRC of 1st RTP Stream                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          begin_seq            |          num_reports          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |R|ECN|  Arrival time offset    | ...                           .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                                                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Report Timestamp (32 bits)                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ccfb {
+    /// Sender of this feedback. Mostly irrelevant, but part of RTCP packets.
+    pub sender_ssrc: Ssrc,
+    /// One report block per reported RTP stream.
+    pub reports: Vec<CcfbReport>,
+    /// Middle 32 bits of the NTP time when this report was produced.
+    ///
+    /// The arrival time offsets are relative to this.
+    pub report_timestamp: u32,
+}
+
+/// The feedback for one RTP stream in a [`Ccfb`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CcfbReport {
+    /// The SSRC of the reported RTP stream.
+    pub ssrc: Ssrc,
+    /// The sequence number of the first metric.
+    pub begin_seq: u16,
+    /// One metric per sequence number starting at `begin_seq`.
+    pub metrics: Vec<CcfbMetric>,
+}
+
+/// Reception of a single RTP packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CcfbMetric {
+    /// Whether the packet was received.
+    pub received: bool,
+    /// ECN marking of the received packet.
+    pub ecn: Ecn,
+    /// Arrival time before the report timestamp in 1/1024 seconds (13 bits).
+    pub arrival_time_offset: u16,
+}
+
+/// Explicit Congestion Notification codepoints.
+///
+/// <https://www.rfc-editor.org/rfc/rfc3168#section-5>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ecn {
+    /// Not ECN-Capable Transport.
+    #[default]
+    NotEct = 0b00,
+    /// ECN Capable Transport(1).
+    Ect1 = 0b01,
+    /// ECN Capable Transport(0).
+    Ect0 = 0b10,
+    /// Congestion Experienced.
+    Ce = 0b11,
+}
+
+/// Number of received packets per ECN marking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EcnCounts {
+    /// Packets not marked as ECN capable.
+    pub not_ect: u64,
+    /// Packets marked ECT(1).
+    pub ect1: u64,
+    /// Packets marked ECT(0).
+    pub ect0: u64,
+    /// Packets marked as Congestion Experienced.
+    pub ce: u64,
+}
+
+impl Ccfb {
+    /// Sum up the ECN markings of all received packets in this feedback.
+    pub fn ecn_counts(&self) -> EcnCounts {
+        let mut counts = EcnCounts::default();
+
+        let received = self
+            .reports
+            .iter()
+            .flat_map(|r| r.metrics.iter())
+            .filter(|m| m.received);
+
+        for m in received {
+            match m.ecn {
+                Ecn::NotEct => counts.not_ect += 1,
+                Ecn::Ect1 => counts.ect1 += 1,
+                Ecn::Ect0 => counts.ect0 += 1,
+                Ecn::Ce => counts.ce += 1,
+            }
+        }
+
+        counts
+    }
+}
+
+impl CcfbReport {
+    fn length_words(&self) -> usize {
+        // ssrc
+        // begin_seq + num_reports
+        // metrics, 2 bytes each padded to the word boundary.
+        2 + (self.metrics.len() + 1) / 2
+    }
+
+    /// Iterate over the reported sequence numbers, extended from a nearby sequence number.
+    pub fn iter(&self, extend_from: SeqNo) -> impl Iterator<Item = (SeqNo, CcfbMetric)> + '_ {
+        let begin = super::extend_u16(Some(*extend_from), self.begin_seq);
+        self.metrics
+            .iter()
+            .enumerate()
+            .map(move |(i, m)| ((begin + i as u64).into(), *m))
+    }
+}
+
+impl CcfbMetric {
+    /// Arrival time before the report timestamp, if received and in range.
+    pub fn arrival_offset(&self) -> Option<Duration> {
+        if !self.received || self.arrival_time_offset >= ATO_OVERRANGE {
+            return None;
+        }
+        Some(Duration::from_micros(
+            self.arrival_time_offset as u64 * 1_000_000 / 1024,
+        ))
+    }
+}
+
+impl From<u8> for Ecn {
+    fn from(v: u8) -> Self {
+        match v & 0b11 {
+            0b00 => Ecn::NotEct,
+            0b01 => Ecn::Ect1,
+            0b10 => Ecn::Ect0,
+            _ => Ecn::Ce,
+        }
+    }
+}
+
+impl RtcpPacket for Ccfb {
+    fn header(&self) -> RtcpHeader {
+        RtcpHeader {
+            rtcp_type: RtcpType::TransportLayerFeedback,
+            feedback_message_type: FeedbackMessageType::TransportFeedback(
+                TransportType::CongestionControlFeedback,
+            ),
+            words_less_one: (self.length_words() - 1) as u16,
+        }
+    }
+
+    fn length_words(&self) -> usize {
+        // header
+        // sender SSRC
+        // report blocks
+        // report timestamp
+        1 + 1 + self.reports.iter().map(|r| r.length_words()).sum::<usize>() + 1
+    }
+
+    fn write_to(&self, buf: &mut [u8]) -> usize {
+        self.header().write_to(&mut buf[..4]);
+        buf[4..8].copy_from_slice(&self.sender_ssrc.to_be_bytes());
+
+        let mut offset = 8;
+
+        for r in &self.reports {
+            buf[offset..offset + 4].copy_from_slice(&r.ssrc.to_be_bytes());
+            buf[offset + 4..offset + 6].copy_from_slice(&r.begin_seq.to_be_bytes());
+            buf[offset + 6..offset + 8].copy_from_slice(&(r.metrics.len() as u16).to_be_bytes());
+            offset += 8;
+
+            for m in &r.metrics {
+                let v = if m.received {
+                    0x8000 | (m.ecn as u16) << 13 | (m.arrival_time_offset & 0x1fff)
+                } else {
+                    0
+                };
+                buf[offset..offset + 2].copy_from_slice(&v.to_be_bytes());
+                offset += 2;
+            }
+
+            if r.metrics.len() % 2 == 1 {
+                buf[offset..offset + 2].copy_from_slice(&[0, 0]);
+                offset += 2;
+            }
+        }
+
+        buf[offset..offset + 4].copy_from_slice(&self.report_timestamp.to_be_bytes());
+        offset += 4;
+
+        offset
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Ccfb {
+    type Error = &'static str;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        if buf.len() < 8 {
+            return Err("Ccfb less than 8 bytes");
+        }
+
+        let sender_ssrc = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]).into();
+
+        // The report timestamp is the last word.
+        let end = buf.len() - 4;
+        let report_timestamp =
+            u32::from_be_bytes([buf[end], buf[end + 1], buf[end + 2], buf[end + 3]]);
+
+        let mut reports = Vec::new();
+        let mut offset = 4;
+
+        while offset < end {
+            if end - offset < 8 {
+                return Err("Ccfb report block less than 8 bytes");
+            }
+
+            let b = &buf[offset..];
+            let ssrc = u32::from_be_bytes([b[0], b[1], b[2], b[3]]).into();
+            let begin_seq = u16::from_be_bytes([b[4], b[5]]);
+            let num_reports = u16::from_be_bytes([b[6], b[7]]) as usize;
+            offset += 8;
+
+            let metrics_len = num_reports * 2;
+            let padded_len = (metrics_len + 3) / 4 * 4;
+            if end - offset < padded_len {
+                return Err("Ccfb metrics beyond end of packet");
+            }
+
+            let metrics = buf[offset..offset + metrics_len]
+                .chunks_exact(2)
+                .map(|c| {
+                    let v = u16::from_be_bytes([c[0], c[1]]);
+                    CcfbMetric {
+                        received: v & 0x8000 > 0,
+                        ecn: ((v >> 13) as u8).into(),
+                        arrival_time_offset: v & 0x1fff,
+                    }
+                })
+                .collect();
+            offset += padded_len;
+
+            reports.push(CcfbReport {
+                ssrc,
+                begin_seq,
+                metrics,
+            });
+        }
+
+        Ok(Ccfb {
+            sender_ssrc,
+            reports,
+            report_timestamp,
+        })
+    }
+}
+
+/// Register of received RTP packets to build [`Ccfb`] reports from.
+#[derive(Debug, Default)]
+pub struct CcfbRecvRegister {
+    streams: BTreeMap<Ssrc, CcfbStreamRegister>,
+}
+
+#[derive(Debug, Default)]
+struct CcfbStreamRegister {
+    /// Next sequence number to report from.
+    report_from: Option<SeqNo>,
+    /// Unreported received packets, ordered by sequence number.
+    received: BTreeMap<SeqNo, (Instant, Ecn)>,
+}
+
+impl CcfbRecvRegister {
+    /// Record a received RTP packet.
+    pub fn update_seq(&mut self, ssrc: Ssrc, seq: SeqNo, time: Instant, ecn: Ecn) {
+        let stream = self.streams.entry(ssrc).or_default();
+
+        if let Some(report_from) = stream.report_from {
+            if seq < report_from {
+                // Already reported as lost. Too late.
+                return;
+            }
+        }
+
+        stream.received.insert(seq, (time, ecn));
+    }
+
+    /// Whether there are packets not yet reported.
+    pub fn has_unreported(&self) -> bool {
+        self.streams.values().any(|s| !s.received.is_empty())
+    }
+
+    /// Build a report of all unreported packets.
+    pub fn build_report(&mut self, now: Instant, max_byte_size: usize) -> Option<Ccfb> {
+        let mut ccfb = Ccfb {
+            sender_ssrc: 0.into(),
+            reports: vec![],
+            report_timestamp: (now.as_ntp_64() >> 16) as u32,
+        };
+
+        // header, sender ssrc and report timestamp
+        let mut words_left = (max_byte_size / 4).checked_sub(3)?;
+
+        for (ssrc, stream) in &mut self.streams {
+            let Some(last) = stream.received.keys().next_back().copied() else {
+                continue;
+            };
+            let first = stream
+                .report_from
+                .or_else(|| stream.received.keys().next().copied())
+                .expect("a first seq");
+
+            // Limit by the max number of metrics in a block, and the space left.
+            let max_count = MAX_REPORTS_PER_BLOCK.min((words_left.saturating_sub(2) * 2) as u64);
+            if max_count == 0 {
+                break;
+            }
+            let count = (*last - *first + 1).min(max_count);
+
+            let metrics = (0..count)
+                .map(|i| {
+                    let seq: SeqNo = (*first + i).into();
+                    match stream.received.remove(&seq) {
+                        Some((time, ecn)) => CcfbMetric {
+                            received: true,
+                            ecn,
+                            arrival_time_offset: arrival_time_offset(now, time),
+                        },
+                        None => CcfbMetric {
+                            received: false,
+                            ecn: Ecn::NotEct,
+                            arrival_time_offset: 0,
+                        },
+                    }
+                })
+                .collect();
+
+            stream.report_from = Some((*first + count).into());
+
+            let report = CcfbReport {
+                ssrc: *ssrc,
+                begin_seq: *first as u16,
+                metrics,
+            };
+            words_left -= report.length_words();
+            ccfb.reports.push(report);
+        }
+
+        if ccfb.reports.is_empty() {
+            return None;
+        }
+
+        Some(ccfb)
+    }
+}
+
+fn arrival_time_offset(now: Instant, arrival: Instant) -> u16 {
+    let offset = now.saturating_duration_since(arrival);
+    let v = offset.as_micros() * 1024 / 1_000_000;
+    v.min(ATO_OVERRANGE as u128) as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn metric(ecn: Ecn, ato: u16) -> CcfbMetric {
+        CcfbMetric {
+            received: true,
+            ecn,
+            arrival_time_offset: ato,
+        }
+    }
+
+    #[test]
+    fn ccfb_roundtrip() {
+        let ccfb = Ccfb {
+            sender_ssrc: 1.into(),
+            reports: vec![
+                CcfbReport {
+                    ssrc: 2.into(),
+                    begin_seq: 65534,
+                    metrics: vec![
+                        metric(Ecn::Ect0, 100),
+                        CcfbMetric {
+                            received: false,
+                            ecn: Ecn::NotEct,
+                            arrival_time_offset: 0,
+                        },
+                        metric(Ecn::Ce, 12),
+                    ],
+                },
+                CcfbReport {
+                    ssrc: 3.into(),
+                    begin_seq: 10,
+                    metrics: vec![metric(Ecn::NotEct, 0x1fff), metric(Ecn::Ect1, 1)],
+                },
+            ],
+            report_timestamp: 0x1234_5678,
+        };
+
+        let mut buf = vec![0; 1500];
+        let n = ccfb.write_to(&mut buf);
+        buf.truncate(n);
+
+        assert_eq!(n, ccfb.length_words() * 4);
+        assert_eq!(n, 4 + 4 + 16 + 12 + 4);
+
+        let header: RtcpHeader = buf.as_slice().try_into().unwrap();
+        assert_eq!(header, ccfb.header());
+
+        let parsed: Ccfb = buf[4..].try_into().unwrap();
+        assert_eq!(parsed, ccfb);
+
+        let counts = parsed.ecn_counts();
+        assert_eq!(
+            counts,
+            EcnCounts {
+                not_ect: 1,
+                ect1: 1,
+                ect0: 1,
+                ce: 1
+            }
+        );
+    }
+
+    #[test]
+    fn ccfb_register() {
+        let now = Instant::now();
+        let mut reg = CcfbRecvRegister::default();
+
+        reg.update_seq(1.into(), 10.into(), now, Ecn::NotEct);
+        reg.update_seq(1.into(), 12.into(), now, Ecn::Ce);
+        assert!(reg.has_unreported());
+
+        let later = now + Duration::from_millis(500);
+        let ccfb = reg.build_report(later, 1200).unwrap();
+        assert!(!reg.has_unreported());
+
+        assert_eq!(ccfb.reports.len(), 1);
+        let r = &ccfb.reports[0];
+        assert_eq!(r.begin_seq, 10);
+        assert_eq!(r.metrics.len(), 3);
+        assert!(r.metrics[0].received);
+        assert!(!r.metrics[1].received);
+        assert_eq!(r.metrics[2].ecn, Ecn::Ce);
+        assert_eq!(r.metrics[2].arrival_time_offset, 512);
+
+        // Next report continues from where the last ended.
+        reg.update_seq(1.into(), 14.into(), later, Ecn::NotEct);
+        // Too late, already reported as lost.
+        reg.update_seq(1.into(), 11.into(), later, Ecn::NotEct);
+        let ccfb = reg.build_report(later, 1200).unwrap();
+        assert_eq!(ccfb.reports[0].begin_seq, 13);
+        assert_eq!(ccfb.reports[0].metrics.len(), 2);
+    }
+}
@@ -65,6 +65,11 @@ pub enum TransportType {
     ///
     /// Definition: <https://tools.ietf.org/html/draft-holmer-rmcat-transport-wide-cc-extensions-01>
     TransportWide = 15,
+
+    /// RTP Control Protocol (RTCP) Feedback for Congestion Control.
+    ///
+    /// Definition: <https://www.rfc-editor.org/rfc/rfc8888>
+    CongestionControlFeedback = 11,
 }
 
 impl TryFrom<u8> for TransportType {
@@ -74,6 +79,7 @@ impl TryFrom<u8> for TransportType {
         use TransportType::*;
         match v {
             1 => Ok(Nack),
+            11 => Ok(CongestionControlFeedback),
             15 => Ok(TransportWide),
             _ => {
                 trace!("Uknown TransportType: {}", v);
@@ -52,6 +52,10 @@ impl RtcpHeader {
                         // TODO
                         0
                     }
+                    TransportType::CongestionControlFeedback => {
+                        // Number of report blocks is not known from the header.
+                        0
+                    }
                 }
             }
             RtcpType::PayloadSpecificFeedback => {
@@ -35,24 +35,26 @@ impl<T> ReportList<T> {
         self.len() == 0
     }
 
-    pub(crate) fn lists_from_iter(iterator: impl IntoIterator<Item = T>) -> Vec<Self> {
-        let mut result = vec![];
-        let mut current = Self::default();
-
-        for (i, item) in iterator.into_iter().enumerate() {
-            if i % 31 == 0 && i != 0 {
-                result.push(current);
-                current = Self::default();
+    /// Chunk the items into lists of max 31 items.
+    ///
+    /// The lists are produced lazily, one at a time, without allocating.
+    pub(crate) fn lists_from_iter(
+        iterator: impl IntoIterator<Item = T>,
+    ) -> impl Iterator<Item = Self> {
+        let mut iterator = iterator.into_iter();
+
+        std::iter::from_fn(move || {
+            let mut current = Self::default();
+
+            while !current.is_full() {
+                let Some(item) = iterator.next() else {
+                    break;
+                };
+                current.push(item);
             }
 
-            current.push(item);
-        }
-
-        if !current.is_empty() {
-            result.push(current);
-        }
-
-        result
+            (!current.is_empty()).then_some(current)
+        })
     }
 
     pub(crate) fn is_full(&self) -> bool {
@@ -212,7 +214,7 @@ mod test {
 
     #[test]
     fn test_lists_from_iter() {
-        let lists = ReportList::lists_from_iter(0..66);
+        let lists: Vec<_> = ReportList::lists_from_iter(0..66).collect();
 
         assert_eq!(lists.len(), 3);
         assert_eq!(lists[0].len(), 31);
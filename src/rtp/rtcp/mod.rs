@@ -37,7 +37,12 @@ mod fir;
 pub use fir::{Fir, FirEntry};
 
 mod twcc;
-pub use twcc::{Twcc, TwccRecvRegister, TwccSendRecord, TwccSendRegister};
+#[cfg(feature = "bwe")]
+pub use twcc::TwccSendRecord;
+pub use twcc::{Twcc, TwccRecvRegister, TwccSendRegister};
+
+mod ccfb;
+pub use ccfb::{Ccfb, CcfbMetric, CcfbRecvRegister, CcfbReport, Ecn, EcnCounts};
 
 mod rtcpfb;
 pub use rtcpfb::RtcpFb;
@@ -88,6 +93,8 @@ pub enum Rtcp {
     Twcc(Twcc),
     /// Receiver Estimated Maximum Bitrate. Feedback to the sender about the maximum bitrate.
     Remb(Remb),
+    /// RFC 8888 Congestion Control Feedback. Standardized alternative to TWCC.
+    Ccfb(Ccfb),
 }
 
 impl Rtcp {
@@ -202,7 +209,7 @@ impl Rtcp {
             }
 
             // Stack source descriptions.
-            (Rtcp::Goodbye(g1), Rtcp::Goodbye(g2)) => {
+            (Rtcp::Goodbye(g1), Rtcp::Goodbye(g2)) if g1.reason == g2.reason => {
                 let n = g1.reports.append_all_possible(&mut g2.reports, words_left);
                 n > 0
             }
@@ -236,6 +243,7 @@ impl Rtcp {
             Rtcp::Fir(v) => v.reports.is_full(),
             Rtcp::Twcc(_) => true,
             Rtcp::Remb(_) => true,
+            Rtcp::Ccfb(_) => true,
         }
     }
 
@@ -263,6 +271,8 @@ impl Rtcp {
             Rtcp::Twcc(_) => false,
             // A REMB report is never empty.
             Rtcp::Remb(_) => false,
+            // A CCFB report is never empty.
+            Rtcp::Ccfb(_) => false,
         }
     }
 
@@ -340,6 +350,7 @@ impl Rtcp {
             Fir(_) => 5,
             Twcc(_) => 6,
             Remb(_) => 7,
+            Ccfb(_) => 8,
             ExtendedReport(_) => 10,
 
             // Goodbye last since they remove stuff.
@@ -361,6 +372,7 @@ impl RtcpPacket for Rtcp {
             Rtcp::Fir(v) => v.header(),
             Rtcp::Twcc(v) => v.header(),
             Rtcp::Remb(v) => v.header(),
+            Rtcp::Ccfb(v) => v.header(),
         }
     }
 
@@ -376,6 +388,7 @@ impl RtcpPacket for Rtcp {
             Rtcp::Fir(v) => v.length_words(),
             Rtcp::Twcc(v) => v.length_words(),
             Rtcp::Remb(v) => v.length_words(),
+            Rtcp::Ccfb(v) => v.length_words(),
         }
     }
 
@@ -391,6 +404,7 @@ impl RtcpPacket for Rtcp {
             Rtcp::Fir(v) => v.write_to(buf),
             Rtcp::Twcc(v) => v.write_to(buf),
             Rtcp::Remb(v) => v.write_to(buf),
+            Rtcp::Ccfb(v) => v.write_to(buf),
         }
     }
 }
@@ -421,6 +435,7 @@ impl<'a> TryFrom<&'a [u8]> for Rtcp {
                 match tlfb {
                     TransportType::Nack => Rtcp::Nack(buf.try_into()?),
                     TransportType::TransportWide => Rtcp::Twcc(buf.try_into()?),
+                    TransportType::CongestionControlFeedback => Rtcp::Ccfb(buf.try_into()?),
                 }
             }
             RtcpType::PayloadSpecificFeedback => {
@@ -472,6 +487,7 @@ mod test {
     use std::time::{Duration, Instant};
 
     use crate::rtp_::MediaTime;
+    use crate::util::{SystemTime, TimeAnchor};
 
     use super::twcc::{Delta, PacketChunk, PacketStatus};
     use super::*;
@@ -603,6 +619,38 @@ mod test {
         assert!(abs < Duration::from_millis(1));
     }
 
+    #[test]
+    fn sr_ntp_time_from_time_anchor() {
+        let start = Instant::now();
+        let unix = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let _time = TimeAnchor::with_system_time(start, unix).enter();
+
+        let t = start + Duration::from_millis(500);
+
+        let mut feedback = VecDeque::new();
+        feedback.push_back(sr(1, t));
+
+        let mut buf = vec![0_u8; 1360];
+        let n = Rtcp::write_packet(&mut feedback, &mut buf, |_| {});
+        buf.truncate(n);
+
+        // The NTP timestamp follows the header and the sender SSRC.
+        let ntp = u64::from_be_bytes(buf[8..16].try_into().unwrap());
+        assert_eq!(ntp >> 32, 1_700_000_000 + 2_208_988_800);
+        assert_eq!(ntp & 0xffff_ffff, 0x8000_0000);
+
+        let mut parsed = VecDeque::new();
+        Rtcp::read_packet(&buf, &mut parsed);
+
+        let Rtcp::SenderReport(s) = parsed.get(0).unwrap() else {
+            panic!("Not a SenderReport in Rtcp");
+        };
+        let t2 = s.sender_info.ntp_time;
+
+        let abs = if t > t2 { t - t2 } else { t2 - t };
+        assert!(abs < Duration::from_micros(1));
+    }
+
     fn sr(ssrc: u32, ntp_time: Instant) -> Rtcp {
         Rtcp::SenderReport(SenderReport {
             sender_info: SenderInfo {
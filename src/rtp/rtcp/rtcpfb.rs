@@ -1,5 +1,7 @@
+use std::collections::VecDeque;
+
+use super::{Ccfb, Rrtr, Rtcp, Sdes, SenderInfo, Ssrc, Twcc};
 use super::{DlrrItem, FirEntry, NackEntry, ReceptionReport, Remb, ReportBlock, ReportList};
-use super::{Rrtr, Rtcp, Sdes, SenderInfo, Ssrc, Twcc};
 
 /// Normalization of [`Rtcp`] so we can deal with one SSRC at a time.
 #[allow(clippy::large_enum_variant)]
@@ -10,12 +12,13 @@ pub enum RtcpFb {
     DlrrItem(DlrrItem),                // rx <- tx
     Rrtr((Rrtr, Ssrc)),                // rx -> tx
     SourceDescription(Sdes),           // tx -> rx
-    Goodbye(Ssrc),                     // tx -> rx
+    Goodbye(Ssrc, Option<String>),     // tx -> rx
     Nack(Ssrc, ReportList<NackEntry>), // rx -> tx
     Pli(Ssrc),                         // rx -> tx
-    Fir(FirEntry),                     // rx -> tx
+    Fir((FirEntry, Ssrc)),             // rx -> tx
     Twcc(Twcc),                        // rx -> tx
     Remb(Remb),                        // rx -> tx
+    Ccfb(Ccfb),                        // rx -> tx
 }
 
 impl RtcpFb {
@@ -24,18 +27,20 @@ impl RtcpFb {
             self,
             RtcpFb::SenderInfo(_)
                 | RtcpFb::SourceDescription(_)
-                | RtcpFb::Goodbye(_)
+                | RtcpFb::Goodbye(..)
                 | RtcpFb::DlrrItem(_)
         )
     }
 
-    pub fn from_rtcp<T: IntoIterator<Item = Rtcp>>(t: T) -> impl Iterator<Item = RtcpFb> {
-        let mut q = Vec::new();
+    /// Split RTCP packets into feedback per SSRC, appended to `q`.
+    ///
+    /// The queue is provided by the caller to reuse the allocation between packets.
+    pub fn from_rtcp<T: IntoIterator<Item = Rtcp>>(t: T, q: &mut VecDeque<RtcpFb>) {
         let iter = t.into_iter();
         for pkt in iter {
             match pkt {
                 Rtcp::SenderReport(v) => {
-                    q.push(RtcpFb::SenderInfo(v.sender_info));
+                    q.push_back(RtcpFb::SenderInfo(v.sender_info));
                     q.extend(v.reports.into_iter().map(RtcpFb::ReceptionReport));
                 }
                 Rtcp::ReceiverReport(v) => {
@@ -44,7 +49,7 @@ impl RtcpFb {
                 Rtcp::ExtendedReport(v) => {
                     for block in v.blocks {
                         match block {
-                            ReportBlock::Rrtr(b) => q.push(RtcpFb::Rrtr((b, v.ssrc))),
+                            ReportBlock::Rrtr(b) => q.push_back(RtcpFb::Rrtr((b, v.ssrc))),
                             ReportBlock::Dlrr(v) => {
                                 q.extend(v.items.iter().map(|i| RtcpFb::DlrrItem(*i)))
                             }
@@ -55,26 +60,34 @@ impl RtcpFb {
                     q.extend(v.reports.into_iter().map(RtcpFb::SourceDescription));
                 }
                 Rtcp::Goodbye(v) => {
-                    q.extend(v.reports.into_iter().map(RtcpFb::Goodbye));
+                    let reason = v.reason;
+                    q.extend(
+                        v.reports
+                            .into_iter()
+                            .map(|s| RtcpFb::Goodbye(s, reason.clone())),
+                    );
                 }
                 Rtcp::Nack(v) => {
-                    q.push(RtcpFb::Nack(v.ssrc, v.reports));
+                    q.push_back(RtcpFb::Nack(v.ssrc, v.reports));
                 }
                 Rtcp::Pli(v) => {
-                    q.push(RtcpFb::Pli(v.ssrc));
+                    q.push_back(RtcpFb::Pli(v.ssrc));
                 }
                 Rtcp::Fir(v) => {
-                    q.extend(v.reports.into_iter().map(RtcpFb::Fir));
+                    let sender_ssrc = v.sender_ssrc;
+                    q.extend(v.reports.into_iter().map(|e| RtcpFb::Fir((e, sender_ssrc))));
                 }
                 Rtcp::Twcc(v) => {
-                    q.push(RtcpFb::Twcc(v));
+                    q.push_back(RtcpFb::Twcc(v));
                 }
                 Rtcp::Remb(v) => {
-                    q.push(RtcpFb::Remb(v));
+                    q.push_back(RtcpFb::Remb(v));
+                }
+                Rtcp::Ccfb(v) => {
+                    q.push_back(RtcpFb::Ccfb(v));
                 }
             }
         }
-        q.into_iter()
     }
 
     pub fn ssrc(&self) -> Ssrc {
@@ -84,12 +97,13 @@ impl RtcpFb {
             RtcpFb::DlrrItem(v) => v.ssrc,
             RtcpFb::Rrtr((_, ssrc)) => *ssrc,
             RtcpFb::SourceDescription(v) => v.ssrc,
-            RtcpFb::Goodbye(v) => *v,
+            RtcpFb::Goodbye(v, _) => *v,
             RtcpFb::Nack(v, _) => *v,
             RtcpFb::Pli(v) => *v,
-            RtcpFb::Fir(v) => v.ssrc,
+            RtcpFb::Fir((v, _)) => v.ssrc,
             RtcpFb::Twcc(v) => v.ssrc,
             RtcpFb::Remb(v) => v.ssrcs.first().map(|ssrc| (*ssrc).into()).unwrap_or(v.ssrc),
+            RtcpFb::Ccfb(v) => v.reports.first().map(|r| r.ssrc).unwrap_or(v.sender_ssrc),
         }
     }
 }
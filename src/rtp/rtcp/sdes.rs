@@ -274,4 +274,29 @@ mod test {
 
         assert_eq!(s1, s2);
     }
+
+    #[test]
+    fn long_value_serialize_deserialize() {
+        let long = "x".repeat(100);
+
+        let mut s1 = Sdes {
+            ssrc: 1.into(),
+            values: ReportList::new(),
+        };
+        s1.values.push((
+            SdesType::CNAME,
+            "{b5d5c5a8-0f0b-4c4e-9c1c-2c3ad2f64b4f}".into(),
+        ));
+        s1.values.push((SdesType::NOTE, long.as_str().into()));
+
+        let mut buf = vec![0; 200];
+        let n = s1.write_to(&mut buf);
+        buf.truncate(n);
+
+        let s2: Sdes = buf.as_slice().try_into().unwrap();
+
+        assert_eq!(s1, s2);
+        assert_eq!(&*s2.values[0].1, "{b5d5c5a8-0f0b-4c4e-9c1c-2c3ad2f64b4f}");
+        assert_eq!(&*s2.values[1].1, long);
+    }
 }
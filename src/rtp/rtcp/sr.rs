@@ -1,6 +1,7 @@
-use std::time::Instant;
+use std::time::Duration;
 
 use crate::rtp_::MediaTime;
+use crate::util::Instant;
 use crate::util::InstantExt;
 
 use super::{FeedbackMessageType, RtcpType, Ssrc};
@@ -64,6 +65,27 @@ impl RtcpPacket for SenderReport {
     }
 }
 impl SenderInfo {
+    /// Map an RTP time from the same stream to the sender's wallclock using
+    /// the NTP/RTP correspondence in this report.
+    ///
+    /// The RTP times are compared as wrapping 32 bit values, so the mapping
+    /// holds across RTP timestamp roll-over. The frequency of `rtp_time` must
+    /// be the clock rate of the stream.
+    pub fn wallclock_for(&self, rtp_time: MediaTime) -> Instant {
+        // The SR rtp_time is in the stream clock rate, but might not carry
+        // that frequency if the report was parsed before the stream was known.
+        let diff = rtp_time
+            .as_rtp_u32()
+            .wrapping_sub(self.rtp_time.as_rtp_u32()) as i32 as i64;
+        let freq = rtp_time.frequency();
+        let offset = Duration::from(MediaTime::new(diff.unsigned_abs(), freq));
+        if diff >= 0 {
+            self.ntp_time + offset
+        } else {
+            self.ntp_time.checked_sub(offset).unwrap_or(self.ntp_time)
+        }
+    }
+
     fn write_to(&self, buf: &mut [u8]) {
         // pub ssrc: Ssrc,
         // pub ntp_time: MediaTime,
@@ -1,11 +1,13 @@
 use std::collections::vec_deque;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::ops::RangeInclusive;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use super::{extend_u16, FeedbackMessageType, RtcpHeader, RtcpPacket};
-use super::{RtcpType, SeqNo, Ssrc, TransportType};
+use super::{Ccfb, RtcpType, SeqNo, Ssrc, TransportType};
+
+use crate::util::Instant;
 
 /// Transport Wide Congestion Control.
 ///
@@ -468,20 +470,27 @@ impl TwccRecvRegister {
     ///
     /// To get periodic stats call this method at fixed intervals.
     pub fn loss(&mut self) -> Option<f32> {
+        let loss = self.peek_loss();
+
+        if let Some(expected) = self.receive_window.expected() {
+            self.receive_window.expected_prior = expected;
+        }
+        self.receive_window.received_prior = self.receive_window.received;
+
+        loss
+    }
+
+    /// Calculate the fraction of lost packets since the last call to [`Self::loss()`],
+    /// without starting a new interval.
+    pub fn peek_loss(&self) -> Option<f32> {
         // Based on the algorithm described in
         // [RFC 3550 Appendix A](https://www.rfc-editor.org/rfc/rfc3550#appendix-A.3), but instead
         // of applying it to an individual RTP stream it's applied to the whole session using TWCC
         // sequence numbers rather than RTP sequence numbers.
-        let max_seq = self.receive_window.max_seq?;
-        let base_seq = self.receive_window.base_seq?;
-        let expected = *max_seq - *base_seq + 1_u64;
+        let expected = self.receive_window.expected()?;
 
         let expected_interval = expected - self.receive_window.expected_prior;
-        self.receive_window.expected_prior = expected;
-
         let received_interval = self.receive_window.received - self.receive_window.received_prior;
-        self.receive_window.received_prior = self.receive_window.received;
-
         let lost_interval = expected_interval.saturating_sub(received_interval);
 
         (expected_interval != 0).then_some(lost_interval as f32 / expected_interval as f32)
@@ -576,6 +585,11 @@ impl ReceiveWindow {
         self.received += 1;
         self.max_seq = self.max_seq.max(Some(seq));
     }
+
+    /// The total number of packets expected.
+    fn expected(&self) -> Option<u64> {
+        Some(*self.max_seq? - *self.base_seq? + 1)
+    }
 }
 
 impl ChunkInterim {
@@ -998,6 +1012,13 @@ pub struct TwccSendRegister {
 
     /// Last registered Twcc number.
     last_registered: SeqNo,
+
+    /// 0 offset for remote time in Ccfb structs, with the report timestamp it corresponds to.
+    ccfb_time_zero: Option<(Instant, u32)>,
+
+    /// Twcc sequence number of the last record sent per (SSRC, RTP sequence number),
+    /// to look up the records of Ccfb reports.
+    rtp_index: HashMap<(Ssrc, u16), SeqNo>,
 }
 
 impl<'a> IntoIterator for &'a TwccSendRegister {
@@ -1021,6 +1042,12 @@ pub struct TwccSendRecord {
     /// Size in bytes of the payload sent.
     size: u16,
 
+    /// Whether the packet was padding (or probe), not media.
+    is_padding: bool,
+
+    /// SSRC and RTP sequence number of the packet, which is what Ccfb reports on.
+    rtp: (Ssrc, u16),
+
     recv_report: Option<TwccRecvReport>,
 }
 
@@ -1039,6 +1066,11 @@ impl TwccSendRecord {
         self.size as usize
     }
 
+    /// Whether the packet was padding (or probe), not media.
+    pub fn is_padding(&self) -> bool {
+        self.is_padding
+    }
+
     /// The time indicated by the remote side for when they received the packet.
     pub fn remote_recv_time(&self) -> Option<Instant> {
         self.recv_report.as_ref().and_then(|r| r.remote_recv_time)
@@ -1067,10 +1099,19 @@ impl TwccSendRegister {
             queue: VecDeque::new(),
             time_zero: None,
             last_registered: 0.into(),
+            ccfb_time_zero: None,
+            rtp_index: HashMap::new(),
         }
     }
 
-    pub fn register_seq(&mut self, seq: SeqNo, now: Instant, size: usize) {
+    pub fn register_seq(
+        &mut self,
+        seq: SeqNo,
+        now: Instant,
+        size: usize,
+        is_padding: bool,
+        rtp: (Ssrc, u16),
+    ) {
         self.last_registered = seq;
         self.queue.push_back(TwccSendRecord {
             seq,
@@ -1078,11 +1119,20 @@ impl TwccSendRegister {
             // In practice the max sizes is constrained by the MTU and will max out around 1200
             // bytes, hence this cast is fine.
             size: size as u16,
+            is_padding,
+            rtp,
             // The recv report, derived from TWCC feedback later.
             recv_report: None,
         });
+        self.rtp_index.insert(rtp, seq);
         while self.queue.len() > self.keep {
-            self.queue.pop_front();
+            let Some(evicted) = self.queue.pop_front() else {
+                break;
+            };
+            // Unless a later record reused the same RTP sequence number.
+            if self.rtp_index.get(&evicted.rtp) == Some(&evicted.seq) {
+                self.rtp_index.remove(&evicted.rtp);
+            }
         }
     }
 
@@ -1159,6 +1209,71 @@ impl TwccSendRegister {
         Some(first_seq_no..=last_seq_no)
     }
 
+    /// Apply a Ccfb RTCP report.
+    ///
+    /// Ccfb reports per SSRC and RTP sequence number, which are matched against the
+    /// send records. Returns the Twcc sequence numbers of the applied packets, in order.
+    /// Packets already reported by an earlier, overlapping, report are not applied again,
+    /// unless they were reported lost and now arrived.
+    pub fn apply_ccfb(&mut self, ccfb: &Ccfb, now: Instant) -> Vec<SeqNo> {
+        let (zero, zero_timestamp) = *self
+            .ccfb_time_zero
+            .get_or_insert((now, ccfb.report_timestamp));
+
+        // The report timestamp is the middle 32 bits of NTP time, in 1/65536 seconds.
+        let since_zero = ccfb.report_timestamp.wrapping_sub(zero_timestamp) as i32;
+        let offset = Duration::from_micros(since_zero.unsigned_abs() as u64 * 1_000_000 / 65536);
+        let report_time = if since_zero >= 0 {
+            zero.checked_add(offset)
+        } else {
+            zero.checked_sub(offset)
+        };
+        let Some(report_time) = report_time else {
+            return vec![];
+        };
+
+        let mut applied = vec![];
+
+        for report in &ccfb.reports {
+            for (i, metric) in report.metrics.iter().enumerate() {
+                let rtp = (report.ssrc, report.begin_seq.wrapping_add(i as u16));
+
+                let Some(seq) = self.rtp_index.get(&rtp) else {
+                    continue;
+                };
+                let Ok(index) = self.queue.binary_search_by_key(seq, |r| r.seq) else {
+                    continue;
+                };
+                let record = &mut self.queue[index];
+
+                // Reports can overlap. A packet is applied again only if it was
+                // reported lost before, and now arrived late.
+                let reported_received = record
+                    .recv_report
+                    .as_ref()
+                    .map(|r| r.remote_recv_time.is_some());
+                if reported_received == Some(true)
+                    || (reported_received == Some(false) && !metric.received)
+                {
+                    continue;
+                }
+
+                record.recv_report = Some(TwccRecvReport {
+                    local_recv_time: now,
+                    remote_recv_time: metric
+                        .arrival_offset()
+                        .and_then(|o| report_time.checked_sub(o)),
+                });
+
+                applied.push(record.seq);
+            }
+        }
+
+        applied.sort();
+
+        applied
+    }
+
     pub fn send_record(&self, seq: SeqNo) -> Option<&TwccSendRecord> {
         let index = self.queue.binary_search_by_key(&seq, |r| r.seq).ok()?;
 
@@ -1167,6 +1282,8 @@ impl TwccSendRegister {
 
     /// Calculate the egress loss for given time window.
     ///
+    /// Only media packets are considered, padding is excluded.
+    ///
     /// **Note:** The register only keeps a limited number of records and using `duration` values
     /// larger than ~1-2 seconds is liable to be inaccurate since some packets sent might have already
     /// been evicted from the register.
@@ -1182,6 +1299,7 @@ impl TwccSendRegister {
             // themselves are lost. In this case considering packets that haven't been reported as
             // lost will incorrectly conclude that there is in fact egress loss.
             .filter(|s| s.recv_report.is_some())
+            .filter(|s| !s.is_padding)
             .take_while(|s| s.local_send_time >= lower_bound);
 
         let (total, lost) = packets.fold((0, 0), |(total, lost), s| {
@@ -1770,7 +1888,7 @@ mod test {
         let mut now = Instant::now();
 
         for i in 0..50 {
-            reg.register_seq(i.into(), now, 0);
+            reg.register_seq(i.into(), now, 0, false, (1.into(), i as u16));
             now = now + Duration::from_micros(15);
         }
 
@@ -1923,7 +2041,7 @@ mod test {
         let mut reg = TwccSendRegister::new(25);
         let mut now = Instant::now();
         for i in 0..25 {
-            reg.register_seq(i.into(), now, 0);
+            reg.register_seq(i.into(), now, 0, false, (1.into(), i as u16));
             now = now + Duration::from_micros(15);
         }
 
@@ -1969,7 +2087,7 @@ mod test {
         let mut reg = TwccSendRegister::new(25);
         let mut now = Instant::now();
         for i in 0..9 {
-            reg.register_seq(i.into(), now, 0);
+            reg.register_seq(i.into(), now, 0, false, (1.into(), i as u16));
             now = now + Duration::from_millis(15);
         }
 
@@ -2015,6 +2133,44 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_twcc_send_register_loss_excludes_padding() {
+        let mut reg = TwccSendRegister::new(25);
+        let mut now = Instant::now();
+        for i in 0..9 {
+            // The lost packets 3 and 5 are padding.
+            reg.register_seq(i.into(), now, 0, i == 3 || i == 5, (1.into(), i as u16));
+            now = now + Duration::from_millis(15);
+        }
+
+        now = now + Duration::from_millis(5);
+        reg.apply_report(
+            Twcc {
+                sender_ssrc: Ssrc::new(),
+                ssrc: Ssrc::new(),
+                base_seq: 0,
+                status_count: 9,
+                reference_time: 35,
+                feedback_count: 0,
+                chunks: [
+                    PacketChunk::VectorDouble(0b11_01_01_01_00_01_00_01, 7),
+                    PacketChunk::Run(PacketStatus::ReceivedSmallDelta, 2),
+                ]
+                .into(),
+                delta: [Delta::Small(10); 7].into(),
+            },
+            now,
+        )
+        .expect("apply_report to return Some(_)");
+
+        assert!(reg.send_record(3.into()).unwrap().is_padding());
+
+        now = now + Duration::from_millis(20);
+        let loss = reg.loss(Duration::from_millis(150), now);
+
+        assert_eq!(loss, Some(0.0));
+    }
+
     #[test]
     fn test_twcc_recv_register_loss() {
         let mut reg = TwccRecvRegister::new(25);
@@ -2029,6 +2185,9 @@ mod test {
             now = now + Duration::from_millis(50);
         }
 
+        // Peeking doesn't start a new interval.
+        assert_eq!(reg.peek_loss(), Some(2.0 / 10.0));
+        assert_eq!(reg.peek_loss(), Some(2.0 / 10.0));
         assert_eq!(reg.loss(), Some(2.0 / 10.0));
 
         for i in 10..20 {
@@ -2042,4 +2201,121 @@ mod test {
 
         assert_eq!(reg.loss(), Some(4.0 / 10.0));
     }
+
+    #[test]
+    fn test_twcc_send_register_apply_ccfb() {
+        use super::super::{CcfbMetric, CcfbReport, Ecn};
+
+        let mut reg = TwccSendRegister::new(25);
+        let mut now = Instant::now();
+        let a: Ssrc = 1.into();
+        let b: Ssrc = 2.into();
+
+        // Two interleaved streams, with RTP sequence numbers from 100.
+        for i in 0..6_u64 {
+            let ssrc = if i % 2 == 0 { a } else { b };
+            reg.register_seq(i.into(), now, 100, false, (ssrc, 100 + (i / 2) as u16));
+            now = now + Duration::from_millis(10);
+        }
+
+        let received = |arrival_time_offset| CcfbMetric {
+            received: true,
+            ecn: Ecn::NotEct,
+            arrival_time_offset,
+        };
+        let lost = CcfbMetric {
+            received: false,
+            ecn: Ecn::NotEct,
+            arrival_time_offset: 0,
+        };
+
+        let ccfb = Ccfb {
+            sender_ssrc: 3.into(),
+            reports: vec![
+                CcfbReport {
+                    ssrc: a,
+                    begin_seq: 100,
+                    metrics: vec![received(60), received(40), received(20)],
+                },
+                CcfbReport {
+                    ssrc: b,
+                    begin_seq: 101,
+                    metrics: vec![lost, received(10)],
+                },
+            ],
+            report_timestamp: 65536,
+        };
+
+        let applied = reg.apply_ccfb(&ccfb, now);
+        let expected: Vec<SeqNo> = vec![0.into(), 2.into(), 3.into(), 4.into(), 5.into()];
+        assert_eq!(applied, expected);
+
+        // Not in the report.
+        assert!(reg.send_record(1.into()).unwrap().rtt().is_none());
+
+        // Reported lost.
+        let lost = reg.send_record(3.into()).unwrap();
+        assert!(lost.rtt().is_some());
+        assert!(lost.remote_recv_time().is_none());
+
+        let t0 = reg
+            .send_record(0.into())
+            .unwrap()
+            .remote_recv_time()
+            .unwrap();
+        let t4 = reg
+            .send_record(4.into())
+            .unwrap()
+            .remote_recv_time()
+            .unwrap();
+        assert_eq!(t4 - t0, Duration::from_micros(39_062));
+
+        // A later report is relative to the report timestamp of the first.
+        let ccfb = Ccfb {
+            sender_ssrc: 3.into(),
+            reports: vec![CcfbReport {
+                ssrc: b,
+                begin_seq: 101,
+                metrics: vec![received(0)],
+            }],
+            report_timestamp: 65536 + 32768,
+        };
+
+        let applied = reg.apply_ccfb(&ccfb, now);
+        assert_eq!(applied, vec![SeqNo::from(3)]);
+
+        // Overlapping reports don't apply the same packets again.
+        let ccfb = Ccfb {
+            sender_ssrc: 3.into(),
+            reports: vec![CcfbReport {
+                ssrc: a,
+                begin_seq: 100,
+                metrics: vec![
+                    received(80),
+                    CcfbMetric {
+                        received: false,
+                        ecn: Ecn::NotEct,
+                        arrival_time_offset: 0,
+                    },
+                    received(40),
+                ],
+            }],
+            report_timestamp: 65536 * 2,
+        };
+
+        let applied = reg.apply_ccfb(&ccfb, now);
+        assert!(applied.is_empty());
+
+        let t3 = reg
+            .send_record(3.into())
+            .unwrap()
+            .remote_recv_time()
+            .unwrap();
+        let t5 = reg
+            .send_record(5.into())
+            .unwrap()
+            .remote_recv_time()
+            .unwrap();
+        assert_eq!(t3 - t5, Duration::from_micros(500_000 + 9_765));
+    }
 }
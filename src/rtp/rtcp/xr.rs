@@ -1,5 +1,4 @@
-use std::time::Instant;
-
+use crate::util::Instant;
 use crate::util::InstantExt;
 
 use super::{FeedbackMessageType, RtcpType, Ssrc};
@@ -26,6 +26,7 @@ pub const SRTP_BLOCK_SIZE: usize = 16;
 const SRTCP_INDEX_LEN: usize = 4;
 const MAX_TAG_LEN: usize = aead_aes_128_gcm::TAG_LEN;
 pub const SRTCP_OVERHEAD: usize = MAX_TAG_LEN + SRTCP_INDEX_LEN;
+#[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
 pub const SRTP_OVERHEAD: usize = MAX_TAG_LEN;
 
 impl SrtpContext {
@@ -102,12 +103,28 @@ pub struct SrtpContext {
 }
 
 impl SrtpContext {
+    #[cfg(test)]
     pub fn protect_rtp(
         &mut self,
         buf: &[u8],
         header: &RtpHeader,
         srtp_index: u64, // same as ext_seq
     ) -> Vec<u8> {
+        let mut output = Vec::new();
+        self.protect_rtp_into(buf, header, srtp_index, &mut output);
+        output
+    }
+
+    /// Like [`SrtpContext::protect_rtp`], but reusing the allocation of `output`.
+    pub fn protect_rtp_into(
+        &mut self,
+        buf: &[u8],
+        header: &RtpHeader,
+        srtp_index: u64, // same as ext_seq
+        output: &mut Vec<u8>,
+    ) {
+        output.clear();
+
         // SRTP layout
         // [header, [rtp, (padding + pad_count)], tag]
 
@@ -140,7 +157,7 @@ impl SrtpContext {
 
         match &mut self.rtp {
             #[cfg(feature = "_internal_test_exports")]
-            Derived::PassThrough => input.to_vec(),
+            Derived::PassThrough => output.extend_from_slice(input),
             Derived::Aes128CmSha1_80 { key, salt, enc, .. } => {
                 assert!(
                     input.len() % SRTP_BLOCK_SIZE == 0,
@@ -150,16 +167,14 @@ impl SrtpContext {
 
                 let iv = aes_128_cm_sha1_80::rtp_iv(*salt, *header.ssrc, srtp_index);
 
-                let mut output = vec![0_u8; buf.len() + HMAC_TAG_LEN];
+                output.resize(buf.len() + HMAC_TAG_LEN, 0);
                 enc.encrypt(&iv, input, &mut output[hlen..])
                     .expect("rtp encrypt");
 
                 output[..hlen].copy_from_slice(&buf[..hlen]);
 
                 let hmac_start = buf.len();
-                aes_128_cm_sha1_80::rtp_hmac(key, &mut output, srtp_index, hmac_start);
-
-                output
+                aes_128_cm_sha1_80::rtp_hmac(key, output, srtp_index, hmac_start);
             }
             Derived::AeadAes128Gcm { salt, enc, .. } => {
                 use aead_aes_128_gcm::TAG_LEN;
@@ -169,13 +184,11 @@ impl SrtpContext {
                 let aad = &buf[..hlen];
 
                 // Input and output lengths for encryption: https://www.rfc-editor.org/rfc/rfc7714#section-5.2.1
-                let mut output = vec![0_u8; buf.len() + TAG_LEN];
+                output.resize(buf.len() + TAG_LEN, 0);
                 enc.encrypt(&iv, aad, input, &mut output[hlen..])
                     .expect("rtp encrypt");
 
                 output[..hlen].copy_from_slice(aad);
-
-                output
             }
         }
     }
@@ -249,7 +262,17 @@ impl SrtpContext {
         }
     }
 
+    #[cfg(test)]
     pub fn protect_rtcp(&mut self, buf: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        self.protect_rtcp_into(buf, &mut output);
+        output
+    }
+
+    /// Like [`SrtpContext::protect_rtcp`], but reusing the allocation of `output`.
+    pub fn protect_rtcp_into(&mut self, buf: &[u8], output: &mut Vec<u8>) {
+        output.clear();
+
         let srtcp_index = self.srtcp_index;
 
         // https://tools.ietf.org/html/rfc3711#page-15
@@ -268,13 +291,13 @@ impl SrtpContext {
 
         match &mut self.rtcp {
             #[cfg(feature = "_internal_test_exports")]
-            Derived::PassThrough => buf.to_vec(),
+            Derived::PassThrough => output.extend_from_slice(buf),
             Derived::Aes128CmSha1_80 { key, salt, enc, .. } => {
                 use aes_128_cm_sha1_80::HMAC_TAG_LEN;
 
                 let iv = aes_128_cm_sha1_80::rtp_iv(*salt, ssrc, srtcp_index as u64);
 
-                let mut output = vec![0_u8; buf.len() + SRTCP_INDEX_LEN + HMAC_TAG_LEN];
+                output.resize(buf.len() + SRTCP_INDEX_LEN + HMAC_TAG_LEN, 0);
                 output[0..8].copy_from_slice(&buf[0..8]);
                 let input = &buf[8..];
                 let encout = &mut output[8..(8 + input.len())];
@@ -285,9 +308,7 @@ impl SrtpContext {
                 to[0..4].copy_from_slice(&e_and_si.to_be_bytes());
 
                 let hmac_index = output.len() - HMAC_TAG_LEN;
-                aes_128_cm_sha1_80::rtcp_hmac(key, &mut output, hmac_index);
-
-                output
+                aes_128_cm_sha1_80::rtcp_hmac(key, output, hmac_index);
             }
             Derived::AeadAes128Gcm { salt, enc, .. } => {
                 use aead_aes_128_gcm::{RTCP_AAD_LEN, TAG_LEN};
@@ -297,7 +318,7 @@ impl SrtpContext {
                 aad[..8].copy_from_slice(&buf[..8]);
                 aad[8..12].copy_from_slice(&e_and_si.to_be_bytes());
 
-                let mut output = vec![0_u8; buf.len() + SRTCP_INDEX_LEN + TAG_LEN];
+                output.resize(buf.len() + SRTCP_INDEX_LEN + TAG_LEN, 0);
                 output[0..8].copy_from_slice(&buf[0..8]);
                 let input = &buf[8..];
 
@@ -309,8 +330,6 @@ impl SrtpContext {
 
                 let to = &mut output[enc_end..];
                 to[0..4].copy_from_slice(&e_and_si.to_be_bytes());
-
-                output
             }
         }
     }
@@ -850,6 +869,23 @@ mod test {
             );
         }
 
+        #[test]
+        fn protect_rtp_into_reuses_buffer() {
+            let mut context = make_rtp_context();
+
+            let header =
+                RtpHeader::parse(&rfc7714::PLAINTEXT_RTP_PACKET[..12], &ExtensionMap::empty())
+                    .expect("header to parse");
+
+            // A dirty buffer from a previous packet.
+            let mut out = vec![0xff; 2000];
+            let ptr = out.as_ptr();
+            context.protect_rtp_into(rfc7714::PLAINTEXT_RTP_PACKET, &header, 0, &mut out);
+
+            assert_eq!(out, rfc7714::PROTECTED_RTP_PACKET);
+            assert_eq!(out.as_ptr(), ptr);
+        }
+
         #[test]
         fn unprotect_rtp_rfc_7714_test() {
             let mut context = make_rtp_context();
@@ -2,7 +2,6 @@ use std::collections::VecDeque;
 
 use super::{ExtensionSerializer, ExtensionValues};
 
-#[allow(dead_code)]
 /// URI for the Video Layers Allocation RTP Header Extension
 pub const URI: &str = "http://www.webrtc.org/experiments/rtp-hdrext/video-layers-allocation00";
 
@@ -21,12 +20,12 @@ pub struct VideoLayersAllocation {
     pub current_simulcast_stream_index: u8,
 
     /// AKA RTP streams
-    /// Max size of this Vec: 5
+    /// Max size of this Vec: 4
     pub simulcast_streams: Vec<SimulcastStreamAllocation>,
 }
 
 /// An allocation for a simulcast stream, which may contain up to 4 allocations for spatial layers.
-/// There may be up to 5 of these per top-level allocation.
+/// There may be up to 4 of these per top-level allocation.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SimulcastStreamAllocation {
     /// May contains many spatial layers, or none.
@@ -34,20 +33,20 @@ pub struct SimulcastStreamAllocation {
     pub spatial_layers: Vec<SpatialLayerAllocation>,
 }
 
-/// An allocation for a spatial layer, which may contain up to 5 allocations for temporal layers.
+/// An allocation for a spatial layer, which may contain up to 4 allocations for temporal layers.
 /// There may be up to 4 per simulcast stream.
 /// Also contains an optional resolution and framerate.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SpatialLayerAllocation {
     /// Contains many temporal layers, or none.
     /// If empty, the spatial layer is not active.
-    /// Max size of this Vec: 5
+    /// Max size of this Vec: 4
     pub temporal_layers: Vec<TemporalLayerAllocation>,
     /// Contains an optional resolution and framerate
     pub resolution_and_framerate: Option<ResolutionAndFramerate>,
 }
 
-/// An allocation for a temporal layer.  There may be up to 5 per spatial layer.
+/// An allocation for a temporal layer.  There may be up to 4 per spatial layer.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct TemporalLayerAllocation {
     /// Cumulative bitrate for this temporal layer and all below it within a spatial layer.
@@ -67,7 +66,6 @@ pub struct ResolutionAndFramerate {
 }
 
 impl VideoLayersAllocation {
-    #[allow(dead_code)]
     fn parse(buf: &[u8]) -> Option<Self> {
         // First byte
         let (&b0, after_b0) = buf.split_first()?;
@@ -204,19 +202,174 @@ impl VideoLayersAllocation {
             simulcast_streams,
         })
     }
+
+    /// The number of bytes needed to write this allocation.
+    ///
+    /// None if the allocation can't be expressed in the header extension, such as
+    /// having more than 4 simulcast streams, spatial or temporal layers.
+    pub fn encoded_len(&self) -> Option<usize> {
+        if !self.is_writable() {
+            return None;
+        }
+
+        let active_count = self.active_spatial_layers().count();
+        if active_count == 0 {
+            // The special case of the header extension being just 0.
+            return Some(1);
+        }
+
+        let mut len = 1;
+
+        if self.shared_spatial_layer_bitmask().is_none() {
+            len += div_round_up(self.simulcast_streams.len(), 2);
+        }
+
+        len += div_round_up(active_count, 4);
+
+        len += self
+            .active_spatial_layers()
+            .flat_map(|s| s.temporal_layers.iter())
+            .map(|t| leb_u63_len(t.cumulative_kbps))
+            .sum::<usize>();
+
+        if self.has_resolutions_and_framerates() {
+            len += 5 * active_count;
+        }
+
+        Some(len)
+    }
+
+    fn write_to(&self, buf: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len()?;
+        let buf = buf.get_mut(..len)?;
+        buf.fill(0);
+
+        let active_count = self.active_spatial_layers().count();
+        if active_count == 0 {
+            return Some(1);
+        }
+
+        // First byte
+        let simulcast_stream_count = self.simulcast_streams.len();
+        let shared_spatial_layer_bitmask = self.shared_spatial_layer_bitmask();
+        buf[0] = (self.current_simulcast_stream_index << 6)
+            | ((simulcast_stream_count as u8 - 1) << 4)
+            | shared_spatial_layer_bitmask.unwrap_or(0);
+        let mut pos = 1;
+
+        // Spatial layer bitmasks, 4 bits per simulcast stream, unless shared.
+        if shared_spatial_layer_bitmask.is_none() {
+            for (index, stream) in self.simulcast_streams.iter().enumerate() {
+                let shift = if index % 2 == 0 { 4 } else { 0 };
+                buf[pos + index / 2] |= spatial_layer_bitmask(stream) << shift;
+            }
+            pos += div_round_up(simulcast_stream_count, 2);
+        }
+
+        // Temporal layer counts, 2 bits per active spatial layer.
+        for (index, spatial_layer) in self.active_spatial_layers().enumerate() {
+            let count_minus_1 = spatial_layer.temporal_layers.len() as u8 - 1;
+            buf[pos + index / 4] |= count_minus_1 << (6 - 2 * (index % 4));
+        }
+        pos += div_round_up(active_count, 4);
+
+        // Temporal layer bitrates
+        for temporal_layer in self
+            .active_spatial_layers()
+            .flat_map(|s| s.temporal_layers.iter())
+        {
+            pos += write_leb_u63(&mut buf[pos..], temporal_layer.cumulative_kbps);
+        }
+
+        // (Optional) resolutions and framerates
+        if self.has_resolutions_and_framerates() {
+            for spatial_layer in self.active_spatial_layers() {
+                let r = spatial_layer
+                    .resolution_and_framerate
+                    .as_ref()
+                    .expect("resolution and framerate for all active layers");
+                buf[pos..pos + 2].copy_from_slice(&(r.width - 1).to_be_bytes());
+                buf[pos + 2..pos + 4].copy_from_slice(&(r.height - 1).to_be_bytes());
+                buf[pos + 4] = r.framerate;
+                pos += 5;
+            }
+        }
+
+        debug_assert_eq!(pos, len);
+
+        Some(len)
+    }
+
+    fn is_writable(&self) -> bool {
+        self.current_simulcast_stream_index <= 3
+            && self.simulcast_streams.len() <= 4
+            && self
+                .simulcast_streams
+                .iter()
+                .all(|s| s.spatial_layers.len() <= 4)
+            && self
+                .active_spatial_layers()
+                .all(|s| s.temporal_layers.len() <= 4)
+            && self
+                .active_spatial_layers()
+                .flat_map(|s| s.temporal_layers.iter())
+                .all(|t| t.cumulative_kbps < (1u64 << 63))
+    }
+
+    fn active_spatial_layers(&self) -> impl Iterator<Item = &SpatialLayerAllocation> {
+        self.simulcast_streams
+            .iter()
+            .flat_map(|s| s.spatial_layers.iter())
+            .filter(|s| !s.temporal_layers.is_empty())
+    }
+
+    fn shared_spatial_layer_bitmask(&self) -> Option<u8> {
+        let first = spatial_layer_bitmask(self.simulcast_streams.first()?);
+        self.simulcast_streams
+            .iter()
+            .all(|s| spatial_layer_bitmask(s) == first)
+            .then_some(first)
+    }
+
+    // Resolutions are either written for all active spatial layers, or none.
+    fn has_resolutions_and_framerates(&self) -> bool {
+        self.active_spatial_layers().all(|s| {
+            s.resolution_and_framerate
+                .as_ref()
+                .map(|r| r.width > 0 && r.height > 0)
+                .unwrap_or(false)
+        })
+    }
+}
+
+// One bit per active spatial layer, with spatial layer 0 in the lowest bit.
+fn spatial_layer_bitmask(stream: &SimulcastStreamAllocation) -> u8 {
+    stream
+        .spatial_layers
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| !s.temporal_layers.is_empty())
+        .fold(0, |bitmask, (index, _)| bitmask | (1 << index))
 }
 
 /// Serializer of the Video Layers Allocation Header Extension
+///
+/// Register with `Extension::with_serializer(vla::URI, vla::Serializer)`. Senders set a
+/// [`VideoLayersAllocation`] in [`ExtensionValues::user_values`] to advertise the active
+/// simulcast/SVC layers and their target bitrates.
 #[derive(Debug)]
 pub struct Serializer;
 
 impl ExtensionSerializer for Serializer {
-    fn write_to(&self, _buf: &mut [u8], ev: &ExtensionValues) -> usize {
-        if ev.user_values.get::<VideoLayersAllocation>().is_some() {
-            // Writing the VLA header extension is currently not supported.
-            todo!();
-        }
-        0
+    fn write_to(&self, buf: &mut [u8], ev: &ExtensionValues) -> usize {
+        let Some(vla) = ev.user_values.get::<VideoLayersAllocation>() else {
+            return 0;
+        };
+        let Some(n) = vla.write_to(buf) else {
+            warn!("Skip writing video layers allocation that can't be serialized");
+            return 0;
+        };
+        n
     }
 
     fn parse_value(&self, buf: &[u8], ev: &mut ExtensionValues) -> bool {
@@ -235,9 +388,12 @@ impl ExtensionSerializer for Serializer {
         false
     }
 
-    fn requires_two_byte_form(&self, _ev: &ExtensionValues) -> bool {
-        // Writing isn't implemented yet
-        false
+    fn requires_two_byte_form(&self, ev: &ExtensionValues) -> bool {
+        ev.user_values
+            .get::<VideoLayersAllocation>()
+            .and_then(|vla| vla.encoded_len())
+            .map(|n| n > 16)
+            .unwrap_or(false)
     }
 }
 
@@ -261,6 +417,27 @@ fn parse_leb_u63(bytes: &[u8]) -> (u64, &[u8]) {
     (0, bytes)
 }
 
+fn leb_u63_len(value: u64) -> usize {
+    let bits = 64 - value.leading_zeros() as usize;
+    div_round_up(bits.max(1), 7)
+}
+
+// Writes at most 9 bytes (63 bits) unsigned, the inverse of parse_leb_u63.
+// returns number of bytes written
+fn write_leb_u63(buf: &mut [u8], mut value: u64) -> usize {
+    let mut index = 0;
+    loop {
+        let chunk = (value & 0b0111_1111) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[index] = chunk;
+            return index + 1;
+        }
+        buf[index] = chunk | 0b1000_0000;
+        index += 1;
+    }
+}
+
 // If successful, the size of the left will be mid,
 // and the size of the right while be buf.len()-mid.
 #[allow(dead_code)]
@@ -777,4 +954,134 @@ mod test {
             })
         );
     }
+
+    fn write(vla: &VideoLayersAllocation) -> Vec<u8> {
+        let mut buf = vec![0; 255];
+        let n = vla.write_to(&mut buf).unwrap();
+        assert_eq!(Some(n), vla.encoded_len());
+        buf.truncate(n);
+        buf
+    }
+
+    #[test]
+    fn test_write_vla_roundtrip() {
+        let cases: &[&[u8]] = &[
+            // Everything inactive
+            &[0b0000_0000],
+            // 3 simulcast streams, shared bitmask, with resolutions
+            &[
+                0b0110_0001,
+                0b0101_0100,
+                100,
+                101,
+                110,
+                111,
+                120,
+                121,
+                1,
+                63,
+                0,
+                179,
+                15,
+                2,
+                127,
+                1,
+                103,
+                30,
+                4,
+                255,
+                2,
+                207,
+                60,
+            ],
+            // 1 simulcast stream with 4 spatial layers, 1 inactive
+            &[0b0000_1011, 0b0101_0100, 100, 101, 110, 111, 120, 121],
+            // Differing bitmasks per simulcast stream
+            &[
+                0b0010_0000,
+                0b0001_0000,
+                0b0000_0000,
+                0b0100_0000,
+                100,
+                101,
+                1,
+                63,
+                0,
+                179,
+                15,
+            ],
+            // Multi byte LEB128 bitrates
+            &[
+                0b0000_0001,
+                0b0100_0000,
+                0b1000_0000,
+                0b0000_0001,
+                0b1111_1111,
+                0b0111_1111,
+            ],
+        ];
+
+        for bytes in cases {
+            let vla = VideoLayersAllocation::parse(bytes).unwrap();
+            assert_eq!(&write(&vla), bytes);
+        }
+    }
+
+    #[test]
+    fn test_write_vla_not_writable() {
+        let temporal_layers = (1..=5)
+            .map(|cumulative_kbps| TemporalLayerAllocation { cumulative_kbps })
+            .collect();
+
+        let vla = VideoLayersAllocation {
+            current_simulcast_stream_index: 0,
+            simulcast_streams: vec![SimulcastStreamAllocation {
+                spatial_layers: vec![SpatialLayerAllocation {
+                    temporal_layers,
+                    resolution_and_framerate: None,
+                }],
+            }],
+        };
+
+        assert_eq!(vla.encoded_len(), None);
+        assert_eq!(vla.write_to(&mut [0; 255]), None);
+    }
+
+    #[test]
+    fn test_write_vla_requires_two_byte_form() {
+        let mut ev = ExtensionValues::default();
+        assert!(!Serializer.requires_two_byte_form(&ev));
+
+        let small = VideoLayersAllocation::parse(&[0b0000_1011, 0b0101_0100, 1, 2, 3, 4, 5, 6]);
+        ev.user_values.set(small.unwrap());
+        assert!(!Serializer.requires_two_byte_form(&ev));
+
+        let large = VideoLayersAllocation::parse(&[
+            0b0110_0001,
+            0b0101_0100,
+            100,
+            101,
+            110,
+            111,
+            120,
+            121,
+            1,
+            63,
+            0,
+            179,
+            15,
+            2,
+            127,
+            1,
+            103,
+            30,
+            4,
+            255,
+            2,
+            207,
+            60,
+        ]);
+        ev.user_values.set(large.unwrap());
+        assert!(Serializer.requires_two_byte_form(&ev));
+    }
 }
@@ -5,7 +5,6 @@ use std::fmt;
 use std::net::SocketAddr;
 use std::panic::UnwindSafe;
 use std::sync::Arc;
-use std::time::Instant;
 
 use sctp_proto::{Association, AssociationHandle, ClientConfig, DatagramEvent};
 use sctp_proto::{Endpoint, EndpointConfig, Stream, StreamEvent, Transmit};
@@ -15,6 +14,11 @@ use thiserror::Error;
 pub use sctp_proto::Error as ProtoError;
 use sctp_proto::ReliabilityType;
 
+use crate::channel::ChannelId;
+use crate::stats::ChannelStats;
+use crate::util::already_happened;
+use crate::util::Instant;
+
 mod dcep;
 use dcep::DcepOpen;
 
@@ -31,6 +35,14 @@ pub enum SctpError {
     #[error("Write on a stream before it was established")]
     WriteBeforeEstablished,
 
+    /// The message is larger than the max-message-size of the remote peer.
+    #[error("Message size {0} exceeds max-message-size {1}")]
+    MessageTooLarge(usize, usize),
+
+    /// The out-of-band negotiated stream id is reserved (RFC 8831).
+    #[error("Stream id {0} is reserved")]
+    ReservedStreamId(u16),
+
     /// The initial DCEP is not valid.
     #[error("DCEP open message too small")]
     DcepOpenTooSmall,
@@ -52,8 +64,22 @@ pub(crate) struct RtcSctp {
     pushed_back_transmit: Option<VecDeque<Vec<u8>>>,
     last_now: Instant,
     client: bool,
+    max_message_size: usize,
+    /// Buffers handed back by the user to be reused for incoming data.
+    recycled: Vec<Vec<u8>>,
 }
 
+/// Max number of recycled buffers to keep around.
+const MAX_RECYCLED: usize = 32;
+
+/// Max message size when the remote peer doesn't signal a=max-message-size.
+///
+/// RFC 8841 section 6.1
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 65536;
+
+/// Room left in the datagram for the DTLS record wrapping the SCTP packet.
+const SCTP_DTLS_OVERHEAD: usize = 30;
+
 /// This is okay because there is no way for a user of Rtc to interact with the Sctp subsystem
 /// in a way that would allow them to observe a potentially broken invariant when catching a panic.
 impl UnwindSafe for RtcSctp {}
@@ -64,6 +90,10 @@ enum RtcSctpState {
     AwaitRemoteAssociation,
     AwaitAssociationEstablished,
     Established,
+    /// The association was lost, typically due to too many retransmits without an
+    /// acknowledgement or an ABORT from the remote peer. Also the state after an
+    /// orderly SHUTDOWN completed.
+    Lost,
 }
 
 impl RtcSctpState {
@@ -86,6 +116,11 @@ struct StreamEntry {
     id: u16,
     /// If we are to close this entry.
     do_close: bool,
+    /// Counters for stats.
+    messages_sent: u64,
+    bytes_sent: u64,
+    messages_received: u64,
+    bytes_received: u64,
 }
 
 pub(crate) enum SctpEvent {
@@ -104,6 +139,9 @@ pub(crate) enum SctpEvent {
         binary: bool,
         data: Vec<u8>,
     },
+    BufferedAmountLow {
+        id: u16,
+    },
 }
 
 /// These are the possible paths:
@@ -197,15 +235,18 @@ impl StreamEntry {
     }
 }
 
+fn create_endpoint(mtu: usize) -> Endpoint {
+    let mut config = EndpointConfig::default();
+    // SCTP is wrapped in DTLS, which adds its own overhead. With a 1200 payload
+    // I've seen DTLS datagrams 77 over, so leave some room.
+    config.max_payload_size((mtu - SCTP_DTLS_OVERHEAD) as u32);
+    let server_config = ServerConfig::default();
+    Endpoint::new(Arc::new(config), Some(Arc::new(server_config)))
+}
+
 impl RtcSctp {
-    pub fn new() -> Self {
-        let mut config = EndpointConfig::default();
-        // Default here is 1200, I've seen warnings that are 77 over.
-        // DTLS above MTU 1200: 1277
-        // Let's try 1120, see if we can avoid warnings.
-        config.max_payload_size(1120);
-        let server_config = ServerConfig::default();
-        let endpoint = Endpoint::new(Arc::new(config), Some(Arc::new(server_config)));
+    pub fn new(mtu: usize) -> Self {
+        let endpoint = create_endpoint(mtu);
         let fake_addr = "1.1.1.1:5000".parse().unwrap();
 
         RtcSctp {
@@ -216,9 +257,20 @@ impl RtcSctp {
             assoc: None,
             entries: vec![],
             pushed_back_transmit: None,
-            last_now: Instant::now(), // placeholder until init()
+            last_now: already_happened(), // placeholder until init()
             client: false,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            recycled: vec![],
+        }
+    }
+
+    /// Change the MTU. Only has an effect before the association is set up.
+    pub fn set_mtu(&mut self, mtu: usize) {
+        if self.state != RtcSctpState::Uninited {
+            debug!("Ignore SCTP MTU change after init");
+            return;
         }
+        self.endpoint = create_endpoint(mtu);
     }
 
     pub fn is_inited(&self) -> bool {
@@ -249,6 +301,18 @@ impl RtcSctp {
         self.client
     }
 
+    /// Set the max message size as signalled by the remote peer.
+    ///
+    /// `None` means the remote didn't signal anything, and `Some(0)` means
+    /// the remote can handle messages of any size.
+    pub fn set_max_message_size(&mut self, max: Option<usize>) {
+        self.max_message_size = match max {
+            None => DEFAULT_MAX_MESSAGE_SIZE,
+            Some(0) => usize::MAX,
+            Some(v) => v,
+        };
+    }
+
     /// Opens a new stream.
     pub fn open_stream(&mut self, id: u16, config: ChannelConfig) {
         // The channel might already have arrived via SCTP, and if it is negotiated out-of-band
@@ -301,6 +365,26 @@ impl RtcSctp {
         }
     }
 
+    /// Start an orderly shutdown of the association (SCTP SHUTDOWN).
+    ///
+    /// The SHUTDOWN chunk is sent once outstanding data is acknowledged.
+    pub fn shutdown(&mut self) {
+        if self.state != RtcSctpState::Established {
+            return;
+        }
+        let Some(assoc) = self.assoc.as_mut() else {
+            return;
+        };
+        if let Err(e) = assoc.shutdown() {
+            debug!("SCTP shutdown failed: {:?}", e);
+        }
+    }
+
+    /// Whether the association is up, which is also the case while shutting down.
+    pub fn is_established(&self) -> bool {
+        self.state == RtcSctpState::Established
+    }
+
     /// Close stream.
     pub fn close_stream(&mut self, id: u16) {
         if let Some(entry) = self.entries.iter_mut().find(|v| v.id == id) {
@@ -320,11 +404,23 @@ impl RtcSctp {
         rec.state == StreamEntryState::Open
     }
 
+    /// The config of an open stream, either provided locally or received via DCEP.
+    pub fn config(&self, id: u16) -> Option<&ChannelConfig> {
+        self.entries
+            .iter()
+            .find(|e| e.id == id)
+            .and_then(|e| e.config.as_ref())
+    }
+
     pub fn write(&mut self, id: u16, binary: bool, buf: &[u8]) -> Result<usize, SctpError> {
         if self.state != RtcSctpState::Established {
             return Err(SctpError::WriteBeforeEstablished);
         }
 
+        if buf.len() > self.max_message_size {
+            return Err(SctpError::MessageTooLarge(buf.len(), self.max_message_size));
+        }
+
         let assoc = self
             .assoc
             .as_mut()
@@ -332,7 +428,7 @@ impl RtcSctp {
 
         let rec = self
             .entries
-            .iter()
+            .iter_mut()
             .find(|e| e.id == id)
             .expect("stream entry for write");
 
@@ -354,7 +450,63 @@ impl RtcSctp {
             PayloadProtocolIdentifier::String
         };
 
-        Ok(stream.write_with_ppi(buf, ppi)?)
+        let n = stream.write_with_ppi(buf, ppi)?;
+
+        rec.messages_sent += 1;
+        rec.bytes_sent += n as u64;
+
+        Ok(n)
+    }
+
+    pub fn channel_stats(&self, sctp_stream_id: u16, id: ChannelId) -> Option<ChannelStats> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.id == sctp_stream_id && e.state == StreamEntryState::Open)?;
+        let config = entry.config.as_ref()?;
+
+        Some(ChannelStats {
+            id,
+            label: config.label.clone(),
+            protocol: config.protocol.clone(),
+            messages_sent: entry.messages_sent,
+            bytes_sent: entry.bytes_sent,
+            messages_received: entry.messages_received,
+            bytes_received: entry.bytes_received,
+        })
+    }
+
+    /// Hand back a buffer to be reused for incoming data.
+    pub fn recycle(&mut self, buf: Vec<u8>) {
+        if self.recycled.len() < MAX_RECYCLED && buf.capacity() > 0 {
+            self.recycled.push(buf);
+        }
+    }
+
+    /// Number of bytes written to the stream that are not yet sent.
+    pub fn buffered_amount(&mut self, id: u16) -> Result<usize, SctpError> {
+        let assoc = self
+            .assoc
+            .as_mut()
+            .ok_or(SctpError::WriteBeforeEstablished)?;
+
+        Ok(assoc.stream(id)?.buffered_amount()?)
+    }
+
+    /// Set the threshold for when to emit [`SctpEvent::BufferedAmountLow`].
+    pub fn set_buffered_amount_low_threshold(
+        &mut self,
+        id: u16,
+        threshold: usize,
+    ) -> Result<(), SctpError> {
+        let assoc = self
+            .assoc
+            .as_mut()
+            .ok_or(SctpError::WriteBeforeEstablished)?;
+
+        Ok(assoc
+            .stream(id)?
+            .set_buffered_amount_low_threshold(threshold)?)
     }
 
     pub fn handle_input(&mut self, now: Instant, data: &[u8]) {
@@ -377,10 +529,12 @@ impl RtcSctp {
                 set_state(&mut self.state, RtcSctpState::AwaitAssociationEstablished);
             }
             DatagramEvent::AssociationEvent(event) => {
-                self.assoc
-                    .as_mut()
-                    .expect("association for event")
-                    .handle_event(event);
+                let Some(assoc) = &mut self.assoc else {
+                    // This happens for late packets after the association is lost.
+                    debug!("Drop association event without association");
+                    return;
+                };
+                assoc.handle_event(event);
             }
         }
     }
@@ -440,6 +594,16 @@ impl RtcSctp {
             return Some(SctpEvent::Transmit { packets: buf });
         }
 
+        if self.state == RtcSctpState::Lost {
+            // Report all remaining streams as closed.
+            let entry = self
+                .entries
+                .iter_mut()
+                .find(|e| e.state != StreamEntryState::Closed)?;
+            entry.set_state(StreamEntryState::Closed);
+            return Some(SctpEvent::Close { id: entry.id });
+        }
+
         // Don't progress to move data between association and endpoint until we have an
         // association we want to drive forward.
         if !self.state.propagate_endpoint_to_assoc() {
@@ -448,13 +612,26 @@ impl RtcSctp {
 
         let assoc = self.assoc.as_mut()?;
 
+        if assoc.is_closed() {
+            // SHUTDOWN, SHUTDOWN ACK and SHUTDOWN COMPLETE are exchanged.
+            info!("SCTP association shut down");
+            set_state(&mut self.state, RtcSctpState::Lost);
+            self.assoc = None;
+            return self.poll();
+        }
+
         while let Some(e) = assoc.poll() {
             if let Event::Connected = e {
                 set_state(&mut self.state, RtcSctpState::Established);
                 return self.poll();
             }
 
-            // TODO: Do we need to handle AssociationLost?
+            if let Event::AssociationLost { reason } = e {
+                warn!("SCTP association lost: {:?}", reason);
+                set_state(&mut self.state, RtcSctpState::Lost);
+                self.assoc = None;
+                return self.poll();
+            }
 
             if let Event::Stream(se) = e {
                 match se {
@@ -476,6 +653,15 @@ impl RtcSctp {
                         info!("Stream {} closed", id);
                         entry.do_close = true;
                     }
+                    StreamEvent::BufferedAmountLow { id } => {
+                        let open = self
+                            .entries
+                            .iter()
+                            .any(|e| e.id == id && e.state == StreamEntryState::Open);
+                        if open {
+                            return Some(SctpEvent::BufferedAmountLow { id });
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -558,6 +744,12 @@ impl RtcSctp {
             }
 
             if entry.do_close && entry.state != StreamEntryState::Closed {
+                // Reset the outgoing stream (RFC 6525) to signal the closing to the
+                // remote peer and free up the stream id. This fails if the remote
+                // already reset the stream, which is fine.
+                if let Err(e) = assoc.stream(entry.id).and_then(|mut s| s.close()) {
+                    debug!("Reset of stream {} failed: {:?}", entry.id, e);
+                }
                 entry.set_state(StreamEntryState::Closed);
                 return Some(SctpEvent::Close { id: entry.id });
             }
@@ -572,11 +764,13 @@ impl RtcSctp {
                 }
             };
 
-            match stream_read_data(&mut stream) {
+            match stream_read_data(&mut stream, &mut self.recycled) {
                 Ok(Some((buf, ppi))) => {
                     if ppi != PayloadProtocolIdentifier::Dcep {
                         // This is the normal path for incoming data.
                         let buf = ppi_adjust_buf(buf, ppi);
+                        entry.messages_received += 1;
+                        entry.bytes_received += buf.len() as u64;
                         let binary = matches!(
                             ppi,
                             PayloadProtocolIdentifier::Binary
@@ -713,6 +907,10 @@ fn stream_entry<'a>(
             state: initial_state,
             id,
             do_close: false,
+            messages_sent: 0,
+            bytes_sent: 0,
+            messages_received: 0,
+            bytes_received: 0,
         };
         entries.push(e);
         entries.last_mut().unwrap()
@@ -721,13 +919,14 @@ fn stream_entry<'a>(
 
 fn stream_read_data(
     stream: &mut Stream,
+    recycled: &mut Vec<Vec<u8>>,
 ) -> Result<Option<(Vec<u8>, PayloadProtocolIdentifier)>, SctpError> {
     let Some(chunks) = stream.read()? else {
         return Ok(None);
     };
 
     let n = chunks.len();
-    let mut buf = vec![0; n];
+    let mut buf = take_buffer(recycled, n);
 
     let l = chunks.read(&mut buf)?;
     assert!(l == n);
@@ -746,6 +945,26 @@ fn stream_read_data(
     Ok(Some((buf, chunks.ppi)))
 }
 
+/// Get a zeroed buffer of size `n`, preferring a recycled one to avoid allocating.
+fn take_buffer(recycled: &mut Vec<Vec<u8>>, n: usize) -> Vec<u8> {
+    // Prefer the smallest buffer that fits to not waste large ones on small messages.
+    let best = recycled
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| b.capacity() >= n)
+        .min_by_key(|(_, b)| b.capacity())
+        .map(|(i, _)| i);
+
+    let mut buf = match best {
+        Some(i) => recycled.swap_remove(i),
+        None => recycled.pop().unwrap_or_default(),
+    };
+
+    buf.clear();
+    buf.resize(n, 0);
+    buf
+}
+
 fn ppi_adjust_buf(mut buf: Vec<u8>, ppi: PayloadProtocolIdentifier) -> Vec<u8> {
     match ppi {
         PayloadProtocolIdentifier::StringEmpty | PayloadProtocolIdentifier::BinaryEmpty => {
@@ -775,6 +994,9 @@ impl fmt::Debug for SctpEvent {
                 .field("binary", binary)
                 .field("data", &data.len())
                 .finish(),
+            Self::BufferedAmountLow { id } => {
+                f.debug_struct("BufferedAmountLow").field("id", id).finish()
+            }
         }
     }
 }
@@ -830,3 +1052,171 @@ impl From<(ReliabilityType, u32)> for Reliability {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn take_buffer_reuses_recycled() {
+        let mut recycled = vec![Vec::with_capacity(10), Vec::with_capacity(100)];
+
+        let buf = take_buffer(&mut recycled, 50);
+        assert_eq!(buf.len(), 50);
+        assert_eq!(buf.capacity(), 100);
+        assert_eq!(recycled.len(), 1);
+
+        let buf = take_buffer(&mut recycled, 5);
+        assert_eq!(buf.len(), 5);
+        assert_eq!(buf.capacity(), 10);
+        assert!(recycled.is_empty());
+
+        let buf = take_buffer(&mut recycled, 5);
+        assert_eq!(buf, vec![0; 5]);
+    }
+
+    fn config(label: &str) -> ChannelConfig {
+        ChannelConfig {
+            label: label.into(),
+            ordered: true,
+            ..Default::default()
+        }
+    }
+
+    /// Deliver the transmits between the two associations for a while, advancing time.
+    fn exchange(
+        a: &mut RtcSctp,
+        b: &mut RtcSctp,
+        now: &mut Instant,
+    ) -> (Vec<SctpEvent>, Vec<SctpEvent>) {
+        let mut events = (vec![], vec![]);
+
+        for _ in 0..100 {
+            a.handle_timeout(*now);
+            b.handle_timeout(*now);
+
+            while let Some(e) = a.poll() {
+                match e {
+                    SctpEvent::Transmit { packets } => {
+                        packets.iter().for_each(|p| b.handle_input(*now, p))
+                    }
+                    e => events.0.push(e),
+                }
+            }
+
+            while let Some(e) = b.poll() {
+                match e {
+                    SctpEvent::Transmit { packets } => {
+                        packets.iter().for_each(|p| a.handle_input(*now, p))
+                    }
+                    e => events.1.push(e),
+                }
+            }
+
+            *now += Duration::from_millis(10);
+        }
+
+        events
+    }
+
+    fn opened(events: &[SctpEvent]) -> Vec<(u16, &str)> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                SctpEvent::Open { id, label } => Some((*id, label.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn closed(events: &[SctpEvent]) -> Vec<u16> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                SctpEvent::Close { id } => Some(*id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn association_lifecycle() {
+        let mut now = Instant::now();
+
+        let mut client = RtcSctp::new(1200);
+        let mut server = RtcSctp::new(1200);
+        client.init(true, now);
+        server.init(false, now);
+
+        // INIT, INIT ACK, COOKIE ECHO, COOKIE ACK, then the in-band DCEP.
+        client.open_stream(1, config("chat"));
+        let (c, s) = exchange(&mut client, &mut server, &mut now);
+
+        assert!(client.is_established());
+        assert!(server.is_established());
+        assert_eq!(opened(&c), vec![(1, "chat")]);
+        assert_eq!(opened(&s), vec![(1, "chat")]);
+
+        // DATA one way, SACK the other.
+        assert_eq!(client.write(1, true, b"hello"), Ok(5));
+        let (_, s) = exchange(&mut client, &mut server, &mut now);
+
+        let data: Vec<_> = s
+            .iter()
+            .filter_map(|e| match e {
+                SctpEvent::Data { id, binary, data } => Some((*id, *binary, data.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(data, vec![(1, true, b"hello".to_vec())]);
+        assert_eq!(client.buffered_amount(1), Ok(0));
+
+        // SHUTDOWN, SHUTDOWN ACK, SHUTDOWN COMPLETE.
+        client.shutdown();
+        let (c, s) = exchange(&mut client, &mut server, &mut now);
+
+        assert!(!client.is_established());
+        assert!(!server.is_established());
+        assert_eq!(closed(&c), vec![1]);
+        assert_eq!(closed(&s), vec![1]);
+        assert_eq!(client.poll_timeout(), None);
+        assert_eq!(
+            client.write(1, true, b"late"),
+            Err(SctpError::WriteBeforeEstablished)
+        );
+    }
+
+    #[test]
+    fn association_lost_closes_streams() {
+        let mut now = Instant::now();
+
+        let mut client = RtcSctp::new(1200);
+        client.init(true, now);
+        client.open_stream(1, config("chat"));
+
+        // The remote never answers, which makes the INIT retransmits run out.
+        let mut events = vec![];
+        for _ in 0..1000 {
+            while let Some(e) = client.poll() {
+                if !matches!(e, SctpEvent::Transmit { .. }) {
+                    events.push(e);
+                }
+            }
+
+            let Some(at) = client.poll_timeout() else {
+                break;
+            };
+            // Retransmits back off exponentially.
+            assert!(at >= now);
+            now = at;
+            client.handle_timeout(now);
+        }
+
+        assert_eq!(closed(&events), vec![1]);
+        assert!(!client.is_established());
+        assert_eq!(client.poll_timeout(), None);
+        assert!(client.poll().is_none());
+    }
+}
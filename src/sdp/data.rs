@@ -6,6 +6,7 @@ use std::fmt::{self};
 use std::num::ParseFloatError;
 use std::ops::Deref;
 use std::str::FromStr;
+use std::time::Duration;
 
 use crate::crypto::Fingerprint;
 use crate::format::Codec;
@@ -267,7 +268,7 @@ impl MediaLine {
     }
 
     pub fn rtp_params(&self) -> Vec<PayloadParams> {
-        let rtp_maps: Vec<_> = self
+        let mut rtp_maps: Vec<_> = self
             .attrs
             .iter()
             .filter_map(|a| {
@@ -279,6 +280,16 @@ impl MediaLine {
             })
             .collect();
 
+        // Static payload types (RFC 3551) in the m-line without an a=rtpmap.
+        for pt in &self.pts {
+            if rtp_maps.iter().any(|(p, _)| p == pt) {
+                continue;
+            }
+            if let Some(spec) = CodecSpec::from_static_pt(*pt) {
+                rtp_maps.push((*pt, spec.into()));
+            }
+        }
+
         let fmtps: Vec<_> = self
             .attrs
             .iter()
@@ -328,6 +339,10 @@ impl MediaLine {
                                 .any(|(cpt, c)| cpt == *pt && c.codec == Codec::Rtx);
                             if is_rtx {
                                 p.resend = Some(**pt);
+                                p.rtx_time = values.iter().find_map(|fp| match fp {
+                                    FormatParam::RtxTime(ms) => Some(*ms),
+                                    _ => None,
+                                });
                             }
                         }
                     }
@@ -344,6 +359,9 @@ impl MediaLine {
                         "transport-cc" => {
                             p.fb_transport_cc = true;
                         }
+                        "ack ccfb" => {
+                            p.fb_ccfb = true;
+                        }
                         "ccm fir" => {
                             p.fb_fir = true;
                         }
@@ -417,7 +435,8 @@ impl MediaLine {
                     }
                 })
                 .count();
-            if rtp_count == 0 {
+            // Static payload types (RFC 3551) don't need an a=rtpmap.
+            if rtp_count == 0 && CodecSpec::from_static_pt(*m).is_none() {
                 return Some(format!("Missing a=rtp_map:{} for mid: {}", m, self.mid()));
             }
             if rtp_count > 1 {
@@ -474,6 +493,26 @@ impl MediaLine {
         None
     }
 
+    pub fn max_message_size(&self) -> Option<usize> {
+        self.attrs.iter().find_map(|a| {
+            if let MediaAttribute::MaxMessageSize(v) = a {
+                Some(*v)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn ptime(&self) -> Option<Duration> {
+        self.attrs.iter().find_map(|a| {
+            if let MediaAttribute::PTime(v) = a {
+                Some(Duration::from_millis(*v as u64))
+            } else {
+                None
+            }
+        })
+    }
+
     /// This hoovers the ice candidates from all m-lines, lots of dupes.
     /// For WebRTC we don't expect different ice states per media line.
     pub fn ice_candidates(&self) -> impl Iterator<Item = &Candidate> {
@@ -804,6 +843,7 @@ pub enum MediaAttribute {
     Mid(Mid),     // 0, 1, 2
     SctpPort(u16),
     MaxMessageSize(usize),
+    PTime(u32), // a=ptime:20
     // a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level
     // a=extmap:2 http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time
     ExtMap {
@@ -903,12 +943,21 @@ pub enum FormatParam {
     /// * 64 00 1f - 6400=high (H)                  1f=level 3.1
     ProfileLevelId(u32),
 
-    /// VP9 profile id
+    /// VP9 or H265 profile id
     ProfileId(u32),
 
+    /// H265 tier flag
+    TierFlag(u8),
+
+    /// H265 level id
+    LevelId(u8),
+
     /// RTX (resend) codecs, which PT it concerns.
     Apt(Pt),
 
+    /// RTX (resend) codecs, how long (ms) the sender keeps packets for resends.
+    RtxTime(u32),
+
     /// Unrecognized fmtp.
     Unknown,
 }
@@ -952,6 +1001,22 @@ impl FormatParam {
                     Unknown
                 }
             }
+            "tier-flag" => {
+                if let Ok(v) = v.parse() {
+                    TierFlag(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
+            "level-id" => {
+                if let Ok(v) = v.parse() {
+                    LevelId(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
             "apt" => {
                 if let Ok(v) = v.parse::<u8>() {
                     Apt(v.into())
@@ -960,6 +1025,14 @@ impl FormatParam {
                     Unknown
                 }
             }
+            "rtx-time" => {
+                if let Ok(v) = v.parse() {
+                    RtxTime(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
             _ => Unknown,
         }
     }
@@ -978,7 +1051,10 @@ impl fmt::Display for FormatParam {
             PacketizationMode(v) => write!(f, "packetization-mode={}", *v),
             ProfileLevelId(v) => write!(f, "profile-level-id={:06x}", *v),
             ProfileId(v) => write!(f, "profile-id={}", *v),
+            TierFlag(v) => write!(f, "tier-flag={}", *v),
+            LevelId(v) => write!(f, "level-id={}", *v),
             Apt(v) => write!(f, "apt={v}"),
+            RtxTime(v) => write!(f, "rtx-time={v}"),
             Unknown => Ok(()),
         }
     }
@@ -997,6 +1073,12 @@ impl PayloadParams {
                 value: "transport-cc".into(),
             });
         }
+        if self.fb_ccfb {
+            attrs.push(MediaAttribute::RtcpFb {
+                pt: self.pt,
+                value: "ack ccfb".into(),
+            });
+        }
         if self.fb_remb {
             attrs.push(MediaAttribute::RtcpFb {
                 pt: self.pt,
@@ -1039,10 +1121,11 @@ impl PayloadParams {
                     channels: None,
                 },
             });
-            attrs.push(MediaAttribute::Fmtp {
-                pt,
-                values: vec![FormatParam::Apt(self.pt)],
-            });
+            let mut values = vec![FormatParam::Apt(self.pt)];
+            if let Some(rtx_time) = self.rtx_time {
+                values.push(FormatParam::RtxTime(rtx_time));
+            }
+            attrs.push(MediaAttribute::Fmtp { pt, values });
         }
     }
 }
@@ -1260,6 +1343,7 @@ impl fmt::Display for MediaAttribute {
             Mid(v) => write!(f, "a=mid:{v}\r\n")?,
             SctpPort(v) => write!(f, "a=sctp-port:{v}\r\n")?,
             MaxMessageSize(v) => write!(f, "a=max-message-size:{v}\r\n")?,
+            PTime(v) => write!(f, "a=ptime:{v}\r\n")?,
             ExtMap { id, ext } => {
                 if !ext.is_serialized() {
                     return Ok(());
@@ -1417,6 +1501,77 @@ mod test {
         assert_eq!(f.to_string(), "minptime=10;useinbandfec=1");
     }
 
+    #[test]
+    fn rtx_time_in_fmtp() {
+        let input = "v=0\r\n\
+        o=- 1 2 IN IP4 0.0.0.0\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        m=video 9 UDP/TLS/RTP/SAVPF 96 97\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=mid:0\r\n\
+        a=sendrecv\r\n\
+        a=rtpmap:96 VP8/90000\r\n\
+        a=rtpmap:97 rtx/90000\r\n\
+        a=fmtp:97 apt=96;rtx-time=500\r\n\
+        ";
+
+        let sdp = Sdp::parse(input).unwrap();
+        let params = sdp.media_lines[0].rtp_params();
+
+        assert_eq!(params[0].resend(), Some(97.into()));
+        assert_eq!(
+            params[0].rtx_time(),
+            Some(std::time::Duration::from_millis(500))
+        );
+
+        let mut attrs = vec![];
+        params[0].as_media_attrs(&mut attrs);
+        let fmtp = attrs.iter().find_map(|a| match a {
+            MediaAttribute::Fmtp { pt, values } if **pt == 97 => Some(values),
+            _ => None,
+        });
+        assert_eq!(
+            fmtp,
+            Some(&vec![
+                FormatParam::Apt(96.into()),
+                FormatParam::RtxTime(500)
+            ])
+        );
+    }
+
+    #[test]
+    fn static_pt_without_rtpmap() {
+        let input = "v=0\r\n\
+        o=- 1 2 IN IP4 0.0.0.0\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        m=audio 9 UDP/TLS/RTP/SAVPF 9 0 8\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=mid:0\r\n\
+        a=sendrecv\r\n\
+        a=rtpmap:9 G722/8000/1\r\n\
+        ";
+
+        let sdp = Sdp::parse(input).unwrap();
+        assert_eq!(sdp.media_lines[0].check_consistent(), None);
+
+        let params = sdp.media_lines[0].rtp_params();
+        let codecs: Vec<_> = params
+            .iter()
+            .map(|p| (*p.pt(), p.spec().codec, p.spec().clock_rate.get()))
+            .collect();
+
+        assert_eq!(
+            codecs,
+            vec![
+                (9, Codec::G722, 8000),
+                (0, Codec::Pcmu, 8000),
+                (8, Codec::Pcma, 8000)
+            ]
+        );
+    }
+
     #[test]
     fn parse_error() {
         let input = "v=0\r\n\
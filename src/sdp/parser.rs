@@ -504,6 +504,16 @@ where
     )
     .map(MediaAttribute::MaxMessageSize);
 
+    // a=ptime:20
+    let ptime = attribute_line(
+        "ptime",
+        not_sp::<Input>().and_then(|s| {
+            s.parse::<u32>()
+                .map_err(StreamErrorFor::<Input>::message_format)
+        }),
+    )
+    .map(MediaAttribute::PTime);
+
     // a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level
     // a=extmap:<value>["/"<direction>] <URI> <extensionattributes>
     let extmap = attribute_line(
@@ -762,6 +772,7 @@ where
         attempt(mid),
         attempt(sctp_port),
         attempt(max_message_size),
+        attempt(ptime),
         attempt(extmap),
         attempt(direction),
         attempt(msid),
@@ -1,28 +1,42 @@
-use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
 
 use crate::bwe::BweKind;
+#[cfg(feature = "bwe")]
+use crate::bwe::{BitrateAllocator, TargetBitrate};
 use crate::crypto::KeyingMaterial;
 use crate::crypto::SrtpProfile;
 use crate::format::CodecConfig;
 use crate::format::PayloadParams;
-use crate::io::{DatagramSend, DATAGRAM_MTU, DATAGRAM_MTU_WARN};
+use crate::io::{DatagramSend, DATAGRAM_MTU_WARN};
+use crate::media::DominantSpeaker;
 use crate::media::KeyframeRequestKind;
 use crate::media::Media;
 use crate::media::{MediaAdded, MediaChanged};
+#[cfg(feature = "bwe")]
+use crate::packet::LeakyBucketPacer;
+#[cfg(feature = "bwe")]
 use crate::packet::SendSideBandwithEstimator;
-use crate::packet::{LeakyBucketPacer, NullPacer, Pacer, PacerImpl};
-use crate::rtp::RawPacket;
+use crate::packet::{NullPacer, Pacer, PacerImpl};
+use crate::rtp::{ObservedPacket, Observer, PacketStage};
+use crate::rtp::{RawPacket, TappedKind, TappedPacket};
+#[cfg(feature = "bwe")]
+use crate::rtp_::Bitrate;
 use crate::rtp_::Direction;
 use crate::rtp_::Pt;
 use crate::rtp_::SeqNo;
 use crate::rtp_::SRTCP_OVERHEAD;
 use crate::rtp_::{extend_u16, RtpHeader, SessionId, TwccRecvRegister, TwccSendRegister};
-use crate::rtp_::{Bitrate, ExtensionMap, Mid, Rtcp, RtcpFb};
-use crate::rtp_::{SrtpContext, Ssrc};
-use crate::stats::StatsSnapshot;
-use crate::streams::{RtpPacket, Streams};
-use crate::util::{already_happened, not_happening, Soonest};
+use crate::rtp_::{CcfbRecvRegister, Ecn, SrtpContext, Ssrc};
+use crate::rtp_::{ExtensionMap, Goodbye, Mid, Rtcp, RtcpFb};
+#[cfg(feature = "bwe")]
+use crate::stats::BweStats;
+use crate::stats::{BufferPoolStats, RttStats, StatsSnapshot};
+use crate::streams::{RtcpIntervals, RtpPacket, SsrcCollision, Streams};
+use crate::streams::{UnknownSsrc, UnknownSsrcPolicy};
+use crate::util::Instant;
+use crate::util::{already_happened, calculate_rtt_ms, not_happening, InstantExt};
+use crate::util::{BufferPool, RttEstimator, Soonest};
 use crate::Event;
 use crate::{net, Reason};
 use crate::{RtcConfig, RtcError};
@@ -32,16 +46,20 @@ use crate::{RtcConfig, RtcError};
 /// network conditions.
 const NACK_MIN_INTERVAL: Duration = Duration::from_millis(33);
 
+/// Maximum time between sending nacks. With a large RTT, there is no point in nacking
+/// again before a retransmission could arrive, but we don't want to wait too long.
+const NACK_MAX_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Max number of buffers to keep in the pool for outgoing packets.
+const MAX_POOL_BUFFERS: usize = 64;
+
 /// Delay between reports of TWCC. This is deliberately very low.
 const TWCC_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Amend to the current_bitrate value.
+#[cfg(feature = "bwe")]
 const PACING_FACTOR: f64 = 1.1;
 
-/// Amount of deviation needed to emit a new BWE value. This is to reduce
-/// the total number BWE events to only fire when there is a substantial change.
-const ESTIMATE_TOLERANCE: f64 = 0.05;
-
 pub(crate) struct Session {
     id: SessionId,
 
@@ -56,10 +74,18 @@ pub(crate) struct Session {
     /// The app m-line. Spliced into medias above.
     app: Option<(Mid, usize)>,
 
+    #[cfg(feature = "sample-api")]
     reordering_size_audio: usize,
+    #[cfg(feature = "sample-api")]
     reordering_size_video: usize,
+    #[cfg(feature = "sample-api")]
+    jitter_buffer_delay: Option<(Duration, Duration)>,
+    #[cfg(feature = "sample-api")]
+    emit_only_decodable: bool,
     pub send_buffer_audio: usize,
     pub send_buffer_video: usize,
+    pub rtx_cache_max_bytes: usize,
+    pub resend_without_rtx: bool,
 
     /// Extension mappings are _per BUNDLE_, but we can only have one a=group BUNDLE
     /// in WebRTC (one ice connection), so they are effectively per session.
@@ -71,23 +97,35 @@ pub(crate) struct Session {
     srtp_rx: Option<SrtpContext>,
     srtp_tx: Option<SrtpContext>,
     last_nack: Instant,
+    rtt: RttEstimator,
     last_twcc: Instant,
     twcc: u64,
     twcc_rx_register: TwccRecvRegister,
     twcc_tx_register: TwccSendRegister,
 
+    #[cfg(feature = "bwe")]
     bwe: Option<Bwe>,
 
     enable_twcc_feedback: bool,
 
+    last_ccfb: Instant,
+    ccfb_rx_register: CcfbRecvRegister,
+    enable_ccfb_feedback: bool,
+
     /// A pacer for sending RTP at specific rate.
     pacer: PacerImpl,
 
     // temporary buffer when getting the next (unencrypted) RTP packet from Media line.
     poll_packet_buf: Vec<u8>,
+    /// Buffers for outgoing SRTP/SRTCP and RTCP compounding. Refilled via Rtc::recycle_transmit().
+    pool: BufferPool,
+    /// Max size of outgoing datagrams.
+    mtu: usize,
 
-    // Next packet for RtpPacket event.
-    pending_packet: Option<RtpPacket>,
+    // Packets for RtpPacket event. This is normally at most one packet, since every
+    // handle_input() is expected to be followed by poll_output(), but we don't want to
+    // lose packets if the API user does several handle_input() in a row.
+    pending_packets: VecDeque<RtpPacket>,
 
     pub ice_lite: bool,
 
@@ -96,10 +134,39 @@ pub(crate) struct Session {
 
     feedback_tx: VecDeque<Rtcp>,
     feedback_rx: VecDeque<Rtcp>,
+    feedback_rx_fb: VecDeque<RtcpFb>,
 
     raw_packets: Option<VecDeque<Box<RawPacket>>>,
+
+    packet_tap: Option<VecDeque<TappedPacket>>,
+
+    observer: Option<Observer>,
+
+    /// What to do with packets from SSRCs not bound to any media.
+    unknown_ssrc_policy: UnknownSsrcPolicy,
+    /// SSRCs we emitted UnknownSsrc events for.
+    unknown_ssrc_seen: HashSet<Ssrc>,
+    /// UnknownSsrc events waiting to be polled.
+    unknown_ssrc_events: VecDeque<UnknownSsrc>,
+    /// RTP packets held for UnknownSsrcPolicy::Buffer.
+    unknown_ssrc_buffer: VecDeque<(Instant, RtpHeader, Vec<u8>)>,
+
+    /// SsrcCollision events waiting to be polled.
+    ssrc_collisions: VecDeque<SsrcCollision>,
+
+    /// Dominant speaker detection, if enabled.
+    dominant_speaker: Option<DominantSpeaker>,
 }
 
+/// Max number of RTP packets held for unknown SSRCs.
+const MAX_UNKNOWN_SSRC_BUFFER: usize = 100;
+
+/// Max number of unknown SSRCs to remember, to not emit repeated events.
+const MAX_UNKNOWN_SSRC_SEEN: usize = 100;
+
+/// Max number of tapped packets held until polled.
+const MAX_TAPPED_PACKETS: usize = 1000;
+
 impl Session {
     pub fn new(config: &RtcConfig) -> Self {
         let mut id = SessionId::new();
@@ -108,14 +175,26 @@ impl Session {
         while *id > MAX_ID {
             id = (*id >> 1).into();
         }
+        #[cfg(feature = "bwe")]
         let (pacer, bwe) = if let Some(rate) = config.bwe_initial_bitrate {
             let pacer = PacerImpl::LeakyBucket(LeakyBucketPacer::new(rate * PACING_FACTOR * 2.0));
 
-            let send_side_bwe = SendSideBandwithEstimator::new(rate);
+            let mut send_side_bwe = SendSideBandwithEstimator::new(rate);
+            let (min, max) = ordered_bounds(config.bwe_bitrate_bounds);
+            send_side_bwe.set_bitrate_bounds(min, max);
+
             let bwe = Bwe {
                 bwe: send_side_bwe,
                 desired_bitrate: Bitrate::ZERO,
                 current_bitrate: rate,
+                probe_target: None,
+                remb_cap: None,
+                bounds: (min, max),
+                estimate_tolerance: config.bwe_estimate_tolerance,
+                allocator: None,
+                need_allocate: false,
+                last_targets: vec![],
+                pending_targets: VecDeque::new(),
 
                 last_emitted_estimate: Bitrate::ZERO,
             };
@@ -124,16 +203,31 @@ impl Session {
         } else {
             (PacerImpl::Null(NullPacer::default()), None)
         };
+        #[cfg(not(feature = "bwe"))]
+        let pacer = PacerImpl::Null(NullPacer::default());
 
         Session {
             id,
             medias: vec![],
-            streams: Streams::default(),
+            streams: Streams::new(RtcpIntervals {
+                audio: config.rtcp_interval_audio,
+                video: config.rtcp_interval_video,
+                keyframe_request: config.keyframe_request_interval,
+                bandwidth: config.rtcp_bandwidth,
+            }),
             app: None,
+            #[cfg(feature = "sample-api")]
             reordering_size_audio: config.reordering_size_audio,
+            #[cfg(feature = "sample-api")]
             reordering_size_video: config.reordering_size_video,
+            #[cfg(feature = "sample-api")]
+            jitter_buffer_delay: config.jitter_buffer_delay,
+            #[cfg(feature = "sample-api")]
+            emit_only_decodable: config.emit_only_decodable,
             send_buffer_audio: config.send_buffer_audio,
             send_buffer_video: config.send_buffer_video,
+            rtx_cache_max_bytes: config.rtx_cache_max_bytes,
+            resend_without_rtx: config.resend_without_rtx,
             exts: config.exts.clone(),
 
             // Both sending and receiving starts from the configured codecs.
@@ -143,24 +237,46 @@ impl Session {
             srtp_rx: None,
             srtp_tx: None,
             last_nack: already_happened(),
+            rtt: RttEstimator::default(),
             last_twcc: already_happened(),
             twcc: 0,
             twcc_rx_register: TwccRecvRegister::new(100),
             twcc_tx_register: TwccSendRegister::new(1000),
+            #[cfg(feature = "bwe")]
             bwe,
             enable_twcc_feedback: false,
+            last_ccfb: already_happened(),
+            ccfb_rx_register: CcfbRecvRegister::default(),
+            enable_ccfb_feedback: false,
             pacer,
             poll_packet_buf: vec![0; 2000],
-            pending_packet: None,
+            pool: BufferPool::new(MAX_POOL_BUFFERS, config.mtu),
+            mtu: config.mtu,
+            pending_packets: VecDeque::new(),
             ice_lite: config.ice_lite,
-            rtp_mode: config.rtp_mode,
+            rtp_mode: config.rtp_mode(),
             feedback_tx: VecDeque::new(),
             feedback_rx: VecDeque::new(),
+            feedback_rx_fb: VecDeque::new(),
             raw_packets: if config.enable_raw_packets {
                 Some(VecDeque::new())
             } else {
                 None
             },
+            packet_tap: if config.enable_packet_tap {
+                Some(VecDeque::new())
+            } else {
+                None
+            },
+            observer: config.packet_observer.clone(),
+            unknown_ssrc_policy: config.unknown_ssrc_policy,
+            unknown_ssrc_seen: HashSet::new(),
+            unknown_ssrc_events: VecDeque::new(),
+            unknown_ssrc_buffer: VecDeque::new(),
+            ssrc_collisions: VecDeque::new(),
+            dominant_speaker: config
+                .enable_dominant_speaker
+                .then(DominantSpeaker::default),
         }
     }
 
@@ -203,12 +319,23 @@ impl Session {
 
     pub fn handle_timeout(&mut self, now: Instant) -> Result<(), RtcError> {
         // Payload any waiting samples
+        #[cfg(feature = "sample-api")]
         self.do_payload(now)?;
 
+        // Frames held back in the jitter buffers are released on the next poll.
+        #[cfg(feature = "sample-api")]
+        for media in &mut self.medias {
+            media.handle_depayload_timeout(now);
+        }
+
         let sender_ssrc = self.streams.first_ssrc_local();
 
         let do_nack = now >= self.nack_at().unwrap_or(not_happening());
 
+        if !self.unknown_ssrc_buffer.is_empty() {
+            self.replay_unknown_ssrc(now);
+        }
+
         self.streams.handle_timeout(
             now,
             sender_ssrc,
@@ -216,6 +343,7 @@ impl Session {
             &self.medias,
             &self.codec_config,
             &mut self.feedback_tx,
+            self.observer.as_ref(),
         );
 
         if do_nack {
@@ -230,13 +358,39 @@ impl Session {
             }
         }
 
+        if let Some(ccfb_at) = self.ccfb_at() {
+            if now >= ccfb_at {
+                self.create_ccfb_feedback(sender_ssrc, now);
+            }
+        }
+
+        #[cfg(feature = "bwe")]
         if let Some(bwe) = self.bwe.as_mut() {
+            bwe.bwe.set_transport_rtt(self.rtt.smoothed());
             bwe.handle_timeout(now);
+
+            let probe_target = bwe.bwe.probe_target(bwe.desired_bitrate, now);
+            if probe_target != bwe.probe_target {
+                bwe.probe_target = probe_target;
+                self.configure_pacer();
+            }
         }
 
         Ok(())
     }
 
+    fn create_ccfb_feedback(&mut self, sender_ssrc: Ssrc, now: Instant) -> Option<()> {
+        self.last_ccfb = now;
+        let mut ccfb = self.ccfb_rx_register.build_report(now, self.mtu - 100)?;
+
+        ccfb.sender_ssrc = sender_ssrc;
+
+        trace!("Created feedback CCFB: {:?}", ccfb);
+        self.feedback_tx.push_front(Rtcp::Ccfb(ccfb));
+
+        Some(())
+    }
+
     fn update_queue_state(&mut self, now: Instant) {
         let iter = self.streams.streams_tx().map(|m| m.queue_state(now));
 
@@ -244,17 +398,22 @@ impl Session {
             return;
         };
 
-        let stream = self
-            .streams
-            .stream_tx_by_mid_rid(padding_request.mid, None)
-            .expect("pacer to use an existing stream");
+        // Padding goes on the RTX SSRC of the chosen layer, never the media SSRC, since
+        // receivers might not expect padding on the media stream.
+        let Some(stream) = self.streams.stream_tx_for_padding(padding_request.mid) else {
+            trace!(
+                "No stream with RTX to pad on for mid: {}",
+                padding_request.mid
+            );
+            return;
+        };
 
         stream.generate_padding(padding_request.padding);
     }
 
     fn create_twcc_feedback(&mut self, sender_ssrc: Ssrc, now: Instant) -> Option<()> {
         self.last_twcc = now;
-        let mut twcc = self.twcc_rx_register.build_report(DATAGRAM_MTU - 100)?;
+        let mut twcc = self.twcc_rx_register.build_report(self.mtu - 100)?;
 
         // These SSRC are on media level, but twcc is on session level,
         // we fill in the first discovered media SSRC in each direction.
@@ -342,18 +501,141 @@ impl Session {
         }
     }
 
+    /// Say BYE for all outgoing SSRCs, including RTX, ahead of closing the session.
+    pub fn goodbye_all(&mut self) {
+        let ssrcs: Vec<Ssrc> = self
+            .streams
+            .streams_tx()
+            .flat_map(|s| [Some(s.ssrc()), s.rtx()])
+            .flatten()
+            .collect();
+
+        for ssrc in ssrcs {
+            self.feedback_tx.push_back(Rtcp::Goodbye(Goodbye {
+                reports: ssrc.into(),
+                reason: None,
+            }));
+        }
+    }
+
+    /// RFC 3550 8.2. The remote is sending on an SSRC we use. Move to a new one and
+    /// say BYE to the old.
+    fn handle_ssrc_collision(&mut self, ssrc: Ssrc) {
+        let new = self.streams.new_ssrc();
+
+        let Some((mid, rid)) = self.streams.change_stream_tx_ssrc(ssrc, new) else {
+            return;
+        };
+
+        warn!(
+            "SSRC collision on {}, change to {} for mid: {}",
+            ssrc, new, mid
+        );
+
+        self.feedback_tx.push_back(Rtcp::Goodbye(Goodbye {
+            reports: ssrc.into(),
+            reason: Some("SSRC collision".to_string()),
+        }));
+
+        self.ssrc_collisions.push_back(SsrcCollision {
+            mid,
+            rid,
+            old: ssrc,
+            new,
+        });
+    }
+
+    fn handle_unknown_ssrc(&mut self, now: Instant, header: RtpHeader, buf: &[u8]) {
+        match self.unknown_ssrc_policy {
+            UnknownSsrcPolicy::Drop => {}
+            UnknownSsrcPolicy::Event => {
+                self.push_unknown_ssrc(UnknownSsrc {
+                    ssrc: header.ssrc,
+                    mid: header.ext_vals.mid,
+                    rid: header.ext_vals.rid.or(header.ext_vals.rid_repair),
+                    pt: Some(header.payload_type),
+                });
+            }
+            UnknownSsrcPolicy::Buffer(_) => {
+                if self.unknown_ssrc_buffer.len() >= MAX_UNKNOWN_SSRC_BUFFER {
+                    self.unknown_ssrc_buffer.pop_front();
+                }
+                self.unknown_ssrc_buffer
+                    .push_back((now, header, buf.to_vec()));
+            }
+        }
+    }
+
+    fn push_unknown_ssrc(&mut self, unknown: UnknownSsrc) {
+        if self.unknown_ssrc_seen.len() >= MAX_UNKNOWN_SSRC_SEEN {
+            self.unknown_ssrc_seen.clear();
+        }
+        if self.unknown_ssrc_seen.insert(unknown.ssrc) {
+            self.unknown_ssrc_events.push_back(unknown);
+        }
+    }
+
+    /// Handle held packets whose SSRC is bound by now, and drop the ones held too long.
+    fn replay_unknown_ssrc(&mut self, now: Instant) {
+        let UnknownSsrcPolicy::Buffer(max_age) = self.unknown_ssrc_policy else {
+            return;
+        };
+
+        let held = std::mem::take(&mut self.unknown_ssrc_buffer);
+        let mut replay = vec![];
+
+        for (t, header, buf) in held {
+            if now.saturating_duration_since(t) > max_age {
+                trace!("Drop held RTP for unknown SSRC: {}", header.ssrc);
+                continue;
+            }
+
+            if self.mid_and_ssrc_for_header(t, &header).is_some() {
+                replay.push((t, header, buf));
+            } else {
+                self.unknown_ssrc_buffer.push_back((t, header, buf));
+            }
+        }
+
+        for (t, header, buf) in replay {
+            debug!("Handle held RTP for SSRC: {}", header.ssrc);
+            self.handle_rtp_received(t, header, &buf);
+        }
+    }
+
     pub(crate) fn handle_rtp(&mut self, now: Instant, mut header: RtpHeader, buf: &[u8]) {
         // Rewrite absolute-send-time (if present) to be relative to now.
         header.ext_vals.update_absolute_send_time(now);
 
         trace!("Handle RTP: {:?}", header);
 
+        if let Some(o) = &self.observer {
+            o.observe(&ObservedPacket::rtp(
+                PacketStage::Received,
+                now,
+                &header,
+                buf,
+            ));
+        }
+
+        self.handle_rtp_received(now, header, buf);
+    }
+
+    /// Handling of RTP after the absolute-send-time rewrite. Held packets for unknown
+    /// SSRCs start here, since they have been rewritten already.
+    fn handle_rtp_received(&mut self, now: Instant, mut header: RtpHeader, buf: &[u8]) {
         // The ssrc is the _main_ ssrc (no the rtx, that might be in the header).
         let Some((mid, ssrc)) = self.mid_and_ssrc_for_header(now, &header) else {
             debug!("No mid/SSRC for header: {:?}", header);
+            self.handle_unknown_ssrc(now, header, buf);
             return;
         };
 
+        // This packet might have bound the SSRC of held packets.
+        if !self.unknown_ssrc_buffer.is_empty() {
+            self.replay_unknown_ssrc(now);
+        }
+
         let srtp = match self.srtp_rx.as_mut() {
             Some(v) => v,
             None => {
@@ -364,6 +646,18 @@ impl Session {
 
         // Both of these unwraps are fine because mid_and_ssrc_for_header guarantees it.
         let media = self.medias.iter_mut().find(|m| m.mid() == mid).unwrap();
+
+        if !media.direction().is_receiving() {
+            // The media might have been changed to sendonly/inactive via a renegotiation,
+            // while the remote peer still has packets in flight.
+            trace!(
+                "Drop RTP for mid {} in direction: {}",
+                mid,
+                media.direction()
+            );
+            return;
+        }
+
         let stream = self.streams.stream_rx(&ssrc).unwrap();
 
         let params = match main_payload_params(&self.codec_config, header.payload_type) {
@@ -392,6 +686,49 @@ impl Session {
             }
         };
 
+        // Only act on collisions for authenticated packets, or anyone on the path could
+        // make us change SSRC.
+        if let Some(other) = header.ext_vals.mid.filter(|m| *m != mid) {
+            // Two remote sources use the same SSRC. The SSRC stays bound to the first mid,
+            // packets from the other source are dropped.
+            debug!(
+                "Drop RTP for SSRC {} bound to mid {}, but sent for mid {}",
+                header.ssrc, mid, other
+            );
+            return;
+        }
+
+        if self.streams.is_local_ssrc(header.ssrc) {
+            self.handle_ssrc_collision(header.ssrc);
+        }
+
+        // The collision handling needs all of self, so look these up again.
+        #[cfg(feature = "sample-api")]
+        let media = self.medias.iter_mut().find(|m| m.mid() == mid).unwrap();
+        let stream = self.streams.stream_rx(&ssrc).unwrap();
+
+        // Only authenticated packets can reset the receive state.
+        let jumped = !is_repair && stream.check_seq_jump(seq_no);
+
+        // Like for an SSRC change, the depayloader can't handle the jump.
+        #[cfg(feature = "sample-api")]
+        if jumped {
+            media.reset_depayloader(pt, stream.rid());
+        }
+        #[cfg(not(feature = "sample-api"))]
+        let _ = jumped;
+
+        if let Some(o) = &self.observer {
+            let packet = ObservedPacket::rtp(PacketStage::Decrypted, now, &header, &data);
+            o.observe(&packet.with_seq_no(seq_no));
+        }
+
+        tap_packet(&mut self.packet_tap, now, TappedKind::RtpRx, || {
+            let mut packet = buf[..header.header_len].to_vec();
+            packet.extend_from_slice(&data);
+            packet
+        });
+
         if header.has_padding && !RtpHeader::unpad_payload(&mut data) {
             // Unpadding failed. Broken data?
             trace!("unpadding of unprotected payload failed");
@@ -409,20 +746,28 @@ impl Session {
             self.twcc_rx_register.update_seq(extended.into(), now);
         }
 
+        // Mark as received for CCFB purposes. This is per SSRC, including RTX.
+        if self.enable_ccfb_feedback {
+            // We don't get the ECN bits from the socket, so everything is not ECT.
+            self.ccfb_rx_register
+                .update_seq(header.ssrc, seq_no, now, Ecn::NotEct);
+        }
+
         // Register reception in nack registers.
         let receipt_outer = stream.update_register(now, &header, clock_rate, is_repair, seq_no);
 
         // RTX packets must be rewritten to be a normal packet. This only changes the
         // the seq_no, however MediaTime might be different when interpreted against the
         // the "main" register.
-        let receipt = if is_repair {
-            // Drop RTX packets that are just empty padding. The payload here
-            // is empty because we would have done RtpHeader::unpad_payload above.
-            // For unpausing, it's enough with the stream.update() already done above.
-            if data.is_empty() {
-                return;
-            }
+        // Drop packets that are just empty padding, either on RTX or as a keep-alive on
+        // the main SSRC. The payload here is empty because we would have done
+        // RtpHeader::unpad_payload above. For unpausing, it's enough with the
+        // stream.update() already done above.
+        if data.is_empty() {
+            return;
+        }
 
+        let receipt = if is_repair {
             // Rewrite the header, and removes the resent seq_no from the body.
             stream.un_rtx(&mut header, &mut data, pt);
 
@@ -439,31 +784,71 @@ impl Session {
             receipt_outer
         };
 
-        let packet = stream.handle_rtp(now, header, data, seq_no, receipt.time);
+        let packet = stream.handle_rtp(
+            now,
+            header,
+            data,
+            seq_no,
+            receipt.time,
+            receipt.is_new_packet,
+        );
+
+        if let Some(o) = &self.observer {
+            let p = ObservedPacket::rtp(PacketStage::Demuxed, now, &packet.header, &packet.payload);
+            o.observe(&p.with_mid(mid).with_seq_no(packet.seq_no));
+        }
+
+        if let Some(ds) = &mut self.dominant_speaker {
+            let ext = &packet.header.ext_vals;
+            if let (true, Some(level)) = (receipt.is_new_packet, ext.audio_level) {
+                ds.handle_audio_level(now, mid, ssrc, level, ext.voice_activity);
+            }
+        }
 
         if self.rtp_mode {
             // In RTP mode, we store the packet temporarily here for the next poll_output().
             // However only if this is a packet not seen before. This filters out spurious resends for padding.
             if receipt.is_new_packet {
-                self.pending_packet = Some(packet);
+                self.pending_packets.push_back(packet);
             }
         } else {
             // In non-RTP mode, we let the Media use a Depayloader.
+            #[cfg(feature = "sample-api")]
             media.depayload(
                 stream.rid(),
                 packet,
                 self.reordering_size_audio,
                 self.reordering_size_video,
                 &self.codec_config,
+                stream.svc_target(),
+                self.emit_only_decodable,
+                self.jitter_buffer_delay,
             );
         }
     }
 
     fn handle_rtcp(&mut self, now: Instant, buf: &[u8]) -> Option<()> {
+        if let Some(o) = &self.observer {
+            o.observe(&ObservedPacket::rtcp(PacketStage::Received, now, buf));
+        }
+
         let srtp: &mut SrtpContext = self.srtp_rx.as_mut()?;
         let unprotected = srtp.unprotect_rtcp(buf)?;
 
+        if let Some(o) = &self.observer {
+            o.observe(&ObservedPacket::rtcp(
+                PacketStage::Decrypted,
+                now,
+                &unprotected,
+            ));
+        }
+
+        tap_packet(&mut self.packet_tap, now, TappedKind::RtcpRx, || {
+            unprotected.clone()
+        });
+
         Rtcp::read_packet(&unprotected, &mut self.feedback_rx);
+        #[cfg(feature = "bwe")]
         let mut need_configure_pacer = false;
 
         if let Some(raw_packets) = &mut self.raw_packets {
@@ -472,19 +857,30 @@ impl Session {
             }
         }
 
-        for fb in RtcpFb::from_rtcp(self.feedback_rx.drain(..)) {
+        // SSRCs of sender reports not bound to any media.
+        let mut unknown = vec![];
+
+        RtcpFb::from_rtcp(self.feedback_rx.drain(..), &mut self.feedback_rx_fb);
+
+        for fb in self.feedback_rx_fb.drain(..) {
             if let RtcpFb::Twcc(twcc) = fb {
                 trace!("Handle TWCC: {:?}", twcc);
                 let range = self.twcc_tx_register.apply_report(twcc, now);
 
-                if let Some(bwe) = &mut self.bwe {
-                    let records = range.and_then(|range| self.twcc_tx_register.send_records(range));
+                #[cfg(feature = "bwe")]
+                {
+                    if let Some(bwe) = &mut self.bwe {
+                        let records =
+                            range.and_then(|range| self.twcc_tx_register.send_records(range));
 
-                    if let Some(records) = records {
-                        bwe.update(records, now);
+                        if let Some(records) = records {
+                            bwe.update(records, now);
+                        }
                     }
+                    need_configure_pacer = true;
                 }
-                need_configure_pacer = true;
+                #[cfg(not(feature = "bwe"))]
+                let _ = range;
 
                 // The funky thing about TWCC reports is that they are never stapled
                 // together with other RTCP packet. If they were though, we want to
@@ -492,10 +888,52 @@ impl Session {
                 continue;
             }
 
+            if let RtcpFb::Ccfb(ccfb) = &fb {
+                // CCFB is handled on session level.
+                trace!("Handle CCFB: {:?} ECN: {:?}", ccfb, ccfb.ecn_counts());
+                let applied = self.twcc_tx_register.apply_ccfb(ccfb, now);
+
+                #[cfg(feature = "bwe")]
+                {
+                    if let Some(bwe) = &mut self.bwe {
+                        if !applied.is_empty() {
+                            let records = applied
+                                .iter()
+                                .filter_map(|seq| self.twcc_tx_register.send_record(*seq));
+
+                            bwe.update(records, now);
+                        }
+                    }
+                    need_configure_pacer = true;
+                }
+                #[cfg(not(feature = "bwe"))]
+                let _ = applied;
+
+                continue;
+            }
+
+            update_rtt_from_rtcp(&mut self.rtt, now, &fb);
+
             if fb.is_for_rx() {
+                // A sender report is the first RTCP from a new sender.
+                let is_sr = matches!(fb, RtcpFb::SenderInfo(_));
+                if is_sr && !self.streams.has_stream_rx(fb.ssrc()) {
+                    unknown.push(fb.ssrc());
+                    continue;
+                }
+
                 let Some(stream) = self.streams.stream_rx(&fb.ssrc()) else {
                     continue;
                 };
+                // The depayloaders are the sample level receive state for the stream.
+                #[cfg(feature = "sample-api")]
+                if matches!(fb, RtcpFb::Goodbye(..)) {
+                    let (mid, rid) = (stream.mid(), stream.rid());
+                    if let Some(media) = self.medias.iter_mut().find(|m| m.mid() == mid) {
+                        media.reset_depayloaders(rid);
+                    }
+                }
+
                 stream.handle_rtcp(now, fb);
             } else {
                 let Some(stream) = self.streams.stream_tx(&fb.ssrc()) else {
@@ -505,8 +943,20 @@ impl Session {
             }
         }
 
+        if self.unknown_ssrc_policy == UnknownSsrcPolicy::Event {
+            for ssrc in unknown {
+                self.push_unknown_ssrc(UnknownSsrc {
+                    ssrc,
+                    mid: None,
+                    rid: None,
+                    pt: None,
+                });
+            }
+        }
+
         // Not in the above if due to lifetime issues, still okay because the method
         // doesn't do anything when BWE isn't configured.
+        #[cfg(feature = "bwe")]
         if need_configure_pacer {
             self.configure_pacer();
         }
@@ -515,12 +965,18 @@ impl Session {
     }
 
     pub fn poll_event(&mut self) -> Option<Event> {
+        #[cfg(feature = "bwe")]
         if let Some(bitrate_estimate) = self.bwe.as_mut().and_then(|bwe| bwe.poll_estimate()) {
             return Some(Event::EgressBitrateEstimate(BweKind::Twcc(
                 bitrate_estimate,
             )));
         }
 
+        #[cfg(feature = "bwe")]
+        if let Some(target) = self.poll_target_bitrate() {
+            return Some(Event::TargetBitrate(target));
+        }
+
         // If we're not ready to flow media, don't send any events.
         if !self.ready_for_srtp() {
             return None;
@@ -532,14 +988,42 @@ impl Session {
             }
         }
 
-        // This must be before pending_packet.take() since we need to emit the unpaused event
+        // This must be before pending_packets.pop_front() since we need to emit the unpaused event
         // before the first packet causing the unpause.
         if let Some(paused) = self.streams.poll_stream_paused() {
             return Some(Event::StreamPaused(paused));
         }
 
+        // Before the first packet after the discontinuity.
+        if let Some(d) = self.streams.poll_stream_discontinuity() {
+            return Some(Event::StreamDiscontinuity(d));
+        }
+
+        if let Some(c) = self.ssrc_collisions.pop_front() {
+            return Some(Event::SsrcCollision(c));
+        }
+
+        if let Some(u) = self.unknown_ssrc_events.pop_front() {
+            return Some(Event::UnknownSsrc(u));
+        }
+
+        if let Some(e) = self.streams.poll_stream_ended() {
+            if let Some(ds) = &mut self.dominant_speaker {
+                ds.remove(e.ssrc);
+            }
+            return Some(Event::StreamEnded(e));
+        }
+
+        if let Some(e) = self.dominant_speaker.as_mut().and_then(|d| d.poll_event()) {
+            return Some(Event::DominantSpeakerChanged(e));
+        }
+
+        if let Some(w) = self.streams.poll_stream_writable() {
+            return Some(Event::StreamWritable(w));
+        }
+
         if self.rtp_mode {
-            if let Some(packet) = self.pending_packet.take() {
+            if let Some(packet) = self.pending_packets.pop_front() {
                 return Some(Event::RtpPacket(packet));
             }
         }
@@ -549,6 +1033,11 @@ impl Session {
         }
 
         if let Some((mid, bitrate)) = self.streams.poll_remb_request() {
+            #[cfg(feature = "bwe")]
+            if let Some(bwe) = self.bwe.as_mut() {
+                bwe.remb_cap = Some(bitrate);
+                self.configure_pacer();
+            }
             return Some(Event::EgressBitrateEstimate(BweKind::Remb(mid, bitrate)));
         }
 
@@ -576,12 +1065,17 @@ impl Session {
         None
     }
 
+    pub fn poll_tapped_packet(&mut self) -> Option<TappedPacket> {
+        self.packet_tap.as_mut()?.pop_front()
+    }
+
     pub fn poll_event_fallible(&mut self) -> Result<Option<Event>, RtcError> {
         // Not relevant in rtp_mode, where the packets are picked up by poll_event().
         if self.rtp_mode {
             return Ok(None);
         }
 
+        #[cfg(feature = "sample-api")]
         for media in &mut self.medias {
             if let Some(e) = media.poll_sample(&self.codec_config)? {
                 return Ok(Some(Event::MediaData(e)));
@@ -602,31 +1096,32 @@ impl Session {
         }
 
         let x = None
-            .or_else(|| self.poll_feedback())
+            .or_else(|| self.poll_feedback(now))
             .or_else(|| self.poll_packet(now));
 
         if let Some(x) = &x {
             // In RTP mode we trust the API user feeds the RTP packet sizes they
             // need for the MTU they are targeting. This warning is only for when
             // str0m does the RTP packetization.
-            if !self.rtp_mode && x.len() > DATAGRAM_MTU_WARN {
-                warn!("RTP above MTU {}: {}", DATAGRAM_MTU_WARN, x.len());
+            let warn_at = self.mtu.max(DATAGRAM_MTU_WARN);
+            if !self.rtp_mode && x.len() > warn_at {
+                warn!("RTP above MTU {}: {}", warn_at, x.len());
             }
         }
 
         x
     }
 
-    fn poll_feedback(&mut self) -> Option<net::DatagramSend> {
+    fn poll_feedback(&mut self, now: Instant) -> Option<net::DatagramSend> {
         if self.feedback_tx.is_empty() {
             return None;
         }
 
         // Round to nearest multiple of 4 bytes.
-        const ENCRYPTABLE_MTU: usize = (DATAGRAM_MTU - SRTCP_OVERHEAD) & !3;
-        assert!(ENCRYPTABLE_MTU % 4 == 0);
+        let encryptable_mtu = (self.mtu - SRTCP_OVERHEAD) & !3;
 
-        let mut data = vec![0_u8; ENCRYPTABLE_MTU];
+        let mut data = self.pool.get();
+        data.resize(encryptable_mtu, 0);
 
         let mut raw_packets = self.raw_packets.as_mut();
         let output = move |fb| {
@@ -636,18 +1131,34 @@ impl Session {
         };
 
         let len = Rtcp::write_packet(&mut self.feedback_tx, &mut data, output);
+        trace!(len, "Created RTCP compound packet");
 
         if len == 0 {
+            self.pool.put(data);
             return None;
         }
 
         data.truncate(len);
 
-        let srtp = self.srtp_tx.as_mut()?;
-        let protected = srtp.protect_rtcp(&data);
+        let Some(srtp) = self.srtp_tx.as_mut() else {
+            self.pool.put(data);
+            return None;
+        };
+
+        tap_packet(&mut self.packet_tap, now, TappedKind::RtcpTx, || {
+            data.clone()
+        });
+
+        let mut protected = self.pool.get();
+        srtp.protect_rtcp_into(&data, &mut protected);
+        self.pool.put(data);
+
+        if let Some(o) = &self.observer {
+            o.observe(&ObservedPacket::rtcp(PacketStage::Sent, now, &protected));
+        }
 
         assert!(
-            protected.len() < DATAGRAM_MTU,
+            protected.len() <= self.mtu,
             "Encrypted SRTCP should be less than MTU"
         );
 
@@ -667,13 +1178,17 @@ impl Session {
 
         let buf = &mut self.poll_packet_buf;
         let twcc_seq = self.twcc;
-
-        // TODO: allow for sending simulcast
-        let stream = self.streams.stream_tx_by_mid_rid(media.mid(), None)?;
+        let mtu = self.mtu;
 
         let params = &self.codec_config;
         let exts = media.remote_extmap();
-        let receipt = stream.poll_packet(now, exts, &mut self.twcc, params, buf)?;
+
+        // The pacer schedules per mid. With simulcast there are several streams (one per rid)
+        // for the same mid, and we send from the first that has something to send.
+        let receipt = self
+            .streams
+            .streams_tx_by_mid(mid)
+            .find_map(|stream| stream.poll_packet(now, exts, &mut self.twcc, params, buf, mtu))?;
 
         let PacketReceipt {
             header,
@@ -697,10 +1212,23 @@ impl Session {
             raw_packets.push_back(Box::new(RawPacket::RtpTx(header.clone(), buf.clone())));
         }
 
-        let protected = srtp_tx.protect_rtp(buf, &header, *seq_no);
+        tap_packet(&mut self.packet_tap, now, TappedKind::RtpTx, || buf.clone());
+
+        let mut protected = self.pool.get();
+        srtp_tx.protect_rtp_into(buf, &header, *seq_no, &mut protected);
 
-        self.twcc_tx_register
-            .register_seq(twcc_seq.into(), now, payload_size);
+        if let Some(o) = &self.observer {
+            let packet = ObservedPacket::rtp(PacketStage::Sent, now, &header, &protected);
+            o.observe(&packet.with_mid(mid).with_seq_no(seq_no));
+        }
+
+        self.twcc_tx_register.register_seq(
+            twcc_seq.into(),
+            now,
+            payload_size,
+            is_padding,
+            (header.ssrc, header.sequence_number),
+        );
 
         // Technically we should wait for the next handle_timeout, but this speeds things up a bit
         // avoiding an extra poll_timeout.
@@ -713,17 +1241,31 @@ impl Session {
         let feedback_at = self.regular_feedback_at();
         let nack_at = self.nack_at();
         let twcc_at = self.twcc_at();
+        let ccfb_at = self.ccfb_at();
         let pacing_at = self.pacer.poll_timeout();
         let packetize_at = self.medias.iter().flat_map(|m| m.poll_timeout()).next();
+        #[cfg(feature = "sample-api")]
+        let jitter_buffer_at = self
+            .medias
+            .iter()
+            .filter_map(|m| m.depayload_timeout())
+            .min();
+        #[cfg(not(feature = "sample-api"))]
+        let jitter_buffer_at = None;
+        #[cfg(feature = "bwe")]
         let bwe_at = self.bwe.as_ref().map(|bwe| bwe.poll_timeout());
+        #[cfg(not(feature = "bwe"))]
+        let bwe_at = None;
         let paused_at = self.paused_at();
         let send_stream_at = self.streams.send_stream();
 
         (feedback_at, Reason::Feedback)
             .soonest((nack_at, Reason::Nack))
             .soonest((twcc_at, Reason::Twcc))
+            .soonest((ccfb_at, Reason::Ccfb))
             .soonest((pacing_at, Reason::Pacing))
             .soonest((packetize_at, Reason::Packetize))
+            .soonest((jitter_buffer_at, Reason::JitterBuffer))
             .soonest((bwe_at, Reason::Bwe))
             .soonest((paused_at, Reason::PauseCheck))
             .soonest((send_stream_at, Reason::SendStream))
@@ -746,7 +1288,11 @@ impl Session {
             return None;
         }
 
-        Some(self.last_nack + NACK_MIN_INTERVAL)
+        // Nacking more often than every half RTT only causes duplicate resends.
+        let half_rtt = self.rtt.smoothed().unwrap_or_default() / 2;
+        let interval = half_rtt.clamp(NACK_MIN_INTERVAL, NACK_MAX_INTERVAL);
+
+        Some(self.last_nack + interval)
     }
 
     fn twcc_at(&self) -> Option<Instant> {
@@ -765,9 +1311,27 @@ impl Session {
         }
     }
 
-    pub fn visit_stats(&mut self, now: Instant, snapshot: &mut StatsSnapshot) {
+    fn ccfb_at(&self) -> Option<Instant> {
+        let is_receiving = self.streams.is_receiving();
+        if is_receiving && self.enable_ccfb_feedback && self.ccfb_rx_register.has_unreported() {
+            Some(self.last_ccfb + TWCC_INTERVAL)
+        } else {
+            None
+        }
+    }
+
+    pub fn enable_ccfb_feedback(&mut self) {
+        if !self.enable_ccfb_feedback {
+            debug!("Enable CCFB feedback");
+            self.enable_ccfb_feedback = true;
+        }
+    }
+
+    /// Fill in the stats. With `advance`, the loss calculations start a new interval,
+    /// which is what the periodic stats events do.
+    pub fn visit_stats(&mut self, now: Instant, snapshot: &mut StatsSnapshot, advance: bool) {
         for stream in self.streams.streams_tx() {
-            stream.visit_stats(snapshot, now);
+            stream.visit_stats(snapshot, now, advance);
         }
 
         for stream in self.streams.streams_rx() {
@@ -775,13 +1339,22 @@ impl Session {
         }
 
         snapshot.tx = snapshot.egress.values().map(|s| s.bytes).sum();
+        snapshot.padding_tx = snapshot.egress.values().map(|s| s.bytes_padding).sum();
         snapshot.rx = snapshot.ingress.values().map(|s| s.bytes).sum();
-        snapshot.bwe_tx = self.bwe.as_ref().and_then(|bwe| bwe.last_estimate());
+        #[cfg(feature = "bwe")]
+        {
+            snapshot.bwe_tx = self.bwe.as_ref().and_then(|bwe| bwe.last_estimate());
+        }
 
         snapshot.egress_loss_fraction = self.twcc_tx_register.loss(Duration::from_secs(1), now);
-        snapshot.ingress_loss_fraction = self.twcc_rx_register.loss();
+        snapshot.ingress_loss_fraction = if advance {
+            self.twcc_rx_register.loss()
+        } else {
+            self.twcc_rx_register.peek_loss()
+        };
     }
 
+    #[cfg(feature = "bwe")]
     pub fn set_bwe_current_bitrate(&mut self, current_bitrate: Bitrate) {
         if let Some(bwe) = self.bwe.as_mut() {
             bwe.current_bitrate = current_bitrate;
@@ -789,6 +1362,7 @@ impl Session {
         }
     }
 
+    #[cfg(feature = "bwe")]
     pub fn set_bwe_desired_bitrate(&mut self, desired_bitrate: Bitrate) {
         if let Some(bwe) = self.bwe.as_mut() {
             bwe.desired_bitrate = desired_bitrate;
@@ -796,6 +1370,117 @@ impl Session {
         }
     }
 
+    pub fn set_mtu(&mut self, mtu: usize) {
+        self.mtu = mtu;
+        self.pool.set_buf_size(mtu);
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    pub fn recycle_send_buffer(&mut self, buf: Vec<u8>) {
+        self.pool.put(buf);
+    }
+
+    pub fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.pool.stats()
+    }
+
+    /// Add an RTT sample measured outside of RTCP, i.e. STUN.
+    pub fn add_rtt_sample(&mut self, now: Instant, rtt: Duration) {
+        self.rtt.update(now, rtt);
+    }
+
+    pub fn rtt_stats(&self) -> Option<RttStats> {
+        self.rtt.stats()
+    }
+
+    #[cfg(feature = "bwe")]
+    pub fn bwe_stats(&mut self, now: Instant) -> Option<BweStats> {
+        let bwe = self.bwe.as_ref()?;
+
+        let pacer_queue_delay = self
+            .streams
+            .streams_tx()
+            .map(|s| s.queue_delay(now))
+            .max()
+            .unwrap_or_default();
+
+        Some(
+            bwe.bwe
+                .stats(bwe.last_estimate(), bwe.remb_cap, pacer_queue_delay),
+        )
+    }
+
+    #[cfg(feature = "bwe")]
+    pub fn bwe_estimate(&self) -> Option<Bitrate> {
+        self.bwe.as_ref().and_then(|bwe| bwe.last_estimate())
+    }
+
+    #[cfg(feature = "bwe")]
+    pub fn set_bwe_bitrate_bounds(&mut self, min: Bitrate, max: Bitrate) {
+        if let Some(bwe) = self.bwe.as_mut() {
+            let (min, max) = ordered_bounds((min, max));
+            bwe.bounds = (min, max);
+            bwe.bwe.set_bitrate_bounds(min, max);
+            self.configure_pacer();
+        }
+    }
+
+    #[cfg(feature = "bwe")]
+    pub fn set_bitrate_allocator(&mut self, allocator: Option<BitrateAllocator>) {
+        if let Some(bwe) = self.bwe.as_mut() {
+            bwe.allocator = allocator;
+            // Emit all targets of the new allocator.
+            bwe.last_targets.clear();
+            bwe.pending_targets.clear();
+            bwe.need_allocate = true;
+        }
+    }
+
+    /// Allocate the last emitted estimate, and pause/resume simulcast layers to match.
+    #[cfg(feature = "bwe")]
+    fn poll_target_bitrate(&mut self) -> Option<TargetBitrate> {
+        let bwe = self.bwe.as_mut()?;
+
+        if bwe.need_allocate && bwe.last_emitted_estimate > Bitrate::ZERO {
+            bwe.need_allocate = false;
+
+            let targets = bwe
+                .allocator
+                .as_ref()
+                .map(|a| a.allocate(bwe.last_emitted_estimate))
+                .unwrap_or_default();
+
+            for target in targets {
+                let previous = bwe.last_targets.iter().position(|t| t.mid == target.mid);
+                if previous.map(|i| &bwe.last_targets[i]) == Some(&target) {
+                    continue;
+                }
+
+                for layer in &target.layers {
+                    // Only simulcast layers have a stream each.
+                    if layer.rid.is_none() {
+                        continue;
+                    }
+                    if let Some(stream) = self.streams.stream_tx_by_mid_rid(target.mid, layer.rid) {
+                        stream.set_paused(!layer.active);
+                    }
+                }
+
+                match previous {
+                    Some(i) => bwe.last_targets[i] = target.clone(),
+                    None => bwe.last_targets.push(target.clone()),
+                }
+                bwe.pending_targets.push_back(target);
+            }
+        }
+
+        bwe.pending_targets.pop_front()
+    }
+
+    #[cfg(feature = "bwe")]
     pub fn reset_bwe(&mut self, init_bitrate: Bitrate) {
         if let Some(bwe) = self.bwe.as_mut() {
             bwe.reset(init_bitrate);
@@ -819,15 +1504,18 @@ impl Session {
         self.streams.remove_streams_by_mid(mid);
     }
 
+    #[cfg(feature = "bwe")]
     fn configure_pacer(&mut self) {
         let Some(bwe) = self.bwe.as_ref() else {
             return;
         };
 
-        let padding_rate = bwe
-            .last_estimate()
-            .map(|estimate| estimate.min(bwe.desired_bitrate))
-            .unwrap_or(Bitrate::ZERO);
+        // While probing, we pad up to the probe target to discover more capacity.
+        let padding_rate = bwe.probe_target.unwrap_or_else(|| {
+            bwe.last_estimate()
+                .map(|estimate| estimate.min(bwe.desired_bitrate))
+                .unwrap_or(Bitrate::ZERO)
+        });
 
         self.pacer.set_padding_rate(padding_rate);
 
@@ -849,9 +1537,10 @@ impl Session {
         self.medias.iter_mut().find(|m| m.mid() == mid)
     }
 
+    #[cfg(feature = "sample-api")]
     fn do_payload(&mut self, now: Instant) -> Result<(), RtcError> {
         for m in &mut self.medias {
-            m.do_payload(now, &mut self.streams, &self.codec_config)?;
+            m.do_payload(now, &mut self.streams, &self.codec_config, self.mtu)?;
         }
 
         Ok(())
@@ -879,6 +1568,7 @@ impl Session {
         true
     }
 
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
     pub fn is_request_keyframe_possible(&self, kind: KeyframeRequestKind) -> bool {
         // TODO: It's possible to have different set of feedback enabled for different
         // payload types. I.e. we could have FIR enabled for H264, but not for VP8.
@@ -891,14 +1581,46 @@ impl Session {
     }
 }
 
+#[cfg(feature = "bwe")]
 struct Bwe {
     bwe: SendSideBandwithEstimator,
     desired_bitrate: Bitrate,
     current_bitrate: Bitrate,
+    /// Padding rate while a probe cluster is active.
+    probe_target: Option<Bitrate>,
+    /// Last incoming REMB. Caps the estimate, or replaces it if there is no TWCC.
+    remb_cap: Option<Bitrate>,
+    /// Min and max bitrate of the estimate.
+    bounds: (Bitrate, Bitrate),
+    /// Amount of deviation needed to emit a new BWE value. This is to reduce
+    /// the total number BWE events to only fire when there is a substantial change.
+    estimate_tolerance: f64,
+    /// Distributes the emitted estimate across the media.
+    allocator: Option<BitrateAllocator>,
+    /// Set when the estimate or the allocator changed.
+    need_allocate: bool,
+    /// Last emitted target per mid, to only emit changes.
+    last_targets: Vec<TargetBitrate>,
+    pending_targets: VecDeque<TargetBitrate>,
 
     last_emitted_estimate: Bitrate,
 }
 
+/// Swap the bounds if min is larger than max, rather than panic when clamping.
+#[cfg(feature = "bwe")]
+fn ordered_bounds((min, max): (Bitrate, Bitrate)) -> (Bitrate, Bitrate) {
+    if min > max {
+        warn!(
+            "BWE bitrate bounds min {} is larger than max {}, swap them",
+            min, max
+        );
+        (max, min)
+    } else {
+        (min, max)
+    }
+}
+
+#[cfg(feature = "bwe")]
 impl Bwe {
     fn handle_timeout(&mut self, now: Instant) {
         self.bwe.handle_timeout(now);
@@ -906,6 +1628,7 @@ impl Bwe {
 
     pub fn reset(&mut self, init_bitrate: Bitrate) {
         self.bwe = SendSideBandwithEstimator::new(init_bitrate);
+        self.bwe.set_bitrate_bounds(self.bounds.0, self.bounds.1);
     }
 
     pub fn update<'t>(
@@ -917,13 +1640,19 @@ impl Bwe {
     }
 
     fn poll_estimate(&mut self) -> Option<Bitrate> {
-        let estimate = self.bwe.last_estimate()?;
+        let estimate = self.last_estimate()?;
 
-        let min = self.last_emitted_estimate * (1.0 - ESTIMATE_TOLERANCE);
-        let max = self.last_emitted_estimate * (1.0 + ESTIMATE_TOLERANCE);
+        let min = self.last_emitted_estimate * (1.0 - self.estimate_tolerance);
+        let max = self.last_emitted_estimate * (1.0 + self.estimate_tolerance);
 
         if estimate < min || estimate > max {
+            debug!(
+                %estimate,
+                previous = %self.last_emitted_estimate,
+                "BWE estimate update"
+            );
             self.last_emitted_estimate = estimate;
+            self.need_allocate = true;
             Some(estimate)
         } else {
             // Estimate is within tolerances.
@@ -936,7 +1665,11 @@ impl Bwe {
     }
 
     fn last_estimate(&self) -> Option<Bitrate> {
-        self.bwe.last_estimate()
+        match (self.bwe.last_estimate(), self.remb_cap) {
+            (Some(estimate), Some(cap)) => Some(estimate.min(cap)),
+            // Without TWCC feedback, fall back on the remote's REMB.
+            (estimate, cap) => estimate.or(cap),
+        }
     }
 }
 
@@ -947,6 +1680,44 @@ pub struct PacketReceipt {
     pub payload_size: usize,
 }
 
+/// RTT sample from the DLSR of a receiver report or the DLRR of an extended report.
+fn update_rtt_from_rtcp(rtt: &mut RttEstimator, now: Instant, fb: &RtcpFb) {
+    let (delay, last_report) = match fb {
+        RtcpFb::ReceptionReport(r) => (r.last_sr_delay, r.last_sr_time),
+        RtcpFb::DlrrItem(d) => (d.last_rr_delay, d.last_rr_time),
+        _ => return,
+    };
+
+    let Some(ms) = calculate_rtt_ms(now.to_ntp_duration(), delay, last_report) else {
+        return;
+    };
+
+    rtt.update(now, Duration::from_secs_f32(ms / 1000.0));
+}
+
+/// Queue a copy of a packet for the tap, dropping the oldest if nobody polls them.
+fn tap_packet(
+    tap: &mut Option<VecDeque<TappedPacket>>,
+    now: Instant,
+    kind: TappedKind,
+    data: impl FnOnce() -> Vec<u8>,
+) {
+    let Some(queue) = tap else {
+        return;
+    };
+
+    if queue.len() >= MAX_TAPPED_PACKETS {
+        trace!("Packet tap full, drop oldest");
+        queue.pop_front();
+    }
+
+    queue.push_back(TappedPacket {
+        timestamp: now,
+        kind,
+        data: data(),
+    });
+}
+
 /// Find the PayloadParams for the given Pt, either when the Pt is the main Pt for the Codec or
 /// when it's the RTX Pt.
 fn main_payload_params(c: &CodecConfig, pt: Pt) -> Option<&PayloadParams> {
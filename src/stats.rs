@@ -1,12 +1,21 @@
 //! Statistics events.
+//!
+//! With the `serde` feature, the stats types implement `Serialize`. Fields of type
+//! `Instant` are skipped, since they have no meaning outside the process.
 
 use std::{
     collections::{HashMap, VecDeque},
-    time::{Duration, Instant},
+    time::Duration,
 };
 
+use std::net::SocketAddr;
+
+#[cfg(feature = "sctp")]
+use crate::channel::ChannelId;
+use crate::net::Protocol;
 use crate::rtp_::{Mid, Rid};
-use crate::Bitrate;
+use crate::util::Instant;
+use crate::{Bitrate, IceConnectionState};
 
 pub(crate) struct Stats {
     last_now: Option<Instant>,
@@ -19,6 +28,8 @@ pub(crate) struct StatsSnapshot {
     pub peer_rx: u64,
     pub tx: u64,
     pub rx: u64,
+    pub padding_tx: u64,
+    pub datagrams_rx: DatagramCounts,
     pub egress_loss_fraction: Option<f32>,
     pub ingress_loss_fraction: Option<f32>,
     pub ingress: HashMap<(Mid, Option<Rid>), MediaIngressStats>,
@@ -34,6 +45,8 @@ impl StatsSnapshot {
             peer_tx: 0,
             tx: 0,
             rx: 0,
+            padding_tx: 0,
+            datagrams_rx: DatagramCounts::default(),
             egress_loss_fraction: None,
             ingress_loss_fraction: None,
             ingress: HashMap::new(),
@@ -44,6 +57,23 @@ impl StatsSnapshot {
     }
 }
 
+impl StatsSnapshot {
+    pub(crate) fn peer_stats(&self) -> PeerStats {
+        PeerStats {
+            peer_bytes_rx: self.peer_rx,
+            peer_bytes_tx: self.peer_tx,
+            bytes_rx: self.rx,
+            bytes_tx: self.tx,
+            padding_bytes_tx: self.padding_tx,
+            datagrams_rx: self.datagrams_rx.clone(),
+            timestamp: self.timestamp,
+            bwe_tx: self.bwe_tx,
+            egress_loss_fraction: self.egress_loss_fraction,
+            ingress_loss_fraction: self.ingress_loss_fraction,
+        }
+    }
+}
+
 // Output events
 
 #[derive(Debug, Clone)]
@@ -57,6 +87,7 @@ pub(crate) enum StatsEvent {
 ///
 /// This event is generated roughly every second
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PeerStats {
     /// Total bytes transmitted.
     pub peer_bytes_rx: u64,
@@ -66,7 +97,14 @@ pub struct PeerStats {
     pub bytes_rx: u64,
     /// Total bytes received, only counting media traffic (rtp payload).
     pub bytes_tx: u64,
+    /// Total padding bytes transmitted for bandwidth probing (rtp payload).
+    ///
+    /// Not included in `bytes_tx`.
+    pub padding_bytes_tx: u64,
+    /// Counts of received datagrams per class.
+    pub datagrams_rx: DatagramCounts,
     /// Timestamp when this event was generated.
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub timestamp: Instant,
     /// The last egress bandwidth estimate from the BWE subsystem, if enabled.
     pub bwe_tx: Option<Bitrate>,
@@ -76,10 +114,32 @@ pub struct PeerStats {
     pub ingress_loss_fraction: Option<f32>,
 }
 
+/// Counts of datagrams by class, as demultiplexed on the first byte (RFC 7983).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DatagramCounts {
+    /// STUN messages.
+    pub stun: u64,
+    /// DTLS records.
+    pub dtls: u64,
+    /// RTP packets.
+    pub rtp: u64,
+    /// RTCP packets.
+    pub rtcp: u64,
+    /// ZRTP packets. These are dropped.
+    pub zrtp: u64,
+    /// TURN channel data. These are dropped, since TURN is unwrapped before `Rtc`.
+    pub turn_channel: u64,
+    /// DTLS, RTP and RTCP dropped for coming from an address not in a verified
+    /// ICE candidate pair. Also counted in their class.
+    pub unverified: u64,
+}
+
 /// Outgoing media statistics in [`Event::MediaEgressStats`][crate::Event::MediaEgressStats].
 ///
 /// note: when simulcast is disabled, `rid` is `None`
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MediaEgressStats {
     /// The identifier of the media these stats are for.
     pub mid: Mid,
@@ -97,25 +157,233 @@ pub struct MediaEgressStats {
     ///
     /// [1]: https://www.w3.org/TR/webrtc-stats/#dom-rtcsentrtpstreamstats-packetssent
     pub packets: u64,
+    /// Total padding bytes sent for bandwidth probing. Not included in `bytes`.
+    pub bytes_padding: u64,
+    /// Total padding packets sent for bandwidth probing. Not included in `packets`.
+    pub packets_padding: u64,
     /// Number of firs received.
     pub firs: u64,
     /// Number of plis received.
     pub plis: u64,
     /// Number of nacks received.
     pub nacks: u64,
+    /// Number of packets requested by the nacks.
+    pub nacked_packets: u64,
+    /// Number of packets requested by the nacks that were still available for resending.
+    pub nacked_packets_cached: u64,
     /// Round-trip-time (ms) extracted from the last RTCP receiver report.
     pub rtt: Option<f32>,
     /// Fraction of packets lost averaged from the RTCP receiver reports received.
     /// `None` if no reports have been received since the last event
     pub loss: Option<f32>,
     /// Timestamp when this event was generated
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub timestamp: Instant,
     // TODO
     // pub remote: RemoteIngressStats,
 }
 
+/// A snapshot of all statistics, obtained via [`Rtc::stats()`][crate::Rtc::stats].
+///
+/// Modeled on the WebRTC `getStats()` API. Each entry has a stable identifier,
+/// `(mid, rid)` for media and `ChannelId` for data channels, so the same
+/// entry can be followed across snapshots.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RtcStats {
+    /// Timestamp when this snapshot was taken.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub timestamp: Instant,
+    /// Transport level stats.
+    pub peer: PeerStats,
+    /// Stats for incoming media, `inbound-rtp` in getStats.
+    pub ingress: Vec<MediaIngressStats>,
+    /// Stats for outgoing media, `outbound-rtp` in getStats.
+    ///
+    /// The `rtt` and `loss` fields are as reported by the remote peer in RTCP
+    /// receiver reports, `remote-inbound-rtp` in getStats.
+    pub egress: Vec<MediaEgressStats>,
+    /// The candidate pair currently used for sending, if any.
+    pub candidate_pair: Option<CandidatePairStats>,
+    /// Stats for open data channels.
+    #[cfg(feature = "sctp")]
+    pub channels: Vec<ChannelStats>,
+    /// Internals of the bandwidth estimation, if enabled.
+    pub bwe: Option<BweStats>,
+    /// Round trip time of the transport, if measured.
+    pub rtt: Option<RttStats>,
+}
+
+/// Round trip time of the transport.
+///
+/// Combines samples from STUN binding requests on the nominated pair and the RTCP
+/// DLSR (receiver reports) and DLRR (extended reports) calculations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RttStats {
+    /// Smoothed RTT, as the SRTT of RFC 6298.
+    pub smoothed: Duration,
+    /// The latest sample.
+    pub latest: Duration,
+    /// The smallest sample in the last 10 seconds.
+    pub min: Duration,
+    /// The largest sample in the last 10 seconds.
+    pub max: Duration,
+}
+
+/// Usage of the internal pool of buffers for outgoing SRTP and SRTCP packets.
+///
+/// In steady state, buffers are returned via [`Rtc::recycle_transmit()`][crate::Rtc::recycle_transmit]
+/// and the pool has close to 100% hit rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BufferPoolStats {
+    /// Number of buffers taken from the pool.
+    pub hits: u64,
+    /// Number of buffers that had to be allocated since the pool was empty.
+    pub misses: u64,
+    /// Number of buffers currently in the pool.
+    pub available: usize,
+}
+
+impl BufferPoolStats {
+    /// Fraction of buffers that were taken from the pool, `0.0..=1.0`.
+    pub fn hit_rate(&self) -> f32 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f32 / total as f32
+    }
+}
+
+/// Internal state of the bandwidth estimation (BWE) for debugging.
+///
+/// The values are meant for analyzing congestion issues, and the exact meaning
+/// can change with the BWE implementation.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BweStats {
+    /// The combined estimate, as emitted in [`Event::EgressBitrateEstimate`][crate::Event::EgressBitrateEstimate].
+    pub estimate: Option<Bitrate>,
+    /// The delay based estimate.
+    pub delay_estimate: Bitrate,
+    /// The loss based estimate.
+    pub loss_estimate: Bitrate,
+    /// The last loss fraction used by the loss based estimate.
+    pub loss_fraction: Option<f32>,
+    /// The bitrate acknowledged by the remote peer in TWCC feedback.
+    pub acked_bitrate: Option<Bitrate>,
+    /// The slope of the delay trendline. A positive slope means queues are building up.
+    pub trendline_slope: f64,
+    /// The adaptive threshold for detecting overuse.
+    pub trendline_threshold: f64,
+    /// Whether the delay based estimator currently detects overuse.
+    pub overusing: bool,
+    /// The mean of the max RTT per TWCC report.
+    pub rtt: Option<Duration>,
+    /// Target and achieved bitrate of the last successful probe.
+    pub last_probe: Option<(Bitrate, Bitrate)>,
+    /// The bitrate cap from the last incoming REMB, if any.
+    pub remb_cap: Option<Bitrate>,
+    /// The longest time any queued packet has been waiting for the pacer.
+    pub pacer_queue_delay: Duration,
+}
+
+/// Stats for the candidate pair in use, `candidate-pair` in getStats.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CandidatePairStats {
+    /// The protocol used for the pair.
+    pub proto: Protocol,
+    /// The local socket address data is sent from.
+    pub local: SocketAddr,
+    /// The remote socket address data is sent to.
+    pub remote: SocketAddr,
+    /// The current ICE connection state.
+    ///
+    /// Changes are also signalled via
+    /// [`Event::IceConnectionStateChange`][crate::Event::IceConnectionStateChange].
+    pub state: IceConnectionState,
+    /// Number of packets sent on this pair, excluding STUN.
+    pub packets_sent: u64,
+    /// Number of bytes sent on this pair, excluding STUN.
+    pub bytes_sent: u64,
+    /// Number of packets received on this pair, excluding STUN.
+    pub packets_received: u64,
+    /// Number of bytes received on this pair, excluding STUN.
+    pub bytes_received: u64,
+    /// Round trip time of the last answered STUN binding request on this pair.
+    pub rtt: Option<Duration>,
+    /// The time of the last STUN binding response on this pair.
+    ///
+    /// This is the consent of the remote peer to receive data (RFC 7675). A consent
+    /// older than 30 seconds means the pair is no longer usable.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub last_consent: Option<Instant>,
+    /// Number of STUN binding requests sent on all pairs since the last ICE restart.
+    pub binding_requests_sent: u64,
+    /// Number of successful STUN binding responses to our requests received on all
+    /// pairs since the last ICE restart.
+    pub binding_responses_received: u64,
+    /// Number of STUN binding requests received on all pairs since the last ICE restart.
+    pub binding_requests_received: u64,
+}
+
+/// Stats for a data channel, `data-channel` in getStats.
+#[cfg(feature = "sctp")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChannelStats {
+    /// The identifier of the channel.
+    pub id: ChannelId,
+    /// The channel label.
+    pub label: String,
+    /// The channel sub-protocol.
+    pub protocol: String,
+    /// Number of messages sent.
+    pub messages_sent: u64,
+    /// Number of bytes sent, counting the message payloads.
+    pub bytes_sent: u64,
+    /// Number of messages received.
+    pub messages_received: u64,
+    /// Number of bytes received, counting the message payloads.
+    pub bytes_received: u64,
+}
+
+impl RtcStats {
+    pub(crate) fn new(
+        snapshot: StatsSnapshot,
+        candidate_pair: Option<CandidatePairStats>,
+        bwe: Option<BweStats>,
+        rtt: Option<RttStats>,
+    ) -> Self {
+        let peer = snapshot.peer_stats();
+
+        let mut ingress: Vec<_> = snapshot.ingress.into_values().collect();
+        let mut egress: Vec<_> = snapshot.egress.into_values().collect();
+
+        // Stable order between snapshots.
+        ingress.sort_by(|a, b| (a.mid, a.rid).cmp(&(b.mid, b.rid)));
+        egress.sort_by(|a, b| (a.mid, a.rid).cmp(&(b.mid, b.rid)));
+
+        RtcStats {
+            timestamp: snapshot.timestamp,
+            peer,
+            ingress,
+            egress,
+            candidate_pair,
+            #[cfg(feature = "sctp")]
+            channels: vec![],
+            bwe,
+            rtt,
+        }
+    }
+}
+
 /// Stats as reported by the remote side (via RTCP ReceiverReports).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RemoteIngressStats {
     /// Total bytes received.
     pub bytes_rx: u64,
@@ -125,6 +393,7 @@ pub struct RemoteIngressStats {
 ///
 /// note: when simulcast is disabled, `rid` is `None`
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MediaIngressStats {
     /// The identifier of the media these stats are for.
     pub mid: Mid,
@@ -145,6 +414,7 @@ pub struct MediaIngressStats {
     /// Fraction of packets lost extracted from the last RTCP receiver report.
     pub loss: Option<f32>,
     /// Timestamp when this event was generated.
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub timestamp: Instant,
     // TODO
     // pub remote: RemoteEgressStats,
@@ -186,6 +456,7 @@ impl MediaIngressStats {
 
 /// Stats as reported by the remote side (via RTCP SenderReports).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RemoteEgressStats {
     /// Total bytes transmitted.
     pub bytes_tx: u64,
@@ -205,6 +476,11 @@ impl Stats {
         }
     }
 
+    /// Change the interval, which takes effect from the last emitted stats.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
     /// Returns true if we want to handle the timeout
     ///
     /// The caller can use this to compute the snapshot only if needed, before calling [`Stats::do_handle_timeout`]
@@ -223,16 +499,7 @@ impl Stats {
     pub fn do_handle_timeout(&mut self, snapshot: &mut StatsSnapshot) {
         // enqueue stats and timestamp them so they can be sent out
 
-        let event = PeerStats {
-            peer_bytes_rx: snapshot.peer_rx,
-            peer_bytes_tx: snapshot.peer_tx,
-            bytes_rx: snapshot.rx,
-            bytes_tx: snapshot.tx,
-            timestamp: snapshot.timestamp,
-            bwe_tx: snapshot.bwe_tx,
-            egress_loss_fraction: snapshot.egress_loss_fraction,
-            ingress_loss_fraction: snapshot.ingress_loss_fraction,
-        };
+        let event = snapshot.peer_stats();
 
         self.events.push_back(StatsEvent::Peer(event));
 
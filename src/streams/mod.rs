@@ -1,43 +1,132 @@
 use std::collections::{HashMap, VecDeque};
 use std::fmt::{self};
 use std::time::Duration;
-use std::time::Instant;
+
+use bytes::Bytes;
 
 use crate::format::CodecConfig;
 use crate::format::PayloadParams;
 use crate::media::{KeyframeRequest, Media};
+use crate::rtp::Observer;
 use crate::rtp_::Ssrc;
 use crate::rtp_::{Bitrate, Pt};
 use crate::rtp_::{MediaTime, SenderInfo};
 use crate::rtp_::{Mid, Rid, SeqNo};
-use crate::rtp_::{Rtcp, RtpHeader};
-use crate::util::{already_happened, NonCryptographicRng};
+use crate::rtp_::{Remb, Rtcp, RtpHeader};
+use crate::util::Instant;
+use crate::util::{already_happened, not_happening, NonCryptographicRng};
+
+use self::remb::{RembEstimator, Totals};
 
-pub use self::receive::StreamRx;
-pub use self::send::StreamTx;
+pub use self::receive::{StreamRx, StreamRxStats};
+pub use self::send::{StreamTx, StreamTxStats};
 
 mod receive;
 pub(crate) mod register;
 pub(crate) mod register_nack;
+mod remb;
 mod rtx_cache;
 pub(crate) mod rtx_cache_buf;
 mod send;
 mod send_queue;
 
-pub(crate) use send::DEFAULT_RTX_CACHE_DURATION;
+pub(crate) use send::{DEFAULT_RTX_CACHE_DURATION, DEFAULT_RTX_CACHE_MAX_BYTES};
 
 // Time between regular receiver reports.
-// https://www.rfc-editor.org/rfc/rfc8829#section-5.1.2
-// Should technically be 4 seconds according to spec, but libWebRTC
-// expects video to be every second, and audio every 5 seconds.
-const RR_INTERVAL_VIDEO: Duration = Duration::from_millis(1000);
-const RR_INTERVAL_AUDIO: Duration = Duration::from_millis(5000);
-
-fn rr_interval(audio: bool) -> Duration {
-    if audio {
-        RR_INTERVAL_AUDIO
-    } else {
-        RR_INTERVAL_VIDEO
+/// Intervals between RTCP sender/receiver reports, and minimum between keyframe requests.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RtcpIntervals {
+    pub audio: Duration,
+    pub video: Duration,
+    pub keyframe_request: Duration,
+    pub bandwidth: Option<RtcpBandwidth>,
+}
+
+impl RtcpIntervals {
+    // https://www.rfc-editor.org/rfc/rfc8829#section-5.1.2
+    // Should technically be 4 seconds according to spec, but libWebRTC
+    // expects video to be every second, and audio every 5 seconds.
+    pub const DEFAULT_VIDEO: Duration = Duration::from_millis(1000);
+    pub const DEFAULT_AUDIO: Duration = Duration::from_millis(5000);
+    // Same as libWebRTC's minimum between keyframe requests.
+    pub const DEFAULT_KEYFRAME_REQUEST: Duration = Duration::from_millis(300);
+
+    fn get(&self, audio: bool, bitrate: Bitrate, sender: bool) -> Duration {
+        if let Some(bw) = &self.bandwidth {
+            return bw.interval(bitrate, sender);
+        }
+
+        if audio {
+            self.audio
+        } else {
+            self.video
+        }
+    }
+}
+
+/// RTCP report intervals derived from the media bitrate, as in RFC 3550 6.2.
+///
+/// The RTCP bandwidth is a `fraction` of the media bitrate of each stream. Of that,
+/// sender reports get the `sender_share` and receiver reports the rest. The resulting
+/// interval is clamped to `min_interval..=max_interval`.
+///
+/// Enabled via [`RtcConfig::set_rtcp_bandwidth()`][crate::RtcConfig::set_rtcp_bandwidth].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RtcpBandwidth {
+    /// Share of the media bitrate used for RTCP. Defaults to 5%.
+    pub fraction: f64,
+    /// Share of the RTCP bandwidth used by senders. Defaults to 25%.
+    pub sender_share: f64,
+    /// Shortest interval between reports. Defaults to 500 milliseconds.
+    pub min_interval: Duration,
+    /// Longest interval between reports. Defaults to 5 seconds.
+    pub max_interval: Duration,
+}
+
+impl RtcpBandwidth {
+    /// Size of a typical compound RTCP packet including UDP/IP headers.
+    const AVG_RTCP_SIZE: f64 = 150.0;
+
+    fn interval(&self, bitrate: Bitrate, sender: bool) -> Duration {
+        let share = if sender {
+            self.sender_share
+        } else {
+            1.0 - self.sender_share
+        };
+
+        let rtcp_bps = bitrate.as_f64() * self.fraction * share;
+
+        if rtcp_bps <= 0.0 {
+            return self.max_interval;
+        }
+
+        let secs = Self::AVG_RTCP_SIZE * 8.0 / rtcp_bps;
+
+        // Cap before converting, since huge values would overflow the Duration.
+        Duration::from_secs_f64(secs.min(self.max_interval.as_secs_f64()))
+            .clamp(self.min_interval, self.max_interval)
+    }
+}
+
+impl Default for RtcpBandwidth {
+    fn default() -> Self {
+        RtcpBandwidth {
+            fraction: 0.05,
+            sender_share: 0.25,
+            min_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Default for RtcpIntervals {
+    fn default() -> Self {
+        RtcpIntervals {
+            audio: Self::DEFAULT_AUDIO,
+            video: Self::DEFAULT_VIDEO,
+            keyframe_request: Self::DEFAULT_KEYFRAME_REQUEST,
+            bandwidth: None,
+        }
     }
 }
 
@@ -56,10 +145,16 @@ pub struct RtpPacket {
     pub time: MediaTime,
 
     /// Parsed RTP header.
+    ///
+    /// For incoming packets, the `mid` and `rid` extension values are filled in from the
+    /// [`StreamRx`] the packet was mapped to, if the sender omitted them.
     pub header: RtpHeader,
 
     /// RTP payload. This contains no header.
-    pub payload: Vec<u8>,
+    ///
+    /// Cloning the payload is cheap, it shares the data. This means a packet received in
+    /// rtp mode can be forwarded to several [`StreamTx`] without copying.
+    pub payload: Bytes,
 
     /// str0m server timestamp.
     ///
@@ -84,6 +179,7 @@ pub struct RtpPacket {
 ///
 /// This means the stream has not received any data for some time (default 1.5 seconds).
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StreamPaused {
     /// The main SSRC of the encoded stream that paused.
     pub ssrc: Ssrc,
@@ -98,6 +194,149 @@ pub struct StreamPaused {
     pub paused: bool,
 }
 
+/// Incoming encoded stream has a discontinuity.
+///
+/// The receive state (sequence number register, RTP/NTP mapping) for the stream has been
+/// reset. Downstream decoders should be flushed and typically need a new keyframe.
+#[derive(Debug)]
+pub struct StreamDiscontinuity {
+    /// The main SSRC of the encoded stream after the discontinuity.
+    pub ssrc: Ssrc,
+
+    /// The mid the encoded stream belongs to.
+    pub mid: Mid,
+
+    /// The rid, if the encoded stream has a rid.
+    pub rid: Option<Rid>,
+
+    /// What caused the discontinuity.
+    pub reason: DiscontinuityReason,
+}
+
+/// Incoming encoded stream was ended by the remote with an RTCP BYE.
+///
+/// The receive state (sequence number register, RTP/NTP mapping, stats) for the stream
+/// has been torn down. Should the remote reuse the SSRC, the stream starts over as new.
+#[derive(Debug)]
+pub struct StreamEnded {
+    /// The main SSRC of the encoded stream that ended.
+    pub ssrc: Ssrc,
+
+    /// The mid the encoded stream belongs to.
+    pub mid: Mid,
+
+    /// The rid, if the encoded stream has a rid.
+    pub rid: Option<Rid>,
+
+    /// The reason text in the BYE, if any.
+    pub reason: Option<String>,
+}
+
+/// Incoming encoded streams from the same remote endpoint.
+///
+/// Streams are grouped by the CNAME the remote sends in RTCP SDES. The streams in a group
+/// share a sender wallclock, which makes the group the sync context for lip-syncing
+/// audio and video. See [`StreamRx::sync_wallclock()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncGroup {
+    /// The CNAME shared by the streams.
+    pub cname: String,
+
+    /// The main SSRCs of the streams in the group.
+    pub ssrcs: Vec<Ssrc>,
+}
+
+/// What to do with incoming RTP/RTCP from SSRCs not bound to any media.
+///
+/// Set via [`RtcConfig::set_unknown_ssrc_policy()`][crate::RtcConfig::set_unknown_ssrc_policy].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum UnknownSsrcPolicy {
+    /// Drop the packets silently. This is the default.
+    #[default]
+    Drop,
+
+    /// Drop the packets, but emit [`Event::UnknownSsrc`][crate::Event::UnknownSsrc] the first
+    /// time an SSRC is seen. This lets an SFU late-bind the SSRC using
+    /// [`DirectApi::expect_stream_rx()`][crate::change::DirectApi::expect_stream_rx].
+    Event,
+
+    /// Hold RTP packets for up to the given duration, waiting for a packet with a MID/RID
+    /// header extension (or an SDP change) that binds the SSRC. Once bound, the held
+    /// packets are handled in order. RTCP is dropped.
+    Buffer(Duration),
+}
+
+/// Incoming RTP/RTCP from an SSRC not bound to any media.
+///
+/// See [`UnknownSsrcPolicy::Event`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownSsrc {
+    /// The unknown SSRC.
+    pub ssrc: Ssrc,
+
+    /// The mid header extension value, if the packet was RTP and had one.
+    pub mid: Option<Mid>,
+
+    /// The rid header extension value, if the packet was RTP and had one.
+    pub rid: Option<Rid>,
+
+    /// The payload type, if the packet was RTP.
+    pub pt: Option<Pt>,
+}
+
+/// The remote used one of our SSRCs, and we moved to a new one (RFC 3550 8.2).
+///
+/// A BYE is sent for the old SSRC. Applications signalling SSRCs out of band (e.g. when
+/// using the Direct API) might need to resignal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsrcCollision {
+    /// The mid of the outgoing stream that changed SSRC.
+    pub mid: Mid,
+
+    /// The rid, if the outgoing stream has a rid.
+    pub rid: Option<Rid>,
+
+    /// The SSRC in collision, main or RTX.
+    pub old: Ssrc,
+
+    /// The SSRC used instead.
+    pub new: Ssrc,
+}
+
+/// Outgoing encoded stream changed whether it accepts writes.
+///
+/// See [`StreamTx::set_queue_delay_budget()`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StreamWritable {
+    /// The main SSRC of the encoded stream.
+    pub ssrc: Ssrc,
+
+    /// The mid the encoded stream belongs to.
+    pub mid: Mid,
+
+    /// The rid, if the encoded stream has a rid.
+    pub rid: Option<Rid>,
+
+    /// Whether the stream accepts writes or not.
+    pub writable: bool,
+}
+
+/// Cause of a [`StreamDiscontinuity`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DiscontinuityReason {
+    /// The remote changed the main SSRC of the stream.
+    SsrcChanged {
+        /// The SSRC used before the change.
+        previous: Ssrc,
+    },
+
+    /// The sequence numbers jumped too far to be packet loss or reordering.
+    SequenceJump,
+}
+
 /// 255 is out of range for a real PT, which is 7 bit.
 const BLANK_PACKET_DEFAULT_PT: Pt = Pt::new_with_value(255);
 
@@ -110,7 +349,7 @@ impl RtpPacket {
                 payload_type: BLANK_PACKET_DEFAULT_PT,
                 ..Default::default()
             },
-            payload: vec![], // This payload is never used. See RtpHeader::create_padding_packet
+            payload: Bytes::new(), // This payload is never used. See RtpHeader::create_padding_packet
             nackable: false,
             last_sender_info: None,
             timestamp: already_happened(),
@@ -148,6 +387,12 @@ pub(crate) struct Streams {
     /// Whether nack reports are enabled. This is an optimization to avoid too frequent
     /// Session::nack_at() when we don't need to send nacks.
     any_nack_active: Option<bool>,
+
+    /// Intervals between sender/receiver reports, handed to each new stream.
+    rtcp_intervals: RtcpIntervals,
+
+    /// Receive side estimate sent as REMB, if enabled.
+    remb: Option<RembEstimator>,
 }
 
 /// Delay between cleaning up the RxLookup.
@@ -163,6 +408,15 @@ struct RxLookup {
     last_used: Instant,
 }
 
+impl Streams {
+    pub(crate) fn new(rtcp_intervals: RtcpIntervals) -> Self {
+        Self {
+            rtcp_intervals,
+            ..Default::default()
+        }
+    }
+}
+
 impl Default for Streams {
     fn default() -> Self {
         Self {
@@ -173,6 +427,8 @@ impl Default for Streams {
             default_ssrc_tx: 0.into(), // this will be changed
             mids_to_report: Vec::with_capacity(10),
             any_nack_active: None,
+            rtcp_intervals: RtcpIntervals::default(),
+            remb: None,
         }
     }
 }
@@ -253,6 +509,9 @@ impl Streams {
         media: &mut Media,
         payload: PayloadParams,
     ) {
+        #[cfg(not(feature = "sample-api"))]
+        let _ = media;
+
         let maybe_stream = self.stream_rx_by_mid_rid(mid, rid);
 
         if let Some(stream) = maybe_stream {
@@ -267,6 +526,7 @@ impl Streams {
                 // When the SSRCs changes the sequence number typically also does, the
                 // depayloader(if in use) relies on sequence numbers and will not handle a
                 // large jump corretly, reset it.
+                #[cfg(feature = "sample-api")]
                 media.reset_depayloader(payload.pt(), rid);
             }
 
@@ -296,10 +556,11 @@ impl Streams {
         // New stream might have enabled nacks.
         self.any_nack_active = None;
 
+        let rtcp_intervals = self.rtcp_intervals;
         let stream = self
             .streams_rx
             .entry(ssrc)
-            .or_insert_with(|| StreamRx::new(ssrc, mid, rid, suppress_nack));
+            .or_insert_with(|| StreamRx::new(ssrc, mid, rid, suppress_nack, rtcp_intervals));
 
         if let Some(rtx) = rtx {
             stream.maybe_reset_rtx(rtx);
@@ -324,9 +585,10 @@ impl Streams {
         mid: Mid,
         rid: Option<Rid>,
     ) -> &mut StreamTx {
+        let rtcp_intervals = self.rtcp_intervals;
         self.streams_tx
             .entry(ssrc)
-            .or_insert_with(|| StreamTx::new(ssrc, rtx, mid, rid))
+            .or_insert_with(|| StreamTx::new(ssrc, rtx, mid, rid, rtcp_intervals))
     }
 
     pub fn remove_stream_tx(&mut self, ssrc: Ssrc) -> bool {
@@ -376,8 +638,63 @@ impl Streams {
 
     pub(crate) fn regular_feedback_at(&self) -> Option<Instant> {
         let r = self.streams_rx.values().map(|s| s.receiver_report_at());
+        let k = self
+            .streams_rx
+            .values()
+            .filter_map(|s| s.keyframe_request_at());
         let s = self.streams_tx.values().map(|s| s.sender_report_at());
-        r.chain(s).min()
+        let remb = self.remb_at();
+        r.chain(k).chain(s).chain(remb).min()
+    }
+
+    fn remb_at(&self) -> Option<Instant> {
+        if !self.is_receiving() {
+            return None;
+        }
+        let remb = self.remb.as_ref()?;
+        // The first update just records the totals to start from.
+        Some(remb.poll_timeout().unwrap_or_else(already_happened))
+    }
+
+    pub(crate) fn enable_remb_feedback(&mut self) {
+        if self.remb.is_none() {
+            debug!("Enable REMB feedback");
+            self.remb = Some(RembEstimator::default());
+        }
+    }
+
+    fn maybe_create_remb(
+        &mut self,
+        now: Instant,
+        sender_ssrc: Ssrc,
+        feedback: &mut VecDeque<Rtcp>,
+    ) {
+        if now < self.remb_at().unwrap_or(not_happening()) {
+            return;
+        }
+
+        let totals = self.streams_rx.values().fold(Totals::default(), |t, s| {
+            let stats = s.stats();
+            Totals {
+                at: None,
+                bytes: t.bytes + stats.bytes,
+                packets: t.packets + stats.packets,
+                lost: t.lost + stats.packets_lost,
+            }
+        });
+
+        // Unwrap is OK since remb_at() is None without an estimator.
+        let Some(bitrate) = self.remb.as_mut().unwrap().update(now, totals) else {
+            return;
+        };
+
+        // One REMB for the whole session, covering all incoming SSRCs.
+        feedback.push_back(Rtcp::Remb(Remb {
+            sender_ssrc,
+            ssrc: 0.into(),
+            bitrate: bitrate.as_f64() as f32,
+            ssrcs: self.streams_rx.keys().copied().collect(),
+        }));
     }
 
     pub(crate) fn paused_at(&self) -> Option<Instant> {
@@ -396,6 +713,7 @@ impl Streams {
         !self.streams_rx.is_empty()
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn handle_timeout(
         &mut self,
         now: Instant,
@@ -404,6 +722,7 @@ impl Streams {
         medias: &[Media],
         config: &CodecConfig,
         feedback: &mut VecDeque<Rtcp>,
+        observer: Option<&Observer>,
     ) {
         self.mids_to_report.clear(); // Clear for checking StreamRx.
         for stream in self.streams_rx.values() {
@@ -413,7 +732,7 @@ impl Streams {
         }
 
         for stream in self.streams_rx.values_mut() {
-            stream.maybe_create_keyframe_request(sender_ssrc, feedback);
+            stream.maybe_create_keyframe_request(now, sender_ssrc, feedback);
             stream.maybe_create_remb_request(sender_ssrc, feedback);
 
             // All StreamRx belonging to the same Mid are reported together.
@@ -428,6 +747,8 @@ impl Streams {
             stream.handle_timeout(now);
         }
 
+        self.maybe_create_remb(now, sender_ssrc, feedback);
+
         self.mids_to_report.clear(); // start over for StreamTx.
         for stream in self.streams_tx.values() {
             if stream.need_sr(now) {
@@ -438,6 +759,8 @@ impl Streams {
         for stream in self.streams_tx.values_mut() {
             let mid = stream.mid();
 
+            stream.maybe_create_goodbye(feedback);
+
             // All StreamTx belonging to the same Mid are reported together.
             if self.mids_to_report.contains(&mid) {
                 stream.create_sr_and_update(now, feedback);
@@ -448,7 +771,7 @@ impl Streams {
             // The unwrap is okay because we cannot have StreamTx with a Mid without the corresponding Media.
             let get_media = move || (medias.iter().find(|m| m.mid() == mid).unwrap(), config);
 
-            stream.handle_timeout(now, get_media);
+            stream.handle_timeout(now, get_media, observer);
         }
 
         if now > self.rx_lookup_at() {
@@ -478,6 +801,47 @@ impl Streams {
         self.streams_rx.values_mut().find_map(|s| s.poll_paused())
     }
 
+    pub(crate) fn poll_stream_discontinuity(&mut self) -> Option<StreamDiscontinuity> {
+        self.streams_rx
+            .values_mut()
+            .find_map(|s| s.poll_discontinuity())
+    }
+
+    pub(crate) fn sync_groups(&self) -> Vec<SyncGroup> {
+        let mut groups: Vec<SyncGroup> = vec![];
+
+        for s in self.streams_rx.values() {
+            let Some(cname) = s.cname() else {
+                continue;
+            };
+
+            if let Some(g) = groups.iter_mut().find(|g| g.cname == cname) {
+                g.ssrcs.push(s.ssrc());
+            } else {
+                groups.push(SyncGroup {
+                    cname: cname.to_string(),
+                    ssrcs: vec![s.ssrc()],
+                });
+            }
+        }
+
+        // Stable order regardless of the HashMap.
+        for g in &mut groups {
+            g.ssrcs.sort();
+        }
+        groups.sort_by(|a, b| a.cname.cmp(&b.cname));
+
+        groups
+    }
+
+    pub(crate) fn poll_stream_ended(&mut self) -> Option<StreamEnded> {
+        self.streams_rx.values_mut().find_map(|s| s.poll_ended())
+    }
+
+    pub(crate) fn poll_stream_writable(&mut self) -> Option<StreamWritable> {
+        self.streams_tx.values_mut().find_map(|s| s.poll_writable())
+    }
+
     pub(crate) fn has_stream_rx(&self, ssrc: Ssrc) -> bool {
         self.streams_rx.contains_key(&ssrc)
     }
@@ -486,6 +850,32 @@ impl Streams {
         self.streams_tx.contains_key(&ssrc)
     }
 
+    /// Whether the SSRC is used by us, either as main or RTX.
+    pub(crate) fn is_local_ssrc(&self, ssrc: Ssrc) -> bool {
+        self.has_stream_tx(ssrc) || self.streams_tx.values().any(|s| s.rtx() == Some(ssrc))
+    }
+
+    /// Move our use of an SSRC (main or RTX) to a new SSRC.
+    pub(crate) fn change_stream_tx_ssrc(
+        &mut self,
+        from: Ssrc,
+        to: Ssrc,
+    ) -> Option<(Mid, Option<Rid>)> {
+        if let Some(mut stream) = self.streams_tx.remove(&from) {
+            stream.change_ssrc(to);
+            let ret = (stream.mid(), stream.rid());
+            self.streams_tx.insert(to, stream);
+            return Some(ret);
+        }
+
+        let stream = self
+            .streams_tx
+            .values_mut()
+            .find(|s| s.rtx() == Some(from))?;
+        stream.change_rtx(to);
+        Some((stream.mid(), stream.rid()))
+    }
+
     pub(crate) fn streams_rx(&mut self) -> impl Iterator<Item = &mut StreamRx> {
         self.streams_rx.values_mut()
     }
@@ -590,6 +980,18 @@ impl Streams {
         self.rx_lookup.retain(|_, v| v.mid != mid);
     }
 
+    /// The tx stream of a mid to generate padding on.
+    ///
+    /// Padding is always sent on the RTX SSRC, so only streams with RTX qualify. With
+    /// simulcast, this picks the layer that most recently sent something, which keeps
+    /// the padding on an active layer.
+    pub(crate) fn stream_tx_for_padding(&mut self, mid: Mid) -> Option<&mut StreamTx> {
+        self.streams_tx
+            .values_mut()
+            .filter(|s| s.mid() == mid && s.padding_enabled() && !s.is_paused())
+            .max_by_key(|s| s.last_used())
+    }
+
     /// An iterator over all the tx streams for a given mid.
     pub(crate) fn streams_tx_by_mid(&mut self, mid: Mid) -> impl Iterator<Item = &mut StreamTx> {
         self.streams_tx.values_mut().filter(move |s| s.mid() == mid)
@@ -684,3 +1086,26 @@ impl fmt::Debug for RtpPacket {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rtcp_bandwidth_interval() {
+        let bw = RtcpBandwidth::default();
+
+        // 5% of 32kbps is 1600bps, of which receivers get 75%.
+        let rr = bw.interval(Bitrate::kbps(32), false);
+        assert_eq!(rr, Duration::from_secs(1));
+
+        // Senders get 25%, which is 3 seconds at 32kbps.
+        let sr = bw.interval(Bitrate::kbps(32), true);
+        assert_eq!(sr, Duration::from_secs(3));
+
+        // High bitrates are clamped to min, low to max.
+        assert_eq!(bw.interval(Bitrate::mbps(5), true), bw.min_interval);
+        assert_eq!(bw.interval(Bitrate::kbps(5), false), bw.max_interval);
+        assert_eq!(bw.interval(Bitrate::ZERO, false), bw.max_interval);
+    }
+}
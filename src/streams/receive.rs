@@ -1,7 +1,7 @@
 use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use crate::media::KeyframeRequestKind;
+use crate::media::{KeyframeRequestKind, SvcTarget};
 use crate::rtp_::{
     extend_u32, Bitrate, DlrrItem, ExtendedReport, Fir, FirEntry, Frequency, MediaTime, Remb,
 };
@@ -9,12 +9,14 @@ use crate::rtp_::{Mid, Pli, Pt, ReceiverReport};
 use crate::rtp_::{ReportBlock, ReportList, Rid, Rrtr, Rtcp, RtcpFb, RtpHeader, SenderInfo, SeqNo};
 use crate::rtp_::{SdesType, Ssrc};
 use crate::stats::{MediaIngressStats, StatsSnapshot};
+use crate::util::value_history::ValueHistory;
+use crate::util::Instant;
 use crate::util::InstantExt;
 use crate::util::{already_happened, calculate_rtt_ms};
 
 use super::register::ReceiverRegister;
-use super::StreamPaused;
-use super::{rr_interval, RtpPacket};
+use super::{DiscontinuityReason, StreamDiscontinuity, StreamEnded, StreamPaused};
+use super::{RtcpIntervals, RtpPacket};
 
 /// Incoming encoded stream.
 ///
@@ -80,6 +82,9 @@ pub struct StreamRx {
     /// If we have a pending keyframe request to send.
     pending_request_keyframe: Option<KeyframeRequestKind>,
 
+    /// Last time we sent a keyframe request.
+    last_keyframe_request: Instant,
+
     /// If we have a pending REMB request to send.
     pending_request_remb: Option<Bitrate>,
 
@@ -89,6 +94,12 @@ pub struct StreamRx {
     /// Last time we produced regular feedback RR.
     last_receiver_report: Instant,
 
+    /// Bytes received over the last second. For RTCP bandwidth based RR intervals.
+    bytes_received: ValueHistory<u64>,
+
+    /// Time between RR.
+    rtcp_intervals: RtcpIntervals,
+
     /// Statistics of incoming data.
     stats: StreamRxStats,
 
@@ -108,29 +119,72 @@ pub struct StreamRx {
 
     /// The configured threshold before considering the lack of packets as going into paused.
     pause_threshold: Duration,
+
+    /// The SVC layer to narrow incoming media to (sample level API).
+    svc_target: Option<SvcTarget>,
+
+    /// A sequence number that was too far behind the max. If the next packet follows
+    /// this one, we consider the remote to have restarted the sequence.
+    seq_jump_probation: Option<SeqNo>,
+
+    /// Whether we need to emit a discontinuity event.
+    need_discontinuity_event: Option<DiscontinuityReason>,
+
+    /// Whether we need to emit an ended event, with the BYE reason.
+    need_ended_event: Option<Option<String>>,
 }
 
-/// Holder of stats.
-#[derive(Debug, Default)]
-pub(crate) struct StreamRxStats {
-    /// count of bytes received, including retransmissions
-    bytes: u64,
-    /// count of packets received, including retransmissions
-    packets: u64,
-    /// count of FIR requests sent
-    firs: u64,
-    /// count of PLI requests sent
-    plis: u64,
-    /// count of NACKs sent
-    nacks: u64,
-    /// round trip time (ms) from the last DLRR, if any
-    rtt: Option<f32>,
-    /// fraction of packets lost from the last RR, if any
-    loss: Option<f32>,
+/// Forward jump in sequence numbers considered a discontinuity (RFC 3550 MAX_DROPOUT).
+const MAX_DROPOUT: u64 = 3000;
+
+/// Backward jump in sequence numbers considered a discontinuity (RFC 3550 MAX_MISORDER).
+const MAX_MISORDER: u64 = 100;
+
+/// Statistics for a [`StreamRx`], obtained via [`StreamRx::stats()`].
+///
+/// These are continuously updated as packets arrive. Frame level counters
+/// (frames assembled, keyframes) are not tracked here since frames are
+/// assembled per media, not per SSRC.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StreamRxStats {
+    /// Count of bytes received, including retransmissions but not duplicates.
+    pub bytes: u64,
+    /// Count of packets received, including retransmissions but not duplicates.
+    ///
+    /// Duplicates, such as resends used as padding, are in `packets_discarded`.
+    pub packets: u64,
+    /// Count of packets lost, as per RFC 3550 (expected minus received).
+    ///
+    /// This can go down when lost packets are recovered via retransmissions.
+    pub packets_lost: u64,
+    /// Count of packets discarded because they were duplicates.
+    pub packets_discarded: u64,
+    /// Interarrival jitter estimate, as per RFC 3550.
+    pub jitter: Option<Duration>,
+    /// Time of the last received packet.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub last_packet: Option<Instant>,
+    /// Count of FIR requests sent.
+    pub firs: u64,
+    /// Count of PLI requests sent.
+    pub plis: u64,
+    /// Count of NACKs sent.
+    pub nacks: u64,
+    /// Round trip time (ms) from the last DLRR, if any.
+    pub rtt: Option<f32>,
+    /// Fraction of packets lost from the last RR, if any.
+    pub loss: Option<f32>,
 }
 
 impl StreamRx {
-    pub(crate) fn new(ssrc: Ssrc, mid: Mid, rid: Option<Rid>, suppress_nack: bool) -> Self {
+    pub(crate) fn new(
+        ssrc: Ssrc,
+        mid: Mid,
+        rid: Option<Rid>,
+        suppress_nack: bool,
+        rtcp_intervals: RtcpIntervals,
+    ) -> Self {
         debug!("Create StreamRx for SSRC: {}", ssrc);
 
         StreamRx {
@@ -149,14 +203,21 @@ impl StreamRx {
             register_rtx: None,
             last_time: None,
             pending_request_keyframe: None,
+            last_keyframe_request: already_happened(),
             pending_request_remb: None,
             fir_seq_no: 0,
             last_receiver_report: already_happened(),
+            bytes_received: ValueHistory::default(),
+            rtcp_intervals,
             stats: StreamRxStats::default(),
             check_paused_at: None,
             paused: true,
             need_paused_event: false,
             pause_threshold: Duration::from_millis(1500),
+            svc_target: None,
+            seq_jump_probation: None,
+            need_discontinuity_event: None,
+            need_ended_event: None,
         }
     }
 
@@ -170,6 +231,22 @@ impl StreamRx {
         self.rtx
     }
 
+    /// Current statistics for this stream.
+    pub fn stats(&self) -> StreamRxStats {
+        let mut stats = self.stats.clone();
+
+        if let Some(register) = &self.register {
+            stats.packets_lost = register.packets_lost();
+
+            if let Some((_, clock_rate)) = self.last_clock_rate {
+                let secs = register.jitter() / clock_rate.get() as f32;
+                stats.jitter = Some(Duration::from_secs_f32(secs));
+            }
+        }
+
+        stats
+    }
+
     /// Mid for this stream.
     ///
     /// In SDP this corresponds to m-line and "Media".
@@ -191,6 +268,30 @@ impl StreamRx {
         self.cname.as_deref()
     }
 
+    /// The RTP time to NTP time mapping from the last sender report (SR).
+    ///
+    /// The `rtp_time` is extended beyond 32 bits and has the clock rate of the stream.
+    /// Comparing the mapping of an audio and a video stream with the same CNAME
+    /// lets a player lip-sync the two. See [`SenderInfo::wallclock_for()`].
+    ///
+    /// The value is None until we receive a first sender report.
+    pub fn sender_info(&self) -> Option<SenderInfo> {
+        self.sender_info.map(|(_, s)| s)
+    }
+
+    /// Map an RTP time of this stream to the sender's wallclock, for lip-sync.
+    ///
+    /// The result is only comparable with that of other streams in the same
+    /// [`SyncGroup`][crate::rtp::SyncGroup], i.e. with the same CNAME, since only those
+    /// share the sender wallclock.
+    ///
+    /// The value is None until we received both a CNAME and a sender report.
+    pub fn sync_wallclock(&self, time: MediaTime) -> Option<Instant> {
+        self.cname.as_ref()?;
+        let (_, info) = self.sender_info?;
+        Some(info.wallclock_for(time))
+    }
+
     /// Set threshold duration for emitting the paused event.
     ///
     /// This event is emitted when no packet have received for this duration.
@@ -198,12 +299,34 @@ impl StreamRx {
         self.pause_threshold = t;
     }
 
+    /// Set the target SVC layer for this stream.
+    ///
+    /// For the sample level API, media above the target is dropped (temporal layers) or
+    /// cut off (spatial layers) before being emitted as [`MediaData`][crate::media::MediaData].
+    /// This lets an SFU subscribe to only the layers it intends to forward.
+    ///
+    /// `None` (the default) emits all layers.
+    pub fn set_svc_target(&mut self, target: Option<SvcTarget>) {
+        self.svc_target = target;
+    }
+
+    /// The current target SVC layer set by [`StreamRx::set_svc_target()`].
+    pub fn svc_target(&self) -> Option<SvcTarget> {
+        self.svc_target
+    }
+
     /// Request a keyframe for an incoming encoded stream.
     ///
     /// * SSRC the identifier of the remote encoded stream to request a keyframe for.
     /// * kind PLI or FIR.
+    ///
+    /// Requests are sent at most once per
+    /// [`RtcConfig::set_keyframe_request_interval()`][crate::RtcConfig::set_keyframe_request_interval].
+    /// Requests made before that are sent together, as FIR if any of them was FIR.
     pub fn request_keyframe(&mut self, kind: KeyframeRequestKind) {
-        self.pending_request_keyframe = Some(kind);
+        if self.pending_request_keyframe != Some(KeyframeRequestKind::Fir) {
+            self.pending_request_keyframe = Some(kind);
+        }
     }
 
     /// Request max recv bitrate for an incoming encoded stream.
@@ -223,7 +346,8 @@ impl StreamRx {
 
     pub(crate) fn receiver_report_at(&self) -> Instant {
         let is_audio = self.rtx.is_none(); // this is maybe not correct, but it's all we got.
-        self.last_receiver_report + rr_interval(is_audio)
+        let bitrate = Bitrate::bps(self.bytes_received.sum() * 8);
+        self.last_receiver_report + self.rtcp_intervals.get(is_audio, bitrate, false)
     }
 
     pub(crate) fn handle_rtcp(&mut self, now: Instant, fb: RtcpFb) {
@@ -253,15 +377,35 @@ impl StreamRx {
             DlrrItem(v) => {
                 self.set_dlrr_item(now, v);
             }
-            Goodbye(_v) => {
-                // We get Goodbye at weird times, like SDP renegotiation, which makes
-                // pausing on the BYE not a good idea. Chrome also reuses the SSRC it
-                // just sent BYE on. Very not helpful.
+            Goodbye(_, reason) => {
+                // We get Goodbye at weird times, like SDP renegotiation. Chrome also reuses
+                // the SSRC it just sent BYE on. Very not helpful. This used to mean ignoring
+                // the BYE, but a sender reusing the SSRC starts over with new sequence numbers
+                // and timestamps, which the old state would take for loss and jumps.
+                // Therefore we keep the StreamRx around, but tear down the receive state so
+                // a reused SSRC starts over as a new stream.
+                self.handle_goodbye(reason);
             }
             _ => {}
         }
     }
 
+    fn handle_goodbye(&mut self, reason: Option<String>) {
+        info!(
+            "Goodbye for mid: {} rid: {:?} SSRC: {} reason: {:?}",
+            self.mid, self.rid, self.ssrc, reason
+        );
+
+        self.register = None;
+        self.register_rtx = None;
+        self.sender_info = None;
+        self.last_time = None;
+        self.seq_jump_probation = None;
+        self.pending_request_keyframe = None;
+        self.stats = StreamRxStats::default();
+        self.need_ended_event = Some(reason);
+    }
+
     fn set_sender_info(&mut self, now: Instant, mut info: SenderInfo) {
         // Extend the incoming time given our knowledge of last time.
         let extended = {
@@ -334,10 +478,54 @@ impl StreamRx {
         }
 
         if let Some(reset_seq_no) = reset_seq_no {
-            reset_seq_no
+            return reset_seq_no;
+        }
+
+        header.sequence_number(register.max_seq())
+    }
+
+    /// Check an authenticated main packet for a jump in sequence numbers.
+    ///
+    /// Returns `true` if the jump is a discontinuity, in which case the receive state
+    /// has been reset.
+    pub(crate) fn check_seq_jump(&mut self, seq_no: SeqNo) -> bool {
+        let Some(max_seq) = self.register.as_ref().and_then(|r| r.max_seq()) else {
+            return false;
+        };
+
+        let is_jump = if seq_no >= max_seq {
+            *seq_no - *max_seq > MAX_DROPOUT
         } else {
-            header.sequence_number(register.max_seq())
+            *max_seq - *seq_no > MAX_MISORDER
+        };
+
+        if !is_jump {
+            self.seq_jump_probation = None;
+            return false;
         }
+
+        // A forward jump moves max_seq, so we can't wait for a confirming second packet.
+        // A backward jump could be a stray old packet, and needs the next packet to follow.
+        let confirmed = seq_no > max_seq || self.seq_jump_probation == Some(seq_no);
+
+        if !confirmed {
+            self.seq_jump_probation = Some((*seq_no + 1).into());
+            return false;
+        }
+
+        info!(
+            "Sequence number jump {} -> {} mid: {} rid: {:?} SSRC: {}",
+            max_seq, seq_no, self.mid, self.rid, self.ssrc
+        );
+
+        self.seq_jump_probation = None;
+        if let Some(r) = &mut self.register {
+            r.clear();
+        }
+        self.last_time = None;
+        self.need_discontinuity_event = Some(DiscontinuityReason::SequenceJump);
+
+        true
     }
 
     pub(crate) fn update_register(
@@ -367,6 +555,11 @@ impl StreamRx {
 
         let is_new_packet = register.update(seq_no, now, header.timestamp, clock_rate.get());
 
+        if !is_new_packet {
+            self.stats.packets_discarded += 1;
+        }
+        self.stats.last_packet = Some(now);
+
         let previous_time = self.last_time.map(|t| t.numer());
         let time_u32 = extend_u32(previous_time, header.timestamp);
         let time = MediaTime::new(time_u32, clock_rate);
@@ -385,13 +578,24 @@ impl StreamRx {
     pub(crate) fn handle_rtp(
         &mut self,
         now: Instant,
-        header: RtpHeader,
+        mut header: RtpHeader,
         data: Vec<u8>,
         seq_no: SeqNo,
         time: MediaTime,
+        is_new_packet: bool,
     ) -> RtpPacket {
         trace!("Handle RTP: {:?}", header);
 
+        // Senders typically stop sending the mid/rid header extensions once the SSRC is
+        // established. Tag the packet with what we mapped it to, so that the simulcast layer
+        // can be read straight off the delivered packet.
+        if header.ext_vals.mid.is_none() {
+            header.ext_vals.mid = Some(self.mid);
+        }
+        if header.ext_vals.rid.is_none() {
+            header.ext_vals.rid = self.rid;
+        }
+
         let need_clock_rate = self.last_clock_rate.map(|(pt, _)| pt) != Some(header.payload_type);
         if need_clock_rate {
             self.last_clock_rate = Some((header.payload_type, time.frequency()));
@@ -406,14 +610,18 @@ impl StreamRx {
             seq_no,
             time,
             header,
-            payload: data,
+            payload: data.into(),
             nackable: false,
             last_sender_info: self.sender_info.map(|(_, s)| s),
             timestamp: now,
         };
 
-        self.stats.bytes += packet.payload.len() as u64;
-        self.stats.packets += 1;
+        // Duplicates, such as resends used for padding, are not counted as media.
+        if is_new_packet {
+            self.stats.bytes += packet.payload.len() as u64;
+            self.stats.packets += 1;
+            self.bytes_received.push(now, packet.payload.len() as u64);
+        }
 
         packet
     }
@@ -437,15 +645,34 @@ impl StreamRx {
         header.ext_vals.rid = header.ext_vals.rid_repair.take();
     }
 
+    /// When a pending keyframe request can be sent.
+    pub(crate) fn keyframe_request_at(&self) -> Option<Instant> {
+        self.pending_request_keyframe?;
+        Some(self.last_keyframe_request + self.rtcp_intervals.keyframe_request)
+    }
+
     pub(crate) fn maybe_create_keyframe_request(
         &mut self,
+        now: Instant,
         sender_ssrc: Ssrc,
         feedback: &mut VecDeque<Rtcp>,
     ) {
-        let Some(kind) = self.pending_request_keyframe.take() else {
+        let Some(at) = self.keyframe_request_at() else {
             return;
         };
 
+        if now < at {
+            // Too soon after the last one, the request stays pending.
+            return;
+        }
+
+        let kind = self
+            .pending_request_keyframe
+            .take()
+            .expect("pending keyframe request");
+
+        self.last_keyframe_request = now;
+
         let ssrc = self.ssrc;
 
         match kind {
@@ -511,9 +738,10 @@ impl StreamRx {
         let xr = self.create_extended_receiver_report(now);
 
         trace!(
-            "Created feedback RR/XR ({:?}/{:?}): {:?} {:?}",
-            self.mid,
-            self.rid,
+            mid = %self.mid,
+            rid = ?self.rid,
+            ssrc = %self.ssrc,
+            "Created feedback RR/XR: {:?} {:?}",
             rr,
             xr
         );
@@ -594,7 +822,7 @@ impl StreamRx {
             nack.sender_ssrc = sender_ssrc;
             nack.ssrc = self.ssrc;
 
-            trace!("Created feedback NACK: {:?}", nack);
+            trace!(mid = %self.mid, ssrc = %self.ssrc, "Created feedback NACK: {:?}", nack);
             feedback.push_back(Rtcp::Nack(nack));
             self.stats.nacks += 1;
         }
@@ -640,6 +868,28 @@ impl StreamRx {
         self.pending_request_keyframe = None;
     }
 
+    pub(crate) fn poll_discontinuity(&mut self) -> Option<StreamDiscontinuity> {
+        let reason = self.need_discontinuity_event.take()?;
+
+        Some(StreamDiscontinuity {
+            ssrc: self.ssrc,
+            mid: self.mid,
+            rid: self.rid,
+            reason,
+        })
+    }
+
+    pub(crate) fn poll_ended(&mut self) -> Option<StreamEnded> {
+        let reason = self.need_ended_event.take()?;
+
+        Some(StreamEnded {
+            ssrc: self.ssrc,
+            mid: self.mid,
+            rid: self.rid,
+            reason,
+        })
+    }
+
     #[must_use]
     pub(crate) fn change_ssrc(&mut self, ssrc: Ssrc) -> bool {
         // Avoid flapping
@@ -654,10 +904,17 @@ impl StreamRx {
 
         // Remember which was the previous in case a stray packet turns up
         // so do we don't go "backwards".
+        let previous = self.ssrc;
         self.previous_ssrc = Some(self.ssrc);
         self.ssrc = ssrc;
         self.register = None;
 
+        // The RTP/NTP mapping and media time belong to the previous SSRC.
+        self.sender_info = None;
+        self.last_time = None;
+        self.seq_jump_probation = None;
+        self.need_discontinuity_event = Some(DiscontinuityReason::SsrcChanged { previous });
+
         true
     }
 
@@ -1,6 +1,5 @@
-use std::time::Instant;
-
 use crate::rtp_::{Nack, ReceptionReport, SeqNo};
+use crate::util::Instant;
 
 use super::register_nack::NackRegister;
 
@@ -90,7 +89,7 @@ impl ReceiverRegister {
     }
 
     /// Generates a NACK report
-    pub fn nack_report(&mut self) -> Option<impl Iterator<Item = Nack>> {
+    pub fn nack_report(&mut self) -> Option<impl Iterator<Item = Nack> + '_> {
         self.nack.nack_reports()
     }
 
@@ -115,6 +114,20 @@ impl ReceiverRegister {
         })
     }
 
+    /// Cumulative number of packets lost, without modifying the state.
+    pub fn packets_lost(&self) -> u64 {
+        let (Some(first), Some(last)) = (self.first, self.max_seq()) else {
+            return 0;
+        };
+
+        (expected(first, last) - self.count as i64).max(0) as u64
+    }
+
+    /// Estimated jitter in the media time base.
+    pub fn jitter(&self) -> f32 {
+        self.jitter
+    }
+
     pub fn max_seq(&self) -> Option<SeqNo> {
         self.nack.max_seq()
     }
@@ -302,6 +315,23 @@ mod test {
         assert_eq!(report.jitter, r.jitter as u32);
     }
 
+    #[test]
+    fn packets_lost_without_report() {
+        let mut r = ReceiverRegister::new();
+        assert_eq!(r.packets_lost(), 0);
+
+        let now = Instant::now();
+        for seq in [1, 2, 4, 5, 8] {
+            r.update((seq as u64).into(), now, 0, 90_000);
+        }
+        assert_eq!(r.packets_lost(), 3);
+
+        // Duplicates don't count and recovery lowers the loss.
+        r.update(4.into(), now, 0, 90_000);
+        r.update(3.into(), now, 0, 90_000);
+        assert_eq!(r.packets_lost(), 2);
+    }
+
     #[test]
     fn expected_received_loss() {
         let first = 14.into();
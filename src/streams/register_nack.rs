@@ -147,25 +147,15 @@ impl NackRegister {
     /// Create a new nack report
     ///
     /// This modifies the state as it counts how many times packets have been nacked
-    pub fn nack_reports(&mut self) -> Option<impl Iterator<Item = Nack>> {
+    pub fn nack_reports(&mut self) -> Option<impl Iterator<Item = Nack> + '_> {
         let Range { start, end } = self.active.clone()?;
         let start = (*start..=*end).find(|s| self.packet((*s).into()).needs_nack())?;
 
-        Some(
-            ReportList::lists_from_iter(NackIterator {
-                reg: self,
-                next: start,
-                end: *end,
-            })
-            .into_iter()
-            .map(|reports| {
-                Nack {
-                    sender_ssrc: 0.into(),
-                    ssrc: 0.into(), // changed when sending
-                    reports,
-                }
-            }),
-        )
+        Some(nacks_from_entries(NackIterator {
+            reg: self,
+            next: start,
+            end: *end,
+        }))
     }
 
     fn as_index(&self, seq: SeqNo) -> usize {
@@ -178,13 +168,23 @@ impl NackRegister {
     }
 }
 
+/// Chunk the entries into NACKs, lazily and without allocating.
+fn nacks_from_entries(entries: impl Iterator<Item = NackEntry>) -> impl Iterator<Item = Nack> {
+    ReportList::lists_from_iter(entries).map(|reports| Nack {
+        sender_ssrc: 0.into(),
+        ssrc: 0.into(), // changed when sending
+        reports,
+    })
+}
+
 #[cfg(test)]
 mod test {
     use std::ops::Range;
 
     use crate::streams::register_nack::MAX_MISORDER;
 
-    use super::NackRegister;
+    use super::{nacks_from_entries, NackRegister};
+    use crate::rtp_::NackEntry;
 
     fn assert_update(
         reg: &mut NackRegister,
@@ -571,4 +571,19 @@ mod test {
         let active = reg.active.clone().expect("nack range");
         assert_eq!(*active.start, 65666);
     }
+
+    #[test]
+    fn nack_entries_over_several_lists() {
+        let entries = (0..40_u16).map(|i| NackEntry {
+            pid: i * 17,
+            blp: 0,
+        });
+
+        let nacks: Vec<_> = nacks_from_entries(entries).collect();
+
+        assert_eq!(nacks.len(), 2);
+        assert_eq!(nacks[0].reports.len(), 31);
+        assert_eq!(nacks[1].reports.len(), 9);
+        assert_eq!(nacks[1].reports[0].pid, 31 * 17);
+    }
 }
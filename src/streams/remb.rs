@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use crate::rtp_::{Bitrate, DataSize};
+use crate::util::Instant;
+
+/// How often the receive side estimate is updated and sent as REMB.
+pub(crate) const REMB_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Never estimate lower than this.
+const MIN_ESTIMATE: Bitrate = Bitrate::kbps(50);
+
+/// Loss fraction above which the estimate is decreased.
+const LOSS_DECREASE: f64 = 0.1;
+
+/// Loss fraction below which the estimate is increased.
+const LOSS_INCREASE: f64 = 0.02;
+
+/// Receive side bandwidth estimator, used when the remote doesn't do TWCC.
+///
+/// This is a loss based estimate over the total of all incoming streams. It is
+/// bounded by the incoming bitrate, since we can't know anything about the capacity
+/// above what the remote sends.
+#[derive(Debug, Default)]
+pub(crate) struct RembEstimator {
+    /// Totals at the last update.
+    last: Option<Totals>,
+    estimate: Option<Bitrate>,
+}
+
+/// Received totals over all incoming streams.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Totals {
+    pub at: Option<Instant>,
+    pub bytes: u64,
+    pub packets: u64,
+    pub lost: u64,
+}
+
+impl RembEstimator {
+    /// Time for the next update.
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        let at = self.last?.at?;
+        Some(at + REMB_INTERVAL)
+    }
+
+    /// Update the estimate with the totals received so far.
+    ///
+    /// Returns the new estimate, once every [`REMB_INTERVAL`].
+    pub fn update(&mut self, now: Instant, totals: Totals) -> Option<Bitrate> {
+        let totals = Totals {
+            at: Some(now),
+            ..totals
+        };
+
+        let Some(last) = self.last else {
+            self.last = Some(totals);
+            return None;
+        };
+
+        // Unwrap is OK since last is always set with a time above.
+        let elapsed = now.saturating_duration_since(last.at.unwrap());
+        if elapsed < REMB_INTERVAL {
+            return None;
+        }
+
+        self.last = Some(totals);
+
+        let bytes = totals.bytes.saturating_sub(last.bytes);
+        let received = totals.packets.saturating_sub(last.packets);
+        // Lost can go down when packets are recovered by retransmissions.
+        let lost = totals.lost.saturating_sub(last.lost);
+
+        let expected = received + lost;
+        if expected == 0 {
+            // Nothing received, keep the previous estimate.
+            return self.estimate;
+        }
+        let loss = lost as f64 / expected as f64;
+
+        let incoming = DataSize::bytes(bytes) / elapsed;
+
+        let estimate = match self.estimate {
+            None => incoming * 1.5,
+            Some(prev) if loss > LOSS_DECREASE => prev.min(incoming) * (1.0 - 0.5 * loss),
+            Some(prev) if loss < LOSS_INCREASE => prev * 1.08,
+            Some(prev) => prev,
+        };
+
+        // Don't run away from what the remote is actually sending.
+        let estimate = estimate.min(incoming * 1.5).max(MIN_ESTIMATE);
+
+        trace!(
+            "REMB estimate: {} incoming: {} loss: {:.3}",
+            estimate,
+            incoming,
+            loss
+        );
+
+        self.estimate = Some(estimate);
+        self.estimate
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn totals(bytes: u64, packets: u64, lost: u64) -> Totals {
+        Totals {
+            at: None,
+            bytes,
+            packets,
+            lost,
+        }
+    }
+
+    fn bps(b: Bitrate) -> u64 {
+        b.as_f64().round() as u64
+    }
+
+    #[test]
+    fn estimate_follows_loss() {
+        let now = Instant::now();
+        let mut e = RembEstimator::default();
+
+        // 125_000 bytes per second is 1Mbps.
+        assert_eq!(e.update(now, totals(0, 0, 0)), None);
+        assert_eq!(e.update(now + REMB_INTERVAL / 2, totals(1000, 10, 0)), None);
+
+        let first = e
+            .update(now + REMB_INTERVAL, totals(125_000, 100, 0))
+            .unwrap();
+        assert_eq!(bps(first), 1_500_000);
+
+        // No loss at the same incoming rate is capped by the incoming rate.
+        let second = e
+            .update(now + REMB_INTERVAL * 2, totals(250_000, 200, 0))
+            .unwrap();
+        assert_eq!(bps(second), 1_500_000);
+
+        // 20% loss backs off below the incoming rate.
+        let third = e
+            .update(now + REMB_INTERVAL * 3, totals(375_000, 280, 20))
+            .unwrap();
+        assert_eq!(bps(third), 900_000);
+
+        // Without loss it grows again.
+        let fourth = e
+            .update(now + REMB_INTERVAL * 4, totals(500_000, 380, 20))
+            .unwrap();
+        assert_eq!(bps(fourth), 972_000);
+
+        // Nothing received keeps the estimate.
+        let fifth = e
+            .update(now + REMB_INTERVAL * 5, totals(500_000, 380, 20))
+            .unwrap();
+        assert_eq!(fifth, fourth);
+    }
+}
@@ -1,8 +1,8 @@
 use std::time::Duration;
-use std::time::Instant;
 
 use crate::io::DATAGRAM_MAX_PACKET_SIZE;
 use crate::rtp_::SeqNo;
+use crate::util::Instant;
 
 use super::rtx_cache_buf::EvictingBuffer;
 use super::RtpPacket;
@@ -19,6 +19,12 @@ pub(crate) struct RtxCache {
     // a SeqNo. However We can half the storage space by using the sentinel
     // values SeqNo::MAX to indicate None
     seq_no_by_quantized_size: [SeqNo; RTX_CACHE_QUANTIZE_SLOTS],
+
+    // Max sum of payload bytes held. Oldest packets are evicted beyond this.
+    max_bytes: usize,
+
+    // Current sum of payload bytes held.
+    bytes: usize,
 }
 
 impl RtxCache {
@@ -26,14 +32,41 @@ impl RtxCache {
         Self {
             packet_by_seq_no: EvictingBuffer::new(10, max_packet_age, max_packet_count),
             seq_no_by_quantized_size: [SeqNo::MAX; RTX_CACHE_QUANTIZE_SLOTS],
+            max_bytes: usize::MAX,
+            bytes: 0,
+        }
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Change the max age. Packets older than this are evicted on the next cached packet.
+    pub fn set_max_age(&mut self, max_age: Duration) {
+        self.packet_by_seq_no.set_max_age(max_age);
+        if max_age.is_zero() {
+            // The buffer holds nothing with a zero age.
+            self.clear();
         }
     }
 
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+        self.remove_excess_bytes();
+    }
+
     pub fn cache_sent_packet(&mut self, packet: RtpPacket, now: Instant) {
         assert!(packet.nackable);
         let seq_no = packet.seq_no;
-        let quantized_size = packet.payload.len() / RTX_CACHE_SIZE_QUANTIZER;
-        self.packet_by_seq_no.push(*seq_no, now, packet);
+        let len = packet.payload.len();
+        let quantized_size = len / RTX_CACHE_SIZE_QUANTIZER;
+        if let Some(replaced) = self.packet_by_seq_no.push(*seq_no, now, packet) {
+            self.bytes = self.bytes.saturating_sub(replaced.payload.len());
+        }
+        // The push is ignored for packets that are already considered evicted.
+        if self.packet_by_seq_no.get(*seq_no).is_some() {
+            self.bytes += len;
+        }
         self.seq_no_by_quantized_size[quantized_size] = seq_no;
         self.remove_old_packets(now);
     }
@@ -59,9 +92,22 @@ impl RtxCache {
     }
 
     fn remove_old_packets(&mut self, now: Instant) {
-        self.packet_by_seq_no.maybe_evict(now);
+        let bytes = &mut self.bytes;
+        self.packet_by_seq_no
+            .maybe_evict_with(now, |p| *bytes = bytes.saturating_sub(p.payload.len()));
+        self.remove_excess_bytes();
+    }
+
+    fn remove_excess_bytes(&mut self) {
+        while self.bytes > self.max_bytes {
+            let Some(p) = self.packet_by_seq_no.evict_oldest() else {
+                break;
+            };
+            self.bytes = self.bytes.saturating_sub(p.payload.len());
+        }
     }
 
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
     pub(crate) fn last_packet(&self) -> Option<&[u8]> {
         let packet = self.packet_by_seq_no.last()?;
         Some(packet.payload.as_ref())
@@ -70,6 +116,7 @@ impl RtxCache {
     pub(crate) fn clear(&mut self) {
         self.packet_by_seq_no.clear();
         self.seq_no_by_quantized_size = [SeqNo::MAX; RTX_CACHE_QUANTIZE_SLOTS];
+        self.bytes = 0;
     }
 }
 
@@ -89,7 +136,7 @@ mod test {
             header: RtpHeader::default(),
             seq_no: seq_no.into(),
             time: MediaTime::from_90khz(0),
-            payload: millis.to_be_bytes().to_vec(),
+            payload: millis.to_be_bytes().to_vec().into(),
             timestamp: after(now, millis),
             last_sender_info: None,
             nackable: true,
@@ -188,4 +235,90 @@ mod test {
             rtx_cache.get_cached_packet_by_seq_no(200.into())
         );
     }
+
+    #[test]
+    fn rtx_cache_max_bytes() {
+        let now = Instant::now();
+        let mut rtx_cache = RtxCache::new(400, Duration::from_secs(3));
+        // Each payload is 8 bytes, room for 3.
+        rtx_cache.set_max_bytes(24);
+
+        for i in 1..=5 {
+            rtx_cache.cache_sent_packet(packet(now, i, i * 10), after(now, i * 10));
+        }
+
+        assert_eq!(None, rtx_cache.get_cached_packet_by_seq_no(2.into()));
+        assert_eq!(
+            Some(&mut packet(now, 3, 30)),
+            rtx_cache.get_cached_packet_by_seq_no(3.into())
+        );
+        assert_eq!(
+            Some(&mut packet(now, 5, 50)),
+            rtx_cache.get_cached_packet_by_seq_no(5.into())
+        );
+
+        // Lowering the budget evicts straight away.
+        rtx_cache.set_max_bytes(8);
+        assert_eq!(None, rtx_cache.get_cached_packet_by_seq_no(4.into()));
+        assert_eq!(
+            Some(&mut packet(now, 5, 50)),
+            rtx_cache.get_cached_packet_by_seq_no(5.into())
+        );
+    }
+
+    #[test]
+    fn rtx_cache_count_cap_keeps_byte_budget() {
+        let now = Instant::now();
+        // Room for 12 packets by count, 12.5 by bytes.
+        let mut rtx_cache = RtxCache::new(12, Duration::from_secs(3));
+        rtx_cache.set_max_bytes(100);
+
+        for i in 1..=20 {
+            rtx_cache.cache_sent_packet(packet(now, i, i * 10), after(now, i * 10));
+        }
+
+        // The count cap holds the last 12. Packets overwritten by the count cap must
+        // not count against the byte budget, or it would evict more.
+        assert_eq!(None, rtx_cache.get_cached_packet_by_seq_no(8.into()));
+        assert_eq!(
+            Some(&mut packet(now, 9, 90)),
+            rtx_cache.get_cached_packet_by_seq_no(9.into())
+        );
+        assert_eq!(
+            Some(&mut packet(now, 20, 200)),
+            rtx_cache.get_cached_packet_by_seq_no(20.into())
+        );
+        assert_eq!(rtx_cache.bytes, 12 * 8);
+    }
+
+    #[test]
+    fn rtx_cache_lower_max_age() {
+        let now = Instant::now();
+        let mut rtx_cache = RtxCache::new(400, Duration::from_secs(3));
+
+        for i in 1..=5 {
+            rtx_cache.cache_sent_packet(packet(now, i, i * 100), after(now, i * 100));
+        }
+
+        // As from a remote rtx-time. Nothing is dropped until the next packet.
+        rtx_cache.set_max_age(Duration::from_millis(250));
+        assert_eq!(
+            Some(&mut packet(now, 1, 100)),
+            rtx_cache.get_cached_packet_by_seq_no(1.into())
+        );
+
+        rtx_cache.cache_sent_packet(packet(now, 6, 600), after(now, 600));
+
+        assert_eq!(None, rtx_cache.get_cached_packet_by_seq_no(3.into()));
+        assert_eq!(
+            Some(&mut packet(now, 4, 400)),
+            rtx_cache.get_cached_packet_by_seq_no(4.into())
+        );
+        assert_eq!(rtx_cache.bytes, 3 * 8);
+
+        // Zero disables the cache.
+        rtx_cache.set_max_age(Duration::ZERO);
+        assert_eq!(None, rtx_cache.get_cached_packet_by_seq_no(6.into()));
+        assert_eq!(rtx_cache.bytes, 0);
+    }
 }
@@ -1,9 +1,10 @@
 #![allow(missing_docs)]
 
 use std::mem;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::util::already_happened;
+use crate::util::Instant;
 
 /// Fixed size buffer that evicts the oldest entries based on time.
 ///
@@ -66,6 +67,11 @@ impl<T> EvictingBuffer<T> {
         (position % self.buf.len() as u64) as usize
     }
 
+    /// Change how long to keep entries for.
+    pub fn set_max_age(&mut self, max_age: Duration) {
+        self.max_age = max_age;
+    }
+
     #[inline(always)]
     fn is_inert(&self) -> bool {
         self.buf.is_empty() || self.max_age.is_zero()
@@ -74,14 +80,16 @@ impl<T> EvictingBuffer<T> {
     /// Push a new entry.
     ///
     /// Position is an increasing sequence number. The sequence can be out of order.
-    pub fn push(&mut self, position: u64, timestamp: Instant, value: T) {
+    ///
+    /// Returns the value this entry replaced, if any.
+    pub fn push(&mut self, position: u64, timestamp: Instant, value: T) -> Option<T> {
         if self.is_inert() {
-            return;
+            return None;
         }
 
         if timestamp < self.last_timeout {
             // Value is already considered evicted.
-            return;
+            return None;
         }
 
         let next_evict = if let Some(v) = self.next_evict {
@@ -94,7 +102,7 @@ impl<T> EvictingBuffer<T> {
 
         if position < next_evict {
             // Do not cache values preceding evict position.
-            return;
+            return None;
         }
 
         let mut index = self.index_for_position(position);
@@ -111,15 +119,16 @@ impl<T> EvictingBuffer<T> {
         }
         self.last_position = Some(position);
 
-        self.buf[index] = Some(Entry {
+        let replaced = self.buf[index].replace(Entry {
             position,
             timestamp,
             value,
         });
+
+        replaced.map(|e| e.value)
     }
 
     /// Get the entry for the previously inserted position.
-    #[allow(unused)]
     pub fn get(&self, position: u64) -> Option<&T> {
         if self.is_inert() {
             return None;
@@ -149,6 +158,11 @@ impl<T> EvictingBuffer<T> {
     }
 
     pub fn maybe_evict(&mut self, now: Instant) {
+        self.maybe_evict_with(now, |_| {});
+    }
+
+    /// Like [`EvictingBuffer::maybe_evict()`], but hands over each evicted value.
+    pub fn maybe_evict_with(&mut self, now: Instant, evicted: impl FnMut(T)) {
         if self.is_inert() {
             return;
         }
@@ -159,10 +173,40 @@ impl<T> EvictingBuffer<T> {
         }
         self.last_timeout = now;
 
-        self.evict(now);
+        self.evict(now, evicted);
+    }
+
+    /// Evict the oldest entry regardless of age.
+    pub fn evict_oldest(&mut self) -> Option<T> {
+        if self.is_inert() {
+            return None;
+        }
+
+        let start_position = self.next_evict?;
+
+        for position in start_position..(start_position + self.buf.len() as u64) {
+            let index = self.index_for_position(position);
+
+            let is_match = self.buf[index]
+                .as_ref()
+                .map(|e| e.position == position)
+                .unwrap_or(false);
+
+            if !is_match {
+                // Gap, or an entry that wrapped around from a later position.
+                continue;
+            }
+
+            let entry = self.buf[index].take().expect("entry at index");
+            self.next_evict = Some(position + 1);
+
+            return Some(entry.value);
+        }
+
+        None
     }
 
-    fn evict(&mut self, now: Instant) {
+    fn evict(&mut self, now: Instant, mut evicted: impl FnMut(T)) {
         let Some(start_position) = self.next_evict else {
             // Before first element been pushed.
             return;
@@ -191,7 +235,9 @@ impl<T> EvictingBuffer<T> {
 
             if age > self.max_age {
                 // Evict.
-                self.buf[index] = None;
+                if let Some(entry) = self.buf[index].take() {
+                    evicted(entry.value);
+                }
             } else {
                 // We assume entries are roughly in time order (some jumble is allowed).
                 // Once we reach an element we should not evict, stop.
@@ -244,6 +290,7 @@ impl<T> EvictingBuffer<T> {
         self.last_position
     }
 
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
     pub fn last(&self) -> Option<&T> {
         if self.is_inert() {
             return None;
@@ -383,4 +430,30 @@ mod test {
         assert_eq!(buf.get(3), Some(&'B'));
         assert_eq!(buf.get(4), Some(&'C'));
     }
+
+    #[test]
+    fn evict_oldest_regardless_of_age() {
+        let mut buf = EvictingBuffer::new(4, Duration::from_secs(10), 10);
+        let now = Instant::now();
+
+        buf.push(5, now + Duration::from_secs(0), 'A');
+        // GAP
+        buf.push(7, now + Duration::from_secs(1), 'C');
+
+        assert_eq!(buf.evict_oldest(), Some('A'));
+        assert_eq!(buf.evict_oldest(), Some('C'));
+        assert_eq!(buf.evict_oldest(), None);
+    }
+
+    #[test]
+    fn push_returns_replaced() {
+        let mut buf = EvictingBuffer::new(2, Duration::from_secs(10), 2);
+        let now = Instant::now();
+
+        assert_eq!(buf.push(2, now, 'A'), None);
+        assert_eq!(buf.push(3, now, 'B'), None);
+
+        // At max size, overwrites 2.
+        assert_eq!(buf.push(4, now, 'C'), Some('A'));
+    }
 }
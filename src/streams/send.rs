@@ -1,11 +1,11 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::time::Duration;
-use std::time::Instant;
+
+use bytes::Bytes;
 
 use crate::format::CodecConfig;
 use crate::format::PayloadParams;
 use crate::io::DATAGRAM_MAX_PACKET_SIZE;
-use crate::io::DATAGRAM_MTU_WARN;
 use crate::io::MAX_RTP_OVERHEAD;
 use crate::media::KeyframeRequestKind;
 use crate::media::Media;
@@ -13,24 +13,26 @@ use crate::media::MediaKind;
 use crate::packet::QueuePriority;
 use crate::packet::QueueSnapshot;
 use crate::packet::QueueState;
+use crate::rtp::{ObservedPacket, Observer, PacketStage};
 use crate::rtp_::Bitrate;
-use crate::rtp_::{extend_u16, Descriptions, ReportList, Rtcp};
+use crate::rtp_::{extend_u16, Descriptions, Goodbye, ReportList, Rtcp};
 use crate::rtp_::{ExtensionMap, ReceptionReport, RtpHeader};
 use crate::rtp_::{ExtensionValues, Frequency, MediaTime, Mid, NackEntry};
-use crate::rtp_::{Pt, Rid, RtcpFb, SenderInfo, SenderReport, Ssrc};
+use crate::rtp_::{Pt, Rid, RtcpFb, RtpError, SenderInfo, SenderReport, Ssrc};
 use crate::rtp_::{Sdes, SdesType, MAX_BLANK_PADDING_PAYLOAD_SIZE};
 use crate::rtp_::{SeqNo, SRTP_BLOCK_SIZE};
 use crate::session::PacketReceipt;
 use crate::stats::MediaEgressStats;
 use crate::stats::StatsSnapshot;
 use crate::util::value_history::ValueHistory;
+use crate::util::Instant;
 use crate::util::{already_happened, calculate_rtt_ms, not_happening};
 use crate::util::{InstantExt, NonCryptographicRng};
 use crate::RtcError;
 
 use super::rtx_cache::RtxCache;
 use super::send_queue::SendQueue;
-use super::{rr_interval, RtpPacket};
+use super::{RtcpIntervals, RtpPacket, StreamWritable};
 
 /// The smallest size of padding for which we attempt to use a spurious resend. For padding
 /// requests smaller than this we use blank packets instead.
@@ -38,6 +40,8 @@ const MIN_SPURIOUS_PADDING_SIZE: usize = 50;
 
 pub const DEFAULT_RTX_CACHE_DURATION: Duration = Duration::from_secs(3);
 
+pub const DEFAULT_RTX_CACHE_MAX_BYTES: usize = 2 * 1024 * 1024;
+
 /// Outgoing encoded stream.
 ///
 /// A stream is a primary SSRC + optional RTX SSRC.
@@ -67,6 +71,7 @@ pub struct StreamTx {
     clock_rate: Option<Frequency>,
 
     /// If we are doing seq_no ourselves (when writing sample mode).
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
     seq_no: SeqNo,
 
     /// If we are using RTX, this is the seq no counter.
@@ -78,6 +83,14 @@ pub struct StreamTx {
     /// Last written media + wallclock time.
     rtp_and_wallclock: Option<(u32, Instant)>,
 
+    /// Main PT and sequence number of the last written packet. Keep-alives on the
+    /// main SSRC continue from here.
+    last_written: Option<(Pt, SeqNo)>,
+
+    /// Number of sequence numbers taken by keep-alives on the main SSRC. This is
+    /// added to the sequence numbers of all following writes.
+    seq_no_offset: u64,
+
     /// Queue of packets to send.
     ///
     /// The packets here do not have correct sequence numbers, header extension values etc.
@@ -89,6 +102,9 @@ pub struct StreamTx {
     /// Audio defaults to not being paced.
     unpaced: Option<bool>,
 
+    /// Whether to answer NACKs by resending on the main SSRC when there is no RTX.
+    resend_without_rtx: bool,
+
     /// Scheduled resends due to NACK or spurious padding.
     resends: VecDeque<Resend>,
 
@@ -105,14 +121,24 @@ pub struct StreamTx {
     /// Last time we produced a SR.
     last_sender_report: Instant,
 
+    /// Time between SR.
+    rtcp_intervals: RtcpIntervals,
+
     /// If we have a pending incoming keyframe request.
     pending_request_keyframe: Option<KeyframeRequestKind>,
 
+    /// Last time we accepted an incoming keyframe request.
+    last_keyframe_request: Instant,
+
+    /// Last FIR sequence number seen per requesting SSRC. FIR is sent repeatedly with the
+    /// same sequence number until the keyframe arrives (RFC 5104 4.3.1.1).
+    fir_seq_no: HashMap<Ssrc, u8>,
+
     /// If we have a pending incoming remb request.
     pending_request_remb: Option<Bitrate>,
 
     /// Statistics of outgoing data.
-    stats: StreamTxStats,
+    stats: StreamTxCounters,
 
     // downsampled rtx ratio (value, last calculation)
     rtx_ratio: (f32, Instant),
@@ -120,11 +146,84 @@ pub struct StreamTx {
     // The _main_ PT to use for padding. This is main PT, since the poll_packet() loop
     // figures out the param.resend() RTX PT using main.
     pt_for_padding: Option<Pt>,
+
+    /// Whether the application has paused this stream.
+    paused: bool,
+
+    /// Whether the stream was paused with a BYE. No sender reports are sent until resumed.
+    said_goodbye: bool,
+
+    /// BYE to send on the next handle_timeout.
+    pending_goodbye: bool,
+
+    /// Interval of RTP silence after which we send a keep-alive.
+    keep_alive: Option<Duration>,
+
+    /// Keep-alive to send on the main SSRC, for streams without RTX.
+    keep_alive_main: bool,
+
+    /// Max queue delay before we stop accepting writes.
+    queue_delay_budget: Option<Duration>,
+
+    /// Whether the queue delay exceeded the budget.
+    congested: bool,
+
+    /// If we need to emit a writable event.
+    need_writable_event: bool,
+
+    /// Contributing sources set on written RTP packets.
+    csrc: Vec<Ssrc>,
+}
+
+/// Statistics for a [`StreamTx`], obtained via [`StreamTx::stats()`].
+///
+/// The remote fields are as reported by the remote peer in RTCP receiver reports.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StreamTxStats {
+    /// Count of bytes sent, including retransmissions.
+    pub bytes: u64,
+    /// Count of packets sent, including retransmissions.
+    pub packets: u64,
+    /// Count of retransmitted bytes.
+    pub bytes_resent: u64,
+    /// Count of retransmitted packets.
+    pub packets_resent: u64,
+    /// Count of padding bytes sent, for bandwidth probing.
+    ///
+    /// Padding is not included in the other counts, nor in the bitrate.
+    pub bytes_padding: u64,
+    /// Count of padding packets sent, for bandwidth probing.
+    pub packets_padding: u64,
+    /// The bitrate sent over the last second, including retransmissions.
+    ///
+    /// This is updated as packets are sent.
+    pub bitrate: Bitrate,
+    /// Count of FIR requests received.
+    pub firs: u64,
+    /// Count of PLI requests received.
+    pub plis: u64,
+    /// Count of NACKs received.
+    pub nacks: u64,
+    /// Count of packets requested by the NACKs.
+    pub nacked_packets: u64,
+    /// Count of packets requested by the NACKs that were still in the RTX cache.
+    ///
+    /// The ones missing were too old for the cache, see [`StreamTx::set_rtx_cache()`].
+    pub nacked_packets_cached: u64,
+    /// Round trip time (ms) from the last receiver report.
+    pub rtt: Option<f32>,
+    /// Fraction of packets lost in the last receiver report.
+    pub remote_loss: Option<f32>,
+    /// Cumulative number of packets lost in the last receiver report.
+    pub remote_packets_lost: Option<u32>,
+    /// Interarrival jitter in the last receiver report.
+    pub remote_jitter: Option<Duration>,
 }
 
 /// Holder of stats.
 #[derive(Debug, Default)]
-pub(crate) struct StreamTxStats {
+pub(crate) struct StreamTxCounters {
     /// count of bytes sent, including retransmissions
     /// <https://www.w3.org/TR/webrtc-stats/#dom-rtcsentrtpstreamstats-bytessent>
     bytes: u64,
@@ -135,23 +234,39 @@ pub(crate) struct StreamTxStats {
     packets: u64,
     /// count of retransmitted packets alone
     packets_resent: u64,
+    /// count of padding bytes, not included in bytes
+    bytes_padding: u64,
+    /// count of padding packets, not included in packets
+    packets_padding: u64,
     /// count of FIR requests received
     firs: u64,
     /// count of PLI requests received
     plis: u64,
     /// count of NACKs received
     nacks: u64,
+    /// count of packets requested by NACKs
+    nacked_packets: u64,
+    /// count of packets requested by NACKs that were still cached
+    nacked_packets_cached: u64,
     /// round trip time (ms)
     /// Can be null in case of missing or bad reports
     rtt: Option<f32>,
     /// losses collecter from RR (known packets, lost ratio)
     losses: Vec<(u64, f32)>,
+    /// the last RR received
+    last_rr: Option<ReceptionReport>,
     bytes_transmitted: ValueHistory<u64>,
     bytes_retransmitted: ValueHistory<u64>,
 }
 
 impl StreamTx {
-    pub(crate) fn new(ssrc: Ssrc, rtx: Option<Ssrc>, mid: Mid, rid: Option<Rid>) -> Self {
+    pub(crate) fn new(
+        ssrc: Ssrc,
+        rtx: Option<Ssrc>,
+        mid: Mid,
+        rid: Option<Rid>,
+        rtcp_intervals: RtcpIntervals,
+    ) -> Self {
         // https://www.rfc-editor.org/rfc/rfc3550#page-13
         // The initial value of the sequence number SHOULD be random (unpredictable)
         // to make known-plaintext attacks on encryption more difficult
@@ -172,18 +287,37 @@ impl StreamTx {
             seq_no_rtx,
             last_used: already_happened(),
             rtp_and_wallclock: None,
+            last_written: None,
+            seq_no_offset: 0,
             send_queue: SendQueue::new(),
             unpaced: None,
+            resend_without_rtx: false,
             resends: VecDeque::new(),
             padding: 0,
             blank_packet: RtpPacket::blank(),
-            rtx_cache: RtxCache::new(2000, DEFAULT_RTX_CACHE_DURATION),
+            rtx_cache: {
+                let mut c = RtxCache::new(2000, DEFAULT_RTX_CACHE_DURATION);
+                c.set_max_bytes(DEFAULT_RTX_CACHE_MAX_BYTES);
+                c
+            },
             last_sender_report: already_happened(),
+            rtcp_intervals,
             pending_request_keyframe: None,
+            last_keyframe_request: already_happened(),
+            fir_seq_no: HashMap::new(),
             pending_request_remb: None,
-            stats: StreamTxStats::default(),
+            stats: StreamTxCounters::default(),
             rtx_ratio: (0.0, already_happened()),
             pt_for_padding: None,
+            paused: false,
+            said_goodbye: false,
+            pending_goodbye: false,
+            keep_alive: None,
+            keep_alive_main: false,
+            queue_delay_budget: None,
+            congested: false,
+            need_writable_event: false,
+            csrc: vec![],
         }
     }
 
@@ -197,6 +331,22 @@ impl StreamTx {
         self.rtx
     }
 
+    pub(crate) fn change_ssrc(&mut self, ssrc: Ssrc) {
+        info!(
+            "Change StreamTx SSRC {} -> {} mid: {} rid: {:?}",
+            self.ssrc, ssrc, self.mid, self.rid
+        );
+        self.ssrc = ssrc;
+    }
+
+    pub(crate) fn change_rtx(&mut self, rtx: Ssrc) {
+        info!(
+            "Change StreamTx RTX {:?} -> {} mid: {} rid: {:?}",
+            self.rtx, rtx, self.mid, self.rid
+        );
+        self.rtx = Some(rtx);
+    }
+
     /// Mid for this stream.
     ///
     /// In SDP this corresponds to m-line and "Media".
@@ -217,8 +367,35 @@ impl StreamTx {
     ///
     /// The default is 1024 packets over 3 seconds.
     pub fn set_rtx_cache(&mut self, max_packets: usize, max_age: Duration) {
+        let max_bytes = self.rtx_cache.max_bytes();
         // Dump old cache to avoid having to deal with resizing logic inside the cache impl.
         self.rtx_cache = RtxCache::new(max_packets, max_age);
+        self.rtx_cache.set_max_bytes(max_bytes);
+    }
+
+    /// Change the max age of the RTX (resend) cache, keeping the packets cached so far.
+    pub(crate) fn set_rtx_cache_max_age(&mut self, max_age: Duration) {
+        self.rtx_cache.set_max_age(max_age);
+    }
+
+    /// Limit the RTX (resend) cache by the sum of cached payload bytes.
+    ///
+    /// When the budget is exceeded, the oldest packets are evicted first. This bounds
+    /// the memory used per stream on top of the limits in [`StreamTx::set_rtx_cache()`].
+    ///
+    /// The default is 2MB.
+    pub fn set_rtx_cache_max_bytes(&mut self, max_bytes: usize) {
+        self.rtx_cache.set_max_bytes(max_bytes);
+    }
+
+    /// The RTP time to NTP time mapping we would send in a sender report (SR) at `now`.
+    ///
+    /// This is derived from the last `rtp_time`/`wallclock` pair given to
+    /// [`StreamTx::write_rtp()`] (or the [`Writer`][crate::media::Writer]). The value
+    /// is None until media has been written.
+    pub fn sender_info(&self, now: Instant) -> Option<SenderInfo> {
+        self.current_rtp_time(now)?;
+        Some(self.create_sender_info(now))
     }
 
     /// Set whether this stream is unpaced or not.
@@ -231,6 +408,150 @@ impl StreamTx {
         self.unpaced = Some(unpaced);
     }
 
+    /// Answer NACKs without RTX by resending the original packet on the main SSRC.
+    ///
+    /// Without RTX, str0m normally does not do resends at all. Some endpoints don't
+    /// negotiate RTX, and with this enabled the cached packet is sent again unmodified,
+    /// with its original sequence number, on the main SSRC (RFC 4585 style). Only
+    /// the transport-wide sequence number is reassigned.
+    ///
+    /// This has no effect on streams that have an RTX SSRC.
+    ///
+    /// Defaults to false.
+    pub fn set_resend_without_rtx(&mut self, enabled: bool) {
+        self.resend_without_rtx = enabled;
+    }
+
+    /// Pause or resume sending RTP on this stream.
+    ///
+    /// While paused, written media is dropped and no RTP (including resends and padding)
+    /// is sent, except for the keep-alives configured with [`StreamTx::set_keep_alive()`].
+    /// RTCP sender reports keep flowing, which keeps the stream alive and the RTP/NTP
+    /// mapping up to date for the remote peer. The remote side will observe
+    /// the pause as [`Event::StreamPaused`][crate::Event::StreamPaused].
+    ///
+    /// Each simulcast layer is a separate `StreamTx`, which means this is also how a
+    /// layer is disabled and enabled at runtime.
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused == paused {
+            return;
+        }
+        debug!("StreamTx SSRC {} paused: {}", self.ssrc, paused);
+        self.paused = paused;
+
+        if paused {
+            self.send_queue.clear();
+            self.resends.clear();
+            self.padding = 0;
+        } else {
+            self.said_goodbye = false;
+            self.pending_goodbye = false;
+        }
+    }
+
+    /// Pause sending RTP on this stream and send an RTCP BYE for it.
+    ///
+    /// This is like [`StreamTx::set_paused()`], but instead of keeping the RTCP sender
+    /// reports flowing, the stream says BYE and goes silent, keep-alives included. The
+    /// remote side will observe this as [`Event::StreamEnded`][crate::Event::StreamEnded].
+    ///
+    /// Resume with `set_paused(false)`. The stream keeps its SSRC, and the remote side
+    /// starts receiving it over as a new stream.
+    pub fn set_paused_with_bye(&mut self) {
+        self.set_paused(true);
+
+        if self.said_goodbye {
+            return;
+        }
+
+        debug!("StreamTx SSRC {} BYE on pause", self.ssrc);
+        self.said_goodbye = true;
+        self.pending_goodbye = true;
+    }
+
+    /// Whether this stream is paused by [`StreamTx::set_paused()`].
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Send a keep-alive when no RTP has been sent for the given interval.
+    ///
+    /// This keeps NAT bindings alive when the stream is paused via [`StreamTx::set_paused()`]
+    /// or when the encoder stops producing packets (such as audio DTX). The keep-alive is a
+    /// small padding-only RTP packet. It is sent on the RTX SSRC when RTX is negotiated,
+    /// and otherwise on the main SSRC using the last written payload type and RTP time.
+    ///
+    /// A keep-alive on the main SSRC takes the next sequence number. The sequence numbers
+    /// of all packets written after it are shifted up by one to make room.
+    ///
+    /// The interval is checked at the timing of the regular RTCP reports, so it is not exact.
+    ///
+    /// Defaults to None (no keep-alive).
+    pub fn set_keep_alive(&mut self, interval: Option<Duration>) {
+        self.keep_alive = interval;
+    }
+
+    /// Number of RTP packets queued, waiting to be released by the pacer.
+    pub fn queued_packets(&self) -> usize {
+        self.send_queue.len()
+    }
+
+    /// How long the oldest queued RTP packet has been waiting for the pacer at `now`.
+    ///
+    /// With BWE enabled, the pacer smooths out bursts such as keyframes to the pacing rate.
+    /// A growing queue delay means the encoder produces more than the network can take,
+    /// and should lower its bitrate.
+    pub fn queue_delay(&self, now: Instant) -> Duration {
+        self.send_queue.delay(now)
+    }
+
+    /// Stop accepting writes when the queue delay exceeds the budget.
+    ///
+    /// When the oldest packet waiting for the pacer is older than `budget`, the stream
+    /// stops being writable. [`StreamTx::write_rtp()`] and [`Writer::write()`][crate::media::Writer::write]
+    /// then fail with [`RtcError::SendQueueFull`] until the queue delay has drained to
+    /// half the budget. Each change is emitted as [`Event::StreamWritable`][crate::Event::StreamWritable].
+    /// This lets the application drop frames or lower the encoder bitrate instead of building
+    /// up latency.
+    ///
+    /// Audio streams are never blocked.
+    ///
+    /// Defaults to None (no limit).
+    pub fn set_queue_delay_budget(&mut self, budget: Option<Duration>) {
+        self.queue_delay_budget = budget;
+    }
+
+    /// Set the contributing sources (CSRC) for the following written RTP packets.
+    ///
+    /// A mixer combining the media of several sources lists the SSRC of the sources that
+    /// contributed to the packet payload (RFC 3550 §7.1). The list applies to all packets
+    /// written with [`StreamTx::write_rtp()`] until changed. Use an empty list to stop
+    /// sending contributing sources.
+    ///
+    /// Fails with [`RtpError::TooManyCsrc`] if there are more than 15 sources.
+    pub fn set_csrc(&mut self, csrc: &[Ssrc]) -> Result<(), RtpError> {
+        if csrc.len() > 15 {
+            return Err(RtpError::TooManyCsrc(csrc.len()));
+        }
+        self.csrc.clear();
+        self.csrc.extend_from_slice(csrc);
+        Ok(())
+    }
+
+    /// The contributing sources (CSRC) set on written RTP packets.
+    ///
+    /// See [`StreamTx::set_csrc()`].
+    pub fn csrc(&self) -> &[Ssrc] {
+        &self.csrc
+    }
+
+    /// Whether the stream currently accepts writes.
+    ///
+    /// See [`StreamTx::set_queue_delay_budget()`].
+    pub fn is_writable(&self) -> bool {
+        !self.congested
+    }
+
     /// Write RTP packet to a send stream.
     ///
     /// The `payload` argument is expected to be only the RTP payload, not the RTP packet header.
@@ -252,7 +573,12 @@ impl StreamTx {
     /// * `nackable` Whether we should respond this packet for incoming NACK from the remote peer. For
     ///              audio this is always false. For temporal encoded video, some packets are discardable
     ///              and this flag should be set accordingly.
-    /// * `payload` RTP packet payload, without header.
+    /// * `payload` RTP packet payload, without header. A [`Bytes`][crate::rtp::Bytes] payload is shared
+    ///             with the send queue and resend cache without copying.
+    ///
+    /// Fails with [`RtcError::SendQueueFull`] if the stream is not writable, see
+    /// [`StreamTx::set_queue_delay_budget()`], and with [`RtpError::PayloadTypeOutOfRange`]
+    /// if `pt` doesn't fit in the RTP header.
     #[allow(clippy::too_many_arguments)]
     pub fn write_rtp(
         &mut self,
@@ -263,10 +589,22 @@ impl StreamTx {
         marker: bool,
         ext_vals: ExtensionValues,
         nackable: bool,
-        payload: Vec<u8>,
+        payload: impl Into<Bytes>,
     ) -> Result<(), RtcError> {
+        if self.congested {
+            return Err(RtcError::SendQueueFull);
+        }
+
+        if *pt > 127 {
+            return Err(RtpError::PayloadTypeOutOfRange(pt).into());
+        }
+
         let first_call = self.rtp_and_wallclock.is_none();
 
+        // Make room for the keep-alives sent on the main SSRC.
+        let seq_no: SeqNo = (*seq_no + self.seq_no_offset).into();
+        self.last_written = Some((pt, seq_no));
+
         if first_call && seq_no.roc() > 0 {
             // TODO: make it possible to supress this.
             warn!("First SeqNo has non-zero ROC ({}), which needs out-of-band signalling to remote peer", seq_no.roc());
@@ -276,12 +614,19 @@ impl StreamTx {
         let media_time = MediaTime::from_secs(time as u64);
         self.rtp_and_wallclock = Some((time, wallclock));
 
+        if self.paused {
+            // Keep the RTP/wallclock mapping for SR, but don't send anything.
+            trace!("Drop RTP for paused StreamTx SSRC {}", self.ssrc);
+            return Ok(());
+        }
+
         let header = RtpHeader {
             sequence_number: *seq_no as u16,
             marker,
             payload_type: pt,
             timestamp: time,
             ssrc: self.ssrc,
+            csrc: self.csrc.clone(),
             ext_vals,
             ..Default::default()
         };
@@ -290,7 +635,7 @@ impl StreamTx {
             seq_no,
             time: media_time,
             header,
-            payload,
+            payload: payload.into(),
             nackable,
             // The overall idea for str0m is to only drive time forward from handle_input. If we
             // used a "now" argument to write_rtp(), we effectively get a second point that also need
@@ -310,7 +655,7 @@ impl StreamTx {
         Ok(())
     }
 
-    fn padding_enabled(&self) -> bool {
+    pub(crate) fn padding_enabled(&self) -> bool {
         self.rtx.is_some() && self.pt_for_padding.is_some()
     }
 
@@ -321,16 +666,27 @@ impl StreamTx {
         twcc: &mut u64,
         params: &[PayloadParams],
         buf: &mut Vec<u8>,
+        mtu: usize,
     ) -> Option<PacketReceipt> {
+        // Paused streams only send keep-alive padding. The send queue and resends
+        // are cleared when pausing, and no regular padding is generated.
+        if self.paused && self.padding == 0 && !self.keep_alive_main {
+            return None;
+        }
+
         let mid = self.mid;
         let rid = self.rid;
+        let ssrc = self.ssrc;
         let ssrc_rtx = self.rtx;
+        let in_band = self.resend_in_band();
 
         let (next, is_padding) = if let Some(next) = self.poll_packet_resend(now) {
             (next, false)
         } else if let Some(next) = self.poll_packet_regular(now) {
             (next, false)
-        } else if let Some(next) = self.poll_packet_padding(now) {
+        } else if let Some(next) = self.poll_packet_padding(now, mtu) {
+            (next, true)
+        } else if let Some(next) = self.poll_packet_keep_alive() {
             (next, true)
         } else {
             return None;
@@ -365,7 +721,7 @@ impl StreamTx {
 
         let mut header = match next.kind {
             NextPacketKind::Regular => {
-                let rtx_possible = param.resend().is_some();
+                let rtx_possible = param.resend().is_some() && ssrc_rtx.is_some();
 
                 if rtx_possible {
                     // Remember PT We want to set these directly on `self` here, but can't
@@ -373,6 +729,9 @@ impl StreamTx {
                     // since the above loop figuring out param needs to be correct also
                     // for the NextPacketKind::Blank case.
                     set_pt_for_padding = Some(pt_main);
+                } else if in_band {
+                    // No RTX, but we answer NACKs by resending on the main SSRC. The
+                    // packet stays nackable and ends up in self.rtx_cache.
                 } else {
                     // If the PT we're sending on doesn't have a corresponding RTX PT,
                     // the packet is de-facto not nackable.
@@ -396,6 +755,25 @@ impl StreamTx {
 
                 header_ref.clone()
             }
+            NextPacketKind::KeepAlive(_) => {
+                // Keep-alive without RTX. The blank packet carries the main PT, sequence
+                // number and RTP time.
+                let mut header = header_ref.clone();
+
+                header.ssrc = ssrc;
+                header.sequence_number = *next.seq_no as u16;
+                header.marker = false;
+
+                header.ext_vals.rid = rid;
+                header.ext_vals.rid_repair = None;
+
+                header
+            }
+            NextPacketKind::Resend(_) if in_band => {
+                // The cached header already has the main SSRC and the original
+                // sequence number. Only the extension values below are updated.
+                header_ref.clone()
+            }
             NextPacketKind::Resend(_) | NextPacketKind::Blank(_) => {
                 // * For the Resend case, we will not have accepted/cached the packet unless
                 //   we have a RTX PT (see logic setting next.pkt.nackable above).
@@ -434,11 +812,13 @@ impl StreamTx {
 
         let mut body_out = &mut buf[header_len..];
 
-        // For resends, the original seq_no is inserted before the payload.
+        // For RTX resends, the original seq_no is inserted before the payload.
         let mut original_seq_len = 0;
         if let NextPacketKind::Resend(orig_seq_no) = next.kind {
-            original_seq_len = RtpHeader::write_original_sequence_number(body_out, orig_seq_no);
-            body_out = &mut body_out[original_seq_len..];
+            if !in_band {
+                original_seq_len = RtpHeader::write_original_sequence_number(body_out, orig_seq_no);
+                body_out = &mut body_out[original_seq_len..];
+            }
         }
 
         let pkt = &next.pkt;
@@ -458,7 +838,7 @@ impl StreamTx {
 
                 body_len + original_seq_len + pad_len
             }
-            NextPacketKind::Blank(len) => {
+            NextPacketKind::Blank(len) | NextPacketKind::KeepAlive(len) => {
                 let len = RtpHeader::create_padding_packet(
                     &mut buf[..],
                     header_len,
@@ -544,8 +924,13 @@ impl StreamTx {
         ratio
     }
 
+    fn resend_in_band(&self) -> bool {
+        self.resend_without_rtx && self.rtx.is_none()
+    }
+
     fn poll_packet_resend(&mut self, now: Instant) -> Option<NextPacket<'_>> {
         let ratio = self.rtx_ratio_downsampled(now);
+        let in_band = self.resend_in_band();
 
         // If we hit the cap, stop doing resends by clearing those we have queued.
         if ratio > 0.15_f32 {
@@ -578,10 +963,15 @@ impl StreamTx {
         self.stats.update_packet_counts(len, true);
         self.stats.bytes_retransmitted.push(now, len);
 
-        let seq_no = self.seq_no_rtx.inc();
-
         let orig_seq_no = pkt.seq_no;
 
+        // In-band resends reuse the original sequence number on the main SSRC.
+        let seq_no = if in_band {
+            orig_seq_no
+        } else {
+            self.seq_no_rtx.inc()
+        };
+
         Some(NextPacket {
             kind: NextPacketKind::Resend(orig_seq_no),
             seq_no,
@@ -610,7 +1000,7 @@ impl StreamTx {
         })
     }
 
-    fn poll_packet_padding(&mut self, _now: Instant) -> Option<NextPacket> {
+    fn poll_packet_padding(&mut self, _now: Instant, mtu: usize) -> Option<NextPacket> {
         if !self.padding_enabled() {
             self.padding = 0;
             return None;
@@ -625,7 +1015,7 @@ impl StreamTx {
             if self.padding > MIN_SPURIOUS_PADDING_SIZE {
                 // Find a historic packet that is smaller than this max size. The max size
                 // is a headroom since we can accept slightly larger padding than asked for.
-                let max_size = (self.padding * 2).min(DATAGRAM_MTU_WARN - MAX_RTP_OVERHEAD);
+                let max_size = (self.padding * 2).min(mtu - MAX_RTP_OVERHEAD);
 
                 let Some(pkt) = self.rtx_cache.get_cached_packet_smaller_than(max_size) else {
                     // Couldn't find spurious packet, try a blank packet instead.
@@ -636,6 +1026,7 @@ impl StreamTx {
                 let seq_no = self.seq_no_rtx.inc();
 
                 self.padding = self.padding.saturating_sub(pkt.payload.len());
+                self.stats.update_padding_counts(pkt.payload.len() as u64);
 
                 return Some(NextPacket {
                     kind: NextPacketKind::Resend(orig_seq_no),
@@ -658,6 +1049,7 @@ impl StreamTx {
         assert!(len <= 255); // should fit in a byte
 
         self.padding = self.padding.saturating_sub(len);
+        self.stats.update_padding_counts(len as u64);
 
         Some(NextPacket {
             kind: NextPacketKind::Blank(len as u8),
@@ -666,12 +1058,50 @@ impl StreamTx {
         })
     }
 
+    fn poll_packet_keep_alive(&mut self) -> Option<NextPacket> {
+        if !self.keep_alive_main {
+            return None;
+        }
+        self.keep_alive_main = false;
+
+        let (pt, last_seq_no) = self.last_written?;
+        let (time, _) = self.rtp_and_wallclock?;
+
+        let seq_no: SeqNo = (*last_seq_no + 1).into();
+        self.last_written = Some((pt, seq_no));
+        self.seq_no_offset += 1;
+
+        let len = SRTP_BLOCK_SIZE;
+        self.stats.update_padding_counts(len as u64);
+
+        let pkt = &mut self.blank_packet;
+        pkt.seq_no = seq_no;
+        pkt.header.payload_type = pt;
+        pkt.header.timestamp = time;
+
+        Some(NextPacket {
+            kind: NextPacketKind::KeepAlive(len as u8),
+            seq_no,
+            pkt,
+        })
+    }
+
     pub(crate) fn sender_report_at(&self) -> Instant {
+        if self.pending_goodbye {
+            // Send the BYE straight away.
+            return already_happened();
+        }
+        if self.said_goodbye {
+            return not_happening();
+        }
         let Some(kind) = self.kind else {
             // First handle_timeout sets the kind. No sender report until then.
             return not_happening();
         };
-        self.last_sender_report + rr_interval(kind.is_audio())
+        let c = &self.stats;
+        let bytes_last_second = c.bytes_transmitted.sum() + c.bytes_retransmitted.sum();
+        let bitrate = Bitrate::bps(bytes_last_second * 8);
+        self.last_sender_report + self.rtcp_intervals.get(kind.is_audio(), bitrate, true)
     }
 
     pub(crate) fn poll_keyframe_request(&mut self) -> Option<KeyframeRequestKind> {
@@ -693,11 +1123,16 @@ impl StreamTx {
             }
             Pli(_) => {
                 self.stats.increase_plis();
-                self.pending_request_keyframe = Some(KeyframeRequestKind::Pli);
+                self.handle_keyframe_request(now, KeyframeRequestKind::Pli);
             }
-            Fir(_) => {
+            Fir((entry, sender_ssrc)) => {
                 self.stats.increase_firs();
-                self.pending_request_keyframe = Some(KeyframeRequestKind::Fir);
+                let prev = self.fir_seq_no.insert(sender_ssrc, entry.seq_no);
+                if prev == Some(entry.seq_no) {
+                    trace!(mid = %self.mid, ssrc = %self.ssrc, "Drop repeated FIR {}", entry.seq_no);
+                    return;
+                }
+                self.handle_keyframe_request(now, KeyframeRequestKind::Fir);
             }
             Remb(r) => {
                 self.pending_request_remb = Some(Bitrate::from(r.bitrate as f64));
@@ -707,23 +1142,57 @@ impl StreamTx {
         }
     }
 
+    fn handle_keyframe_request(&mut self, now: Instant, kind: KeyframeRequestKind) {
+        if let Some(pending) = &mut self.pending_request_keyframe {
+            // Not polled yet, merge into the pending one.
+            if kind == KeyframeRequestKind::Fir {
+                *pending = kind;
+            }
+            return;
+        }
+
+        if now < self.last_keyframe_request + self.rtcp_intervals.keyframe_request {
+            trace!(mid = %self.mid, ssrc = %self.ssrc, "Drop {:?} within interval", kind);
+            return;
+        }
+
+        self.pending_request_keyframe = Some(kind);
+        self.last_keyframe_request = now;
+    }
+
     pub(crate) fn handle_nack(
         &mut self,
         entries: impl Iterator<Item = NackEntry>,
         now: Instant,
     ) -> Option<()> {
+        if self.paused {
+            return None;
+        }
+
         // Turning NackEntry into SeqNo we need to know a SeqNo "close by" to lengthen the 16 bit
         // sequence number into the 64 bit we have in SeqNo.
-        let seq_no = self.rtx_cache.last_cached_seq_no()?;
+        let Some(seq_no) = self.rtx_cache.last_cached_seq_no() else {
+            // Nothing cached, none of the requested packets are available.
+            self.stats.nacked_packets +=
+                entries.map(|n| 1 + n.blp.count_ones() as u64).sum::<u64>();
+            return None;
+        };
         let iter = entries.flat_map(|n| n.into_iter(seq_no));
 
         // Schedule all resends. They will be handled on next poll_packet
         for seq_no in iter {
+            self.stats.nacked_packets += 1;
+
             let Some(packet) = self.rtx_cache.get_cached_packet_by_seq_no(seq_no) else {
                 // Packet was not available in RTX cache, it has probably expired.
+                trace!(mid = %self.mid, ssrc = %self.ssrc, %seq_no, "NACK for uncached packet");
                 continue;
             };
 
+            self.stats.nacked_packets_cached += 1;
+
+            trace!(mid = %self.mid, ssrc = %self.ssrc, %seq_no, "Schedule NACK resend");
+
             let resend = Resend {
                 seq_no,
                 queued_at: now,
@@ -736,13 +1205,18 @@ impl StreamTx {
     }
 
     pub(crate) fn need_sr(&self, now: Instant) -> bool {
-        now >= self.sender_report_at()
+        !self.said_goodbye && now >= self.sender_report_at()
     }
 
     pub(crate) fn create_sr_and_update(&mut self, now: Instant, feedback: &mut VecDeque<Rtcp>) {
+        // Other streams of the same mid might need a report, but not one that said BYE.
+        if self.said_goodbye {
+            return;
+        }
+
         let sr = self.create_sender_report(now);
 
-        trace!("Created feedback SR: {:?}", sr);
+        trace!(mid = %self.mid, ssrc = %self.ssrc, "Created feedback SR: {:?}", sr);
         feedback.push_back(Rtcp::SenderReport(sr));
 
         if let Some(ds) = self.create_sdes() {
@@ -753,9 +1227,23 @@ impl StreamTx {
         self.last_sender_report = now;
     }
 
+    pub(crate) fn maybe_create_goodbye(&mut self, feedback: &mut VecDeque<Rtcp>) {
+        if !self.pending_goodbye {
+            return;
+        }
+        self.pending_goodbye = false;
+
+        for ssrc in [Some(self.ssrc), self.rtx].into_iter().flatten() {
+            feedback.push_back(Rtcp::Goodbye(Goodbye {
+                reports: ssrc.into(),
+                reason: None,
+            }));
+        }
+    }
+
     fn create_sender_report(&self, now: Instant) -> SenderReport {
         SenderReport {
-            sender_info: self.sender_info(now),
+            sender_info: self.create_sender_info(now),
             reports: ReportList::new(),
         }
     }
@@ -777,7 +1265,7 @@ impl StreamTx {
         Some(d)
     }
 
-    fn sender_info(&self, now: Instant) -> SenderInfo {
+    fn create_sender_info(&self, now: Instant) -> SenderInfo {
         let rtp_time = self.current_rtp_time(now).unwrap_or(MediaTime::ZERO);
 
         SenderInfo {
@@ -812,10 +1300,12 @@ impl StreamTx {
         Some(rtp_time.rebase(clock_rate))
     }
 
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
     pub(crate) fn next_seq_no(&mut self) -> SeqNo {
         self.seq_no.inc()
     }
 
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
     pub(crate) fn last_packet(&self) -> Option<&[u8]> {
         if self.send_queue.is_empty() {
             self.rtx_cache.last_packet()
@@ -824,8 +1314,44 @@ impl StreamTx {
         }
     }
 
-    pub(crate) fn visit_stats(&mut self, snapshot: &mut StatsSnapshot, now: Instant) {
-        self.stats.fill(snapshot, self.mid, self.rid, now);
+    /// Current statistics for this stream.
+    pub fn stats(&self) -> StreamTxStats {
+        let c = &self.stats;
+
+        let bytes_last_second = c.bytes_transmitted.sum() + c.bytes_retransmitted.sum();
+
+        let rr = c.last_rr.as_ref();
+        let remote_jitter = rr.zip(self.clock_rate).map(|(rr, clock_rate)| {
+            Duration::from_secs_f64(rr.jitter as f64 / clock_rate.get() as f64)
+        });
+
+        StreamTxStats {
+            bytes: c.bytes,
+            packets: c.packets,
+            bytes_resent: c.bytes_resent,
+            packets_resent: c.packets_resent,
+            bytes_padding: c.bytes_padding,
+            packets_padding: c.packets_padding,
+            bitrate: Bitrate::bps(bytes_last_second * 8),
+            firs: c.firs,
+            plis: c.plis,
+            nacks: c.nacks,
+            nacked_packets: c.nacked_packets,
+            nacked_packets_cached: c.nacked_packets_cached,
+            rtt: c.rtt,
+            remote_loss: rr.map(|rr| rr.fraction_lost as f32 / u8::MAX as f32),
+            remote_packets_lost: rr.map(|rr| rr.packets_lost),
+            remote_jitter,
+        }
+    }
+
+    pub(crate) fn visit_stats(
+        &mut self,
+        snapshot: &mut StatsSnapshot,
+        now: Instant,
+        advance: bool,
+    ) {
+        self.stats.fill(snapshot, self.mid, self.rid, now, advance);
     }
 
     pub(crate) fn queue_state(&mut self, now: Instant) -> QueueState {
@@ -883,7 +1409,13 @@ impl StreamTx {
     }
 
     fn queue_state_padding(&self, now: Instant) -> Option<QueueSnapshot> {
-        if self.padding == 0 {
+        let size = if self.keep_alive_main {
+            SRTP_BLOCK_SIZE
+        } else {
+            self.padding
+        };
+
+        if size == 0 {
             return None;
         }
 
@@ -891,13 +1423,14 @@ impl StreamTx {
         const AVERAGE_PADDING_PACKET_SIZE: usize = 800;
         const FAKE_PADDING_DURATION_MILLIS: usize = 5;
 
-        let fake_packets = self.padding / AVERAGE_PADDING_PACKET_SIZE;
+        // At least one packet, or the pacer never polls small amounts such as a keep-alive.
+        let fake_packets = (size / AVERAGE_PADDING_PACKET_SIZE).max(1);
         let fake_millis = fake_packets * FAKE_PADDING_DURATION_MILLIS;
         let fake_duration = Duration::from_millis(fake_millis as u64);
 
         Some(QueueSnapshot {
             created_at: now,
-            size: self.padding,
+            size,
             packet_count: fake_packets as u32,
             total_queue_time_origin: fake_duration,
             priority: QueuePriority::Padding,
@@ -905,8 +1438,12 @@ impl StreamTx {
         })
     }
 
+    pub(crate) fn last_used(&self) -> Instant {
+        self.last_used
+    }
+
     pub(crate) fn generate_padding(&mut self, padding: usize) {
-        if !self.padding_enabled() {
+        if !self.padding_enabled() || self.paused {
             return;
         }
         self.padding += padding;
@@ -920,6 +1457,7 @@ impl StreamTx {
         &mut self,
         now: Instant,
         get_media: impl FnOnce() -> (&'a Media, &'a CodecConfig),
+        observer: Option<&Observer>,
     ) {
         // If kind is None, this is the first time we ever get a handle_timeout.
         if self.kind.is_none() {
@@ -927,7 +1465,88 @@ impl StreamTx {
             self.on_first_timeout(media, config);
         }
 
-        self.send_queue.handle_timeout(now);
+        let mid = self.mid;
+        self.send_queue.handle_timeout(now, |p| {
+            if let Some(o) = observer {
+                let packet = ObservedPacket::rtp(PacketStage::Queued, now, &p.header, &p.payload);
+                o.observe(&packet.with_mid(mid).with_seq_no(p.seq_no));
+            }
+        });
+
+        self.maybe_keep_alive(now);
+
+        self.update_congested(now);
+    }
+
+    fn update_congested(&mut self, now: Instant) {
+        let congested = match self.queue_delay_budget {
+            // Audio is small and latency sensitive, never block it.
+            Some(budget) if self.kind != Some(MediaKind::Audio) => {
+                let delay = self.send_queue.delay(now);
+                if self.congested {
+                    // Hysteresis to not flap around the budget.
+                    delay > budget / 2
+                } else {
+                    delay > budget
+                }
+            }
+            _ => false,
+        };
+
+        if congested != self.congested {
+            debug!(
+                "StreamTx SSRC {} writable: {} queue delay: {:?}",
+                self.ssrc,
+                !congested,
+                self.send_queue.delay(now)
+            );
+            self.congested = congested;
+            self.need_writable_event = true;
+        }
+    }
+
+    pub(crate) fn poll_writable(&mut self) -> Option<StreamWritable> {
+        if !self.need_writable_event {
+            return None;
+        }
+
+        self.need_writable_event = false;
+
+        Some(StreamWritable {
+            ssrc: self.ssrc,
+            mid: self.mid,
+            rid: self.rid,
+            writable: !self.congested,
+        })
+    }
+
+    fn maybe_keep_alive(&mut self, now: Instant) {
+        let Some(interval) = self.keep_alive else {
+            return;
+        };
+
+        // No keep-alive before we sent any media.
+        if self.rtp_and_wallclock.is_none() || self.padding > 0 || self.keep_alive_main {
+            return;
+        }
+
+        // The remote side was told this SSRC is gone.
+        if self.said_goodbye {
+            return;
+        }
+
+        if now.saturating_duration_since(self.last_used) < interval {
+            return;
+        }
+
+        trace!("Keep-alive for StreamTx SSRC {}", self.ssrc);
+
+        if self.padding_enabled() {
+            self.padding = SRTP_BLOCK_SIZE;
+        } else {
+            // Without RTX, the keep-alive goes on the main SSRC.
+            self.keep_alive_main = true;
+        }
     }
 
     fn on_first_timeout(&mut self, media: &Media, config: &CodecConfig) {
@@ -967,7 +1586,7 @@ impl StreamTx {
     }
 }
 
-impl StreamTxStats {
+impl StreamTxCounters {
     fn update_packet_counts(&mut self, bytes: u64, is_resend: bool) {
         self.packets += 1;
         self.bytes += bytes;
@@ -977,6 +1596,11 @@ impl StreamTxStats {
         }
     }
 
+    fn update_padding_counts(&mut self, bytes: u64) {
+        self.packets_padding += 1;
+        self.bytes_padding += bytes;
+    }
+
     fn increase_nacks(&mut self) {
         self.nacks += 1;
     }
@@ -1002,6 +1626,7 @@ impl StreamTxStats {
 
         self.losses
             .push((ext_seq, r.fraction_lost as f32 / u8::MAX as f32));
+        self.last_rr = Some(r);
     }
 
     pub(crate) fn fill(
@@ -1010,6 +1635,7 @@ impl StreamTxStats {
         mid: Mid,
         rid: Option<Rid>,
         now: Instant,
+        advance: bool,
     ) {
         if self.bytes == 0 {
             return;
@@ -1036,7 +1662,10 @@ impl StreamTxStats {
             result.is_finite().then_some(result)
         };
 
-        self.losses.drain(..self.losses.len().saturating_sub(1));
+        if advance {
+            // Keep the last RR as the start of the next interval.
+            self.losses.drain(..self.losses.len().saturating_sub(1));
+        }
 
         snapshot.egress.insert(
             key,
@@ -1045,9 +1674,13 @@ impl StreamTxStats {
                 rid,
                 bytes: self.bytes,
                 packets: self.packets,
+                bytes_padding: self.bytes_padding,
+                packets_padding: self.packets_padding,
                 firs: self.firs,
                 plis: self.plis,
                 nacks: self.nacks,
+                nacked_packets: self.nacked_packets,
+                nacked_packets_cached: self.nacked_packets_cached,
                 rtt: self.rtt,
                 loss,
                 timestamp: now,
@@ -1067,6 +1700,7 @@ enum NextPacketKind {
     Regular,
     Resend(SeqNo),
     Blank(u8),
+    KeepAlive(u8),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -1075,3 +1709,90 @@ struct Resend {
     queued_at: Instant,
     payload_size: usize,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::rtp_::FirEntry;
+
+    use super::*;
+
+    fn stream() -> StreamTx {
+        let intervals = RtcpIntervals {
+            audio: RtcpIntervals::DEFAULT_AUDIO,
+            video: RtcpIntervals::DEFAULT_VIDEO,
+            keyframe_request: Duration::ZERO,
+            bandwidth: None,
+        };
+        StreamTx::new(1.into(), None, Mid::from("0"), None, intervals)
+    }
+
+    fn fir(sender_ssrc: u32, seq_no: u8) -> RtcpFb {
+        let entry = FirEntry {
+            ssrc: 1.into(),
+            seq_no,
+        };
+        RtcpFb::Fir((entry, sender_ssrc.into()))
+    }
+
+    #[test]
+    fn fir_repeated_seq_no() {
+        let mut s = stream();
+        let now = Instant::now();
+
+        s.handle_rtcp(now, fir(10, 0));
+        assert_eq!(s.poll_keyframe_request(), Some(KeyframeRequestKind::Fir));
+
+        // Retransmission of the same FIR.
+        s.handle_rtcp(now, fir(10, 0));
+        assert_eq!(s.poll_keyframe_request(), None);
+
+        // Same seq_no, but from another requester.
+        s.handle_rtcp(now, fir(11, 0));
+        assert_eq!(s.poll_keyframe_request(), Some(KeyframeRequestKind::Fir));
+
+        // Next request.
+        s.handle_rtcp(now, fir(10, 1));
+        assert_eq!(s.poll_keyframe_request(), Some(KeyframeRequestKind::Fir));
+
+        // All of them are counted.
+        assert_eq!(s.stats().firs, 4);
+    }
+
+    #[test]
+    fn write_rtp_shares_payload() {
+        let mut s = stream();
+        let now = Instant::now();
+
+        let payload = Bytes::from(vec![1_u8; 100]);
+        s.write_rtp(
+            96.into(),
+            0.into(),
+            0,
+            now,
+            false,
+            ExtensionValues::default(),
+            true,
+            payload.clone(),
+        )
+        .unwrap();
+
+        // The queued packet refers to the same memory as the written payload.
+        let queued = s.send_queue.last().unwrap();
+        assert_eq!(queued.payload.as_ptr(), payload.as_ptr());
+        assert_eq!(queued.payload.len(), 100);
+
+        // A Vec<u8> is still accepted.
+        s.write_rtp(
+            96.into(),
+            1.into(),
+            0,
+            now,
+            false,
+            ExtensionValues::default(),
+            true,
+            vec![2_u8; 10],
+        )
+        .unwrap();
+        assert_eq!(s.queued_packets(), 2);
+    }
+}
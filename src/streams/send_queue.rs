@@ -1,8 +1,9 @@
 use std::collections::VecDeque;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::packet::{QueuePriority, QueueSnapshot};
 use crate::util::not_happening;
+use crate::util::Instant;
 
 use super::RtpPacket;
 
@@ -30,15 +31,20 @@ impl SendQueue {
         self.queue.push_back(packet);
     }
 
-    pub fn handle_timeout(&mut self, now: Instant) {
-        for pkt in self.queue.iter_mut().rev() {
-            if pkt.timestamp != not_happening() {
-                // all enqueued packets are timestamped.
-                break;
-            } else {
-                pkt.timestamp = now;
-                self.total.increase(now, pkt.payload.len());
-            }
+    pub fn handle_timeout(&mut self, now: Instant, mut on_queued: impl FnMut(&RtpPacket)) {
+        // Packets are timestamped in order, so the ones needing it are at the end.
+        let unstamped = self
+            .queue
+            .iter()
+            .rev()
+            .take_while(|p| p.timestamp == not_happening())
+            .count();
+        let first = self.queue.len() - unstamped;
+
+        for pkt in self.queue.range_mut(first..) {
+            pkt.timestamp = now;
+            self.total.increase(now, pkt.payload.len());
+            on_queued(pkt);
         }
     }
 
@@ -73,10 +79,25 @@ impl SendQueue {
         Some(packet)
     }
 
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Time the oldest (timestamped) packet has been waiting.
+    pub fn delay(&self, now: Instant) -> Duration {
+        self.queue
+            .iter()
+            .find(|p| p.timestamp != not_happening())
+            .map(|p| now.saturating_duration_since(p.timestamp))
+            .unwrap_or_default()
+    }
+
+    #[cfg_attr(not(feature = "sample-api"), allow(dead_code))]
     pub fn last(&self) -> Option<&RtpPacket> {
         self.queue.back()
     }
@@ -198,6 +219,8 @@ impl TotalQueue {
 
 #[cfg(test)]
 mod test {
+    use bytes::Bytes;
+
     use crate::rtp_::MediaTime;
     use crate::rtp_::RtpHeader;
 
@@ -211,7 +234,7 @@ mod test {
             seq_no: 0.into(),
             time: MediaTime::from_90khz(10),
             header: RtpHeader::default(),
-            payload: vec![],
+            payload: Bytes::new(),
             timestamp: Instant::now(),
             last_sender_info: None,
             nackable: true,
@@ -246,13 +269,13 @@ mod test {
             seq_no: 0.into(),
             time: MediaTime::from_90khz(10),
             header: RtpHeader::default(),
-            payload: vec![42, 42],
+            payload: vec![42, 42].into(),
             timestamp: start,
             last_sender_info: None,
             nackable: true,
         });
 
-        queue.handle_timeout(start);
+        queue.handle_timeout(start, |_| {});
 
         assert!(queue.peek().is_some());
         assert!(!queue.need_timeout());
@@ -0,0 +1,270 @@
+//! Async adapter driving an [`Rtc`] on a tokio runtime.
+//!
+//! Enable with the `tokio` feature.
+//!
+//! str0m is sans-IO, which means the user normally writes the event loop that moves data
+//! between the UDP socket and [`Rtc::handle_input()`] / [`Rtc::poll_output()`]. This module
+//! is that loop for users that are happy with a single UDP socket and tokio.
+//!
+//! [`RtcHandle::spawn()`] moves the `Rtc` and the socket into a tokio task. The returned
+//! handle is used to receive events and make changes to the `Rtc`.
+//!
+//! ```no_run
+//! # #[cfg(feature = "sctp")]
+//! # async fn run() -> Result<(), str0m::RtcError> {
+//! use std::time::Instant;
+//! use tokio::net::UdpSocket;
+//! use str0m::{Candidate, Event, Rtc};
+//! use str0m::tokio::RtcHandle;
+//!
+//! // The socket address is the candidate the remote peer sends to, which means it
+//! // can't be an unspecified address like 0.0.0.0.
+//! let socket = UdpSocket::bind("192.168.1.10:0").await?;
+//! let addr = socket.local_addr()?;
+//!
+//! let mut rtc = Rtc::new();
+//! rtc.add_local_candidate(Candidate::host(addr, "udp")?);
+//! // SDP negotiation...
+//!
+//! let (mut handle, _task) = RtcHandle::spawn(rtc, socket);
+//!
+//! while let Some(event) = handle.next_event().await {
+//!     if let Event::ChannelOpen(id, _) = event {
+//!         handle.channel_send(id, false, b"hello".to_vec()).await?;
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use ::tokio::net::UdpSocket;
+use ::tokio::sync::{mpsc, oneshot};
+use ::tokio::task::JoinHandle;
+use ::tokio::time::sleep_until;
+
+#[cfg(feature = "sctp")]
+use crate::channel::ChannelId;
+#[cfg(feature = "sample-api")]
+use crate::media::{MediaTime, Mid, Pt};
+use crate::net::{DatagramRecv, Protocol, Receive};
+use crate::{Event, Input, Output, Rtc, RtcError};
+
+/// Max number of events buffered before the task waits for [`RtcHandle::next_event()`].
+const EVENT_BUFFER: usize = 256;
+
+/// Max number of queued calls from the handle to the task.
+const COMMAND_BUFFER: usize = 64;
+
+type Command = Box<dyn FnOnce(&mut Rtc) + Send>;
+
+/// Handle to an [`Rtc`] driven by a tokio task.
+///
+/// Dropping the handle stops the task.
+pub struct RtcHandle {
+    commands: mpsc::Sender<Command>,
+    events: mpsc::Receiver<Event>,
+}
+
+impl RtcHandle {
+    /// Spawn a task on the current tokio runtime driving `rtc` with `socket`.
+    ///
+    /// The address of `socket` must be a local candidate of `rtc`. It's the destination of
+    /// incoming datagrams, which are matched against the local candidates. A socket bound to
+    /// an unspecified address, like `0.0.0.0`, ends the task with an error.
+    ///
+    /// After [`Rtc::disconnect()`], the task keeps running to complete the orderly close
+    /// (RTCP BYE, SCTP shutdown and DTLS close_notify). It ends once [`Event::Closed`] is
+    /// delivered to [`RtcHandle::next_event()`], or when the handle is dropped.
+    /// Errors from the `Rtc` or the socket ends the task and are returned via the `JoinHandle`.
+    pub fn spawn(rtc: Rtc, socket: UdpSocket) -> (RtcHandle, JoinHandle<Result<(), RtcError>>) {
+        let (commands_tx, commands_rx) = mpsc::channel(COMMAND_BUFFER);
+        let (events_tx, events_rx) = mpsc::channel(EVENT_BUFFER);
+
+        let task = ::tokio::spawn(run(rtc, socket, commands_rx, events_tx));
+
+        let handle = RtcHandle {
+            commands: commands_tx,
+            events: events_rx,
+        };
+
+        (handle, task)
+    }
+
+    /// Wait for the next event.
+    ///
+    /// Returns `None` when the task has stopped.
+    pub async fn next_event(&mut self) -> Option<Event> {
+        self.events.recv().await
+    }
+
+    /// Run a closure with exclusive access to the `Rtc` in the task.
+    ///
+    /// This is the way to do anything not covered by the other functions of the handle,
+    /// such as SDP negotiation. The task handles the output caused by the closure before
+    /// waiting for network input again.
+    ///
+    /// Calls are handled before network input, also while the task waits for room in a
+    /// full event buffer. It's therefore fine to await a call in a loop reading
+    /// [`RtcHandle::next_event()`].
+    pub async fn call<F, R>(&self, f: F) -> Result<R, RtcError>
+    where
+        F: FnOnce(&mut Rtc) -> Result<R, RtcError> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+
+        let command: Command = Box::new(move |rtc| {
+            let _ = tx.send(f(rtc));
+        });
+
+        self.commands.send(command).await.map_err(|_| stopped())?;
+
+        rx.await.map_err(|_| stopped())?
+    }
+
+    /// Write media to the `mid`. See [`Writer::write()`][crate::media::Writer::write].
+    #[cfg(feature = "sample-api")]
+    pub async fn write(
+        &self,
+        mid: Mid,
+        pt: Pt,
+        wallclock: Instant,
+        rtp_time: MediaTime,
+        data: Vec<u8>,
+    ) -> Result<(), RtcError> {
+        self.call(move |rtc| {
+            let writer = rtc.writer(mid).ok_or(RtcError::NoSenderSource)?;
+            writer.write(pt, wallclock, rtp_time, data)
+        })
+        .await
+    }
+
+    /// Send data on a channel. See [`Channel::write()`][crate::channel::Channel::write].
+    ///
+    /// Returns the number of bytes written.
+    #[cfg(feature = "sctp")]
+    pub async fn channel_send(
+        &self,
+        id: ChannelId,
+        binary: bool,
+        data: Vec<u8>,
+    ) -> Result<usize, RtcError> {
+        self.call(move |rtc| {
+            let mut channel = rtc.channel(id).ok_or_else(|| {
+                RtcError::Io(io::Error::new(io::ErrorKind::NotFound, "channel not open"))
+            })?;
+            channel.write(binary, &data)
+        })
+        .await
+    }
+}
+
+fn stopped() -> RtcError {
+    RtcError::Io(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "rtc task stopped",
+    ))
+}
+
+async fn run(
+    mut rtc: Rtc,
+    socket: UdpSocket,
+    mut commands: mpsc::Receiver<Command>,
+    events: mpsc::Sender<Event>,
+) -> Result<(), RtcError> {
+    let local: SocketAddr = socket.local_addr()?;
+    if local.ip().is_unspecified() {
+        return Err(RtcError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "socket bound to an unspecified address",
+        )));
+    }
+
+    let mut buf = vec![0; 2000];
+
+    loop {
+        let timeout = loop {
+            match rtc.poll_output()? {
+                Output::Timeout(t) => break t,
+                Output::Transmit(t) => {
+                    socket.send_to(&t.contents, t.destination).await?;
+                    rtc.recycle_transmit(t.contents);
+                }
+                Output::Event(e) => {
+                    let closed = matches!(e, Event::Closed);
+
+                    // Keep handling commands while waiting for room in the event buffer.
+                    // The caller might await a call() before it reads the next event.
+                    let permit = loop {
+                        ::tokio::select! {
+                            biased;
+                            command = commands.recv() => {
+                                let Some(command) = command else {
+                                    // Handle is dropped.
+                                    return Ok(());
+                                };
+                                command(&mut rtc);
+                            }
+                            permit = events.reserve() => break permit,
+                        }
+                    };
+
+                    let Ok(permit) = permit else {
+                        // Handle is dropped.
+                        return Ok(());
+                    };
+                    permit.send(e);
+
+                    if closed {
+                        // The orderly close after disconnect() is done.
+                        return Ok(());
+                    }
+                }
+            }
+        };
+
+        // Sleeping until a converted Instant works since tokio's Instant wraps std's.
+        let deadline = ::tokio::time::Instant::from_std(timeout);
+
+        ::tokio::select! {
+            biased;
+            command = commands.recv() => {
+                let Some(command) = command else {
+                    // Handle is dropped.
+                    return Ok(());
+                };
+                command(&mut rtc);
+            }
+            _ = sleep_until(deadline) => {
+                rtc.handle_input(Input::Timeout(Instant::now()))?;
+            }
+            r = socket.recv_from(&mut buf) => {
+                let (n, source) = r?;
+
+                let contents: DatagramRecv = match (&buf[..n]).try_into() {
+                    Ok(v) => v,
+                    Err(e) => {
+                        debug!("Ignore datagram from {}: {:?}", source, e);
+                        continue;
+                    }
+                };
+
+                let input = Input::Receive(
+                    Instant::now(),
+                    Receive {
+                        proto: Protocol::Udp,
+                        source,
+                        destination: local,
+                        contents,
+                    },
+                );
+
+                rtc.handle_input(input)?;
+            }
+        }
+    }
+}
@@ -1,4 +1,12 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
+
+// There is no clock in std on wasm32-unknown-unknown, where `Instant::now()` and
+// `SystemTime::now()` panic. web-time provides the same types backed by the browser.
+// Everything in str0m uses these rather than `std::time` directly.
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+pub use std::time::{Instant, SystemTime};
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+pub use web_time::{Instant, SystemTime};
 
 mod bit_pattern;
 
@@ -6,8 +14,17 @@ pub(crate) use bit_pattern::BitPattern;
 
 pub(crate) mod value_history;
 
+mod pool;
+pub(crate) use pool::BufferPool;
+
+mod rtt;
+pub(crate) use rtt::RttEstimator;
+
 mod time_tricks;
-pub(crate) use time_tricks::{already_happened, epoch_to_beginning, not_happening, InstantExt};
+pub(crate) use time_tricks::{
+    already_happened, beginning_instant, epoch_to_beginning, not_happening, InstantExt,
+};
+pub(crate) use time_tricks::{TimeAnchor, TimeAnchorGuard};
 
 pub(crate) trait Soonest {
     fn soonest(self, other: Self) -> Self;
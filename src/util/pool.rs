@@ -0,0 +1,93 @@
+use crate::stats::BufferPoolStats;
+
+/// Pool of reusable buffers for the outgoing packet path.
+///
+/// Buffers handed out are empty, with at least `buf_size` capacity. Buffers are
+/// returned with [`BufferPool::put()`], and dropped if the pool is full or the buffer
+/// has shrunk below `buf_size`.
+#[derive(Debug)]
+pub(crate) struct BufferPool {
+    free: Vec<Vec<u8>>,
+    max: usize,
+    buf_size: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl BufferPool {
+    pub fn new(max: usize, buf_size: usize) -> Self {
+        BufferPool {
+            free: Vec::with_capacity(max),
+            max,
+            buf_size,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Change the minimum capacity of buffers. Smaller buffers in the pool are dropped.
+    pub fn set_buf_size(&mut self, buf_size: usize) {
+        self.buf_size = buf_size;
+        self.free.retain(|b| b.capacity() >= buf_size);
+    }
+
+    pub fn get(&mut self) -> Vec<u8> {
+        if let Some(mut buf) = self.free.pop() {
+            self.hits += 1;
+            buf.clear();
+            buf
+        } else {
+            self.misses += 1;
+            Vec::with_capacity(self.buf_size)
+        }
+    }
+
+    pub fn put(&mut self, buf: Vec<u8>) {
+        if self.free.len() < self.max && buf.capacity() >= self.buf_size {
+            self.free.push(buf);
+        }
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        BufferPoolStats {
+            hits: self.hits,
+            misses: self.misses,
+            available: self.free.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reuse_and_limits() {
+        let mut pool = BufferPool::new(1, 100);
+
+        let mut a = pool.get();
+        assert!(a.capacity() >= 100);
+        a.extend_from_slice(&[1, 2, 3]);
+        let ptr = a.as_ptr();
+
+        let b = pool.get();
+        pool.put(a);
+        // Pool is full.
+        pool.put(b);
+        // Too small.
+        pool.put(Vec::with_capacity(10));
+
+        let c = pool.get();
+        assert!(c.is_empty());
+        assert_eq!(c.as_ptr(), ptr);
+
+        assert_eq!(
+            pool.stats(),
+            BufferPoolStats {
+                hits: 1,
+                misses: 2,
+                available: 0,
+            }
+        );
+    }
+}
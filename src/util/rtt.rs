@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::stats::RttStats;
+use crate::util::Instant;
+
+/// Window for the min/max RTT.
+const RTT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Smoothed round trip time from samples of different sources (STUN, RTCP DLSR/DLRR).
+///
+/// The smoothing is the same as the SRTT of RFC 6298, with an alpha of 1/8.
+#[derive(Debug, Default)]
+pub(crate) struct RttEstimator {
+    smoothed: Option<Duration>,
+    latest: Option<Duration>,
+    history: VecDeque<(Instant, Duration)>,
+}
+
+impl RttEstimator {
+    pub fn update(&mut self, now: Instant, sample: Duration) {
+        self.smoothed = Some(match self.smoothed {
+            Some(s) => (s * 7 + sample) / 8,
+            None => sample,
+        });
+        self.latest = Some(sample);
+
+        self.history.push_back((now, sample));
+        while let Some((t, _)) = self.history.front() {
+            if now.saturating_duration_since(*t) <= RTT_WINDOW {
+                break;
+            }
+            self.history.pop_front();
+        }
+    }
+
+    pub fn smoothed(&self) -> Option<Duration> {
+        self.smoothed
+    }
+
+    pub fn stats(&self) -> Option<RttStats> {
+        let min = self.history.iter().map(|(_, r)| *r).min()?;
+        let max = self.history.iter().map(|(_, r)| *r).max()?;
+
+        Some(RttStats {
+            smoothed: self.smoothed?,
+            latest: self.latest?,
+            min,
+            max,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn smoothed_and_window() {
+        let now = Instant::now();
+        let mut r = RttEstimator::default();
+        assert!(r.stats().is_none());
+
+        r.update(now, Duration::from_millis(80));
+        assert_eq!(r.smoothed(), Some(Duration::from_millis(80)));
+
+        r.update(now + Duration::from_secs(1), Duration::from_millis(160));
+        let stats = r.stats().unwrap();
+        assert_eq!(stats.smoothed, Duration::from_millis(90));
+        assert_eq!(stats.latest, Duration::from_millis(160));
+        assert_eq!(stats.min, Duration::from_millis(80));
+        assert_eq!(stats.max, Duration::from_millis(160));
+
+        // The first sample falls out of the window.
+        r.update(now + Duration::from_secs(12), Duration::from_millis(120));
+        let stats = r.stats().unwrap();
+        assert_eq!(stats.min, Duration::from_millis(120));
+        assert_eq!(stats.max, Duration::from_millis(120));
+    }
+}
@@ -1,8 +1,10 @@
-use std::time::SystemTime;
-use std::time::{Duration, Instant};
+use std::cell::Cell;
+use std::time::Duration;
 
 use once_cell::sync::Lazy;
 
+use crate::util::{Instant, SystemTime};
+
 pub(crate) fn not_happening() -> Instant {
     const YEARS_100: Duration = Duration::from_secs(60 * 60 * 24 * 365 * 100);
     static FUTURE: Lazy<Instant> = Lazy::new(|| Instant::now() + YEARS_100);
@@ -14,44 +16,106 @@ pub(crate) fn not_happening() -> Instant {
 // This is indeed a bit dodgy, but we want str0m's internal idea of time to be completely
 // driven from the external API using `Instant`. What works against us is that Instant can't
 // represent things like UNIX EPOCH (but SystemTime can).
-static BEGINNING_OF_TIME: Lazy<(Instant, SystemTime)> = Lazy::new(|| {
-    // These two should be "frozen" the same instant. Hopefully they are not differing too much.
-    let now = Instant::now();
-    let now_sys = SystemTime::now();
-
-    // Find an Instant in the past which is up to an hour back.
-    let beginning_of_time = {
-        let mut secs = 3600;
-        loop {
-            let dur = Duration::from_secs(secs);
-            if let Some(v) = now.checked_sub(dur) {
-                break v;
-            }
-            secs -= 1;
-            if secs == 0 {
-                panic!("Failed to find a beginning of time instant");
-            }
+//
+// Each Rtc has its own beginning of time, taken from the first `now` it is driven with. That
+// way the mapping doesn't depend on other Rtc instances in the same process, or on when the
+// first conversion happens to be done.
+//
+// The conversions are deep down in the RTCP and RTP header extension code, which has no
+// reference to the Rtc. The Rtc instead enters its anchor for the current thread while it
+// handles input or polls output.
+
+/// The "beginning of time" of one `Rtc`, in both Instant and SystemTime.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TimeAnchor {
+    instant: Instant,
+    system: SystemTime,
+}
+
+thread_local! {
+    /// The anchor of the Rtc that is handling input or polling output on this thread.
+    static CURRENT_ANCHOR: Cell<Option<TimeAnchor>> = Cell::new(None);
+}
+
+/// Restores the previous anchor of the thread when dropped.
+pub(crate) struct TimeAnchorGuard(Option<TimeAnchor>);
+
+impl TimeAnchor {
+    /// Freeze the "beginning of time" relative to `now`.
+    pub fn new(now: Instant) -> Self {
+        // The SystemTime is the only wallclock input. It's used to map Instant to
+        // NTP/unix time, and doesn't affect any other timing.
+        Self::with_system_time(now, SystemTime::now())
+    }
+
+    /// Freeze the "beginning of time" with `now` being the same moment as `now_sys`.
+    pub fn with_system_time(now: Instant, now_sys: SystemTime) -> Self {
+        let instant = hour_back(now);
+        let since_beginning_of_time = now - instant;
+
+        TimeAnchor {
+            instant,
+            system: now_sys - since_beginning_of_time,
         }
-    };
+    }
 
-    // This might be less than 1 hour if the machine uptime is less.
-    let since_beginning_of_time = Instant::now() - beginning_of_time;
+    /// The wallclock time of `t`.
+    pub fn system_time(&self, t: Instant) -> SystemTime {
+        self.system + t.saturating_duration_since(self.instant)
+    }
+
+    /// Use this anchor for all conversions on the current thread until the guard is dropped.
+    pub fn enter(self) -> TimeAnchorGuard {
+        let previous = CURRENT_ANCHOR.with(|c| c.replace(Some(self)));
+        TimeAnchorGuard(previous)
+    }
+}
+
+impl Drop for TimeAnchorGuard {
+    fn drop(&mut self) {
+        CURRENT_ANCHOR.with(|c| c.set(self.0));
+    }
+}
+
+fn beginning_of_time() -> (Instant, SystemTime) {
+    // Conversions outside of an Rtc (typically only in tests) fall back on the current time.
+    static FALLBACK: Lazy<TimeAnchor> = Lazy::new(|| TimeAnchor::new(Instant::now()));
+
+    let anchor = CURRENT_ANCHOR.with(|c| c.get()).unwrap_or(*FALLBACK);
+
+    (anchor.instant, anchor.system)
+}
 
-    let beginning_of_time_sys = now_sys - since_beginning_of_time;
+// Find an Instant in the past which is up to an hour back.
+fn hour_back(now: Instant) -> Instant {
+    let mut secs = 3600;
+    loop {
+        let dur = Duration::from_secs(secs);
+        if let Some(v) = now.checked_sub(dur) {
+            break v;
+        }
+        secs -= 1;
+        if secs == 0 {
+            panic!("Failed to find a beginning of time instant");
+        }
+    }
+}
 
-    // This pair represents our "beginning of time" for the same moment.
-    (beginning_of_time, beginning_of_time_sys)
-});
+/// The Instant [`epoch_to_beginning()`] is relative to.
+pub(crate) fn beginning_instant() -> Instant {
+    beginning_of_time().0
+}
 
 pub fn epoch_to_beginning() -> Duration {
-    BEGINNING_OF_TIME
+    beginning_of_time()
         .1
         .duration_since(SystemTime::UNIX_EPOCH)
         .expect("beginning of time to be after epoch")
 }
 
 pub(crate) fn already_happened() -> Instant {
-    BEGINNING_OF_TIME.0
+    static PAST: Lazy<Instant> = Lazy::new(|| hour_back(Instant::now()));
+    *PAST
 }
 
 pub trait InstantExt {
@@ -89,12 +153,14 @@ impl InstantExt for Instant {
         // This is a bit fishy. We "freeze" a moment in time for Instant and SystemTime,
         // so we can make relative comparisons of Instant - Instant and translate that to
         // SystemTime - unix epoch. Hopefully the error is quite small.
-        if *self < BEGINNING_OF_TIME.0 {
+        let (beginning, beginning_sys) = beginning_of_time();
+
+        if *self < beginning {
             warn!("Time went backwards from beginning_of_time Instant");
         }
 
-        let duration_since_time_0 = self.duration_since(BEGINNING_OF_TIME.0);
-        let system_time = BEGINNING_OF_TIME.1 + duration_since_time_0;
+        let duration_since_time_0 = self.duration_since(beginning);
+        let system_time = beginning_sys + duration_since_time_0;
 
         system_time
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -128,17 +194,17 @@ impl InstantExt for Instant {
         // Time in SystemTime
         let sys = SystemTime::UNIX_EPOCH + secs_dur;
 
+        let (beginning, beginning_sys) = beginning_of_time();
+
         // Relative duration from our beginning of time.
-        let since_beginning_of_time = sys
-            .duration_since(BEGINNING_OF_TIME.1)
-            .unwrap_or(Duration::ZERO);
+        let since_beginning_of_time = sys.duration_since(beginning_sys).unwrap_or(Duration::ZERO);
 
         // Translate relative to Instant
-        BEGINNING_OF_TIME.0 + since_beginning_of_time
+        beginning + since_beginning_of_time
     }
 
     fn as_ntp_64(&self) -> u64 {
-        let since_beginning_of_time = self.duration_since(BEGINNING_OF_TIME.0);
+        let since_beginning_of_time = self.duration_since(beginning_of_time().0);
 
         let since_epoch = since_beginning_of_time + epoch_to_beginning();
         let secs_epoch = since_epoch.as_secs_f64();
@@ -183,4 +249,50 @@ mod test {
     fn from_ntp_64() {
         Instant::from_ntp_64(0);
     }
+
+    #[test]
+    fn time_anchor_per_rtc() {
+        let start = Instant::now();
+        let sys = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        // b is driven the first time 10 seconds after a, but at the same wallclock.
+        let a = TimeAnchor::with_system_time(start, sys);
+        let b = TimeAnchor::with_system_time(start + Duration::from_secs(10), sys);
+
+        let t = start + Duration::from_secs(20);
+
+        let unix_a = {
+            let _time = a.enter();
+            t.to_unix_duration()
+        };
+        let unix_b = {
+            let _time = b.enter();
+            t.to_unix_duration()
+        };
+
+        assert_eq!(unix_a, Duration::from_secs(1_700_000_020));
+        assert_eq!(unix_b, Duration::from_secs(1_700_000_010));
+
+        // Leaving a nested anchor restores the outer one.
+        let _time = a.enter();
+        {
+            let _time = b.enter();
+            assert_eq!(t.to_unix_duration(), unix_b);
+        }
+        assert_eq!(t.to_unix_duration(), unix_a);
+    }
+
+    #[test]
+    fn time_anchor_system_time() {
+        let start = Instant::now();
+        let sys = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let a = TimeAnchor::with_system_time(start, sys);
+
+        assert_eq!(a.system_time(start), sys);
+        assert_eq!(
+            a.system_time(start + Duration::from_secs(5)),
+            sys + Duration::from_secs(5)
+        );
+    }
 }
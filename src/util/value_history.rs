@@ -2,9 +2,11 @@ use std::{
     collections::VecDeque,
     iter::Sum,
     ops::{AddAssign, SubAssign},
-    time::{Duration, Instant},
+    time::Duration,
 };
 
+use crate::util::Instant;
+
 /// Holds a history values of type T for up to a certain Duration, as well as the
 /// cumulated (total) value.
 #[derive(Debug)]
@@ -0,0 +1,151 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::bwe::{Bitrate, BitrateAllocator, TargetBitrate};
+use str0m::change::MediaConfig;
+use str0m::media::{Direction, MediaKind, Mid, Rid};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn allocation_toggles_simulcast_layers() -> Result<(), RtcError> {
+    init_log();
+    let l_rtc = Rtc::builder().enable_bwe(Some(Bitrate::kbps(300))).build();
+    let r_rtc = Rtc::builder().build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let rid_h: Rid = "h".into();
+    let rid_l: Rid = "l".into();
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        let config = MediaConfig {
+            rids: vec![rid_h, rid_l],
+            ..Default::default()
+        };
+        change
+            .add_media_with_config(MediaKind::Video, Direction::SendOnly, config)
+            .unwrap()
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    // The high layer is way above what the estimate allows.
+    let mut alloc = BitrateAllocator::new();
+    alloc.add_simulcast(
+        mid,
+        1,
+        &[(rid_l, Bitrate::kbps(50)), (rid_h, Bitrate::kbps(5000))],
+    );
+    l.bwe().set_bitrate_allocator(Some(alloc));
+
+    write_layers(&mut l, &mut r, mid, &[rid_l, rid_h], Duration::from_secs(3))?;
+
+    let target = last_target(&l, mid).expect("a target bitrate event");
+    assert!(target.layers[0].active);
+    assert!(!target.layers[1].active);
+    assert_eq!(target.bitrate, target.layers[0].bitrate);
+    assert!(target.bitrate > Bitrate::kbps(50));
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_value(target).unwrap();
+        assert_eq!(json["layers"][0]["active"].as_bool(), Some(true));
+        assert_eq!(json["layers"][1]["active"].as_bool(), Some(false));
+    }
+
+    assert!(!is_paused(&mut l, mid, rid_l));
+    assert!(is_paused(&mut l, mid, rid_h));
+
+    // A new allocator where both layers fit resumes the high layer.
+    let count = target_count(&l, mid);
+    let mut alloc = BitrateAllocator::new();
+    alloc.add_simulcast(
+        mid,
+        1,
+        &[(rid_l, Bitrate::kbps(50)), (rid_h, Bitrate::kbps(50))],
+    );
+    l.bwe().set_bitrate_allocator(Some(alloc));
+
+    progress(&mut l, &mut r)?;
+
+    assert_eq!(target_count(&l, mid), count + 1);
+    let target = last_target(&l, mid).unwrap();
+    assert!(target.layers.iter().all(|l| l.active));
+
+    assert!(!is_paused(&mut l, mid, rid_l));
+    assert!(!is_paused(&mut l, mid, rid_h));
+
+    Ok(())
+}
+
+fn write_layers(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    mid: Mid,
+    rids: &[Rid],
+    duration: Duration,
+) -> Result<(), RtcError> {
+    let pt = l.params_vp8().pt();
+    let until = l.duration() + duration;
+
+    let mut write_at = l.last;
+
+    while l.duration() < until {
+        if l.last >= write_at {
+            write_at = l.last + Duration::from_millis(100);
+            for rid in rids {
+                let wallclock = l.start + l.duration();
+                let time = l.duration().into();
+                l.writer(mid)
+                    .unwrap()
+                    .rid(*rid)
+                    .write(pt, wallclock, time, vec![1_u8; 100])?;
+            }
+        }
+
+        progress(l, r)?;
+    }
+
+    Ok(())
+}
+
+fn targets(l: &TestRtc, mid: Mid) -> impl Iterator<Item = &TargetBitrate> {
+    l.events.iter().filter_map(move |(_, e)| match e {
+        Event::TargetBitrate(t) if t.mid == mid => Some(t),
+        _ => None,
+    })
+}
+
+fn last_target(l: &TestRtc, mid: Mid) -> Option<&TargetBitrate> {
+    targets(l, mid).last()
+}
+
+fn target_count(l: &TestRtc, mid: Mid) -> usize {
+    targets(l, mid).count()
+}
+
+fn is_paused(l: &mut TestRtc, mid: Mid, rid: Rid) -> bool {
+    l.direct_api()
+        .stream_tx_by_mid(mid, Some(rid))
+        .unwrap()
+        .is_paused()
+}
@@ -0,0 +1,238 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::bwe::Bitrate;
+use str0m::media::{Direction, MediaKind};
+use str0m::net::Receive;
+use str0m::{Candidate, Input, Output, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+/// Like `common::progress`, but handing back the transmitted buffers.
+fn progress_recycle(l: &mut TestRtc, r: &mut TestRtc) -> Result<(), RtcError> {
+    let (f, t) = if l.last < r.last { (l, r) } else { (r, l) };
+
+    loop {
+        f.span
+            .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+        match f.span.in_scope(|| f.rtc.poll_output())? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                let input = Input::Receive(
+                    f.last,
+                    Receive {
+                        proto: v.proto,
+                        source: v.source,
+                        destination: v.destination,
+                        contents: (&*v.contents).try_into()?,
+                    },
+                );
+                t.span.in_scope(|| t.rtc.handle_input(input))?;
+                f.rtc.recycle_transmit(v.contents);
+            }
+            Output::Event(v) => {
+                f.events.push((f.last, v));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn buffer_pool() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendRecv, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = l.params_opus();
+    let pt = params.pt();
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, [1_u8; 80])?;
+
+        progress_recycle(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    // Both the SRTP and SRTCP buffers are reused.
+    let stats = l.rtc.buffer_pool_stats();
+    assert!(stats.hits > 200);
+    assert!(stats.hit_rate() > 0.95);
+
+    let stats = r.rtc.buffer_pool_stats();
+    assert!(stats.hits > 0);
+
+    Ok(())
+}
+
+#[test]
+pub fn buffer_pool_padding() -> Result<(), RtcError> {
+    init_log();
+
+    let l_rtc = Rtc::builder().enable_bwe(Some(Bitrate::kbps(500))).build();
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    l.bwe().set_current_bitrate(Bitrate::kbps(10));
+    l.bwe().set_desired_bitrate(Bitrate::kbps(500));
+
+    let pt = l.params_vp8().pt();
+
+    // Media way below the estimate, which makes the pacer pad.
+    let mut write_at = l.last;
+    let mut misses_warm = None;
+
+    loop {
+        if l.last >= write_at {
+            write_at = l.last + Duration::from_millis(100);
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid)
+                .unwrap()
+                .write(pt, wallclock, time, vec![1_u8; 100])?;
+        }
+
+        progress_recycle(&mut l, &mut r)?;
+
+        if misses_warm.is_none() && l.duration() > Duration::from_secs(2) {
+            misses_warm = Some(l.rtc.buffer_pool_stats().misses);
+        }
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let tx = l.direct_api().stream_tx_by_mid(mid, None).unwrap().stats();
+    assert!(tx.packets_padding > 100, "{tx:?}");
+
+    // Padding is mostly what is sent, and goes through the pool as well.
+    let stats = l.rtc.buffer_pool_stats();
+    assert!(stats.hits > tx.packets_padding);
+    assert!(stats.hit_rate() > 0.95, "{stats:?}");
+
+    // No allocations in steady state.
+    assert_eq!(Some(stats.misses), misses_warm);
+
+    Ok(())
+}
+
+#[test]
+pub fn caller_provided_buffer() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    // A buffer allocated by the caller, such as from an arena of registered buffers.
+    let buf: Vec<u8> = Vec::with_capacity(2000);
+    let ptr = buf.as_ptr();
+    l.rtc.recycle_transmit(buf.into());
+
+    let pt = l.params_opus().pt();
+    let wallclock = l.start + l.duration();
+    let time = l.duration().into();
+    l.writer(mid)
+        .unwrap()
+        .write(pt, wallclock, time, [1_u8; 80])?;
+
+    // The next outgoing SRTP is written straight into the caller's buffer.
+    let mut found = false;
+    for _ in 0..10 {
+        l.rtc.handle_input(Input::Timeout(l.last))?;
+
+        loop {
+            match l.rtc.poll_output()? {
+                Output::Timeout(v) => {
+                    l.last = v.max(l.last + Duration::from_millis(1));
+                    break;
+                }
+                Output::Transmit(v) => {
+                    found |= v.contents.as_ptr() == ptr;
+                }
+                _ => {}
+            }
+        }
+
+        if found {
+            break;
+        }
+    }
+
+    assert!(found, "expected the caller provided buffer to be used");
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::bwe::Bitrate;
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn bwe_bounds_min_larger_than_max() -> Result<(), RtcError> {
+    init_log();
+
+    // Inverted bounds in the config don't stop build().
+    let l_rtc = Rtc::builder()
+        .enable_bwe(Some(Bitrate::kbps(300)))
+        .set_bwe_bitrate_bounds(Bitrate::kbps(500), Bitrate::kbps(100))
+        .build();
+    let r_rtc = Rtc::builder().build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    // Neither do inverted bounds at runtime.
+    l.bwe()
+        .set_bitrate_bounds(Bitrate::kbps(200), Bitrate::kbps(100));
+
+    let settle_time = l.duration() + Duration::from_millis(500);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    assert!(l.is_connected());
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::bwe::{Bitrate, BweKind};
+use str0m::format::CodecConfig;
+use str0m::media::{Direction, MediaKind};
+use str0m::rtp::rtcp::Rtcp;
+use str0m::rtp::RawPacket;
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn ccfb_drives_bwe() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l_config = Rtc::builder()
+        .enable_bwe(Some(Bitrate::kbps(300)))
+        .enable_raw_packets(true);
+    ccfb_only(l_config.codec_config());
+    let mut r_config = Rtc::builder().enable_raw_packets(true);
+    ccfb_only(r_config.codec_config());
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_config.build());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_config.build());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 1000])?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let rtcp_rx: Vec<_> = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e.as_raw_packet() {
+            Some(RawPacket::RtcpRx(v)) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert!(rtcp_rx.iter().any(|v| matches!(v, Rtcp::Ccfb(_))));
+    assert!(!rtcp_rx.iter().any(|v| matches!(v, Rtcp::Twcc(_))));
+
+    // Without TWCC, the estimate can only come from the CCFB feedback.
+    let estimates: Vec<_> = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::EgressBitrateEstimate(BweKind::Twcc(v)) => Some(*v),
+            _ => None,
+        })
+        .collect();
+
+    assert!(!estimates.is_empty(), "expected an estimate from CCFB");
+    assert!(estimates.iter().any(|v| *v != Bitrate::kbps(300)));
+
+    Ok(())
+}
+
+/// Negotiate CCFB instead of TWCC.
+fn ccfb_only(config: &mut CodecConfig) {
+    let params: Vec<_> = config.params().to_vec();
+    config.clear();
+
+    for mut p in params {
+        p.set_fb_transport_cc(false);
+        p.set_fb_ccfb(true);
+        config.add_payload_params(p);
+    }
+}
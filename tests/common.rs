@@ -108,6 +108,7 @@ pub fn progress(l: &mut TestRtc, r: &mut TestRtc) -> Result<(), RtcError> {
             Output::Event(v) => {
                 f.events.push((f.last, v));
             }
+            _ => {}
         }
     }
 
@@ -148,6 +149,7 @@ pub fn progress_with_loss(l: &mut TestRtc, r: &mut TestRtc, loss: f32) -> Result
             Output::Event(v) => {
                 f.events.push((f.last, v));
             }
+            _ => {}
         }
     }
 
@@ -231,6 +233,11 @@ pub fn connect_l_r() -> (TestRtc, TestRtc) {
         .set_reordering_size_audio(0)
         .build();
 
+    connect_l_r_with_rtc(rtc1, rtc2)
+}
+
+/// Like [`connect_l_r()`], but with the given instances.
+pub fn connect_l_r_with_rtc(rtc1: Rtc, rtc2: Rtc) -> (TestRtc, TestRtc) {
     let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc1);
     let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc2);
 
@@ -0,0 +1,69 @@
+use str0m::{ConnectionState, DtlsState, Event, Rtc, RtcError};
+
+mod common;
+use common::{connect_l_r_with_rtc, init_log, progress, TestRtc};
+
+fn connection_states(t: &TestRtc) -> Vec<ConnectionState> {
+    t.events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::ConnectionStateChange(v) => Some(*v),
+            _ => None,
+        })
+        .collect()
+}
+
+fn dtls_states(t: &TestRtc) -> Vec<DtlsState> {
+    t.events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::DtlsStateChange(v) => Some(*v),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+pub fn connection_state_lifecycle() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r_with_rtc(Rtc::new(), Rtc::new());
+
+    for _ in 0..10 {
+        progress(&mut l, &mut r)?;
+    }
+
+    assert_eq!(l.connection_state(), ConnectionState::Connected);
+    assert_eq!(l.dtls_state(), DtlsState::Connected);
+
+    l.disconnect();
+
+    for _ in 0..10 {
+        progress(&mut l, &mut r)?;
+    }
+
+    assert_eq!(
+        connection_states(&l),
+        vec![
+            ConnectionState::Connecting,
+            ConnectionState::Connected,
+            ConnectionState::Closed
+        ]
+    );
+    assert_eq!(
+        dtls_states(&l),
+        vec![
+            DtlsState::Connecting,
+            DtlsState::Connected,
+            DtlsState::Closed
+        ]
+    );
+
+    // The remote side is still connected, as far as it knows.
+    assert_eq!(
+        connection_states(&r),
+        vec![ConnectionState::Connecting, ConnectionState::Connected]
+    );
+
+    Ok(())
+}
@@ -103,11 +103,68 @@ pub fn vp9_not_contiguous() -> Result<(), RtcError> {
     Ok(())
 }
 
+#[test]
+pub fn only_decodable_drops_until_keyframe() -> Result<(), RtcError> {
+    init_log();
+
+    // 14263 is lost, 14264 depends on it and 14265 is the next keyframe.
+    let all = Server::with_vp8_input()
+        .skip_packet(14263)
+        .timeout(Duration::seconds(5))
+        .get_output()?;
+    let output = Server::with_vp8_input()
+        .skip_packet(14263)
+        .timeout(Duration::seconds(5))
+        .only_decodable()
+        .get_output()?;
+
+    let contains = |d: &MediaData, seq: u64| d.seq_range.contains(&seq.into());
+
+    // Every emitted frame is decodable.
+    assert!(output.iter().all(|d| d.contiguous || d.is_keyframe()));
+    assert!(!output.iter().any(|d| contains(d, 14264)));
+
+    // Decoding resumes at the keyframe.
+    let keyframe = output.iter().find(|d| contains(d, 14265)).unwrap();
+    assert!(keyframe.is_keyframe());
+
+    // Nothing else is dropped.
+    let dropped: Vec<_> = all
+        .iter()
+        .filter(|d| !output.iter().any(|o| o.seq_range == d.seq_range))
+        .collect();
+    assert!(dropped.iter().all(|d| contains(d, 14264)));
+
+    Ok(())
+}
+
+#[test]
+pub fn only_decodable_without_keyframe() -> Result<(), RtcError> {
+    init_log();
+
+    // There is no keyframe after the lost 14337.
+    let output = Server::with_vp8_input()
+        .skip_packet(14337)
+        .timeout(Duration::seconds(5))
+        .only_decodable()
+        .get_output()?;
+
+    assert!(!output.is_empty());
+    for data in &output {
+        assert!(data.contiguous);
+        assert!(*data.seq_range.end() < 14337.into());
+    }
+    assert!(output.iter().any(|d| d.seq_range.contains(&14336.into())));
+
+    Ok(())
+}
+
 struct Server {
     codec: Codec,
     input_data: common::PcapData,
     skip_packet: Option<u16>,
     timeout: Option<Duration>,
+    only_decodable: bool,
 }
 
 impl Server {
@@ -125,6 +182,7 @@ impl Server {
             input_data,
             skip_packet: None,
             timeout: None,
+            only_decodable: false,
         }
     }
 
@@ -138,12 +196,20 @@ impl Server {
         self
     }
 
+    fn only_decodable(mut self) -> Self {
+        self.only_decodable = true;
+        self
+    }
+
     fn get_output(self) -> Result<Vec<MediaData>, RtcError> {
         let mut l = TestRtc::new(info_span!("L"));
 
         // We need to lower the default reordering buffer size, or we won't make it
         // past the dropped packet.
-        let rtc_r = Rtc::builder().set_reordering_size_video(5).build();
+        let rtc_r = Rtc::builder()
+            .set_reordering_size_video(5)
+            .set_emit_only_decodable(self.only_decodable)
+            .build();
 
         let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
 
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use str0m::media::{MediaKind, Mid};
+use str0m::rtp::{ExtensionValues, Ssrc};
+use str0m::{Event, RtcError};
+
+mod common;
+use common::{connect_l_r, init_log, progress};
+
+#[test]
+pub fn csrc() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid: Mid = "aud".into();
+    let ssrc: Ssrc = 1.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+    r.direct_api().expect_stream_rx(ssrc, None, mid, None);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let mut write_at = l.last;
+    let mut count = 0;
+
+    loop {
+        if count < 10 && l.last >= write_at {
+            write_at = l.last + Duration::from_millis(100);
+            count += 1;
+
+            // A mixer with changing contributors, the last five packets have none.
+            let csrc: Vec<Ssrc> = match count {
+                1..=5 => vec![10.into(), (10 + count).into()],
+                _ => vec![],
+            };
+
+            let wallclock = l.start + l.duration();
+            let mut direct = l.direct_api();
+            let stream = direct.stream_tx(&ssrc).unwrap();
+            stream.set_csrc(&csrc)?;
+            stream.write_rtp(
+                pt,
+                (count as u64).into(),
+                count * 960,
+                wallclock,
+                false,
+                ExtensionValues::default(),
+                false,
+                vec![1, 2, 3, 4],
+            )?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(2) {
+            break;
+        }
+    }
+
+    let received: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::RtpPacket(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(received.len(), 10);
+
+    for p in received {
+        let n = p.header.sequence_number as u32;
+        let expected: Vec<Ssrc> = match n {
+            1..=5 => vec![10.into(), (10 + n).into()],
+            _ => vec![],
+        };
+        assert_eq!(p.header.csrc, expected);
+        assert_eq!(*p.payload, [1, 2, 3, 4]);
+    }
+
+    // Too many contributing sources.
+    let too_many: Vec<Ssrc> = (0..16).map(Ssrc::from).collect();
+    let mut direct = l.direct_api();
+    let stream = direct.stream_tx(&ssrc).unwrap();
+    assert!(stream.set_csrc(&too_many).is_err());
+
+    Ok(())
+}
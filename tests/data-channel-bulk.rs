@@ -0,0 +1,80 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::{Candidate, Event, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, progress_with_loss, TestRtc};
+
+#[test]
+pub fn data_channel_bulk_transfer_with_loss() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("File".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    const TOTAL: usize = 1_000_000;
+    const CHUNK: usize = 10_000;
+    const HIGH_WATER: usize = 100_000;
+
+    let mut sent = 0;
+
+    loop {
+        // Flow controlled sender, like a file transfer would do.
+        if let Some(mut chan) = l.channel(cid) {
+            while sent < TOTAL && chan.buffered_amount()? < HIGH_WATER {
+                let n = chan.write(true, &[7; CHUNK])?;
+                if n == 0 {
+                    break;
+                }
+                sent += n;
+            }
+        }
+
+        // Loss exercises the SACK gap reports and fast retransmit.
+        progress_with_loss(&mut l, &mut r, 0.02)?;
+
+        let received: usize = r
+            .events
+            .iter()
+            .filter_map(|(_, e)| match e {
+                Event::ChannelData(d) => Some(d.data.len()),
+                _ => None,
+            })
+            .sum();
+
+        if received == TOTAL {
+            break;
+        }
+
+        if l.duration() > Duration::from_secs(30) {
+            panic!("Only received {} of {} bytes", received, TOTAL);
+        }
+    }
+
+    Ok(())
+}
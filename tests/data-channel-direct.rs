@@ -2,7 +2,8 @@ use std::net::Ipv4Addr;
 use std::time::Duration;
 
 use str0m::channel::ChannelConfig;
-use str0m::{Candidate, Event, RtcConfig, RtcError};
+use str0m::error::SctpError;
+use str0m::{Candidate, Event, Rtc, RtcConfig, RtcError};
 use tracing::info_span;
 
 mod common;
@@ -50,8 +51,8 @@ pub fn data_channel_direct() -> Result<(), RtcError> {
         label: "my-chan".into(),
         ..Default::default()
     };
-    let cid = l.direct_api().create_data_channel(config.clone());
-    r.direct_api().create_data_channel(config);
+    let cid = l.direct_api().create_data_channel(config.clone())?;
+    let rcid = r.direct_api().create_data_channel(config)?;
 
     loop {
         if l.is_connected() || r.is_connected() {
@@ -96,6 +97,34 @@ pub fn data_channel_direct() -> Result<(), RtcError> {
         .events
         .iter()
         .any(|(_, event)| event == &Event::ChannelClose(cid)));
+    // The stream reset signals the close to the remote peer.
+    assert!(r
+        .events
+        .iter()
+        .any(|(_, event)| event == &Event::ChannelClose(rcid)));
 
     Ok(())
 }
+
+#[test]
+pub fn data_channel_direct_reserved_stream_id() {
+    let mut rtc = Rtc::new();
+
+    let config = ChannelConfig {
+        negotiated: Some(65535),
+        label: "reserved".into(),
+        ..Default::default()
+    };
+
+    let err = rtc.direct_api().create_data_channel(config.clone());
+    assert!(matches!(
+        err,
+        Err(RtcError::Sctp(SctpError::ReservedStreamId(65535)))
+    ));
+
+    let err = rtc.sdp_api().add_channel_with_config(config);
+    assert!(matches!(
+        err,
+        Err(RtcError::Sctp(SctpError::ReservedStreamId(65535)))
+    ));
+}
@@ -1,7 +1,9 @@
 use std::net::Ipv4Addr;
 use std::time::Duration;
 
-use str0m::{Candidate, RtcError};
+use str0m::channel::{ChannelConfig, Reliability};
+use str0m::error::SctpError;
+use str0m::{Candidate, Event, RtcError};
 use tracing::info_span;
 
 mod common;
@@ -53,5 +55,226 @@ pub fn data_channel() -> Result<(), RtcError> {
 
     assert!(r.events.len() > 120);
 
+    let (rid, label) = r
+        .events
+        .iter()
+        .find_map(|(_, e)| match e {
+            Event::ChannelOpen(id, label) if label == "My little channel" => {
+                Some((*id, label.clone()))
+            }
+            _ => None,
+        })
+        .expect("remote channel open");
+
+    // The remote side learns the config via DCEP.
+    let chan = r.channel(rid).unwrap();
+    let config = chan.config().unwrap();
+    assert_eq!(config.label, label);
+    assert_eq!(config.reliability, Reliability::Reliable);
+    assert_eq!(config.negotiated, None);
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_unreliable() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let config = ChannelConfig {
+        label: "Unreliable".into(),
+        ordered: false,
+        reliability: Reliability::MaxPacketLifetime { lifetime: 500 },
+        ..Default::default()
+    };
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel_with_config(config.clone())?;
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    loop {
+        if let Some(mut chan) = l.channel(cid) {
+            chan.write(true, &[1, 2, 3]).expect("to write binary");
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let rid = r
+        .events
+        .iter()
+        .find_map(|(_, e)| match e {
+            Event::ChannelOpen(id, _) => Some(*id),
+            _ => None,
+        })
+        .expect("remote channel open");
+
+    let received = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::ChannelData(d) if d.binary))
+        .count();
+    assert!(received > 0);
+
+    // Unordered and partial reliability is signalled in the DCEP open.
+    let chan = r.channel(rid).unwrap();
+    assert_eq!(chan.config(), Some(&config));
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_large_message() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("Large".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    // Many times the SCTP chunk size.
+    let large = vec![42_u8; 50_000];
+    let mut sent = false;
+
+    loop {
+        if !sent {
+            if let Some(mut chan) = l.channel(cid) {
+                // The remote signals a=max-message-size:262144
+                let err = chan.write(true, &vec![0; 300_000]).unwrap_err();
+                assert!(matches!(
+                    err,
+                    RtcError::Sctp(SctpError::MessageTooLarge(300_000, 262_144))
+                ));
+
+                chan.write(true, &large).expect("to write large message");
+                sent = true;
+            }
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let received: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::ChannelData(d) => Some(&d.data),
+            _ => None,
+        })
+        .collect();
+
+    // Reassembled into one message.
+    assert_eq!(received, vec![&large]);
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_buffered_amount_low() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("Buffered".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let mut written = false;
+
+    loop {
+        if !written {
+            if let Some(mut chan) = l.channel(cid) {
+                chan.set_buffered_amount_low_threshold(1000)?;
+                for _ in 0..10 {
+                    chan.write(true, &[1; 10_000])?;
+                }
+                assert!(chan.buffered_amount()? > 1000);
+                written = true;
+            }
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    assert!(l
+        .events
+        .iter()
+        .any(|(_, e)| e == &Event::ChannelBufferedAmountLow(cid)));
+    assert_eq!(l.channel(cid).unwrap().buffered_amount()?, 0);
+
     Ok(())
 }
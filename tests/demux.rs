@@ -0,0 +1,106 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{MediaKind, Mid};
+use str0m::net::{Protocol, Receive};
+use str0m::rtp::{ExtensionValues, Ssrc};
+use str0m::{Event, Input, RtcError};
+
+mod common;
+use common::{connect_l_r, init_log, progress};
+
+#[test]
+pub fn demux_counts() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid: Mid = "aud".into();
+    let ssrc: Ssrc = 1.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+    r.direct_api().expect_stream_rx(ssrc, None, mid, None);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let mut write_at = l.last;
+    let mut count = 0;
+
+    loop {
+        if count < 10 && l.last >= write_at {
+            write_at = l.last + Duration::from_millis(100);
+            count += 1;
+
+            let wallclock = l.start + l.duration();
+            let mut direct = l.direct_api();
+            let stream = direct.stream_tx(&ssrc).unwrap();
+            stream.write_rtp(
+                pt,
+                (count as u64).into(),
+                count * 960,
+                wallclock,
+                false,
+                ExtensionValues::default(),
+                false,
+                vec![1, 2, 3, 4],
+            )?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    // ZRTP and TURN channel data on the same socket are dropped.
+    let source = (Ipv4Addr::new(1, 1, 1, 1), 1000).into();
+    let destination = (Ipv4Addr::new(2, 2, 2, 2), 2000).into();
+    for byte0 in [16, 64, 70] {
+        let buf = [byte0; 40];
+        let receive = Receive::new(Protocol::Udp, source, destination, &buf).unwrap();
+        let now = r.last;
+        r.handle_input(Input::Receive(now, receive))?;
+    }
+
+    // RTP from an address not in a verified candidate pair is dropped.
+    let unverified = (Ipv4Addr::new(3, 3, 3, 3), 3000).into();
+    let mut buf = [0; 40];
+    buf[0] = 0x80;
+    buf[1] = *pt;
+    let receive = Receive::new(Protocol::Udp, unverified, destination, &buf).unwrap();
+    let now = r.last;
+    r.handle_input(Input::Receive(now, receive))?;
+
+    // Outside the RFC 7983 ranges.
+    let buf = [100; 40];
+    assert!(Receive::new(Protocol::Udp, source, destination, &buf).is_err());
+
+    let counts = r.stats().peer.datagrams_rx;
+
+    assert!(counts.stun > 0, "{counts:?}");
+    assert!(counts.dtls > 0, "{counts:?}");
+    assert_eq!(counts.rtp, 11);
+    assert!(counts.rtcp > 0, "{counts:?}");
+    assert_eq!(counts.zrtp, 1);
+    assert_eq!(counts.turn_channel, 2);
+    assert_eq!(counts.unverified, 1);
+
+    let received = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::RtpPacket(_)))
+        .count();
+    assert_eq!(received, 10);
+
+    // The dropped datagrams don't disturb the session.
+    assert!(r.is_connected());
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, DtlsState, Event, Output, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{connect_l_r_with_rtc, init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn disconnect_emits_closed_last() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r_with_rtc(Rtc::new(), Rtc::new());
+
+    for _ in 0..10 {
+        progress(&mut l, &mut r)?;
+    }
+
+    l.disconnect();
+    assert!(!l.is_alive());
+
+    // Remaining output, such as close_notify and SCTP SHUTDOWN, is delivered to the remote.
+    for _ in 0..20 {
+        progress(&mut l, &mut r)?;
+    }
+
+    let closed = l
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::Closed))
+        .count();
+    assert_eq!(closed, 1);
+    assert!(matches!(l.events.last(), Some((_, Event::Closed))));
+
+    // Inert after the Closed event.
+    assert!(matches!(l.poll_output()?, Output::Timeout(_)));
+
+    // The remote handles the orderly close without error.
+    assert!(r.is_alive());
+
+    Ok(())
+}
+
+#[test]
+pub fn disconnect_order_seen_by_remote() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_channel("closing".into());
+        change.add_media(MediaKind::Audio, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 80])?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    assert!(r
+        .events
+        .iter()
+        .any(|(_, e)| matches!(e, Event::ChannelOpen(_, _))));
+
+    l.disconnect();
+    let r_before = r.events.len();
+
+    for _ in 0..100 {
+        progress(&mut l, &mut r)?;
+        if l.events.iter().any(|(_, e)| matches!(e, Event::Closed)) {
+            break;
+        }
+    }
+
+    assert!(matches!(l.events.last(), Some((_, Event::Closed))));
+
+    // The remote gets the RTCP BYE, then the SCTP shutdown, and the close_notify last.
+    let pos = |f: fn(&Event) -> bool| {
+        r.events[r_before..]
+            .iter()
+            .position(|(_, e)| f(e))
+            .expect("remote event after disconnect")
+    };
+    let bye = pos(|e| matches!(e, Event::StreamEnded(_)));
+    let channel_close = pos(|e| matches!(e, Event::ChannelClose(_)));
+    let close_notify = pos(|e| matches!(e, Event::DtlsStateChange(DtlsState::Closed)));
+
+    assert!(bye < channel_close);
+    assert!(channel_close < close_notify);
+
+    assert_eq!(r.dtls_state(), DtlsState::Closed);
+
+    Ok(())
+}
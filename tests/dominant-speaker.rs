@@ -0,0 +1,85 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn dominant_speaker() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let rtc = Rtc::builder().enable_dominant_speaker(true).build();
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid_a = change.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+    let mid_b = change.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let start = l.duration();
+    let mut write_at = l.last;
+
+    loop {
+        if l.last >= write_at {
+            write_at = l.last + Duration::from_millis(20);
+
+            // A speaks the first second, then B.
+            let a_speaks = l.duration() - start < Duration::from_secs(1);
+
+            for (mid, speaks) in [(mid_a, a_speaks), (mid_b, !a_speaks)] {
+                let level = if speaks { -20 } else { -127 };
+                let wallclock = l.start + l.duration();
+                let time = l.duration().into();
+                l.writer(mid).unwrap().audio_level(level, speaks).write(
+                    pt,
+                    wallclock,
+                    time,
+                    vec![1, 2, 3],
+                )?;
+            }
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() - start > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let speakers: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::DominantSpeakerChanged(v) => Some(v.mid),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(speakers, [mid_a, mid_b]);
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::change::{DtlsCert, FingerprintHash};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{connect_l_r_with_rtc, init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn regenerate_before_handshake() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let before = l.direct_api().local_dtls_fingerprint();
+    let after = l.regenerate_dtls_cert()?;
+    assert_ne!(before, after);
+    assert_eq!(l.direct_api().local_dtls_fingerprint(), after);
+
+    progress(&mut l, &mut r)?;
+
+    let regenerated: Vec<_> = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::DtlsCertRegenerated(v) => Some(v.clone()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(regenerated, vec![after]);
+
+    Ok(())
+}
+
+#[test]
+pub fn regenerate_after_handshake() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    negotiate(&mut l, &mut r, |change| {
+        let _ = change.add_channel("My little channel".into());
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let before = l.direct_api().local_dtls_fingerprint();
+    assert_eq!(
+        r.direct_api().remote_dtls_fingerprint(),
+        Some(before.clone())
+    );
+
+    // The new certificate is signaled straight away, but only used from the next negotiation.
+    let after = l.regenerate_dtls_cert()?;
+    assert_ne!(before, after);
+    assert_eq!(l.direct_api().local_dtls_fingerprint(), after);
+    assert_eq!(l.dtls_cert().fingerprint(), before);
+
+    negotiate(&mut l, &mut r, |change| {
+        change.ice_restart(true);
+    });
+
+    assert!(!l.is_connected());
+    assert!(!r.is_connected());
+
+    loop {
+        if l.duration() > Duration::from_secs(10) {
+            panic!("Failed to renegotiate DTLS in 10 seconds");
+        }
+
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+
+        progress(&mut l, &mut r)?;
+    }
+
+    assert_eq!(l.dtls_cert().fingerprint(), after);
+    assert_eq!(r.direct_api().remote_dtls_fingerprint(), Some(after));
+
+    Ok(())
+}
+
+#[test]
+pub fn warn_before_expiry() -> Result<(), RtcError> {
+    init_log();
+
+    // The warning is more than the lifetime, which means it's due straight away.
+    let cert = DtlsCert::new_openssl_with_lifetime(Duration::from_secs(3600));
+    assert!(cert.created() < cert.expires());
+
+    let rtc1 = Rtc::builder()
+        .set_dtls_cert(cert.clone())
+        .set_dtls_cert_expiry_warning(Some(Duration::from_secs(7200)))
+        .build();
+    let rtc2 = Rtc::builder().set_dtls_cert_expiry_warning(None).build();
+
+    let (mut l, mut r) = connect_l_r_with_rtc(rtc1, rtc2);
+
+    for _ in 0..10 {
+        progress(&mut l, &mut r)?;
+    }
+
+    let expiring: Vec<_> = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::DtlsCertExpiring(v) => Some(*v),
+            _ => None,
+        })
+        .collect();
+
+    // Only once per certificate.
+    assert_eq!(expiring, vec![cert.expires()]);
+
+    assert!(!r
+        .events
+        .iter()
+        .any(|(_, e)| matches!(e, Event::DtlsCertExpiring(_))));
+
+    Ok(())
+}
+
+#[test]
+pub fn verify_remote_sha384_sha512() {
+    init_log();
+
+    let rtc1 = Rtc::builder()
+        .set_fingerprint_hash(FingerprintHash::Sha384)
+        .build();
+    let rtc2 = Rtc::builder()
+        .set_fingerprint_hash(FingerprintHash::Sha512)
+        .build();
+
+    let (mut l, mut r) = connect_l_r_with_rtc(rtc1, rtc2);
+
+    let finger_l = l.direct_api().local_dtls_fingerprint();
+    let finger_r = r.direct_api().local_dtls_fingerprint();
+    assert_eq!(finger_l.hash_func, "sha-384");
+    assert_eq!(finger_l.bytes.len(), 48);
+    assert_eq!(finger_r.hash_func, "sha-512");
+    assert_eq!(finger_r.bytes.len(), 64);
+
+    for _ in 0..10 {
+        progress(&mut l, &mut r).expect("clean progress");
+    }
+
+    assert!(l.is_connected() && l.is_alive());
+    assert!(r.is_connected() && r.is_alive());
+}
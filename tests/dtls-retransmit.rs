@@ -0,0 +1,158 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::net::Receive;
+use str0m::{Candidate, Input, Output, Reason, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, TestRtc};
+
+#[test]
+pub fn dtls_handshake_retransmit() -> Result<(), RtcError> {
+    init_log();
+
+    // Lose the first flight of L, the ClientHello.
+    let loss = handshake_with_loss(DtlsLoss {
+        remaining: 1,
+        ..Default::default()
+    })?;
+
+    assert!(loss.dtls_timeout, "expected a timeout with Reason::Dtls");
+    assert!(loss.dtls_sent >= 2, "expected the ClientHello to be resent");
+
+    Ok(())
+}
+
+#[test]
+pub fn dtls_final_flight_retransmit() -> Result<(), RtcError> {
+    init_log();
+
+    // Lose the last flight of R, which is sent once R is connected. L resends its
+    // last flight, which R must answer even though it's connected.
+    let loss = handshake_with_loss(DtlsLoss {
+        remaining_final_r: 1,
+        ..Default::default()
+    })?;
+
+    assert!(loss.dtls_timeout, "expected a timeout with Reason::Dtls");
+    assert_eq!(loss.remaining_final_r, 0);
+
+    Ok(())
+}
+
+fn handshake_with_loss(mut loss: DtlsLoss) -> Result<DtlsLoss, RtcError> {
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp").unwrap();
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp").unwrap();
+    l.add_local_candidate(host1.clone());
+    l.add_remote_candidate(host2.clone());
+    r.add_local_candidate(host2);
+    r.add_remote_candidate(host1);
+
+    let finger_l = l.direct_api().local_dtls_fingerprint();
+    let finger_r = r.direct_api().local_dtls_fingerprint();
+    l.direct_api().set_remote_fingerprint(finger_r);
+    r.direct_api().set_remote_fingerprint(finger_l);
+
+    let creds_l = l.direct_api().local_ice_credentials();
+    let creds_r = r.direct_api().local_ice_credentials();
+    l.direct_api().set_remote_ice_credentials(creds_r);
+    r.direct_api().set_remote_ice_credentials(creds_l);
+
+    l.direct_api().set_ice_controlling(true);
+    r.direct_api().set_ice_controlling(false);
+
+    l.direct_api().start_dtls(true).unwrap();
+    r.direct_api().start_dtls(false).unwrap();
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+
+        assert!(l.duration() < Duration::from_secs(10), "handshake stalled");
+
+        progress_drop_dtls(&mut l, &mut r, &mut loss)?;
+    }
+
+    // Recovering took at least the initial retransmit timeout of simulated time.
+    assert!(l.duration() >= Duration::from_secs(1));
+
+    Ok(loss)
+}
+
+#[derive(Default)]
+struct DtlsLoss {
+    /// DTLS datagrams to drop from L.
+    remaining: usize,
+    /// DTLS handshake datagrams to drop from R once R is connected.
+    remaining_final_r: usize,
+    dtls_sent: usize,
+    dtls_timeout: bool,
+}
+
+fn progress_drop_dtls(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    loss: &mut DtlsLoss,
+) -> Result<(), RtcError> {
+    let (f, t, from_l) = if l.last < r.last {
+        (l, r, true)
+    } else {
+        (r, l, false)
+    };
+
+    loop {
+        f.span
+            .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+        match f.span.in_scope(|| f.rtc.poll_output())? {
+            Output::Timeout(v) => {
+                if from_l && f.rtc.last_timeout_reason() == Reason::Dtls {
+                    loss.dtls_timeout = true;
+                }
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                let data = v.contents;
+
+                let is_dtls = (20..=63).contains(&data[0]);
+                if from_l && is_dtls {
+                    loss.dtls_sent += 1;
+                    if loss.remaining > 0 {
+                        loss.remaining -= 1;
+                        continue;
+                    }
+                }
+
+                let is_handshake = data[0] == 20 || data[0] == 22;
+                if !from_l && is_handshake && f.rtc.is_connected() && loss.remaining_final_r > 0 {
+                    loss.remaining_final_r -= 1;
+                    continue;
+                }
+
+                let input = Input::Receive(
+                    f.last,
+                    Receive {
+                        proto: v.proto,
+                        source: v.source,
+                        destination: v.destination,
+                        contents: (&*data).try_into()?,
+                    },
+                );
+                t.span.in_scope(|| t.rtc.handle_input(input))?;
+            }
+            Output::Event(v) => {
+                f.events.push((f.last, v));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
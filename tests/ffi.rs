@@ -0,0 +1,183 @@
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+use std::{ptr, slice};
+
+use str0m::ffi::*;
+use str0m::format::Codec;
+use str0m::media::{Direction, MediaKind, MediaTime};
+use str0m::net::{Protocol, Receive};
+use str0m::{Candidate, Event, Input, Output, Rtc, RtcError};
+
+mod common;
+use common::init_log;
+
+fn bytes<'a>(s: Str0mSlice) -> &'a [u8] {
+    if s.len == 0 {
+        return &[];
+    }
+    unsafe { slice::from_raw_parts(s.ptr, s.len) }
+}
+
+fn micros(start: Instant, now: Instant) -> u64 {
+    (now - start).as_micros() as u64
+}
+
+#[test]
+pub fn ffi_media() -> Result<(), RtcError> {
+    init_log();
+
+    let start = Instant::now();
+    let mut now = start;
+
+    let l_addr = (Ipv4Addr::new(1, 1, 1, 1), 1000).into();
+    let r_addr = Str0mAddr {
+        family: 4,
+        ip: [2, 2, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        port: 2000,
+    };
+
+    let mut l = Rtc::new();
+    l.add_local_candidate(Candidate::host(l_addr, "udp")?);
+
+    let r = str0m_rtc_new(0);
+    assert!(!r.is_null());
+
+    unsafe {
+        assert_eq!(
+            str0m_rtc_add_local_candidate(r, &r_addr, Str0mProtocol::Udp as u32),
+            STR0M_OK
+        );
+
+        // Bad arguments are reported, not panicked on.
+        let bad = Str0mAddr {
+            family: 5,
+            ..r_addr
+        };
+        assert_eq!(
+            str0m_rtc_add_local_candidate(r, &bad, 0),
+            STR0M_ERR_ARGUMENT
+        );
+        assert!(!bytes(str0m_rtc_last_error(r)).is_empty());
+        assert_eq!(
+            str0m_rtc_add_local_candidate(r, ptr::null(), 0),
+            STR0M_ERR_ARGUMENT
+        );
+    }
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+    let offer = offer.to_sdp_string();
+
+    let mut answer = Str0mSlice {
+        ptr: ptr::null(),
+        len: 0,
+    };
+    let status = unsafe { str0m_rtc_accept_offer(r, offer.as_ptr(), offer.len(), &mut answer) };
+    assert_eq!(status, STR0M_OK);
+
+    let answer = std::str::from_utf8(bytes(answer)).unwrap();
+    let answer = str0m::change::SdpAnswer::from_sdp_string(answer).unwrap();
+    l.sdp_api().accept_answer(pending, answer)?;
+
+    let pt = l
+        .codec_config()
+        .find(|p| p.spec().codec == Codec::Opus)
+        .unwrap()
+        .pt();
+
+    let mut r_events = vec![];
+    let mut connected = false;
+
+    while now - start < Duration::from_secs(10) {
+        // Drive the Rust side.
+        if connected {
+            let time = MediaTime::from_micros(micros(start, now));
+            let writer = l.writer(mid).unwrap();
+            writer.write(pt, now, time, b"hello ffi".to_vec())?;
+        }
+        l.handle_input(Input::Timeout(now))?;
+        loop {
+            match l.poll_output()? {
+                Output::Timeout(_) => break,
+                Output::Transmit(t) => unsafe {
+                    let source: Str0mAddr = t.source.into();
+                    let destination: Str0mAddr = t.destination.into();
+                    let status = str0m_rtc_handle_receive(
+                        r,
+                        micros(start, now),
+                        Str0mProtocol::Udp as u32,
+                        &source,
+                        &destination,
+                        t.contents.as_ptr(),
+                        t.contents.len(),
+                    );
+                    assert_eq!(status, STR0M_OK);
+                },
+                Output::Event(Event::Connected) => connected = true,
+                _ => {}
+            }
+        }
+
+        // Drive the C ABI side.
+        unsafe {
+            assert_eq!(str0m_rtc_handle_timeout(r, micros(start, now)), STR0M_OK);
+        }
+        loop {
+            let mut out = std::mem::MaybeUninit::<Str0mOutput>::uninit();
+            let status = unsafe { str0m_rtc_poll_output(r, out.as_mut_ptr()) };
+            assert_eq!(status, STR0M_OK);
+            let out = unsafe { out.assume_init() };
+
+            match out.kind {
+                Str0mOutputKind::Timeout => {
+                    // Timeouts are on the same clock as the input.
+                    assert!(out.timeout_us >= micros(start, now));
+                    break;
+                }
+                Str0mOutputKind::Transmit => {
+                    assert_eq!(out.proto, Str0mProtocol::Udp);
+                    assert_eq!(out.source.port, 2000);
+                    let receive = Receive::new(
+                        Protocol::Udp,
+                        (Ipv4Addr::new(2, 2, 2, 2), 2000).into(),
+                        l_addr,
+                        bytes(out.data),
+                    )?;
+                    l.handle_input(Input::Receive(now, receive))?;
+                }
+                Str0mOutputKind::Event => {
+                    let data = bytes(out.data).to_vec();
+                    let mid = bytes(out.mid).to_vec();
+                    r_events.push((out.event, mid, data));
+                }
+            }
+        }
+
+        let got_data = r_events
+            .iter()
+            .any(|(e, _, _)| *e == Str0mEventKind::MediaData);
+        if got_data {
+            break;
+        }
+
+        now += Duration::from_millis(10);
+    }
+
+    assert!(r_events
+        .iter()
+        .any(|(e, _, _)| *e == Str0mEventKind::Connected));
+    assert!(r_events
+        .iter()
+        .any(|(e, m, _)| *e == Str0mEventKind::MediaAdded && m == mid.as_bytes()));
+    assert!(r_events.iter().any(|(e, m, d)| {
+        *e == Str0mEventKind::MediaData && m == mid.as_bytes() && d == b"hello ffi"
+    }));
+
+    unsafe { str0m_rtc_free(r) };
+
+    // Freeing null is a no-op.
+    unsafe { str0m_rtc_free(ptr::null_mut()) };
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::change::SdpAnswer;
+use str0m::format::Codec;
+use str0m::media::{Direction, Frequency, MediaKind, MediaTime};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn g711() -> Result<(), RtcError> {
+    // One write of 60ms is sent as three packets of the default 20ms ptime.
+    let sizes = write_60ms(None)?;
+    assert_eq!(sizes, [160, 160, 160]);
+    Ok(())
+}
+
+#[test]
+pub fn g711_remote_ptime() -> Result<(), RtcError> {
+    // The remote wants 30ms packets.
+    let sizes = write_60ms(Some(30))?;
+    assert_eq!(sizes, [240, 240]);
+    Ok(())
+}
+
+/// Write 60ms of PCMU and return the sizes of the packets received.
+fn write_60ms(remote_ptime: Option<u32>) -> Result<Vec<usize>, RtcError> {
+    init_log();
+
+    let rtc = || {
+        Rtc::builder()
+            .clear_codecs()
+            .enable_g711(true)
+            .enable_g722(true)
+            .build()
+    };
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let sdp = offer.to_sdp_string();
+    assert!(sdp.contains("m=audio 9 UDP/TLS/RTP/SAVPF 0 8 9\r\n"));
+    assert!(sdp.contains("a=rtpmap:0 PCMU/8000\r\n"));
+    assert!(sdp.contains("a=rtpmap:8 PCMA/8000\r\n"));
+    assert!(sdp.contains("a=rtpmap:9 G722/8000\r\n"));
+
+    let mut answer = r.rtc.sdp_api().accept_offer(offer)?;
+    if let Some(ptime) = remote_ptime {
+        let sdp = answer
+            .to_sdp_string()
+            .replace("a=mid:", &format!("a=ptime:{ptime}\r\na=mid:"));
+        answer = SdpAnswer::from_sdp_string(&sdp)?;
+    }
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    let expected_ptime = remote_ptime.map(|v| Duration::from_millis(v as u64));
+    assert_eq!(l.media(mid).unwrap().remote_ptime(), expected_ptime);
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = l
+        .writer(mid)
+        .unwrap()
+        .payload_params()
+        .find(|p| p.spec().codec == Codec::Pcmu)
+        .unwrap()
+        .clone();
+    assert_eq!(*params.pt(), 0);
+
+    let size = params.spec().ptime_size(Duration::from_millis(60)).unwrap();
+    assert_eq!(size, 480);
+
+    let wallclock = l.start + l.duration();
+    let time = MediaTime::new(8000, Frequency::EIGHT_KHZ);
+    l.writer(mid)
+        .unwrap()
+        .write(params.pt(), wallclock, time, vec![0xd5; size])?;
+
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(1) {
+            break;
+        }
+    }
+
+    let media: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    let mut numer = 8000;
+    for m in &media {
+        assert_eq!(m.pt, params.pt());
+        assert_eq!(m.time.numer(), numer);
+        assert_eq!(m.time.frequency(), Frequency::EIGHT_KHZ);
+        numer += m.data.len() as u64;
+    }
+
+    Ok(media.iter().map(|m| m.data.len()).collect())
+}
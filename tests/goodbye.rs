@@ -0,0 +1,92 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn goodbye_tears_down_receive_state() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 80])?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    // Let any paced RTP arrive ahead of the BYE.
+    for _ in 0..50 {
+        progress(&mut l, &mut r)?;
+    }
+
+    let ssrc = r.direct_api().stream_rx_by_mid(mid, None).unwrap().ssrc();
+    assert!(r.direct_api().stream_rx(&ssrc).unwrap().stats().packets > 0);
+
+    l.disconnect();
+
+    for _ in 0..100 {
+        progress(&mut l, &mut r)?;
+        if l.events.iter().any(|(_, e)| matches!(e, Event::Closed)) {
+            break;
+        }
+    }
+
+    let ended: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::StreamEnded(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(ended.len(), 1);
+    assert_eq!(ended[0].ssrc, ssrc);
+    assert_eq!(ended[0].mid, mid);
+    assert_eq!(ended[0].reason, None);
+
+    // The stream is kept, since the remote might reuse the SSRC, but starts over.
+    let mut api = r.direct_api();
+    let stream = api.stream_rx(&ssrc).expect("stream kept after BYE");
+    assert_eq!(stream.stats().packets, 0);
+    assert!(stream.sender_info().is_none());
+
+    Ok(())
+}
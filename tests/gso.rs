@@ -0,0 +1,132 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::net::Receive;
+use str0m::{Candidate, Event, Input, Output, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, TestRtc};
+
+/// Like `common::progress`, but using GSO output and splitting the segments on receive.
+fn progress_gso(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    max_segments: &mut usize,
+) -> Result<(), RtcError> {
+    let (f, t) = if l.last < r.last { (l, r) } else { (r, l) };
+
+    f.span
+        .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+    loop {
+        match f.span.in_scope(|| f.rtc.poll_output_gso(8))? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                let segments: Vec<&[u8]> = v.segments().collect();
+
+                if let Some(size) = v.segment_size {
+                    assert!(segments.len() > 1);
+                    assert!(segments.len() <= 8);
+                    let (last, rest) = segments.split_last().unwrap();
+                    assert!(rest.iter().all(|s| s.len() == size));
+                    assert!(last.len() <= size);
+                } else {
+                    assert_eq!(segments.len(), 1);
+                }
+
+                *max_segments = (*max_segments).max(segments.len());
+
+                for s in segments {
+                    let input = Input::Receive(
+                        f.last,
+                        Receive {
+                            proto: v.proto,
+                            source: v.source,
+                            destination: v.destination,
+                            contents: s.try_into()?,
+                        },
+                    );
+                    t.span.in_scope(|| t.rtc.handle_input(input))?;
+                }
+            }
+            Output::Event(v) => {
+                f.events.push((f.last, v));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn gso_output() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    let mut max_segments = 0;
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress_gso(&mut l, &mut r, &mut max_segments)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+
+    loop {
+        // Only write when L is the side progressing next. Each handle_input() packetizes
+        // one write, and writing faster than that fills up the queue.
+        if l.last < r.last {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            // Large enough to be split over several packets.
+            let data = vec![1_u8; 5000];
+            l.writer(mid).unwrap().write(pt, wallclock, time, data)?;
+        }
+
+        progress_gso(&mut l, &mut r, &mut max_segments)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    assert!(max_segments > 1);
+
+    let media: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert!(media.len() > 50);
+    assert!(media.iter().all(|m| m.data.len() == 5000));
+
+    Ok(())
+}
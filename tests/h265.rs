@@ -0,0 +1,123 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::format::Codec;
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+const START_CODE: &[u8] = &[0, 0, 0, 1];
+
+fn annexb(nalus: &[&[u8]]) -> Vec<u8> {
+    let mut v = vec![];
+    for nalu in nalus {
+        v.extend_from_slice(START_CODE);
+        v.extend_from_slice(nalu);
+    }
+    v
+}
+
+#[test]
+pub fn h265() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc = || Rtc::builder().clear_codecs().enable_h265(true).build();
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let sdp = offer.to_sdp_string();
+    assert!(sdp.contains("a=rtpmap:49 H265/90000"));
+    assert!(sdp.contains("a=fmtp:49 profile-id=1;tier-flag=0;level-id=93"));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = l
+        .writer(mid)
+        .unwrap()
+        .payload_params()
+        .next()
+        .unwrap()
+        .clone();
+    assert_eq!(params.spec().codec, Codec::H265);
+    let pt = params.pt();
+
+    let vps: &[u8] = &[0x40, 0x01, 0x0c, 0x01, 0xff];
+    let sps: &[u8] = &[0x42, 0x01, 0x01, 0x01, 0x60];
+    let pps: &[u8] = &[0x44, 0x01, 0xc0, 0xf2];
+    let mut idr = vec![0x26, 0x01];
+    idr.extend((0..5000).map(|i| (i % 251) as u8 + 2));
+    let mut trail = vec![0x02, 0x01];
+    trail.extend((0..800).map(|i| (i % 13) as u8 + 2));
+
+    let keyframe = annexb(&[vps, sps, pps, &idr]);
+    let delta = annexb(&[&trail]);
+
+    let mut write_at = l.last;
+    let mut count = 0;
+
+    loop {
+        if count < 10 && l.last >= write_at {
+            write_at = l.last + Duration::from_millis(33);
+
+            let data = if count == 0 { &keyframe } else { &delta };
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid)
+                .unwrap()
+                .write(pt, wallclock, time, data.clone())?;
+
+            count += 1;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(2) {
+            break;
+        }
+    }
+
+    let media: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(media.len(), 10);
+
+    assert!(media[0].is_keyframe());
+    assert_eq!(media[0].data, keyframe);
+
+    for m in &media[1..] {
+        assert!(!m.is_keyframe());
+        assert_eq!(m.data, delta);
+    }
+
+    Ok(())
+}
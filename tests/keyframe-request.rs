@@ -0,0 +1,132 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, KeyframeRequestKind, MediaKind, Mid};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+/// L sends video to R. R requests a keyframe for every progress over 2 seconds.
+///
+/// Returns (PLIs sent by R, PLIs received by L, KeyframeRequest events at L).
+fn request_keyframes(
+    l_interval: Duration,
+    r_interval: Duration,
+) -> Result<(u64, u64, usize), RtcError> {
+    let l_rtc = Rtc::builder()
+        .set_keyframe_request_interval(l_interval)
+        .build();
+    let r_rtc = Rtc::builder()
+        .set_keyframe_request_interval(r_interval)
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid: Mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+    let start = l.duration();
+
+    loop {
+        if l.last < r.last {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid)
+                .unwrap()
+                .write(pt, wallclock, time, vec![1_u8; 80])?;
+        }
+
+        let has_media = r
+            .events
+            .iter()
+            .any(|(_, e)| matches!(e, Event::MediaData(_)));
+
+        if has_media {
+            r.direct_api()
+                .stream_rx_by_mid(mid, None)
+                .unwrap()
+                .request_keyframe(KeyframeRequestKind::Pli);
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() - start > Duration::from_secs(2) {
+            break;
+        }
+    }
+
+    // Let the last requests arrive.
+    let settle = l.duration() + Duration::from_millis(100);
+    while l.duration() < settle {
+        progress(&mut l, &mut r)?;
+    }
+
+    let sent = r
+        .direct_api()
+        .stream_rx_by_mid(mid, None)
+        .unwrap()
+        .stats()
+        .plis;
+    let received = l
+        .direct_api()
+        .stream_tx_by_mid(mid, None)
+        .unwrap()
+        .stats()
+        .plis;
+    let events = l
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::KeyframeRequest(_)))
+        .count();
+
+    Ok((sent, received, events))
+}
+
+#[test]
+pub fn keyframe_request_outgoing_debounce() -> Result<(), RtcError> {
+    init_log();
+
+    let (sent, received, events) = request_keyframes(Duration::ZERO, Duration::from_millis(300))?;
+
+    // One every 300ms over 2 seconds.
+    assert!((6..=8).contains(&sent), "sent {sent}");
+    assert_eq!(received, sent);
+    assert_eq!(events as u64, received);
+
+    Ok(())
+}
+
+#[test]
+pub fn keyframe_request_incoming_collapse() -> Result<(), RtcError> {
+    init_log();
+
+    let (sent, received, events) = request_keyframes(Duration::from_millis(300), Duration::ZERO)?;
+
+    // R sends a burst of PLIs, which all arrive, but L only emits one every 300ms.
+    assert!(sent > 20, "sent {sent}");
+    assert_eq!(received, sent);
+    assert!((6..=8).contains(&events), "events {events}");
+
+    Ok(())
+}
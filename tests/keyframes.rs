@@ -91,6 +91,7 @@ pub fn test_vp8_keyframes_detection() -> Result<(), RtcError> {
         let assume_keyframe = data.seq_range.contains(&14260.into())
             || data.seq_range.contains(&14262.into())
             || data.seq_range.contains(&14265.into());
+        assert_eq!(extra.is_keyframe, data.is_keyframe());
         if extra.is_keyframe {
             assert!(assume_keyframe, "Expected keyframe");
         } else {
@@ -183,6 +184,7 @@ pub fn test_vp9_keyframes_detection() -> Result<(), RtcError> {
             || data.seq_range.contains(&20296.into())
             || data.seq_range.contains(&20301.into())
             || data.seq_range.contains(&20351.into());
+        assert_eq!(extra.is_keyframe, data.is_keyframe());
         if extra.is_keyframe {
             assert!(assume_keyframe, "Expected keyframe");
         } else {
@@ -278,6 +280,7 @@ pub fn test_h264_keyframes_detection() -> Result<(), RtcError> {
             || data.seq_range.contains(&19403.into())
             || data.seq_range.contains(&19453.into())
             || data.seq_range.contains(&19503.into());
+        assert_eq!(extra.is_keyframe, data.is_keyframe());
         if extra.is_keyframe {
             assert!(assume_keyframe, "Expected keyframe");
         } else {
@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+use str0m::loopback::{LinkConditions, Loopback};
+use str0m::media::{Direction, MediaKind};
+use str0m::{Event, Rtc, RtcError};
+
+mod common;
+use common::init_log;
+
+fn run(seed: u64) -> Result<(usize, Vec<Duration>), RtcError> {
+    let start = Instant::now();
+    let mut lo = Loopback::new(Rtc::new(), Rtc::new(), start);
+
+    lo.set_seed(seed);
+    lo.set_conditions(LinkConditions {
+        loss: 0.05,
+        latency: Duration::from_millis(40),
+        jitter: Duration::from_millis(20),
+    });
+
+    let mid = lo.negotiate(|api| api.add_media(MediaKind::Audio, Direction::SendOnly, None, None));
+
+    assert!(lo.run_until_connected(Duration::from_secs(10))?);
+
+    let pt =
+        lo.l.rtc
+            .codec_config()
+            .find(|p| p.spec().codec.is_audio())
+            .map(|p| p.pt())
+            .unwrap();
+
+    let data = [1_u8; 80];
+
+    for _ in 0..250 {
+        let now = lo.now();
+        let time = (now - start).into();
+        lo.l.rtc.writer(mid).unwrap().write(pt, now, time, data)?;
+
+        lo.run_until(now + Duration::from_millis(20))?;
+    }
+
+    lo.run_until(lo.now() + Duration::from_secs(1))?;
+
+    let received: Vec<_> =
+        lo.r.events
+            .iter()
+            .filter_map(|(t, e)| match e {
+                Event::MediaData(_) => Some(*t - start),
+                _ => None,
+            })
+            .collect();
+
+    Ok((received.len(), received))
+}
+
+#[test]
+pub fn loopback_deterministic() -> Result<(), RtcError> {
+    init_log();
+
+    let (count_a, times_a) = run(42)?;
+    let (count_b, times_b) = run(42)?;
+
+    // Most of the audio makes it through the loss.
+    assert!(count_a > 200, "received {}", count_a);
+
+    // Same seed, same outcome.
+    assert_eq!(count_a, count_b);
+    assert_eq!(times_a, times_b);
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::net::Receive;
+use str0m::{Candidate, Event, Input, Output, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, TestRtc};
+
+/// Like `common::progress`, but keeps track of the largest datagram sent by each side.
+fn progress_max(l: &mut TestRtc, r: &mut TestRtc, max: &mut [usize; 2]) -> Result<(), RtcError> {
+    let l_first = l.last < r.last;
+    let (f, t) = if l_first { (l, r) } else { (r, l) };
+    let max = if l_first { &mut max[0] } else { &mut max[1] };
+
+    loop {
+        f.span
+            .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+        match f.span.in_scope(|| f.rtc.poll_output())? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                *max = (*max).max(v.contents.len());
+                let input = Input::Receive(
+                    f.last,
+                    Receive {
+                        proto: v.proto,
+                        source: v.source,
+                        destination: v.destination,
+                        contents: (&*v.contents).try_into()?,
+                    },
+                );
+                t.span.in_scope(|| t.rtc.handle_input(input))?;
+            }
+            Output::Event(v) => {
+                f.events.push((f.last, v));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn mtu_limits_datagram_size() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc = Rtc::builder().set_mtu(700).build();
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc);
+    let mut r = TestRtc::new(info_span!("R"));
+
+    assert_eq!(l.mtu(), 700);
+    assert_eq!(r.mtu(), 1150);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    let mut max = [0, 0];
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress_max(&mut l, &mut r, &mut max)?;
+    }
+
+    let max_time = l.last.max(r.last);
+    l.last = max_time;
+    r.last = max_time;
+
+    let pt = l.params_vp8().pt();
+
+    loop {
+        {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            // Large enough to be split over several packets.
+            let data = vec![1_u8; 5000];
+            l.writer(mid).unwrap().write(pt, wallclock, time, data)?;
+        }
+
+        progress_max(&mut l, &mut r, &mut max)?;
+
+        if l.duration() > Duration::from_secs(2) {
+            break;
+        }
+    }
+
+    // Packets fill up the configured size, but never go above it.
+    assert!(max[0] <= 700, "L sent {} bytes", max[0]);
+    assert!(max[0] > 500, "L sent {} bytes", max[0]);
+
+    let media_count = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::MediaData(_)))
+        .count();
+    assert!(media_count > 0);
+
+    // Lowering the MTU mid-session applies to the following RTP packets.
+    l.set_mtu(600);
+    assert_eq!(l.mtu(), 600);
+
+    // Drain anything packetized before the change.
+    let drain_until = l.duration() + Duration::from_millis(500);
+    while l.duration() < drain_until {
+        progress_max(&mut l, &mut r, &mut max)?;
+    }
+
+    let mut max = [0, 0];
+
+    loop {
+        {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            let data = vec![1_u8; 5000];
+            l.writer(mid).unwrap().write(pt, wallclock, time, data)?;
+        }
+
+        progress_max(&mut l, &mut r, &mut max)?;
+
+        if l.duration() > Duration::from_secs(4) {
+            break;
+        }
+    }
+
+    assert!(max[0] <= 600, "L sent {} bytes", max[0]);
+
+    // Out of range values are clamped.
+    l.set_mtu(10_000);
+    assert_eq!(l.mtu(), 1500);
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use str0m::format::Codec;
+use str0m::media::MediaKind;
+use str0m::rtp::rtcp::Rtcp;
+use str0m::rtp::{ExtensionValues, RawPacket, Ssrc};
+use str0m::{Rtc, RtcError};
+
+mod common;
+use common::{connect_l_r_with_rtc, init_log, progress, progress_with_loss};
+
+#[test]
+pub fn loss_recovery_without_rtx() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc1 = Rtc::builder()
+        .set_rtp_mode(true)
+        .enable_raw_packets(true)
+        .set_resend_without_rtx(true)
+        .build();
+    let rtc2 = Rtc::builder()
+        .set_rtp_mode(true)
+        .enable_raw_packets(true)
+        .build();
+
+    let (mut l, mut r) = connect_l_r_with_rtc(rtc1, rtc2);
+
+    let mid = "vid".into();
+
+    // No RTX SSRC on either side.
+    let ssrc: Ssrc = 42.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Video);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Video);
+    r.direct_api().expect_stream_rx(ssrc, None, mid, None);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = l.params_vp8();
+    assert_eq!(params.spec().codec, Codec::Vp8);
+    let pt = params.pt();
+
+    let to_write = &[0x1, 0x2, 0x3, 0x4];
+    let num_packets: usize = 1000;
+
+    for index in 0..num_packets {
+        let wallclock = l.start + l.duration();
+
+        let mut direct = l.direct_api();
+        let stream = direct.stream_tx(&ssrc).unwrap();
+
+        let time = (index * 1000 + 47_000_000) as u32;
+        let seq_no = (47_000 + index as u64).into();
+
+        stream
+            .write_rtp(
+                pt,
+                seq_no,
+                time,
+                wallclock,
+                false,
+                ExtensionValues::default(),
+                true,
+                to_write.to_vec(),
+            )
+            .expect("clean write");
+
+        if !(10..=990).contains(&index) {
+            progress(&mut l, &mut r)?;
+        } else {
+            progress_with_loss(&mut l, &mut r, 0.05)?;
+        }
+    }
+
+    let settle_time = l.duration() + Duration::from_secs(2);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    let nacks_rx = l
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e.as_raw_packet(), Some(RawPacket::RtcpRx(Rtcp::Nack(_)))))
+        .count();
+
+    assert!(nacks_rx > 0);
+
+    // Everything arrives on the main SSRC and PT, with the original sequence numbers.
+    let mut packets_rx = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e.as_raw_packet() {
+            Some(RawPacket::RtpRx(p, _)) => {
+                assert_eq!(p.ssrc, ssrc);
+                assert_eq!(p.payload_type, pt);
+                Some(p.sequence_number)
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    packets_rx.sort();
+    packets_rx.dedup();
+
+    assert_eq!(packets_rx.first(), Some(&47_000));
+    assert_eq!(packets_rx.last(), Some(&47_999));
+    assert_eq!(packets_rx.len(), num_packets);
+
+    let stats = l.direct_api().stream_tx(&ssrc).unwrap().stats();
+    assert!(stats.packets_resent > 0);
+
+    Ok(())
+}
@@ -165,6 +165,11 @@ pub fn loss_recovery() -> Result<(), RtcError> {
     assert_eq!(discontinuities.len(), 0);
     assert_eq!(packets_rx.len(), num_packets);
 
+    // every nacked packet was still in the RTX cache.
+    let stats = l.direct_api().stream_tx(&ssrc).unwrap().stats();
+    assert!(stats.nacked_packets > 0);
+    assert_eq!(stats.nacked_packets_cached, stats.nacked_packets);
+
     Ok(())
 }
 
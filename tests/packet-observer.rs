@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind, Mid};
+use str0m::rtp::{ObservedPacket, PacketObserver, PacketStage, SeqNo};
+use str0m::{Candidate, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[derive(Debug, Default)]
+struct Recorder(Mutex<Vec<Observed>>);
+
+#[derive(Debug, Clone)]
+struct Observed {
+    stage: PacketStage,
+    is_rtcp: bool,
+    mid: Option<Mid>,
+    seq_no: Option<SeqNo>,
+    len: usize,
+}
+
+impl PacketObserver for Recorder {
+    fn on_packet(&self, packet: &ObservedPacket<'_>) {
+        assert_eq!(packet.is_rtcp, packet.header.is_none());
+
+        self.0.lock().unwrap().push(Observed {
+            stage: packet.stage,
+            is_rtcp: packet.is_rtcp,
+            mid: packet.mid,
+            seq_no: packet.seq_no,
+            len: packet.data.len(),
+        });
+    }
+}
+
+impl Recorder {
+    fn rtp(&self, stage: PacketStage) -> Vec<Observed> {
+        let all = self.0.lock().unwrap();
+        all.iter()
+            .filter(|o| o.stage == stage && !o.is_rtcp)
+            .cloned()
+            .collect()
+    }
+
+    fn count(&self) -> HashMap<(PacketStage, bool), usize> {
+        let mut map = HashMap::new();
+        for o in self.0.lock().unwrap().iter() {
+            *map.entry((o.stage, o.is_rtcp)).or_default() += 1;
+        }
+        map
+    }
+}
+
+#[test]
+pub fn packet_observer() -> Result<(), RtcError> {
+    init_log();
+
+    let l_rec = Arc::new(Recorder::default());
+    let r_rec = Arc::new(Recorder::default());
+
+    let l_rtc = Rtc::builder()
+        .set_packet_observer(Some(l_rec.clone()))
+        .build();
+    let r_rtc = Rtc::builder()
+        .set_packet_observer(Some(r_rec.clone()))
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let data_a = [1_u8; 80];
+
+    loop {
+        {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid).unwrap().write(pt, wallclock, time, data_a)?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    // Sending side: every packet queued is sent, in order.
+    let queued = l_rec.rtp(PacketStage::Queued);
+    let sent = l_rec.rtp(PacketStage::Sent);
+
+    assert!(queued.len() > 100);
+    assert!(queued.iter().all(|o| o.mid == Some(mid) && o.len == 80));
+    assert!(sent.iter().all(|o| o.mid == Some(mid) && o.len > 80));
+
+    let queued_seq: Vec<_> = queued.iter().map(|o| o.seq_no.unwrap()).collect();
+    let sent_seq: Vec<_> = sent.iter().map(|o| o.seq_no.unwrap()).collect();
+    assert!(queued_seq.windows(2).all(|w| w[0] < w[1]));
+    assert!(sent_seq.starts_with(&queued_seq[..queued_seq.len() - 5]));
+
+    // Receiving side: received -> decrypted -> demuxed.
+    let received = r_rec.rtp(PacketStage::Received);
+    let decrypted = r_rec.rtp(PacketStage::Decrypted);
+    let demuxed = r_rec.rtp(PacketStage::Demuxed);
+
+    assert!(received.len() > 100);
+    assert_eq!(received.len(), decrypted.len());
+    assert_eq!(decrypted.len(), demuxed.len());
+    assert!(received
+        .iter()
+        .all(|o| o.mid.is_none() && o.seq_no.is_none()));
+    assert!(demuxed.iter().all(|o| o.mid == Some(mid) && o.len == 80));
+
+    // RTCP flows in both directions.
+    let l_count = l_rec.count();
+    let r_count = r_rec.count();
+    assert!(l_count[&(PacketStage::Decrypted, true)] > 0);
+    assert!(r_count[&(PacketStage::Sent, true)] > 0);
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::rtp::{TappedKind, TappedPacket};
+use str0m::{Candidate, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn packet_tap() -> Result<(), RtcError> {
+    init_log();
+
+    let l_rtc = Rtc::builder().enable_packet_tap(true).build();
+    let r_rtc = Rtc::builder().enable_packet_tap(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+    let data_a = [1_u8; 80];
+
+    let mut l_tapped: Vec<TappedPacket> = vec![];
+    let mut r_tapped: Vec<TappedPacket> = vec![];
+
+    loop {
+        {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid).unwrap().write(pt, wallclock, time, data_a)?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        l_tapped.extend(std::iter::from_fn(|| l.poll_tapped_packet()));
+        r_tapped.extend(std::iter::from_fn(|| r.poll_tapped_packet()));
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let of_kind = |tapped: &[TappedPacket], kind: TappedKind| -> Vec<Vec<u8>> {
+        tapped
+            .iter()
+            .filter(|p| p.kind == kind)
+            .map(|p| p.data.clone())
+            .collect()
+    };
+
+    let rtp_sent = of_kind(&l_tapped, TappedKind::RtpTx);
+    let rtp_received = of_kind(&r_tapped, TappedKind::RtpRx);
+
+    assert!(rtp_received.len() > 100);
+    assert!(rtp_received.iter().all(|p| rtp_sent.contains(p)));
+
+    // Version 2 and our payload in the clear, before any padding.
+    for p in &rtp_received {
+        assert_eq!(p[0] >> 6, 2);
+        let has_padding = p[0] & 0b0010_0000 > 0;
+        let pad_len = if has_padding {
+            p[p.len() - 1] as usize
+        } else {
+            0
+        };
+        assert!(p[..p.len() - pad_len].ends_with(&data_a));
+    }
+
+    let rtcp_sent = of_kind(&r_tapped, TappedKind::RtcpTx);
+    let rtcp_received = of_kind(&l_tapped, TappedKind::RtcpRx);
+
+    assert!(!rtcp_received.is_empty());
+    assert!(rtcp_received.iter().all(|p| rtcp_sent.contains(p)));
+
+    // Packet type of the first packet in the compound is a valid RTCP type.
+    for p in &rtcp_received {
+        assert!((200..=207).contains(&p[1]));
+    }
+
+    assert!(l_tapped
+        .windows(2)
+        .all(|w| w[0].timestamp <= w[1].timestamp));
+
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::bwe::Bitrate;
+use str0m::change::MediaConfig;
+use str0m::media::{Direction, MediaKind, Mid, Rid};
+use str0m::rtp::{RawPacket, Ssrc};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn padding_excluded_from_media_stats() -> Result<(), RtcError> {
+    init_log();
+    let l_rtc = Rtc::builder()
+        .enable_bwe(Some(Bitrate::kbps(500)))
+        .set_stats_interval(Some(Duration::from_secs(1)))
+        .build();
+    let r_rtc = Rtc::builder().build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    l.bwe().set_current_bitrate(Bitrate::kbps(10));
+    l.bwe().set_desired_bitrate(Bitrate::kbps(500));
+
+    let pt = l.params_vp8().pt();
+
+    // Media way below the estimate, which makes the pacer pad.
+    let data = vec![1_u8; 100];
+    let mut write_at = l.last;
+
+    loop {
+        if l.last >= write_at {
+            write_at = l.last + Duration::from_millis(100);
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid)
+                .unwrap()
+                .write(pt, wallclock, time, data.clone())?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let tx = l.direct_api().stream_tx_by_mid(mid, None).unwrap().stats();
+    let rx = r.direct_api().stream_rx_by_mid(mid, None).unwrap().stats();
+
+    assert!(tx.packets_padding > 0);
+    assert!(tx.bytes_padding > tx.bytes, "{tx:?}");
+
+    // Every media packet arrives, padding is not counted as media.
+    assert_eq!(tx.packets_resent, 0);
+    assert_eq!(tx.packets, rx.packets);
+    assert!(tx.bitrate < Bitrate::kbps(20), "{tx:?}");
+
+    let peer = l
+        .events
+        .iter()
+        .rev()
+        .find_map(|(_, e)| match e {
+            Event::PeerStats(v) => Some(v),
+            _ => None,
+        })
+        .unwrap();
+
+    assert!(peer.padding_bytes_tx > 0);
+    assert!(peer.bytes_tx < peer.padding_bytes_tx, "{peer:?}");
+    assert_eq!(peer.egress_loss_fraction, Some(0.0));
+
+    Ok(())
+}
+
+#[test]
+pub fn padding_on_rtx_of_active_layer() -> Result<(), RtcError> {
+    init_log();
+    let l_rtc = Rtc::builder().enable_bwe(Some(Bitrate::kbps(500))).build();
+    let r_rtc = Rtc::builder().enable_raw_packets(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let rid_h: Rid = "h".into();
+    let rid_l: Rid = "l".into();
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        let config = MediaConfig {
+            rids: vec![rid_h, rid_l],
+            ..Default::default()
+        };
+        change
+            .add_media_with_config(MediaKind::Video, Direction::SendOnly, config)
+            .unwrap()
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    // Only the low layer is active to start with.
+    write_layer(&mut l, &mut r, mid, rid_l, Duration::from_millis(500))?;
+
+    l.bwe().set_current_bitrate(Bitrate::kbps(10));
+    l.bwe().set_desired_bitrate(Bitrate::kbps(500));
+
+    write_layer(&mut l, &mut r, mid, rid_l, Duration::from_secs(3))?;
+
+    assert!(padding_packets(&mut l, mid, rid_l) > 0);
+    assert_eq!(padding_packets(&mut l, mid, rid_h), 0);
+
+    // Switch over to the high layer.
+    l.direct_api()
+        .stream_tx_by_mid(mid, Some(rid_l))
+        .unwrap()
+        .set_paused(true);
+    let padding_l = padding_packets(&mut l, mid, rid_l);
+
+    write_layer(&mut l, &mut r, mid, rid_h, Duration::from_secs(3))?;
+
+    assert!(padding_packets(&mut l, mid, rid_h) > 0);
+    assert_eq!(padding_packets(&mut l, mid, rid_l), padding_l);
+
+    // Padding never arrives on the media SSRCs.
+    let mut api = l.direct_api();
+    let rtx: Vec<Ssrc> = [rid_h, rid_l]
+        .iter()
+        .map(|rid| {
+            api.stream_tx_by_mid(mid, Some(*rid))
+                .unwrap()
+                .rtx()
+                .unwrap()
+        })
+        .collect();
+
+    let received: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e.as_raw_packet() {
+            Some(RawPacket::RtpRx(header, data)) => Some((header.ssrc, data.is_empty())),
+            _ => None,
+        })
+        .collect();
+
+    assert!(received.iter().any(|(ssrc, _)| rtx.contains(ssrc)));
+    assert!(received
+        .iter()
+        .all(|(ssrc, blank)| !blank || rtx.contains(ssrc)));
+
+    Ok(())
+}
+
+fn write_layer(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    mid: Mid,
+    rid: Rid,
+    duration: Duration,
+) -> Result<(), RtcError> {
+    let pt = l.params_vp8().pt();
+    let until = l.duration() + duration;
+
+    // Media way below the estimate, which makes the pacer pad.
+    let mut write_at = l.last;
+
+    while l.duration() < until {
+        if l.last >= write_at {
+            write_at = l.last + Duration::from_millis(100);
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid)
+                .unwrap()
+                .rid(rid)
+                .write(pt, wallclock, time, vec![1_u8; 100])?;
+        }
+
+        progress(l, r)?;
+    }
+
+    Ok(())
+}
+
+fn padding_packets(l: &mut TestRtc, mid: Mid, rid: Rid) -> u64 {
+    l.direct_api()
+        .stream_tx_by_mid(mid, Some(rid))
+        .unwrap()
+        .stats()
+        .packets_padding
+}
@@ -2,6 +2,7 @@ use std::net::Ipv4Addr;
 use std::time::Duration;
 
 use str0m::bwe::{Bitrate, BweKind};
+use str0m::format::CodecConfig;
 use str0m::media::{Direction, MediaKind};
 use str0m::{Candidate, Event, Rtc, RtcError};
 use tracing::info_span;
@@ -74,3 +75,84 @@ pub fn remb() -> Result<(), RtcError> {
 
     Ok(())
 }
+
+#[test]
+pub fn remb_fallback_without_twcc() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l_config = Rtc::builder().enable_bwe(Some(Bitrate::kbps(300)));
+    no_transport_cc(l_config.codec_config());
+    let mut r_config = Rtc::builder();
+    no_transport_cc(r_config.codec_config());
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_config.build());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_config.build());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 1000])?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    // R estimates on the receive side and sends REMB, since there is no TWCC.
+    let rembs: Vec<_> = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::EgressBitrateEstimate(BweKind::Remb(m, v)) => {
+                assert_eq!(*m, mid);
+                Some(*v)
+            }
+            _ => None,
+        })
+        .collect();
+
+    assert!(rembs.len() >= 3, "expected regular REMB, got: {:?}", rembs);
+
+    // Without TWCC feedback, the REMB is the estimate on the send side.
+    let last = *rembs.last().unwrap();
+    assert_eq!(l.bwe().estimate(), Some(last));
+
+    Ok(())
+}
+
+/// Negotiate REMB, but not TWCC.
+fn no_transport_cc(config: &mut CodecConfig) {
+    let params: Vec<_> = config.params().to_vec();
+    config.clear();
+
+    for mut p in params {
+        p.set_fb_transport_cc(false);
+        config.add_payload_params(p);
+    }
+}
@@ -109,6 +109,10 @@ pub fn rtp_direct_mid_rid() -> Result<(), RtcError> {
 
     assert_eq!(media.len(), 3);
 
+    // All packets are tagged with the layer they belong to.
+    assert!(media.iter().all(|p| p.header.ext_vals.mid == Some(mid)));
+    assert!(media.iter().all(|p| p.header.ext_vals.rid == Some(rid)));
+
     assert!(l.media(mid).is_some());
     assert!(l.direct_api().stream_tx_by_mid(mid, None).is_some());
     l.direct_api().remove_media(mid);
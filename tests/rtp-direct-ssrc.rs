@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 use std::time::Duration;
 
+use str0m::error::RtpError;
 use str0m::format::Codec;
 use str0m::media::MediaKind;
 use str0m::rtp::{ExtensionValues, Ssrc};
@@ -142,3 +143,38 @@ pub fn rtp_direct_ssrc() -> Result<(), RtcError> {
 
     Ok(())
 }
+
+#[test]
+pub fn rtp_direct_pt_out_of_range() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, _r) = connect_l_r();
+
+    let mid = "aud".into();
+    let ssrc: Ssrc = 1.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    let wallclock = l.start + l.duration();
+    let mut direct = l.direct_api();
+    let stream = direct.stream_tx(&ssrc).unwrap();
+
+    let res = stream.write_rtp(
+        200.into(),
+        47_000.into(),
+        47_000_000,
+        wallclock,
+        false,
+        ExtensionValues::default(),
+        false,
+        vec![1, 2, 3],
+    );
+
+    assert!(matches!(
+        res,
+        Err(RtcError::Rtp(RtpError::PayloadTypeOutOfRange(pt))) if *pt == 200
+    ));
+
+    Ok(())
+}
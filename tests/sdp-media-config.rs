@@ -0,0 +1,126 @@
+use str0m::change::MediaConfig;
+use str0m::media::{Direction, MediaKind, Mid};
+use str0m::RtcError;
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, TestRtc};
+
+#[test]
+pub fn sdp_media_config_explicit_ids() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let config = MediaConfig {
+        stream_id: Some("stream".into()),
+        track_id: Some("track".into()),
+        mid: Some("v0".into()),
+        rids: vec!["h".into(), "l".into()],
+        ssrcs: vec![
+            (1000.into(), Some(1001.into())),
+            (2000.into(), Some(2001.into())),
+        ],
+    };
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media_with_config(MediaKind::Video, Direction::SendOnly, config)?;
+    assert_eq!(mid, "v0".into());
+
+    let (offer, pending) = change.apply().unwrap();
+    let sdp = offer.to_sdp_string();
+
+    assert!(sdp.contains("a=mid:v0"));
+    assert!(sdp.contains("a=msid:stream track"));
+    assert!(sdp.contains("a=simulcast:send h;l"));
+    assert!(sdp.contains("a=ssrc:1000 cname:"));
+    assert!(sdp.contains("a=ssrc:2001 cname:"));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    assert!(r.rtc.media(mid).is_some());
+
+    let mut api = l.direct_api();
+    let tx = api
+        .stream_tx(&1000.into())
+        .expect("stream for explicit ssrc");
+    assert_eq!(tx.mid(), mid);
+    assert_eq!(tx.rtx(), Some(1001.into()));
+    assert_eq!(tx.rid(), Some("h".into()));
+
+    let tx = api
+        .stream_tx(&2000.into())
+        .expect("stream for explicit ssrc");
+    assert_eq!(tx.rid(), Some("l".into()));
+
+    Ok(())
+}
+
+#[test]
+pub fn sdp_media_config_rejects_conflicts() {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let mid: Mid = "a0".into();
+
+    negotiate(&mut l, &mut r, |change| {
+        let config = MediaConfig {
+            mid: Some(mid),
+            ssrcs: vec![(42.into(), None)],
+            ..Default::default()
+        };
+        change
+            .add_media_with_config(MediaKind::Audio, Direction::SendRecv, config)
+            .unwrap();
+    });
+
+    let mut change = l.sdp_api();
+
+    // The mid is already used by the session.
+    let config = MediaConfig {
+        mid: Some(mid),
+        ..Default::default()
+    };
+    let err = change.add_media_with_config(MediaKind::Audio, Direction::SendRecv, config);
+    assert!(matches!(err, Err(RtcError::InvalidMediaConfig(_))));
+
+    // The SSRC is already used by the session.
+    let config = MediaConfig {
+        ssrcs: vec![(42.into(), None)],
+        ..Default::default()
+    };
+    let err = change.add_media_with_config(MediaKind::Audio, Direction::SendRecv, config);
+    assert!(matches!(err, Err(RtcError::InvalidMediaConfig(_))));
+
+    // One SSRC pair per RID.
+    let config = MediaConfig {
+        rids: vec!["h".into(), "l".into()],
+        ssrcs: vec![(7.into(), Some(8.into()))],
+        ..Default::default()
+    };
+    let err = change.add_media_with_config(MediaKind::Video, Direction::SendOnly, config);
+    assert!(matches!(err, Err(RtcError::InvalidMediaConfig(_))));
+
+    // Duplicate RIDs.
+    let config = MediaConfig {
+        rids: vec!["h".into(), "h".into()],
+        ..Default::default()
+    };
+    let err = change.add_media_with_config(MediaKind::Video, Direction::SendOnly, config);
+    assert!(matches!(err, Err(RtcError::InvalidMediaConfig(_))));
+
+    // A mid taken by a change in the same batch.
+    let config = MediaConfig {
+        mid: Some("b0".into()),
+        ..Default::default()
+    };
+    change
+        .add_media_with_config(MediaKind::Audio, Direction::SendRecv, config.clone())
+        .unwrap();
+    let err = change.add_media_with_config(MediaKind::Audio, Direction::SendRecv, config);
+    assert!(matches!(err, Err(RtcError::InvalidMediaConfig(_))));
+}
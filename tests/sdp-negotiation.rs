@@ -9,9 +9,12 @@ use str0m::format::FormatParams;
 use str0m::format::PayloadParams;
 use str0m::media::Direction;
 use str0m::media::Frequency;
+use str0m::media::KeyframeRequestKind;
 use str0m::media::MediaKind;
+use str0m::media::MediaTime;
 use str0m::rtp::{Extension, ExtensionMap};
 use str0m::Rtc;
+use str0m::RtcError;
 use tracing::info_span;
 use tracing::Span;
 
@@ -373,6 +376,68 @@ fn media_creator_can_change_inactive_to_recvonly() {
     assert_eq!(m_r.direction(), Direction::SendOnly);
 }
 
+#[test]
+fn write_fails_when_direction_is_not_sending() {
+    init_log();
+    let (mut l, mut r) = (
+        TestRtc::new_with_rtc(
+            info_span!("L"),
+            Rtc::builder().clear_codecs().enable_vp8(true).build(),
+        ),
+        TestRtc::new_with_rtc(
+            info_span!("R"),
+            Rtc::builder().clear_codecs().enable_vp8(true).build(),
+        ),
+    );
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    // R is the receiving side of a sendonly media.
+    let m_r = r.media(mid).unwrap();
+    assert_eq!(m_r.direction(), Direction::RecvOnly);
+
+    let pt = r.params_vp8().pt();
+    let now = r.start;
+    let res = r
+        .writer(mid)
+        .unwrap()
+        .write(pt, now, MediaTime::from_90khz(0), vec![1, 2, 3]);
+
+    assert!(matches!(
+        res,
+        Err(RtcError::NotSendingDirection(Direction::RecvOnly))
+    ));
+}
+
+#[test]
+fn request_keyframe_fails_when_direction_is_not_receiving() {
+    init_log();
+    let (mut l, mut r) = (
+        TestRtc::new_with_rtc(
+            info_span!("L"),
+            Rtc::builder().clear_codecs().enable_vp8(true).build(),
+        ),
+        TestRtc::new_with_rtc(
+            info_span!("R"),
+            Rtc::builder().clear_codecs().enable_vp8(true).build(),
+        ),
+    );
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    // L is only sending, so there is nothing to request a keyframe for.
+    let res = l
+        .writer(mid)
+        .unwrap()
+        .request_keyframe(None, KeyframeRequestKind::Pli);
+
+    assert!(matches!(res, Err(RtcError::NotReceivingDirection)));
+}
+
 fn with_params(
     span_l: Span,
     params_l: &[PayloadParams],
@@ -471,3 +536,38 @@ fn h264(pt: u8) -> PayloadParams {
         },
     )
 }
+
+#[test]
+fn multiple_media_of_same_kind() {
+    init_log();
+    let (mut l, mut r) = (TestRtc::new(info_span!("L")), TestRtc::new(info_span!("R")));
+
+    let mids = negotiate(&mut l, &mut r, |change| {
+        [
+            change.add_media(MediaKind::Audio, Direction::SendRecv, None, None),
+            change.add_media(MediaKind::Video, Direction::SendOnly, None, None),
+            change.add_media(MediaKind::Video, Direction::SendOnly, None, None),
+            change.add_media(MediaKind::Audio, Direction::RecvOnly, None, None),
+        ]
+    });
+
+    let l_mids: Vec<_> = l.medias().map(|m| m.mid()).collect();
+    let r_mids: Vec<_> = r.medias().map(|m| m.mid()).collect();
+    assert_eq!(l_mids, mids);
+    assert_eq!(r_mids, mids);
+
+    let kinds: Vec<_> = r.medias().map(|m| (m.kind(), m.direction())).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (MediaKind::Audio, Direction::SendRecv),
+            (MediaKind::Video, Direction::RecvOnly),
+            (MediaKind::Video, Direction::RecvOnly),
+            (MediaKind::Audio, Direction::SendOnly),
+        ]
+    );
+
+    // Each video media has its own writer.
+    assert!(l.writer(mids[1]).is_some());
+    assert!(l.writer(mids[2]).is_some());
+}
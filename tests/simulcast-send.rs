@@ -0,0 +1,151 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::change::MediaConfig;
+use str0m::media::{Direction, MediaKind, Mid, Rid};
+use str0m::rtp::Ssrc;
+use str0m::{Candidate, Event, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn simulcast_send() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let rid_h: Rid = "h".into();
+    let rid_l: Rid = "l".into();
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        let config = MediaConfig {
+            rids: vec![rid_h, rid_l],
+            ..Default::default()
+        };
+        change
+            .add_media_with_config(MediaKind::Video, Direction::SendOnly, config)
+            .unwrap()
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    // Each layer has its own SSRC and RTX.
+    let (ssrc_h, rtx_h) = tx_ssrcs(&mut l, mid, rid_h);
+    let (ssrc_l, rtx_l) = tx_ssrcs(&mut l, mid, rid_l);
+    assert_ne!(ssrc_h, ssrc_l);
+    assert!(rtx_h.is_some() && rtx_l.is_some());
+    assert_ne!(rtx_h, rtx_l);
+
+    write_layers(&mut l, &mut r, mid, &[rid_h, rid_l], Duration::from_secs(3))?;
+
+    // The receiver tells the layers apart by the RID extension.
+    let packets_h = rx_packets(&mut r, mid, rid_h);
+    let packets_l = rx_packets(&mut r, mid, rid_l);
+    assert!(packets_h > 0);
+    assert!(packets_l > 0);
+
+    let mut api = r.direct_api();
+    let rx_h = api.stream_rx_by_mid(mid, Some(rid_h)).unwrap();
+    assert_eq!(rx_h.ssrc(), ssrc_h);
+    // Sender reports are sent per layer.
+    assert!(rx_h.sender_info().is_some());
+    let rx_l = api.stream_rx_by_mid(mid, Some(rid_l)).unwrap();
+    assert_eq!(rx_l.ssrc(), ssrc_l);
+    assert!(rx_l.sender_info().is_some());
+
+    let rids: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => v.rid,
+            _ => None,
+        })
+        .collect();
+    assert!(rids.contains(&rid_h));
+    assert!(rids.contains(&rid_l));
+
+    // Disable the low layer at runtime.
+    l.direct_api()
+        .stream_tx_by_mid(mid, Some(rid_l))
+        .unwrap()
+        .set_paused(true);
+
+    write_layers(&mut l, &mut r, mid, &[rid_h, rid_l], Duration::from_secs(1))?;
+
+    assert!(rx_packets(&mut r, mid, rid_h) > packets_h);
+    let paused_l = rx_packets(&mut r, mid, rid_l);
+    assert_eq!(paused_l, packets_l);
+
+    // And enable it again.
+    l.direct_api()
+        .stream_tx_by_mid(mid, Some(rid_l))
+        .unwrap()
+        .set_paused(false);
+
+    write_layers(&mut l, &mut r, mid, &[rid_h, rid_l], Duration::from_secs(1))?;
+
+    assert!(rx_packets(&mut r, mid, rid_l) > paused_l);
+
+    Ok(())
+}
+
+fn write_layers(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    mid: Mid,
+    rids: &[Rid],
+    duration: Duration,
+) -> Result<(), RtcError> {
+    let pt = l.params_vp8().pt();
+    let until = l.duration() + duration;
+
+    while l.duration() < until {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        for rid in rids {
+            l.writer(mid)
+                .unwrap()
+                .rid(*rid)
+                .write(pt, wallclock, time, vec![1_u8; 500])?;
+        }
+
+        progress(l, r)?;
+    }
+
+    // Let the pacer drain.
+    for _ in 0..50 {
+        progress(l, r)?;
+    }
+
+    Ok(())
+}
+
+fn tx_ssrcs(l: &mut TestRtc, mid: Mid, rid: Rid) -> (Ssrc, Option<Ssrc>) {
+    let mut api = l.direct_api();
+    let tx = api.stream_tx_by_mid(mid, Some(rid)).expect("layer stream");
+    (tx.ssrc(), tx.rtx())
+}
+
+fn rx_packets(r: &mut TestRtc, mid: Mid, rid: Rid) -> u64 {
+    r.direct_api()
+        .stream_rx_by_mid(mid, Some(rid))
+        .map(|s| s.stats().packets)
+        .unwrap_or(0)
+}
@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use str0m::media::{MediaKind, Mid};
+use str0m::rtp::{ExtensionValues, Ssrc};
+use str0m::{Event, RtcError};
+
+mod common;
+use common::{connect_l_r, init_log, progress};
+
+#[test]
+pub fn ssrc_collision() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid: Mid = "aud".into();
+    let ssrc: Ssrc = 1.into();
+
+    // Both sides declare sending on the same SSRC, but only L sends.
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+    r.direct_api().declare_stream_tx(ssrc, None, mid, None);
+    r.direct_api().expect_stream_rx(ssrc, None, mid, None);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let mut write_at = l.last;
+    let mut count = 0;
+
+    loop {
+        if count < 10 && l.last >= write_at {
+            write_at = l.last + Duration::from_millis(100);
+            count += 1;
+
+            let wallclock = l.start + l.duration();
+            let mut direct = l.direct_api();
+            let stream = direct.stream_tx(&ssrc).unwrap();
+            stream.write_rtp(
+                pt,
+                (count as u64).into(),
+                count * 960,
+                wallclock,
+                false,
+                ExtensionValues::default(),
+                false,
+                vec![1, 2, 3, 4],
+            )?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(2) {
+            break;
+        }
+    }
+
+    let collisions: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::SsrcCollision(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(collisions[0].mid, mid);
+    assert_eq!(collisions[0].old, ssrc);
+
+    // R moved its outgoing stream to the new SSRC.
+    let new = collisions[0].new;
+    assert_ne!(new, ssrc);
+    let tx = r.direct_api().stream_tx_by_mid(mid, None).unwrap().ssrc();
+    assert_eq!(tx, new);
+
+    // L is unaffected, and R receives all its packets.
+    assert!(l
+        .events
+        .iter()
+        .all(|(_, e)| !matches!(e, Event::SsrcCollision(_))));
+
+    let received = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::RtpPacket(_)))
+        .count();
+    assert_eq!(received, 10);
+
+    Ok(())
+}
+
+#[test]
+pub fn ssrc_collision_remote_sources() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid_a: Mid = "aud".into();
+    let mid_b: Mid = "aub".into();
+    let ssrc: Ssrc = 1.into();
+
+    l.direct_api().declare_media(mid_a, MediaKind::Audio);
+    l.direct_api().declare_media(mid_b, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid_a, None);
+
+    r.direct_api().declare_media(mid_a, MediaKind::Audio);
+    r.direct_api().declare_media(mid_b, MediaKind::Audio);
+    r.direct_api().expect_stream_rx(ssrc, None, mid_a, None);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let mut write_at = l.last;
+    let mut count = 0;
+
+    loop {
+        if count < 10 && l.last >= write_at {
+            write_at = l.last + Duration::from_millis(100);
+            count += 1;
+
+            // Halfway through, a second source starts using the same SSRC for mid B.
+            if count == 6 {
+                let mut direct = l.direct_api();
+                direct.remove_stream_tx(ssrc);
+                direct.declare_stream_tx(ssrc, None, mid_b, None);
+            }
+
+            let wallclock = l.start + l.duration();
+            let mut direct = l.direct_api();
+            let stream = direct.stream_tx(&ssrc).unwrap();
+            stream.write_rtp(
+                pt,
+                (count as u64).into(),
+                count * 960,
+                wallclock,
+                false,
+                ExtensionValues::default(),
+                false,
+                vec![1, 2, 3, 4],
+            )?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(2) {
+            break;
+        }
+    }
+
+    // The SSRC stays bound to mid A, and the packets for mid B are dropped.
+    let received: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::RtpPacket(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(received.len(), 5);
+    assert!(received
+        .iter()
+        .all(|p| p.header.ext_vals.mid == Some(mid_a)));
+
+    // None of this is a collision with our own SSRCs.
+    assert!(r
+        .events
+        .iter()
+        .all(|(_, e)| !matches!(e, Event::SsrcCollision(_))));
+
+    Ok(())
+}
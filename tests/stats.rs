@@ -1,6 +1,7 @@
 use std::net::Ipv4Addr;
 use std::time::Duration;
 
+use str0m::bwe::Bitrate;
 use str0m::format::Codec;
 use str0m::media::{Direction, MediaKind};
 use str0m::stats::MediaEgressStats;
@@ -8,7 +9,7 @@ use str0m::{Candidate, Event, RtcConfig, RtcError};
 use tracing::info_span;
 
 mod common;
-use common::{init_log, progress, TestRtc};
+use common::{init_log, negotiate, progress, progress_with_loss, TestRtc};
 
 #[test]
 pub fn stats() -> Result<(), RtcError> {
@@ -136,3 +137,463 @@ pub fn stats() -> Result<(), RtcError> {
 
     Ok(())
 }
+
+#[test]
+pub fn stats_snapshot() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let cid = change.add_channel("Stats".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 80])?;
+
+        if let Some(mut chan) = l.channel(cid) {
+            chan.write(true, &[1; 10])?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let stats = l.stats();
+
+    assert_eq!(stats.egress.len(), 1);
+    assert_eq!(stats.egress[0].mid, mid);
+    assert!(stats.egress[0].packets > 0);
+    assert!(stats.peer.peer_bytes_tx > 0);
+
+    let pair = stats.candidate_pair.expect("candidate pair");
+    assert_eq!(pair.remote.port(), 2000);
+    assert!(pair.state.is_connected());
+    assert!(pair.packets_sent > 0);
+    assert!(pair.bytes_sent > pair.packets_sent);
+    assert!(pair.packets_received > 0);
+    assert!(pair.rtt.is_some());
+    assert!(pair.last_consent.is_some());
+    assert!(pair.binding_requests_sent > 0);
+    assert!(pair.binding_responses_received > 0);
+
+    // BWE is not enabled.
+    assert!(stats.bwe.is_none());
+
+    let rtt = stats.rtt.expect("rtt from STUN and RTCP");
+    assert!(rtt.min <= rtt.smoothed && rtt.smoothed <= rtt.max);
+    assert_eq!(l.rtt(), Some(rtt));
+
+    assert_eq!(stats.channels.len(), 1);
+    assert_eq!(stats.channels[0].id, cid);
+    assert_eq!(stats.channels[0].label, "Stats");
+    assert!(stats.channels[0].messages_sent > 0);
+    assert_eq!(
+        stats.channels[0].bytes_sent,
+        stats.channels[0].messages_sent * 10
+    );
+
+    let stats = r.stats();
+    assert_eq!(stats.ingress.len(), 1);
+    assert!(stats.ingress[0].packets > 0);
+    assert!(stats.channels[0].messages_received > 0);
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(
+            json["ingress"][0]["packets"].as_u64(),
+            Some(stats.ingress[0].packets)
+        );
+        // Instant has no meaning outside the process.
+        assert!(json.get("timestamp").is_none());
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn stats_candidate_pair_counters() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendRecv, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+
+    let write_for = |l: &mut TestRtc, r: &mut TestRtc, secs: u64| -> Result<(), RtcError> {
+        let until = l.duration() + Duration::from_secs(secs);
+        while l.duration() < until {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid)
+                .unwrap()
+                .write(pt, wallclock, time, vec![1_u8; 80])?;
+            progress(l, r)?;
+        }
+        Ok(())
+    };
+
+    write_for(&mut l, &mut r, 2)?;
+    let l0 = l.stats().candidate_pair.expect("l pair");
+    let r0 = r.stats().candidate_pair.expect("r pair");
+
+    // Long enough for RTCP from R, which does not send media.
+    write_for(&mut l, &mut r, 8)?;
+    let l1 = l.stats().candidate_pair.expect("l pair");
+    let r1 = r.stats().candidate_pair.expect("r pair");
+
+    // Both sides count at the same point and exclude STUN, so over a lossless
+    // window, what one side sends is exactly what the other side receives.
+    assert!(l1.packets_sent > l0.packets_sent);
+    assert_eq!(
+        l1.packets_sent - l0.packets_sent,
+        r1.packets_received - r0.packets_received
+    );
+    assert_eq!(
+        l1.bytes_sent - l0.bytes_sent,
+        r1.bytes_received - r0.bytes_received
+    );
+    assert!(r1.packets_sent > r0.packets_sent);
+    assert_eq!(
+        r1.packets_sent - r0.packets_sent,
+        l1.packets_received - l0.packets_received
+    );
+    assert_eq!(
+        r1.bytes_sent - r0.bytes_sent,
+        l1.bytes_received - l0.bytes_received
+    );
+
+    // STUN keeps going on the pair, counted only in the binding stats.
+    assert!(l1.binding_requests_sent > l0.binding_requests_sent);
+    assert!(l1.binding_responses_received > l0.binding_responses_received);
+    assert!(l1.binding_responses_received <= l1.binding_requests_sent);
+    assert!(r1.binding_requests_received > r0.binding_requests_received);
+
+    Ok(())
+}
+
+#[test]
+pub fn stats_snapshot_keeps_loss_interval() -> Result<(), RtcError> {
+    init_log();
+
+    let config = || RtcConfig::new().set_stats_interval(Some(Duration::from_secs(1)));
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), config().build());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), config().build());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let start = r.events.len();
+    fastrand::seed(42);
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 80])?;
+
+        progress_with_loss(&mut l, &mut r, 0.2)?;
+
+        // Snapshots in between must not reset the interval of the stats events.
+        r.stats();
+
+        if l.duration() > Duration::from_secs(10) {
+            break;
+        }
+    }
+
+    let losses: Vec<_> = r.events[start..]
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::PeerStats(v) => v.ingress_loss_fraction,
+            _ => None,
+        })
+        .collect();
+
+    // Each event covers a second of packets, which is loss close to the 20% we drop.
+    assert!(losses.len() > 5, "stats events: {}", losses.len());
+    for loss in losses.iter().skip(1) {
+        assert!((0.05..0.5).contains(loss), "loss: {}", loss);
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn stats_stream_rx_tx() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 80])?;
+
+        progress(&mut l, &mut r)?;
+
+        // Long enough to get receiver reports.
+        if l.duration() > Duration::from_secs(10) {
+            break;
+        }
+    }
+
+    let last = r.last;
+    let stats = r
+        .direct_api()
+        .stream_rx_by_mid(mid, None)
+        .expect("stream rx")
+        .stats();
+
+    assert!(stats.packets > 100);
+    assert_eq!(stats.bytes, stats.packets * 80);
+    assert_eq!(stats.packets_lost, 0);
+    assert_eq!(stats.packets_discarded, 0);
+    assert!(stats.jitter.is_some());
+    assert!(stats.last_packet.unwrap() <= last);
+
+    let stats = l
+        .direct_api()
+        .stream_tx_by_mid(mid, None)
+        .expect("stream tx")
+        .stats();
+
+    assert!(stats.packets > 100);
+    assert_eq!(stats.packets_resent, 0);
+    assert!(stats.bitrate.as_u64() > 0);
+    assert!(stats.rtt.is_some());
+    assert_eq!(stats.remote_packets_lost, Some(0));
+    assert!(stats.remote_jitter.is_some());
+
+    Ok(())
+}
+
+#[test]
+pub fn stats_interval_at_runtime() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let peer_stats = |t: &TestRtc| {
+        t.events
+            .iter()
+            .filter(|(_, e)| matches!(e, Event::PeerStats(_)))
+            .count()
+    };
+
+    // Disabled by default.
+    assert_eq!(peer_stats(&l), 0);
+
+    l.set_stats_interval(Some(Duration::from_secs(1)));
+    let start = l.duration();
+
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > start + Duration::from_millis(5500) {
+            break;
+        }
+    }
+
+    let count = peer_stats(&l);
+    assert!((4..=6).contains(&count), "Unexpected PeerStats: {}", count);
+
+    l.set_stats_interval(None);
+
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > start + Duration::from_secs(10) {
+            break;
+        }
+    }
+
+    assert_eq!(peer_stats(&l), count);
+
+    Ok(())
+}
+
+#[test]
+pub fn stats_bwe() -> Result<(), RtcError> {
+    init_log();
+
+    let l_rtc = RtcConfig::new()
+        .enable_bwe(Some(Bitrate::kbps(300)))
+        .build();
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 1000])?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let bwe = l.stats().bwe.expect("bwe stats");
+
+    assert!(bwe.estimate.is_some());
+    assert!(bwe.acked_bitrate.is_some());
+    assert!(bwe.rtt.is_some());
+    assert!(bwe.delay_estimate >= bwe.estimate.unwrap());
+    assert!(bwe.trendline_threshold > 0.0);
+    // R sends no REMB.
+    assert!(bwe.remb_cap.is_none());
+
+    // A burst, like a keyframe, waits for the pacer.
+    let wallclock = l.start + l.duration();
+    let time = l.duration().into();
+    l.writer(mid)
+        .unwrap()
+        .write(pt, wallclock, time, vec![1_u8; 50_000])?;
+    let burst_at = l.duration();
+    while l.duration() < burst_at + Duration::from_millis(50) {
+        progress(&mut l, &mut r)?;
+    }
+
+    let bwe = l.stats().bwe.expect("bwe stats");
+    assert!(bwe.pacer_queue_delay > Duration::ZERO);
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::bwe::Bitrate;
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn stream_tx_backpressure() -> Result<(), RtcError> {
+    init_log();
+    let l_rtc = Rtc::builder().enable_bwe(Some(Bitrate::kbps(100))).build();
+    let r_rtc = Rtc::builder().build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    l.direct_api()
+        .stream_tx_by_mid(mid, None)
+        .unwrap()
+        .set_queue_delay_budget(Some(Duration::from_millis(200)));
+
+    let pt = l.params_vp8().pt();
+
+    // Way more than the pacer lets through at the initial estimate.
+    let data = vec![1_u8; 10_000];
+    let mut blocked = 0;
+
+    loop {
+        // Stop writing half way to let the queue drain.
+        if l.duration() < Duration::from_secs(3) {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            match l
+                .writer(mid)
+                .unwrap()
+                .write(pt, wallclock, time, data.clone())
+            {
+                Ok(()) => {}
+                Err(RtcError::SendQueueFull) => blocked += 1,
+                Err(e) => return Err(e),
+            }
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(20) {
+            break;
+        }
+    }
+
+    assert!(blocked > 0);
+
+    let writable: Vec<_> = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::StreamWritable(w) => Some(w.writable),
+            _ => None,
+        })
+        .collect();
+
+    // Might toggle while we keep writing, but ends up writable once drained.
+    assert_eq!(writable.first(), Some(&false));
+    assert_eq!(writable.last(), Some(&true));
+
+    Ok(())
+}
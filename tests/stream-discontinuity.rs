@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use str0m::media::{MediaKind, Mid};
+use str0m::rtp::{DiscontinuityReason, ExtensionValues, Ssrc};
+use str0m::{Event, RtcError};
+
+mod common;
+use common::{connect_l_r, init_log, progress, TestRtc};
+
+#[test]
+pub fn discontinuity_on_sequence_jump() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid = "aud".into();
+    let ssrc: Ssrc = 42.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    write_packets(&mut l, &mut r, ssrc, 1000..1020)?;
+
+    // A stray old packet is not enough to be a discontinuity.
+    write_packets(&mut l, &mut r, ssrc, 900..901)?;
+    write_packets(&mut l, &mut r, ssrc, 1020..1030)?;
+    assert!(discontinuities(&r).is_empty());
+
+    // A jump too far ahead to be loss.
+    write_packets(&mut l, &mut r, ssrc, 20_000..20_020)?;
+
+    let found = discontinuities(&r);
+    assert_eq!(found.len(), 1);
+    let (index, ssrc_after, mid_after, reason) = found[0];
+    assert_eq!(ssrc_after, ssrc);
+    assert_eq!(mid_after, mid);
+    assert_eq!(reason, DiscontinuityReason::SequenceJump);
+
+    // The event comes before the first packet after the jump.
+    let first_after = first_packet_index(&r, 20_000).expect("packet after jump");
+    assert!(index < first_after);
+
+    // The receive state starts over from the jump, it is not counted as loss.
+    let stats = r.direct_api().stream_rx(&ssrc).unwrap().stats();
+    assert_eq!(stats.packets_lost, 0);
+
+    Ok(())
+}
+
+#[test]
+pub fn discontinuity_on_ssrc_change() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid = "aud".into();
+    let ssrc1: Ssrc = 42.into();
+    let ssrc2: Ssrc = 43.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc1, None, mid, None);
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    write_packets(&mut l, &mut r, ssrc1, 1000..1010)?;
+    assert!(discontinuities(&r).is_empty());
+
+    // The remote switches source for the same mid.
+    l.direct_api().remove_stream_tx(ssrc1);
+    l.direct_api().declare_stream_tx(ssrc2, None, mid, None);
+
+    write_packets(&mut l, &mut r, ssrc2, 5000..5010)?;
+
+    let found = discontinuities(&r);
+    assert_eq!(found.len(), 1);
+    let (index, ssrc_after, mid_after, reason) = found[0];
+    assert_eq!(ssrc_after, ssrc2);
+    assert_eq!(mid_after, mid);
+    assert_eq!(reason, DiscontinuityReason::SsrcChanged { previous: ssrc1 });
+
+    let first_after = first_packet_index(&r, 5000).expect("packet after change");
+    assert!(index < first_after);
+
+    // The stream is the same, under the new SSRC.
+    let rx = r.direct_api().stream_rx_by_mid(mid, None).unwrap().ssrc();
+    assert_eq!(rx, ssrc2);
+
+    Ok(())
+}
+
+fn write_packets(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    ssrc: Ssrc,
+    seq_nos: std::ops::Range<u64>,
+) -> Result<(), RtcError> {
+    let pt = l.params_opus().pt();
+
+    for seq_no in seq_nos {
+        let wallclock = l.start + l.duration();
+        let time = (seq_no * 960) as u32;
+
+        let mut direct = l.direct_api();
+        let stream = direct.stream_tx(&ssrc).unwrap();
+        stream
+            .write_rtp(
+                pt,
+                seq_no.into(),
+                time,
+                wallclock,
+                false,
+                ExtensionValues::default(),
+                false,
+                vec![0x1, 0x2, 0x3, 0x4],
+            )
+            .expect("clean write");
+
+        let until = l.duration() + Duration::from_millis(20);
+        while l.duration() < until {
+            progress(l, r)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn discontinuities(r: &TestRtc) -> Vec<(usize, Ssrc, Mid, DiscontinuityReason)> {
+    r.events
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (_, e))| match e {
+            Event::StreamDiscontinuity(v) => Some((i, v.ssrc, v.mid, v.reason)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn first_packet_index(r: &TestRtc, seq_no: u16) -> Option<usize> {
+    r.events.iter().position(|(_, e)| match e {
+        Event::RtpPacket(v) => v.header.sequence_number == seq_no,
+        _ => false,
+    })
+}
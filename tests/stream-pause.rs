@@ -0,0 +1,331 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::rtp::{ExtensionValues, Ssrc};
+use str0m::{Candidate, Event, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{connect_l_r, init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn stream_tx_pause() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid = "aud".into();
+    let ssrc_tx: Ssrc = 42.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc_tx, None, mid, None);
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+
+    let mut write_at = l.last + Duration::from_millis(300);
+    let mut count: u64 = 0;
+
+    loop {
+        if l.start + l.duration() > write_at && count < 6 {
+            write_at = l.last + Duration::from_millis(300);
+            let wallclock = l.start + l.duration();
+
+            let mut direct = l.direct_api();
+            let stream = direct.stream_tx(&ssrc_tx).unwrap();
+
+            // Pause after the first two packets.
+            if count == 2 {
+                stream.set_paused(true);
+                assert!(stream.is_paused());
+            }
+
+            let time = (count * 1000 + 47_000_000) as u32;
+            let seq_no = (47_000 + count).into();
+
+            stream
+                .write_rtp(
+                    pt,
+                    seq_no,
+                    time,
+                    wallclock,
+                    false,
+                    ExtensionValues::default(),
+                    false,
+                    vec![0x1, 0x2, 0x3, count as u8],
+                )
+                .expect("clean write");
+
+            count += 1;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(10) {
+            break;
+        }
+    }
+
+    let received = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::RtpPacket(_)))
+        .count();
+
+    assert_eq!(received, 2);
+
+    // Sender reports keep flowing while paused.
+    let rx = r.direct_api().stream_rx(&ssrc_tx).map(|s| s.sender_info());
+    assert!(matches!(rx, Some(Some(_))));
+
+    Ok(())
+}
+
+#[test]
+pub fn stream_tx_keep_alive_while_paused() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    // Video negotiates RTX, which the keep-alive is sent on.
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+
+    while l.duration() < Duration::from_secs(2) {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 100])?;
+        progress(&mut l, &mut r)?;
+    }
+
+    for _ in 0..20 {
+        progress(&mut l, &mut r)?;
+    }
+
+    let mut api = l.direct_api();
+    let tx = api.stream_tx_by_mid(mid, None).unwrap();
+    // Without BWE there is no padding.
+    assert_eq!(tx.stats().packets_padding, 0);
+    let packets = tx.stats().packets;
+
+    tx.set_keep_alive(Some(Duration::from_secs(1)));
+    tx.set_paused(true);
+
+    let rx_packets = r
+        .direct_api()
+        .stream_rx_by_mid(mid, None)
+        .unwrap()
+        .stats()
+        .packets;
+
+    let until = l.duration() + Duration::from_secs(6);
+    while l.duration() < until {
+        progress(&mut l, &mut r)?;
+    }
+
+    let stats = l.direct_api().stream_tx_by_mid(mid, None).unwrap().stats();
+    assert!(stats.packets_padding >= 2, "{stats:?}");
+    assert_eq!(stats.packets, packets);
+
+    // The keep-alives are not media.
+    let rx = r.direct_api().stream_rx_by_mid(mid, None).unwrap().stats();
+    assert_eq!(rx.packets, rx_packets);
+
+    Ok(())
+}
+
+#[test]
+pub fn stream_tx_keep_alive_without_rtx() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid = "aud".into();
+    let ssrc_tx: Ssrc = 42.into();
+
+    // Audio without RTX, the keep-alive goes on the main SSRC.
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc_tx, None, mid, None);
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    for count in 0..2 {
+        write_opus(&mut l, ssrc_tx, count);
+        for _ in 0..5 {
+            progress(&mut l, &mut r)?;
+        }
+    }
+
+    {
+        let mut direct = l.direct_api();
+        let stream = direct.stream_tx(&ssrc_tx).unwrap();
+        stream.set_keep_alive(Some(Duration::from_secs(1)));
+        stream.set_paused(true);
+    }
+
+    let until = l.duration() + Duration::from_secs(6);
+    while l.duration() < until {
+        progress(&mut l, &mut r)?;
+    }
+
+    let padding = {
+        let mut direct = l.direct_api();
+        let stream = direct.stream_tx(&ssrc_tx).unwrap();
+        stream.set_paused(false);
+        stream.stats().packets_padding
+    };
+    assert!(padding >= 2, "padding packets: {}", padding);
+
+    // The keep-alives are not media.
+    let received = |r: &TestRtc| {
+        r.events
+            .iter()
+            .filter_map(|(_, e)| match e {
+                Event::RtpPacket(p) => Some(p.header.sequence_number),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(received(&r), vec![47_000, 47_001]);
+
+    write_opus(&mut l, ssrc_tx, 2);
+    for _ in 0..5 {
+        progress(&mut l, &mut r)?;
+    }
+
+    // The keep-alives took sequence numbers, which shifts the following packets.
+    let seq_nos = received(&r);
+    assert_eq!(seq_nos.len(), 3);
+    assert_eq!(seq_nos[2], 47_002 + padding as u16);
+
+    Ok(())
+}
+
+#[test]
+pub fn stream_tx_pause_with_bye() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid = "aud".into();
+    let ssrc_tx: Ssrc = 42.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc_tx, None, mid, None);
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    for count in 0..2 {
+        write_opus(&mut l, ssrc_tx, count);
+        for _ in 0..5 {
+            progress(&mut l, &mut r)?;
+        }
+    }
+
+    l.direct_api()
+        .stream_tx(&ssrc_tx)
+        .unwrap()
+        .set_paused_with_bye();
+
+    let until = l.duration() + Duration::from_secs(6);
+    while l.duration() < until {
+        progress(&mut l, &mut r)?;
+    }
+
+    let ended: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::StreamEnded(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(ended.len(), 1);
+    assert_eq!(ended[0].ssrc, ssrc_tx);
+
+    // No sender reports after the BYE.
+    let rx = r.direct_api().stream_rx(&ssrc_tx).map(|s| s.sender_info());
+    assert!(matches!(rx, Some(None)));
+
+    // Resuming starts the stream over.
+    l.direct_api()
+        .stream_tx(&ssrc_tx)
+        .unwrap()
+        .set_paused(false);
+    write_opus(&mut l, ssrc_tx, 2);
+
+    let until = l.duration() + Duration::from_secs(6);
+    while l.duration() < until {
+        progress(&mut l, &mut r)?;
+    }
+
+    let received = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::RtpPacket(_)))
+        .count();
+    assert_eq!(received, 3);
+
+    let rx = r.direct_api().stream_rx(&ssrc_tx).map(|s| s.sender_info());
+    assert!(matches!(rx, Some(Some(_))));
+
+    Ok(())
+}
+
+fn write_opus(l: &mut TestRtc, ssrc: Ssrc, count: u64) {
+    let pt = l.params_opus().pt();
+    let wallclock = l.start + l.duration();
+
+    let mut direct = l.direct_api();
+    let stream = direct.stream_tx(&ssrc).unwrap();
+
+    let time = (count * 1000 + 47_000_000) as u32;
+    let seq_no = (47_000 + count).into();
+
+    stream
+        .write_rtp(
+            pt,
+            seq_no,
+            time,
+            wallclock,
+            false,
+            ExtensionValues::default(),
+            false,
+            vec![0x1, 0x2, 0x3, count as u8],
+        )
+        .expect("clean write");
+}
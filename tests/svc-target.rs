@@ -0,0 +1,130 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::format::{Codec, CodecExtra};
+use str0m::media::{Direction, MediaKind, SvcTarget};
+use str0m::{Candidate, Event, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::vp9_data;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn svc_target_narrows_vp9() -> Result<(), RtcError> {
+    init_log();
+
+    let all = receive_vp9(None)?;
+    assert!(!all.is_empty());
+
+    let target = SvcTarget {
+        spatial: 0,
+        temporal: 0,
+    };
+    let narrowed = receive_vp9(Some(target))?;
+
+    for (tid, layers_scheme, len) in &narrowed {
+        assert!(tid.map_or(true, |t| t <= target.temporal));
+        assert!(layers_scheme[1..].iter().all(|l| l.is_none()));
+        if let Some(end) = layers_scheme[0] {
+            assert!(*len <= end);
+        }
+    }
+
+    // Only the frames above the temporal target are dropped.
+    let expected = all
+        .iter()
+        .filter(|(tid, _, _)| tid.map_or(true, |t| t <= target.temporal))
+        .count();
+    assert_eq!(narrowed.len(), expected);
+
+    Ok(())
+}
+
+/// The tid, layers scheme and data length of a received MediaData.
+type Received = (Option<u8>, Vec<Option<usize>>, usize);
+
+/// Send the VP9 test data from L to R, with an SVC target on the R stream.
+fn receive_vp9(target: Option<SvcTarget>) -> Result<Vec<Received>, RtcError> {
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    // The stream is known from the a=ssrc lines in the SDP.
+    r.direct_api()
+        .stream_rx_by_mid(mid, None)
+        .expect("stream from SDP")
+        .set_svc_target(target);
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = l.params_vp9();
+    assert_eq!(params.spec().codec, Codec::Vp9);
+    let pt = params.pt();
+
+    for (relative, header, payload) in vp9_data() {
+        // Keep RTC time progressed to be "in sync" with the test data.
+        while (l.last - max) < relative {
+            progress(&mut l, &mut r)?;
+        }
+
+        let absolute = max + relative;
+
+        let mut direct = l.direct_api();
+        let tx = direct.stream_tx_by_mid(mid, None).unwrap();
+        tx.write_rtp(
+            pt,
+            header.sequence_number(None),
+            header.timestamp,
+            absolute,
+            header.marker,
+            header.ext_vals,
+            true,
+            payload,
+        )
+        .unwrap();
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let received = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(d) => Some(d),
+            _ => None,
+        })
+        .map(|d| {
+            let CodecExtra::Vp9(extra) = d.codec_extra else {
+                panic!("Got non VP9 CodecExtra")
+            };
+            (extra.tid, extra.layers_scheme.to_vec(), d.data.len())
+        })
+        .collect();
+
+    Ok(received)
+}
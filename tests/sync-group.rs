@@ -0,0 +1,119 @@
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+use str0m::media::{Direction, MediaKind, MediaTime, Mid};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn sync_group_by_cname() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc = |span| {
+        let rtc = Rtc::builder()
+            .set_rtcp_report_interval(Duration::from_millis(500), Duration::from_millis(500))
+            .build();
+        TestRtc::new_with_rtc(span, rtc)
+    };
+
+    let mut l = rtc(info_span!("L"));
+    let mut r = rtc(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    // Audio and video with the same CNAME, which is the track id, and a second audio
+    // with another.
+    let (audio, video, other) = negotiate(&mut l, &mut r, |change| {
+        let s1 = Some("s1".to_string());
+        let t1 = Some("s1-track".to_string());
+        let t2 = Some("s2-track".to_string());
+        (
+            change.add_media(
+                MediaKind::Audio,
+                Direction::SendOnly,
+                s1.clone(),
+                t1.clone(),
+            ),
+            change.add_media(MediaKind::Video, Direction::SendOnly, s1.clone(), t1),
+            change.add_media(MediaKind::Audio, Direction::SendOnly, s1, t2),
+        )
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt_audio = l.params_opus().pt();
+    let pt_video = l.params_vp8().pt();
+
+    loop {
+        if l.last < r.last {
+            let wallclock = l.start + l.duration();
+            let time: MediaTime = l.duration().into();
+            for (mid, pt) in [(audio, pt_audio), (video, pt_video), (other, pt_audio)] {
+                l.writer(mid)
+                    .unwrap()
+                    .write(pt, wallclock, time, vec![1_u8; 80])?;
+            }
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let mut api = r.direct_api();
+    let mut ssrc = |mid: Mid| api.stream_rx_by_mid(mid, None).unwrap().ssrc();
+    let (ssrc_audio, ssrc_video, ssrc_other) = (ssrc(audio), ssrc(video), ssrc(other));
+
+    let groups = r.direct_api().sync_groups();
+    assert_eq!(groups.len(), 2);
+
+    let mut s1 = vec![ssrc_audio, ssrc_video];
+    s1.sort();
+    assert_eq!(groups[0].cname, "s1-track");
+    assert_eq!(groups[0].ssrcs, s1);
+    assert_eq!(groups[1].cname, "s2-track");
+    assert_eq!(groups[1].ssrcs, vec![ssrc_other]);
+
+    // Audio and video were written with the same wallclock, which means the sender
+    // wallclock of the last media in each stream lines up with its media time.
+    let offset = |r: &mut TestRtc, mid: Mid| -> Instant {
+        let time = r
+            .events
+            .iter()
+            .rev()
+            .find_map(|(_, e)| match e {
+                Event::MediaData(v) if v.mid == mid => Some(v.time),
+                _ => None,
+            })
+            .unwrap();
+        let mut api = r.direct_api();
+        let stream = api.stream_rx_by_mid(mid, None).unwrap();
+        let wallclock = stream.sync_wallclock(time).unwrap();
+        wallclock - Duration::from(time)
+    };
+
+    let offset_audio = offset(&mut r, audio);
+    let offset_video = offset(&mut r, video);
+
+    let diff = offset_audio.max(offset_video) - offset_audio.min(offset_video);
+    assert!(diff < Duration::from_millis(5), "diff {diff:?}");
+
+    Ok(())
+}
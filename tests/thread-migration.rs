@@ -0,0 +1,72 @@
+use std::net::Ipv4Addr;
+use std::thread;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn thread_migration() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+
+    // Move the sessions to a new thread for each second of media, like a
+    // work-stealing runtime could do.
+    for _ in 0..3 {
+        let handle = thread::spawn(move || -> Result<_, RtcError> {
+            let until = l.duration() + Duration::from_secs(1);
+
+            while l.duration() < until {
+                let wallclock = l.start + l.duration();
+                let time = l.duration().into();
+                l.writer(mid)
+                    .unwrap()
+                    .write(pt, wallclock, time, [1_u8; 80])?;
+
+                progress(&mut l, &mut r)?;
+            }
+
+            Ok((l, r))
+        });
+
+        (l, r) = handle.join().expect("thread to not panic")?;
+    }
+
+    let media_count = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::MediaData(_)))
+        .count();
+
+    assert!(media_count > 100, "Not enough MediaData: {}", media_count);
+
+    Ok(())
+}
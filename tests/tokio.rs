@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use str0m::tokio::RtcHandle;
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+mod common;
+use common::init_log;
+
+#[tokio::test]
+pub async fn tokio_data_channel() -> Result<(), RtcError> {
+    init_log();
+
+    let l_socket = UdpSocket::bind("127.0.0.1:0").await?;
+    let r_socket = UdpSocket::bind("127.0.0.1:0").await?;
+
+    let mut l = Rtc::new();
+    let mut r = Rtc::new();
+
+    l.add_local_candidate(Candidate::host(l_socket.local_addr()?, "udp")?);
+    r.add_local_candidate(Candidate::host(r_socket.local_addr()?, "udp")?);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("test".into());
+    let (offer, pending) = change.apply().unwrap();
+    let answer = r.sdp_api().accept_offer(offer)?;
+    l.sdp_api().accept_answer(pending, answer)?;
+
+    let (mut l, l_task) = RtcHandle::spawn(l, l_socket);
+    let (mut r, _r_task) = RtcHandle::spawn(r, r_socket);
+
+    let received = timeout(Duration::from_secs(10), async {
+        loop {
+            tokio::select! {
+                Some(event) = l.next_event() => {
+                    if let Event::ChannelOpen(id, _) = event {
+                        assert_eq!(id, cid);
+                        l.channel_send(id, false, b"hello".to_vec()).await?;
+                    }
+                }
+                Some(event) = r.next_event() => {
+                    if let Event::ChannelData(data) = event {
+                        break Ok::<_, RtcError>(data.data);
+                    }
+                }
+            }
+        }
+    })
+    .await
+    .expect("data before timeout")?;
+
+    assert_eq!(received, b"hello");
+
+    // Changes to the Rtc are done in the task.
+    let connected = r.call(|rtc| Ok(rtc.is_connected())).await?;
+    assert!(connected);
+
+    // The task drives the orderly close to the end.
+    l.call(|rtc| {
+        rtc.disconnect();
+        Ok(())
+    })
+    .await?;
+
+    let (l_closed, r_channel_closed) = timeout(Duration::from_secs(10), async {
+        let mut l_closed = false;
+        let mut r_channel_closed = false;
+        while !l_closed || !r_channel_closed {
+            tokio::select! {
+                event = l.next_event(), if !l_closed => {
+                    l_closed = matches!(event, Some(Event::Closed));
+                }
+                Some(event) = r.next_event() => {
+                    if matches!(event, Event::ChannelClose(id) if id == cid) {
+                        r_channel_closed = true;
+                    }
+                }
+            }
+        }
+        (l_closed, r_channel_closed)
+    })
+    .await
+    .expect("close before timeout");
+
+    assert!(l_closed);
+    assert!(r_channel_closed);
+
+    // No more events after Closed, and the task ends.
+    assert!(l.next_event().await.is_none());
+    let result = timeout(Duration::from_secs(10), l_task)
+        .await
+        .expect("task to end")
+        .expect("task to not panic");
+    assert!(result.is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn tokio_unspecified_address() -> Result<(), RtcError> {
+    init_log();
+
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+
+    let (_handle, task) = RtcHandle::spawn(Rtc::new(), socket);
+
+    let result = timeout(Duration::from_secs(10), task)
+        .await
+        .expect("task to end")
+        .expect("task to not panic");
+
+    match result {
+        Err(RtcError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput),
+        r => panic!("expected an io error, got {:?}", r),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use str0m::media::{MediaKind, Mid};
+use str0m::rtp::{ExtensionValues, Ssrc, UnknownSsrcPolicy};
+use str0m::{Event, Rtc, RtcError};
+
+mod common;
+use common::{connect_l_r_with_rtc, init_log, progress, TestRtc};
+
+/// L sends 20 packets on an SSRC R doesn't know about, one every 100ms. R declares
+/// the media and binds the SSRC once `bind()` says so, given the time since the
+/// first packet.
+///
+/// Returns (UnknownSsrc events at R, sequence numbers of the RTP at R).
+fn send_unknown(
+    policy: UnknownSsrcPolicy,
+    bind: impl Fn(&TestRtc, Duration) -> bool,
+) -> Result<(Vec<Ssrc>, Vec<u16>), RtcError> {
+    let rtc1 = Rtc::builder().set_rtp_mode(true).build();
+    let rtc2 = Rtc::builder()
+        .set_rtp_mode(true)
+        .set_reordering_size_audio(0)
+        .set_unknown_ssrc_policy(policy)
+        .build();
+
+    let (mut l, mut r) = connect_l_r_with_rtc(rtc1, rtc2);
+
+    let mid: Mid = "aud".into();
+    let ssrc: Ssrc = 1.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let start = l.last;
+    let mut write_at = l.last;
+    let mut count = 0;
+    let mut bound = false;
+
+    loop {
+        if count < 20 && l.last >= write_at {
+            write_at = l.last + Duration::from_millis(100);
+            count += 1;
+
+            let wallclock = l.start + l.duration();
+            let mut direct = l.direct_api();
+            let stream = direct.stream_tx(&ssrc).unwrap();
+            stream.write_rtp(
+                pt,
+                (count as u64).into(),
+                count * 960,
+                wallclock,
+                false,
+                ExtensionValues::default(),
+                false,
+                vec![1, 2, 3, 4],
+            )?;
+        }
+
+        if !bound && bind(&r, l.last - start) {
+            bound = true;
+            r.direct_api().declare_media(mid, MediaKind::Audio);
+            r.direct_api().expect_stream_rx(ssrc, None, mid, None);
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let unknown = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::UnknownSsrc(v) => Some(v.ssrc),
+            _ => None,
+        })
+        .collect();
+
+    let seq_nos = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::RtpPacket(v) => Some(v.header.sequence_number),
+            _ => None,
+        })
+        .collect();
+
+    Ok((unknown, seq_nos))
+}
+
+#[test]
+pub fn unknown_ssrc_drop() -> Result<(), RtcError> {
+    init_log();
+
+    let (unknown, seq_nos) = send_unknown(UnknownSsrcPolicy::Drop, |_, t| {
+        t > Duration::from_millis(500)
+    })?;
+
+    assert!(unknown.is_empty());
+
+    // The packets before binding are lost.
+    assert!(seq_nos[0] > 1, "{seq_nos:?}");
+    assert_eq!(*seq_nos.last().unwrap(), 20);
+
+    Ok(())
+}
+
+#[test]
+pub fn unknown_ssrc_event() -> Result<(), RtcError> {
+    init_log();
+
+    let (unknown, seq_nos) = send_unknown(UnknownSsrcPolicy::Event, |r, _| {
+        r.events
+            .iter()
+            .any(|(_, e)| matches!(e, Event::UnknownSsrc(_)))
+    })?;
+
+    // One event, even though several packets arrived before binding.
+    assert_eq!(unknown, vec![1.into()]);
+
+    // Only the first packet is lost, since we bind on the event.
+    assert_eq!(seq_nos, (2..=20).collect::<Vec<_>>());
+
+    Ok(())
+}
+
+#[test]
+pub fn unknown_ssrc_buffer() -> Result<(), RtcError> {
+    init_log();
+
+    let policy = UnknownSsrcPolicy::Buffer(Duration::from_secs(1));
+    let (unknown, seq_nos) = send_unknown(policy, |_, t| t > Duration::from_millis(500))?;
+
+    assert!(unknown.is_empty());
+
+    // Held packets are handled once bound.
+    assert_eq!(seq_nos, (1..=20).collect::<Vec<_>>());
+
+    Ok(())
+}